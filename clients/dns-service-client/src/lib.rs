@@ -21,7 +21,12 @@ progenitor::generate_api!(
         DnsConfig = internal_dns_types::config::DnsConfig,
         DnsConfigParams = internal_dns_types::config::DnsConfigParams,
         DnsConfigZone = internal_dns_types::config::DnsConfigZone,
+        DnsGenerationConfig = internal_dns_types::config::DnsGenerationConfig,
+        DnsGenerationQueryResult =
+            internal_dns_types::config::DnsGenerationQueryResult,
+        DnsGenerationSummary = internal_dns_types::config::DnsGenerationSummary,
         DnsRecord = internal_dns_types::config::DnsRecord,
+        Generation = omicron_common::api::external::Generation,
         Srv = internal_dns_types::config::Srv,
     }
 );
@@ -1007,6 +1007,7 @@ pub enum ResourceType {
     IpPool,
     IpPoolResource,
     InstanceNetworkInterface,
+    InstanceTemplate,
     InternetGateway,
     InternetGatewayIpPool,
     InternetGatewayIpAddress,
@@ -1020,6 +1021,9 @@ pub enum ResourceType {
     Switch,
     SagaDbg,
     Snapshot,
+    SnapshotExport,
+    SnapshotGroup,
+    SnapshotPolicy,
     Volume,
     Vpc,
     VpcFirewallRule,
@@ -1030,6 +1034,7 @@ pub enum ResourceType {
     MetricProducer,
     RoleBuiltin,
     TufRepo,
+    TufRepoUpload,
     TufArtifact,
     SwitchPort,
     UserBuiltin,
@@ -51,6 +51,7 @@ use gateway_client::types::SpType;
 use indicatif::ProgressBar;
 use indicatif::ProgressDrawTarget;
 use indicatif::ProgressStyle;
+use internal_dns_types::config::ZONE_APEX_NAME;
 use internal_dns_types::names::ServiceName;
 use ipnetwork::IpNetwork;
 use itertools::Itertools;
@@ -153,6 +154,7 @@ use omicron_uuid_kinds::SledUuid;
 use omicron_uuid_kinds::VolumeUuid;
 use omicron_uuid_kinds::ZpoolUuid;
 use sled_agent_client::VolumeConstructionRequest;
+use slog_error_chain::InlineErrorChain;
 use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
@@ -161,6 +163,7 @@ use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Display;
 use std::future::Future;
+use std::net::SocketAddr;
 use std::num::NonZeroU32;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -469,6 +472,8 @@ enum DnsCommands {
     Diff(DnsVersionArgs),
     /// Show the full contents of a given DNS zone and version
     Names(DnsVersionArgs),
+    /// Show what a given DNS version would answer for a name
+    Query(DnsQueryArgs),
 }
 
 #[derive(Debug, Args, Clone)]
@@ -480,6 +485,22 @@ struct DnsVersionArgs {
     version: u32,
 }
 
+#[derive(Debug, Args, Clone)]
+struct DnsQueryArgs {
+    #[command(flatten)]
+    version: DnsVersionArgs,
+    /// fully-qualified DNS name to look up
+    name: String,
+    /// HTTP address of a DNS server to compare against the database
+    /// (may be repeated; by default, all servers for the group are found
+    /// using internal DNS)
+    #[clap(long = "server")]
+    servers: Vec<SocketAddr>,
+    /// only report what the database says (do not contact DNS servers)
+    #[clap(long, conflicts_with = "servers")]
+    db_only: bool,
+}
+
 #[derive(Clone, Copy, Debug, ValueEnum)]
 enum CliDnsGroup {
     Internal,
@@ -493,6 +514,13 @@ impl CliDnsGroup {
             CliDnsGroup::External => DnsGroup::External,
         }
     }
+
+    fn service_name(&self) -> ServiceName {
+        match self {
+            CliDnsGroup::Internal => ServiceName::InternalDns,
+            CliDnsGroup::External => ServiceName::ExternalDns,
+        }
+    }
 }
 
 #[derive(Debug, Args, Clone)]
@@ -614,7 +642,7 @@ struct CollectionIdArgs {
 }
 
 #[derive(Debug, Clone, Copy)]
-enum CollectionIdOrLatest {
+pub(crate) enum CollectionIdOrLatest {
     Latest,
     CollectionId(CollectionUuid),
 }
@@ -633,7 +661,7 @@ impl FromStr for CollectionIdOrLatest {
 }
 
 impl CollectionIdOrLatest {
-    async fn to_collection(
+    pub(crate) async fn to_collection(
         &self,
         opctx: &OpContext,
         datastore: &DataStore,
@@ -1220,6 +1248,17 @@ impl DbArgs {
                         cmd_db_dns_names(&opctx, &datastore, &fetch_opts, args)
                             .await
                     }
+                    DbCommands::Dns(DnsArgs { command: DnsCommands::Query(args) }) => {
+                        cmd_db_dns_query(
+                            omdb,
+                            log,
+                            &opctx,
+                            &datastore,
+                            &fetch_opts,
+                            args,
+                        )
+                        .await
+                    }
                     DbCommands::Inventory(inventory_args) => {
                         cmd_db_inventory(
                             &opctx,
@@ -5081,6 +5120,159 @@ async fn cmd_db_dns_names(
     Ok(())
 }
 
+/// Run `omdb db dns query`.
+async fn cmd_db_dns_query(
+    omdb: &Omdb,
+    log: &slog::Logger,
+    opctx: &OpContext,
+    datastore: &DataStore,
+    fetch_opts: &DbFetchOptions,
+    args: &DnsQueryArgs,
+) -> Result<(), anyhow::Error> {
+    let limit = fetch_opts.fetch_limit;
+    let (group_zones, version) =
+        load_zones_version(opctx, datastore, limit, &args.version).await?;
+    let group = args.version.group;
+
+    // Figure out which zone (if any) the name belongs to and look it up in
+    // the database.  As in the DNS server, records for the zone's apex are
+    // stored under `ZONE_APEX_NAME`.
+    let fqdn = args.name.trim_end_matches('.').to_lowercase();
+    let found_zone = group_zones.iter().find_map(|zone| {
+        let zone_name = zone.zone_name.to_lowercase();
+        let key = fqdn.strip_suffix(&zone_name)?;
+        if !key.is_empty() && !key.ends_with('.') {
+            // e.g., "fooexample.com" is not in zone "example.com"
+            return None;
+        }
+        let key = match key.trim_end_matches('.') {
+            "" => ZONE_APEX_NAME,
+            key => key,
+        };
+        Some((zone, key.to_owned()))
+    });
+
+    println!("DNS group:                  {:?}", group);
+    println!("requested version:          {}", *version.version);
+    println!("name:                       {}", fqdn);
+    let db_records = match found_zone {
+        None => {
+            println!("zone:                       (none)");
+            println!("");
+            println!("database: name is not in any zone in this group");
+            None
+        }
+        Some((zone, key)) => {
+            println!("zone:                       {}", zone.zone_name);
+            println!("");
+
+            use nexus_db_schema::schema::dns_name::dsl;
+            let names = dsl::dns_name
+                .filter(dsl::dns_zone_id.eq(zone.id))
+                .filter(dsl::name.eq(key.clone()))
+                .filter(dsl::version_added.le(version.version))
+                .filter(
+                    dsl::version_removed
+                        .is_null()
+                        .or(dsl::version_removed.gt(version.version)),
+                )
+                .limit(1)
+                .select(DnsName::as_select())
+                .load_async(&*datastore.pool_connection_for_tests().await?)
+                .await
+                .context("loading name")?;
+            let mut records = match names.into_iter().next() {
+                Some(name) => name.records().context("parsing records")?,
+                None => Vec::new(),
+            };
+            records.sort();
+            if records.is_empty() {
+                println!("database: no records (NXDOMAIN)");
+            } else {
+                println!("database:");
+                print_name("", &key, Ok(records.clone()));
+            }
+            Some(records)
+        }
+    };
+
+    if args.db_only {
+        return Ok(());
+    }
+
+    let servers = if !args.servers.is_empty() {
+        args.servers.clone()
+    } else {
+        match omdb.dns_lookup_all(log.clone(), group.service_name()).await {
+            Ok(addrs) => addrs.into_iter().map(SocketAddr::V6).collect(),
+            Err(error) => {
+                eprintln!(
+                    "warning: failed to find DNS servers for group {:?}: {:#}",
+                    group, error
+                );
+                Vec::new()
+            }
+        }
+    };
+
+    // Ask each DNS server what it would have answered at this version and
+    // compare that with the database.
+    let generation = *version.version;
+    for server in servers {
+        println!("");
+        let client = dns_service_client::Client::new(
+            &format!("http://{}", server),
+            log.clone(),
+        );
+        let result = match client.dns_generation_query(&generation, &fqdn).await
+        {
+            Ok(result) => result.into_inner(),
+            Err(error)
+                if error.status() == Some(http::StatusCode::NOT_FOUND) =>
+            {
+                println!(
+                    "server {}: version {} is not retained",
+                    server, *version.version
+                );
+                continue;
+            }
+            Err(error) => {
+                println!(
+                    "server {}: error: {}",
+                    server,
+                    InlineErrorChain::new(&error)
+                );
+                continue;
+            }
+        };
+
+        let mut server_records = result.records;
+        server_records.sort();
+        let server_answer = result.zone.map(|_| server_records);
+        let summary = if server_answer == db_records {
+            "matches database"
+        } else {
+            "DIFFERS FROM DATABASE"
+        };
+        match server_answer {
+            None => println!(
+                "server {}: not authoritative (SERVFAIL) ({})",
+                server, summary
+            ),
+            Some(records) if records.is_empty() => println!(
+                "server {}: no records (NXDOMAIN) ({})",
+                server, summary
+            ),
+            Some(records) => {
+                println!("server {}: ({})", server, summary);
+                print_name("", &fqdn, Ok(records));
+            }
+        }
+    }
+
+    Ok(())
+}
+
 async fn cmd_db_eips(
     opctx: &OpContext,
     datastore: &DataStore,
@@ -6,6 +6,7 @@
 
 use crate::Omdb;
 use crate::check_allow_destructive::DestructiveOperationToken;
+use crate::db::CollectionIdOrLatest;
 use crate::db::DbUrlOptions;
 use crate::helpers::CONNECTION_OPTIONS_HEADING;
 use crate::helpers::ConfirmationPrompt;
@@ -38,12 +39,17 @@ use nexus_client::types::UninitializedSledId;
 use nexus_db_lookup::LookupPath;
 use nexus_db_queries::db::DataStore;
 use nexus_inventory::now_db_precision;
+use nexus_reconfigurator_blippy::Blippy;
+use nexus_reconfigurator_blippy::BlippyReportSortKey;
+use nexus_reconfigurator_preparation::PlanningInputFromDb;
 use nexus_saga_recovery::LastPass;
 use nexus_types::deployment::Blueprint;
+use nexus_types::deployment::BlueprintTopology;
 use nexus_types::deployment::ClickhouseMode;
 use nexus_types::deployment::ClickhousePolicy;
 use nexus_types::deployment::OximeterReadMode;
 use nexus_types::deployment::OximeterReadPolicy;
+use nexus_types::deployment::ZonePlacementPolicy;
 use nexus_types::internal_api::background::AbandonedVmmReaperStatus;
 use nexus_types::internal_api::background::BlueprintRendezvousStatus;
 use nexus_types::internal_api::background::InstanceReincarnationStatus;
@@ -56,11 +62,13 @@ use nexus_types::internal_api::background::RegionSnapshotReplacementFinishStatus
 use nexus_types::internal_api::background::RegionSnapshotReplacementGarbageCollectStatus;
 use nexus_types::internal_api::background::RegionSnapshotReplacementStartStatus;
 use nexus_types::internal_api::background::RegionSnapshotReplacementStepStatus;
+use nexus_types::internal_api::background::SnapshotPolicyStatus;
 use nexus_types::internal_api::background::SupportBundleCleanupReport;
 use nexus_types::internal_api::background::SupportBundleCollectionReport;
 use nexus_types::internal_api::background::TufArtifactReplicationCounters;
 use nexus_types::internal_api::background::TufArtifactReplicationRequest;
 use nexus_types::internal_api::background::TufArtifactReplicationStatus;
+use nexus_types::internal_api::background::TufRepoUploadPrunerStatus;
 use nexus_types::inventory::BaseboardId;
 use omicron_uuid_kinds::BlueprintUuid;
 use omicron_uuid_kinds::CollectionUuid;
@@ -73,6 +81,7 @@ use serde::Deserialize;
 use slog_error_chain::InlineErrorChain;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::num::NonZeroU32;
 use std::str::FromStr;
 use std::sync::Arc;
 use tabled::Tabled;
@@ -126,6 +135,8 @@ enum NexusCommands {
     Sagas(SagasArgs),
     /// interact with sleds
     Sleds(SledsArgs),
+    /// interact with zone placement policy
+    ZonePlacementPolicy(ZonePlacementPolicyArgs),
 }
 
 #[derive(Debug, Args)]
@@ -186,6 +197,8 @@ enum BlueprintsCommands {
     Show(BlueprintIdArgs),
     /// Diff two blueprints
     Diff(BlueprintDiffArgs),
+    /// Check a blueprint for problems, including against inventory
+    Blippy(BlueprintBlippyArgs),
     /// Delete a blueprint
     Delete(BlueprintIdArgs),
     /// Interact with the current target blueprint
@@ -194,6 +207,12 @@ enum BlueprintsCommands {
     Regenerate,
     /// Import a blueprint
     Import(BlueprintImportArgs),
+    /// Show the review status and audit trail of a blueprint
+    Audit(BlueprintIdArgs),
+    /// Show what executing a blueprint would change, without changing anything
+    Plan(BlueprintIdArgs),
+    /// Show a blueprint's sleds, zpools, datasets, zones, and IPs as a graph
+    Topology(BlueprintTopologyArgs),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -276,6 +295,47 @@ struct BlueprintDiffArgs {
     exit_code: bool,
 }
 
+#[derive(Debug, Args)]
+struct BlueprintBlippyArgs {
+    // Checking against inventory and the planning input requires a database
+    // connection
+    #[clap(flatten)]
+    db_url_opts: DbUrlOptions,
+
+    /// id of blueprint (or `target` for the current target)
+    blueprint_id: BlueprintIdOrCurrentTarget,
+
+    /// id of the inventory collection to check against (or `latest`)
+    #[clap(long, default_value = "latest")]
+    collection_id: CollectionIdOrLatest,
+}
+
+#[derive(Debug, Args)]
+struct BlueprintTopologyArgs {
+    // Annotating the graph with inventory requires a database connection
+    #[clap(flatten)]
+    db_url_opts: DbUrlOptions,
+
+    /// id of blueprint (or `target` for the current target)
+    blueprint_id: BlueprintIdOrCurrentTarget,
+
+    /// mark which nodes this inventory collection (or `latest`) found
+    #[clap(long)]
+    collection_id: Option<CollectionIdOrLatest>,
+
+    /// output format
+    #[clap(long, value_enum, default_value_t = BlueprintTopologyFormat::Dot)]
+    format: BlueprintTopologyFormat,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum BlueprintTopologyFormat {
+    /// Graphviz DOT
+    Dot,
+    /// JSON list of nodes and edges
+    Json,
+}
+
 #[derive(Debug, Args)]
 struct CollectionIdArgs {
     /// id of an inventory collection
@@ -297,11 +357,11 @@ enum BlueprintTargetCommands {
     /// Enable the current target blueprint
     ///
     /// Fails if the specified blueprint id is not the current target
-    Enable(BlueprintIdArgs),
+    Enable(BlueprintTargetEnableArgs),
     /// Disable the current target blueprint
     ///
     /// Fails if the specified blueprint id is not the current target
-    Disable(BlueprintIdArgs),
+    Disable(BlueprintTargetEnableArgs),
 }
 
 #[derive(Debug, Args)]
@@ -314,6 +374,18 @@ struct BlueprintTargetSetArgs {
     /// before proceeding
     #[clap(long)]
     diff: bool,
+    /// who is making this change (recorded in the blueprint's audit trail)
+    #[clap(long)]
+    actor: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct BlueprintTargetEnableArgs {
+    /// id of blueprint (or `target` for the current target)
+    blueprint_id: BlueprintIdOrCurrentTarget,
+    /// who is making this change (recorded in the blueprint's audit trail)
+    #[clap(long)]
+    actor: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -395,6 +467,39 @@ enum OximeterReadPolicyMode {
     Cluster,
 }
 
+#[derive(Debug, Args)]
+struct ZonePlacementPolicyArgs {
+    #[command(subcommand)]
+    command: ZonePlacementPolicyCommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum ZonePlacementPolicyCommands {
+    /// Get the current policy
+    Get,
+    /// Set the new policy, replacing the current one
+    Set(ZonePlacementPolicySetArgs),
+}
+
+#[derive(Debug, Args)]
+struct ZonePlacementPolicySetArgs {
+    /// Maximum number of discretionary zones (of all kinds) on any one sled
+    ///
+    /// If not given, sleds are limited only by their number of zpools.
+    #[arg(long)]
+    max_zones_per_sled: Option<u32>,
+
+    /// Sled to reserve for instances (may be repeated)
+    #[arg(long = "reserved-sled", value_name = "SLED_ID")]
+    reserved_sleds: Vec<SledUuid>,
+
+    /// Spread redundant zones across ranges of this many consecutive cubbies
+    ///
+    /// If not given, sleds are not grouped into failure domains.
+    #[arg(long)]
+    cubbies_per_failure_domain: Option<NonZeroU32>,
+}
+
 #[derive(Debug, Args)]
 struct SagasArgs {
     #[command(subcommand)]
@@ -532,6 +637,9 @@ impl NexusArgs {
             NexusCommands::Blueprints(BlueprintsArgs {
                 command: BlueprintsCommands::Diff(args),
             }) => cmd_nexus_blueprints_diff(&client, args).await,
+            NexusCommands::Blueprints(BlueprintsArgs {
+                command: BlueprintsCommands::Blippy(args),
+            }) => cmd_nexus_blueprints_blippy(&client, args, omdb, log).await,
             NexusCommands::Blueprints(BlueprintsArgs {
                 command: BlueprintsCommands::Delete(args),
             }) => {
@@ -589,6 +697,15 @@ impl NexusArgs {
                 let token = omdb.check_allow_destructive()?;
                 cmd_nexus_blueprints_import(&client, token, args).await
             }
+            NexusCommands::Blueprints(BlueprintsArgs {
+                command: BlueprintsCommands::Audit(args),
+            }) => cmd_nexus_blueprints_audit(&client, args).await,
+            NexusCommands::Blueprints(BlueprintsArgs {
+                command: BlueprintsCommands::Plan(args),
+            }) => cmd_nexus_blueprints_plan(&client, args).await,
+            NexusCommands::Blueprints(BlueprintsArgs {
+                command: BlueprintsCommands::Topology(args),
+            }) => cmd_nexus_blueprints_topology(&client, args, omdb, log).await,
 
             NexusCommands::ClickhousePolicy(ClickhousePolicyArgs {
                 command,
@@ -667,6 +784,19 @@ impl NexusArgs {
                 cmd_nexus_sled_expunge_disk(&client, args, omdb, log, token)
                     .await
             }
+
+            NexusCommands::ZonePlacementPolicy(ZonePlacementPolicyArgs {
+                command,
+            }) => match command {
+                ZonePlacementPolicyCommands::Get => {
+                    cmd_nexus_zone_placement_policy_get(&client).await
+                }
+                ZonePlacementPolicyCommands::Set(args) => {
+                    let token = omdb.check_allow_destructive()?;
+                    cmd_nexus_zone_placement_policy_set(&client, args, token)
+                        .await
+                }
+            },
         }
     }
 }
@@ -1011,12 +1141,18 @@ fn print_task_details(bgtask: &BackgroundTask, details: &serde_json::Value) {
         "service_firewall_rule_propagation" => {
             print_task_service_firewall_rule_propagation(details);
         }
+        "snapshot_policy" => {
+            print_task_snapshot_policy(details);
+        }
         "support_bundle_collector" => {
             print_task_support_bundle_collector(details);
         }
         "tuf_artifact_replication" => {
             print_task_tuf_artifact_replication(details);
         }
+        "tuf_repo_upload_pruner" => {
+            print_task_tuf_repo_upload_pruner(details);
+        }
         _ => {
             println!(
                 "warning: unknown background task: {:?} \
@@ -1100,6 +1236,7 @@ fn print_task_blueprint_executor(details: &serde_json::Value) {
         target_id: Uuid,
         enabled: bool,
         execution_error: Option<NestedError>,
+        awaiting_approval_from: Option<String>,
     }
 
     match serde_json::from_value::<BlueprintExecutorStatus>(value) {
@@ -1116,10 +1253,12 @@ fn print_task_blueprint_executor(details: &serde_json::Value) {
             ]);
             builder.push_record([
                 "execution:".to_string(),
-                if status.enabled {
-                    "enabled".to_string()
-                } else {
-                    "disabled".to_string()
+                match (status.enabled, &status.awaiting_approval_from) {
+                    (false, _) => "disabled".to_string(),
+                    (true, Some(approver)) => {
+                        format!("held (awaiting approval from {approver})")
+                    }
+                    (true, None) => "enabled".to_string(),
                 },
             ]);
 
@@ -2174,6 +2313,39 @@ fn print_task_service_firewall_rule_propagation(details: &serde_json::Value) {
     };
 }
 
+fn print_task_snapshot_policy(details: &serde_json::Value) {
+    match serde_json::from_value::<SnapshotPolicyStatus>(details.clone()) {
+        Err(error) => eprintln!(
+            "warning: failed to interpret task details: {:?}: {:?}",
+            error, details
+        ),
+
+        Ok(SnapshotPolicyStatus {
+            policies_found,
+            snapshots_created,
+            snapshots_deleted,
+            errors,
+        }) => {
+            println!("    snapshot policies found: {policies_found}");
+
+            println!("    snapshots created: {}", snapshots_created.len());
+            for id in &snapshots_created {
+                println!("    > {id}");
+            }
+
+            println!("    snapshots deleted: {}", snapshots_deleted.len());
+            for id in &snapshots_deleted {
+                println!("    > {id}");
+            }
+
+            println!("    errors: {}", errors.len());
+            for line in &errors {
+                println!("    > {line}");
+            }
+        }
+    }
+}
+
 fn print_task_support_bundle_collector(details: &serde_json::Value) {
     #[derive(Deserialize)]
     struct SupportBundleCollectionStatus {
@@ -2314,6 +2486,27 @@ fn print_task_tuf_artifact_replication(details: &serde_json::Value) {
     }
 }
 
+fn print_task_tuf_repo_upload_pruner(details: &serde_json::Value) {
+    match serde_json::from_value::<TufRepoUploadPrunerStatus>(details.clone()) {
+        Err(error) => eprintln!(
+            "warning: failed to interpret task details: {:?}: {:?}",
+            error, details
+        ),
+
+        Ok(TufRepoUploadPrunerStatus { uploads_deleted, errors }) => {
+            println!("    uploads deleted: {}", uploads_deleted.len());
+            for id in &uploads_deleted {
+                println!("    > {id}");
+            }
+
+            println!("    errors: {}", errors.len());
+            for line in &errors {
+                println!("    > {line}");
+            }
+        }
+    }
+}
+
 /// Summarizes an `ActivationReason`
 fn reason_str(reason: &ActivationReason) -> &'static str {
     match reason {
@@ -2645,6 +2838,9 @@ async fn cmd_nexus_blueprints_show(
 ) -> Result<(), anyhow::Error> {
     let blueprint = args.blueprint_id.resolve_to_blueprint(client).await?;
     println!("{}", blueprint.display());
+    if let Some(report) = &blueprint.planning_report {
+        println!("{report}");
+    }
     Ok(())
 }
 
@@ -2665,6 +2861,85 @@ async fn cmd_nexus_blueprints_diff(
     Ok(())
 }
 
+async fn cmd_nexus_blueprints_blippy(
+    client: &nexus_client::Client,
+    args: &BlueprintBlippyArgs,
+    omdb: &Omdb,
+    log: &slog::Logger,
+) -> Result<(), anyhow::Error> {
+    let datastore = args.db_url_opts.connect(omdb, log).await?;
+    let result = cmd_nexus_blueprints_blippy_with_datastore(
+        &datastore, client, args, log,
+    )
+    .await;
+    datastore.terminate().await;
+    result
+}
+
+// `omdb nexus blueprints blippy`, but borrowing a datastore
+async fn cmd_nexus_blueprints_blippy_with_datastore(
+    datastore: &Arc<DataStore>,
+    client: &nexus_client::Client,
+    args: &BlueprintBlippyArgs,
+    log: &slog::Logger,
+) -> Result<(), anyhow::Error> {
+    use nexus_db_queries::context::OpContext;
+
+    let opctx = OpContext::for_tests(log.clone(), datastore.clone());
+    let opctx = &opctx;
+
+    let blueprint = args.blueprint_id.resolve_to_blueprint(client).await?;
+    let collection = args.collection_id.to_collection(opctx, datastore).await?;
+    let planning_input =
+        PlanningInputFromDb::assemble(opctx, datastore, Some(&collection))
+            .await
+            .context("assembling planning input")?;
+
+    let report = Blippy::new(&blueprint)
+        .with_planning_input(&planning_input)
+        .with_collection(&collection)
+        .into_report(BlippyReportSortKey::Severity);
+    println!("checked against inventory collection {}", collection.id);
+    println!("{}", report.display());
+    Ok(())
+}
+
+async fn cmd_nexus_blueprints_topology(
+    client: &nexus_client::Client,
+    args: &BlueprintTopologyArgs,
+    omdb: &Omdb,
+    log: &slog::Logger,
+) -> Result<(), anyhow::Error> {
+    use nexus_db_queries::context::OpContext;
+
+    let blueprint = args.blueprint_id.resolve_to_blueprint(client).await?;
+    let collection = match args.collection_id {
+        None => None,
+        Some(collection_id) => {
+            let datastore = args.db_url_opts.connect(omdb, log).await?;
+            let opctx = OpContext::for_tests(log.clone(), datastore.clone());
+            let result = collection_id.to_collection(&opctx, &datastore).await;
+            datastore.terminate().await;
+            Some(result?)
+        }
+    };
+
+    let mut topology = BlueprintTopology::new(&blueprint);
+    if let Some(collection) = &collection {
+        topology = topology.with_collection(collection);
+    }
+    let graph = topology.build();
+    match args.format {
+        BlueprintTopologyFormat::Dot => print!("{}", graph.dot()),
+        BlueprintTopologyFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&graph)
+                .context("serializing topology graph")?
+        ),
+    }
+    Ok(())
+}
+
 async fn cmd_nexus_blueprints_delete(
     client: &nexus_client::Client,
     args: &BlueprintIdArgs,
@@ -2752,6 +3027,7 @@ async fn cmd_nexus_blueprints_target_set(
         .blueprint_target_set(&nexus_client::types::BlueprintTargetSet {
             target_id: args.blueprint_id,
             enabled,
+            actor: args.actor.clone(),
         })
         .await
         .with_context(|| {
@@ -2763,7 +3039,7 @@ async fn cmd_nexus_blueprints_target_set(
 
 async fn cmd_nexus_blueprints_target_set_enabled(
     client: &nexus_client::Client,
-    args: &BlueprintIdArgs,
+    args: &BlueprintTargetEnableArgs,
     enabled: bool,
     _destruction_token: DestructiveOperationToken,
 ) -> Result<(), anyhow::Error> {
@@ -2774,6 +3050,7 @@ async fn cmd_nexus_blueprints_target_set_enabled(
             &nexus_client::types::BlueprintTargetSet {
                 target_id: blueprint_id,
                 enabled,
+                actor: args.actor.clone(),
             },
         )
         .await
@@ -2812,6 +3089,102 @@ async fn cmd_nexus_blueprints_import(
     Ok(())
 }
 
+async fn cmd_nexus_blueprints_audit(
+    client: &nexus_client::Client,
+    args: &BlueprintIdArgs,
+) -> Result<(), anyhow::Error> {
+    #[derive(Tabled)]
+    #[tabled(rename_all = "SCREAMING_SNAKE_CASE")]
+    struct AuditRow {
+        time: String,
+        action: String,
+        actor: String,
+        enabled: String,
+    }
+
+    let blueprint_id = args.blueprint_id.resolve_to_id(client).await?;
+
+    println!("blueprint: {blueprint_id}");
+    match client.blueprint_approval_view(blueprint_id.as_untyped_uuid()).await {
+        Ok(approval) => {
+            println!(
+                "    proposed by:       {} at {}",
+                approval.proposed_by,
+                humantime::format_rfc3339_millis(approval.time_proposed.into()),
+            );
+            println!("    required approver: {}", approval.required_approver);
+            match (&approval.approved_by, approval.time_approved) {
+                (Some(approved_by), Some(time_approved)) => println!(
+                    "    approved by:       {} at {}",
+                    approved_by,
+                    humantime::format_rfc3339_millis(time_approved.into()),
+                ),
+                _ => {
+                    println!("    approved by:       (held: awaiting approval)")
+                }
+            }
+        }
+        Err(error) if error.status() == Some(StatusCode::NOT_FOUND) => {
+            println!("    not proposed for review");
+        }
+        Err(error) => {
+            return Err(error).with_context(|| {
+                format!("fetching review status of blueprint {blueprint_id}")
+            });
+        }
+    }
+
+    let entries = client
+        .blueprint_target_audit_list(blueprint_id.as_untyped_uuid())
+        .await
+        .with_context(|| {
+            format!("listing audit trail of blueprint {blueprint_id}")
+        })?
+        .into_inner();
+    println!();
+    if entries.is_empty() {
+        println!("no audit trail entries");
+        return Ok(());
+    }
+
+    let rows = entries.into_iter().map(|entry| AuditRow {
+        time: humantime::format_rfc3339_millis(entry.time.into()).to_string(),
+        action: entry.action.to_string(),
+        actor: entry.actor.unwrap_or_else(|| String::from("<unknown>")),
+        enabled: match entry.enabled {
+            Some(true) => String::from("yes"),
+            Some(false) => String::from("no"),
+            None => String::from("-"),
+        },
+    });
+    let table = tabled::Table::new(rows)
+        .with(tabled::settings::Style::empty())
+        .with(tabled::settings::Padding::new(0, 1, 0, 0))
+        .to_string();
+    println!("{}", textwrap::indent(&table, "    "));
+    Ok(())
+}
+
+async fn cmd_nexus_blueprints_plan(
+    client: &nexus_client::Client,
+    args: &BlueprintIdArgs,
+) -> Result<(), anyhow::Error> {
+    let blueprint_id = args.blueprint_id.resolve_to_id(client).await?;
+    let plan = client
+        .blueprint_execution_plan(blueprint_id.as_untyped_uuid())
+        .await
+        .with_context(|| {
+            format!("planning execution of blueprint {blueprint_id}")
+        })?
+        .into_inner();
+    print!("{plan}");
+    if plan.is_empty() {
+        println!();
+        println!("executing this blueprint would not change anything");
+    }
+    Ok(())
+}
+
 async fn cmd_nexus_clickhouse_policy_get(
     client: &nexus_client::Client,
 ) -> Result<(), anyhow::Error> {
@@ -2995,6 +3368,68 @@ async fn cmd_nexus_oximeter_read_policy_set(
     Ok(())
 }
 
+async fn cmd_nexus_zone_placement_policy_get(
+    client: &nexus_client::Client,
+) -> Result<(), anyhow::Error> {
+    let policy = client
+        .zone_placement_policy_get()
+        .await
+        .context("retrieving zone placement policy")?
+        .into_inner();
+
+    println!("Zone Placement Policy: ");
+    println!("    version: {}", policy.version);
+    println!("    creation time: {}", policy.time_created);
+    match policy.max_discretionary_zones_per_sled {
+        Some(max) => println!("    max discretionary zones per sled: {max}"),
+        None => println!("    max discretionary zones per sled: (no limit)"),
+    }
+    match policy.cubbies_per_failure_domain {
+        Some(n) => println!("    cubbies per failure domain: {n}"),
+        None => println!("    cubbies per failure domain: (not spreading)"),
+    }
+    if policy.reserved_sleds.is_empty() {
+        println!("    sleds reserved for instances: (none)");
+    } else {
+        println!("    sleds reserved for instances:");
+        for sled_id in &policy.reserved_sleds {
+            println!("        {sled_id}");
+        }
+    }
+
+    Ok(())
+}
+
+async fn cmd_nexus_zone_placement_policy_set(
+    client: &nexus_client::Client,
+    args: &ZonePlacementPolicySetArgs,
+    _destruction_token: DestructiveOperationToken,
+) -> Result<(), anyhow::Error> {
+    let current = client
+        .zone_placement_policy_get()
+        .await
+        .context("retrieving zone placement policy")?
+        .into_inner();
+    let new_policy = ZonePlacementPolicy {
+        version: current.version + 1,
+        max_discretionary_zones_per_sled: args.max_zones_per_sled,
+        reserved_sleds: args.reserved_sleds.iter().copied().collect(),
+        cubbies_per_failure_domain: args.cubbies_per_failure_domain,
+        time_created: now_db_precision(),
+    };
+
+    client.zone_placement_policy_set(&new_policy).await.with_context(|| {
+        format!("inserting new policy at version {}", new_policy.version)
+    })?;
+
+    println!(
+        "Successfully inserted new policy at version {}",
+        new_policy.version
+    );
+
+    Ok(())
+}
+
 /// Runs `omdb nexus sagas list`
 async fn cmd_nexus_sagas_list(
     client: &nexus_client::Client,
@@ -0,0 +1,708 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Randomized fault-injection runs of the plan/execute/inventory loop
+//!
+//! A chaos run loads an example system and then applies a random sequence of
+//! events to it: planning, full or partial execution, inventory collection,
+//! sled and disk expungement, and sled additions. Leaving out execution or
+//! inventory collection between plans is how failed execution and stale
+//! inventory are simulated. Along the way (and after letting the system
+//! settle) we check a few invariants:
+//!
+//! * every blueprint the planner produces is clean according to blippy
+//! * once faults stop, the planner converges: repeated rounds of planning,
+//!   execution, and inventory collection eventually stop making changes
+//! * the converged blueprint meets the policy's redundancy targets, to the
+//!   extent that there are enough in-service sleds to do so
+//!
+//! Each event is expressed as a `reconfigurator-cli` command, so when a run
+//! fails we can shrink its events to a minimal sequence that still fails in
+//! the same way and print that as a script that can be replayed with
+//! `reconfigurator-cli <file>`.
+
+use crate::ReconfiguratorSim;
+use crate::TopLevelArgs;
+use crate::run_command;
+use anyhow::Context;
+use clap::CommandFactory;
+use clap::FromArgMatches;
+use nexus_reconfigurator_blippy::Blippy;
+use nexus_reconfigurator_blippy::BlippyReportSortKey;
+use nexus_sled_agent_shared::inventory::ZoneKind;
+use nexus_types::deployment::Blueprint;
+use nexus_types::deployment::BlueprintZoneDisposition;
+use nexus_types::deployment::PlanningInput;
+use nexus_types::deployment::SledFilter;
+use nexus_types::external_api::views::PhysicalDiskPolicy;
+use omicron_uuid_kinds::GenericUuid;
+use omicron_uuid_kinds::PhysicalDiskUuid;
+use omicron_uuid_kinds::SledUuid;
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+use strum::IntoEnumIterator;
+use swrite::{SWrite, swriteln};
+
+/// Chaos runs never expunge sleds below this many in-service sleds
+///
+/// With fewer sleds than this, the redundancy targets for the example system
+/// can't be met at all and the runs stop being interesting.
+const MIN_IN_SERVICE_SLEDS: usize = 3;
+
+/// Settings for a set of chaos runs
+#[derive(Clone, Debug)]
+pub(crate) struct ChaosConfig {
+    /// number of sleds in the example system each run starts from
+    pub nsleds: usize,
+    /// number of random events in each run
+    pub nevents: usize,
+    /// number of fault-free plan/execute/inventory rounds within which the
+    /// planner must stop making changes
+    pub convergence_rounds: usize,
+}
+
+/// The result of a single chaos run
+#[derive(Debug)]
+pub(crate) enum ChaosOutcome {
+    Passed,
+    Failed {
+        /// the invariant that was violated
+        failure: ChaosFailure,
+        /// a minimal script reproducing the failure
+        script: String,
+    },
+}
+
+/// An invariant violated by a chaos run
+#[derive(Clone, Debug)]
+pub(crate) struct ChaosFailure {
+    pub kind: ChaosFailureKind,
+    pub message: String,
+}
+
+impl ChaosFailure {
+    /// Whether `other` reproduces this failure, for the purposes of shrinking
+    ///
+    /// Removing events easily causes some *other* command to fail, so command
+    /// failures only match if their messages do. The messages for other kinds
+    /// of failures include blueprint ids, which change as events are removed,
+    /// so for those only the kind is compared.
+    fn is_reproduced_by(&self, other: &ChaosFailure) -> bool {
+        match self.kind {
+            ChaosFailureKind::Command => {
+                other.kind == self.kind && other.message == self.message
+            }
+            ChaosFailureKind::Blippy
+            | ChaosFailureKind::Convergence
+            | ChaosFailureKind::Redundancy => other.kind == self.kind,
+        }
+    }
+}
+
+impl fmt::Display for ChaosFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
+
+/// The kinds of invariant a chaos run checks
+///
+/// When shrinking, a smaller sequence of events only counts as reproducing a
+/// failure if it fails in the same way (see
+/// [`ChaosFailure::is_reproduced_by()`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ChaosFailureKind {
+    /// a command failed
+    Command,
+    /// blippy reported problems with a blueprint produced by the planner
+    Blippy,
+    /// the planner kept making changes after faults stopped
+    Convergence,
+    /// the converged blueprint doesn't meet the policy's redundancy targets
+    Redundancy,
+}
+
+impl fmt::Display for ChaosFailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ChaosFailureKind::Command => "command failed",
+            ChaosFailureKind::Blippy => "blippy found problems",
+            ChaosFailureKind::Convergence => "planner did not converge",
+            ChaosFailureKind::Redundancy => "redundancy targets not met",
+        };
+        f.write_str(s)
+    }
+}
+
+/// One random event applied to the simulated system
+#[derive(Clone, Debug)]
+enum ChaosEvent {
+    /// run the planner against the latest blueprint and inventory
+    Plan,
+    /// execute the latest blueprint on every sled
+    Execute,
+    /// execute the latest blueprint on only some sleds
+    ExecutePartial(Vec<SledUuid>),
+    /// collect inventory
+    Inventory,
+    /// an operator expunges a sled
+    ExpungeSled(SledUuid),
+    /// an operator expunges a physical disk
+    ExpungeDisk(SledUuid, PhysicalDiskUuid),
+    /// an operator adds a sled
+    AddSled(SledUuid),
+}
+
+impl ChaosEvent {
+    fn command(&self) -> String {
+        match self {
+            ChaosEvent::Plan => PLAN_COMMAND.to_string(),
+            ChaosEvent::Execute => EXECUTE_COMMAND.to_string(),
+            ChaosEvent::ExecutePartial(sled_ids) => {
+                let mut command = EXECUTE_COMMAND.to_string();
+                for sled_id in sled_ids {
+                    command.push_str(&format!(" --sled-id {sled_id}"));
+                }
+                command
+            }
+            ChaosEvent::Inventory => INVENTORY_COMMAND.to_string(),
+            ChaosEvent::ExpungeSled(sled_id) => {
+                format!("sled-expunge {sled_id}")
+            }
+            ChaosEvent::ExpungeDisk(sled_id, disk_id) => {
+                format!("sled-expunge-disk {sled_id} {disk_id}")
+            }
+            ChaosEvent::AddSled(sled_id) => format!("sled-add {sled_id}"),
+        }
+    }
+
+    /// Returns this event, adjusted to refer only to sleds in `known_sleds`,
+    /// or `None` if nothing is left of it
+    ///
+    /// `AddSled` events add their sled to `known_sleds`.
+    fn restricted_to(
+        &self,
+        known_sleds: &mut BTreeSet<SledUuid>,
+    ) -> Option<ChaosEvent> {
+        match self {
+            ChaosEvent::Plan | ChaosEvent::Execute | ChaosEvent::Inventory => {
+                Some(self.clone())
+            }
+            ChaosEvent::ExecutePartial(sled_ids) => {
+                let sled_ids: Vec<_> = sled_ids
+                    .iter()
+                    .filter(|sled_id| known_sleds.contains(sled_id))
+                    .copied()
+                    .collect();
+                if sled_ids.is_empty() {
+                    None
+                } else {
+                    Some(ChaosEvent::ExecutePartial(sled_ids))
+                }
+            }
+            ChaosEvent::ExpungeSled(sled_id)
+            | ChaosEvent::ExpungeDisk(sled_id, _) => {
+                known_sleds.contains(sled_id).then(|| self.clone())
+            }
+            ChaosEvent::AddSled(sled_id) => {
+                known_sleds.insert(*sled_id);
+                Some(self.clone())
+            }
+        }
+    }
+}
+
+const PLAN_COMMAND: &str = "blueprint-plan latest latest";
+const EXECUTE_COMMAND: &str = "blueprint-apply-zones latest";
+const INVENTORY_COMMAND: &str = "inventory-generate";
+
+/// Performs one chaos run with the given seed.
+pub(crate) fn chaos_run(
+    seed: &str,
+    config: &ChaosConfig,
+) -> anyhow::Result<ChaosOutcome> {
+    let setup = setup_command(seed, config);
+    let events = generate_events(seed, config, &setup)?;
+
+    let Err((failure, _)) = run_events(&setup, &events, config) else {
+        return Ok(ChaosOutcome::Passed);
+    };
+
+    // Shrink the events to a minimal sequence that fails the same way, then
+    // rerun that sequence to get the exact commands it ran.
+    let events = shrink(events, |candidate| {
+        run_events(&setup, candidate, config)
+            .is_err_and(|(f, _)| failure.is_reproduced_by(&f))
+    });
+    let (failure, commands) = run_events(&setup, &events, config)
+        .expect_err("shrunk events still fail");
+
+    let mut script = String::new();
+    swriteln!(script, "# chaos run with seed {seed:?}");
+    swriteln!(script, "# {}", failure.kind);
+    for command in commands {
+        swriteln!(script, "{command}");
+    }
+    Ok(ChaosOutcome::Failed { failure, script })
+}
+
+fn setup_command(seed: &str, config: &ChaosConfig) -> String {
+    // Seeds are passed through the REPL, which splits on whitespace.
+    let seed: String = seed.chars().filter(|c| !c.is_whitespace()).collect();
+    format!("load-example --seed {seed} --nsleds {}", config.nsleds)
+}
+
+/// Tracks the simulated system well enough to generate events that make
+/// sense (e.g., only expunging disks that are still in service).
+struct EventModel {
+    sleds: Vec<SledUuid>,
+    in_service_sleds: BTreeSet<SledUuid>,
+    in_service_disks: BTreeMap<SledUuid, Vec<PhysicalDiskUuid>>,
+}
+
+fn generate_events(
+    seed: &str,
+    config: &ChaosConfig,
+    setup: &str,
+) -> anyhow::Result<Vec<ChaosEvent>> {
+    let mut rng: StdRng = typed_rng::from_seed(seed, "chaos-events");
+
+    // Load the example system to find out which sleds and disks it has.
+    let mut sim = new_sim();
+    execute(&mut sim, setup).context("loading example system")?;
+    let planning_input = sim
+        .current_state()
+        .system()
+        .description()
+        .to_planning_input_builder()
+        .context("generating planning input")?
+        .build();
+    let mut model = EventModel {
+        sleds: planning_input.all_sled_ids(SledFilter::InService).collect(),
+        in_service_sleds: planning_input
+            .all_sled_ids(SledFilter::InService)
+            .collect(),
+        in_service_disks: planning_input
+            .all_sled_resources(SledFilter::InService)
+            .map(|(sled_id, resources)| {
+                let disks = resources
+                    .zpools
+                    .values()
+                    .filter(|disk| disk.policy == PhysicalDiskPolicy::InService)
+                    .map(|disk| disk.disk_id)
+                    .collect();
+                (sled_id, disks)
+            })
+            .collect(),
+    };
+
+    let mut events = Vec::with_capacity(config.nevents);
+    while events.len() < config.nevents {
+        if let Some(event) = random_event(&mut rng, &mut model) {
+            events.push(event);
+        }
+    }
+    Ok(events)
+}
+
+/// Picks a random event, or returns `None` if the event picked doesn't apply
+/// to the system right now.
+fn random_event(
+    rng: &mut StdRng,
+    model: &mut EventModel,
+) -> Option<ChaosEvent> {
+    match rng.gen_range(0..100) {
+        0..25 => Some(ChaosEvent::Plan),
+        25..45 => Some(ChaosEvent::Execute),
+        45..55 => {
+            let amount = rng.gen_range(1..=model.sleds.len());
+            let mut sled_ids: Vec<_> =
+                model.sleds.choose_multiple(rng, amount).copied().collect();
+            sled_ids.sort();
+            Some(ChaosEvent::ExecutePartial(sled_ids))
+        }
+        55..75 => Some(ChaosEvent::Inventory),
+        75..83 => {
+            // Only disks on sleds that were part of the example system are
+            // tracked, which is fine: the point is to exercise the planner's
+            // handling of expunged disks, not to cover every disk.
+            let candidates: Vec<_> = model
+                .in_service_disks
+                .iter()
+                .filter(|(sled_id, _)| model.in_service_sleds.contains(sled_id))
+                .flat_map(|(sled_id, disks)| {
+                    disks.iter().map(move |disk_id| (*sled_id, *disk_id))
+                })
+                .collect();
+            let &(sled_id, disk_id) = candidates.choose(rng)?;
+            model
+                .in_service_disks
+                .get_mut(&sled_id)
+                .expect("sled is present")
+                .retain(|id| *id != disk_id);
+            Some(ChaosEvent::ExpungeDisk(sled_id, disk_id))
+        }
+        83..90 => {
+            if model.in_service_sleds.len() <= MIN_IN_SERVICE_SLEDS {
+                return None;
+            }
+            let candidates: Vec<_> =
+                model.in_service_sleds.iter().copied().collect();
+            let sled_id = *candidates.choose(rng)?;
+            model.in_service_sleds.remove(&sled_id);
+            Some(ChaosEvent::ExpungeSled(sled_id))
+        }
+        _ => {
+            let sled_id = SledUuid::from_untyped_uuid(
+                uuid::Builder::from_random_bytes(rng.gen()).into_uuid(),
+            );
+            model.sleds.push(sled_id);
+            model.in_service_sleds.insert(sled_id);
+            Some(ChaosEvent::AddSled(sled_id))
+        }
+    }
+}
+
+/// Runs the setup command and then `events` in a fresh simulator, followed by
+/// fault-free rounds of planning, execution, and inventory collection.
+///
+/// Events that refer to sleds whose `AddSled` event isn't present (because
+/// shrinking removed it) are skipped, rather than being run only to fail.
+///
+/// On failure, returns the violated invariant along with every command that
+/// was run, up to and including the one that failed.
+fn run_events(
+    setup: &str,
+    events: &[ChaosEvent],
+    config: &ChaosConfig,
+) -> Result<(), (ChaosFailure, Vec<String>)> {
+    let mut sim = new_sim();
+    let mut commands = Vec::new();
+    let mut run = |sim: &mut ReconfiguratorSim, command: String| {
+        let result = execute(sim, &command);
+        commands.push(command);
+        result.map_err(|error| ChaosFailure {
+            kind: ChaosFailureKind::Command,
+            message: format!("{:#}", error),
+        })
+    };
+
+    let result = (|| -> Result<(), ChaosFailure> {
+        run(&mut sim, setup.to_string())?;
+        let mut known_sleds: BTreeSet<_> = sim
+            .current_state()
+            .system()
+            .description()
+            .to_planning_input_builder()
+            .map_err(|error| ChaosFailure {
+                kind: ChaosFailureKind::Command,
+                message: format!("generating planning input: {error:#}"),
+            })?
+            .build()
+            .all_sled_ids(SledFilter::All)
+            .collect();
+        for event in events {
+            let Some(event) = event.restricted_to(&mut known_sleds) else {
+                continue;
+            };
+            run(&mut sim, event.command())?;
+            if let ChaosEvent::Plan = event {
+                check_blippy(&sim)?;
+            }
+        }
+
+        // Let the system settle.
+        let mut converged = false;
+        for _ in 0..config.convergence_rounds {
+            run(&mut sim, PLAN_COMMAND.to_string())?;
+            check_blippy(&sim)?;
+            let blueprint = latest_blueprint(&sim);
+            let parent_id = blueprint
+                .parent_blueprint_id
+                .expect("planned blueprint has a parent");
+            let parent = sim
+                .current_state()
+                .system()
+                .get_blueprint(parent_id)
+                .expect("parent blueprint exists");
+            if !blueprint.diff_since_blueprint(parent).has_changes() {
+                converged = true;
+                break;
+            }
+            run(&mut sim, EXECUTE_COMMAND.to_string())?;
+            run(&mut sim, INVENTORY_COMMAND.to_string())?;
+        }
+        if !converged {
+            return Err(ChaosFailure {
+                kind: ChaosFailureKind::Convergence,
+                message: format!(
+                    "planner still making changes after {} rounds",
+                    config.convergence_rounds
+                ),
+            });
+        }
+        check_redundancy(&sim)
+    })();
+
+    result.map_err(|failure| (failure, commands))
+}
+
+fn new_sim() -> ReconfiguratorSim {
+    let log = slog::Logger::root(slog::Discard, slog::o!());
+    ReconfiguratorSim::new(log, None)
+}
+
+/// Parses and runs one command, the same way the REPL would.
+fn execute(sim: &mut ReconfiguratorSim, command: &str) -> anyhow::Result<()> {
+    let args = TopLevelArgs::command()
+        .multicall(true)
+        .try_get_matches_from(command.split_whitespace())
+        .and_then(|matches| TopLevelArgs::from_arg_matches(&matches))
+        .with_context(|| format!("parsing command {command:?}"))?;
+    run_command(sim, args)?;
+    Ok(())
+}
+
+fn latest_blueprint(sim: &ReconfiguratorSim) -> &Blueprint {
+    sim.current_state()
+        .system()
+        .all_blueprints()
+        .last()
+        .expect("example system has a blueprint")
+}
+
+fn planning_input_for(
+    sim: &ReconfiguratorSim,
+    blueprint: &Blueprint,
+) -> Result<PlanningInput, ChaosFailure> {
+    sim.planning_input(blueprint).map_err(|error| ChaosFailure {
+        kind: ChaosFailureKind::Command,
+        message: format!("constructing planning input: {error:#}"),
+    })
+}
+
+fn check_blippy(sim: &ReconfiguratorSim) -> Result<(), ChaosFailure> {
+    let blueprint = latest_blueprint(sim);
+    let planning_input = planning_input_for(sim, blueprint)?;
+    let report = Blippy::new(blueprint)
+        .with_planning_input(&planning_input)
+        .into_report(BlippyReportSortKey::Severity);
+    if report.notes().is_empty() {
+        Ok(())
+    } else {
+        Err(ChaosFailure {
+            kind: ChaosFailureKind::Blippy,
+            message: format!(
+                "blueprint {}:\n{}",
+                blueprint.id,
+                report.display()
+            ),
+        })
+    }
+}
+
+fn check_redundancy(sim: &ReconfiguratorSim) -> Result<(), ChaosFailure> {
+    let blueprint = latest_blueprint(sim);
+    let planning_input = planning_input_for(sim, blueprint)?;
+    let nsleds = planning_input.all_sled_ids(SledFilter::InService).count();
+
+    let mut problems = Vec::new();
+    for kind in ZoneKind::iter() {
+        let Some(target) = planning_input.target_zone_count(kind) else {
+            continue;
+        };
+        let expected = target.min(nsleds);
+        let actual = blueprint
+            .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+            .filter(|(_, zone)| zone.zone_type.kind() == kind)
+            .count();
+        if actual < expected {
+            problems.push(format!(
+                "{} in-service {} zones (expected at least {expected})",
+                actual,
+                kind.report_str(),
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ChaosFailure {
+            kind: ChaosFailureKind::Redundancy,
+            message: format!(
+                "blueprint {}: {}",
+                blueprint.id,
+                problems.join(", ")
+            ),
+        })
+    }
+}
+
+/// Shrinks `items` to a smaller sequence for which `fails` still returns
+/// true, by repeatedly trying to remove chunks of decreasing size.
+///
+/// `fails(&items)` is assumed to be true to begin with. The result is
+/// 1-minimal: removing any single remaining item makes `fails` return false.
+fn shrink<T: Clone>(
+    mut items: Vec<T>,
+    mut fails: impl FnMut(&[T]) -> bool,
+) -> Vec<T> {
+    let mut chunk_size = items.len().div_ceil(2).max(1);
+    loop {
+        let mut removed_any = false;
+        let mut start = 0;
+        while start < items.len() {
+            let end = (start + chunk_size).min(items.len());
+            let mut candidate = items[..start].to_vec();
+            candidate.extend_from_slice(&items[end..]);
+            if fails(&candidate) {
+                items = candidate;
+                removed_any = true;
+            } else {
+                start = end;
+            }
+        }
+        if chunk_size == 1 {
+            if !removed_any {
+                return items;
+            }
+        } else if !removed_any {
+            chunk_size = chunk_size.div_ceil(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChaosEvent;
+    use super::ReconfiguratorSim;
+    use super::execute;
+    use super::new_sim;
+    use super::shrink;
+    use nexus_types::deployment::PlanningInput;
+    use nexus_types::deployment::SledFilter;
+    use nexus_types::external_api::views::PhysicalDiskPolicy;
+    use nexus_types::external_api::views::SledPolicy;
+    use omicron_uuid_kinds::SledUuid;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_shrink() {
+        // Fails whenever both 3 and 7 are present, in that order.
+        let fails = |items: &[u32]| {
+            let three = items.iter().position(|i| *i == 3);
+            let seven = items.iter().position(|i| *i == 7);
+            matches!((three, seven), (Some(a), Some(b)) if a < b)
+        };
+        let items: Vec<u32> = (0..20).collect();
+        assert_eq!(shrink(items, fails), vec![3, 7]);
+
+        // An item that's needed on its own shrinks to just that item.
+        let items: Vec<u32> = (0..20).collect();
+        assert_eq!(shrink(items, |items| items.contains(&11)), vec![11]);
+
+        // If even the empty sequence fails, that's what we get back.
+        let items: Vec<u32> = (0..5).collect();
+        assert_eq!(shrink(items, |_| true), Vec::<u32>::new());
+    }
+
+    fn planning_input(sim: &ReconfiguratorSim) -> PlanningInput {
+        sim.current_state()
+            .system()
+            .description()
+            .to_planning_input_builder()
+            .expect("generated planning input")
+            .build()
+    }
+
+    #[test]
+    fn test_expunge_commands() {
+        let mut sim = new_sim();
+        execute(&mut sim, "load-example --seed test_expunge_commands")
+            .expect("loaded example system");
+        let input = planning_input(&sim);
+        let mut sleds = input.all_sled_resources(SledFilter::InService);
+        let (expunged_sled_id, _) = sleds.next().expect("a sled");
+        let (disk_sled_id, resources) = sleds.next().expect("another sled");
+        let disk_id = resources.zpools.values().next().expect("a disk").disk_id;
+
+        execute(&mut sim, &format!("sled-expunge {expunged_sled_id}"))
+            .expect("expunged sled");
+        execute(
+            &mut sim,
+            &format!("sled-expunge-disk {disk_sled_id} {disk_id}"),
+        )
+        .expect("expunged disk");
+
+        let input = planning_input(&sim);
+        let sled = input
+            .sled_lookup(SledFilter::All, expunged_sled_id)
+            .expect("expunged sled is still present");
+        assert_eq!(sled.policy, SledPolicy::Expunged);
+        let sled = input
+            .sled_lookup(SledFilter::InService, disk_sled_id)
+            .expect("sled with expunged disk is still in service");
+        let disk = sled
+            .resources
+            .zpools
+            .values()
+            .find(|disk| disk.disk_id == disk_id)
+            .expect("expunged disk is still present");
+        assert_eq!(disk.policy, PhysicalDiskPolicy::Expunged);
+        let other_disks = sled
+            .resources
+            .zpools
+            .values()
+            .filter(|disk| disk.disk_id != disk_id);
+        for disk in other_disks {
+            assert_eq!(disk.policy, PhysicalDiskPolicy::InService);
+        }
+
+        // Expunging something that doesn't exist fails.
+        let unknown_sled_id = SledUuid::new_v4();
+        execute(&mut sim, &format!("sled-expunge {unknown_sled_id}"))
+            .expect_err("expunging an unknown sled fails");
+        execute(
+            &mut sim,
+            &format!("sled-expunge-disk {expunged_sled_id} {disk_id}"),
+        )
+        .expect_err("expunging a disk on the wrong sled fails");
+    }
+
+    #[test]
+    fn test_restricted_to() {
+        let example_sled_id = SledUuid::new_v4();
+        let added_sled_id = SledUuid::new_v4();
+        let mut known_sleds = BTreeSet::from([example_sled_id]);
+
+        // Events referring to a sled that hasn't been added are dropped...
+        assert!(
+            ChaosEvent::ExpungeSled(added_sled_id)
+                .restricted_to(&mut known_sleds)
+                .is_none()
+        );
+        let Some(ChaosEvent::ExecutePartial(sled_ids)) =
+            ChaosEvent::ExecutePartial(vec![example_sled_id, added_sled_id])
+                .restricted_to(&mut known_sleds)
+        else {
+            panic!("partial execution should have been kept");
+        };
+        assert_eq!(sled_ids, vec![example_sled_id]);
+
+        // ... but not once it has been.
+        ChaosEvent::AddSled(added_sled_id)
+            .restricted_to(&mut known_sleds)
+            .expect("adding a sled is kept");
+        assert!(
+            ChaosEvent::ExpungeSled(added_sled_id)
+                .restricted_to(&mut known_sleds)
+                .is_some()
+        );
+    }
+}
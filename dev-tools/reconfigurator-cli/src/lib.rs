@@ -5,6 +5,7 @@
 //! developer REPL for driving blueprint planning
 
 use anyhow::{Context, anyhow, bail};
+use camino::Utf8Path;
 use camino::Utf8PathBuf;
 use clap::ValueEnum;
 use clap::{Args, Parser, Subcommand};
@@ -17,11 +18,15 @@ use nexus_reconfigurator_blippy::Blippy;
 use nexus_reconfigurator_blippy::BlippyReportSortKey;
 use nexus_reconfigurator_planning::blueprint_builder::BlueprintBuilder;
 use nexus_reconfigurator_planning::example::ExampleSystemBuilder;
+use nexus_reconfigurator_planning::example::example_tuf_repo;
 use nexus_reconfigurator_planning::planner::Planner;
 use nexus_reconfigurator_planning::system::{SledBuilder, SystemDescription};
 use nexus_reconfigurator_simulation::SimState;
 use nexus_reconfigurator_simulation::SimStateBuilder;
+use nexus_reconfigurator_simulation::SimSystem;
 use nexus_reconfigurator_simulation::Simulator;
+use nexus_sled_agent_shared::inventory::ZoneKind;
+use nexus_types::deployment::BlueprintTopology;
 use nexus_types::deployment::OmicronZoneNic;
 use nexus_types::deployment::PlanningInput;
 use nexus_types::deployment::SledFilter;
@@ -36,9 +41,11 @@ use nexus_types::deployment::{
 use nexus_types::deployment::{BlueprintZoneImageVersion, PendingMgsUpdate};
 use nexus_types::external_api::views::SledPolicy;
 use nexus_types::external_api::views::SledProvisionPolicy;
+use nexus_types::external_api::views::SledState;
 use omicron_common::address::REPO_DEPOT_PORT;
 use omicron_common::api::external::Generation;
 use omicron_common::api::external::Name;
+use omicron_common::api::external::TufRepoDescription;
 use omicron_common::policy::NEXUS_REDUNDANCY;
 use omicron_repl_utils::run_repl_from_file;
 use omicron_repl_utils::run_repl_on_stdin;
@@ -46,6 +53,7 @@ use omicron_uuid_kinds::BlueprintUuid;
 use omicron_uuid_kinds::CollectionUuid;
 use omicron_uuid_kinds::GenericUuid;
 use omicron_uuid_kinds::OmicronZoneUuid;
+use omicron_uuid_kinds::PhysicalDiskUuid;
 use omicron_uuid_kinds::ReconfiguratorSimUuid;
 use omicron_uuid_kinds::SledUuid;
 use omicron_uuid_kinds::VnicUuid;
@@ -53,12 +61,15 @@ use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::Write;
 use std::io::IsTerminal;
-use swrite::{SWrite, swriteln};
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+use swrite::{SWrite, swrite, swriteln};
 use tabled::Tabled;
 use tufaceous_artifact::ArtifactHash;
 use tufaceous_artifact::ArtifactVersion;
 use tufaceous_artifact::ArtifactVersionError;
 
+mod chaos;
 mod log_capture;
 
 /// REPL state
@@ -70,6 +81,15 @@ struct ReconfiguratorSim {
     current: ReconfiguratorSimUuid,
     // The current system state
     log: slog::Logger,
+    // Results of `assert` commands run so far.
+    assertions: AssertionTally,
+}
+
+/// Counts of `assert` commands that passed and failed
+#[derive(Clone, Copy, Debug, Default)]
+struct AssertionTally {
+    passed: usize,
+    failed: usize,
 }
 
 impl ReconfiguratorSim {
@@ -78,6 +98,7 @@ impl ReconfiguratorSim {
             sim: Simulator::new(&log, seed),
             current: Simulator::ROOT_ID,
             log,
+            assertions: AssertionTally::default(),
         }
     }
 
@@ -165,6 +186,32 @@ pub struct CmdReconfiguratorSim {
     /// The RNG seed to initialize the simulator with.
     #[clap(long)]
     seed: Option<String>,
+
+    /// Run every `*.txt` scenario file in this directory, each against a
+    /// fresh simulator, and report which ones had failing `assert` commands
+    /// or commands that failed to parse or run.
+    ///
+    /// Unless `--seed` is provided, each scenario is seeded with its file
+    /// name so that its results are deterministic.
+    #[clap(long, conflicts_with = "input_file")]
+    scenarios: Option<Utf8PathBuf>,
+
+    /// Perform this many randomized fault-injection runs of the
+    /// plan/execute/inventory loop, checking invariants along the way.
+    ///
+    /// Each failing run is shrunk to a minimal script that reproduces the
+    /// failure. Runs are seeded from `--seed` (or a generated seed) and the
+    /// run number, so a failing run can be repeated.
+    #[clap(long, conflicts_with_all = ["input_file", "scenarios"])]
+    chaos: Option<usize>,
+
+    /// number of sleds in the example system each chaos run starts from
+    #[clap(long, default_value_t = 5, requires = "chaos")]
+    chaos_nsleds: usize,
+
+    /// number of random events in each chaos run
+    #[clap(long, default_value_t = 40, requires = "chaos")]
+    chaos_events: usize,
 }
 
 impl CmdReconfiguratorSim {
@@ -173,6 +220,13 @@ impl CmdReconfiguratorSim {
         let (log_capture, log) =
             LogCapture::new(std::io::stdout().is_terminal());
 
+        if let Some(scenarios_dir) = &self.scenarios {
+            return self.run_scenarios(scenarios_dir, &log_capture, &log);
+        }
+        if let Some(nruns) = self.chaos {
+            return self.run_chaos(nruns, &log);
+        }
+
         let seed_provided = self.seed.is_some();
         let mut sim = ReconfiguratorSim::new(log, self.seed);
         if seed_provided {
@@ -182,15 +236,126 @@ impl CmdReconfiguratorSim {
         }
 
         if let Some(input_file) = &self.input_file {
+            // Unlike scenarios, input files may deliberately run commands that
+            // fail (e.g., to show their error messages), so only failed
+            // assertions fail the run.
             run_repl_from_file(input_file, &mut |cmd: TopLevelArgs| {
                 process_command(&mut sim, cmd, &log_capture)
-            })
+            })?;
+            let AssertionTally { passed, failed } = sim.assertions;
+            if failed > 0 {
+                bail!("{failed} of {} assertions failed", passed + failed);
+            }
+            Ok(())
         } else {
             run_repl_on_stdin(&mut |cmd: TopLevelArgs| {
                 process_command(&mut sim, cmd, &log_capture)
             })
         }
     }
+
+    fn run_scenarios(
+        &self,
+        scenarios_dir: &Utf8Path,
+        log_capture: &LogCapture,
+        log: &slog::Logger,
+    ) -> anyhow::Result<()> {
+        let mut paths = Vec::new();
+        for entry in scenarios_dir
+            .read_dir_utf8()
+            .with_context(|| format!("reading {scenarios_dir}"))?
+        {
+            let entry =
+                entry.with_context(|| format!("reading {scenarios_dir}"))?;
+            if entry.path().extension() == Some("txt") {
+                paths.push(entry.path().to_owned());
+            }
+        }
+        if paths.is_empty() {
+            bail!("no scenario files (*.txt) found in {scenarios_dir}");
+        }
+        paths.sort();
+
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            let name = path.file_stem().unwrap_or(path.as_str()).to_owned();
+            let seed = self.seed.clone().unwrap_or_else(|| name.clone());
+            println!("=== scenario {name} (RNG seed: {seed})");
+
+            let mut sim = ReconfiguratorSim::new(log.clone(), Some(seed));
+            let result = run_repl_from_file(&path, &mut |cmd: TopLevelArgs| {
+                process_command(&mut sim, cmd, log_capture)
+            });
+            let outcome = match result {
+                Err(error) => format!("ERROR  {name}: {error:#}"),
+                Ok(ncommands_failed) => {
+                    // A command that fails to parse or run (e.g., because of a
+                    // typo) might have been meant to set up or check
+                    // something, so it fails the scenario just as a failed
+                    // assertion does. Failed assertions are counted among
+                    // the failed commands, too.
+                    let AssertionTally { passed, failed } = sim.assertions;
+                    if failed > 0 || ncommands_failed > 0 {
+                        format!(
+                            "FAIL   {name} ({failed} of {} assertions \
+                             failed, {ncommands_failed} commands failed)",
+                            passed + failed
+                        )
+                    } else {
+                        format!("PASS   {name} ({passed} assertions)")
+                    }
+                }
+            };
+            results.push(outcome);
+        }
+
+        println!("=== scenario results");
+        for outcome in &results {
+            println!("{outcome}");
+        }
+        let nfailed = results.iter().filter(|o| !o.starts_with("PASS")).count();
+        if nfailed > 0 {
+            bail!("{nfailed} of {} scenarios failed", results.len());
+        }
+        Ok(())
+    }
+
+    fn run_chaos(
+        &self,
+        nruns: usize,
+        log: &slog::Logger,
+    ) -> anyhow::Result<()> {
+        let base_seed = match &self.seed {
+            Some(seed) => seed.clone(),
+            None => Simulator::new(log, None).initial_seed().to_owned(),
+        };
+        let config = chaos::ChaosConfig {
+            nsleds: self.chaos_nsleds,
+            nevents: self.chaos_events,
+            convergence_rounds: 10,
+        };
+
+        let mut nfailed = 0;
+        for i in 0..nruns {
+            let seed = format!("{base_seed}-{i}");
+            match chaos::chaos_run(&seed, &config)? {
+                chaos::ChaosOutcome::Passed => {
+                    println!("PASS   chaos run {i} (RNG seed: {seed})");
+                }
+                chaos::ChaosOutcome::Failed { failure, script } => {
+                    nfailed += 1;
+                    println!("FAIL   chaos run {i} (RNG seed: {seed})");
+                    println!("{failure}");
+                    println!("minimal reproducer:");
+                    println!("{script}");
+                }
+            }
+        }
+        if nfailed > 0 {
+            bail!("{nfailed} of {nruns} chaos runs failed");
+        }
+        Ok(())
+    }
 }
 
 /// Processes one "line" of user input.
@@ -198,12 +363,28 @@ fn process_command(
     sim: &mut ReconfiguratorSim,
     cmd: TopLevelArgs,
     logs: &LogCapture,
+) -> anyhow::Result<Option<String>> {
+    let cmd_result = run_command(sim, cmd);
+
+    for line in logs.take_log_lines() {
+        println!("{line}");
+    }
+
+    cmd_result
+}
+
+/// Runs one parsed command against the simulator.
+fn run_command(
+    sim: &mut ReconfiguratorSim,
+    cmd: TopLevelArgs,
 ) -> anyhow::Result<Option<String>> {
     let TopLevelArgs { command } = cmd;
-    let cmd_result = match command {
+    match command {
         Commands::SledList => cmd_sled_list(sim),
         Commands::SledAdd(args) => cmd_sled_add(sim, args),
         Commands::SledShow(args) => cmd_sled_show(sim, args),
+        Commands::SledExpunge(args) => cmd_sled_expunge(sim, args),
+        Commands::SledExpungeDisk(args) => cmd_sled_expunge_disk(sim, args),
         Commands::SiloList => cmd_silo_list(sim),
         Commands::SiloAdd(args) => cmd_silo_add(sim, args),
         Commands::SiloRemove(args) => cmd_silo_remove(sim, args),
@@ -220,6 +401,11 @@ fn process_command(
             cmd_blueprint_diff_inventory(sim, args)
         }
         Commands::BlueprintSave(args) => cmd_blueprint_save(sim, args),
+        Commands::BlueprintTopology(args) => cmd_blueprint_topology(sim, args),
+        Commands::BlueprintApplyZones(args) => {
+            cmd_blueprint_apply_zones(sim, args)
+        }
+        Commands::Assert(args) => cmd_assert(sim, args),
         Commands::Show => cmd_show(sim),
         Commands::Set(args) => cmd_set(sim, args),
         Commands::Load(args) => cmd_load(sim, args),
@@ -227,13 +413,7 @@ fn process_command(
         Commands::FileContents(args) => cmd_file_contents(args),
         Commands::Save(args) => cmd_save(sim, args),
         Commands::Wipe(args) => cmd_wipe(sim, args),
-    };
-
-    for line in logs.take_log_lines() {
-        println!("{line}");
     }
-
-    cmd_result
 }
 
 // clap configuration for the REPL commands
@@ -253,6 +433,10 @@ enum Commands {
     SledAdd(SledAddArgs),
     /// show details about one sled
     SledShow(SledArgs),
+    /// expunge a sled (as an operator would)
+    SledExpunge(SledExpungeArgs),
+    /// expunge one of a sled's physical disks (as an operator would)
+    SledExpungeDisk(SledExpungeDiskArgs),
 
     /// list silos
     SiloList,
@@ -269,7 +453,7 @@ enum Commands {
     /// list all blueprints
     BlueprintList,
     /// run blippy on a blueprint
-    BlueprintBlippy(BlueprintArgs),
+    BlueprintBlippy(BlueprintBlippyArgs),
     /// run planner to generate a new blueprint
     BlueprintPlan(BlueprintPlanArgs),
     /// edit contents of a blueprint directly
@@ -284,6 +468,19 @@ enum Commands {
     BlueprintDiffInventory(BlueprintDiffInventoryArgs),
     /// write one blueprint to a file
     BlueprintSave(BlueprintSaveArgs),
+    /// show the sleds, zpools, datasets, zones, and IPs in a blueprint as a
+    /// graph (Graphviz DOT or JSON)
+    BlueprintTopology(BlueprintTopologyArgs),
+    /// simulate executing a blueprint's zones: update each sled's Omicron
+    /// zones to match the blueprint, as seen by later inventory collections
+    BlueprintApplyZones(BlueprintApplyZonesArgs),
+
+    /// check a property of the simulated system
+    ///
+    /// A failed assertion is reported like any other error, and also causes
+    /// the scenario (or input file) to fail once all commands have run.
+    #[command(subcommand)]
+    Assert(AssertArgs),
 
     /// show system properties
     Show,
@@ -319,6 +516,20 @@ struct SledArgs {
     filter: SledFilter,
 }
 
+#[derive(Debug, Args)]
+struct SledExpungeArgs {
+    /// id of the sled
+    sled_id: SledUuid,
+}
+
+#[derive(Debug, Args)]
+struct SledExpungeDiskArgs {
+    /// id of the sled
+    sled_id: SledUuid,
+    /// id of the physical disk
+    disk_id: PhysicalDiskUuid,
+}
+
 #[derive(Debug, Args)]
 struct SiloAddRemoveArgs {
     /// name of the silo
@@ -331,21 +542,102 @@ struct InventoryArgs {
     collection_id: CollectionUuid,
 }
 
+/// Identifies a blueprint: either a specific blueprint id or "latest", the
+/// blueprint most recently added to the system
+#[derive(Clone, Copy, Debug)]
+enum BlueprintIdOpt {
+    Latest,
+    Id(BlueprintUuid),
+}
+
+impl FromStr for BlueprintIdOpt {
+    type Err = <BlueprintUuid as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "latest" {
+            Ok(BlueprintIdOpt::Latest)
+        } else {
+            Ok(BlueprintIdOpt::Id(s.parse()?))
+        }
+    }
+}
+
+impl BlueprintIdOpt {
+    fn resolve(self, system: &SimSystem) -> anyhow::Result<BlueprintUuid> {
+        match self {
+            BlueprintIdOpt::Latest => system
+                .all_blueprints()
+                .last()
+                .map(|blueprint| blueprint.id)
+                .ok_or_else(|| anyhow!("no blueprints have been added")),
+            BlueprintIdOpt::Id(id) => Ok(id),
+        }
+    }
+}
+
+/// Identifies an inventory collection: either a specific collection id or
+/// "latest", the collection most recently added to the system
+#[derive(Clone, Copy, Debug)]
+enum CollectionIdOpt {
+    Latest,
+    Id(CollectionUuid),
+}
+
+impl FromStr for CollectionIdOpt {
+    type Err = <CollectionUuid as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "latest" {
+            Ok(CollectionIdOpt::Latest)
+        } else {
+            Ok(CollectionIdOpt::Id(s.parse()?))
+        }
+    }
+}
+
+impl CollectionIdOpt {
+    fn resolve(self, system: &SimSystem) -> anyhow::Result<CollectionUuid> {
+        match self {
+            CollectionIdOpt::Latest => system
+                .all_collections()
+                .last()
+                .map(|collection| collection.id)
+                .ok_or_else(|| {
+                    anyhow!("no inventory collections have been added")
+                }),
+            CollectionIdOpt::Id(id) => Ok(id),
+        }
+    }
+}
+
 #[derive(Debug, Args)]
 struct BlueprintPlanArgs {
-    /// id of the blueprint on which this one will be based
-    parent_blueprint_id: BlueprintUuid,
-    /// id of the inventory collection to use in planning
+    /// id of the blueprint on which this one will be based, or "latest"
+    parent_blueprint_id: BlueprintIdOpt,
+    /// id of the inventory collection to use in planning, or "latest"
     ///
     /// Must be provided unless there is only one collection in the loaded
     /// state.
-    collection_id: Option<CollectionUuid>,
+    collection_id: Option<CollectionIdOpt>,
+    /// also print the planner's report explaining each decision it made
+    #[clap(long)]
+    explain: bool,
+}
+
+#[derive(Debug, Args)]
+struct BlueprintBlippyArgs {
+    /// id of the blueprint, or "latest"
+    blueprint_id: BlueprintIdOpt,
+    /// also check the blueprint against this inventory collection (or
+    /// "latest")
+    #[clap(long)]
+    collection_id: Option<CollectionIdOpt>,
 }
 
 #[derive(Debug, Args)]
 struct BlueprintEditArgs {
-    /// id of the blueprint to edit
-    blueprint_id: BlueprintUuid,
+    /// id of the blueprint to edit, or "latest"
+    blueprint_id: BlueprintIdOpt,
     /// "creator" field for the new blueprint
     #[arg(long)]
     creator: Option<String>,
@@ -442,8 +734,19 @@ fn parse_blueprint_zone_image_version(
 
 #[derive(Debug, Args)]
 struct BlueprintArgs {
-    /// id of the blueprint
-    blueprint_id: BlueprintUuid,
+    /// id of the blueprint, or "latest"
+    blueprint_id: BlueprintIdOpt,
+}
+
+#[derive(Debug, Args)]
+struct BlueprintApplyZonesArgs {
+    /// id of the blueprint, or "latest"
+    blueprint_id: BlueprintIdOpt,
+    /// only apply zones to this sled (may be repeated; defaults to all sleds)
+    ///
+    /// This simulates execution that was interrupted partway through.
+    #[clap(long = "sled-id")]
+    sled_ids: Vec<SledUuid>,
 }
 
 #[derive(Debug, Args)]
@@ -462,6 +765,62 @@ enum CliDnsGroup {
     External,
 }
 
+#[derive(Debug, Subcommand)]
+enum AssertArgs {
+    /// a blueprint has exactly this many in-service zones of a kind
+    ZoneCount {
+        /// id of the blueprint, or "latest"
+        blueprint_id: BlueprintIdOpt,
+        /// kind of zone (e.g., "nexus", "internal_dns", "crucible")
+        #[clap(value_parser = parse_zone_kind)]
+        zone_kind: ZoneKind,
+        /// expected number of zones
+        count: usize,
+        /// only count zones on this sled
+        #[clap(long)]
+        sled_id: Option<SledUuid>,
+    },
+    /// two blueprints have no meaningful differences
+    BlueprintDiffEmpty {
+        /// id of the blueprint, or "latest"
+        blueprint_id: BlueprintIdOpt,
+        /// id of the blueprint to compare against, or "latest" (defaults to
+        /// the blueprint's parent)
+        other_blueprint_id: Option<BlueprintIdOpt>,
+    },
+    /// blippy reports no problems with a blueprint
+    BlippyClean {
+        /// id of the blueprint, or "latest"
+        blueprint_id: BlueprintIdOpt,
+    },
+    /// the DNS config generated from a blueprint contains a name
+    DnsContains {
+        /// id of the blueprint, or "latest"
+        blueprint_id: BlueprintIdOpt,
+        /// DNS group (internal or external)
+        dns_group: CliDnsGroup,
+        /// name, relative to the DNS zone (e.g., "_nexus._tcp")
+        name: String,
+    },
+    /// a blueprint has this many in-service zones using images from the
+    /// target release
+    ZonesOnTargetRelease {
+        /// id of the blueprint, or "latest"
+        blueprint_id: BlueprintIdOpt,
+        /// expected number of zones (defaults to all in-service zones)
+        count: Option<usize>,
+    },
+}
+
+fn parse_zone_kind(s: &str) -> Result<ZoneKind, String> {
+    ZoneKind::iter().find(|kind| kind.report_str() == s).ok_or_else(|| {
+        format!(
+            "unknown zone kind {s:?} (expected one of: {})",
+            ZoneKind::iter().map(|kind| kind.report_str()).join(", ")
+        )
+    })
+}
+
 #[derive(Debug, Args)]
 struct BlueprintDiffInventoryArgs {
     /// id of the inventory collection
@@ -478,12 +837,35 @@ struct BlueprintSaveArgs {
     filename: Utf8PathBuf,
 }
 
+#[derive(Debug, Args)]
+struct BlueprintTopologyArgs {
+    /// id of the blueprint, or "latest"
+    blueprint_id: BlueprintIdOpt,
+    /// mark which nodes this inventory collection (or "latest") found
+    #[clap(long)]
+    collection_id: Option<CollectionIdOpt>,
+    /// output format
+    #[clap(long, value_enum, default_value_t = TopologyFormat::Dot)]
+    format: TopologyFormat,
+    /// write the graph to this file instead of printing it
+    #[clap(long)]
+    output: Option<Utf8PathBuf>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TopologyFormat {
+    /// Graphviz DOT
+    Dot,
+    /// JSON list of nodes and edges
+    Json,
+}
+
 #[derive(Debug, Args)]
 struct BlueprintDiffArgs {
-    /// id of the first blueprint
-    blueprint1_id: BlueprintUuid,
-    /// id of the second blueprint
-    blueprint2_id: BlueprintUuid,
+    /// id of the first blueprint, or "latest"
+    blueprint1_id: BlueprintIdOpt,
+    /// id of the second blueprint, or "latest"
+    blueprint2_id: BlueprintIdOpt,
 }
 
 #[derive(Debug, Subcommand)]
@@ -492,6 +874,29 @@ enum SetArgs {
     NumNexus { num_nexus: u16 },
     /// system's external DNS zone name (suffix)
     ExternalDnsZoneName { zone_name: String },
+    /// system version of the target release (for planning), or "none"
+    ///
+    /// This synthesizes a TUF repo containing one zone artifact for each kind
+    /// of zone at this version.
+    TargetRelease {
+        #[clap(value_parser = parse_target_release)]
+        version: TargetReleaseVersion,
+    },
+}
+
+/// wrapper so that clap doesn't treat `Option` as "argument is optional"
+#[derive(Clone, Debug)]
+struct TargetReleaseVersion(Option<semver::Version>);
+
+fn parse_target_release(
+    version: &str,
+) -> Result<TargetReleaseVersion, semver::Error> {
+    // Treat the literal string "none" as clearing the target release.
+    if version == "none" {
+        return Ok(TargetReleaseVersion(None));
+    }
+
+    Ok(TargetReleaseVersion(Some(version.parse()?)))
 }
 
 #[derive(Debug, Args)]
@@ -669,6 +1074,33 @@ fn cmd_sled_show(
     Ok(Some(s))
 }
 
+fn cmd_sled_expunge(
+    sim: &mut ReconfiguratorSim,
+    args: SledExpungeArgs,
+) -> anyhow::Result<Option<String>> {
+    let mut state = sim.current_state().to_mut();
+    state.system_mut().description_mut().sled_expunge(args.sled_id)?;
+    sim.commit_and_bump(
+        format!("reconfigurator-cli sled-expunge: {}", args.sled_id),
+        state,
+    );
+    Ok(Some(format!("expunged sled {}", args.sled_id)))
+}
+
+fn cmd_sled_expunge_disk(
+    sim: &mut ReconfiguratorSim,
+    args: SledExpungeDiskArgs,
+) -> anyhow::Result<Option<String>> {
+    let SledExpungeDiskArgs { sled_id, disk_id } = args;
+    let mut state = sim.current_state().to_mut();
+    state.system_mut().description_mut().sled_expunge_disk(sled_id, disk_id)?;
+    sim.commit_and_bump(
+        format!("reconfigurator-cli sled-expunge-disk: {sled_id} {disk_id}"),
+        state,
+    );
+    Ok(Some(format!("expunged disk {disk_id} on sled {sled_id}")))
+}
+
 fn cmd_inventory_list(
     sim: &mut ReconfiguratorSim,
 ) -> anyhow::Result<Option<String>> {
@@ -772,12 +1204,27 @@ fn cmd_blueprint_list(
 
 fn cmd_blueprint_blippy(
     sim: &mut ReconfiguratorSim,
-    args: BlueprintArgs,
+    args: BlueprintBlippyArgs,
 ) -> anyhow::Result<Option<String>> {
     let state = sim.current_state();
-    let blueprint = state.system().get_blueprint(args.blueprint_id)?;
-    let report =
-        Blippy::new(&blueprint).into_report(BlippyReportSortKey::Severity);
+    let blueprint_id = args.blueprint_id.resolve(state.system())?;
+    let blueprint = state.system().get_blueprint(blueprint_id)?;
+    let collection = args
+        .collection_id
+        .map(|id| {
+            let id = id.resolve(state.system())?;
+            anyhow::Ok(state.system().get_collection(id)?)
+        })
+        .transpose()?;
+    let planning_input = sim
+        .planning_input(blueprint)
+        .context("failed to construct planning input")?;
+    let mut blippy =
+        Blippy::new(blueprint).with_planning_input(&planning_input);
+    if let Some(collection) = collection {
+        blippy = blippy.with_collection(collection);
+    }
+    let report = blippy.into_report(BlippyReportSortKey::Severity);
     Ok(Some(format!("{}", report.display())))
 }
 
@@ -785,12 +1232,17 @@ fn cmd_blueprint_plan(
     sim: &mut ReconfiguratorSim,
     args: BlueprintPlanArgs,
 ) -> anyhow::Result<Option<String>> {
+    let parent_blueprint_id =
+        args.parent_blueprint_id.resolve(sim.current_state().system())?;
+    let collection_id = args
+        .collection_id
+        .map(|id| id.resolve(sim.current_state().system()))
+        .transpose()?;
+
     let mut state = sim.current_state().to_mut();
     let rng = state.rng_mut().next_planner_rng();
     let system = state.system_mut();
 
-    let parent_blueprint_id = args.parent_blueprint_id;
-    let collection_id = args.collection_id;
     let parent_blueprint = system.get_blueprint(parent_blueprint_id)?;
     let collection = match collection_id {
         Some(collection_id) => system.get_collection(collection_id)?,
@@ -822,10 +1274,15 @@ fn cmd_blueprint_plan(
     .with_rng(rng);
 
     let blueprint = planner.plan().context("generating blueprint")?;
-    let rv = format!(
+    let mut rv = format!(
         "generated blueprint {} based on parent blueprint {}",
         blueprint.id, parent_blueprint_id,
     );
+    if args.explain {
+        if let Some(report) = &blueprint.planning_report {
+            swrite!(rv, "\n{}", report.to_string().trim_end());
+        }
+    }
     system.add_blueprint(blueprint)?;
 
     sim.commit_and_bump("reconfigurator-cli blueprint-plan".to_owned(), state);
@@ -837,11 +1294,13 @@ fn cmd_blueprint_edit(
     sim: &mut ReconfiguratorSim,
     args: BlueprintEditArgs,
 ) -> anyhow::Result<Option<String>> {
+    let blueprint_id =
+        args.blueprint_id.resolve(sim.current_state().system())?;
+
     let mut state = sim.current_state().to_mut();
     let rng = state.rng_mut().next_planner_rng();
     let system = state.system_mut();
 
-    let blueprint_id = args.blueprint_id;
     let blueprint = system.get_blueprint(blueprint_id)?;
     let creator = args.creator.as_deref().unwrap_or("reconfigurator-cli");
     let planning_input = sim
@@ -1004,19 +1463,189 @@ fn cmd_blueprint_show(
     args: BlueprintArgs,
 ) -> anyhow::Result<Option<String>> {
     let state = sim.current_state();
-    let blueprint = state.system().get_blueprint(args.blueprint_id)?;
+    let blueprint_id = args.blueprint_id.resolve(state.system())?;
+    let blueprint = state.system().get_blueprint(blueprint_id)?;
     Ok(Some(format!("{}", blueprint.display())))
 }
 
+fn cmd_assert(
+    sim: &mut ReconfiguratorSim,
+    args: AssertArgs,
+) -> anyhow::Result<Option<String>> {
+    // Errors evaluating an assertion (e.g., a nonexistent blueprint) count as
+    // failures, too.
+    match check_assertion(sim, args) {
+        Ok(Ok(description)) => {
+            sim.assertions.passed += 1;
+            Ok(Some(format!("assertion passed: {description}")))
+        }
+        Ok(Err(failure)) => {
+            sim.assertions.failed += 1;
+            Err(anyhow!("assertion failed: {failure}"))
+        }
+        Err(error) => {
+            sim.assertions.failed += 1;
+            Err(error.context("assertion failed"))
+        }
+    }
+}
+
+/// Evaluates an assertion, returning a description of what was checked if it
+/// holds or an explanation of why it doesn't.
+fn check_assertion(
+    sim: &ReconfiguratorSim,
+    args: AssertArgs,
+) -> anyhow::Result<Result<String, String>> {
+    let state = sim.current_state();
+    let system = state.system();
+    match args {
+        AssertArgs::ZoneCount { blueprint_id, zone_kind, count, sled_id } => {
+            let blueprint_id = blueprint_id.resolve(system)?;
+            let blueprint = system.get_blueprint(blueprint_id)?;
+            let actual = blueprint
+                .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+                .filter(|(zone_sled_id, zone)| {
+                    zone.zone_type.kind() == zone_kind
+                        && sled_id.is_none_or(|id| id == *zone_sled_id)
+                })
+                .count();
+            let location = match sled_id {
+                Some(sled_id) => format!("sled {sled_id}"),
+                None => "all sleds".to_string(),
+            };
+            let kind = zone_kind.report_str();
+            if actual == count {
+                Ok(Ok(format!(
+                    "blueprint {blueprint_id} has {count} in-service {kind} \
+                     zones on {location}"
+                )))
+            } else {
+                Ok(Err(format!(
+                    "blueprint {blueprint_id} has {actual} in-service {kind} \
+                     zones on {location} (expected {count})"
+                )))
+            }
+        }
+        AssertArgs::BlueprintDiffEmpty { blueprint_id, other_blueprint_id } => {
+            let blueprint_id = blueprint_id.resolve(system)?;
+            let blueprint = system.get_blueprint(blueprint_id)?;
+            let other_id = match other_blueprint_id {
+                Some(id) => id.resolve(system)?,
+                None => blueprint.parent_blueprint_id.ok_or_else(|| {
+                    anyhow!("blueprint {blueprint_id} has no parent")
+                })?,
+            };
+            let other = system.get_blueprint(other_id)?;
+            let diff = blueprint.diff_since_blueprint(other);
+            if diff.has_changes() {
+                Ok(Err(format!(
+                    "blueprint {blueprint_id} differs from {other_id}:\n{}",
+                    diff.display()
+                )))
+            } else {
+                Ok(Ok(format!(
+                    "blueprint {blueprint_id} has no changes from {other_id}"
+                )))
+            }
+        }
+        AssertArgs::BlippyClean { blueprint_id } => {
+            let blueprint_id = blueprint_id.resolve(system)?;
+            let blueprint = system.get_blueprint(blueprint_id)?;
+            let planning_input = sim
+                .planning_input(blueprint)
+                .context("failed to construct planning input")?;
+            let report = Blippy::new(blueprint)
+                .with_planning_input(&planning_input)
+                .into_report(BlippyReportSortKey::Severity);
+            if report.notes().is_empty() {
+                Ok(Ok(format!("blippy found no problems in {blueprint_id}")))
+            } else {
+                Ok(Err(format!(
+                    "blippy found {} problem(s):\n{}",
+                    report.notes().len(),
+                    report.display()
+                )))
+            }
+        }
+        AssertArgs::DnsContains { blueprint_id, dns_group, name } => {
+            let blueprint_id = blueprint_id.resolve(system)?;
+            let blueprint = system.get_blueprint(blueprint_id)?;
+            let dns_zone = match dns_group {
+                CliDnsGroup::Internal => {
+                    let sleds_by_id = make_sleds_by_id(system.description())?;
+                    blueprint_internal_dns_config(
+                        blueprint,
+                        &sleds_by_id,
+                        &Default::default(),
+                    )?
+                }
+                CliDnsGroup::External => blueprint_external_dns_config(
+                    blueprint,
+                    state.config().silo_names(),
+                    state.config().external_dns_zone_name().to_owned(),
+                ),
+            };
+            if dns_zone.records.contains_key(&name) {
+                Ok(Ok(format!(
+                    "DNS zone {} for blueprint {blueprint_id} contains {name:?}",
+                    dns_zone.zone_name
+                )))
+            } else {
+                Ok(Err(format!(
+                    "DNS zone {} for blueprint {blueprint_id} does not \
+                     contain {name:?}",
+                    dns_zone.zone_name
+                )))
+            }
+        }
+        AssertArgs::ZonesOnTargetRelease { blueprint_id, count } => {
+            let blueprint_id = blueprint_id.resolve(system)?;
+            let blueprint = system.get_blueprint(blueprint_id)?;
+            let tuf_repo = system
+                .description()
+                .get_tuf_repo()
+                .ok_or_else(|| anyhow!("no target release is set"))?;
+            let release = &tuf_repo.repo.system_version;
+            let zones: Vec<_> = blueprint
+                .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+                .map(|(_, zone)| zone)
+                .collect();
+            let total = zones.len();
+            let actual = zones
+                .iter()
+                .filter(|zone| {
+                    matches!(
+                        zone.image_source,
+                        BlueprintZoneImageSource::Artifact { hash, .. }
+                            if tuf_repo.artifacts.iter().any(|a| a.hash == hash)
+                    )
+                })
+                .count();
+            let count = count.unwrap_or(total);
+            if actual == count {
+                Ok(Ok(format!(
+                    "blueprint {blueprint_id} has {count} of {total} \
+                     in-service zones on release {release}"
+                )))
+            } else {
+                Ok(Err(format!(
+                    "blueprint {blueprint_id} has {actual} of {total} \
+                     in-service zones on release {release} (expected {count})"
+                )))
+            }
+        }
+    }
+}
+
 fn cmd_blueprint_diff(
     sim: &mut ReconfiguratorSim,
     args: BlueprintDiffArgs,
 ) -> anyhow::Result<Option<String>> {
     let mut rv = String::new();
-    let blueprint1_id = args.blueprint1_id;
-    let blueprint2_id = args.blueprint2_id;
-
     let state = sim.current_state();
+    let blueprint1_id = args.blueprint1_id.resolve(state.system())?;
+    let blueprint2_id = args.blueprint2_id.resolve(state.system())?;
+
     let blueprint1 = state.system().get_blueprint(blueprint1_id)?;
     let blueprint2 = state.system().get_blueprint(blueprint2_id)?;
 
@@ -1162,6 +1791,81 @@ fn cmd_blueprint_save(
     Ok(Some(format!("saved blueprint {} to {:?}", blueprint_id, output_path)))
 }
 
+fn cmd_blueprint_topology(
+    sim: &mut ReconfiguratorSim,
+    args: BlueprintTopologyArgs,
+) -> anyhow::Result<Option<String>> {
+    let state = sim.current_state();
+    let blueprint_id = args.blueprint_id.resolve(state.system())?;
+    let blueprint = state.system().get_blueprint(blueprint_id)?;
+    let collection = args
+        .collection_id
+        .map(|id| {
+            let id = id.resolve(state.system())?;
+            anyhow::Ok(state.system().get_collection(id)?)
+        })
+        .transpose()?;
+
+    let mut topology = BlueprintTopology::new(blueprint);
+    if let Some(collection) = collection {
+        topology = topology.with_collection(collection);
+    }
+    let graph = topology.build();
+    let output_str = match args.format {
+        TopologyFormat::Dot => graph.dot().to_string(),
+        TopologyFormat::Json => serde_json::to_string_pretty(&graph)
+            .context("serializing topology graph")?,
+    };
+
+    match &args.output {
+        None => Ok(Some(output_str)),
+        Some(output_path) => {
+            std::fs::write(output_path, &output_str)
+                .with_context(|| format!("write {:?}", output_path))?;
+            Ok(Some(format!(
+                "saved topology of blueprint {} to {:?}",
+                blueprint_id, output_path
+            )))
+        }
+    }
+}
+
+fn cmd_blueprint_apply_zones(
+    sim: &mut ReconfiguratorSim,
+    args: BlueprintApplyZonesArgs,
+) -> anyhow::Result<Option<String>> {
+    let blueprint_id =
+        args.blueprint_id.resolve(sim.current_state().system())?;
+    let mut state = sim.current_state().to_mut();
+    let blueprint = state.system_mut().get_blueprint(blueprint_id)?.clone();
+
+    let mut nsleds = 0;
+    for (sled_id, sled_cfg) in blueprint.sleds {
+        // Decommissioned sleds are no longer running anything.
+        if sled_cfg.state != SledState::Active {
+            continue;
+        }
+        if !args.sled_ids.is_empty() && !args.sled_ids.contains(&sled_id) {
+            continue;
+        }
+        let zones_config = sled_cfg.into_in_service_sled_config().zones_config;
+        state
+            .system_mut()
+            .description_mut()
+            .sled_set_omicron_zones(sled_id, zones_config)?;
+        nsleds += 1;
+    }
+
+    let rv = format!(
+        "applied zones from blueprint {blueprint_id} to {nsleds} sleds"
+    );
+    sim.commit_and_bump(
+        format!("reconfigurator-cli blueprint-apply-zones: {blueprint_id}"),
+        state,
+    );
+    Ok(Some(rv))
+}
+
 fn cmd_save(
     sim: &mut ReconfiguratorSim,
     args: SaveArgs,
@@ -1238,9 +1942,21 @@ fn cmd_show(sim: &mut ReconfiguratorSim) -> anyhow::Result<Option<String>> {
             .num_nexus()
             .map_or_else(|| "default".to_owned(), |n| n.to_string())
     );
+    swriteln!(
+        s,
+        "target release: {}",
+        display_target_release(state.system().description().get_tuf_repo()),
+    );
     Ok(Some(s))
 }
 
+fn display_target_release(tuf_repo: Option<&TufRepoDescription>) -> String {
+    tuf_repo.map_or_else(
+        || "none".to_owned(),
+        |repo| repo.repo.system_version.to_string(),
+    )
+}
+
 // TODO: consider moving this to a method on `SimState`.
 fn do_print_properties(s: &mut String, state: &SimState) {
     swriteln!(
@@ -1308,6 +2024,19 @@ fn cmd_set(
             state.config_mut().set_external_dns_zone_name(zone_name);
             rv
         }
+        SetArgs::TargetRelease { version: TargetReleaseVersion(version) } => {
+            let description = state.system_mut().description_mut();
+            let rv = format!(
+                "target release: {} -> {}",
+                display_target_release(description.get_tuf_repo()),
+                version.as_ref().map_or_else(
+                    || "none".to_owned(),
+                    |version| version.to_string()
+                ),
+            );
+            description.tuf_repo(version.map(example_tuf_repo));
+            rv
+        }
     };
 
     sim.commit_and_bump(format!("reconfigurator-cli set: {}", rv), state);
@@ -4,6 +4,7 @@
 
 use camino::Utf8Path;
 use expectorate::assert_contents;
+use omicron_test_utils::dev::test_cmds::EXIT_FAILURE;
 use omicron_test_utils::dev::test_cmds::EXIT_SUCCESS;
 use omicron_test_utils::dev::test_cmds::Redactor;
 use omicron_test_utils::dev::test_cmds::assert_exit_code;
@@ -120,3 +121,87 @@ fn test_set_mgs_updates() {
     assert_contents("tests/output/cmd-set-mgs-updates-stdout", &stdout_text);
     assert_contents("tests/output/cmd-set-mgs-updates-stderr", &stderr_text);
 }
+
+// Render the topology of a small blueprint.
+#[test]
+fn test_blueprint_topology() {
+    let (exit_status, stdout_text, stderr_text) = run_cli(
+        "tests/input/cmds-blueprint-topology.txt",
+        &["--seed", "test_blueprint_topology"],
+    );
+    assert_exit_code(exit_status, EXIT_SUCCESS, &stderr_text);
+
+    // The example system uses a fixed seed, which means that UUIDs are
+    // deterministic. Some of the test commands also use those UUIDs, and it's
+    // convenient for everyone if they aren't redacted.
+    let stdout_text = Redactor::default().uuids(false).do_redact(&stdout_text);
+    assert_contents("tests/output/cmd-blueprint-topology-stdout", &stdout_text);
+    assert_contents("tests/output/cmd-blueprint-topology-stderr", &stderr_text);
+}
+
+// Run every scenario file, each of which checks its own results with `assert`
+// commands.
+#[test]
+fn test_scenarios() {
+    let scenarios_dir = Utf8Path::new("tests/scenarios")
+        .canonicalize_utf8()
+        .expect("scenarios directory canonicalized");
+    let tmpdir = camino_tempfile::tempdir().expect("failed to create tmpdir");
+    let exec = Exec::cmd(path_to_cli())
+        .arg("--scenarios")
+        .arg(scenarios_dir.as_str())
+        .cwd(tmpdir.path());
+    let (exit_status, stdout_text, stderr_text) = run_command(exec);
+    println!("{stdout_text}");
+    assert_exit_code(exit_status, EXIT_SUCCESS, &stderr_text);
+}
+
+// Check that scenarios with commands that fail to parse or run are reported
+// as failing, even if none of their assertions fail.
+#[test]
+fn test_scenarios_failing() {
+    let scenarios_dir = Utf8Path::new("tests/scenarios-failing")
+        .canonicalize_utf8()
+        .expect("scenarios directory canonicalized");
+    let tmpdir = camino_tempfile::tempdir().expect("failed to create tmpdir");
+    let exec = Exec::cmd(path_to_cli())
+        .arg("--scenarios")
+        .arg(scenarios_dir.as_str())
+        .cwd(tmpdir.path());
+    let (exit_status, stdout_text, stderr_text) = run_command(exec);
+    assert_exit_code(exit_status, EXIT_FAILURE, &stderr_text);
+    assert!(
+        stderr_text.contains("1 of 1 scenarios failed"),
+        "unexpected stderr: {stderr_text}"
+    );
+    assert_contents("tests/output/cmd-scenarios-failing-stdout", &stdout_text);
+}
+
+// Check that a failing `assert` command causes the run to fail.
+#[test]
+fn test_assert_failure() {
+    let (exit_status, stdout_text, stderr_text) = run_cli(
+        "tests/input/cmds-assert-failure.txt",
+        &["--seed", "test_assert_failure"],
+    );
+    println!("{stdout_text}");
+    assert_exit_code(exit_status, EXIT_FAILURE, &stderr_text);
+    assert!(
+        stderr_text.contains("1 of 2 assertions failed"),
+        "unexpected stderr: {stderr_text}"
+    );
+}
+
+// Run a few short randomized fault-injection runs with a fixed seed. Any
+// failure is printed along with a minimal script that reproduces it.
+#[test]
+fn test_chaos() {
+    let tmpdir = camino_tempfile::tempdir().expect("failed to create tmpdir");
+    let exec = Exec::cmd(path_to_cli())
+        .args(&["--seed", "test_chaos", "--chaos", "3"])
+        .args(&["--chaos-events", "25"])
+        .cwd(tmpdir.path());
+    let (exit_status, stdout_text, stderr_text) = run_command(exec);
+    println!("{stdout_text}");
+    assert_exit_code(exit_status, EXIT_SUCCESS, &stderr_text);
+}
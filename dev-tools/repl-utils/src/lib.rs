@@ -23,24 +23,29 @@ use std::io::BufReader;
 /// This is useful for expectorate tests.  You can define a file of input
 /// commands and check it against known output.  The output has the commands in
 /// it so that it's easier to verify.
+///
+/// Returns the number of commands that failed to parse or run.  (These don't
+/// stop the REPL, just as they wouldn't interactively.)
 pub fn run_repl_from_file<C: Parser>(
     input_file: &Utf8Path,
     run_one: &mut dyn FnMut(C) -> anyhow::Result<Option<String>>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<usize> {
     let file = File::open(&input_file)
         .with_context(|| format!("open {:?}", &input_file))?;
     let bufread = BufReader::new(file);
+    let mut nfailed = 0;
     for maybe_buffer in bufread.lines() {
         let buffer =
             maybe_buffer.with_context(|| format!("read {:?}", &input_file))?;
         println!("> {}", buffer);
         match process_entry(buffer, run_one) {
             LoopResult::Continue => (),
+            LoopResult::Failed => nfailed += 1,
             LoopResult::Bail(error) => return Err(error),
         }
         println!();
     }
-    Ok(())
+    Ok(nfailed)
 }
 
 /// Runs a REPL using stdin/stdout
@@ -76,7 +81,7 @@ pub fn run_repl_on_stdin<C: Parser>(
         match ed.read_line(&prompt) {
             Ok(Signal::Success(buffer)) => match process_entry(buffer, run_one)
             {
-                LoopResult::Continue => (),
+                LoopResult::Continue | LoopResult::Failed => (),
                 LoopResult::Bail(error) => return Err(error),
             },
             Ok(Signal::CtrlD) | Ok(Signal::CtrlC) => break,
@@ -121,7 +126,10 @@ fn process_entry<C: Parser>(
         Err(error) => {
             // We failed to parse the command.  Print the error.
             return match error.print() {
-                // Assuming that worked, just take another lap.
+                // Assuming that worked, just take another lap.  (clap also
+                // uses this path for requests for help, which aren't
+                // failures.)
+                Ok(_) if error.use_stderr() => LoopResult::Failed,
                 Ok(_) => LoopResult::Continue,
                 // If we failed to even print the error, that itself is a fatal
                 // error.
@@ -134,12 +142,16 @@ fn process_entry<C: Parser>(
     };
 
     match run_one(command) {
-        Err(error) => println!("error: {:#}", error),
-        Ok(Some(s)) => println!("{}", s),
-        Ok(None) => (),
+        Err(error) => {
+            println!("error: {:#}", error);
+            LoopResult::Failed
+        }
+        Ok(Some(s)) => {
+            println!("{}", s);
+            LoopResult::Continue
+        }
+        Ok(None) => LoopResult::Continue,
     }
-
-    LoopResult::Continue
 }
 
 /// Describes next steps after evaluating one "line" of user input
@@ -152,6 +164,10 @@ enum LoopResult {
     /// Show the prompt and accept another command
     Continue,
 
+    /// The command failed to parse or run; show the prompt and accept another
+    /// command
+    Failed,
+
     /// Exit the REPL with a fatal error
     Bail(anyhow::Error),
 }
@@ -8,6 +8,7 @@ use anyhow::Context;
 use anyhow::anyhow;
 use buf_list::BufList;
 use bytes::Buf;
+use bytes::Bytes;
 use camino::Utf8PathBuf;
 use clap::Parser;
 use dropshot::FreeformBody;
@@ -17,11 +18,14 @@ use dropshot::Path;
 use dropshot::RequestContext;
 use dropshot::ServerBuilder;
 use futures::stream::TryStreamExt;
+use range_requests::EntityTag;
+use range_requests::RequestContextEx;
 use repo_depot_api::ArtifactPathParams;
 use repo_depot_api::RepoDepotApi;
 use slog::info;
 use slog_error_chain::InlineErrorChain;
 use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tough::Repository;
@@ -148,20 +152,13 @@ impl RepoMetadata {
         let omicron_repo = &self.repos[*repo_index];
         Some((omicron_repo.repo(), target_name))
     }
-}
-
-struct StandaloneApiImpl;
 
-impl RepoDepotApi for StandaloneApiImpl {
-    type Context = Arc<RepoMetadata>;
-
-    async fn artifact_get_by_sha256(
-        rqctx: RequestContext<Self::Context>,
-        path_params: Path<ArtifactPathParams>,
-    ) -> Result<HttpResponseOk<FreeformBody>, HttpError> {
-        let repo_metadata = rqctx.context();
-        let requested_sha = &path_params.into_inner().sha256;
-        let (tuf_repo, target_name) = repo_metadata
+    /// Reads the full contents of the target with the given hash.
+    pub async fn read_target(
+        &self,
+        requested_sha: &ArtifactHash,
+    ) -> Result<Bytes, HttpError> {
+        let (tuf_repo, target_name) = self
             .repo_and_target_name_for_hash(requested_sha)
             .ok_or_else(|| {
                 HttpError::for_not_found(
@@ -193,9 +190,67 @@ impl RepoDepotApi for StandaloneApiImpl {
                     InlineErrorChain::new(&error),
                 ))
             })?;
-        let body = dropshot::Body::with_content(
-            buf_list.copy_to_bytes(buf_list.num_bytes()),
-        );
+        Ok(buf_list.copy_to_bytes(buf_list.num_bytes()))
+    }
+}
+
+struct StandaloneApiImpl;
+
+impl RepoDepotApi for StandaloneApiImpl {
+    type Context = Arc<RepoMetadata>;
+
+    async fn artifact_get_by_sha256(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<ArtifactPathParams>,
+    ) -> Result<HttpResponseOk<FreeformBody>, HttpError> {
+        let requested_sha = &path_params.into_inner().sha256;
+        let contents = rqctx.context().read_target(requested_sha).await?;
+        let body = dropshot::Body::with_content(contents);
         Ok(HttpResponseOk(FreeformBody::from(body)))
     }
+
+    async fn artifact_get_range_by_sha256(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<ArtifactPathParams>,
+    ) -> Result<http::Response<dropshot::Body>, HttpError> {
+        let requested_sha = &path_params.into_inner().sha256;
+        let contents = rqctx.context().read_target(requested_sha).await?;
+        let len = contents.len() as u64;
+
+        let etag = EntityTag::new(requested_sha.to_string())
+            .expect("hex strings are valid entity tags");
+        let range = rqctx.range().filter(|r| r.if_range_matches(&etag));
+        let (ranges, data) = match range.map(|range| range.parse(len)) {
+            Some(Ok(ranges)) => {
+                let data = ranges
+                    .iter()
+                    .map(|range| {
+                        contents.slice(
+                            range.start() as usize
+                                ..=range.end_inclusive() as usize,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                (Some(ranges), data)
+            }
+            Some(Err(err_response)) => return Ok(err_response),
+            None => (None, vec![contents]),
+        };
+
+        let mut response = range_requests::make_get_response(
+            ranges,
+            len,
+            None::<http::HeaderValue>,
+            futures::stream::iter(data.into_iter().map(Ok::<_, Infallible>)),
+        )
+        .map_err(|error| {
+            HttpError::for_internal_error(
+                InlineErrorChain::new(&error).to_string(),
+            )
+        })?;
+        response
+            .headers_mut()
+            .insert(http::header::ETAG, etag.to_header_value());
+        Ok(response)
+    }
 }
@@ -34,6 +34,12 @@
 //!
 //! See RFD 367 for more on DNS propagation.
 //!
+//! There are also a few read-only endpoints for debugging.  DNS servers retain
+//! the data for a few generations older than the current one.  These endpoints
+//! let an operator list those generations, fetch any one of them, and find out
+//! what the server would have answered for a particular name at that
+//! generation.  That's useful when chasing down problems with DNS propagation.
+//!
 //! ## ETags and Conditional Requests
 //!
 //! It's idiomatic in HTTP use ETags and conditional requests to provide
@@ -89,11 +95,17 @@
 //! in-progress one.  How large do we allow that queue to grow?  At some point
 //! we'll need to stop queueing them.  So why bother at all?
 
-use dropshot::{HttpError, HttpResponseOk, RequestContext};
-use internal_dns_types::config::{DnsConfig, DnsConfigParams};
+use dropshot::{HttpError, HttpResponseOk, Path, Query, RequestContext};
+use internal_dns_types::config::{
+    DnsConfig, DnsConfigParams, DnsGenerationConfig, DnsGenerationQueryResult,
+    DnsGenerationSummary,
+};
+use omicron_common::api::external::Generation;
 use openapi_manager_types::{
     SupportedVersion, SupportedVersions, api_versions,
 };
+use schemars::JsonSchema;
+use serde::Deserialize;
 
 api_versions!([
     // WHEN CHANGING THE API (part 1 of 2):
@@ -107,6 +119,7 @@ api_versions!([
     // |  example for the next person.
     // v
     // (next_int, IDENT),
+    (2, GENERATION_HISTORY),
     (1, INITIAL),
 ]);
 
@@ -142,4 +155,50 @@ pub trait DnsServerApi {
         rqctx: RequestContext<Self::Context>,
         rq: dropshot::TypedBody<DnsConfigParams>,
     ) -> Result<dropshot::HttpResponseUpdatedNoContent, dropshot::HttpError>;
+
+    /// List the generations of DNS data retained by this server
+    #[endpoint(
+        method = GET,
+        path = "/generations",
+        versions = VERSION_GENERATION_HISTORY..,
+    )]
+    async fn dns_generations_list(
+        rqctx: RequestContext<Self::Context>,
+    ) -> Result<HttpResponseOk<Vec<DnsGenerationSummary>>, HttpError>;
+
+    /// Fetch the full contents of a retained generation of DNS data
+    #[endpoint(
+        method = GET,
+        path = "/generations/{generation}",
+        versions = VERSION_GENERATION_HISTORY..,
+    )]
+    async fn dns_generation_get(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<GenerationPathParam>,
+    ) -> Result<HttpResponseOk<DnsGenerationConfig>, HttpError>;
+
+    /// Report what this server would have answered for a name when it was
+    /// serving a particular retained generation of DNS data
+    #[endpoint(
+        method = GET,
+        path = "/generations/{generation}/query",
+        versions = VERSION_GENERATION_HISTORY..,
+    )]
+    async fn dns_generation_query(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<GenerationPathParam>,
+        query_params: Query<GenerationQueryParams>,
+    ) -> Result<HttpResponseOk<DnsGenerationQueryResult>, HttpError>;
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+pub struct GenerationPathParam {
+    /// generation of DNS data
+    pub generation: Generation,
+}
+
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+pub struct GenerationQueryParams {
+    /// fully-qualified DNS name to look up
+    pub name: String,
 }
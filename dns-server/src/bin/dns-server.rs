@@ -11,8 +11,9 @@ use clap::Parser;
 use serde::Deserialize;
 use slog::info;
 use slog::o;
-use std::net::{SocketAddr, SocketAddrV6};
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
 use std::path::PathBuf;
+use uuid::Uuid;
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -24,6 +25,13 @@ struct Args {
 
     #[clap(long, action)]
     dns_address: SocketAddr,
+
+    /// Unique identifier for this DNS server
+    ///
+    /// If provided, query metrics are exported to oximeter using this as the
+    /// producer ID, overriding any `[metrics]` ID in the config file.
+    #[clap(long, action)]
+    id: Option<Uuid>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -31,6 +39,11 @@ pub struct Config {
     pub log: dropshot::ConfigLogging,
     pub dropshot: dropshot::ConfigDropshot,
     pub storage: dns_server::storage::Config,
+    /// If present, export query metrics to oximeter
+    #[serde(default)]
+    pub metrics: Option<dns_server::metrics::MetricsConfig>,
+    #[serde(default)]
+    pub query_log: dns_server::metrics::QueryLogConfig,
 }
 
 #[tokio::main]
@@ -43,6 +56,16 @@ async fn main() -> Result<(), anyhow::Error> {
         .with_context(|| format!("parse config file {:?}", config_file))?;
 
     config.dropshot.bind_address = SocketAddr::V6(args.http_address);
+    if let Some(id) = args.id {
+        let registration_address = config
+            .metrics
+            .as_ref()
+            .and_then(|metrics| metrics.registration_address);
+        config.metrics = Some(dns_server::metrics::MetricsConfig {
+            id,
+            registration_address,
+        });
+    }
     eprintln!("{:?}", config);
 
     let log = config
@@ -50,8 +73,10 @@ async fn main() -> Result<(), anyhow::Error> {
         .to_logger("dns-server")
         .context("failed to create logger")?;
 
-    let dns_server_config =
-        dns_server::dns_server::Config { bind_address: args.dns_address };
+    let dns_server_config = dns_server::dns_server::Config {
+        bind_address: args.dns_address,
+        query_log: config.query_log.clone(),
+    };
 
     info!(&log, "config";
         "config" => ?config,
@@ -64,14 +89,28 @@ async fn main() -> Result<(), anyhow::Error> {
     )
     .context("initializing persistent storage")?;
 
-    let (_dns_server, dropshot_server) = dns_server::start_servers(
-        log,
+    let (dns_server, dropshot_server) = dns_server::start_servers(
+        log.clone(),
         store,
         &dns_server_config,
         &config.dropshot,
     )
     .await?;
 
+    // Serve metrics on the same IP as the HTTP server.
+    let _producer_server = config
+        .metrics
+        .as_ref()
+        .map(|metrics_config| {
+            dns_server::metrics::start_producer_server(
+                &log,
+                metrics_config,
+                dns_server.metrics(),
+                IpAddr::V6(*args.http_address.ip()),
+            )
+        })
+        .transpose()?;
+
     dropshot_server
         .await
         .map_err(|error_message| anyhow!("server exiting: {}", error_message))
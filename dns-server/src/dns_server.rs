@@ -7,6 +7,10 @@
 //! The facilities here handle binding a UDP socket, receiving DNS messages on
 //! that socket, and replying to them.
 
+use crate::metrics::QueryLogConfig;
+use crate::metrics::QueryMetrics;
+use crate::metrics::QueryOutcome;
+use crate::metrics::UNKNOWN_ZONE;
 use crate::storage;
 use crate::storage::QueryError;
 use crate::storage::Store;
@@ -33,6 +37,7 @@ use slog::{Logger, debug, error, info, o, trace};
 use std::net::SocketAddr;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Instant;
 use thiserror::Error;
 use tokio::net::UdpSocket;
 use uuid::Uuid;
@@ -42,6 +47,9 @@ use uuid::Uuid;
 pub struct Config {
     /// The address to listen for DNS requests on
     pub bind_address: SocketAddr,
+    /// Configuration for sampled logging of individual queries
+    #[serde(default)]
+    pub query_log: QueryLogConfig,
 }
 
 /// Handle to the DNS server
@@ -49,6 +57,7 @@ pub struct Config {
 /// Dropping this handle shuts down the DNS server.
 pub struct ServerHandle {
     local_address: SocketAddr,
+    metrics: QueryMetrics,
     handle: tokio::task::JoinHandle<anyhow::Result<()>>,
 }
 
@@ -62,6 +71,11 @@ impl ServerHandle {
     pub fn local_address(&self) -> SocketAddr {
         self.local_address
     }
+
+    /// Returns the query metrics maintained by this server
+    pub fn metrics(&self) -> &QueryMetrics {
+        &self.metrics
+    }
 }
 
 /// DNS (protocol) server
@@ -72,6 +86,7 @@ pub struct Server {
     log: Logger,
     store: storage::Store,
     server_socket: Arc<UdpSocket>,
+    metrics: QueryMetrics,
 }
 
 impl Server {
@@ -98,9 +113,11 @@ impl Server {
             "local_address" => ?local_address
         );
 
-        let server = Server { log, store, server_socket };
+        let metrics = QueryMetrics::new(&config.query_log);
+        let server =
+            Server { log, store, server_socket, metrics: metrics.clone() };
         let handle = tokio::task::spawn(server.run());
-        Ok(ServerHandle { local_address, handle })
+        Ok(ServerHandle { local_address, metrics, handle })
     }
 
     async fn run(self) -> anyhow::Result<()> {
@@ -125,6 +142,7 @@ impl Server {
                 log,
                 store: self.store.clone(),
                 socket: self.server_socket.clone(),
+                metrics: self.metrics.clone(),
                 client_addr,
                 packet: buf,
                 req_id,
@@ -142,6 +160,7 @@ struct Request {
     log: Logger,
     store: Store,
     socket: Arc<UdpSocket>,
+    metrics: QueryMetrics,
     client_addr: SocketAddr,
     packet: Vec<u8>,
     #[allow(dead_code)]
@@ -165,8 +184,9 @@ async fn handle_dns_packet(request: Request) {
     };
 
     // Handle the message.
-    match handle_dns_message(&request, &mr).await {
-        Ok(_) => (),
+    let start = Instant::now();
+    let outcome = match handle_dns_message(&request, &mr).await {
+        Ok(_) => QueryOutcome::NoError,
         Err(error) => {
             let header = Header::response_from_request(mr.header());
             let rb_servfail = MessageResponseBuilder::from_message_request(&mr);
@@ -184,16 +204,31 @@ async fn handle_dns_packet(request: Request) {
                         rb_servfail,
                         &header,
                     )
-                    .await
+                    .await;
+                    QueryOutcome::NxDomain
                 }
                 RequestError::ServFail(_) => {
                     let rb_servfail =
                         MessageResponseBuilder::from_message_request(&mr);
-                    respond_servfail(&request, rb_servfail, &header).await
+                    respond_servfail(&request, rb_servfail, &header).await;
+                    QueryOutcome::ServFail
                 }
-            };
+            }
         }
-    }
+    };
+
+    // Record metrics for this query.
+    let latency = start.elapsed();
+    let query = mr.query();
+    let zone = request.store.zone_for_name(query.name());
+    request.metrics.record(
+        log,
+        zone.as_deref().unwrap_or(UNKNOWN_ZONE),
+        &query.original().name().to_string(),
+        &query.query_type().to_string(),
+        outcome,
+        latency,
+    );
 }
 
 /// Describes how to respond to a particular request failure
@@ -5,12 +5,20 @@
 //! Dropshot server for configuring DNS namespace
 
 use crate::storage::{self, UpdateError};
-use dns_server_api::DnsServerApi;
+use dns_server_api::{
+    DnsServerApi, GenerationPathParam, GenerationQueryParams,
+};
 use dns_service_client::{
     ERROR_CODE_BAD_UPDATE_GENERATION, ERROR_CODE_UPDATE_IN_PROGRESS,
 };
-use dropshot::RequestContext;
-use internal_dns_types::config::{DnsConfig, DnsConfigParams};
+use dropshot::{HttpError, HttpResponseOk, Path, Query, RequestContext};
+use hickory_resolver::Name;
+use internal_dns_types::config::{
+    DnsConfig, DnsConfigParams, DnsGenerationConfig, DnsGenerationQueryResult,
+    DnsGenerationSummary,
+};
+use omicron_common::api::external::Generation;
+use std::str::FromStr;
 
 pub struct Context {
     store: storage::Store,
@@ -57,6 +65,62 @@ impl DnsServerApi for DnsServerApiImpl {
             .await?;
         Ok(dropshot::HttpResponseUpdatedNoContent())
     }
+
+    async fn dns_generations_list(
+        rqctx: RequestContext<Context>,
+    ) -> Result<HttpResponseOk<Vec<DnsGenerationSummary>>, HttpError> {
+        let apictx = rqctx.context();
+        let generations =
+            apictx.store.generations_list().map_err(internal_error)?;
+        Ok(HttpResponseOk(generations))
+    }
+
+    async fn dns_generation_get(
+        rqctx: RequestContext<Context>,
+        path_params: Path<GenerationPathParam>,
+    ) -> Result<HttpResponseOk<DnsGenerationConfig>, HttpError> {
+        let apictx = rqctx.context();
+        let generation = path_params.into_inner().generation;
+        let config = apictx
+            .store
+            .generation_config(generation)
+            .map_err(internal_error)?
+            .ok_or_else(|| generation_not_found(generation))?;
+        Ok(HttpResponseOk(config))
+    }
+
+    async fn dns_generation_query(
+        rqctx: RequestContext<Context>,
+        path_params: Path<GenerationPathParam>,
+        query_params: Query<GenerationQueryParams>,
+    ) -> Result<HttpResponseOk<DnsGenerationQueryResult>, HttpError> {
+        let apictx = rqctx.context();
+        let generation = path_params.into_inner().generation;
+        let name_str = query_params.into_inner().name;
+        let name = Name::from_str(&name_str).map_err(|error| {
+            HttpError::for_bad_request(
+                None,
+                format!("invalid DNS name {:?}: {:#}", name_str, error),
+            )
+        })?;
+        let result = apictx
+            .store
+            .generation_query(generation, &name)
+            .map_err(internal_error)?
+            .ok_or_else(|| generation_not_found(generation))?;
+        Ok(HttpResponseOk(result))
+    }
+}
+
+fn internal_error(error: anyhow::Error) -> HttpError {
+    HttpError::for_internal_error(format!("internal error: {:#}", error))
+}
+
+fn generation_not_found(generation: Generation) -> HttpError {
+    HttpError::for_not_found(
+        None,
+        format!("generation {} is not retained by this server", generation),
+    )
 }
 
 impl From<UpdateError> for dropshot::HttpError {
@@ -41,9 +41,13 @@
 //!    over the DNS protocol
 //! 3. A Dropshot server that serves HTTP endpoints for reading and modifying
 //!    the persistent DNS data
+//!
+//! The DNS server also keeps [`metrics`] about the queries it answers, which
+//! can be exported to oximeter.
 
 pub mod dns_server;
 pub mod http_server;
+pub mod metrics;
 pub mod storage;
 
 use anyhow::{Context, anyhow};
@@ -134,7 +138,10 @@ impl TransientServer {
         let (dns_server, dropshot_server) = start_servers(
             dns_log,
             store,
-            &dns_server::Config { bind_address: dns_bind_address },
+            &dns_server::Config {
+                bind_address: dns_bind_address,
+                query_log: metrics::QueryLogConfig::default(),
+            },
             &dropshot::ConfigDropshot {
                 bind_address: "[::1]:0".parse().unwrap(),
                 default_request_body_max_bytes: 4 * 1024 * 1024,
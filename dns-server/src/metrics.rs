@@ -0,0 +1,472 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Query metrics and sampled query logging for the DNS server
+//!
+//! The DNS server always keeps track of per-zone, per-record-type query
+//! counters and latency histograms in memory.  These are exported to oximeter
+//! only when the server is configured as a metric producer (see
+//! [`MetricsConfig`] and [`start_producer_server`]).
+
+use omicron_common::api::internal::nexus::ProducerEndpoint;
+use omicron_common::api::internal::nexus::ProducerKind;
+use oximeter::MetricsError;
+use oximeter::Sample;
+use oximeter::histogram::Histogram;
+use oximeter::histogram::Record;
+use oximeter::types::Cumulative;
+use oximeter::types::ProducerRegistry;
+use serde::Deserialize;
+use slog::Logger;
+use slog::info;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use uuid::Uuid;
+
+oximeter::use_timeseries!("dns-server.toml");
+pub use dns_server::DnsServer;
+use dns_server::Queries;
+use dns_server::QueryLatency;
+
+/// Label used for the `zone` field of queries for names for which we are not
+/// authoritative
+pub const UNKNOWN_ZONE: &str = "unknown";
+
+/// Interval at which we ask oximeter to collect our metrics
+const COLLECTION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum request body size for the producer server
+///
+/// Oximeter's requests to us don't carry a body, so this can be small.
+const PRODUCER_REQUEST_MAX_BYTES: usize = 1024 * 1024;
+
+/// Configuration for exporting query metrics to oximeter
+#[derive(Deserialize, Debug, Clone)]
+pub struct MetricsConfig {
+    /// Unique identifier for this DNS server, used as both the producer ID and
+    /// the `id` field of the `dns_server` target
+    pub id: Uuid,
+    /// Explicit address of Nexus to register with
+    ///
+    /// If this is not provided, Nexus is located using internal DNS.
+    #[serde(default)]
+    pub registration_address: Option<SocketAddr>,
+}
+
+/// Configuration for the sampled structured query log
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct QueryLogConfig {
+    /// Log one out of every `sample_one_in` queries
+    ///
+    /// If this is 0 (the default), query logging is disabled.
+    #[serde(default)]
+    pub sample_one_in: u64,
+}
+
+/// Outcome of a single DNS query, as far as metrics are concerned
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum QueryOutcome {
+    NoError,
+    NxDomain,
+    ServFail,
+}
+
+impl QueryOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueryOutcome::NoError => "noerror",
+            QueryOutcome::NxDomain => "nxdomain",
+            QueryOutcome::ServFail => "servfail",
+        }
+    }
+}
+
+/// In-memory query metrics, shared by all tasks handling DNS queries
+///
+/// This is cheap to clone.  All clones refer to the same underlying data.
+#[derive(Clone, Debug)]
+pub struct QueryMetrics {
+    inner: Arc<Mutex<QueryMetricsInner>>,
+    query_log: Arc<QueryLog>,
+}
+
+#[derive(Debug)]
+struct QueryMetricsInner {
+    queries: BTreeMap<(String, String, QueryOutcome), Queries>,
+    latencies: BTreeMap<(String, String), QueryLatency>,
+    /// Template histogram, cloned for each new (zone, record type) pair
+    histogram: Histogram<u64>,
+}
+
+#[derive(Debug)]
+struct QueryLog {
+    sample_one_in: u64,
+    nqueries: AtomicU64,
+}
+
+impl QueryMetrics {
+    pub fn new(query_log: &QueryLogConfig) -> Self {
+        // Bins span 1 microsecond to 10 seconds.
+        let histogram = Histogram::span_decades(3, 10)
+            .expect("hardcoded histogram bins are valid");
+        QueryMetrics {
+            inner: Arc::new(Mutex::new(QueryMetricsInner {
+                queries: BTreeMap::new(),
+                latencies: BTreeMap::new(),
+                histogram,
+            })),
+            query_log: Arc::new(QueryLog {
+                sample_one_in: query_log.sample_one_in,
+                nqueries: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Record the outcome of one DNS query
+    ///
+    /// If query logging is enabled and this query was selected by sampling, a
+    /// structured log entry describing it is emitted to `log`.
+    pub fn record(
+        &self,
+        log: &Logger,
+        zone: &str,
+        name: &str,
+        record_type: &str,
+        outcome: QueryOutcome,
+        latency: Duration,
+    ) {
+        {
+            let mut inner = self.inner.lock().unwrap();
+            inner
+                .queries
+                .entry((zone.to_string(), record_type.to_string(), outcome))
+                .or_insert_with(|| Queries {
+                    zone: zone.to_string().into(),
+                    record_type: record_type.to_string().into(),
+                    response_code: outcome.as_str().into(),
+                    datum: Cumulative::default(),
+                })
+                .datum += 1;
+
+            let histogram = inner.histogram.clone();
+            let entry = inner
+                .latencies
+                .entry((zone.to_string(), record_type.to_string()))
+                .or_insert_with(|| QueryLatency {
+                    zone: zone.to_string().into(),
+                    record_type: record_type.to_string().into(),
+                    datum: histogram,
+                });
+            // Latencies beyond the range of the histogram are counted in its
+            // overflow bin, so this can only fail if the latency doesn't fit in
+            // a u64 worth of nanoseconds.  That's not worth failing over.
+            let _ = entry
+                .datum
+                .sample(u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX));
+        }
+
+        if self.query_log.should_log() {
+            info!(log, "query log";
+                "zone" => zone,
+                "name" => name,
+                "record_type" => record_type,
+                "response_code" => outcome.as_str(),
+                "latency_us" => latency.as_micros(),
+            );
+        }
+    }
+
+    /// Returns an oximeter producer that reports these metrics under the given
+    /// target
+    pub fn producer(&self, target: DnsServer) -> QueryMetricsProducer {
+        QueryMetricsProducer { target, metrics: self.clone() }
+    }
+
+    /// Returns the total number of queries recorded for the given zone with
+    /// the given outcome (across all record types)
+    ///
+    /// This is primarily intended for testing.
+    pub fn query_count(&self, zone: &str, outcome: QueryOutcome) -> u64 {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .queries
+            .iter()
+            .filter(|((z, _, o), _)| z == zone && *o == outcome)
+            .map(|(_, q)| q.datum.value())
+            .sum()
+    }
+}
+
+impl QueryLog {
+    fn should_log(&self) -> bool {
+        if self.sample_one_in == 0 {
+            return false;
+        }
+        let n = self.nqueries.fetch_add(1, Ordering::Relaxed);
+        n % self.sample_one_in == 0
+    }
+}
+
+/// [`oximeter::Producer`] for the DNS server's query metrics
+#[derive(Debug)]
+pub struct QueryMetricsProducer {
+    target: DnsServer,
+    metrics: QueryMetrics,
+}
+
+impl oximeter::Producer for QueryMetricsProducer {
+    fn produce(
+        &mut self,
+    ) -> Result<Box<dyn Iterator<Item = Sample>>, MetricsError> {
+        let inner = self.metrics.inner.lock().unwrap();
+        let mut v =
+            Vec::with_capacity(inner.queries.len() + inner.latencies.len());
+        for metric in inner.queries.values() {
+            v.push(Sample::new(&self.target, metric)?);
+        }
+        for metric in inner.latencies.values() {
+            v.push(Sample::new(&self.target, metric)?);
+        }
+        Ok(Box::new(v.into_iter()))
+    }
+}
+
+/// Starts an oximeter producer server exporting `metrics`
+///
+/// The producer server listens on an arbitrary port on `listen_ip`, which
+/// should generally be the same IP as the DNS server's HTTP interface.
+pub fn start_producer_server(
+    log: &Logger,
+    config: &MetricsConfig,
+    metrics: &QueryMetrics,
+    listen_ip: IpAddr,
+) -> Result<oximeter_producer::Server, anyhow::Error> {
+    let registry = ProducerRegistry::with_id(config.id);
+    registry
+        .register_producer(metrics.producer(DnsServer { id: config.id }))
+        .map_err(|error| {
+        anyhow::anyhow!("registering query metrics producer: {:#}", error)
+    })?;
+
+    let producer_config = oximeter_producer::Config {
+        server_info: ProducerEndpoint {
+            id: config.id,
+            kind: ProducerKind::Service,
+            address: SocketAddr::new(listen_ip, 0),
+            interval: COLLECTION_INTERVAL,
+        },
+        registration_address: config.registration_address,
+        default_request_body_max_bytes: PRODUCER_REQUEST_MAX_BYTES,
+        log: oximeter_producer::LogConfig::Logger(
+            log.new(slog::o!("component" => "producer-server")),
+        ),
+    };
+
+    oximeter_producer::Server::with_registry(registry, &producer_config)
+        .map_err(|error| {
+            anyhow::anyhow!("starting metrics producer server: {:#}", error)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::DnsServer;
+    use super::MetricsConfig;
+    use super::QueryLogConfig;
+    use super::QueryMetrics;
+    use super::QueryOutcome;
+    use super::start_producer_server;
+    use dropshot::ApiDescription;
+    use dropshot::ConfigDropshot;
+    use dropshot::HttpError;
+    use dropshot::HttpResponseCreated;
+    use dropshot::HttpServer;
+    use dropshot::RequestContext;
+    use dropshot::ServerBuilder;
+    use dropshot::TypedBody;
+    use dropshot::endpoint;
+    use omicron_common::api::internal::nexus::ProducerEndpoint;
+    use omicron_common::api::internal::nexus::ProducerRegistrationResponse;
+    use omicron_test_utils::dev::poll::{CondCheckError, wait_for_condition};
+    use omicron_test_utils::dev::test_setup_log;
+    use oximeter::Producer;
+    use oximeter::types::ProducerResults;
+    use oximeter::types::ProducerResultsItem;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_query_metrics() {
+        let logctx = test_setup_log("test_query_metrics");
+        let log = &logctx.log;
+        let metrics = QueryMetrics::new(&QueryLogConfig { sample_one_in: 2 });
+        let zone = "oxide.internal";
+        for _ in 0..3 {
+            metrics.record(
+                log,
+                zone,
+                "a.oxide.internal.",
+                "AAAA",
+                QueryOutcome::NoError,
+                Duration::from_micros(50),
+            );
+        }
+        metrics.record(
+            log,
+            zone,
+            "a.oxide.internal.",
+            "SRV",
+            QueryOutcome::NoError,
+            Duration::from_micros(50),
+        );
+        metrics.record(
+            log,
+            zone,
+            "b.oxide.internal.",
+            "AAAA",
+            QueryOutcome::NxDomain,
+            Duration::from_millis(2),
+        );
+
+        assert_eq!(metrics.query_count(zone, QueryOutcome::NoError), 4);
+        assert_eq!(metrics.query_count(zone, QueryOutcome::NxDomain), 1);
+        assert_eq!(metrics.query_count(zone, QueryOutcome::ServFail), 0);
+
+        // We expect one counter per (record type, outcome) pair and one
+        // histogram per record type.
+        let mut producer = metrics.producer(DnsServer { id: Uuid::new_v4() });
+        let samples: Vec<_> = producer.produce().unwrap().collect();
+        assert_eq!(samples.len(), 5);
+        assert_eq!(
+            samples
+                .iter()
+                .filter(|s| s.timeseries_name == "dns_server:queries")
+                .count(),
+            3
+        );
+        assert_eq!(
+            samples
+                .iter()
+                .filter(|s| s.timeseries_name == "dns_server:query_latency")
+                .count(),
+            2
+        );
+
+        logctx.cleanup_successful();
+    }
+
+    type FakeNexusContext = Arc<Mutex<Vec<ProducerEndpoint>>>;
+
+    // Mock of the Nexus endpoint with which producers register.
+    #[endpoint {
+        method = POST,
+        path = "/metrics/producers",
+    }]
+    async fn register_producer(
+        rqctx: RequestContext<FakeNexusContext>,
+        body: TypedBody<ProducerEndpoint>,
+    ) -> Result<HttpResponseCreated<ProducerRegistrationResponse>, HttpError>
+    {
+        rqctx.context().lock().unwrap().push(body.into_inner());
+        Ok(HttpResponseCreated(ProducerRegistrationResponse {
+            lease_duration: Duration::from_secs(60),
+        }))
+    }
+
+    fn spawn_fake_nexus_server(
+        log: &slog::Logger,
+    ) -> HttpServer<FakeNexusContext> {
+        let mut api = ApiDescription::new();
+        api.register(register_producer).expect("registered endpoint");
+        ServerBuilder::new(api, Arc::new(Mutex::new(Vec::new())), log.clone())
+            .config(ConfigDropshot {
+                bind_address: "[::1]:0".parse().unwrap(),
+                ..Default::default()
+            })
+            .start()
+            .expect("started fake Nexus")
+    }
+
+    #[tokio::test]
+    async fn test_producer_server() {
+        let logctx = test_setup_log("test_producer_server");
+        let log = &logctx.log;
+        let fake_nexus = spawn_fake_nexus_server(log);
+
+        let metrics = QueryMetrics::new(&QueryLogConfig::default());
+        metrics.record(
+            log,
+            "oxide.internal",
+            "a.oxide.internal.",
+            "AAAA",
+            QueryOutcome::NoError,
+            Duration::from_micros(50),
+        );
+
+        let config = MetricsConfig {
+            id: Uuid::new_v4(),
+            registration_address: Some(fake_nexus.local_addr()),
+        };
+        let server = start_producer_server(
+            log,
+            &config,
+            &metrics,
+            "::1".parse().unwrap(),
+        )
+        .expect("started producer server");
+
+        // The server should register itself with Nexus under the configured
+        // ID, at the address on which it's listening.
+        let registered = wait_for_condition(
+            || async {
+                match fake_nexus.app_private().lock().unwrap().first() {
+                    Some(endpoint) => Ok(*endpoint),
+                    None => Err(CondCheckError::<()>::NotYet),
+                }
+            },
+            &Duration::from_millis(50),
+            &Duration::from_secs(30),
+        )
+        .await
+        .expect("producer did not register with Nexus");
+        assert_eq!(registered.id, config.id);
+        assert_eq!(registered.address, server.address());
+
+        // Collecting from it, as oximeter would, should return the queries
+        // recorded above.
+        let results: ProducerResults =
+            reqwest::get(format!("http://{}/{}", server.address(), config.id))
+                .await
+                .expect("collected from producer")
+                .error_for_status()
+                .expect("collection succeeded")
+                .json()
+                .await
+                .expect("parsed producer results");
+        let samples: Vec<_> = results
+            .into_iter()
+            .flat_map(|item| match item {
+                ProducerResultsItem::Ok(samples) => samples,
+                ProducerResultsItem::Err(error) => {
+                    panic!("producer failed: {error:?}")
+                }
+            })
+            .collect();
+        assert!(
+            samples.iter().any(|s| s.timeseries_name == "dns_server:queries"),
+            "missing query counter in {samples:?}"
+        );
+
+        fake_nexus.close().await.expect("closed fake Nexus");
+        logctx.cleanup_successful();
+    }
+}
@@ -97,12 +97,14 @@ use camino::Utf8PathBuf;
 use hickory_proto::rr::LowerName;
 use hickory_resolver::Name;
 use internal_dns_types::config::{
-    DnsConfig, DnsConfigParams, DnsConfigZone, DnsRecord,
+    DnsConfig, DnsConfigParams, DnsConfigZone, DnsGenerationConfig,
+    DnsGenerationQueryResult, DnsGenerationSummary, DnsRecord, ZONE_APEX_NAME,
 };
 use omicron_common::api::external::Generation;
 use serde::{Deserialize, Serialize};
 use sled::transaction::ConflictableTransactionError;
 use slog::{debug, error, info, o, warn};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
@@ -249,50 +251,7 @@ impl Store {
         let zones = config
             .zones
             .iter()
-            .map(|zone_name| {
-                // TODO-correctness What happens if any of these trees are
-                // removed while we're doing this (as might happen if somebody
-                // does an update)?  In practice this seems unlikely because we
-                // keep the last few generations' trees.  If it does happen, it
-                // seems like we'll wind up bailing with a SERVFAIL.  That's not
-                // great, but it's not the worst.  A retry should work as long
-                // as updates aren't constantly streaming in.  If this becomes a
-                // problem, we could centrally track the generations being read
-                // and avoid deleting trees that we would otherwise prune until
-                // those reads finish.  (That creates a new problem: what if the
-                // read gets stuck for some reason?  We don't want to leave
-                // these trees hanging around forever.)
-                let tree_name =
-                    Self::tree_name_for_zone(zone_name, config.generation);
-                let tree = self
-                    .db
-                    .open_tree(&tree_name)
-                    .with_context(|| format!("opening tree {:?}", tree_name))?;
-
-                let records = tree
-                    .iter()
-                    .map(|entry| {
-                        let (name_bytes, records_bytes) =
-                            entry.context("loading entry")?;
-                        let name = std::str::from_utf8(&name_bytes)
-                            .with_context(|| {
-                                format!("parsing {:?} key name", tree_name)
-                            })?;
-                        let records: Vec<DnsRecord> =
-                            serde_json::from_slice(&records_bytes)
-                                .with_context(|| {
-                                    format!(
-                                        "parsing {:?} key {:?}",
-                                        tree_name, name
-                                    )
-                                })?;
-                        Ok((name.to_owned(), records))
-                    })
-                    .collect::<anyhow::Result<_>>()
-                    .context("assembling records")?;
-
-                Ok(DnsConfigZone { zone_name: zone_name.to_owned(), records })
-            })
+            .map(|zone_name| self.zone_config(zone_name, config.generation))
             .collect::<anyhow::Result<_>>()?;
 
         Ok(DnsConfig {
@@ -303,6 +262,156 @@ impl Store {
         })
     }
 
+    /// Loads all the names and records for one zone at one generation
+    fn zone_config(
+        &self,
+        zone_name: &str,
+        generation: Generation,
+    ) -> Result<DnsConfigZone, anyhow::Error> {
+        // TODO-correctness What happens if this tree is removed while we're
+        // doing this (as might happen if somebody does an update)?  In practice
+        // this seems unlikely because we keep the last few generations' trees.
+        // If it does happen, it seems like we'll wind up bailing with a
+        // SERVFAIL.  That's not great, but it's not the worst.  A retry should
+        // work as long as updates aren't constantly streaming in.  If this
+        // becomes a problem, we could centrally track the generations being
+        // read and avoid deleting trees that we would otherwise prune until
+        // those reads finish.  (That creates a new problem: what if the read
+        // gets stuck for some reason?  We don't want to leave these trees
+        // hanging around forever.)
+        let tree_name = Self::tree_name_for_zone(zone_name, generation);
+        let tree = self
+            .db
+            .open_tree(&tree_name)
+            .with_context(|| format!("opening tree {:?}", tree_name))?;
+
+        let records = tree
+            .iter()
+            .map(|entry| {
+                let (name_bytes, records_bytes) =
+                    entry.context("loading entry")?;
+                let name =
+                    std::str::from_utf8(&name_bytes).with_context(|| {
+                        format!("parsing {:?} key name", tree_name)
+                    })?;
+                let records: Vec<DnsRecord> =
+                    serde_json::from_slice(&records_bytes).with_context(
+                        || format!("parsing {:?} key {:?}", tree_name, name),
+                    )?;
+                Ok((name.to_owned(), records))
+            })
+            .collect::<anyhow::Result<_>>()
+            .context("assembling records")?;
+
+        Ok(DnsConfigZone { zone_name: zone_name.to_owned(), records })
+    }
+
+    /// Returns the zones present in each generation that we're still
+    /// retaining, up to and including the current one
+    ///
+    /// For the current generation, the zone names come from the current
+    /// config.  For older generations, they're inferred from the names of the
+    /// trees that we haven't yet pruned.
+    fn retained_generations(
+        &self,
+    ) -> Result<(CurrentConfig, BTreeMap<Generation, Vec<String>>), anyhow::Error>
+    {
+        let config = self.read_config()?;
+        let mut generations = BTreeMap::new();
+        for (generation, tree_name) in self.all_name_trees() {
+            // Trees newer than the current generation belong to an update
+            // that's in progress (or was interrupted).  They were never served.
+            if generation >= config.generation {
+                continue;
+            }
+            let zone_name = tree_name
+                .splitn(4, '_')
+                .nth(3)
+                .expect("all_name_trees() returns well-formed names");
+            generations
+                .entry(generation)
+                .or_insert_with(Vec::new)
+                .push(zone_name.to_owned());
+        }
+        generations.insert(config.generation, config.zones.clone());
+        Ok((config, generations))
+    }
+
+    /// Lists the generations of DNS data that we're still retaining, newest
+    /// first
+    pub(crate) fn generations_list(
+        &self,
+    ) -> Result<Vec<DnsGenerationSummary>, anyhow::Error> {
+        let (config, generations) = self.retained_generations()?;
+        Ok(generations
+            .into_iter()
+            .rev()
+            .map(|(generation, zones)| DnsGenerationSummary {
+                generation,
+                current: generation == config.generation,
+                zones,
+            })
+            .collect())
+    }
+
+    /// Fetches the full contents of a retained generation of DNS data
+    ///
+    /// Returns `None` if we're not retaining this generation.
+    pub(crate) fn generation_config(
+        &self,
+        generation: Generation,
+    ) -> Result<Option<DnsGenerationConfig>, anyhow::Error> {
+        let (_, generations) = self.retained_generations()?;
+        let Some(zone_names) = generations.get(&generation) else {
+            return Ok(None);
+        };
+        let zones = zone_names
+            .iter()
+            .map(|zone_name| self.zone_config(zone_name, generation))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Some(DnsGenerationConfig { generation, zones }))
+    }
+
+    /// Reports what we would have answered for `name` while serving a retained
+    /// generation of DNS data
+    ///
+    /// Returns `None` if we're not retaining this generation.
+    pub(crate) fn generation_query(
+        &self,
+        generation: Generation,
+        name: &Name,
+    ) -> Result<Option<DnsGenerationQueryResult>, anyhow::Error> {
+        let (_, generations) = self.retained_generations()?;
+        let Some(zone_names) = generations.get(&generation) else {
+            return Ok(None);
+        };
+
+        let mut result = DnsGenerationQueryResult {
+            generation,
+            name: name.to_string(),
+            zone: None,
+            records: Vec::new(),
+        };
+        let Some(zone_name) =
+            Self::find_zone(zone_names, &LowerName::new(name))
+        else {
+            return Ok(Some(result));
+        };
+
+        result.zone = Some(zone_name.clone());
+        match self.query_zone(zone_name, generation, name) {
+            Ok(records) => result.records = records,
+            Err(QueryError::NoName(_)) => (),
+            Err(error) => {
+                return Err(anyhow!(error).context(format!(
+                    "querying generation {} for {:?}",
+                    generation, result.name
+                )));
+            }
+        }
+        Ok(Some(result))
+    }
+
     async fn begin_update<'a, 'b>(
         &'a self,
         req_id: &'b str,
@@ -587,6 +696,26 @@ impl Store {
         self.prune_trees(trees_to_prune, "too old");
     }
 
+    /// Returns the name of the zone containing `name`, if this server is
+    /// authoritative for it
+    ///
+    /// This is used for labeling query metrics.  Callers looking for records
+    /// should use [`Store::query()`] instead.
+    pub(crate) fn zone_for_name(&self, name: &LowerName) -> Option<String> {
+        let config = self.read_config().ok()?;
+        Self::find_zone(&config.zones, name).cloned()
+    }
+
+    fn find_zone<'a>(
+        zones: &'a [String],
+        name: &LowerName,
+    ) -> Option<&'a String> {
+        zones.iter().find(|z| {
+            let zone_name = LowerName::from(Name::from_str(&z).unwrap());
+            zone_name.zone_of(name)
+        })
+    }
+
     /// Returns a non-empty list of DNS records associated with the name in the
     /// given DNS request.
     ///
@@ -617,16 +746,21 @@ impl Store {
     ) -> Result<Vec<DnsRecord>, QueryError> {
         let config = self.read_config().map_err(QueryError::QueryFail)?;
 
-        let zone_name = config
-            .zones
-            .iter()
-            .find(|z| {
-                let zone_name = LowerName::from(Name::from_str(&z).unwrap());
-                zone_name.zone_of(name)
-            })
+        let zone_name = Self::find_zone(&config.zones, name)
             .ok_or_else(|| QueryError::NoZone(orig_name.to_string()))?;
+        self.query_zone(zone_name, config.generation, orig_name)
+    }
 
-        let tree_name = Self::tree_name_for_zone(zone_name, config.generation);
+    /// Returns a non-empty list of DNS records associated with `orig_name`,
+    /// which must be contained within zone `zone_name`, at the given
+    /// generation
+    fn query_zone(
+        &self,
+        zone_name: &str,
+        generation: Generation,
+        orig_name: &Name,
+    ) -> Result<Vec<DnsRecord>, QueryError> {
+        let tree_name = Self::tree_name_for_zone(zone_name, generation);
         let tree = self
             .db
             .open_tree(&tree_name)
@@ -636,6 +770,7 @@ impl Store {
         // The name tree stores just the part of each name that doesn't include
         // the zone.  So we need to trim the zone part from the name provided in
         // the request.  (This basically duplicates work in `zone_of` above.)
+        // The zone's apex is stored as `ZONE_APEX_NAME`.
         let name_str = orig_name.to_string();
         let key = {
             let zone_name = Name::from_str(zone_name).unwrap();
@@ -643,13 +778,17 @@ impl Store {
             assert!(zone_name.num_labels() <= orig_name.num_labels());
             let name_only_labels =
                 usize::from(orig_name.num_labels() - zone_name.num_labels());
-            let mut name_only =
-                Name::from_labels(orig_name.iter().take(name_only_labels))
-                    .unwrap();
-            name_only.set_fqdn(false);
-            let key = name_only.to_string().to_lowercase();
-            assert!(!key.ends_with('.'));
-            key
+            if name_only_labels == 0 {
+                ZONE_APEX_NAME.to_owned()
+            } else {
+                let mut name_only =
+                    Name::from_labels(orig_name.iter().take(name_only_labels))
+                        .unwrap();
+                name_only.set_fqdn(false);
+                let key = name_only.to_string().to_lowercase();
+                assert!(!key.ends_with('.'));
+                key
+            }
         };
 
         debug!(&self.log, "query key"; "key" => &key);
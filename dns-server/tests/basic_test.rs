@@ -4,6 +4,7 @@
 
 use anyhow::{Context, Result};
 use camino_tempfile::Utf8TempDir;
+use dns_server::metrics::QueryOutcome;
 use dns_service_client::Client;
 use dropshot::{HandlerTaskMode, test_util::LogContext};
 use hickory_client::{
@@ -22,14 +23,17 @@ use hickory_resolver::{
     },
 };
 use internal_dns_types::config::{
-    DnsConfigParams, DnsConfigZone, DnsRecord, Srv,
+    DnsConfigParams, DnsConfigZone, DnsRecord, Srv, ZONE_APEX_NAME,
 };
+use omicron_common::api::external::Generation;
+use omicron_test_utils::dev::poll::{CondCheckError, wait_for_condition};
 use omicron_test_utils::dev::test_setup_log;
 use slog::o;
 use std::{
     collections::HashMap,
     net::Ipv6Addr,
     net::{IpAddr, Ipv4Addr},
+    time::Duration,
 };
 
 const TEST_ZONE: &'static str = "oxide.internal";
@@ -325,6 +329,28 @@ pub async fn name_contains_zone() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[tokio::test]
+pub async fn zone_apex() -> Result<(), anyhow::Error> {
+    let test_ctx = init_client_server("zone_apex").await?;
+    let client = &test_ctx.client;
+    let resolver = &test_ctx.resolver;
+
+    // Records for the zone itself are stored under the apex name.
+    let addr = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x2);
+    let input_records = HashMap::from([(
+        ZONE_APEX_NAME.to_string(),
+        vec![DnsRecord::Aaaa(addr)],
+    )]);
+    dns_records_create(client, TEST_ZONE, input_records).await?;
+
+    let response = resolver.lookup_ip(TEST_ZONE.to_string() + ".").await?;
+    let address = response.iter().next().expect("no addresses returned!");
+    assert_eq!(address, addr);
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
 #[tokio::test]
 pub async fn empty_record() -> Result<(), anyhow::Error> {
     let test_ctx = init_client_server("empty_record").await?;
@@ -375,6 +401,24 @@ pub async fn nxdomain() -> Result<(), anyhow::Error> {
     lookup_ip_expect_nxdomain(&resolver, &format!("unicorn.{}.", TEST_ZONE))
         .await;
 
+    // The failed lookup should be reflected in the server's query metrics.
+    // These are recorded after the response is sent, so we may have to wait
+    // briefly for them to show up.
+    let metrics = test_ctx.dns_server.metrics();
+    wait_for_condition(
+        || async {
+            if metrics.query_count(TEST_ZONE, QueryOutcome::NxDomain) > 0 {
+                Ok(())
+            } else {
+                Err(CondCheckError::<()>::NotYet)
+            }
+        },
+        &Duration::from_millis(10),
+        &Duration::from_secs(30),
+    )
+    .await
+    .expect("NXDOMAIN response was not counted");
+
     test_ctx.cleanup().await;
     Ok(())
 }
@@ -417,6 +461,80 @@ pub async fn servfail() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+#[tokio::test]
+pub async fn generation_history() -> Result<(), anyhow::Error> {
+    let test_ctx = init_client_server("generation_history").await?;
+    let client = &test_ctx.client;
+
+    // Create two generations: the first with one name and the second adding
+    // another.
+    let addr1 = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x1);
+    let addr2 = Ipv6Addr::new(0xfd, 0, 0, 0, 0, 0, 0, 0x2);
+    dns_records_create(
+        client,
+        TEST_ZONE,
+        HashMap::from([("devron".to_string(), vec![DnsRecord::Aaaa(addr1)])]),
+    )
+    .await?;
+    dns_records_create(
+        client,
+        TEST_ZONE,
+        HashMap::from([("unicorn".to_string(), vec![DnsRecord::Aaaa(addr2)])]),
+    )
+    .await?;
+
+    // Both generations should be retained, with the newest one current.
+    let generations = client.dns_generations_list().await?.into_inner();
+    let summary: Vec<_> = generations
+        .iter()
+        .map(|g| (u64::from(g.generation), g.current, g.zones.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (2, true, vec![TEST_ZONE.to_string()]),
+            (1, false, vec![TEST_ZONE.to_string()]),
+        ]
+    );
+
+    let gen1 = Generation::from_u32(1);
+    let gen2 = Generation::from_u32(2);
+    let config1 = client.dns_generation_get(&gen1).await?.into_inner();
+    assert_eq!(config1.generation, gen1);
+    assert_eq!(config1.zones.len(), 1);
+    assert_eq!(
+        config1.zones[0].records.keys().collect::<Vec<_>>(),
+        vec!["devron"]
+    );
+
+    // The new name did not exist in generation 1, but does in generation 2.
+    let name = format!("unicorn.{}.", TEST_ZONE);
+    let result = client.dns_generation_query(&gen1, &name).await?.into_inner();
+    assert_eq!(result.zone.as_deref(), Some(TEST_ZONE));
+    assert!(result.records.is_empty());
+    let result = client.dns_generation_query(&gen2, &name).await?.into_inner();
+    assert_eq!(result.zone.as_deref(), Some(TEST_ZONE));
+    assert_eq!(result.records, vec![DnsRecord::Aaaa(addr2)]);
+
+    // We are not authoritative for names outside our zones.
+    let result = client
+        .dns_generation_query(&gen2, "unicorn.example.com.")
+        .await?
+        .into_inner();
+    assert_eq!(result.zone, None);
+    assert!(result.records.is_empty());
+
+    // Generations that we never had are reported as not found.
+    let error = client
+        .dns_generation_get(&Generation::from_u32(5))
+        .await
+        .expect_err("unexpectedly found generation 5");
+    assert_eq!(error.status(), Some(http::StatusCode::NOT_FOUND));
+
+    test_ctx.cleanup().await;
+    Ok(())
+}
+
 struct TestContext {
     client: Client,
     resolver: TokioAsyncResolver,
@@ -454,6 +572,7 @@ async fn init_client_server(
     // launch a dns server
     let dns_server_config = dns_server::dns_server::Config {
         bind_address: "[::1]:0".parse().unwrap(),
+        query_log: Default::default(),
     };
     let (dns_server, dropshot_server) = dns_server::start_servers(
         log.clone(),
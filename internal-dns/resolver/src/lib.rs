@@ -5,5 +5,7 @@
 //! A resolver for internal DNS names (see RFD 248).
 
 mod resolver;
+mod watcher;
 
 pub use resolver::*;
+pub use watcher::*;
@@ -6,13 +6,18 @@ use hickory_resolver::TokioAsyncResolver;
 use hickory_resolver::config::{
     LookupIpStrategy, NameServerConfig, Protocol, ResolverConfig, ResolverOpts,
 };
+use hickory_resolver::error::ResolveErrorKind;
 use hickory_resolver::lookup::SrvLookup;
 use internal_dns_types::names::ServiceName;
 use omicron_common::address::{
     AZ_PREFIX, DNS_PORT, Ipv6Subnet, get_internal_dns_server_addresses,
 };
-use slog::{debug, error, info, trace};
+use slog::{debug, error, info, o, trace};
+use std::collections::BTreeSet;
 use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::time::{Duration, Instant};
+
+use crate::watcher::{ServiceWatcher, WatchConfig, WatchedQorbResolver};
 
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum ResolveError {
@@ -64,6 +69,49 @@ impl QorbResolver {
             config,
         ))
     }
+
+    /// Returns a qorb resolver for `service` that is driven by a
+    /// [`ServiceWatcher`] rather than qorb's own DNS resolver.
+    ///
+    /// Unlike [`QorbResolver::for_service`], which resolves the service once
+    /// and never again, the returned resolver re-resolves the service as the
+    /// TTLs of our DNS records expire and notifies the pool whenever the set of
+    /// backends changes.  This lets pools pick up new or expunged backends
+    /// shortly after a new DNS generation is deployed.
+    pub fn for_service_watched(
+        &self,
+        log: &slog::Logger,
+        service: ServiceName,
+        config: WatchConfig,
+    ) -> Result<qorb::resolver::BoxedResolver, ResolveError> {
+        let resolver = Resolver::new_from_addrs_with_cache_mode(
+            log.new(o!("component" => "QorbResolver")),
+            &self.bootstrap_dns_ips,
+            CacheMode::TtlAware,
+        )?;
+        Ok(Box::new(WatchedQorbResolver::new(
+            resolver.watch_service(service, config),
+        )))
+    }
+}
+
+/// Describes how a [`Resolver`] caches the responses it receives
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CacheMode {
+    /// Cache positive responses according to their TTL, but cache negative
+    /// responses for at most 15 seconds regardless of what the server says
+    ///
+    /// Callers that need to observe changes sooner than that must use
+    /// [`Resolver::clear_cache`].
+    #[default]
+    Default,
+
+    /// Cache both positive and negative responses for exactly as long as the
+    /// DNS server says they're valid
+    ///
+    /// Negative responses that don't carry a TTL (because the server did not
+    /// include an SOA record) are not cached at all.
+    TtlAware,
 }
 
 /// A wrapper around a DNS resolver, providing a way to conveniently
@@ -74,6 +122,14 @@ pub struct Resolver {
     resolver: TokioAsyncResolver,
 }
 
+/// The result of resolving the full set of targets for a service
+#[derive(Clone, Debug)]
+pub(crate) struct ServiceTargetsLookup {
+    pub targets: BTreeSet<SocketAddrV6>,
+    /// When this result should be considered stale
+    pub valid_until: Instant,
+}
+
 type BoxError = Box<dyn std::error::Error + Send + Sync>;
 
 // By implementing this trait, [Resolver] can be used as an argument to
@@ -108,7 +164,22 @@ impl Resolver {
         log: slog::Logger,
         dns_addrs: &[SocketAddr],
     ) -> Result<Self, ResolveError> {
-        info!(log, "new DNS resolver"; "addresses" => ?dns_addrs);
+        Self::new_from_addrs_with_cache_mode(log, dns_addrs, CacheMode::Default)
+    }
+
+    /// Construct a new DNS resolver from specific DNS server addresses, using
+    /// the given [`CacheMode`].
+    pub fn new_from_addrs_with_cache_mode(
+        log: slog::Logger,
+        dns_addrs: &[SocketAddr],
+        cache_mode: CacheMode,
+    ) -> Result<Self, ResolveError> {
+        info!(
+            log,
+            "new DNS resolver";
+            "addresses" => ?dns_addrs,
+            "cache_mode" => ?cache_mode,
+        );
 
         let mut rc = ResolverConfig::new();
         let dns_server_count = dns_addrs.len();
@@ -129,7 +200,17 @@ impl Resolver {
         // The underlay is IPv6 only, so this helps avoid needless lookups of
         // the IPv4 variant.
         opts.ip_strategy = LookupIpStrategy::Ipv6Only;
-        opts.negative_max_ttl = Some(std::time::Duration::from_secs(15));
+        match cache_mode {
+            CacheMode::Default => {
+                opts.negative_max_ttl = Some(Duration::from_secs(15));
+            }
+            CacheMode::TtlAware => {
+                opts.positive_min_ttl = Some(Duration::ZERO);
+                opts.positive_max_ttl = None;
+                opts.negative_min_ttl = Some(Duration::ZERO);
+                opts.negative_max_ttl = None;
+            }
+        }
         let resolver = TokioAsyncResolver::tokio(rc, opts);
 
         Ok(Self { log, resolver })
@@ -194,6 +275,76 @@ impl Resolver {
         self.resolver.clear_cache();
     }
 
+    /// Returns a [`ServiceWatcher`] that keeps track of the set of targets
+    /// for `service`, re-resolving it as the DNS records expire.
+    ///
+    /// Watchers work best with resolvers using [`CacheMode::TtlAware`].  With
+    /// the default mode, a watcher may not notice that a service has come into
+    /// existence until a cached negative response expires.
+    pub fn watch_service(
+        &self,
+        service: ServiceName,
+        config: WatchConfig,
+    ) -> ServiceWatcher {
+        let log = self.log.new(o!(
+            "component" => "ServiceWatcher",
+            "service" => service.srv_name(),
+        ));
+        ServiceWatcher::new(log, self.clone(), service, config)
+    }
+
+    /// Returns the full set of targets for `service`, along with the time at
+    /// which that set should be considered stale
+    ///
+    /// Unlike the other lookup functions, this treats a service with no
+    /// records as a successful lookup with no targets.  It also fails if any
+    /// of the SRV records' targets can't be resolved, rather than returning
+    /// whichever targets could be, so that callers don't mistake a transient
+    /// lookup failure for a backend going away.  The result is stale as soon
+    /// as any of the SRV or AAAA records it was built from expires.
+    pub(crate) async fn lookup_service_targets_with_expiry(
+        &self,
+        service: ServiceName,
+    ) -> Result<ServiceTargetsLookup, ResolveError> {
+        let name = service.srv_name();
+        trace!(self.log, "lookup_service_targets_with_expiry";
+            "dns_name" => &name);
+        let response = match self.resolver.srv_lookup(&name).await {
+            Ok(response) => response,
+            Err(error) => match error.kind() {
+                ResolveErrorKind::NoRecordsFound { negative_ttl, .. } => {
+                    let ttl = Duration::from_secs(u64::from(
+                        negative_ttl.unwrap_or(0),
+                    ));
+                    return Ok(ServiceTargetsLookup {
+                        targets: BTreeSet::new(),
+                        valid_until: Instant::now() + ttl,
+                    });
+                }
+                _ => return Err(error.into()),
+            },
+        };
+
+        let mut valid_until = response.as_lookup().valid_until();
+        let lookups = response.iter().map(|srv| {
+            let target = srv.target().clone();
+            let port = srv.port();
+            async move {
+                self.resolver.ipv6_lookup(target).await.map(|ips| (ips, port))
+            }
+        });
+        let mut targets = BTreeSet::new();
+        for result in futures::future::join_all(lookups).await {
+            let (ips, port) = result?;
+            valid_until = valid_until.min(ips.as_lookup().valid_until());
+            targets.extend(
+                ips.into_iter()
+                    .map(|aaaa| SocketAddrV6::new(aaaa.into(), port, 0, 0)),
+            );
+        }
+        Ok(ServiceTargetsLookup { targets, valid_until })
+    }
+
     /// Returns the targets of the SRV records for a DNS name
     ///
     /// The returned values are generally other DNS names that themselves would
@@ -410,8 +561,10 @@ impl Resolver {
 
 #[cfg(test)]
 mod test {
+    use super::CacheMode;
     use super::ResolveError;
     use super::Resolver;
+    use crate::{ServiceTargets, WatchConfig};
     use anyhow::Context;
     use assert_matches::assert_matches;
     use dropshot::{
@@ -420,17 +573,21 @@ mod test {
     };
     use internal_dns_types::config::DnsConfigBuilder;
     use internal_dns_types::config::DnsConfigParams;
+    use internal_dns_types::config::DnsRecord;
     use internal_dns_types::names::DNS_ZONE;
     use internal_dns_types::names::ServiceName;
     use omicron_test_utils::dev::test_setup_log;
     use omicron_uuid_kinds::OmicronZoneUuid;
     use slog::{Logger, o};
+    use std::collections::BTreeSet;
     use std::collections::HashMap;
     use std::net::Ipv6Addr;
     use std::net::SocketAddr;
     use std::net::SocketAddrV6;
     use std::str::FromStr;
+    use std::time::Duration;
     use tempfile::TempDir;
+    use tokio::sync::watch;
 
     struct DnsServer {
         // We hang onto the storage_path even though it's never used because
@@ -470,6 +627,7 @@ mod test {
                 store,
                 &dns_server::dns_server::Config {
                     bind_address: "[::1]:0".parse().unwrap(),
+                    query_log: Default::default(),
                 },
                 &dropshot::ConfigDropshot {
                     bind_address: "[::1]:0".parse().unwrap(),
@@ -765,6 +923,177 @@ mod test {
         logctx.cleanup_successful();
     }
 
+    // A ServiceWatcher reports the service's targets as they come and go.
+    #[tokio::test]
+    async fn watch_service_observes_changes() {
+        let logctx = test_setup_log("watch_service_observes_changes");
+        let dns_server = DnsServer::create(&logctx.log).await;
+        let resolver = Resolver::new_from_addrs_with_cache_mode(
+            logctx.log.new(o!("component" => "DnsResolver")),
+            &[dns_server.dns_server_address()],
+            CacheMode::TtlAware,
+        )
+        .unwrap();
+        let config = WatchConfig {
+            min_refresh: Duration::from_millis(50),
+            max_refresh: Duration::from_millis(50),
+            error_retry: Duration::from_millis(50),
+        };
+        let watcher = resolver.watch_service(ServiceName::Cockroach, config);
+        let rx = watcher.subscribe();
+        let wait_for =
+            |rx: &watch::Receiver<ServiceTargets>,
+             expected: BTreeSet<SocketAddrV6>| {
+                let mut rx = rx.clone();
+                async move {
+                    tokio::time::timeout(
+                        Duration::from_secs(30),
+                        rx.wait_for(|targets| **targets == expected),
+                    )
+                    .await
+                    .expect("timed out waiting for targets")
+                    .expect("watcher went away");
+                }
+            };
+
+        // Initially, there are no records and so no targets.
+        assert!(watcher.current().is_empty());
+
+        let config_for = |addrs: &[SocketAddrV6]| {
+            let mut dns_builder = DnsConfigBuilder::new();
+            for addr in addrs {
+                let zone = dns_builder
+                    .host_zone(OmicronZoneUuid::new_v4(), *addr.ip())
+                    .unwrap();
+                dns_builder
+                    .service_backend_zone(
+                        ServiceName::Cockroach,
+                        &zone,
+                        addr.port(),
+                    )
+                    .unwrap();
+            }
+            dns_builder.build_full_config_for_initial_generation()
+        };
+
+        // Add a backend.
+        let addr1 = SocketAddrV6::new(
+            Ipv6Addr::from_str("ff::01").unwrap(),
+            1111,
+            0,
+            0,
+        );
+        let mut dns_config = config_for(&[addr1]);
+        dns_server.update(&dns_config).await.unwrap();
+        wait_for(&rx, BTreeSet::from([addr1])).await;
+
+        // Add a second backend.
+        let addr2 = SocketAddrV6::new(
+            Ipv6Addr::from_str("ff::02").unwrap(),
+            2222,
+            0,
+            0,
+        );
+        let generation = dns_config.generation.next();
+        dns_config = config_for(&[addr1, addr2]);
+        dns_config.generation = generation;
+        dns_server.update(&dns_config).await.unwrap();
+        wait_for(&rx, BTreeSet::from([addr1, addr2])).await;
+
+        // Remove all records.  The watcher should report no targets.
+        dns_config.generation = dns_config.generation.next();
+        dns_config.zones[0].records = HashMap::new();
+        dns_server.update(&dns_config).await.unwrap();
+        wait_for(&rx, BTreeSet::new()).await;
+
+        drop(watcher);
+        dns_server.cleanup_successful();
+        logctx.cleanup_successful();
+    }
+
+    // A ServiceWatcher keeps its previous targets if one of the service's
+    // targets can't be resolved, rather than dropping that target.
+    #[tokio::test]
+    async fn watch_service_keeps_targets_on_partial_failure() {
+        let logctx =
+            test_setup_log("watch_service_keeps_targets_on_partial_failure");
+        let dns_server = DnsServer::create(&logctx.log).await;
+        let resolver = Resolver::new_from_addrs_with_cache_mode(
+            logctx.log.new(o!("component" => "DnsResolver")),
+            &[dns_server.dns_server_address()],
+            CacheMode::TtlAware,
+        )
+        .unwrap();
+
+        let addrs = [
+            SocketAddrV6::new(
+                Ipv6Addr::from_str("ff::01").unwrap(),
+                1111,
+                0,
+                0,
+            ),
+            SocketAddrV6::new(
+                Ipv6Addr::from_str("ff::02").unwrap(),
+                2222,
+                0,
+                0,
+            ),
+        ];
+        let mut dns_builder = DnsConfigBuilder::new();
+        for addr in &addrs {
+            let zone = dns_builder
+                .host_zone(OmicronZoneUuid::new_v4(), *addr.ip())
+                .unwrap();
+            dns_builder
+                .service_backend_zone(
+                    ServiceName::Cockroach,
+                    &zone,
+                    addr.port(),
+                )
+                .unwrap();
+        }
+        let mut dns_config =
+            dns_builder.build_full_config_for_initial_generation();
+        dns_server.update(&dns_config).await.unwrap();
+
+        let config = WatchConfig {
+            min_refresh: Duration::from_millis(50),
+            max_refresh: Duration::from_millis(50),
+            error_retry: Duration::from_millis(50),
+        };
+        let watcher = resolver.watch_service(ServiceName::Cockroach, config);
+        let mut rx = watcher.subscribe();
+        tokio::time::timeout(
+            Duration::from_secs(30),
+            rx.wait_for(|targets| **targets == BTreeSet::from(addrs)),
+        )
+        .await
+        .expect("timed out waiting for targets")
+        .expect("watcher went away");
+
+        // Remove the AAAA record for the second backend, leaving its SRV
+        // record in place.  The lookup as a whole now fails.
+        dns_config.generation = dns_config.generation.next();
+        dns_config.zones[0].records.retain(|_, records| {
+            !records.contains(&DnsRecord::Aaaa(*addrs[1].ip()))
+        });
+        dns_server.update(&dns_config).await.unwrap();
+        resolver.clear_cache();
+        resolver
+            .lookup_service_targets_with_expiry(ServiceName::Cockroach)
+            .await
+            .expect_err("lookup with an unresolvable target should fail");
+
+        // Give the watcher a chance to re-resolve a few times.  It should
+        // still report both backends.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(*watcher.current(), BTreeSet::from(addrs));
+
+        drop(watcher);
+        dns_server.cleanup_successful();
+        logctx.cleanup_successful();
+    }
+
     // What follows is a "test endpoint" to validate that the integration of
     // the DNS server, resolver, and progenitor all work together correctly.
 
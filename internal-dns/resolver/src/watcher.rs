@@ -0,0 +1,233 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Watching the set of targets for a service as it changes over time
+
+use crate::resolver::{Resolver, ServiceTargetsLookup};
+use internal_dns_types::names::ServiceName;
+use qorb::backend;
+use qorb::resolver::AllBackends;
+use slog::{debug, info, warn};
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{SocketAddr, SocketAddrV6};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// The set of targets for a service, as reported by a [`ServiceWatcher`]
+pub type ServiceTargets = Arc<BTreeSet<SocketAddrV6>>;
+
+/// Controls how often a [`ServiceWatcher`] re-resolves its service
+#[derive(Clone, Copy, Debug)]
+pub struct WatchConfig {
+    /// Minimum time between lookups
+    ///
+    /// Our DNS servers currently report a TTL of 0 for all records
+    /// (<https://github.com/oxidecomputer/omicron/issues/6790>), so without a
+    /// floor on the refresh interval, a watcher would query continuously.
+    pub min_refresh: Duration,
+    /// Maximum time between lookups, regardless of the TTL of the records
+    pub max_refresh: Duration,
+    /// Time to wait before retrying after a lookup fails
+    pub error_retry: Duration,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            min_refresh: Duration::from_secs(1),
+            max_refresh: Duration::from_secs(60),
+            error_retry: Duration::from_secs(5),
+        }
+    }
+}
+
+impl WatchConfig {
+    /// Returns how long to wait before re-resolving a result that's valid
+    /// until `valid_until`
+    fn refresh_after(&self, now: Instant, valid_until: Instant) -> Duration {
+        valid_until
+            .saturating_duration_since(now)
+            .clamp(self.min_refresh, self.max_refresh.max(self.min_refresh))
+    }
+}
+
+/// Keeps track of the set of targets for a service
+///
+/// A watcher runs a background task that re-resolves the service whenever the
+/// previous answer expires (subject to the bounds in [`WatchConfig`]).
+/// Consumers can [`subscribe`](ServiceWatcher::subscribe) to be notified only
+/// when the set of targets actually changes.
+///
+/// A service with no records is reported as an empty set of targets.  If a
+/// lookup fails for some other reason (e.g., none of the DNS servers could be
+/// reached, or one of the service's targets could not be resolved), the
+/// previous set of targets is left in place.
+///
+/// The background task is stopped when the watcher is dropped.
+pub struct ServiceWatcher {
+    rx: watch::Receiver<ServiceTargets>,
+    task: JoinHandle<()>,
+}
+
+impl ServiceWatcher {
+    pub(crate) fn new(
+        log: slog::Logger,
+        resolver: Resolver,
+        service: ServiceName,
+        config: WatchConfig,
+    ) -> Self {
+        let (tx, rx) = watch::channel(Arc::new(BTreeSet::new()));
+        let task =
+            tokio::spawn(watch_service(log, resolver, service, config, tx));
+        ServiceWatcher { rx, task }
+    }
+
+    /// Returns a receiver that's notified each time the set of targets
+    /// changes
+    pub fn subscribe(&self) -> watch::Receiver<ServiceTargets> {
+        self.rx.clone()
+    }
+
+    /// Returns the most recently resolved set of targets
+    pub fn current(&self) -> ServiceTargets {
+        self.rx.borrow().clone()
+    }
+}
+
+impl Drop for ServiceWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn watch_service(
+    log: slog::Logger,
+    resolver: Resolver,
+    service: ServiceName,
+    config: WatchConfig,
+    tx: watch::Sender<ServiceTargets>,
+) {
+    loop {
+        let wait =
+            match resolver.lookup_service_targets_with_expiry(service).await {
+                Ok(ServiceTargetsLookup { targets, valid_until }) => {
+                    tx.send_if_modified(|current| {
+                        if **current == targets {
+                            return false;
+                        }
+                        info!(
+                            log,
+                            "service targets changed";
+                            "old" => ?current,
+                            "new" => ?targets,
+                        );
+                        *current = Arc::new(targets);
+                        true
+                    });
+                    config.refresh_after(Instant::now(), valid_until)
+                }
+                Err(error) => {
+                    warn!(
+                        log,
+                        "failed to resolve service targets";
+                        "error" => %error,
+                    );
+                    config.error_retry
+                }
+            };
+
+        debug!(log, "waiting to re-resolve"; "wait" => ?wait);
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Implements [`qorb::resolver::Resolver`] on top of a [`ServiceWatcher`]
+///
+/// See [`crate::QorbResolver::for_service_watched`].
+pub(crate) struct WatchedQorbResolver {
+    tx: watch::Sender<AllBackends>,
+    task: JoinHandle<()>,
+    // Held only so that the watcher keeps running for as long as we do.
+    _watcher: ServiceWatcher,
+}
+
+impl WatchedQorbResolver {
+    pub(crate) fn new(watcher: ServiceWatcher) -> Self {
+        let mut rx = watcher.subscribe();
+        let (tx, _) = watch::channel(backends_for(&rx.borrow_and_update()));
+        let task = tokio::spawn({
+            let tx = tx.clone();
+            async move {
+                while rx.changed().await.is_ok() {
+                    let backends = backends_for(&rx.borrow_and_update());
+                    tx.send_replace(backends);
+                }
+            }
+        });
+        WatchedQorbResolver { tx, task, _watcher: watcher }
+    }
+}
+
+impl qorb::resolver::Resolver for WatchedQorbResolver {
+    fn monitor(&mut self) -> watch::Receiver<AllBackends> {
+        self.tx.subscribe()
+    }
+}
+
+impl Drop for WatchedQorbResolver {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn backends_for(targets: &BTreeSet<SocketAddrV6>) -> AllBackends {
+    Arc::new(
+        targets
+            .iter()
+            .map(|addr| {
+                (
+                    backend::Name::new(addr.to_string()),
+                    backend::Backend { address: SocketAddr::V6(*addr) },
+                )
+            })
+            .collect::<BTreeMap<_, _>>(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::WatchConfig;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_refresh_after() {
+        let config = WatchConfig {
+            min_refresh: Duration::from_secs(1),
+            max_refresh: Duration::from_secs(60),
+            error_retry: Duration::from_secs(5),
+        };
+        let now = Instant::now();
+
+        // Already-expired and zero-TTL results are bounded below.
+        assert_eq!(config.refresh_after(now, now), Duration::from_secs(1));
+        assert_eq!(
+            config.refresh_after(now + Duration::from_secs(5), now),
+            Duration::from_secs(1)
+        );
+
+        // TTLs within the bounds are honored exactly.
+        assert_eq!(
+            config.refresh_after(now, now + Duration::from_secs(30)),
+            Duration::from_secs(30)
+        );
+
+        // Long TTLs are bounded above.
+        assert_eq!(
+            config.refresh_after(now, now + Duration::from_secs(3600)),
+            Duration::from_secs(60)
+        );
+    }
+}
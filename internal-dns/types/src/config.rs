@@ -664,6 +664,10 @@ pub struct DnsConfig {
     pub zones: Vec<DnsConfigZone>,
 }
 
+/// Name under which a [`DnsConfigZone`] stores the records for the zone's apex
+/// (i.e., for the zone name itself)
+pub const ZONE_APEX_NAME: &str = "@";
+
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 pub struct DnsConfigZone {
     pub zone_name: String,
@@ -736,6 +740,48 @@ pub struct Srv {
     pub target: String,
 }
 
+/// Summary of one generation of DNS data retained by a DNS server
+///
+/// DNS servers keep a few generations older than the current one for
+/// debugging.  See [`DnsGenerationConfig`].
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct DnsGenerationSummary {
+    pub generation: Generation,
+    /// whether this is the generation that the server is currently serving
+    pub current: bool,
+    /// names of the DNS zones present in this generation
+    pub zones: Vec<String>,
+}
+
+/// The full contents of one generation of DNS data retained by a DNS server
+///
+/// Unlike [`DnsConfig`], this does not include any timestamps because DNS
+/// servers only keep those for the generation that they're currently serving.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct DnsGenerationConfig {
+    pub generation: Generation,
+    pub zones: Vec<DnsConfigZone>,
+}
+
+/// The result of evaluating a DNS query against a specific generation of DNS
+/// data retained by a DNS server
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct DnsGenerationQueryResult {
+    pub generation: Generation,
+    /// the name that was queried
+    pub name: String,
+    /// the zone containing the queried name
+    ///
+    /// If this is `None`, the server was not authoritative for the name in
+    /// this generation and would have responded with SERVFAIL.
+    pub zone: Option<String>,
+    /// all records associated with the queried name, regardless of type
+    ///
+    /// If `zone` is present and this is empty, the server would have responded
+    /// with NXDOMAIN.
+    pub records: Vec<DnsRecord>,
+}
+
 #[cfg(test)]
 mod test {
     use super::{DnsConfigBuilder, Host, ServiceName};
@@ -95,6 +95,7 @@ pub async fn blueprint_edit_current_target(
         .blueprint_target_set(&BlueprintTargetSet {
             enabled: true,
             target_id: blueprint2.id,
+            actor: None,
         })
         .await
         .expect("setting new target");
@@ -42,14 +42,16 @@ async fn test_nexus_add_remove(lc: &LiveTestContext) {
     let log = lc.log();
     let opctx = lc.opctx();
     let datastore = lc.datastore();
-    let planning_input = PlanningInputFromDb::assemble(&opctx, &datastore)
-        .await
-        .expect("planning input");
     let collection = datastore
         .inventory_get_latest_collection(opctx)
         .await
-        .expect("latest inventory collection")
-        .unwrap_or_else(|| CollectionBuilder::new("test").build());
+        .expect("latest inventory collection");
+    let planning_input =
+        PlanningInputFromDb::assemble(&opctx, &datastore, collection.as_ref())
+            .await
+            .expect("planning input");
+    let collection =
+        collection.unwrap_or_else(|| CollectionBuilder::new("test").build());
     let initial_nexus_clients = lc.all_internal_nexus_clients().await.unwrap();
     let nexus = initial_nexus_clients.first().expect("internal Nexus client");
 
@@ -200,9 +202,13 @@ async fn test_nexus_add_remove(lc: &LiveTestContext) {
 
     // Now run through the planner.
     info!(log, "running through planner");
-    let planning_input = PlanningInputFromDb::assemble(&opctx, &datastore)
-        .await
-        .expect("planning input");
+    let planning_input = PlanningInputFromDb::assemble(
+        &opctx,
+        &datastore,
+        Some(&latest_collection),
+    )
+    .await
+    .expect("planning input");
     let (_, parent_blueprint) = datastore
         .blueprint_target_get_current_full(opctx)
         .await
@@ -240,6 +246,7 @@ async fn test_nexus_add_remove(lc: &LiveTestContext) {
         .blueprint_target_set(&BlueprintTargetSet {
             enabled: true,
             target_id: new_blueprint.id,
+            actor: None,
         })
         .await
         .expect("setting target blueprint");
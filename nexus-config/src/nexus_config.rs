@@ -272,6 +272,8 @@ pub struct MgdConfig {
 struct UnvalidatedTunables {
     max_vpc_ipv4_subnet_prefix: u8,
     load_timeout: Option<std::time::Duration>,
+    #[serde(default)]
+    image_import_allow_internal_urls: bool,
 }
 
 /// Tunable configuration parameters, intended for use in test environments or
@@ -289,6 +291,13 @@ pub struct Tunables {
     ///
     /// If "None", nexus loops forever during initialization.
     pub load_timeout: Option<std::time::Duration>,
+
+    /// Whether images may be imported from URLs that resolve to loopback,
+    /// private, link-local, or other non-global addresses.
+    ///
+    /// This should only be set in test environments, where image files are
+    /// served locally.
+    pub image_import_allow_internal_urls: bool,
 }
 
 // Convert from the unvalidated tunables, verifying each parameter as needed.
@@ -300,6 +309,8 @@ impl TryFrom<UnvalidatedTunables> for Tunables {
         Ok(Tunables {
             max_vpc_ipv4_subnet_prefix: unvalidated.max_vpc_ipv4_subnet_prefix,
             load_timeout: unvalidated.load_timeout,
+            image_import_allow_internal_urls: unvalidated
+                .image_import_allow_internal_urls,
         })
     }
 }
@@ -351,6 +362,7 @@ impl Default for Tunables {
         Tunables {
             max_vpc_ipv4_subnet_prefix: MAX_VPC_IPV4_SUBNET_PREFIX,
             load_timeout: None,
+            image_import_allow_internal_urls: false,
         }
     }
 }
@@ -421,6 +433,10 @@ pub struct BackgroundTaskConfig {
     /// configuration for read-only region replacement start task
     pub read_only_region_replacement_start:
         ReadOnlyRegionReplacementStartConfig,
+    /// configuration for scheduled snapshot policy task
+    pub snapshot_policy: SnapshotPolicyConfig,
+    /// configuration for resumable TUF repository upload pruning task
+    pub tuf_repo_upload_pruner: TufRepoUploadPrunerConfig,
 }
 
 #[serde_as]
@@ -745,6 +761,25 @@ pub struct ReadOnlyRegionReplacementStartConfig {
     pub period_secs: Duration,
 }
 
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SnapshotPolicyConfig {
+    /// period (in seconds) for periodic activations of this background task
+    ///
+    /// This bounds how late a scheduled snapshot may be taken, so it should be
+    /// much shorter than the shortest snapshot policy interval.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub period_secs: Duration,
+}
+
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TufRepoUploadPrunerConfig {
+    /// period (in seconds) for periodic activations of this background task
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub period_secs: Duration,
+}
+
 /// Configuration for a nexus server
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct PackageConfig {
@@ -1005,6 +1040,8 @@ mod test {
             tuf_artifact_replication.period_secs = 300
             tuf_artifact_replication.min_sled_replication = 3
             read_only_region_replacement_start.period_secs = 30
+            snapshot_policy.period_secs = 60
+            tuf_repo_upload_pruner.period_secs = 300
             [default_region_allocation_strategy]
             type = "random"
             seed = 0
@@ -1074,7 +1111,8 @@ mod test {
                     schema: None,
                     tunables: Tunables {
                         max_vpc_ipv4_subnet_prefix: 27,
-                        load_timeout: None
+                        load_timeout: None,
+                        image_import_allow_internal_urls: false,
                     },
                     dendrite: HashMap::from([(
                         SwitchLocation::Switch0,
@@ -1210,6 +1248,12 @@ mod test {
                             ReadOnlyRegionReplacementStartConfig {
                                 period_secs: Duration::from_secs(30),
                             },
+                        snapshot_policy: SnapshotPolicyConfig {
+                            period_secs: Duration::from_secs(60),
+                        },
+                        tuf_repo_upload_pruner: TufRepoUploadPrunerConfig {
+                            period_secs: Duration::from_secs(300),
+                        },
                     },
                     default_region_allocation_strategy:
                         crate::nexus_config::RegionAllocationStrategy::Random {
@@ -1296,6 +1340,8 @@ mod test {
             tuf_artifact_replication.period_secs = 300
             tuf_artifact_replication.min_sled_replication = 3
             read_only_region_replacement_start.period_secs = 30
+            snapshot_policy.period_secs = 60
+            tuf_repo_upload_pruner.period_secs = 300
             [default_region_allocation_strategy]
             type = "random"
             "##,
@@ -498,7 +498,8 @@ impl OmicronZoneType {
 /// 1. [`Self::zone_prefix`]: Used to construct zone names.
 /// 2. [`Self::service_prefix`]: Used to construct SMF service names.
 /// 3. [`Self::name_prefix`]: Used to construct `Name` instances.
-/// 4. [`Self::report_str`]: Used for reporting and testing.
+/// 4. [`Self::report_str`]: Used for reporting and testing. This is also the
+///    serde representation.
 ///
 /// There is no `Display` impl to ensure that users explicitly choose the
 /// representation they want. (Please play close attention to this! The
@@ -512,8 +513,19 @@ impl OmicronZoneType {
 /// please add it here rather than doing something ad-hoc in the calling code
 /// so it's more legible.
 #[derive(
-    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, EnumIter,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    EnumIter,
+    Deserialize,
+    Serialize,
 )]
+#[serde(rename_all = "snake_case")]
 pub enum ZoneKind {
     BoundaryNtp,
     Clickhouse,
@@ -617,6 +629,26 @@ impl ZoneKind {
             ZoneKind::Oximeter => "oximeter",
         }
     }
+
+    /// Return the name of the zone artifact in a TUF repo that provides the
+    /// image for this kind of zone. This matches the package name in
+    /// `package-manifest.toml` and is guaranteed to be stable over time.
+    pub fn artifact_name(self) -> &'static str {
+        match self {
+            // BoundaryNtp and InternalNtp both use "ntp".
+            ZoneKind::BoundaryNtp | ZoneKind::InternalNtp => Self::NTP_PREFIX,
+            ZoneKind::Clickhouse => "clickhouse",
+            ZoneKind::ClickhouseKeeper => "clickhouse_keeper",
+            ZoneKind::ClickhouseServer => "clickhouse_server",
+            ZoneKind::CockroachDb => "cockroachdb",
+            ZoneKind::Crucible => "crucible-zone",
+            ZoneKind::CruciblePantry => "crucible-pantry-zone",
+            ZoneKind::ExternalDns => "external-dns",
+            ZoneKind::InternalDns => "internal-dns",
+            ZoneKind::Nexus => "nexus",
+            ZoneKind::Oximeter => "oximeter",
+        }
+    }
 }
 
 /// Where Sled Agent should get the image for a zone.
@@ -691,4 +723,17 @@ mod tests {
             });
         }
     }
+
+    #[test]
+    fn test_serde_matches_report_str() {
+        for zone_kind in ZoneKind::iter() {
+            let serialized = serde_json::to_value(zone_kind).unwrap();
+            assert_eq!(
+                serialized,
+                serde_json::Value::from(zone_kind.report_str()),
+                "serde representation of {zone_kind:?} should match \
+                 report_str()",
+            );
+        }
+    }
 }
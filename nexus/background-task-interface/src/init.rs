@@ -44,6 +44,8 @@ pub struct BackgroundTasks {
     pub task_region_snapshot_replacement_finish: Activator,
     pub task_tuf_artifact_replication: Activator,
     pub task_read_only_region_replacement_start: Activator,
+    pub task_snapshot_policy: Activator,
+    pub task_tuf_repo_upload_pruner: Activator,
 
     // Handles to activate background tasks that do not get used by Nexus
     // at-large.  These background tasks are implementation details as far as
@@ -21,13 +21,15 @@ use nexus_db_schema::schema::{
     bp_clickhouse_keeper_zone_id_to_node_id,
     bp_clickhouse_server_zone_id_to_node_id, bp_omicron_dataset,
     bp_omicron_physical_disk, bp_omicron_zone, bp_omicron_zone_nic,
-    bp_oximeter_read_policy, bp_sled_metadata, bp_target,
+    bp_oximeter_read_policy, bp_sled_metadata, bp_target, bp_target_approval,
+    bp_target_audit,
 };
 use nexus_sled_agent_shared::inventory::OmicronZoneDataset;
 use nexus_types::deployment::BlueprintDatasetDisposition;
 use nexus_types::deployment::BlueprintPhysicalDiskConfig;
 use nexus_types::deployment::BlueprintPhysicalDiskDisposition;
 use nexus_types::deployment::BlueprintTarget;
+use nexus_types::deployment::BlueprintTargetAuditAction;
 use nexus_types::deployment::BlueprintZoneConfig;
 use nexus_types::deployment::BlueprintZoneDisposition;
 use nexus_types::deployment::BlueprintZoneType;
@@ -65,6 +67,10 @@ pub struct Blueprint {
     pub time_created: DateTime<Utc>,
     pub creator: String,
     pub comment: String,
+    // A JSON-serialized `nexus_types::deployment::PlanningReport`. The report
+    // is informational only, so we store it as an opaque blob rather than
+    // normalizing it into its own tables.
+    pub planning_report: Option<serde_json::Value>,
 }
 
 impl From<&'_ nexus_types::deployment::Blueprint> for Blueprint {
@@ -81,6 +87,12 @@ impl From<&'_ nexus_types::deployment::Blueprint> for Blueprint {
             time_created: bp.time_created,
             creator: bp.creator.clone(),
             comment: bp.comment.clone(),
+            planning_report: bp.planning_report.as_ref().map(|report| {
+                serde_json::to_value(report).expect(
+                    "serializing a PlanningReport can't fail: it contains \
+                     only strings, unit enums, and UUIDs",
+                )
+            }),
         }
     }
 }
@@ -136,6 +148,121 @@ impl From<BpTarget> for nexus_types::deployment::BlueprintTarget {
     }
 }
 
+/// See [`nexus_types::deployment::BlueprintApproval`].
+#[derive(Queryable, Clone, Debug, Selectable, Insertable)]
+#[diesel(table_name = bp_target_approval)]
+pub struct BpTargetApproval {
+    pub blueprint_id: DbTypedUuid<BlueprintKind>,
+    pub proposed_by: String,
+    pub time_proposed: DateTime<Utc>,
+    pub required_approver: String,
+    pub approved_by: Option<String>,
+    pub time_approved: Option<DateTime<Utc>>,
+}
+
+impl From<nexus_types::deployment::BlueprintApproval> for BpTargetApproval {
+    fn from(value: nexus_types::deployment::BlueprintApproval) -> Self {
+        Self {
+            blueprint_id: value.blueprint_id.into(),
+            proposed_by: value.proposed_by,
+            time_proposed: value.time_proposed,
+            required_approver: value.required_approver,
+            approved_by: value.approved_by,
+            time_approved: value.time_approved,
+        }
+    }
+}
+
+impl From<BpTargetApproval> for nexus_types::deployment::BlueprintApproval {
+    fn from(value: BpTargetApproval) -> Self {
+        Self {
+            blueprint_id: value.blueprint_id.into(),
+            proposed_by: value.proposed_by,
+            time_proposed: value.time_proposed,
+            required_approver: value.required_approver,
+            approved_by: value.approved_by,
+            time_approved: value.time_approved,
+        }
+    }
+}
+
+impl_enum_type!(
+    BpTargetAuditActionEnum:
+
+    /// See [`BlueprintTargetAuditAction`].
+    #[derive(Clone, Copy, Debug, AsExpression, FromSqlRow, PartialEq)]
+    pub enum DbBpTargetAuditAction;
+
+    // Enum values
+    Proposed => b"proposed"
+    Approved => b"approved"
+    MadeTarget => b"made_target"
+    SetEnabled => b"set_enabled"
+);
+
+impl From<BlueprintTargetAuditAction> for DbBpTargetAuditAction {
+    fn from(value: BlueprintTargetAuditAction) -> Self {
+        match value {
+            BlueprintTargetAuditAction::Proposed => Self::Proposed,
+            BlueprintTargetAuditAction::Approved => Self::Approved,
+            BlueprintTargetAuditAction::MadeTarget => Self::MadeTarget,
+            BlueprintTargetAuditAction::SetEnabled => Self::SetEnabled,
+        }
+    }
+}
+
+impl From<DbBpTargetAuditAction> for BlueprintTargetAuditAction {
+    fn from(value: DbBpTargetAuditAction) -> Self {
+        match value {
+            DbBpTargetAuditAction::Proposed => Self::Proposed,
+            DbBpTargetAuditAction::Approved => Self::Approved,
+            DbBpTargetAuditAction::MadeTarget => Self::MadeTarget,
+            DbBpTargetAuditAction::SetEnabled => Self::SetEnabled,
+        }
+    }
+}
+
+/// See [`nexus_types::deployment::BlueprintTargetAuditEntry`].
+#[derive(Queryable, Clone, Debug, Selectable, Insertable)]
+#[diesel(table_name = bp_target_audit)]
+pub struct BpTargetAudit {
+    pub id: Uuid,
+    pub time_recorded: DateTime<Utc>,
+    pub blueprint_id: DbTypedUuid<BlueprintKind>,
+    pub action: DbBpTargetAuditAction,
+    pub actor: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+impl BpTargetAudit {
+    pub fn new(
+        entry: nexus_types::deployment::BlueprintTargetAuditEntry,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            time_recorded: entry.time,
+            blueprint_id: entry.blueprint_id.into(),
+            action: entry.action.into(),
+            actor: entry.actor,
+            enabled: entry.enabled,
+        }
+    }
+}
+
+impl From<BpTargetAudit>
+    for nexus_types::deployment::BlueprintTargetAuditEntry
+{
+    fn from(value: BpTargetAudit) -> Self {
+        Self {
+            time: value.time_recorded,
+            blueprint_id: value.blueprint_id.into(),
+            action: value.action.into(),
+            actor: value.actor,
+            enabled: value.enabled,
+        }
+    }
+}
+
 /// See [`nexus_types::deployment::BlueprintSledConfig::state`].
 #[derive(Queryable, Clone, Debug, Selectable, Insertable)]
 #[diesel(table_name = bp_sled_metadata)]
@@ -0,0 +1,129 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Database representation of instance templates
+
+use super::{ByteCount, InstanceAutoRestartPolicy, InstanceCpuCount};
+use db_macros::Resource;
+use nexus_db_schema::schema::instance_template;
+use nexus_types::external_api::params;
+use nexus_types::external_api::views;
+use nexus_types::identity::Resource;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::NameOrId;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A description of instances to create, shared by every instance created
+/// from it
+#[derive(
+    Queryable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    Resource,
+    Serialize,
+    Deserialize,
+)]
+#[diesel(table_name = instance_template)]
+pub struct InstanceTemplate {
+    #[diesel(embed)]
+    pub identity: InstanceTemplateIdentity,
+
+    pub project_id: Uuid,
+
+    pub ncpus: InstanceCpuCount,
+    pub memory: ByteCount,
+    pub user_data: Vec<u8>,
+    pub auto_restart_policy: Option<InstanceAutoRestartPolicy>,
+
+    // The template's network interfaces, external IPs, boot disk and SSH keys
+    // are stored as a single JSON object; consumers use devices() to get them
+    // back out.
+    devices: serde_json::Value,
+}
+
+/// Form of an instance template's devices used when serializing to the
+/// database
+//
+// Network interfaces, external IPs and boot disks are each described by a
+// tagged enum (or a list of them) in the API, and are only ever read back to
+// be handed to instance creation as a whole. Storing them normalized would
+// mean several tables that mirror `InstanceCreate` for no benefit, so they're
+// stored as JSON in the template's row instead.
+//
+// WARNING: this is the serialized form of API types, so changes to
+// `InstanceNetworkInterfaceAttachment`, `ExternalIpCreate`,
+// `InstanceTemplateBootDisk` or `NameOrId` that are not
+// backwards-compatible with their serde representation require a schema
+// migration that rewrites existing templates.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InstanceTemplateDevices {
+    pub network_interfaces: params::InstanceNetworkInterfaceAttachment,
+    pub external_ips: Vec<params::ExternalIpCreate>,
+    pub boot_disk: Option<params::InstanceTemplateBootDisk>,
+    pub ssh_public_keys: Option<Vec<NameOrId>>,
+}
+
+impl InstanceTemplate {
+    pub fn new(
+        project_id: Uuid,
+        params: params::InstanceTemplateCreate,
+    ) -> Result<Self, Error> {
+        let devices = serde_json::to_value(&InstanceTemplateDevices {
+            network_interfaces: params.network_interfaces,
+            external_ips: params.external_ips,
+            boot_disk: params.boot_disk,
+            ssh_public_keys: params.ssh_public_keys,
+        })
+        .map_err(|e| {
+            Error::internal_error(&format!(
+                "failed to serialize instance template devices: {:#}",
+                e
+            ))
+        })?;
+        Ok(Self {
+            identity: InstanceTemplateIdentity::new(
+                Uuid::new_v4(),
+                params.identity,
+            ),
+            project_id,
+            ncpus: params.ncpus.into(),
+            memory: params.memory.into(),
+            user_data: params.user_data,
+            auto_restart_policy: params.auto_restart_policy.map(Into::into),
+            devices,
+        })
+    }
+
+    pub fn devices(&self) -> Result<InstanceTemplateDevices, Error> {
+        serde_json::from_value(self.devices.clone()).map_err(|e| {
+            Error::internal_error(&format!(
+                "failed to deserialize instance template devices from \
+                 database: {:#}",
+                e
+            ))
+        })
+    }
+}
+
+impl TryFrom<InstanceTemplate> for views::InstanceTemplate {
+    type Error = Error;
+    fn try_from(template: InstanceTemplate) -> Result<Self, Self::Error> {
+        let devices = template.devices()?;
+        Ok(Self {
+            identity: template.identity(),
+            project_id: template.project_id,
+            ncpus: template.ncpus.into(),
+            memory: template.memory.into(),
+            user_data: template.user_data,
+            network_interfaces: devices.network_interfaces,
+            external_ips: devices.external_ips,
+            boot_disk: devices.boot_disk,
+            ssh_public_keys: devices.ssh_public_keys,
+            auto_restart_policy: template.auto_restart_policy.map(Into::into),
+        })
+    }
+}
@@ -39,6 +39,7 @@ mod instance;
 mod instance_auto_restart_policy;
 mod instance_cpu_count;
 mod instance_state;
+mod instance_template;
 mod internet_gateway;
 mod inventory;
 mod ip_pool;
@@ -97,10 +98,14 @@ mod sled_resource_vmm;
 mod sled_state;
 mod sled_underlay_subnet_allocation;
 mod snapshot;
+mod snapshot_export;
+mod snapshot_group;
+mod snapshot_policy;
 mod ssh_key;
 mod support_bundle;
 mod switch;
 mod tuf_repo;
+mod tuf_repo_upload;
 mod typed_uuid;
 mod unsigned;
 mod upstairs_repair;
@@ -118,6 +123,7 @@ mod vpc_firewall_rule;
 mod vpc_route;
 mod vpc_router;
 mod vpc_subnet;
+mod zone_placement_policy;
 mod zpool;
 
 // This module namespacing is a quirk to allow `db-macros` to refer to
@@ -161,6 +167,7 @@ pub use instance::*;
 pub use instance_auto_restart_policy::*;
 pub use instance_cpu_count::*;
 pub use instance_state::*;
+pub use instance_template::*;
 pub use internet_gateway::*;
 pub use inventory::*;
 pub use ip_pool::*;
@@ -208,6 +215,9 @@ pub use sled_resource_vmm::*;
 pub use sled_state::*;
 pub use sled_underlay_subnet_allocation::*;
 pub use snapshot::*;
+pub use snapshot_export::*;
+pub use snapshot_group::*;
+pub use snapshot_policy::*;
 pub use ssh_key::*;
 pub use support_bundle::*;
 pub use switch::*;
@@ -215,6 +225,7 @@ pub use switch_interface::*;
 pub use switch_port::*;
 pub use target_release::*;
 pub use tuf_repo::*;
+pub use tuf_repo_upload::*;
 pub use typed_uuid::to_db_typed_uuid;
 pub use upstairs_repair::*;
 pub use user_builtin::*;
@@ -233,6 +244,7 @@ pub use vpc_firewall_rule::*;
 pub use vpc_route::*;
 pub use vpc_router::*;
 pub use vpc_subnet::*;
+pub use zone_placement_policy::*;
 pub use zpool::*;
 
 // TODO: The existence of both impl_enum_type and impl_enum_wrapper is a
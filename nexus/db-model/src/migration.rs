@@ -7,6 +7,7 @@ use crate::MigrationState;
 use chrono::DateTime;
 use chrono::Utc;
 use nexus_db_schema::schema::migration;
+use nexus_types::external_api::views;
 use omicron_common::api::internal::nexus;
 use omicron_uuid_kinds::{GenericUuid, InstanceUuid};
 use serde::Deserialize;
@@ -65,6 +66,9 @@ pub struct Migration {
 
     /// The time the target VMM state was most recently updated.
     pub time_target_updated: Option<DateTime<Utc>>,
+
+    /// Why the migration failed, once Nexus has processed its failure.
+    pub failure_reason: Option<String>,
 }
 
 impl Migration {
@@ -87,6 +91,7 @@ impl Migration {
             target_propolis_id,
             target_gen: Generation::new(),
             time_target_updated: None,
+            failure_reason: None,
         }
     }
 
@@ -108,3 +113,52 @@ impl Migration {
             || self.target_state == MigrationState::COMPLETED
     }
 }
+
+impl From<Migration> for views::InstanceMigration {
+    fn from(migration: Migration) -> Self {
+        let state = if migration.either_side_failed() {
+            views::InstanceMigrationState::Failed
+        } else if migration.either_side_completed() {
+            views::InstanceMigrationState::Completed
+        } else if migration.source_state == MigrationState::IN_PROGRESS
+            || migration.target_state == MigrationState::IN_PROGRESS
+        {
+            views::InstanceMigrationState::InProgress
+        } else {
+            views::InstanceMigrationState::Pending
+        };
+
+        // Nexus records why a migration failed once it has processed the
+        // failure. Until then, say which side reported it.
+        let failure_reason = match state {
+            views::InstanceMigrationState::Failed => {
+                migration.failure_reason.clone().or_else(|| {
+                    let side = match (
+                        migration.source_state == MigrationState::FAILED,
+                        migration.target_state == MigrationState::FAILED,
+                    ) {
+                        (true, true) => "source and destination sleds",
+                        (true, false) => "source sled",
+                        (false, _) => "destination sled",
+                    };
+                    Some(format!("the migration failed on the {side}"))
+                })
+            }
+            _ => None,
+        };
+
+        let time_updated = migration
+            .time_source_updated
+            .max(migration.time_target_updated)
+            .unwrap_or(migration.time_created);
+
+        Self {
+            id: migration.id,
+            instance_id: migration.instance_id,
+            time_created: migration.time_created,
+            time_updated,
+            state,
+            failure_reason,
+        }
+    }
+}
@@ -16,7 +16,7 @@ use std::{collections::BTreeMap, sync::LazyLock};
 ///
 /// This must be updated when you change the database schema.  Refer to
 /// schema/crdb/README.adoc in the root of this repository for details.
-pub const SCHEMA_VERSION: Version = Version::new(138, 0, 0);
+pub const SCHEMA_VERSION: Version = Version::new(148, 0, 0);
 
 /// List of all past database schema versions, in *reverse* order
 ///
@@ -28,6 +28,16 @@ static KNOWN_VERSIONS: LazyLock<Vec<KnownVersion>> = LazyLock::new(|| {
         // |  leaving the first copy as an example for the next person.
         // v
         // KnownVersion::new(next_int, "unique-dirname-with-the-sql-files"),
+        KnownVersion::new(148, "zone-placement-cubby-range"),
+        KnownVersion::new(147, "tuf-repo-upload"),
+        KnownVersion::new(146, "instance-template"),
+        KnownVersion::new(145, "snapshot-group"),
+        KnownVersion::new(144, "snapshot-policy"),
+        KnownVersion::new(143, "migration-failure-reason"),
+        KnownVersion::new(142, "snapshot-export"),
+        KnownVersion::new(141, "zone-placement-policy"),
+        KnownVersion::new(140, "blueprint-approval"),
+        KnownVersion::new(139, "blueprint-planning-report"),
         KnownVersion::new(138, "saga-abandoned-state"),
         KnownVersion::new(137, "oximeter-read-policy"),
         KnownVersion::new(136, "do-not-provision-flag-for-crucible-dataset"),
@@ -328,12 +328,13 @@ impl SledUpdate {
 #[derive(Clone, Debug)]
 pub struct SledReservationConstraints {
     must_select_from: Vec<Uuid>,
+    cannot_select_from: Vec<Uuid>,
 }
 
 impl SledReservationConstraints {
     /// Creates a constraint set with no constraints in it.
     pub fn none() -> Self {
-        Self { must_select_from: Vec::new() }
+        Self { must_select_from: Vec::new(), cannot_select_from: Vec::new() }
     }
 
     /// If the constraints include a set of sleds that the caller must select
@@ -347,6 +348,11 @@ impl SledReservationConstraints {
             Some(&self.must_select_from)
         }
     }
+
+    /// Returns the sleds that the caller must not select, which may be empty.
+    pub fn cannot_select_from(&self) -> &[Uuid] {
+        &self.cannot_select_from
+    }
 }
 
 #[derive(Debug)]
@@ -369,6 +375,14 @@ impl SledReservationConstraintBuilder {
         self
     }
 
+    /// Adds a "must not select any of the following sled IDs" constraint. If
+    /// such a constraint already exists, appends the supplied sled IDs to the
+    /// "cannot select from" list.
+    pub fn cannot_select_from(mut self, sled_ids: &[Uuid]) -> Self {
+        self.constraints.cannot_select_from.extend(sled_ids);
+        self
+    }
+
     /// Builds a set of constraints from this builder's current state.
     pub fn build(self) -> SledReservationConstraints {
         self.constraints
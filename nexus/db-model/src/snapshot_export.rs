@@ -0,0 +1,122 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use super::{ByteCount, impl_enum_type};
+use crate::typed_uuid::DbTypedUuid;
+use chrono::{DateTime, Utc};
+use nexus_db_schema::schema::snapshot_export;
+use nexus_types::external_api::views;
+use omicron_common::api::external;
+use omicron_uuid_kinds::VolumeKind;
+use omicron_uuid_kinds::VolumeUuid;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddrV6;
+use uuid::Uuid;
+
+impl_enum_type!(
+    SnapshotExportFormatEnum:
+
+    #[derive(Clone, Copy, Debug, AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq)]
+    pub enum SnapshotExportFormat;
+
+    Raw => b"raw"
+    Qcow2 => b"qcow2"
+);
+
+impl From<views::SnapshotExportFormat> for SnapshotExportFormat {
+    fn from(format: views::SnapshotExportFormat) -> Self {
+        match format {
+            views::SnapshotExportFormat::Raw => Self::Raw,
+            views::SnapshotExportFormat::Qcow2 => Self::Qcow2,
+        }
+    }
+}
+
+impl From<SnapshotExportFormat> for views::SnapshotExportFormat {
+    fn from(format: SnapshotExportFormat) -> Self {
+        match format {
+            SnapshotExportFormat::Raw => Self::Raw,
+            SnapshotExportFormat::Qcow2 => Self::Qcow2,
+        }
+    }
+}
+
+/// An export of a snapshot's contents as a downloadable image
+///
+/// The snapshot is read through `volume_id`, a read-only copy of the
+/// snapshot's volume that stays attached to the Pantry at `pantry_address`
+/// for as long as the export exists.
+#[derive(
+    Queryable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+)]
+#[diesel(table_name = snapshot_export)]
+pub struct SnapshotExport {
+    pub id: Uuid,
+    pub time_created: DateTime<Utc>,
+    pub time_modified: DateTime<Utc>,
+    pub time_deleted: Option<DateTime<Utc>>,
+
+    pub snapshot_id: Uuid,
+    volume_id: DbTypedUuid<VolumeKind>,
+    pantry_address: String,
+
+    pub format: SnapshotExportFormat,
+    /// Size of the exported image
+    #[diesel(column_name = size_bytes)]
+    pub size: ByteCount,
+    pub bytes_exported: ByteCount,
+}
+
+impl SnapshotExport {
+    pub fn new(
+        id: Uuid,
+        snapshot_id: Uuid,
+        volume_id: VolumeUuid,
+        pantry_address: SocketAddrV6,
+        format: SnapshotExportFormat,
+        size: ByteCount,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            time_created: now,
+            time_modified: now,
+            time_deleted: None,
+            snapshot_id,
+            volume_id: volume_id.into(),
+            pantry_address: pantry_address.to_string(),
+            format,
+            size,
+            bytes_exported: ByteCount::from(external::ByteCount::from(0u32)),
+        }
+    }
+
+    pub fn volume_id(&self) -> VolumeUuid {
+        self.volume_id.into()
+    }
+
+    pub fn pantry_address(&self) -> SocketAddrV6 {
+        self.pantry_address.parse().unwrap()
+    }
+}
+
+impl From<SnapshotExport> for views::SnapshotExport {
+    fn from(export: SnapshotExport) -> Self {
+        Self {
+            id: export.id,
+            snapshot_id: export.snapshot_id,
+            time_created: export.time_created,
+            format: export.format.into(),
+            size: export.size.into(),
+            bytes_exported: export.bytes_exported.into(),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Database representation of snapshot groups
+
+use super::{Name, impl_enum_type};
+use db_macros::Resource;
+use nexus_db_schema::schema::snapshot_group;
+use nexus_db_schema::schema::snapshot_group_member;
+use nexus_types::external_api::params;
+use nexus_types::external_api::views;
+use nexus_types::identity::Resource;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+impl_enum_type!(
+    SnapshotGroupStateEnum:
+
+    #[derive(Clone, Copy, Debug, AsExpression, FromSqlRow, Serialize, Deserialize, PartialEq)]
+    pub enum SnapshotGroupState;
+
+    Creating => b"creating"
+    Ready => b"ready"
+);
+
+impl From<SnapshotGroupState> for views::SnapshotGroupState {
+    fn from(state: SnapshotGroupState) -> Self {
+        match state {
+            SnapshotGroupState::Creating => Self::Creating,
+            SnapshotGroupState::Ready => Self::Ready,
+        }
+    }
+}
+
+/// A set of snapshots of an instance's disks, taken at the same point
+#[derive(
+    Queryable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    Resource,
+    Serialize,
+    Deserialize,
+    PartialEq,
+)]
+#[diesel(table_name = snapshot_group)]
+pub struct SnapshotGroup {
+    #[diesel(embed)]
+    pub identity: SnapshotGroupIdentity,
+
+    pub project_id: Uuid,
+    pub instance_id: Uuid,
+
+    pub state: SnapshotGroupState,
+}
+
+impl SnapshotGroup {
+    pub fn new(
+        id: Uuid,
+        project_id: Uuid,
+        instance_id: Uuid,
+        params: params::SnapshotGroupCreate,
+    ) -> Self {
+        Self {
+            identity: SnapshotGroupIdentity::new(id, params.identity),
+            project_id,
+            instance_id,
+            state: SnapshotGroupState::Creating,
+        }
+    }
+
+    /// Converts the group into its external view, given its members
+    pub fn into_view(
+        self,
+        members: Vec<SnapshotGroupMember>,
+    ) -> views::SnapshotGroup {
+        views::SnapshotGroup {
+            identity: self.identity(),
+            project_id: self.project_id,
+            instance_id: self.instance_id,
+            state: self.state.into(),
+            members: members.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// One of the snapshots in a snapshot group
+#[derive(
+    Queryable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+)]
+#[diesel(table_name = snapshot_group_member)]
+pub struct SnapshotGroupMember {
+    pub group_id: Uuid,
+    pub snapshot_id: Uuid,
+    pub disk_id: Uuid,
+    pub disk_name: Name,
+}
+
+impl From<SnapshotGroupMember> for views::SnapshotGroupMember {
+    fn from(member: SnapshotGroupMember) -> Self {
+        Self {
+            snapshot_id: member.snapshot_id,
+            disk_id: member.disk_id,
+            disk_name: member.disk_name.into(),
+        }
+    }
+}
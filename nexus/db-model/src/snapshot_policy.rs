@@ -0,0 +1,126 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Database representation of scheduled snapshot policies
+
+use crate::SqlU32;
+use chrono::{DateTime, Utc};
+use db_macros::Resource;
+use nexus_db_schema::schema::snapshot_policy;
+use nexus_db_schema::schema::snapshot_policy_snapshot;
+use nexus_types::external_api::params;
+use nexus_types::external_api::views;
+use nexus_types::identity::Resource;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// A schedule for snapshotting a disk, or every disk in a project
+#[derive(
+    Queryable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    Resource,
+    Serialize,
+    Deserialize,
+    PartialEq,
+)]
+#[diesel(table_name = snapshot_policy)]
+pub struct SnapshotPolicy {
+    #[diesel(embed)]
+    pub identity: SnapshotPolicyIdentity,
+
+    pub project_id: Uuid,
+    /// The disk to snapshot, or `None` for every disk in the project
+    pub disk_id: Option<Uuid>,
+
+    pub interval_secs: SqlU32,
+    /// How many of the policy's snapshots of each disk are kept
+    pub retention_count: SqlU32,
+
+    /// When the policy last took or deleted snapshots
+    pub time_last_run: Option<DateTime<Utc>>,
+    /// What went wrong the last time the policy ran, if anything
+    pub last_error: Option<String>,
+}
+
+impl SnapshotPolicy {
+    pub fn new(
+        project_id: Uuid,
+        disk_id: Option<Uuid>,
+        params: params::SnapshotPolicyCreate,
+    ) -> Self {
+        Self {
+            identity: SnapshotPolicyIdentity::new(
+                Uuid::new_v4(),
+                params.identity,
+            ),
+            project_id,
+            disk_id,
+            interval_secs: params.interval_secs.into(),
+            retention_count: params.retention_count.into(),
+            time_last_run: None,
+            last_error: None,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(u64::from(*self.interval_secs))
+    }
+}
+
+impl From<SnapshotPolicy> for views::SnapshotPolicy {
+    fn from(policy: SnapshotPolicy) -> Self {
+        Self {
+            identity: policy.identity(),
+            project_id: policy.project_id,
+            disk_id: policy.disk_id,
+            interval_secs: *policy.interval_secs,
+            retention_count: *policy.retention_count,
+            time_last_run: policy.time_last_run,
+            last_error: policy.last_error,
+        }
+    }
+}
+
+/// A snapshot taken, or to be taken, by a snapshot policy
+///
+/// The record is inserted when a Nexus claims the scheduled time for a disk,
+/// and `snapshot_id` is filled in once the snapshot has been created.
+#[derive(
+    Queryable,
+    Insertable,
+    Selectable,
+    Clone,
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+)]
+#[diesel(table_name = snapshot_policy_snapshot)]
+pub struct SnapshotPolicySnapshot {
+    pub policy_id: Uuid,
+    pub disk_id: Uuid,
+    pub time_scheduled: DateTime<Utc>,
+    pub time_created: DateTime<Utc>,
+    pub snapshot_id: Option<Uuid>,
+}
+
+impl SnapshotPolicySnapshot {
+    pub fn new(
+        policy_id: Uuid,
+        disk_id: Uuid,
+        time_scheduled: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            policy_id,
+            disk_id,
+            time_scheduled,
+            time_created: Utc::now(),
+            snapshot_id: None,
+        }
+    }
+}
@@ -0,0 +1,73 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use chrono::{DateTime, Utc};
+use nexus_db_schema::schema::{tuf_repo_upload, tuf_repo_upload_chunk};
+use nexus_types::external_api::views;
+use uuid::Uuid;
+
+/// A resumable upload of a TUF repository archive.
+///
+/// The data received so far is stored as a sequence of
+/// [`TufRepoUploadChunk`]s, so an upload can be continued through any Nexus
+/// instance.
+#[derive(Queryable, Insertable, Clone, Debug, Selectable)]
+#[diesel(table_name = tuf_repo_upload)]
+pub struct TufRepoUpload {
+    pub id: Uuid,
+    pub file_name: String,
+    pub size: i64,
+    pub bytes_received: i64,
+    pub time_created: DateTime<Utc>,
+    pub time_modified: DateTime<Utc>,
+    pub time_deleted: Option<DateTime<Utc>>,
+}
+
+impl TufRepoUpload {
+    /// Creates a new, empty `TufRepoUpload` ready for insertion.
+    pub fn new(file_name: String, size: u64) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            file_name,
+            size: size as i64,
+            bytes_received: 0,
+            time_created: now,
+            time_modified: now,
+            time_deleted: None,
+        }
+    }
+
+    /// Returns the total size of the archive being uploaded.
+    pub fn size(&self) -> u64 {
+        self.size as u64
+    }
+
+    /// Returns the number of bytes received so far.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received as u64
+    }
+}
+
+impl From<TufRepoUpload> for views::TufRepoUpload {
+    fn from(upload: TufRepoUpload) -> Self {
+        Self {
+            id: upload.id,
+            size: upload.size(),
+            bytes_received: upload.bytes_received(),
+            file_name: upload.file_name,
+            time_created: upload.time_created,
+            time_modified: upload.time_modified,
+        }
+    }
+}
+
+/// A contiguous range of bytes received for a [`TufRepoUpload`].
+#[derive(Queryable, Insertable, Clone, Debug, Selectable)]
+#[diesel(table_name = tuf_repo_upload_chunk)]
+pub struct TufRepoUploadChunk {
+    pub upload_id: Uuid,
+    pub byte_offset: i64,
+    pub data: Vec<u8>,
+}
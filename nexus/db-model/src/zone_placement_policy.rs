@@ -0,0 +1,65 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Database representation of a zone placement policy
+
+use crate::SqlU32;
+use chrono::{DateTime, Utc};
+use nexus_db_schema::schema::zone_placement_policy;
+use nexus_types::deployment;
+use omicron_uuid_kinds::GenericUuid;
+use omicron_uuid_kinds::SledUuid;
+use std::num::NonZeroU32;
+use uuid::Uuid;
+
+#[derive(Queryable, Clone, Debug, Selectable, Insertable)]
+#[diesel(table_name = zone_placement_policy)]
+pub struct ZonePlacementPolicy {
+    pub version: SqlU32,
+    pub max_discretionary_zones_per_sled: Option<SqlU32>,
+    pub reserved_sled_ids: Vec<Uuid>,
+    pub time_created: DateTime<Utc>,
+    pub cubbies_per_failure_domain: Option<SqlU32>,
+}
+
+impl From<ZonePlacementPolicy> for deployment::ZonePlacementPolicy {
+    fn from(value: ZonePlacementPolicy) -> Self {
+        deployment::ZonePlacementPolicy {
+            version: value.version.0,
+            max_discretionary_zones_per_sled: value
+                .max_discretionary_zones_per_sled
+                .map(|max| max.0),
+            reserved_sleds: value
+                .reserved_sled_ids
+                .into_iter()
+                .map(SledUuid::from_untyped_uuid)
+                .collect(),
+            // The database constraint ensures this is nonzero.
+            cubbies_per_failure_domain: value
+                .cubbies_per_failure_domain
+                .and_then(|n| NonZeroU32::new(n.0)),
+            time_created: value.time_created,
+        }
+    }
+}
+
+impl From<deployment::ZonePlacementPolicy> for ZonePlacementPolicy {
+    fn from(value: deployment::ZonePlacementPolicy) -> Self {
+        ZonePlacementPolicy {
+            version: value.version.into(),
+            max_discretionary_zones_per_sled: value
+                .max_discretionary_zones_per_sled
+                .map(SqlU32::from),
+            reserved_sled_ids: value
+                .reserved_sleds
+                .into_iter()
+                .map(|sled_id| sled_id.into_untyped_uuid())
+                .collect(),
+            time_created: value.time_created,
+            cubbies_per_failure_domain: value
+                .cubbies_per_failure_domain
+                .map(|n| SqlU32::from(n.get())),
+        }
+    }
+}
@@ -52,12 +52,15 @@ use nexus_db_model::BpOmicronZoneNic;
 use nexus_db_model::BpOximeterReadPolicy;
 use nexus_db_model::BpSledMetadata;
 use nexus_db_model::BpTarget;
+use nexus_db_model::BpTargetAudit;
 use nexus_db_model::TufArtifact;
 use nexus_db_model::to_db_typed_uuid;
 use nexus_types::deployment::Blueprint;
 use nexus_types::deployment::BlueprintMetadata;
 use nexus_types::deployment::BlueprintSledConfig;
 use nexus_types::deployment::BlueprintTarget;
+use nexus_types::deployment::BlueprintTargetAuditAction;
+use nexus_types::deployment::BlueprintTargetAuditEntry;
 use nexus_types::deployment::ClickhouseClusterConfig;
 use nexus_types::deployment::CockroachDbPreserveDowngrade;
 use nexus_types::deployment::OximeterReadMode;
@@ -77,8 +80,11 @@ use std::collections::BTreeMap;
 use tufaceous_artifact::KnownArtifactKind;
 use uuid::Uuid;
 
+mod approval;
 mod external_networking;
 
+pub use external_networking::ExternalNetworkingChanges;
+
 impl DataStore {
     /// List blueprints
     pub async fn blueprints_list(
@@ -444,6 +450,7 @@ impl DataStore {
             time_created,
             creator,
             comment,
+            planning_report,
         ) = {
             use nexus_db_schema::schema::blueprint::dsl;
 
@@ -469,8 +476,25 @@ impl DataStore {
                 blueprint.time_created,
                 blueprint.creator,
                 blueprint.comment,
+                blueprint.planning_report,
             )
         };
+        // The planning report is informational only. If we can't parse one
+        // (e.g., because it was written by a Nexus with a different notion of
+        // what a report contains), log that and drop it rather than failing
+        // to read the blueprint.
+        let planning_report = planning_report.and_then(|report| {
+            serde_json::from_value(report)
+                .inspect_err(|error| {
+                    warn!(
+                        opctx.log,
+                        "dropping unparseable planning report from blueprint";
+                        "blueprint_id" => %blueprint_id,
+                        "error" => %error,
+                    );
+                })
+                .ok()
+        });
         let cockroachdb_setting_preserve_downgrade =
             CockroachDbPreserveDowngrade::from_optional_string(
                 &cockroachdb_setting_preserve_downgrade,
@@ -951,6 +975,7 @@ impl DataStore {
             time_created,
             creator,
             comment,
+            planning_report,
         })
     }
 
@@ -1276,14 +1301,16 @@ impl DataStore {
     /// must be the current target. To instead change the current target's
     /// properties (particularly whether it's enabled), use
     /// [`DataStore::blueprint_target_set_current_enabled`].
+    ///
+    /// The change is recorded in the audit trail as made by the actor
+    /// authenticated in `opctx`.
     pub async fn blueprint_target_set_current(
         &self,
         opctx: &OpContext,
         target: BlueprintTarget,
     ) -> Result<(), Error> {
-        let conn = self.pool_connection_authorized(opctx).await?;
-        Self::blueprint_target_set_current_on_connection(&conn, opctx, target)
-            .await
+        let actor = Self::blueprint_target_audit_actor(opctx);
+        self.blueprint_target_set_current_audited(opctx, target, actor).await
     }
 
     /// Variant of [Self::blueprint_target_set_current] which may be called from
@@ -1297,16 +1324,38 @@ impl DataStore {
             .authorize(authz::Action::Modify, &authz::BLUEPRINT_CONFIG)
             .await?;
 
+        let audit = BpTargetAudit::new(BlueprintTargetAuditEntry {
+            time: target.time_made_target,
+            blueprint_id: target.target_id,
+            action: BlueprintTargetAuditAction::MadeTarget,
+            actor: Self::blueprint_target_audit_actor(opctx),
+            enabled: Some(target.enabled),
+        });
+
+        Self::blueprint_target_insert_on_connection(conn, target)
+            .await
+            .map_err(Error::from)?;
+        Self::blueprint_target_audit_insert_on_connection(conn, audit)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Inserts `target` as the new current target blueprint, without any
+    /// authorization check
+    ///
+    /// Errors that may be retried within a transaction are reported as
+    /// [`TransactionError::Database`].
+    async fn blueprint_target_insert_on_connection(
+        conn: &async_bb8_diesel::Connection<DbConnection>,
+        target: BlueprintTarget,
+    ) -> Result<(), TransactionError<Error>> {
         let query = InsertTargetQuery {
             target_id: target.target_id,
             enabled: target.enabled,
             time_made_target: target.time_made_target,
         };
 
-        query
-            .execute_async(conn)
-            .await
-            .map_err(|e| Error::from(query.decode_error(e)))?;
+        query.execute_async(conn).await.map_err(|e| query.decode_error(e))?;
 
         Ok(())
     }
@@ -1328,11 +1377,21 @@ impl DataStore {
         opctx: &OpContext,
         target: BlueprintTarget,
     ) -> Result<(), Error> {
-        use nexus_db_schema::schema::bp_target::dsl;
+        let actor = Self::blueprint_target_audit_actor(opctx);
+        self.blueprint_target_set_current_enabled_audited(opctx, target, actor)
+            .await
+    }
 
-        opctx
-            .authorize(authz::Action::Modify, &authz::BLUEPRINT_CONFIG)
-            .await?;
+    /// Records a new version of the current target blueprint with `target`'s
+    /// `enabled` field, without any authorization check
+    ///
+    /// Errors that may be retried within a transaction are reported as
+    /// [`TransactionError::Database`].
+    async fn blueprint_target_update_enabled_on_connection(
+        conn: &async_bb8_diesel::Connection<DbConnection>,
+        target: BlueprintTarget,
+    ) -> Result<(), TransactionError<Error>> {
+        use nexus_db_schema::schema::bp_target::dsl;
 
         // Diesel requires us to use an alias in order to refer to the
         // `bp_target` table twice in the same query.
@@ -1381,17 +1440,14 @@ impl DataStore {
             .filter(dsl::blueprint_id.eq(to_db_typed_uuid(target.target_id)))
             .insert_into(dsl::bp_target);
 
-        let conn = self.pool_connection_authorized(opctx).await?;
-
-        let num_inserted = query
-            .execute_async(&*conn)
-            .await
-            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+        let num_inserted = query.execute_async(conn).await?;
 
         match num_inserted {
-            0 => Err(Error::invalid_request(format!(
-                "Blueprint {} is not the current target blueprint",
-                target.target_id
+            0 => Err(TransactionError::CustomError(Error::invalid_request(
+                format!(
+                    "Blueprint {} is not the current target blueprint",
+                    target.target_id
+                ),
             ))),
             1 => Ok(()),
             // This is impossible, not only due to the `.limit(1)` in the
@@ -1498,6 +1554,15 @@ enum InsertTargetError {
     Other(DieselError),
 }
 
+impl From<InsertTargetError> for TransactionError<Error> {
+    fn from(value: InsertTargetError) -> Self {
+        match value {
+            InsertTargetError::Other(e) => TransactionError::Database(e),
+            e => TransactionError::CustomError(Error::from(e)),
+        }
+    }
+}
+
 impl From<InsertTargetError> for Error {
     fn from(value: InsertTargetError) -> Self {
         match value {
@@ -1857,6 +1922,7 @@ mod tests {
     use nexus_reconfigurator_planning::blueprint_builder::EnsureMultiple;
     use nexus_reconfigurator_planning::example::ExampleSystemBuilder;
     use nexus_reconfigurator_planning::example::example;
+    use nexus_types::deployment::BlueprintApproval;
     use nexus_types::deployment::BlueprintPhysicalDiskDisposition;
     use nexus_types::deployment::BlueprintZoneConfig;
     use nexus_types::deployment::BlueprintZoneDisposition;
@@ -1993,6 +2059,7 @@ mod tests {
             policy: SledPolicy::provisionable(),
             state: SledState::Active,
             resources,
+            failure_domain: None,
         }
     }
 
@@ -2703,6 +2770,215 @@ mod tests {
         logctx.cleanup_successful();
     }
 
+    #[tokio::test]
+    async fn test_blueprint_approval() {
+        // Setup
+        let logctx = dev::test_setup_log("test_blueprint_approval");
+        let db = TestDatabase::new_with_datastore(&logctx.log).await;
+        let (opctx, datastore) = (db.opctx(), db.datastore());
+
+        // Create an initial blueprint (the target) and a child.
+        let collection = CollectionBuilder::new("test").build();
+        let blueprint1 = BlueprintBuilder::build_empty_with_sleds(
+            std::iter::empty(),
+            "test1",
+        );
+        let blueprint2 = BlueprintBuilder::new_based_on(
+            &logctx.log,
+            &blueprint1,
+            &EMPTY_PLANNING_INPUT,
+            &collection,
+            "test2",
+        )
+        .expect("failed to create builder")
+        .build();
+        datastore.blueprint_insert(&opctx, &blueprint1).await.unwrap();
+        datastore.blueprint_insert(&opctx, &blueprint2).await.unwrap();
+        datastore
+            .blueprint_target_set_current(
+                &opctx,
+                BlueprintTarget {
+                    target_id: blueprint1.id,
+                    enabled: true,
+                    time_made_target: now_db_precision(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Setting the target without naming an actor records the one
+        // authenticated in `opctx`.
+        let test_actor = opctx.authn.actor().unwrap().actor_id().to_string();
+        let audit = datastore
+            .blueprint_target_audit_list(&opctx, blueprint1.id)
+            .await
+            .unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].action, BlueprintTargetAuditAction::MadeTarget);
+        assert_eq!(audit[0].actor.as_deref(), Some(test_actor.as_str()));
+
+        // Blueprints that haven't been proposed have no approval status.
+        assert_eq!(
+            datastore.blueprint_approval_get(&opctx, blueprint2.id).await,
+            Ok(None)
+        );
+
+        // Proposers can't name themselves as the required approver.
+        let err = datastore
+            .blueprint_propose(
+                &opctx,
+                BlueprintApproval {
+                    blueprint_id: blueprint2.id,
+                    proposed_by: "alice".to_string(),
+                    required_approver: "alice".to_string(),
+                    time_proposed: now_db_precision(),
+                    approved_by: None,
+                    time_approved: None,
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("by its proposer"), "{err}");
+
+        // The current target can't be proposed.
+        let proposal = |blueprint_id| BlueprintApproval {
+            blueprint_id,
+            proposed_by: "alice".to_string(),
+            required_approver: "bob".to_string(),
+            time_proposed: now_db_precision(),
+            approved_by: None,
+            time_approved: None,
+        };
+        let err = datastore
+            .blueprint_propose(&opctx, proposal(blueprint1.id))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("is the current target"), "{err}");
+
+        // Propose blueprint2; it's held until approved, and can't be
+        // proposed again.
+        let proposed = proposal(blueprint2.id);
+        datastore.blueprint_propose(&opctx, proposed.clone()).await.unwrap();
+        let approval = datastore
+            .blueprint_approval_get(&opctx, blueprint2.id)
+            .await
+            .unwrap()
+            .expect("blueprint was proposed");
+        assert_eq!(approval, proposed);
+        assert!(approval.is_held());
+        let err = datastore
+            .blueprint_propose(&opctx, proposal(blueprint2.id))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("already been proposed"), "{err}");
+
+        // Only the required approver may approve it, and only once.
+        let err = datastore
+            .blueprint_approve(
+                &opctx,
+                blueprint2.id,
+                "alice".to_string(),
+                now_db_precision(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("by its proposer"), "{err}");
+        let err = datastore
+            .blueprint_approve(
+                &opctx,
+                blueprint2.id,
+                "mallory".to_string(),
+                now_db_precision(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("must be approved by bob"), "{err}");
+        let approval = datastore
+            .blueprint_approve(
+                &opctx,
+                blueprint2.id,
+                "bob".to_string(),
+                now_db_precision(),
+            )
+            .await
+            .unwrap();
+        assert!(!approval.is_held());
+        assert_eq!(approval.approved_by.as_deref(), Some("bob"));
+        let err = datastore
+            .blueprint_approve(
+                &opctx,
+                blueprint2.id,
+                "bob".to_string(),
+                now_db_precision(),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("already been approved"), "{err}");
+
+        // Make blueprint2 the target and then disable it. A change that fails
+        // isn't recorded.
+        let target = BlueprintTarget {
+            target_id: blueprint2.id,
+            enabled: true,
+            time_made_target: now_db_precision(),
+        };
+        datastore
+            .blueprint_target_set_current_audited(
+                &opctx,
+                target,
+                Some("carol".to_string()),
+            )
+            .await
+            .unwrap();
+        datastore
+            .blueprint_target_set_current_audited(
+                &opctx,
+                target,
+                Some("carol".to_string()),
+            )
+            .await
+            .unwrap_err();
+        datastore
+            .blueprint_target_set_current_enabled_audited(
+                &opctx,
+                BlueprintTarget {
+                    enabled: false,
+                    time_made_target: now_db_precision(),
+                    ..target
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Every step was recorded in the audit trail.
+        let audit = datastore
+            .blueprint_target_audit_list(&opctx, blueprint2.id)
+            .await
+            .unwrap();
+        let actions: Vec<_> = audit
+            .iter()
+            .map(|e| (e.action, e.actor.as_deref(), e.enabled))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (BlueprintTargetAuditAction::Proposed, Some("alice"), None),
+                (BlueprintTargetAuditAction::Approved, Some("bob"), None),
+                (
+                    BlueprintTargetAuditAction::MadeTarget,
+                    Some("carol"),
+                    Some(true)
+                ),
+                (BlueprintTargetAuditAction::SetEnabled, None, Some(false)),
+            ]
+        );
+
+        // Clean up.
+        db.terminate().await;
+        logctx.cleanup_successful();
+    }
+
     async fn create_blueprint_with_external_ip(
         datastore: &DataStore,
         opctx: &OpContext,
@@ -0,0 +1,404 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Review of blueprints before execution, and the audit trail of changes to
+//! the target blueprint
+
+use crate::authz;
+use crate::context::OpContext;
+use crate::db::DataStore;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::DateTime;
+use chrono::Utc;
+use diesel::ExpressionMethods;
+use diesel::OptionalExtension;
+use diesel::QueryDsl;
+use diesel::expression::SelectableHelper;
+use diesel::result::Error as DieselError;
+use nexus_db_errors::ErrorHandler;
+use nexus_db_errors::OptionalError;
+use nexus_db_errors::TransactionError;
+use nexus_db_errors::public_error_from_diesel;
+use nexus_db_lookup::DbConnection;
+use nexus_db_model::BpTargetApproval;
+use nexus_db_model::BpTargetAudit;
+use nexus_db_model::to_db_typed_uuid;
+use nexus_types::deployment::BlueprintApproval;
+use nexus_types::deployment::BlueprintTarget;
+use nexus_types::deployment::BlueprintTargetAuditAction;
+use nexus_types::deployment::BlueprintTargetAuditEntry;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::ResourceType;
+use omicron_uuid_kinds::BlueprintUuid;
+use omicron_uuid_kinds::GenericUuid;
+
+impl DataStore {
+    /// Propose a blueprint for review before it's executed
+    ///
+    /// Until it's approved (see [`DataStore::blueprint_approve`]), the
+    /// blueprint is "held": it may be made the current target, but blueprint
+    /// execution skips it. A blueprint may only be proposed once, and only
+    /// before it has been made the current target. The required approver
+    /// must be someone other than the proposer.
+    pub async fn blueprint_propose(
+        &self,
+        opctx: &OpContext,
+        approval: BlueprintApproval,
+    ) -> Result<(), Error> {
+        opctx
+            .authorize(authz::Action::Modify, &authz::BLUEPRINT_CONFIG)
+            .await?;
+
+        let blueprint_id = approval.blueprint_id;
+        if approval.required_approver == approval.proposed_by {
+            return Err(Error::invalid_request(format!(
+                "blueprint {blueprint_id} cannot be approved by its proposer",
+            )));
+        }
+        let audit = BpTargetAudit::new(BlueprintTargetAuditEntry {
+            time: approval.time_proposed,
+            blueprint_id,
+            action: BlueprintTargetAuditAction::Proposed,
+            actor: Some(approval.proposed_by.clone()),
+            enabled: None,
+        });
+        let approval = BpTargetApproval::from(approval);
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let err = OptionalError::new();
+        self.transaction_retry_wrapper("blueprint_propose")
+            .transaction(&conn, |conn| {
+                let err = err.clone();
+                let approval = approval.clone();
+                let audit = audit.clone();
+                async move {
+                    // The blueprint must exist...
+                    let exists = {
+                        use nexus_db_schema::schema::blueprint::dsl;
+                        dsl::blueprint
+                            .filter(dsl::id.eq(to_db_typed_uuid(blueprint_id)))
+                            .select(dsl::id)
+                            .first_async::<uuid::Uuid>(&conn)
+                            .await
+                            .optional()?
+                            .is_some()
+                    };
+                    if !exists {
+                        return Err(err.bail(TransactionError::CustomError(
+                            Error::not_found_by_id(
+                                ResourceType::Blueprint,
+                                blueprint_id.as_untyped_uuid(),
+                            ),
+                        )));
+                    }
+
+                    // ... and must not already be the target: holding a
+                    // blueprint that's already being executed would be
+                    // surprising at best.
+                    let current_target =
+                        Self::blueprint_current_target_only(&conn)
+                            .await
+                            .map_err(|txn_err| txn_err.into_diesel(&err))?;
+                    if current_target.target_id == blueprint_id {
+                        return Err(err.bail(TransactionError::CustomError(
+                            Error::conflict(format!(
+                                "blueprint {blueprint_id} is the current \
+                                 target and cannot be proposed for review",
+                            )),
+                        )));
+                    }
+
+                    let ninserted = {
+                        use nexus_db_schema::schema::bp_target_approval::dsl;
+                        diesel::insert_into(dsl::bp_target_approval)
+                            .values(approval)
+                            .on_conflict(dsl::blueprint_id)
+                            .do_nothing()
+                            .execute_async(&conn)
+                            .await?
+                    };
+                    if ninserted == 0 {
+                        return Err(err.bail(TransactionError::CustomError(
+                            Error::conflict(format!(
+                                "blueprint {blueprint_id} has already been \
+                                 proposed",
+                            )),
+                        )));
+                    }
+
+                    Self::blueprint_target_audit_insert_on_connection(
+                        &conn, audit,
+                    )
+                    .await?;
+
+                    Ok(())
+                }
+            })
+            .await
+            .map_err(|e| match err.take() {
+                Some(err) => err.into(),
+                None => public_error_from_diesel(e, ErrorHandler::Server),
+            })
+    }
+
+    /// Approve a proposed blueprint for execution
+    ///
+    /// `approved_by` must match the required approver named when the
+    /// blueprint was proposed, and must not be the proposer.
+    pub async fn blueprint_approve(
+        &self,
+        opctx: &OpContext,
+        blueprint_id: BlueprintUuid,
+        approved_by: String,
+        time_approved: DateTime<Utc>,
+    ) -> Result<BlueprintApproval, Error> {
+        opctx
+            .authorize(authz::Action::Modify, &authz::BLUEPRINT_CONFIG)
+            .await?;
+
+        let audit = BpTargetAudit::new(BlueprintTargetAuditEntry {
+            time: time_approved,
+            blueprint_id,
+            action: BlueprintTargetAuditAction::Approved,
+            actor: Some(approved_by.clone()),
+            enabled: None,
+        });
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let err = OptionalError::new();
+        self.transaction_retry_wrapper("blueprint_approve")
+            .transaction(&conn, |conn| {
+                let err = err.clone();
+                let approved_by = approved_by.clone();
+                let audit = audit.clone();
+                async move {
+                    use nexus_db_schema::schema::bp_target_approval::dsl;
+
+                    let approval = dsl::bp_target_approval
+                        .filter(
+                            dsl::blueprint_id
+                                .eq(to_db_typed_uuid(blueprint_id)),
+                        )
+                        .select(BpTargetApproval::as_select())
+                        .first_async(&conn)
+                        .await
+                        .optional()?;
+                    let Some(approval) = approval else {
+                        return Err(err.bail(TransactionError::CustomError(
+                            Error::invalid_request(format!(
+                                "blueprint {blueprint_id} has not been \
+                                 proposed for review",
+                            )),
+                        )));
+                    };
+                    if let Some(previous) = &approval.approved_by {
+                        return Err(err.bail(TransactionError::CustomError(
+                            Error::conflict(format!(
+                                "blueprint {blueprint_id} has already been \
+                                 approved by {previous}",
+                            )),
+                        )));
+                    }
+                    if approval.proposed_by == approved_by {
+                        return Err(err.bail(TransactionError::CustomError(
+                            Error::invalid_request(format!(
+                                "blueprint {blueprint_id} cannot be approved \
+                                 by its proposer",
+                            )),
+                        )));
+                    }
+                    if approval.required_approver != approved_by {
+                        return Err(err.bail(TransactionError::CustomError(
+                            Error::invalid_request(format!(
+                                "blueprint {blueprint_id} must be approved \
+                                 by {}",
+                                approval.required_approver,
+                            )),
+                        )));
+                    }
+
+                    let approval = diesel::update(dsl::bp_target_approval)
+                        .filter(
+                            dsl::blueprint_id
+                                .eq(to_db_typed_uuid(blueprint_id)),
+                        )
+                        .filter(dsl::approved_by.is_null())
+                        .set((
+                            dsl::approved_by.eq(approved_by),
+                            dsl::time_approved.eq(time_approved),
+                        ))
+                        .returning(BpTargetApproval::as_returning())
+                        .get_result_async(&conn)
+                        .await?;
+
+                    Self::blueprint_target_audit_insert_on_connection(
+                        &conn, audit,
+                    )
+                    .await?;
+
+                    Ok(approval.into())
+                }
+            })
+            .await
+            .map_err(|e| match err.take() {
+                Some(err) => err.into(),
+                None => public_error_from_diesel(e, ErrorHandler::Server),
+            })
+    }
+
+    /// Fetch the review status of a blueprint, if it was proposed for review
+    pub async fn blueprint_approval_get(
+        &self,
+        opctx: &OpContext,
+        blueprint_id: BlueprintUuid,
+    ) -> Result<Option<BlueprintApproval>, Error> {
+        use nexus_db_schema::schema::bp_target_approval::dsl;
+
+        opctx.authorize(authz::Action::Read, &authz::BLUEPRINT_CONFIG).await?;
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let approval = dsl::bp_target_approval
+            .filter(dsl::blueprint_id.eq(to_db_typed_uuid(blueprint_id)))
+            .select(BpTargetApproval::as_select())
+            .first_async(&*conn)
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+        Ok(approval.map(BlueprintApproval::from))
+    }
+
+    /// Set the current target blueprint, recording the change and who made it
+    /// in the audit trail
+    ///
+    /// This behaves like [`DataStore::blueprint_target_set_current`], but
+    /// records `actor` rather than the actor authenticated in `opctx`.
+    pub async fn blueprint_target_set_current_audited(
+        &self,
+        opctx: &OpContext,
+        target: BlueprintTarget,
+        actor: Option<String>,
+    ) -> Result<(), Error> {
+        opctx
+            .authorize(authz::Action::Modify, &authz::BLUEPRINT_CONFIG)
+            .await?;
+
+        let audit = BpTargetAudit::new(BlueprintTargetAuditEntry {
+            time: target.time_made_target,
+            blueprint_id: target.target_id,
+            action: BlueprintTargetAuditAction::MadeTarget,
+            actor,
+            enabled: Some(target.enabled),
+        });
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let err = OptionalError::new();
+        self.transaction_retry_wrapper("blueprint_target_set_current_audited")
+            .transaction(&conn, |conn| {
+                let err = err.clone();
+                let audit = audit.clone();
+                async move {
+                    Self::blueprint_target_insert_on_connection(&conn, target)
+                        .await
+                        .map_err(|txn_err| txn_err.into_diesel(&err))?;
+                    Self::blueprint_target_audit_insert_on_connection(
+                        &conn, audit,
+                    )
+                    .await
+                }
+            })
+            .await
+            .map_err(|e| match err.take() {
+                Some(err) => err.into(),
+                None => public_error_from_diesel(e, ErrorHandler::Server),
+            })
+    }
+
+    /// Set the current target blueprint's `enabled` field, recording the
+    /// change and who made it in the audit trail
+    ///
+    /// This behaves like [`DataStore::blueprint_target_set_current_enabled`],
+    /// but records `actor` rather than the actor authenticated in `opctx`.
+    pub async fn blueprint_target_set_current_enabled_audited(
+        &self,
+        opctx: &OpContext,
+        target: BlueprintTarget,
+        actor: Option<String>,
+    ) -> Result<(), Error> {
+        opctx
+            .authorize(authz::Action::Modify, &authz::BLUEPRINT_CONFIG)
+            .await?;
+
+        let audit = BpTargetAudit::new(BlueprintTargetAuditEntry {
+            time: target.time_made_target,
+            blueprint_id: target.target_id,
+            action: BlueprintTargetAuditAction::SetEnabled,
+            actor,
+            enabled: Some(target.enabled),
+        });
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let err = OptionalError::new();
+        self.transaction_retry_wrapper(
+            "blueprint_target_set_current_enabled_audited",
+        )
+        .transaction(&conn, |conn| {
+            let err = err.clone();
+            let audit = audit.clone();
+            async move {
+                Self::blueprint_target_update_enabled_on_connection(
+                    &conn, target,
+                )
+                .await
+                .map_err(|txn_err| txn_err.into_diesel(&err))?;
+                Self::blueprint_target_audit_insert_on_connection(&conn, audit)
+                    .await
+            }
+        })
+        .await
+        .map_err(|e| match err.take() {
+            Some(err) => err.into(),
+            None => public_error_from_diesel(e, ErrorHandler::Server),
+        })
+    }
+
+    /// Describes the actor authenticated in `opctx` for the audit trail
+    pub(super) fn blueprint_target_audit_actor(
+        opctx: &OpContext,
+    ) -> Option<String> {
+        opctx.authn.actor().map(|actor| actor.actor_id().to_string())
+    }
+
+    pub(super) async fn blueprint_target_audit_insert_on_connection(
+        conn: &async_bb8_diesel::Connection<DbConnection>,
+        audit: BpTargetAudit,
+    ) -> Result<(), DieselError> {
+        use nexus_db_schema::schema::bp_target_audit::dsl;
+
+        diesel::insert_into(dsl::bp_target_audit)
+            .values(audit)
+            .execute_async(conn)
+            .await?;
+        Ok(())
+    }
+
+    /// List the audit trail for a blueprint, oldest entry first
+    pub async fn blueprint_target_audit_list(
+        &self,
+        opctx: &OpContext,
+        blueprint_id: BlueprintUuid,
+    ) -> ListResultVec<BlueprintTargetAuditEntry> {
+        use nexus_db_schema::schema::bp_target_audit::dsl;
+
+        opctx.authorize(authz::Action::Read, &authz::BLUEPRINT_CONFIG).await?;
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let entries = dsl::bp_target_audit
+            .filter(dsl::blueprint_id.eq(to_db_typed_uuid(blueprint_id)))
+            .order_by((dsl::time_recorded, dsl::id))
+            .select(BpTargetAudit::as_select())
+            .load_async(&*conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+        Ok(entries.into_iter().map(BlueprintTargetAuditEntry::from).collect())
+    }
+}
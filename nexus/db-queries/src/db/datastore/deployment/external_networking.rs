@@ -5,6 +5,7 @@
 //! Manages allocation and deallocation of external networking resources
 //! required for blueprint realization
 
+use crate::authz;
 use crate::context::OpContext;
 use crate::db::DataStore;
 use crate::db::fixed_data::vpc_subnet::DNS_VPC_SUBNET;
@@ -14,7 +15,9 @@ use nexus_db_lookup::DbConnection;
 use nexus_db_model::IncompleteNetworkInterface;
 use nexus_db_model::IpPool;
 use nexus_sled_agent_shared::inventory::ZoneKind;
+use nexus_types::deployment::Blueprint;
 use nexus_types::deployment::BlueprintZoneConfig;
+use nexus_types::deployment::BlueprintZoneDisposition;
 use nexus_types::deployment::OmicronZoneExternalIp;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::IdentityMetadataCreateParams;
@@ -29,7 +32,105 @@ use slog::info;
 use slog::warn;
 use slog_error_chain::InlineErrorChain;
 
+/// External networking changes needed to realize a blueprint
+///
+/// See [`DataStore::blueprint_external_networking_changes()`].
+#[derive(Debug, Default)]
+pub struct ExternalNetworkingChanges {
+    /// external IPs to allocate to in-service zones
+    pub ips_to_allocate:
+        Vec<(OmicronZoneUuid, ZoneKind, OmicronZoneExternalIp)>,
+    /// vNICs to allocate to in-service zones
+    pub nics_to_allocate: Vec<(OmicronZoneUuid, ZoneKind, NetworkInterface)>,
+    /// external IPs still allocated to zones that are no longer in service
+    pub ips_to_deallocate:
+        Vec<(OmicronZoneUuid, ZoneKind, OmicronZoneExternalIp)>,
+    /// vNICs still allocated to zones that are no longer in service
+    pub nics_to_deallocate: Vec<(OmicronZoneUuid, ZoneKind, NetworkInterface)>,
+}
+
 impl DataStore {
+    /// Determine which external networking IPs and service vNICs
+    /// [`DataStore::blueprint_ensure_external_networking_resources()`] would
+    /// allocate or deallocate for `blueprint`, without changing anything
+    ///
+    /// Returns an error in the same cases where allocation would fail because a
+    /// zone already has conflicting resources allocated.
+    pub async fn blueprint_external_networking_changes(
+        &self,
+        opctx: &OpContext,
+        blueprint: &Blueprint,
+    ) -> Result<ExternalNetworkingChanges, Error> {
+        opctx.authorize(authz::Action::Read, &authz::BLUEPRINT_CONFIG).await?;
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let mut changes = ExternalNetworkingChanges::default();
+
+        for (_, z) in blueprint
+            .all_omicron_zones(|disposition| !disposition.is_in_service())
+        {
+            let Some((external_ip, nic)) = z.zone_type.external_networking()
+            else {
+                continue;
+            };
+            let kind = z.zone_type.kind();
+
+            let allocated_ips = self
+                .external_ip_list_service_on_connection(
+                    &conn,
+                    z.id.into_untyped_uuid(),
+                )
+                .await?;
+            if allocated_ips
+                .iter()
+                .any(|ip| ip.id == external_ip.id().into_untyped_uuid())
+            {
+                changes.ips_to_deallocate.push((z.id, kind, external_ip));
+            }
+
+            let allocated_nics = self
+                .service_list_network_interfaces_on_connection(
+                    &conn,
+                    z.id.into_untyped_uuid(),
+                )
+                .await?;
+            if allocated_nics.iter().any(|n| n.identity.id == nic.id) {
+                changes.nics_to_deallocate.push((z.id, kind, nic.clone()));
+            }
+        }
+
+        for (_, z) in
+            blueprint.all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+        {
+            let Some((external_ip, nic)) = z.zone_type.external_networking()
+            else {
+                continue;
+            };
+            let kind = z.zone_type.kind();
+            let log = opctx.log.new(slog::o!(
+                "action" => "plan-external-networking",
+                "zone_kind" => kind.report_str(),
+                "zone_id" => z.id.to_string(),
+            ));
+
+            if !self
+                .is_external_ip_already_allocated(
+                    &conn,
+                    z.id,
+                    external_ip,
+                    &log,
+                )
+                .await?
+            {
+                changes.ips_to_allocate.push((z.id, kind, external_ip));
+            }
+            if !self.is_nic_already_allocated(&conn, z.id, nic, &log).await? {
+                changes.nics_to_allocate.push((z.id, kind, nic.clone()));
+            }
+        }
+
+        Ok(changes)
+    }
+
     pub(super) async fn ensure_zone_external_networking_allocated_on_connection(
         &self,
         conn: &async_bb8_diesel::Connection<DbConnection>,
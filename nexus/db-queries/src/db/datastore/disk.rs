@@ -550,6 +550,62 @@ impl DataStore {
         Ok(updated)
     }
 
+    /// Changes the recorded size of a disk from `old_size` to `new_size`
+    ///
+    /// This does not change the disk's volume: the caller is responsible for
+    /// that.  If the disk is already `new_size`, this succeeds without making
+    /// any changes.  If it is neither `old_size` nor `new_size` (e.g., because
+    /// of a concurrent resize), this fails.
+    pub async fn disk_update_size(
+        &self,
+        opctx: &OpContext,
+        authz_disk: &authz::Disk,
+        old_size: db::model::ByteCount,
+        new_size: db::model::ByteCount,
+    ) -> UpdateResult<Disk> {
+        opctx.authorize(authz::Action::Modify, authz_disk).await?;
+
+        let disk_id = authz_disk.id();
+        use nexus_db_schema::schema::disk::dsl;
+        let result = diesel::update(dsl::disk)
+            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::id.eq(disk_id))
+            .filter(dsl::size_bytes.eq(old_size))
+            .set((
+                dsl::size_bytes.eq(new_size),
+                dsl::time_modified.eq(Utc::now()),
+            ))
+            .check_if_exists::<Disk>(disk_id)
+            .execute_and_check(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel(
+                    e,
+                    ErrorHandler::NotFoundByResource(authz_disk),
+                )
+            })?;
+
+        match result.status {
+            UpdateStatus::Updated => Ok(result.found),
+            UpdateStatus::NotUpdatedButExists => {
+                let disk = result.found;
+                if disk.time_deleted().is_none() && disk.size == new_size {
+                    // To maintain idempotency, if the disk has already been
+                    // resized, don't throw an error.
+                    Ok(disk)
+                } else if disk.time_deleted().is_some() {
+                    Err(authz_disk.not_found())
+                } else {
+                    Err(Error::conflict(format!(
+                        "disk size is {}, expected {}",
+                        disk.size.to_bytes(),
+                        old_size.to_bytes(),
+                    )))
+                }
+            }
+        }
+    }
+
     /// Fetches information about a Disk that the caller has previously fetched
     ///
     /// The only difference between this function and a new fetch by id is that
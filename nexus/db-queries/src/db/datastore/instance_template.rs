@@ -0,0 +1,181 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`DataStore`] methods on [`InstanceTemplate`]s.
+
+use super::DataStore;
+use crate::authz;
+use crate::context::OpContext;
+use crate::db::model::InstanceTemplate;
+use crate::db::model::Name;
+use crate::db::pagination::paginated;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::Utc;
+use diesel::OptionalExtension;
+use diesel::prelude::*;
+use nexus_db_errors::ErrorHandler;
+use nexus_db_errors::public_error_from_diesel;
+use nexus_types::identity::Resource;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::LookupType;
+use omicron_common::api::external::NameOrId;
+use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::http_pagination::PaginatedBy;
+use ref_cast::RefCast;
+use uuid::Uuid;
+
+impl DataStore {
+    pub async fn instance_template_create(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        template: InstanceTemplate,
+    ) -> CreateResult<InstanceTemplate> {
+        opctx.authorize(authz::Action::CreateChild, authz_project).await?;
+
+        use nexus_db_schema::schema::instance_template::dsl;
+
+        let name = template.name().as_str().to_string();
+        diesel::insert_into(dsl::instance_template)
+            .values(template)
+            .returning(InstanceTemplate::as_returning())
+            .get_result_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel(
+                    e,
+                    ErrorHandler::Conflict(
+                        ResourceType::InstanceTemplate,
+                        &name,
+                    ),
+                )
+            })
+    }
+
+    pub async fn instance_template_list(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        pagparams: &PaginatedBy<'_>,
+    ) -> ListResultVec<InstanceTemplate> {
+        opctx.authorize(authz::Action::ListChildren, authz_project).await?;
+
+        use nexus_db_schema::schema::instance_template::dsl;
+        match pagparams {
+            PaginatedBy::Id(pagparams) => {
+                paginated(dsl::instance_template, dsl::id, &pagparams)
+            }
+            PaginatedBy::Name(pagparams) => paginated(
+                dsl::instance_template,
+                dsl::name,
+                &pagparams.map_name(|n| Name::ref_cast(n)),
+            ),
+        }
+        .filter(dsl::time_deleted.is_null())
+        .filter(dsl::project_id.eq(authz_project.id()))
+        .select(InstanceTemplate::as_select())
+        .load_async(&*self.pool_connection_authorized(opctx).await?)
+        .await
+        .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Fetches an instance template in the given project by name or ID
+    pub async fn instance_template_fetch(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        template: &NameOrId,
+    ) -> LookupResult<InstanceTemplate> {
+        opctx.authorize(authz::Action::Read, authz_project).await?;
+
+        use nexus_db_schema::schema::instance_template::dsl;
+
+        let query = dsl::instance_template
+            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::time_deleted.is_null())
+            .into_boxed();
+        let (query, lookup_type) = match template {
+            NameOrId::Id(id) => {
+                (query.filter(dsl::id.eq(*id)), LookupType::ById(*id))
+            }
+            NameOrId::Name(name) => (
+                query.filter(dsl::name.eq(name.to_string())),
+                LookupType::ByName(name.to_string()),
+            ),
+        };
+
+        query
+            .select(InstanceTemplate::as_select())
+            .first_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?
+            .ok_or_else(|| {
+                lookup_type.into_not_found(ResourceType::InstanceTemplate)
+            })
+    }
+
+    /// Returns the ID of the project an instance template belongs to
+    ///
+    /// This does no authorization: it lets callers that only have the
+    /// template's ID find the project to authorize against.
+    pub async fn instance_template_project_id(
+        &self,
+        opctx: &OpContext,
+        template_id: Uuid,
+    ) -> LookupResult<Uuid> {
+        use nexus_db_schema::schema::instance_template::dsl;
+
+        dsl::instance_template
+            .filter(dsl::id.eq(template_id))
+            .filter(dsl::time_deleted.is_null())
+            .select(dsl::project_id)
+            .first_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?
+            .ok_or_else(|| {
+                Error::not_found_by_id(
+                    ResourceType::InstanceTemplate,
+                    &template_id,
+                )
+            })
+    }
+
+    /// Soft-deletes an instance template
+    ///
+    /// Instances created from the template are left alone.
+    pub async fn instance_template_delete(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        template_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::CreateChild, authz_project).await?;
+
+        use nexus_db_schema::schema::instance_template::dsl;
+
+        let updated = diesel::update(dsl::instance_template)
+            .filter(dsl::id.eq(template_id))
+            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::time_deleted.is_null())
+            .set(dsl::time_deleted.eq(Utc::now()))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        if updated == 0 {
+            return Err(Error::not_found_by_id(
+                ResourceType::InstanceTemplate,
+                &template_id,
+            ));
+        }
+
+        Ok(())
+    }
+}
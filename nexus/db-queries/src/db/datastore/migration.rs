@@ -109,6 +109,32 @@ impl DataStore {
             .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
     }
 
+    /// Records why a failed migration failed.
+    ///
+    /// Nothing is recorded if the migration doesn't exist or hasn't failed,
+    /// or if a reason has already been recorded for it. Returns whether the
+    /// reason was recorded.
+    pub async fn migration_set_failure_reason(
+        &self,
+        opctx: &OpContext,
+        migration_id: Uuid,
+        reason: &str,
+    ) -> UpdateResult<bool> {
+        let failed = MigrationState(nexus::MigrationState::Failed);
+        diesel::update(dsl::migration)
+            .filter(dsl::id.eq(migration_id))
+            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::failure_reason.is_null())
+            .filter(
+                dsl::source_state.eq(failed).or(dsl::target_state.eq(failed)),
+            )
+            .set(dsl::failure_reason.eq(reason.to_string()))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map(|rows_updated| rows_updated > 0)
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
     /// Unconditionally mark a migration record as deleted.
     pub async fn migration_mark_deleted(
         &self,
@@ -360,6 +386,57 @@ mod tests {
         logctx.cleanup_successful();
     }
 
+    #[tokio::test]
+    async fn test_migration_failure_reason() {
+        // Setup
+        let logctx = dev::test_setup_log("test_migration_failure_reason");
+        let db = TestDatabase::new_with_datastore(&logctx.log).await;
+        let (opctx, datastore) = (db.opctx(), db.datastore());
+        let authz_instance = create_test_instance(&datastore, &opctx).await;
+        let instance_id = InstanceUuid::from_untyped_uuid(authz_instance.id());
+
+        let migration = insert_migration(&datastore, &opctx, instance_id).await;
+
+        // A migration that hasn't failed doesn't get a reason.
+        let recorded = datastore
+            .migration_set_failure_reason(&opctx, migration.id, "too soon")
+            .await
+            .expect("must update migration");
+        assert!(!recorded);
+
+        datastore
+            .migration_mark_failed(&opctx, migration.id)
+            .await
+            .expect("must mark migration failed");
+        let recorded = datastore
+            .migration_set_failure_reason(&opctx, migration.id, "it broke")
+            .await
+            .expect("must update migration");
+        assert!(recorded);
+
+        // The first reason sticks.
+        let recorded = datastore
+            .migration_set_failure_reason(&opctx, migration.id, "again")
+            .await
+            .expect("must update migration");
+        assert!(!recorded);
+
+        let list = datastore
+            .instance_list_migrations(
+                &opctx,
+                instance_id,
+                &DataPageParams::max_page(),
+            )
+            .await
+            .expect("must list migrations");
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].failure_reason.as_deref(), Some("it broke"));
+
+        // Clean up.
+        db.terminate().await;
+        logctx.cleanup_successful();
+    }
+
     #[track_caller]
     fn assert_all_migrations_found(
         expected: &[&Migration],
@@ -66,6 +66,7 @@ mod external_ip;
 mod identity_provider;
 mod image;
 pub mod instance;
+mod instance_template;
 mod inventory;
 mod ip_pool;
 mod ipv4_nat_entry;
@@ -93,6 +94,9 @@ mod silo_user;
 mod sled;
 mod sled_instance;
 mod snapshot;
+mod snapshot_export;
+mod snapshot_group;
+mod snapshot_policy;
 mod ssh_key;
 mod support_bundle;
 mod switch;
@@ -109,9 +113,11 @@ mod vmm;
 mod volume;
 mod volume_repair;
 mod vpc;
+mod zone_placement_policy;
 mod zpool;
 
 pub use address_lot::AddressLotCreateResult;
+pub use deployment::ExternalNetworkingChanges;
 pub use dns::DataStoreDnsTest;
 pub use dns::DnsVersionUpdateBuilder;
 pub use instance::{
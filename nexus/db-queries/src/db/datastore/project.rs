@@ -220,10 +220,13 @@ impl DataStore {
     }
 
     generate_fn_to_ensure_none_in_project!(instance, name, String);
+    generate_fn_to_ensure_none_in_project!(instance_template, name, String);
     generate_fn_to_ensure_none_in_project!(disk, name, String);
     generate_fn_to_ensure_none_in_project!(floating_ip, name, String);
     generate_fn_to_ensure_none_in_project!(project_image, name, String);
     generate_fn_to_ensure_none_in_project!(snapshot, name, String);
+    generate_fn_to_ensure_none_in_project!(snapshot_group, name, String);
+    generate_fn_to_ensure_none_in_project!(snapshot_policy, name, String);
     generate_fn_to_ensure_none_in_project!(vpc, name, String);
     generate_fn_to_ensure_none_in_project!(affinity_group, name, String);
     generate_fn_to_ensure_none_in_project!(anti_affinity_group, name, String);
@@ -239,10 +242,15 @@ impl DataStore {
 
         // Verify that child resources do not exist.
         self.ensure_no_instances_in_project(opctx, authz_project).await?;
+        self.ensure_no_instance_templates_in_project(opctx, authz_project)
+            .await?;
         self.ensure_no_disks_in_project(opctx, authz_project).await?;
         self.ensure_no_floating_ips_in_project(opctx, authz_project).await?;
         self.ensure_no_project_images_in_project(opctx, authz_project).await?;
         self.ensure_no_snapshots_in_project(opctx, authz_project).await?;
+        self.ensure_no_snapshot_groups_in_project(opctx, authz_project).await?;
+        self.ensure_no_snapshot_policys_in_project(opctx, authz_project)
+            .await?;
         self.ensure_no_vpcs_in_project(opctx, authz_project).await?;
         self.ensure_no_affinity_groups_in_project(opctx, authz_project).await?;
         self.ensure_no_anti_affinity_groups_in_project(opctx, authz_project)
@@ -1068,6 +1068,7 @@ mod test {
                     time_created: Utc::now(),
                     creator: "test suite".to_string(),
                     comment: "test suite".to_string(),
+                    planning_report: None,
                 },
                 physical_disks: vec![],
                 zpools: vec![],
@@ -1556,6 +1557,7 @@ mod test {
             time_created: now_db_precision(),
             creator: "test suite".to_string(),
             comment: "test blueprint".to_string(),
+            planning_report: None,
         };
 
         let rack = datastore
@@ -1816,6 +1818,7 @@ mod test {
             time_created: now_db_precision(),
             creator: "test suite".to_string(),
             comment: "test blueprint".to_string(),
+            planning_report: None,
         };
 
         let rack = datastore
@@ -2025,6 +2028,7 @@ mod test {
             time_created: now_db_precision(),
             creator: "test suite".to_string(),
             comment: "test blueprint".to_string(),
+            planning_report: None,
         };
 
         let result = datastore
@@ -2164,6 +2168,7 @@ mod test {
             time_created: now_db_precision(),
             creator: "test suite".to_string(),
             comment: "test blueprint".to_string(),
+            planning_report: None,
         };
 
         let result = datastore
@@ -149,6 +149,14 @@ impl DataStore {
 
                 Ok(db_image.block_size)
             }
+            params::DiskSource::Disk { disk_id } => {
+                let (.., db_disk) = LookupPath::new(opctx, self)
+                    .disk_id(*disk_id)
+                    .fetch()
+                    .await?;
+
+                Ok(db_disk.block_size)
+            }
             params::DiskSource::ImportingBlocks { block_size } => {
                 Ok(db::model::BlockSize::try_from(*block_size)
                     .map_err(|e| Error::invalid_request(&e.to_string()))?)
@@ -470,6 +470,11 @@ impl DataStore {
             .flatten()
             .map(|id| SledUuid::from_untyped_uuid(*id))
             .collect();
+        let excluded_sleds: HashSet<SledUuid> = constraints
+            .cannot_select_from()
+            .iter()
+            .map(|id| SledUuid::from_untyped_uuid(*id))
+            .collect();
 
         // Query for the set of possible sleds using a CTE.
         //
@@ -505,6 +510,7 @@ impl DataStore {
             if fits
                 && (must_use_sleds.is_empty()
                     || must_use_sleds.contains(&sled_id))
+                && !excluded_sleds.contains(&sled_id)
             {
                 sled_targets.insert(sled_id);
             }
@@ -1328,6 +1334,67 @@ pub(in crate::db::datastore) mod test {
         logctx.cleanup_successful();
     }
 
+    #[tokio::test]
+    async fn sled_reservation_create_excluded_sleds() {
+        let logctx =
+            dev::test_setup_log("sled_reservation_create_excluded_sleds");
+        let db = TestDatabase::new_with_datastore(&logctx.log).await;
+        let (opctx, datastore) = (db.opctx(), db.datastore());
+
+        let (excluded_sled, _) =
+            datastore.sled_upsert(test_new_sled_update()).await.unwrap();
+        let resources = db::model::Resources::new(
+            1,
+            ByteCount::try_from(1024).unwrap(),
+            ByteCount::try_from(1024).unwrap(),
+        );
+
+        // With only the excluded sled available, nothing can be reserved.
+        let constraints = db::model::SledReservationConstraintBuilder::new()
+            .cannot_select_from(&[excluded_sled.id()])
+            .build();
+        let error = datastore
+            .sled_reservation_create(
+                &opctx,
+                InstanceUuid::new_v4(),
+                PropolisUuid::new_v4(),
+                resources.clone(),
+                constraints,
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, external::Error::InsufficientCapacity { .. }));
+
+        // Once another sled is added, it's always chosen.
+        let (other_sled, _) =
+            datastore.sled_upsert(test_new_sled_update()).await.unwrap();
+        for _ in 0..10 {
+            let constraints =
+                db::model::SledReservationConstraintBuilder::new()
+                    .cannot_select_from(&[excluded_sled.id()])
+                    .build();
+            let resource = datastore
+                .sled_reservation_create(
+                    &opctx,
+                    InstanceUuid::new_v4(),
+                    PropolisUuid::new_v4(),
+                    resources.clone(),
+                    constraints,
+                )
+                .await
+                .unwrap();
+            assert_eq!(resource.sled_id.into_untyped_uuid(), other_sled.id());
+
+            datastore
+                .sled_reservation_delete(&opctx, resource.id.into())
+                .await
+                .unwrap();
+        }
+
+        db.terminate().await;
+        logctx.cleanup_successful();
+    }
+
     // Utilities to help with Affinity Testing
 
     // Create a resource request that will entirely fill a sled.
@@ -0,0 +1,179 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`DataStore`] methods on [`SnapshotExport`]s.
+
+use super::DataStore;
+use crate::authz;
+use crate::context::OpContext;
+use crate::db::model::SnapshotExport;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::Utc;
+use diesel::OptionalExtension;
+use diesel::prelude::*;
+use nexus_db_errors::ErrorHandler;
+use nexus_db_errors::public_error_from_diesel;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::LookupType;
+use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::UpdateResult;
+use omicron_common::bail_unless;
+use uuid::Uuid;
+
+impl DataStore {
+    /// Records a new export of a snapshot
+    ///
+    /// This is idempotent: if an export with the same id already exists, that
+    /// record is returned unchanged.
+    pub async fn snapshot_export_create(
+        &self,
+        opctx: &OpContext,
+        authz_snapshot: &authz::Snapshot,
+        export: SnapshotExport,
+    ) -> CreateResult<SnapshotExport> {
+        opctx.authorize(authz::Action::Modify, authz_snapshot).await?;
+        bail_unless!(
+            export.snapshot_id == authz_snapshot.id(),
+            "export {} is for snapshot {}, not {}",
+            export.id,
+            export.snapshot_id,
+            authz_snapshot.id()
+        );
+
+        use nexus_db_schema::schema::snapshot_export::dsl;
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let export_id = export.id;
+
+        diesel::insert_into(dsl::snapshot_export)
+            .values(export)
+            .on_conflict(dsl::id)
+            .do_nothing()
+            .execute_async(&*conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        dsl::snapshot_export
+            .filter(dsl::id.eq(export_id))
+            .select(SnapshotExport::as_select())
+            .get_result_async(&*conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Fetches an export of the given snapshot that has not been deleted
+    pub async fn snapshot_export_fetch(
+        &self,
+        opctx: &OpContext,
+        authz_snapshot: &authz::Snapshot,
+        export_id: Uuid,
+    ) -> LookupResult<SnapshotExport> {
+        opctx.authorize(authz::Action::Read, authz_snapshot).await?;
+
+        use nexus_db_schema::schema::snapshot_export::dsl;
+
+        dsl::snapshot_export
+            .filter(dsl::id.eq(export_id))
+            .filter(dsl::snapshot_id.eq(authz_snapshot.id()))
+            .filter(dsl::time_deleted.is_null())
+            .select(SnapshotExport::as_select())
+            .first_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?
+            .ok_or_else(|| {
+                Error::not_found_by_id(ResourceType::SnapshotExport, &export_id)
+            })
+    }
+
+    /// Lists the exports of a snapshot that have not been deleted
+    pub async fn snapshot_export_list_for_snapshot(
+        &self,
+        opctx: &OpContext,
+        authz_snapshot: &authz::Snapshot,
+    ) -> ListResultVec<SnapshotExport> {
+        opctx.authorize(authz::Action::Read, authz_snapshot).await?;
+
+        use nexus_db_schema::schema::snapshot_export::dsl;
+
+        dsl::snapshot_export
+            .filter(dsl::snapshot_id.eq(authz_snapshot.id()))
+            .filter(dsl::time_deleted.is_null())
+            .select(SnapshotExport::as_select())
+            .load_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Soft-deletes an export
+    ///
+    /// Deleting an export that was already deleted is not an error.
+    pub async fn snapshot_export_delete(
+        &self,
+        opctx: &OpContext,
+        authz_snapshot: &authz::Snapshot,
+        export_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Modify, authz_snapshot).await?;
+
+        use nexus_db_schema::schema::snapshot_export::dsl;
+
+        diesel::update(dsl::snapshot_export)
+            .filter(dsl::id.eq(export_id))
+            .filter(dsl::snapshot_id.eq(authz_snapshot.id()))
+            .filter(dsl::time_deleted.is_null())
+            .set(dsl::time_deleted.eq(Utc::now()))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel(
+                    e,
+                    ErrorHandler::NotFoundByLookup(
+                        ResourceType::SnapshotExport,
+                        LookupType::ById(export_id),
+                    ),
+                )
+            })?;
+
+        Ok(())
+    }
+
+    /// Adds `bytes` to the number of bytes streamed out of an export
+    ///
+    /// This is a running total across all downloads of the export, so it can
+    /// exceed the export's size if the image is fetched more than once.
+    pub async fn snapshot_export_add_bytes_exported(
+        &self,
+        opctx: &OpContext,
+        authz_snapshot: &authz::Snapshot,
+        export_id: Uuid,
+        bytes: u64,
+    ) -> UpdateResult<()> {
+        opctx.authorize(authz::Action::Read, authz_snapshot).await?;
+
+        let bytes = i64::try_from(bytes).map_err(|_| {
+            Error::internal_error(&format!("byte count {bytes} out of range"))
+        })?;
+
+        use nexus_db_schema::schema::snapshot_export::dsl;
+
+        diesel::update(dsl::snapshot_export)
+            .filter(dsl::id.eq(export_id))
+            .filter(dsl::snapshot_id.eq(authz_snapshot.id()))
+            .filter(dsl::time_deleted.is_null())
+            .set((
+                dsl::bytes_exported.eq(dsl::bytes_exported + bytes),
+                dsl::time_modified.eq(Utc::now()),
+            ))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`DataStore`] methods on [`SnapshotGroup`]s.
+
+use super::DataStore;
+use crate::authz;
+use crate::context::OpContext;
+use crate::db::model::Name;
+use crate::db::model::SnapshotGroup;
+use crate::db::model::SnapshotGroupMember;
+use crate::db::model::SnapshotGroupState;
+use crate::db::pagination::paginated;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::Utc;
+use diesel::OptionalExtension;
+use diesel::prelude::*;
+use nexus_db_errors::ErrorHandler;
+use nexus_db_errors::OptionalError;
+use nexus_db_errors::public_error_from_diesel;
+use nexus_types::identity::Resource;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::LookupType;
+use omicron_common::api::external::NameOrId;
+use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::UpdateResult;
+use omicron_common::api::external::http_pagination::PaginatedBy;
+use ref_cast::RefCast;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+impl DataStore {
+    /// Creates a snapshot group and its members
+    ///
+    /// This is idempotent, so that saga nodes can be replayed: if a group
+    /// with the same ID already exists, it's returned unchanged.
+    pub async fn snapshot_group_create(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        group: SnapshotGroup,
+        members: Vec<SnapshotGroupMember>,
+    ) -> CreateResult<SnapshotGroup> {
+        opctx.authorize(authz::Action::CreateChild, authz_project).await?;
+
+        let err = OptionalError::new();
+        let conn = self.pool_connection_authorized(opctx).await?;
+
+        self.transaction_retry_wrapper("snapshot_group_create")
+            .transaction(&conn, |conn| {
+                let err = err.clone();
+                let group = group.clone();
+                let members = members.clone();
+                async move {
+                    use nexus_db_schema::schema::snapshot_group::dsl;
+                    use nexus_db_schema::schema::snapshot_group_member::dsl as member_dsl;
+
+                    let group_id = group.id();
+                    let name = group.name().as_str().to_string();
+                    diesel::insert_into(dsl::snapshot_group)
+                        .values(group)
+                        .on_conflict(dsl::id)
+                        .do_nothing()
+                        .execute_async(&conn)
+                        .await
+                        .map_err(|e| {
+                            err.bail_retryable_or_else(e, |e| {
+                                public_error_from_diesel(
+                                    e,
+                                    ErrorHandler::Conflict(
+                                        ResourceType::SnapshotGroup,
+                                        &name,
+                                    ),
+                                )
+                            })
+                        })?;
+
+                    diesel::insert_into(member_dsl::snapshot_group_member)
+                        .values(members)
+                        .on_conflict((
+                            member_dsl::group_id,
+                            member_dsl::snapshot_id,
+                        ))
+                        .do_nothing()
+                        .execute_async(&conn)
+                        .await?;
+
+                    let group = dsl::snapshot_group
+                        .filter(dsl::id.eq(group_id))
+                        .select(SnapshotGroup::as_select())
+                        .get_result_async(&conn)
+                        .await?;
+                    Ok(group)
+                }
+            })
+            .await
+            .map_err(|e| {
+                if let Some(err) = err.take() {
+                    return err;
+                }
+                public_error_from_diesel(e, ErrorHandler::Server)
+            })
+    }
+
+    /// Lists the snapshot groups in a project, along with their members
+    pub async fn snapshot_group_list(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        pagparams: &PaginatedBy<'_>,
+    ) -> ListResultVec<(SnapshotGroup, Vec<SnapshotGroupMember>)> {
+        opctx.authorize(authz::Action::ListChildren, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_group::dsl;
+        use nexus_db_schema::schema::snapshot_group_member::dsl as member_dsl;
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let groups = match pagparams {
+            PaginatedBy::Id(pagparams) => {
+                paginated(dsl::snapshot_group, dsl::id, &pagparams)
+            }
+            PaginatedBy::Name(pagparams) => paginated(
+                dsl::snapshot_group,
+                dsl::name,
+                &pagparams.map_name(|n| Name::ref_cast(n)),
+            ),
+        }
+        .filter(dsl::time_deleted.is_null())
+        .filter(dsl::project_id.eq(authz_project.id()))
+        .select(SnapshotGroup::as_select())
+        .load_async(&*conn)
+        .await
+        .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        // Fetch the members of every group in the page at once.
+        let group_ids: Vec<Uuid> = groups.iter().map(|g| g.id()).collect();
+        let mut members_by_group: BTreeMap<Uuid, Vec<SnapshotGroupMember>> =
+            BTreeMap::new();
+        for member in member_dsl::snapshot_group_member
+            .filter(member_dsl::group_id.eq_any(group_ids))
+            .order((member_dsl::group_id, member_dsl::disk_name))
+            .select(SnapshotGroupMember::as_select())
+            .load_async(&*conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?
+        {
+            members_by_group.entry(member.group_id).or_default().push(member);
+        }
+
+        Ok(groups
+            .into_iter()
+            .map(|group| {
+                let members =
+                    members_by_group.remove(&group.id()).unwrap_or_default();
+                (group, members)
+            })
+            .collect())
+    }
+
+    /// Fetches a snapshot group in the given project by name or ID
+    pub async fn snapshot_group_fetch(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        group: &NameOrId,
+    ) -> LookupResult<SnapshotGroup> {
+        opctx.authorize(authz::Action::Read, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_group::dsl;
+
+        let query = dsl::snapshot_group
+            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::time_deleted.is_null())
+            .into_boxed();
+        let (query, lookup_type) = match group {
+            NameOrId::Id(id) => {
+                (query.filter(dsl::id.eq(*id)), LookupType::ById(*id))
+            }
+            NameOrId::Name(name) => (
+                query.filter(dsl::name.eq(name.to_string())),
+                LookupType::ByName(name.to_string()),
+            ),
+        };
+
+        query
+            .select(SnapshotGroup::as_select())
+            .first_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?
+            .ok_or_else(|| {
+                lookup_type.into_not_found(ResourceType::SnapshotGroup)
+            })
+    }
+
+    /// Lists the members of a snapshot group, ordered by disk name
+    pub async fn snapshot_group_member_list(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        group_id: Uuid,
+    ) -> ListResultVec<SnapshotGroupMember> {
+        opctx.authorize(authz::Action::Read, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_group_member::dsl;
+
+        dsl::snapshot_group_member
+            .filter(dsl::group_id.eq(group_id))
+            .order(dsl::disk_name)
+            .select(SnapshotGroupMember::as_select())
+            .load_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Returns the ID of the project a snapshot group belongs to
+    ///
+    /// This does no authorization: it lets callers that only have the
+    /// group's ID find the project to authorize against.
+    pub async fn snapshot_group_project_id(
+        &self,
+        opctx: &OpContext,
+        group_id: Uuid,
+    ) -> LookupResult<Uuid> {
+        use nexus_db_schema::schema::snapshot_group::dsl;
+
+        dsl::snapshot_group
+            .filter(dsl::id.eq(group_id))
+            .filter(dsl::time_deleted.is_null())
+            .select(dsl::project_id)
+            .first_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?
+            .ok_or_else(|| {
+                Error::not_found_by_id(ResourceType::SnapshotGroup, &group_id)
+            })
+    }
+
+    /// Marks a snapshot group as ready, once all of its snapshots are
+    pub async fn snapshot_group_set_ready(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        group_id: Uuid,
+    ) -> UpdateResult<()> {
+        opctx.authorize(authz::Action::CreateChild, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_group::dsl;
+
+        let updated = diesel::update(dsl::snapshot_group)
+            .filter(dsl::id.eq(group_id))
+            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::time_deleted.is_null())
+            .set((
+                dsl::state.eq(SnapshotGroupState::Ready),
+                dsl::time_modified.eq(Utc::now()),
+            ))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        if updated == 0 {
+            return Err(Error::not_found_by_id(
+                ResourceType::SnapshotGroup,
+                &group_id,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Soft-deletes a snapshot group
+    ///
+    /// The group's snapshots must have been deleted first.
+    pub async fn snapshot_group_delete(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        group_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::CreateChild, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_group::dsl;
+
+        let updated = diesel::update(dsl::snapshot_group)
+            .filter(dsl::id.eq(group_id))
+            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::time_deleted.is_null())
+            .set(dsl::time_deleted.eq(Utc::now()))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        if updated == 0 {
+            return Err(Error::not_found_by_id(
+                ResourceType::SnapshotGroup,
+                &group_id,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Removes all trace of a snapshot group that failed to be created
+    ///
+    /// This is idempotent, and only meant for unwinding the snapshot group
+    /// create saga.
+    pub async fn snapshot_group_hard_delete(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        group_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::CreateChild, authz_project).await?;
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+
+        self.transaction_retry_wrapper("snapshot_group_hard_delete")
+            .transaction(&conn, |conn| async move {
+                use nexus_db_schema::schema::snapshot_group::dsl;
+                use nexus_db_schema::schema::snapshot_group_member::dsl as member_dsl;
+
+                diesel::delete(member_dsl::snapshot_group_member)
+                    .filter(member_dsl::group_id.eq(group_id))
+                    .execute_async(&conn)
+                    .await?;
+                diesel::delete(dsl::snapshot_group)
+                    .filter(dsl::id.eq(group_id))
+                    .execute_async(&conn)
+                    .await?;
+                Ok(())
+            })
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+}
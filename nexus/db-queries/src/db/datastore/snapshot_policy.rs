@@ -0,0 +1,444 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! [`DataStore`] methods on [`SnapshotPolicy`]s.
+
+use super::DataStore;
+use crate::authz;
+use crate::context::OpContext;
+use crate::db::model::Disk;
+use crate::db::model::Name;
+use crate::db::model::Snapshot;
+use crate::db::model::SnapshotPolicy;
+use crate::db::model::SnapshotPolicySnapshot;
+use crate::db::pagination::paginated;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::Utc;
+use diesel::OptionalExtension;
+use diesel::prelude::*;
+use nexus_db_errors::ErrorHandler;
+use nexus_db_errors::public_error_from_diesel;
+use nexus_types::identity::Resource;
+use omicron_common::api::external;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DataPageParams;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::LookupType;
+use omicron_common::api::external::NameOrId;
+use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::UpdateResult;
+use omicron_common::api::external::http_pagination::PaginatedBy;
+use ref_cast::RefCast;
+use uuid::Uuid;
+
+impl DataStore {
+    pub async fn snapshot_policy_create(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        policy: SnapshotPolicy,
+    ) -> CreateResult<SnapshotPolicy> {
+        opctx.authorize(authz::Action::CreateChild, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_policy::dsl;
+
+        let name = policy.name().as_str().to_string();
+        diesel::insert_into(dsl::snapshot_policy)
+            .values(policy)
+            .returning(SnapshotPolicy::as_returning())
+            .get_result_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel(
+                    e,
+                    ErrorHandler::Conflict(ResourceType::SnapshotPolicy, &name),
+                )
+            })
+    }
+
+    pub async fn snapshot_policy_list(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        pagparams: &PaginatedBy<'_>,
+    ) -> ListResultVec<SnapshotPolicy> {
+        opctx.authorize(authz::Action::ListChildren, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_policy::dsl;
+        match pagparams {
+            PaginatedBy::Id(pagparams) => {
+                paginated(dsl::snapshot_policy, dsl::id, &pagparams)
+            }
+            PaginatedBy::Name(pagparams) => paginated(
+                dsl::snapshot_policy,
+                dsl::name,
+                &pagparams.map_name(|n| Name::ref_cast(n)),
+            ),
+        }
+        .filter(dsl::time_deleted.is_null())
+        .filter(dsl::project_id.eq(authz_project.id()))
+        .select(SnapshotPolicy::as_select())
+        .load_async(&*self.pool_connection_authorized(opctx).await?)
+        .await
+        .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Fetches a snapshot policy in the given project by name or ID
+    pub async fn snapshot_policy_fetch(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        policy: &NameOrId,
+    ) -> LookupResult<SnapshotPolicy> {
+        opctx.authorize(authz::Action::Read, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_policy::dsl;
+
+        let query = dsl::snapshot_policy
+            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::time_deleted.is_null())
+            .into_boxed();
+        let (query, lookup_type) = match policy {
+            NameOrId::Id(id) => {
+                (query.filter(dsl::id.eq(*id)), LookupType::ById(*id))
+            }
+            NameOrId::Name(name) => (
+                query.filter(dsl::name.eq(name.to_string())),
+                LookupType::ByName(name.to_string()),
+            ),
+        };
+
+        query
+            .select(SnapshotPolicy::as_select())
+            .first_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?
+            .ok_or_else(|| {
+                lookup_type.into_not_found(ResourceType::SnapshotPolicy)
+            })
+    }
+
+    /// Returns the ID of the project a snapshot policy belongs to
+    ///
+    /// This does no authorization: it lets callers that only have the
+    /// policy's ID find the project to authorize against.
+    pub async fn snapshot_policy_project_id(
+        &self,
+        opctx: &OpContext,
+        policy_id: Uuid,
+    ) -> LookupResult<Uuid> {
+        use nexus_db_schema::schema::snapshot_policy::dsl;
+
+        dsl::snapshot_policy
+            .filter(dsl::id.eq(policy_id))
+            .filter(dsl::time_deleted.is_null())
+            .select(dsl::project_id)
+            .first_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .optional()
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?
+            .ok_or_else(|| {
+                Error::not_found_by_id(ResourceType::SnapshotPolicy, &policy_id)
+            })
+    }
+
+    /// Soft-deletes a snapshot policy
+    ///
+    /// Snapshots the policy has already taken are left alone.
+    pub async fn snapshot_policy_delete(
+        &self,
+        opctx: &OpContext,
+        authz_project: &authz::Project,
+        policy_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::CreateChild, authz_project).await?;
+
+        use nexus_db_schema::schema::snapshot_policy::dsl;
+
+        let updated = diesel::update(dsl::snapshot_policy)
+            .filter(dsl::id.eq(policy_id))
+            .filter(dsl::project_id.eq(authz_project.id()))
+            .filter(dsl::time_deleted.is_null())
+            .set(dsl::time_deleted.eq(Utc::now()))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        if updated == 0 {
+            return Err(Error::not_found_by_id(
+                ResourceType::SnapshotPolicy,
+                &policy_id,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Lists the snapshot policies of every project, for the background task
+    /// that carries them out
+    pub async fn snapshot_policy_list_all(
+        &self,
+        opctx: &OpContext,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<SnapshotPolicy> {
+        opctx.authorize(authz::Action::ListChildren, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::snapshot_policy::dsl;
+
+        paginated(dsl::snapshot_policy, dsl::id, pagparams)
+            .filter(dsl::time_deleted.is_null())
+            .select(SnapshotPolicy::as_select())
+            .load_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Lists the disks a snapshot policy applies to
+    ///
+    /// Only disks that are attached or detached are returned: disks in any
+    /// other state are being changed in ways that snapshots must not race
+    /// with, and are picked up by a later run of the policy.
+    pub async fn snapshot_policy_disk_list(
+        &self,
+        opctx: &OpContext,
+        policy: &SnapshotPolicy,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<Disk> {
+        opctx.authorize(authz::Action::ListChildren, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::disk::dsl;
+
+        let mut query = paginated(dsl::disk, dsl::id, pagparams)
+            .filter(dsl::project_id.eq(policy.project_id))
+            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::disk_state.eq_any(vec![
+                external::DiskState::Detached.label(),
+                external::DiskState::Attached(Uuid::nil()).label(),
+            ]));
+        if let Some(disk_id) = policy.disk_id {
+            query = query.filter(dsl::id.eq(disk_id));
+        }
+
+        query
+            .select(Disk::as_select())
+            .load_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Claims the snapshot of a disk scheduled for `record.time_scheduled`
+    ///
+    /// Returns false if the snapshot was already claimed, by this Nexus or
+    /// another.
+    pub async fn snapshot_policy_snapshot_claim(
+        &self,
+        opctx: &OpContext,
+        record: SnapshotPolicySnapshot,
+    ) -> Result<bool, Error> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::snapshot_policy_snapshot::dsl;
+
+        let inserted = diesel::insert_into(dsl::snapshot_policy_snapshot)
+            .values(record)
+            .on_conflict((dsl::policy_id, dsl::disk_id, dsl::time_scheduled))
+            .do_nothing()
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        Ok(inserted > 0)
+    }
+
+    /// Records the snapshot taken for a claimed scheduled snapshot
+    pub async fn snapshot_policy_snapshot_set_id(
+        &self,
+        opctx: &OpContext,
+        record: &SnapshotPolicySnapshot,
+        snapshot_id: Uuid,
+    ) -> UpdateResult<()> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::snapshot_policy_snapshot::dsl;
+
+        diesel::update(dsl::snapshot_policy_snapshot)
+            .filter(dsl::policy_id.eq(record.policy_id))
+            .filter(dsl::disk_id.eq(record.disk_id))
+            .filter(dsl::time_scheduled.eq(record.time_scheduled))
+            .set(dsl::snapshot_id.eq(snapshot_id))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        Ok(())
+    }
+
+    /// Lists the snapshots of a disk taken by a policy that the policy no
+    /// longer retains, oldest first
+    pub async fn snapshot_policy_expired_snapshots(
+        &self,
+        opctx: &OpContext,
+        policy: &SnapshotPolicy,
+        disk_id: Uuid,
+    ) -> ListResultVec<Snapshot> {
+        opctx.authorize(authz::Action::ListChildren, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::snapshot;
+        use nexus_db_schema::schema::snapshot_policy_snapshot::dsl;
+
+        let mut expired = dsl::snapshot_policy_snapshot
+            .inner_join(
+                snapshot::table
+                    .on(snapshot::id.nullable().eq(dsl::snapshot_id)),
+            )
+            .filter(dsl::policy_id.eq(policy.id()))
+            .filter(dsl::disk_id.eq(disk_id))
+            .filter(snapshot::time_deleted.is_null())
+            .order(dsl::time_scheduled.desc())
+            .offset(i64::from(*policy.retention_count))
+            .select(Snapshot::as_select())
+            .load_async::<Snapshot>(
+                &*self.pool_connection_authorized(opctx).await?,
+            )
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        expired.reverse();
+        Ok(expired)
+    }
+
+    /// Records the outcome of a run of a snapshot policy
+    pub async fn snapshot_policy_record_run(
+        &self,
+        opctx: &OpContext,
+        policy_id: Uuid,
+        error: Option<String>,
+    ) -> UpdateResult<()> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::snapshot_policy::dsl;
+
+        diesel::update(dsl::snapshot_policy)
+            .filter(dsl::id.eq(policy_id))
+            .set((dsl::time_last_run.eq(Utc::now()), dsl::last_error.eq(error)))
+            .execute_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::pub_test_utils::TestDatabase;
+    use crate::db::pub_test_utils::helpers::create_project;
+    use chrono::DateTime;
+    use nexus_types::external_api::params;
+    use omicron_common::api::external::IdentityMetadataCreateParams;
+    use omicron_test_utils::dev;
+
+    #[tokio::test]
+    async fn test_snapshot_policy_claim_is_exclusive() {
+        let logctx =
+            dev::test_setup_log("test_snapshot_policy_claim_is_exclusive");
+        let db = TestDatabase::new_with_datastore(&logctx.log).await;
+        let (opctx, datastore) = (db.opctx(), db.datastore());
+
+        let (authz_project, _) =
+            create_project(opctx, datastore, "policy-project").await;
+
+        let policy = datastore
+            .snapshot_policy_create(
+                opctx,
+                &authz_project,
+                SnapshotPolicy::new(
+                    authz_project.id(),
+                    None,
+                    params::SnapshotPolicyCreate {
+                        identity: IdentityMetadataCreateParams {
+                            name: "hourly".parse().unwrap(),
+                            description: String::from("every hour"),
+                        },
+                        disk: None,
+                        interval_secs: 3600,
+                        retention_count: 24,
+                    },
+                ),
+            )
+            .await
+            .unwrap();
+
+        let disk_id = Uuid::new_v4();
+        let slot = DateTime::from_timestamp(3600 * 1000, 0).unwrap();
+        let record = SnapshotPolicySnapshot::new(policy.id(), disk_id, slot);
+
+        // Only the first claim of a scheduled snapshot succeeds.
+        assert!(
+            datastore
+                .snapshot_policy_snapshot_claim(opctx, record.clone())
+                .await
+                .unwrap()
+        );
+        assert!(
+            !datastore
+                .snapshot_policy_snapshot_claim(opctx, record.clone())
+                .await
+                .unwrap()
+        );
+
+        // The next scheduled snapshot is separate.
+        let next = SnapshotPolicySnapshot::new(
+            policy.id(),
+            disk_id,
+            slot + chrono::Duration::seconds(3600),
+        );
+        assert!(
+            datastore
+                .snapshot_policy_snapshot_claim(opctx, next)
+                .await
+                .unwrap()
+        );
+
+        // The policy can be found by name, and is gone once deleted.
+        let fetched = datastore
+            .snapshot_policy_fetch(
+                opctx,
+                &authz_project,
+                &NameOrId::Name("hourly".parse().unwrap()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(fetched.id(), policy.id());
+        assert_eq!(
+            datastore
+                .snapshot_policy_project_id(opctx, policy.id())
+                .await
+                .unwrap(),
+            authz_project.id()
+        );
+
+        datastore
+            .snapshot_policy_delete(opctx, &authz_project, policy.id())
+            .await
+            .unwrap();
+        let error = datastore
+            .snapshot_policy_fetch(
+                opctx,
+                &authz_project,
+                &NameOrId::Id(policy.id()),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::ObjectNotFound { .. }));
+
+        db.terminate().await;
+        logctx.cleanup_successful();
+    }
+}
@@ -12,22 +12,32 @@ use crate::context::OpContext;
 use crate::db::model::SemverVersion;
 use crate::db::pagination::paginated;
 use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::{DateTime, Utc};
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
 use nexus_db_errors::OptionalError;
 use nexus_db_errors::{ErrorHandler, public_error_from_diesel};
 use nexus_db_lookup::DbConnection;
-use nexus_db_model::{ArtifactHash, TufArtifact, TufRepo, TufRepoDescription};
+use nexus_db_model::{
+    ArtifactHash, TufArtifact, TufRepo, TufRepoDescription, TufRepoUpload,
+    TufRepoUploadChunk,
+};
 use omicron_common::api::external::{
-    self, CreateResult, DataPageParams, Generation, ListResultVec,
-    LookupResult, LookupType, ResourceType, TufRepoInsertStatus,
+    self, CreateResult, DataPageParams, DeleteResult, Generation,
+    ListResultVec, LookupResult, LookupType, ResourceType, TufRepoInsertStatus,
+    UpdateResult,
 };
+use omicron_uuid_kinds::GenericUuid;
 use omicron_uuid_kinds::TufRepoKind;
 use omicron_uuid_kinds::TypedUuid;
 use swrite::{SWrite, swrite};
 use tufaceous_artifact::ArtifactVersion;
 use uuid::Uuid;
 
+/// How many pieces of a resumable TUF repository upload to delete from the
+/// database at once.
+const TUF_REPO_UPLOAD_PURGE_BATCH_SIZE: i64 = 16;
+
 /// The return value of [`DataStore::update_tuf_repo_insert`].
 ///
 /// This is similar to [`external::TufRepoInsertResponse`], but uses
@@ -139,6 +149,39 @@ impl DataStore {
         Ok(TufRepoDescription { repo, artifacts })
     }
 
+    /// Returns the TUF repo description corresponding to this repo ID.
+    pub async fn update_tuf_repo_get_by_id(
+        &self,
+        opctx: &OpContext,
+        repo_id: TypedUuid<TufRepoKind>,
+    ) -> LookupResult<TufRepoDescription> {
+        opctx.authorize(authz::Action::Read, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::tuf_repo::dsl;
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+
+        let repo = dsl::tuf_repo
+            .filter(dsl::id.eq(nexus_db_model::to_db_typed_uuid(repo_id)))
+            .select(TufRepo::as_select())
+            .first_async::<TufRepo>(&*conn)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel(
+                    e,
+                    ErrorHandler::NotFoundByLookup(
+                        ResourceType::TufRepo,
+                        LookupType::ById(repo_id.into_untyped_uuid()),
+                    ),
+                )
+            })?;
+
+        let artifacts = artifacts_for_repo(repo.id.into(), &conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+        Ok(TufRepoDescription { repo, artifacts })
+    }
+
     /// Returns the list of all TUF repo artifacts known to the system.
     pub async fn update_tuf_artifact_list(
         &self,
@@ -169,6 +212,238 @@ impl DataStore {
             .await
             .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
     }
+
+    /// Creates a new resumable TUF repository upload.
+    pub async fn tuf_repo_upload_create(
+        &self,
+        opctx: &OpContext,
+        upload: TufRepoUpload,
+    ) -> CreateResult<TufRepoUpload> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::tuf_repo_upload::dsl;
+
+        diesel::insert_into(dsl::tuf_repo_upload)
+            .values(upload)
+            .returning(TufRepoUpload::as_returning())
+            .get_result_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Returns the resumable TUF repository upload with this ID.
+    pub async fn tuf_repo_upload_get(
+        &self,
+        opctx: &OpContext,
+        upload_id: Uuid,
+    ) -> LookupResult<TufRepoUpload> {
+        opctx.authorize(authz::Action::Read, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::tuf_repo_upload::dsl;
+
+        dsl::tuf_repo_upload
+            .filter(dsl::id.eq(upload_id))
+            .filter(dsl::time_deleted.is_null())
+            .select(TufRepoUpload::as_select())
+            .first_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| {
+                public_error_from_diesel(
+                    e,
+                    ErrorHandler::NotFoundByLookup(
+                        ResourceType::TufRepoUpload,
+                        LookupType::ById(upload_id),
+                    ),
+                )
+            })
+    }
+
+    /// Appends `data` to a resumable TUF repository upload.
+    ///
+    /// `byte_offset` must be equal to the number of bytes received so far,
+    /// which guarantees that concurrent writers cannot interleave their data.
+    /// Returns the updated upload.
+    pub async fn tuf_repo_upload_append(
+        &self,
+        opctx: &OpContext,
+        upload_id: Uuid,
+        byte_offset: u64,
+        data: Vec<u8>,
+    ) -> UpdateResult<TufRepoUpload> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::tuf_repo_upload::dsl;
+        use nexus_db_schema::schema::tuf_repo_upload_chunk::dsl as chunk_dsl;
+
+        let offset = i64::try_from(byte_offset).map_err(|_| {
+            external::Error::invalid_request("write offset is too large")
+        })?;
+        let new_len = offset + data.len() as i64;
+
+        let err = OptionalError::new();
+        let conn = self.pool_connection_authorized(opctx).await?;
+        self.transaction_retry_wrapper("tuf_repo_upload_append")
+            .transaction(&conn, |conn| {
+                let err = err.clone();
+                let data = data.clone();
+                async move {
+                    let upload = diesel::update(dsl::tuf_repo_upload)
+                        .filter(dsl::id.eq(upload_id))
+                        .filter(dsl::time_deleted.is_null())
+                        .filter(dsl::bytes_received.eq(offset))
+                        .filter(dsl::size.ge(new_len))
+                        .set((
+                            dsl::bytes_received.eq(new_len),
+                            dsl::time_modified.eq(Utc::now()),
+                        ))
+                        .returning(TufRepoUpload::as_returning())
+                        .get_result_async(&conn)
+                        .await
+                        .optional()?;
+                    let Some(upload) = upload else {
+                        // Figure out why the update didn't match.
+                        let upload = dsl::tuf_repo_upload
+                            .filter(dsl::id.eq(upload_id))
+                            .filter(dsl::time_deleted.is_null())
+                            .select(TufRepoUpload::as_select())
+                            .first_async(&conn)
+                            .await
+                            .optional()?;
+                        return Err(err.bail(match upload {
+                            None => external::Error::not_found_by_id(
+                                ResourceType::TufRepoUpload,
+                                &upload_id,
+                            ),
+                            Some(upload) if upload.bytes_received != offset => {
+                                external::Error::conflict(format!(
+                                    "write offset {offset} does not match the \
+                                     number of bytes received so far ({})",
+                                    upload.bytes_received,
+                                ))
+                            }
+                            Some(upload) => {
+                                external::Error::invalid_request(format!(
+                                    "write would extend the repository past \
+                                     its declared size of {} bytes",
+                                    upload.size,
+                                ))
+                            }
+                        }));
+                    };
+
+                    if !data.is_empty() {
+                        diesel::insert_into(chunk_dsl::tuf_repo_upload_chunk)
+                            .values(TufRepoUploadChunk {
+                                upload_id,
+                                byte_offset: offset,
+                                data,
+                            })
+                            .execute_async(&conn)
+                            .await?;
+                    }
+
+                    Ok(upload)
+                }
+            })
+            .await
+            .map_err(|e| match err.take() {
+                Some(err) => err,
+                None => public_error_from_diesel(e, ErrorHandler::Server),
+            })
+    }
+
+    /// Lists the data received so far for a resumable TUF repository upload,
+    /// in order of byte offset.
+    pub async fn tuf_repo_upload_chunk_list(
+        &self,
+        opctx: &OpContext,
+        upload_id: Uuid,
+        pagparams: &DataPageParams<'_, i64>,
+    ) -> ListResultVec<TufRepoUploadChunk> {
+        opctx.authorize(authz::Action::Read, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::tuf_repo_upload_chunk::dsl;
+
+        paginated(dsl::tuf_repo_upload_chunk, dsl::byte_offset, pagparams)
+            .filter(dsl::upload_id.eq(upload_id))
+            .select(TufRepoUploadChunk::as_select())
+            .load_async(&*self.pool_connection_authorized(opctx).await?)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Deletes a resumable TUF repository upload along with its data.
+    ///
+    /// Returns a not-found error if the upload has already been deleted.
+    pub async fn tuf_repo_upload_delete(
+        &self,
+        opctx: &OpContext,
+        upload_id: Uuid,
+    ) -> DeleteResult {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::tuf_repo_upload::dsl;
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let deleted = diesel::update(dsl::tuf_repo_upload)
+            .filter(dsl::id.eq(upload_id))
+            .filter(dsl::time_deleted.is_null())
+            .set(dsl::time_deleted.eq(Utc::now()))
+            .execute_async(&*conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+        if deleted == 0 {
+            return Err(external::Error::not_found_by_id(
+                ResourceType::TufRepoUpload,
+                &upload_id,
+            ));
+        }
+
+        tuf_repo_upload_purge(&conn, upload_id)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+
+    /// Deletes resumable TUF repository uploads that have not received data
+    /// since `cutoff`, returning the IDs of the uploads deleted.
+    ///
+    /// This also finishes deleting the data of any uploads whose deletion was
+    /// interrupted.
+    pub async fn tuf_repo_upload_delete_idle(
+        &self,
+        opctx: &OpContext,
+        cutoff: DateTime<Utc>,
+    ) -> ListResultVec<Uuid> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        use nexus_db_schema::schema::tuf_repo_upload::dsl;
+
+        let conn = self.pool_connection_authorized(opctx).await?;
+        // There should only ever be a handful of uploads in progress, so we
+        // don't bother paginating.
+        let deleted: Vec<Uuid> = diesel::update(dsl::tuf_repo_upload)
+            .filter(dsl::time_deleted.is_null())
+            .filter(dsl::time_modified.lt(cutoff))
+            .set(dsl::time_deleted.eq(Utc::now()))
+            .returning(dsl::id)
+            .get_results_async(&*conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        let to_purge: Vec<Uuid> = dsl::tuf_repo_upload
+            .filter(dsl::time_deleted.is_not_null())
+            .select(dsl::id)
+            .load_async(&*conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+        for upload_id in to_purge {
+            tuf_repo_upload_purge(&conn, upload_id).await.map_err(|e| {
+                public_error_from_diesel(e, ErrorHandler::Server)
+            })?;
+        }
+
+        Ok(deleted)
+    }
 }
 
 // This is a separate method mostly to make rustfmt not bail out on long lines
@@ -436,6 +711,50 @@ async fn get_generation(
     Ok(generation.0)
 }
 
+/// Deletes a resumable TUF repository upload and its data, if it exists and
+/// (when `idle_cutoff` is provided) has not been modified since the cutoff.
+///
+/// Returns whether the upload was deleted.
+/// Deletes the data of a resumable TUF repository upload that has been marked
+/// deleted, and then the upload itself.
+///
+/// An upload can hold gigabytes of data, far more than CockroachDB allows a
+/// single transaction to delete, so the data is deleted a batch at a time. If
+/// this is interrupted, it's picked up again by
+/// [`DataStore::tuf_repo_upload_delete_idle`].
+async fn tuf_repo_upload_purge(
+    conn: &async_bb8_diesel::Connection<DbConnection>,
+    upload_id: Uuid,
+) -> Result<(), DieselError> {
+    use nexus_db_schema::schema::tuf_repo_upload::dsl;
+    use nexus_db_schema::schema::tuf_repo_upload_chunk::dsl as chunk_dsl;
+
+    loop {
+        let offsets: Vec<i64> = chunk_dsl::tuf_repo_upload_chunk
+            .filter(chunk_dsl::upload_id.eq(upload_id))
+            .order(chunk_dsl::byte_offset)
+            .limit(TUF_REPO_UPLOAD_PURGE_BATCH_SIZE)
+            .select(chunk_dsl::byte_offset)
+            .load_async(conn)
+            .await?;
+        if offsets.is_empty() {
+            break;
+        }
+        diesel::delete(chunk_dsl::tuf_repo_upload_chunk)
+            .filter(chunk_dsl::upload_id.eq(upload_id))
+            .filter(chunk_dsl::byte_offset.eq_any(offsets))
+            .execute_async(conn)
+            .await?;
+    }
+
+    diesel::delete(dsl::tuf_repo_upload)
+        .filter(dsl::id.eq(upload_id))
+        .filter(dsl::time_deleted.is_not_null())
+        .execute_async(conn)
+        .await?;
+    Ok(())
+}
+
 async fn put_generation(
     conn: &async_bb8_diesel::Connection<DbConnection>,
     old_generation: nexus_db_model::Generation,
@@ -10,10 +10,13 @@ use crate::db;
 use crate::db::model::ByteCount;
 use crate::db::model::VirtualProvisioningCollection;
 use crate::db::queries::virtual_provisioning_collection_update::VirtualProvisioningCollectionUpdate;
+use crate::db::queries::virtual_provisioning_collection_update::not_enough_storage_error;
 use async_bb8_diesel::AsyncRunQueryDsl;
+use chrono::Utc;
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
 use nexus_db_errors::ErrorHandler;
+use nexus_db_errors::OptionalError;
 use nexus_db_errors::public_error_from_diesel;
 use nexus_db_lookup::DbConnection;
 use omicron_common::api::external::{DeleteResult, Error};
@@ -258,6 +261,119 @@ impl DataStore {
         Ok(provisions)
     }
 
+    /// Changes the storage provisioned for disk `id` from `old_size` to
+    /// `new_size`, transitively updating all provisions from project -> fleet.
+    ///
+    /// Growing a disk is subject to the silo's storage quota. If the disk
+    /// already has `new_size` provisioned, nothing is changed.
+    pub async fn virtual_provisioning_collection_resize_disk(
+        &self,
+        opctx: &OpContext,
+        id: Uuid,
+        project_id: Uuid,
+        old_size: ByteCount,
+        new_size: ByteCount,
+    ) -> Result<Vec<VirtualProvisioningCollection>, Error> {
+        use nexus_db_schema::schema::project::dsl as project_dsl;
+        use nexus_db_schema::schema::silo_quotas::dsl as quotas_dsl;
+        use nexus_db_schema::schema::virtual_provisioning_collection::dsl as collection_dsl;
+        use nexus_db_schema::schema::virtual_provisioning_resource::dsl as resource_dsl;
+
+        let err = OptionalError::new();
+        let conn = self.pool_connection_authorized(opctx).await?;
+        let provisions = self
+            .transaction_retry_wrapper(
+                "virtual_provisioning_collection_resize_disk",
+            )
+            .transaction(&conn, |conn| {
+                let err = err.clone();
+                async move {
+                    let provisioned = resource_dsl::virtual_provisioning_resource
+                        .filter(resource_dsl::id.eq(id))
+                        .select(resource_dsl::virtual_disk_bytes_provisioned)
+                        .get_result_async::<ByteCount>(&conn)
+                        .await?;
+                    let silo_id = project_dsl::project
+                        .filter(project_dsl::id.eq(project_id))
+                        .select(project_dsl::silo_id)
+                        .get_result_async::<Uuid>(&conn)
+                        .await?;
+                    let collection_ids =
+                        [project_id, silo_id, *nexus_db_fixed_data::FLEET_ID];
+
+                    if provisioned == new_size {
+                        return collection_dsl::virtual_provisioning_collection
+                            .filter(collection_dsl::id.eq_any(collection_ids))
+                            .select(VirtualProvisioningCollection::as_select())
+                            .get_results_async(&conn)
+                            .await;
+                    }
+                    if provisioned != old_size {
+                        return Err(err.bail(Error::conflict(format!(
+                            "disk {id} has {} bytes provisioned, expected {}",
+                            provisioned.to_bytes(),
+                            old_size.to_bytes(),
+                        ))));
+                    }
+
+                    let diff = i64::from(new_size) - i64::from(old_size);
+                    if diff > 0 {
+                        let quota = quotas_dsl::silo_quotas
+                            .filter(quotas_dsl::silo_id.eq(silo_id))
+                            .select(quotas_dsl::storage_bytes)
+                            .get_result_async::<i64>(&conn)
+                            .await?;
+                        let silo_provisioned =
+                            collection_dsl::virtual_provisioning_collection
+                                .filter(collection_dsl::id.eq(silo_id))
+                                .select(
+                                    collection_dsl::virtual_disk_bytes_provisioned,
+                                )
+                                .get_result_async::<i64>(&conn)
+                                .await?;
+                        if silo_provisioned + diff > quota {
+                            return Err(err.bail(
+                                not_enough_storage_error(),
+                            ));
+                        }
+                    }
+
+                    let now = Utc::now();
+                    diesel::update(resource_dsl::virtual_provisioning_resource)
+                        .filter(resource_dsl::id.eq(id))
+                        .set((
+                            resource_dsl::virtual_disk_bytes_provisioned
+                                .eq(new_size),
+                            resource_dsl::time_modified.eq(now),
+                        ))
+                        .execute_async(&conn)
+                        .await?;
+                    diesel::update(collection_dsl::virtual_provisioning_collection)
+                        .filter(collection_dsl::id.eq_any(collection_ids))
+                        .set((
+                            collection_dsl::virtual_disk_bytes_provisioned.eq(
+                                collection_dsl::virtual_disk_bytes_provisioned
+                                    + diff,
+                            ),
+                            collection_dsl::time_modified.eq(now),
+                        ))
+                        .returning(VirtualProvisioningCollection::as_returning())
+                        .get_results_async(&conn)
+                        .await
+                }
+            })
+            .await
+            .map_err(|e| {
+                if let Some(err) = err.take() {
+                    return err;
+                }
+                public_error_from_diesel(e, ErrorHandler::Server)
+            })?;
+        self.virtual_provisioning_collection_producer
+            .append_disk_metrics(&provisions)?;
+        Ok(provisions)
+    }
+
     /// Transitively updates all CPU/RAM provisions from project -> fleet.
     pub async fn virtual_provisioning_collection_insert_instance(
         &self,
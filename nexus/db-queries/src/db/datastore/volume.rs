@@ -1987,6 +1987,148 @@ impl DataStore {
             })
     }
 
+    /// Append a read-write Region `sub_volume` to a volume, growing it.
+    ///
+    /// Sub-volumes are identified by the id in their Crucible options. The
+    /// generation numbers of the existing sub-volumes are bumped. As this is
+    /// part of a saga, appending a sub-volume that the volume already contains
+    /// does nothing.
+    pub async fn volume_append_sub_volume(
+        &self,
+        volume_id: VolumeUuid,
+        sub_volume: VolumeConstructionRequest,
+    ) -> Result<(), Error> {
+        let VolumeConstructionRequest::Region { opts, .. } = &sub_volume else {
+            return Err(Error::internal_error(
+                "only Region sub-volumes can be appended to a volume",
+            ));
+        };
+        let sub_volume_id = opts.id;
+
+        self.volume_update_sub_volumes(
+            "volume_append_sub_volume",
+            volume_id,
+            move |sub_volumes| {
+                let exists = sub_volumes.iter().any(|sv| {
+                    matches!(
+                        sv,
+                        VolumeConstructionRequest::Region { opts, .. }
+                            if opts.id == sub_volume_id
+                    )
+                });
+                if !exists {
+                    // An Upstairs will only accept a new construction request
+                    // if the generation numbers of its existing regions have
+                    // increased.
+                    for sv in sub_volumes.iter_mut() {
+                        if let VolumeConstructionRequest::Region {
+                            gen, ..
+                        } = sv
+                        {
+                            *gen += 1;
+                        }
+                    }
+                    sub_volumes.push(sub_volume.clone());
+                }
+            },
+        )
+        .await
+    }
+
+    /// Remove the Region sub-volume whose Crucible options have id
+    /// `sub_volume_id` from a volume, undoing `volume_append_sub_volume`.
+    ///
+    /// Removing a sub-volume that the volume does not contain does nothing.
+    pub async fn volume_remove_sub_volume(
+        &self,
+        volume_id: VolumeUuid,
+        sub_volume_id: Uuid,
+    ) -> Result<(), Error> {
+        self.volume_update_sub_volumes(
+            "volume_remove_sub_volume",
+            volume_id,
+            move |sub_volumes| {
+                sub_volumes.retain(|sv| {
+                    !matches!(
+                        sv,
+                        VolumeConstructionRequest::Region { opts, .. }
+                            if opts.id == sub_volume_id
+                    )
+                });
+            },
+        )
+        .await
+    }
+
+    // Apply `update` to the sub-volumes of a (non-deleted) volume's
+    // construction request in a single transaction.
+    async fn volume_update_sub_volumes<F>(
+        &self,
+        name: &'static str,
+        volume_id: VolumeUuid,
+        update: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&mut Vec<VolumeConstructionRequest>) + Clone + Send + Sync,
+    {
+        let err = OptionalError::new();
+        let conn = self.pool_connection_unauthorized().await?;
+        self.transaction_retry_wrapper(name)
+            .transaction(&conn, |conn| {
+                let err = err.clone();
+                let update = update.clone();
+                async move {
+                    use nexus_db_schema::schema::volume::dsl;
+
+                    let volume = dsl::volume
+                        .filter(dsl::id.eq(to_db_typed_uuid(volume_id)))
+                        .filter(dsl::time_deleted.is_null())
+                        .select(Volume::as_select())
+                        .first_async::<Volume>(&conn)
+                        .await?;
+
+                    let mut vcr: VolumeConstructionRequest =
+                        serde_json::from_str(volume.data()).map_err(|e| {
+                            err.bail(Error::internal_error(&format!(
+                                "failed to deserialize volume {volume_id}: {e}"
+                            )))
+                        })?;
+
+                    let VolumeConstructionRequest::Volume {
+                        sub_volumes, ..
+                    } = &mut vcr
+                    else {
+                        return Err(err.bail(Error::internal_error(&format!(
+                            "volume {volume_id} is not a Volume"
+                        ))));
+                    };
+
+                    update(sub_volumes);
+
+                    let data = serde_json::to_string(&vcr).map_err(|e| {
+                        err.bail(Error::internal_error(&format!(
+                            "failed to serialize volume {volume_id}: {e}"
+                        )))
+                    })?;
+
+                    diesel::update(dsl::volume)
+                        .filter(dsl::id.eq(to_db_typed_uuid(volume_id)))
+                        .set(dsl::data.eq(data))
+                        .execute_async(&conn)
+                        .await?;
+
+                    Ok(())
+                }
+            })
+            .await
+            .map_err(|e| {
+                if let Some(err) = err.take() {
+                    return err;
+                }
+                public_error_from_diesel(e, ErrorHandler::Server)
+            })
+    }
+
     /// Return all the read-write regions in a volume whose target address
     /// matches the argument dataset's.
     pub async fn get_dataset_rw_regions_in_volume(
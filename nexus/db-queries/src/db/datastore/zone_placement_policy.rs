@@ -0,0 +1,205 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Queries related to the zone placement policy
+
+use super::DataStore;
+use crate::authz;
+use crate::context::OpContext;
+use async_bb8_diesel::AsyncRunQueryDsl;
+use diesel::ExpressionMethods;
+use diesel::QueryDsl;
+use diesel::dsl::sql_query;
+use diesel::sql_types;
+use nexus_db_errors::ErrorHandler;
+use nexus_db_errors::public_error_from_diesel;
+use nexus_db_model::SqlU32;
+use nexus_db_model::ZonePlacementPolicy as DbZonePlacementPolicy;
+use nexus_types::deployment::ZonePlacementPolicy;
+use omicron_common::api::external::Error;
+
+impl DataStore {
+    /// Return the zone placement policy with the highest version
+    pub async fn zone_placement_policy_get_latest(
+        &self,
+        opctx: &OpContext,
+    ) -> Result<ZonePlacementPolicy, Error> {
+        opctx.authorize(authz::Action::Read, &authz::BLUEPRINT_CONFIG).await?;
+        let conn = self.pool_connection_authorized(opctx).await?;
+
+        use nexus_db_schema::schema::zone_placement_policy::dsl;
+
+        let latest_policy = dsl::zone_placement_policy
+            .order_by(dsl::version.desc())
+            .first_async::<DbZonePlacementPolicy>(&*conn)
+            .await
+            .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))?;
+
+        Ok(latest_policy.into())
+    }
+
+    /// Insert the current version of the policy in the database
+    ///
+    /// Only succeeds if the prior version is the latest version currently
+    /// in the `zone_placement_policy` table.
+    pub async fn zone_placement_policy_insert_latest_version(
+        &self,
+        opctx: &OpContext,
+        policy: &ZonePlacementPolicy,
+    ) -> Result<(), Error> {
+        // We pre-populate the database with version 1, so any new
+        // version must be greater
+        if policy.version < 2 {
+            return Err(Error::invalid_request(
+                "policy version must be greater than 1",
+            ));
+        }
+        opctx
+            .authorize(authz::Action::Modify, &authz::BLUEPRINT_CONFIG)
+            .await?;
+
+        let num_inserted = self
+            .zone_placement_policy_insert_next_policy(opctx, &policy)
+            .await?;
+        match num_inserted {
+            0 => Err(Error::invalid_request(format!(
+                "policy version {} is not the most recent",
+                policy.version
+            ))),
+            1 => Ok(()),
+            // This is impossible because we are explicitly inserting only one
+            // row with a unique primary key.
+            _ => unreachable!("query inserted more than one row"),
+        }
+    }
+
+    /// Insert the next version of the policy in the database
+    ///
+    /// Only succeeds if the prior version is the latest version currently
+    /// in the `zone_placement_policy` table.
+    ///
+    /// Panics if `policy.version <= 1`;
+    async fn zone_placement_policy_insert_next_policy(
+        &self,
+        opctx: &OpContext,
+        policy: &ZonePlacementPolicy,
+    ) -> Result<usize, Error> {
+        assert!(policy.version > 1);
+        let prev_version = policy.version - 1;
+        let policy = DbZonePlacementPolicy::from(policy.clone());
+
+        sql_query(
+            r"INSERT INTO zone_placement_policy
+                 (version, max_discretionary_zones_per_sled,
+                  reserved_sled_ids, time_created, cubbies_per_failure_domain)
+                 SELECT $1, $2, $3, $4, $6
+                  FROM zone_placement_policy WHERE version = $5 AND version IN
+                   (SELECT version FROM zone_placement_policy
+                    ORDER BY version DESC LIMIT 1)",
+        )
+        .bind::<sql_types::BigInt, SqlU32>(policy.version)
+        .bind::<sql_types::Nullable<sql_types::BigInt>, Option<SqlU32>>(
+            policy.max_discretionary_zones_per_sled,
+        )
+        .bind::<sql_types::Array<sql_types::Uuid>, _>(policy.reserved_sled_ids)
+        .bind::<sql_types::Timestamptz, _>(policy.time_created)
+        .bind::<sql_types::BigInt, SqlU32>(prev_version.into())
+        .bind::<sql_types::Nullable<sql_types::BigInt>, Option<SqlU32>>(
+            policy.cubbies_per_failure_domain,
+        )
+        .execute_async(&*self.pool_connection_authorized(opctx).await?)
+        .await
+        .map_err(|e| public_error_from_diesel(e, ErrorHandler::Server))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pub_test_utils::TestDatabase;
+    use nexus_inventory::now_db_precision;
+    use omicron_test_utils::dev;
+    use omicron_uuid_kinds::SledUuid;
+    use std::collections::BTreeSet;
+    use std::num::NonZeroU32;
+
+    #[tokio::test]
+    async fn test_zone_placement_policy_basic() {
+        // Setup
+        let logctx = dev::test_setup_log("test_zone_placement_policy_basic");
+        let db = TestDatabase::new_with_datastore(&logctx.log).await;
+        let (opctx, datastore) = (db.opctx(), db.datastore());
+
+        // The initial policy places no limits on zone placement.
+        let initial = datastore
+            .zone_placement_policy_get_latest(opctx)
+            .await
+            .expect("read initial policy");
+        assert_eq!(initial.version, 1);
+        assert_eq!(initial.max_discretionary_zones_per_sled, None);
+        assert!(initial.reserved_sleds.is_empty());
+        assert_eq!(initial.cubbies_per_failure_domain, None);
+
+        // Fail to insert a policy with version 1
+        let mut policy = ZonePlacementPolicy {
+            version: 1,
+            max_discretionary_zones_per_sled: Some(4),
+            reserved_sleds: BTreeSet::from([
+                SledUuid::new_v4(),
+                SledUuid::new_v4(),
+            ]),
+            cubbies_per_failure_domain: NonZeroU32::new(8),
+            time_created: now_db_precision(),
+        };
+        assert!(
+            datastore
+                .zone_placement_policy_insert_latest_version(opctx, &policy)
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("policy version must be greater than 1")
+        );
+
+        // Inserting version 3 before version 2 should not work
+        policy.version = 3;
+        assert!(
+            datastore
+                .zone_placement_policy_insert_latest_version(opctx, &policy)
+                .await
+                .unwrap_err()
+                .to_string()
+                .contains("policy version 3 is not the most recent")
+        );
+
+        // Inserting version 2 should work, and we should read back exactly
+        // what we wrote.
+        policy.version = 2;
+        datastore
+            .zone_placement_policy_insert_latest_version(opctx, &policy)
+            .await
+            .expect("inserted version 2");
+        assert_eq!(
+            datastore.zone_placement_policy_get_latest(opctx).await.unwrap(),
+            policy
+        );
+
+        // Clearing the limits again is just another version.
+        policy.version = 3;
+        policy.max_discretionary_zones_per_sled = None;
+        policy.reserved_sleds.clear();
+        policy.cubbies_per_failure_domain = None;
+        datastore
+            .zone_placement_policy_insert_latest_version(opctx, &policy)
+            .await
+            .expect("inserted version 3");
+        assert_eq!(
+            datastore.zone_placement_policy_get_latest(opctx).await.unwrap(),
+            policy
+        );
+
+        // Clean up.
+        db.terminate().await;
+        logctx.cleanup_successful();
+    }
+}
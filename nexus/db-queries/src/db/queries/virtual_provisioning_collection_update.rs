@@ -59,12 +59,7 @@ pub fn from_diesel(e: DieselError) -> external::Error {
                 }
             }
             NOT_ENOUGH_STORAGE_SENTINEL => {
-                return external::Error::InsufficientCapacity {
-                    message: MessagePair::new_full(
-                         "Storage Limit Exceeded: Not enough storage to complete request. Either remove unneeded disks and snapshots to free up resources or contact the rack operator to request a capacity increase.".to_string(),
-                         "User tried to allocate a disk or snapshot but the virtual provisioning resource table indicated that there were not enough storage available to satisfy the request.".to_string(),
-                    )
-                }
+                return not_enough_storage_error();
             }
             _ => {}
         }
@@ -75,6 +70,16 @@ pub fn from_diesel(e: DieselError) -> external::Error {
     )
 }
 
+/// Returns the error reported when a silo's storage quota would be exceeded
+pub fn not_enough_storage_error() -> external::Error {
+    external::Error::InsufficientCapacity {
+        message: MessagePair::new_full(
+             "Storage Limit Exceeded: Not enough storage to complete request. Either remove unneeded disks and snapshots to free up resources or contact the rack operator to request a capacity increase.".to_string(),
+             "User tried to allocate a disk or snapshot but the virtual provisioning resource table indicated that there were not enough storage available to satisfy the request.".to_string(),
+        )
+    }
+}
+
 /// The virtual resource collection is only updated when a resource is inserted
 /// or deleted from the resource provisioning table. By probing for the presence
 /// or absence of a resource, we can update collections at the same time as we
@@ -27,6 +27,7 @@ define_enums! {
     BlockSizeEnum => "block_size",
     BpDatasetDispositionEnum => "bp_dataset_disposition",
     BpPhysicalDiskDispositionEnum => "bp_physical_disk_disposition",
+    BpTargetAuditActionEnum => "bp_target_audit_action",
     BpZoneDispositionEnum => "bp_zone_disposition",
     BpZoneImageSourceEnum => "bp_zone_image_source",
     CabooseWhichEnum => "caboose_which",
@@ -66,6 +67,8 @@ define_enums! {
     SledPolicyEnum => "sled_policy",
     SledRoleEnum => "sled_role",
     SledStateEnum => "sled_state",
+    SnapshotExportFormatEnum => "snapshot_export_format",
+    SnapshotGroupStateEnum => "snapshot_group_state",
     SnapshotStateEnum => "snapshot_state",
     SpTypeEnum => "sp_type",
     SupportBundleStateEnum => "support_bundle_state",
@@ -408,6 +408,82 @@ table! {
     }
 }
 
+table! {
+    snapshot_export (id) {
+        id -> Uuid,
+        time_created -> Timestamptz,
+        time_modified -> Timestamptz,
+        time_deleted -> Nullable<Timestamptz>,
+
+        snapshot_id -> Uuid,
+        volume_id -> Uuid,
+        pantry_address -> Text,
+
+        format -> crate::enums::SnapshotExportFormatEnum,
+        size_bytes -> Int8,
+        bytes_exported -> Int8,
+    }
+}
+
+table! {
+    snapshot_policy (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        time_created -> Timestamptz,
+        time_modified -> Timestamptz,
+        time_deleted -> Nullable<Timestamptz>,
+
+        project_id -> Uuid,
+        disk_id -> Nullable<Uuid>,
+
+        interval_secs -> Int8,
+        retention_count -> Int8,
+
+        time_last_run -> Nullable<Timestamptz>,
+        last_error -> Nullable<Text>,
+    }
+}
+
+table! {
+    snapshot_policy_snapshot (policy_id, disk_id, time_scheduled) {
+        policy_id -> Uuid,
+        disk_id -> Uuid,
+        time_scheduled -> Timestamptz,
+        time_created -> Timestamptz,
+
+        snapshot_id -> Nullable<Uuid>,
+    }
+}
+
+allow_tables_to_appear_in_same_query!(snapshot, snapshot_policy_snapshot);
+
+table! {
+    snapshot_group (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        time_created -> Timestamptz,
+        time_modified -> Timestamptz,
+        time_deleted -> Nullable<Timestamptz>,
+
+        project_id -> Uuid,
+        instance_id -> Uuid,
+
+        state -> crate::enums::SnapshotGroupStateEnum,
+    }
+}
+
+table! {
+    snapshot_group_member (group_id, snapshot_id) {
+        group_id -> Uuid,
+        snapshot_id -> Uuid,
+
+        disk_id -> Uuid,
+        disk_name -> Text,
+    }
+}
+
 table! {
     instance (id) {
         id -> Uuid,
@@ -438,6 +514,26 @@ table! {
 
 joinable!(instance -> vmm (active_propolis_id));
 
+table! {
+    instance_template (id) {
+        id -> Uuid,
+        name -> Text,
+        description -> Text,
+        time_created -> Timestamptz,
+        time_modified -> Timestamptz,
+        time_deleted -> Nullable<Timestamptz>,
+
+        project_id -> Uuid,
+
+        ncpus -> Int8,
+        memory -> Int8,
+        user_data -> Binary,
+        auto_restart_policy -> Nullable<crate::enums::InstanceAutoRestartPolicyEnum>,
+
+        devices -> Jsonb,
+    }
+}
+
 table! {
     vmm (id) {
         id -> Uuid,
@@ -915,6 +1011,16 @@ table! {
     }
 }
 
+table! {
+    zone_placement_policy (version) {
+        version -> Int8,
+        max_discretionary_zones_per_sled -> Nullable<Int8>,
+        reserved_sled_ids -> Array<Uuid>,
+        time_created -> Timestamptz,
+        cubbies_per_failure_domain -> Nullable<Int8>,
+    }
+}
+
 table! {
     rack (id) {
         id -> Uuid,
@@ -1435,6 +1541,26 @@ table! {
     }
 }
 
+table! {
+    tuf_repo_upload (id) {
+        id -> Uuid,
+        file_name -> Text,
+        size -> Int8,
+        bytes_received -> Int8,
+        time_created -> Timestamptz,
+        time_modified -> Timestamptz,
+        time_deleted -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    tuf_repo_upload_chunk (upload_id, byte_offset) {
+        upload_id -> Uuid,
+        byte_offset -> Int8,
+        data -> Binary,
+    }
+}
+
 table! {
     target_release (generation) {
         generation -> Int8,
@@ -1716,6 +1842,8 @@ table! {
         cockroachdb_fingerprint -> Text,
 
         cockroachdb_setting_preserve_downgrade -> Nullable<Text>,
+
+        planning_report -> Nullable<Jsonb>,
     }
 }
 
@@ -1730,6 +1858,33 @@ table! {
     }
 }
 
+table! {
+    bp_target_approval (blueprint_id) {
+        blueprint_id -> Uuid,
+
+        proposed_by -> Text,
+        time_proposed -> Timestamptz,
+
+        required_approver -> Text,
+
+        approved_by -> Nullable<Text>,
+        time_approved -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    bp_target_audit (id) {
+        id -> Uuid,
+        time_recorded -> Timestamptz,
+
+        blueprint_id -> Uuid,
+
+        action -> crate::enums::BpTargetAuditActionEnum,
+        actor -> Nullable<Text>,
+        enabled -> Nullable<Bool>,
+    }
+}
+
 table! {
     bp_sled_metadata (blueprint_id, sled_id) {
         blueprint_id -> Uuid,
@@ -2065,6 +2220,7 @@ table! {
         target_propolis_id -> Uuid,
         target_gen -> Int8,
         time_target_updated -> Nullable<Timestamptz>,
+        failure_reason -> Nullable<Text>,
     }
 }
 
@@ -31,6 +31,11 @@ const DISK_BULK_WRITE_MAX_BYTES: usize = 8 * MIB;
 // Full release repositories are currently (Dec 2024) 1.8 GiB and are likely to
 // continue growing.
 const PUT_UPDATE_REPOSITORY_MAX_BYTES: usize = 4 * GIB;
+// Resumable uploads are sent in pieces, each of which may be much smaller than
+// the full repository.
+const TUF_REPO_UPLOAD_WRITE_MAX_BYTES: usize = 512 * MIB;
+// Uploaded image files hold an entire disk, up to the largest disk size.
+const IMAGE_UPLOAD_MAX_BYTES: usize = 1023 * GIB;
 
 // API ENDPOINT FUNCTION NAMING CONVENTIONS
 //
@@ -1085,6 +1090,22 @@ pub trait NexusExternalApi {
         finalize_params: TypedBody<params::FinalizeDisk>,
     ) -> Result<HttpResponseUpdatedNoContent, HttpError>;
 
+    /// Resize disk
+    ///
+    /// Grow a disk to a larger size. The disk must be detached, or attached
+    /// to an instance that is stopped.
+    #[endpoint {
+        method = POST,
+        path = "/v1/disks/{disk}/resize",
+        tags = ["disks"],
+    }]
+    async fn disk_resize(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::DiskPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+        resize_params: TypedBody<params::DiskResize>,
+    ) -> Result<HttpResponseOk<Disk>, HttpError>;
+
     // Instances
 
     /// List instances
@@ -1159,6 +1180,38 @@ pub trait NexusExternalApi {
         path_params: Path<params::InstancePath>,
     ) -> Result<HttpResponseAccepted<Instance>, HttpError>;
 
+    /// Migrate an instance
+    ///
+    /// Start live-migrating a running instance to another sled. The instance
+    /// keeps running while it migrates; use the instance's migration list to
+    /// follow the migration's progress.
+    #[endpoint {
+        method = POST,
+        path = "/v1/instances/{instance}/migrate",
+        tags = ["instances"],
+    }]
+    async fn instance_migrate(
+        rqctx: RequestContext<Self::Context>,
+        query_params: Query<params::OptionalProjectSelector>,
+        path_params: Path<params::InstancePath>,
+        migrate_params: TypedBody<params::InstanceMigrate>,
+    ) -> Result<HttpResponseAccepted<Instance>, HttpError>;
+
+    /// List instance migrations
+    ///
+    /// List the live migrations of an instance between sleds, including any
+    /// in progress.
+    #[endpoint {
+        method = GET,
+        path = "/v1/instances/{instance}/migrations",
+        tags = ["instances"],
+    }]
+    async fn instance_migration_list(
+        rqctx: RequestContext<Self::Context>,
+        query_params: Query<PaginatedById<params::OptionalProjectSelector>>,
+        path_params: Path<params::InstancePath>,
+    ) -> Result<HttpResponseOk<ResultsPage<views::InstanceMigration>>, HttpError>;
+
     /// Boot instance
     #[endpoint {
         method = POST,
@@ -1294,6 +1347,80 @@ pub trait NexusExternalApi {
         path_params: Path<params::InstancePath>,
     ) -> Result<HttpResponseOk<ResultsPage<views::AntiAffinityGroup>>, HttpError>;
 
+    // Instance templates
+
+    /// List instance templates
+    #[endpoint {
+        method = GET,
+        path = "/v1/instance-templates",
+        tags = ["instances"],
+    }]
+    async fn instance_template_list(
+        rqctx: RequestContext<Self::Context>,
+        query_params: Query<PaginatedByNameOrId<params::ProjectSelector>>,
+    ) -> Result<HttpResponseOk<ResultsPage<views::InstanceTemplate>>, HttpError>;
+
+    /// Create instance template
+    ///
+    /// Describes instances that can later be created in bulk with the same
+    /// CPU, memory, boot disk, networking and user data.
+    #[endpoint {
+        method = POST,
+        path = "/v1/instance-templates",
+        tags = ["instances"],
+    }]
+    async fn instance_template_create(
+        rqctx: RequestContext<Self::Context>,
+        query_params: Query<params::ProjectSelector>,
+        new_template: TypedBody<params::InstanceTemplateCreate>,
+    ) -> Result<HttpResponseCreated<views::InstanceTemplate>, HttpError>;
+
+    /// Fetch instance template
+    #[endpoint {
+        method = GET,
+        path = "/v1/instance-templates/{instance_template}",
+        tags = ["instances"],
+    }]
+    async fn instance_template_view(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::InstanceTemplatePath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseOk<views::InstanceTemplate>, HttpError>;
+
+    /// Delete instance template
+    ///
+    /// Instances already created from the template are not affected.
+    #[endpoint {
+        method = DELETE,
+        path = "/v1/instance-templates/{instance_template}",
+        tags = ["instances"],
+    }]
+    async fn instance_template_delete(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::InstanceTemplatePath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseDeleted, HttpError>;
+
+    /// Create instances from template
+    ///
+    /// Creates `count` instances from the template, naming each after
+    /// `name_pattern`, and adds them to the given affinity and anti-affinity
+    /// groups. The request fails without creating any instances if it is
+    /// invalid as a whole; otherwise the outcome for each instance is
+    /// reported separately, so some instances may be created even though
+    /// others could not be.
+    #[endpoint {
+        method = POST,
+        path = "/v1/instance-templates/{instance_template}/instantiate",
+        tags = ["instances"],
+    }]
+    async fn instance_template_instantiate(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::InstanceTemplatePath>,
+        query_params: Query<params::OptionalProjectSelector>,
+        instantiate_params: TypedBody<params::InstanceTemplateInstantiate>,
+    ) -> Result<HttpResponseOk<views::InstanceTemplateInstantiation>, HttpError>;
+
     // Affinity Groups
 
     /// List affinity groups
@@ -1994,6 +2121,23 @@ pub trait NexusExternalApi {
         new_image: TypedBody<params::ImageCreate>,
     ) -> Result<HttpResponseCreated<views::Image>, HttpError>;
 
+    /// Upload image
+    ///
+    /// Create a new image in a project from a raw or qcow2 image file sent as
+    /// the request body.
+    #[endpoint {
+        method = POST,
+        path = "/v1/images/{image}/upload",
+        tags = ["images"],
+        request_body_max_bytes = IMAGE_UPLOAD_MAX_BYTES,
+    }]
+    async fn image_upload(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::ImageUploadPath>,
+        query_params: Query<params::ImageUpload>,
+        body: StreamingBody,
+    ) -> Result<HttpResponseCreated<views::Image>, HttpError>;
+
     /// Fetch image
     ///
     /// Fetch the details for a specific image in a project.
@@ -2207,6 +2351,201 @@ pub trait NexusExternalApi {
         query_params: Query<params::OptionalProjectSelector>,
     ) -> Result<HttpResponseDeleted, HttpError>;
 
+    /// Export snapshot
+    ///
+    /// Prepare a snapshot for download as a raw or qcow2 disk image. The
+    /// image can be downloaded from the export's content endpoint until the
+    /// export is deleted, and the snapshot can't be deleted while it has
+    /// exports.
+    #[endpoint {
+        method = POST,
+        path = "/v1/snapshots/{snapshot}/exports",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_export_create(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+        export_params: TypedBody<params::SnapshotExportCreate>,
+    ) -> Result<HttpResponseCreated<views::SnapshotExport>, HttpError>;
+
+    /// Fetch snapshot export
+    #[endpoint {
+        method = GET,
+        path = "/v1/snapshots/{snapshot}/exports/{export_id}",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_export_view(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotExportPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseOk<views::SnapshotExport>, HttpError>;
+
+    /// Delete snapshot export
+    ///
+    /// Any downloads of the export that are in progress will fail.
+    #[endpoint {
+        method = DELETE,
+        path = "/v1/snapshots/{snapshot}/exports/{export_id}",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_export_delete(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotExportPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseDeleted, HttpError>;
+
+    /// Download snapshot export
+    ///
+    /// Supports range requests, so interrupted downloads can be resumed.
+    #[endpoint {
+        method = GET,
+        path = "/v1/snapshots/{snapshot}/exports/{export_id}/content",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_export_download(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotExportPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<Response<Body>, HttpError>;
+
+    /// Fetch the size of a snapshot export's content
+    #[endpoint {
+        method = HEAD,
+        path = "/v1/snapshots/{snapshot}/exports/{export_id}/content",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_export_head(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotExportPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<Response<Body>, HttpError>;
+
+    // Snapshot policies
+
+    /// List snapshot policies
+    #[endpoint {
+        method = GET,
+        path = "/v1/snapshot-policies",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_policy_list(
+        rqctx: RequestContext<Self::Context>,
+        query_params: Query<PaginatedByNameOrId<params::ProjectSelector>>,
+    ) -> Result<HttpResponseOk<ResultsPage<views::SnapshotPolicy>>, HttpError>;
+
+    /// Create snapshot policy
+    ///
+    /// Snapshots the policy's disk, or every disk in the project, at the
+    /// given interval, and deletes the policy's oldest snapshots of each disk
+    /// beyond its retention count.
+    #[endpoint {
+        method = POST,
+        path = "/v1/snapshot-policies",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_policy_create(
+        rqctx: RequestContext<Self::Context>,
+        query_params: Query<params::ProjectSelector>,
+        new_policy: TypedBody<params::SnapshotPolicyCreate>,
+    ) -> Result<HttpResponseCreated<views::SnapshotPolicy>, HttpError>;
+
+    /// Fetch snapshot policy
+    #[endpoint {
+        method = GET,
+        path = "/v1/snapshot-policies/{snapshot_policy}",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_policy_view(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotPolicyPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseOk<views::SnapshotPolicy>, HttpError>;
+
+    /// Delete snapshot policy
+    ///
+    /// Snapshots already taken by the policy are not deleted.
+    #[endpoint {
+        method = DELETE,
+        path = "/v1/snapshot-policies/{snapshot_policy}",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_policy_delete(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotPolicyPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseDeleted, HttpError>;
+
+    // Snapshot groups
+
+    /// List snapshot groups
+    #[endpoint {
+        method = GET,
+        path = "/v1/snapshot-groups",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_group_list(
+        rqctx: RequestContext<Self::Context>,
+        query_params: Query<PaginatedByNameOrId<params::ProjectSelector>>,
+    ) -> Result<HttpResponseOk<ResultsPage<views::SnapshotGroup>>, HttpError>;
+
+    /// Create snapshot group
+    ///
+    /// Snapshots every disk attached to the instance, capturing all of them at
+    /// the same point. The instance must be stopped.
+    #[endpoint {
+        method = POST,
+        path = "/v1/snapshot-groups",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_group_create(
+        rqctx: RequestContext<Self::Context>,
+        query_params: Query<params::ProjectSelector>,
+        new_group: TypedBody<params::SnapshotGroupCreate>,
+    ) -> Result<HttpResponseCreated<views::SnapshotGroup>, HttpError>;
+
+    /// Fetch snapshot group
+    #[endpoint {
+        method = GET,
+        path = "/v1/snapshot-groups/{snapshot_group}",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_group_view(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotGroupPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseOk<views::SnapshotGroup>, HttpError>;
+
+    /// Delete snapshot group
+    ///
+    /// The group's snapshots are deleted too.
+    #[endpoint {
+        method = DELETE,
+        path = "/v1/snapshot-groups/{snapshot_group}",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_group_delete(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotGroupPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseDeleted, HttpError>;
+
+    /// Restore snapshot group
+    ///
+    /// Creates a new disk from each of the group's snapshots. Either all of
+    /// the disks are created, or none are.
+    #[endpoint {
+        method = POST,
+        path = "/v1/snapshot-groups/{snapshot_group}/restore",
+        tags = ["snapshots"],
+    }]
+    async fn snapshot_group_restore(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::SnapshotGroupPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+        restore_params: TypedBody<params::SnapshotGroupRestore>,
+    ) -> Result<HttpResponseCreated<Vec<Disk>>, HttpError>;
+
     // VPCs
 
     /// List VPCs
@@ -2907,6 +3246,88 @@ pub trait NexusExternalApi {
         body: StreamingBody,
     ) -> Result<HttpResponseOk<TufRepoInsertResponse>, HttpError>;
 
+    /// Start a resumable TUF repository upload
+    ///
+    /// The repository archive is then sent in one or more requests to
+    /// `/v1/system/update/repository-uploads/{upload_id}/data`, and processed
+    /// once the upload is finalized. Uploads are stored in the database, so
+    /// they may be continued through any Nexus instance. Uploads that receive
+    /// no data for an hour are discarded.
+    #[endpoint {
+        method = POST,
+        path = "/v1/system/update/repository-uploads",
+        tags = ["system/update"],
+        unpublished = true,
+    }]
+    async fn system_update_repository_upload_create(
+        rqctx: RequestContext<Self::Context>,
+        params: TypedBody<params::TufRepoUploadCreate>,
+    ) -> Result<HttpResponseCreated<views::TufRepoUpload>, HttpError>;
+
+    /// Fetch TUF repository upload
+    ///
+    /// Clients resuming an interrupted upload use `bytes_received` as the
+    /// offset of their next write.
+    #[endpoint {
+        method = GET,
+        path = "/v1/system/update/repository-uploads/{upload_id}",
+        tags = ["system/update"],
+        unpublished = true,
+    }]
+    async fn system_update_repository_upload_view(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::TufRepoUploadPath>,
+    ) -> Result<HttpResponseOk<views::TufRepoUpload>, HttpError>;
+
+    /// Write data to TUF repository upload
+    ///
+    /// The request body is appended to the upload, and must begin at the
+    /// offset given by `bytes_received`. If the request is interrupted, any
+    /// data that arrived is retained.
+    #[endpoint {
+        method = PUT,
+        path = "/v1/system/update/repository-uploads/{upload_id}/data",
+        tags = ["system/update"],
+        unpublished = true,
+        request_body_max_bytes = TUF_REPO_UPLOAD_WRITE_MAX_BYTES,
+    }]
+    async fn system_update_repository_upload_write(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::TufRepoUploadPath>,
+        query_params: Query<params::TufRepoUploadWriteParams>,
+        body: StreamingBody,
+    ) -> Result<HttpResponseOk<views::TufRepoUpload>, HttpError>;
+
+    /// Finalize TUF repository upload
+    ///
+    /// Verify the SHA-256 of the uploaded archive and process the repository
+    /// it contains. The upload is discarded once the repository has been
+    /// recorded; if this fails, the upload is kept so that finalization can be
+    /// retried (or the upload deleted).
+    #[endpoint {
+        method = POST,
+        path = "/v1/system/update/repository-uploads/{upload_id}/finalize",
+        tags = ["system/update"],
+        unpublished = true,
+    }]
+    async fn system_update_repository_upload_finalize(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::TufRepoUploadPath>,
+        params: TypedBody<params::TufRepoUploadFinalize>,
+    ) -> Result<HttpResponseOk<TufRepoInsertResponse>, HttpError>;
+
+    /// Cancel TUF repository upload
+    #[endpoint {
+        method = DELETE,
+        path = "/v1/system/update/repository-uploads/{upload_id}",
+        tags = ["system/update"],
+        unpublished = true,
+    }]
+    async fn system_update_repository_upload_delete(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::TufRepoUploadPath>,
+    ) -> Result<HttpResponseDeleted, HttpError>;
+
     /// Fetch TUF repository description
     ///
     /// Fetch description of TUF repository by system version.
@@ -2952,6 +3373,44 @@ pub trait NexusExternalApi {
         params: TypedBody<params::SetTargetReleaseParams>,
     ) -> Result<HttpResponseCreated<views::TargetRelease>, HttpError>;
 
+    /// Propose a blueprint for review
+    ///
+    /// The authenticated user is recorded as the proposer. A proposed
+    /// blueprint is held: it may be made the target, but it will not be
+    /// executed until the required approver has approved it.
+    #[endpoint {
+        method = POST,
+        path = "/v1/system/update/blueprints/{blueprint_id}/propose",
+        tags = ["system/update"],
+        unpublished = true,
+    }]
+    async fn system_update_blueprint_propose(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::BlueprintPath>,
+        params: TypedBody<params::BlueprintPropose>,
+    ) -> Result<
+        HttpResponseOk<nexus_types::deployment::BlueprintApproval>,
+        HttpError,
+    >;
+
+    /// Approve a proposed blueprint for execution
+    ///
+    /// The authenticated user must be the blueprint's required approver, and
+    /// may not be the user who proposed it.
+    #[endpoint {
+        method = POST,
+        path = "/v1/system/update/blueprints/{blueprint_id}/approve",
+        tags = ["system/update"],
+        unpublished = true,
+    }]
+    async fn system_update_blueprint_approve(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<params::BlueprintPath>,
+    ) -> Result<
+        HttpResponseOk<nexus_types::deployment::BlueprintApproval>,
+        HttpError,
+    >;
+
     // Silo users
 
     /// List users
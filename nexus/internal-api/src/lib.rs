@@ -11,8 +11,9 @@ use dropshot::{
 };
 use nexus_types::{
     deployment::{
-        Blueprint, BlueprintMetadata, BlueprintTarget, BlueprintTargetSet,
-        ClickhousePolicy, OximeterReadPolicy,
+        Blueprint, BlueprintApproval, BlueprintMetadata, BlueprintTarget,
+        BlueprintTargetAuditEntry, BlueprintTargetSet, ClickhousePolicy,
+        ExecutionPlan, OximeterReadPolicy, ZonePlacementPolicy,
     },
     external_api::{
         params::{PhysicalDiskPath, SledSelector, UninitializedSledId},
@@ -459,6 +460,39 @@ pub trait NexusInternalApi {
         target: TypedBody<BlueprintTargetSet>,
     ) -> Result<HttpResponseOk<BlueprintTarget>, HttpError>;
 
+    // Reviewing blueprints before execution
+
+    /// Fetches the review status of a proposed blueprint
+    #[endpoint {
+        method = GET,
+        path = "/deployment/blueprints/all/{blueprint_id}/approval",
+    }]
+    async fn blueprint_approval_view(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<nexus_types::external_api::params::BlueprintPath>,
+    ) -> Result<HttpResponseOk<BlueprintApproval>, HttpError>;
+
+    /// Lists who proposed, approved, and enabled a blueprint, oldest first
+    #[endpoint {
+        method = GET,
+        path = "/deployment/blueprints/all/{blueprint_id}/audit",
+    }]
+    async fn blueprint_target_audit_list(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<nexus_types::external_api::params::BlueprintPath>,
+    ) -> Result<HttpResponseOk<Vec<BlueprintTargetAuditEntry>>, HttpError>;
+
+    /// Shows what executing a blueprint would change right now, without
+    /// changing anything
+    #[endpoint {
+        method = GET,
+        path = "/deployment/blueprints/all/{blueprint_id}/execution-plan",
+    }]
+    async fn blueprint_execution_plan(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<nexus_types::external_api::params::BlueprintPath>,
+    ) -> Result<HttpResponseOk<ExecutionPlan>, HttpError>;
+
     // Generating blueprints
 
     /// Generates a new blueprint for the current system, re-evaluating anything
@@ -585,6 +619,25 @@ pub trait NexusInternalApi {
         rqctx: RequestContext<Self::Context>,
         policy: TypedBody<OximeterReadPolicy>,
     ) -> Result<HttpResponseUpdatedNoContent, HttpError>;
+
+    /// Get the current zone placement policy
+    #[endpoint {
+        method = GET,
+        path = "/deployment/zone-placement-policy"
+    }]
+    async fn zone_placement_policy_get(
+        rqctx: RequestContext<Self::Context>,
+    ) -> Result<HttpResponseOk<ZonePlacementPolicy>, HttpError>;
+
+    /// Set the new zone placement policy
+    #[endpoint {
+        method = POST,
+        path = "/deployment/zone-placement-policy"
+    }]
+    async fn zone_placement_policy_set(
+        rqctx: RequestContext<Self::Context>,
+        policy: TypedBody<ZonePlacementPolicy>,
+    ) -> Result<HttpResponseUpdatedNoContent, HttpError>;
 }
 
 /// Path parameters for Sled Agent requests (internal API)
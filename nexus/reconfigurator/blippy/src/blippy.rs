@@ -6,20 +6,29 @@ use crate::checks;
 use crate::report::BlippyReport;
 use crate::report::BlippyReportSortKey;
 use core::fmt;
+use nexus_sled_agent_shared::inventory::ZoneKind;
 use nexus_types::deployment::Blueprint;
 use nexus_types::deployment::BlueprintDatasetConfig;
 use nexus_types::deployment::BlueprintZoneConfig;
+use nexus_types::deployment::FailureDomain;
+use nexus_types::deployment::PlanningInput;
+use nexus_types::inventory::Collection;
 use nexus_types::inventory::ZpoolName;
 use omicron_common::address::DnsSubnet;
 use omicron_common::address::Ipv6Subnet;
 use omicron_common::address::SLED_PREFIX;
 use omicron_common::api::external::MacAddr;
 use omicron_common::disk::DatasetKind;
+use omicron_common::disk::DiskIdentity;
+use omicron_uuid_kinds::CollectionUuid;
+use omicron_uuid_kinds::OmicronZoneUuid;
+use omicron_uuid_kinds::PhysicalDiskUuid;
 use omicron_uuid_kinds::SledUuid;
 use omicron_uuid_kinds::ZpoolUuid;
 use std::collections::BTreeSet;
 use std::net::IpAddr;
 use std::net::SocketAddrV6;
+use tufaceous_artifact::ArtifactHash;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Note {
@@ -32,6 +41,9 @@ pub enum Severity {
     /// Indicates an issue with a blueprint that should be corrected by a future
     /// planning run.
     BackwardsCompatibility,
+    /// Indicates a blueprint that is valid but is less resilient to failures
+    /// than it could be.
+    Warning,
     /// Indicates a serious problem that means the blueprint is invalid.
     Fatal,
 }
@@ -40,6 +52,7 @@ impl fmt::Display for Severity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Severity::BackwardsCompatibility => write!(f, "BACKCOMPAT"),
+            Severity::Warning => write!(f, "WARNING"),
             Severity::Fatal => write!(f, "FATAL"),
         }
     }
@@ -48,42 +61,53 @@ impl fmt::Display for Severity {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Kind {
     Sled { sled_id: SledUuid, kind: SledKind },
+    FailureDomain { domain: FailureDomain, kind: FailureDomainKind },
 }
 
 impl Kind {
     pub fn display_component(&self) -> impl fmt::Display + '_ {
         enum Component<'a> {
             Sled(&'a SledUuid),
+            FailureDomain(&'a FailureDomain),
         }
 
         impl fmt::Display for Component<'_> {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 match self {
                     Component::Sled(id) => write!(f, "sled {id}"),
+                    Component::FailureDomain(domain) => {
+                        write!(f, "failure domain {domain}")
+                    }
                 }
             }
         }
 
         match self {
             Kind::Sled { sled_id, .. } => Component::Sled(sled_id),
+            Kind::FailureDomain { domain, .. } => {
+                Component::FailureDomain(domain)
+            }
         }
     }
 
     pub fn display_subkind(&self) -> impl fmt::Display + '_ {
         enum Subkind<'a> {
             Sled(&'a SledKind),
+            FailureDomain(&'a FailureDomainKind),
         }
 
         impl fmt::Display for Subkind<'_> {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 match self {
                     Subkind::Sled(kind) => write!(f, "{kind}"),
+                    Subkind::FailureDomain(kind) => write!(f, "{kind}"),
                 }
             }
         }
 
         match self {
             Kind::Sled { kind, .. } => Subkind::Sled(kind),
+            Kind::FailureDomain { kind, .. } => Subkind::FailureDomain(kind),
         }
     }
 }
@@ -177,6 +201,29 @@ pub enum SledKind {
         dataset: BlueprintDatasetConfig,
         address: SocketAddrV6,
     },
+    /// An active sled is not present in an inventory collection.
+    SledMissingFromInventory { collection_id: CollectionUuid },
+    /// An in-service disk is not reported by its sled in an inventory
+    /// collection.
+    PhysicalDiskMissingFromInventory {
+        disk_id: PhysicalDiskUuid,
+        identity: DiskIdentity,
+        collection_id: CollectionUuid,
+    },
+    /// An in-service zone uses a zpool that is not reported by its sled in an
+    /// inventory collection.
+    ZoneOnZpoolMissingFromInventory {
+        zone: BlueprintZoneConfig,
+        zpool: ZpoolName,
+        collection_id: CollectionUuid,
+    },
+    /// An in-service zone's image is an artifact that is not known to be in
+    /// the repo depot.
+    ZoneImageNotInDepot { zone: BlueprintZoneConfig, hash: ArtifactHash },
+    /// An in-service discretionary zone is on a sled reserved for instances.
+    DiscretionaryZoneOnReservedSled { zone: BlueprintZoneConfig },
+    /// A sled has more in-service discretionary zones than policy allows.
+    TooManyDiscretionaryZones { num_zones: usize, max: u32 },
 }
 
 impl fmt::Display for SledKind {
@@ -372,6 +419,95 @@ impl fmt::Display for SledKind {
                     dataset.kind, dataset.id, address,
                 )
             }
+            SledKind::SledMissingFromInventory { collection_id } => {
+                write!(
+                    f,
+                    "sled is missing from inventory collection {collection_id}"
+                )
+            }
+            SledKind::PhysicalDiskMissingFromInventory {
+                disk_id,
+                identity,
+                collection_id,
+            } => {
+                write!(
+                    f,
+                    "in-service disk {disk_id} ({identity:?}) is missing \
+                     from inventory collection {collection_id}",
+                )
+            }
+            SledKind::ZoneOnZpoolMissingFromInventory {
+                zone,
+                zpool,
+                collection_id,
+            } => {
+                write!(
+                    f,
+                    "in-service zone {:?} {} uses zpool {zpool}, which is \
+                     missing from inventory collection {collection_id}",
+                    zone.zone_type.kind(),
+                    zone.id,
+                )
+            }
+            SledKind::ZoneImageNotInDepot { zone, hash } => {
+                write!(
+                    f,
+                    "in-service zone {:?} {} uses image artifact {hash}, \
+                     which is not part of the target release and is not \
+                     running anywhere according to inventory",
+                    zone.zone_type.kind(),
+                    zone.id,
+                )
+            }
+            SledKind::DiscretionaryZoneOnReservedSled { zone } => {
+                write!(
+                    f,
+                    "in-service discretionary zone {:?} {} is on a sled \
+                     reserved for instances",
+                    zone.zone_type.kind(),
+                    zone.id,
+                )
+            }
+            SledKind::TooManyDiscretionaryZones { num_zones, max } => {
+                write!(
+                    f,
+                    "sled has {num_zones} in-service discretionary zones, \
+                     but policy allows at most {max}",
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FailureDomainKind {
+    /// Every in-service zone of a kind that should be spread across failure
+    /// domains is in this one failure domain, even though in-service sleds
+    /// span multiple failure domains.
+    AllReplicasInOneDomain {
+        zone_kind: ZoneKind,
+        zones: BTreeSet<OmicronZoneUuid>,
+        num_available_domains: usize,
+    },
+}
+
+impl fmt::Display for FailureDomainKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailureDomainKind::AllReplicasInOneDomain {
+                zone_kind,
+                zones,
+                num_available_domains,
+            } => {
+                write!(
+                    f,
+                    "all {} in-service {} zones are in this failure domain \
+                     (in-service sleds span {num_available_domains} \
+                     failure domains)",
+                    zones.len(),
+                    zone_kind.report_str(),
+                )
+            }
         }
     }
 }
@@ -416,20 +552,55 @@ impl fmt::Display for NoteDisplay<'_> {
 #[derive(Debug)]
 pub struct Blippy<'a> {
     blueprint: &'a Blueprint,
+    planning_input: Option<&'a PlanningInput>,
+    collection: Option<&'a Collection>,
     notes: Vec<Note>,
 }
 
 impl<'a> Blippy<'a> {
+    /// Prepare to check `blueprint`
+    ///
+    /// By default, only checks of the blueprint's internal consistency are
+    /// performed. Use [`Blippy::with_planning_input()`] and
+    /// [`Blippy::with_collection()`] to enable further checks. Checks are
+    /// performed by [`Blippy::into_report()`].
     pub fn new(blueprint: &'a Blueprint) -> Self {
-        let mut slf = Self { blueprint, notes: Vec::new() };
-        checks::perform_all_blueprint_only_checks(&mut slf);
-        slf
+        Self {
+            blueprint,
+            planning_input: None,
+            collection: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Also perform checks that require the planning input the blueprint was
+    /// (or will be) used with, such as whether redundant zones are spread
+    /// across failure domains.
+    pub fn with_planning_input(mut self, input: &'a PlanningInput) -> Self {
+        self.planning_input = Some(input);
+        self
+    }
+
+    /// Also perform checks that compare the blueprint against an inventory
+    /// collection (typically the latest one), such as whether all in-service
+    /// sleds, disks, and zpools are present in inventory.
+    pub fn with_collection(mut self, collection: &'a Collection) -> Self {
+        self.collection = Some(collection);
+        self
     }
 
     pub fn blueprint(&self) -> &'a Blueprint {
         self.blueprint
     }
 
+    pub fn planning_input(&self) -> Option<&'a PlanningInput> {
+        self.planning_input
+    }
+
+    pub fn collection(&self) -> Option<&'a Collection> {
+        self.collection
+    }
+
     pub(crate) fn push_sled_note(
         &mut self,
         sled_id: SledUuid,
@@ -439,10 +610,23 @@ impl<'a> Blippy<'a> {
         self.notes.push(Note { severity, kind: Kind::Sled { sled_id, kind } });
     }
 
+    pub(crate) fn push_failure_domain_note(
+        &mut self,
+        domain: FailureDomain,
+        severity: Severity,
+        kind: FailureDomainKind,
+    ) {
+        self.notes.push(Note {
+            severity,
+            kind: Kind::FailureDomain { domain, kind },
+        });
+    }
+
     pub fn into_report(
-        self,
+        mut self,
         sort_key: BlippyReportSortKey,
     ) -> BlippyReport<'a> {
+        checks::perform_all_checks(&mut self);
         BlippyReport::new(self.blueprint, self.notes, sort_key)
     }
 }
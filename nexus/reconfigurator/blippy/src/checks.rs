@@ -3,8 +3,10 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use crate::blippy::Blippy;
+use crate::blippy::FailureDomainKind;
 use crate::blippy::Severity;
 use crate::blippy::SledKind;
+use nexus_sled_agent_shared::inventory::OmicronZoneImageSource;
 use nexus_sled_agent_shared::inventory::ZoneKind;
 use nexus_types::deployment::BlueprintDatasetConfig;
 use nexus_types::deployment::BlueprintDatasetDisposition;
@@ -12,27 +14,62 @@ use nexus_types::deployment::BlueprintPhysicalDiskDisposition;
 use nexus_types::deployment::BlueprintSledConfig;
 use nexus_types::deployment::BlueprintZoneConfig;
 use nexus_types::deployment::BlueprintZoneDisposition;
+use nexus_types::deployment::BlueprintZoneImageSource;
 use nexus_types::deployment::BlueprintZoneType;
+use nexus_types::deployment::FailureDomain;
 use nexus_types::deployment::OmicronZoneExternalIp;
+use nexus_types::deployment::PlanningInput;
+use nexus_types::deployment::SledFilter;
+use nexus_types::deployment::ZonePlacementPolicy;
 use nexus_types::deployment::blueprint_zone_type;
+use nexus_types::external_api::views::SledState;
+use nexus_types::inventory::Collection;
 use omicron_common::address::DnsSubnet;
 use omicron_common::address::Ipv6Subnet;
 use omicron_common::address::SLED_PREFIX;
 use omicron_common::disk::DatasetKind;
+use omicron_common::disk::DiskIdentity;
 use omicron_uuid_kinds::SledUuid;
 use omicron_uuid_kinds::ZpoolUuid;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::btree_map::Entry;
 use std::net::Ipv6Addr;
+use tufaceous_artifact::ArtifactHash;
 
-pub(crate) fn perform_all_blueprint_only_checks(blippy: &mut Blippy<'_>) {
+pub(crate) fn perform_all_checks(blippy: &mut Blippy<'_>) {
+    perform_all_blueprint_only_checks(blippy);
+    if let Some(input) = blippy.planning_input() {
+        perform_all_planning_input_checks(blippy, input);
+    }
+    if let Some(collection) = blippy.collection() {
+        perform_all_collection_checks(blippy, collection);
+    }
+}
+
+fn perform_all_blueprint_only_checks(blippy: &mut Blippy<'_>) {
     check_underlay_ips(blippy);
     check_external_networking(blippy);
     check_dataset_zpool_uniqueness(blippy);
     check_datasets(blippy);
 }
 
+fn perform_all_planning_input_checks(
+    blippy: &mut Blippy<'_>,
+    input: &PlanningInput,
+) {
+    check_failure_domain_spread(blippy, input);
+    check_zone_placement_policy(blippy, input);
+}
+
+fn perform_all_collection_checks(
+    blippy: &mut Blippy<'_>,
+    collection: &Collection,
+) {
+    check_sleds_against_inventory(blippy, collection);
+    check_zone_images_against_inventory(blippy, collection);
+}
+
 fn check_underlay_ips(blippy: &mut Blippy<'_>) {
     let mut underlay_ips: BTreeMap<Ipv6Addr, &BlueprintZoneConfig> =
         BTreeMap::new();
@@ -540,6 +577,231 @@ fn check_datasets(blippy: &mut Blippy<'_>) {
     }
 }
 
+fn check_failure_domain_spread(blippy: &mut Blippy<'_>, input: &PlanningInput) {
+    let domains_by_sled: BTreeMap<SledUuid, &FailureDomain> = input
+        .all_sleds(SledFilter::InService)
+        .filter_map(|(sled_id, details)| {
+            details.failure_domain.as_ref().map(|domain| (sled_id, domain))
+        })
+        .collect();
+    let num_available_domains =
+        domains_by_sled.values().collect::<BTreeSet<_>>().len();
+
+    // If every in-service sled is in the same failure domain (or we don't
+    // know about any failure domains at all), there's nothing to spread
+    // across.
+    if num_available_domains < 2 {
+        return;
+    }
+
+    // Group the in-service zones of each kind we spread by failure domain. A
+    // zone on a sled with no known failure domain gets a `None` domain, which
+    // we treat as distinct from every known domain.
+    let mut zones_by_kind: BTreeMap<
+        ZoneKind,
+        BTreeMap<Option<&FailureDomain>, BTreeSet<_>>,
+    > = BTreeMap::new();
+    for (sled_id, zone) in blippy
+        .blueprint()
+        .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+    {
+        let zone_kind = zone.zone_type.kind();
+        if !FailureDomain::should_spread(zone_kind) {
+            continue;
+        }
+        zones_by_kind
+            .entry(zone_kind)
+            .or_default()
+            .entry(domains_by_sled.get(&sled_id).copied())
+            .or_default()
+            .insert(zone.id);
+    }
+
+    for (zone_kind, mut zones_by_domain) in zones_by_kind {
+        if zones_by_domain.len() != 1 {
+            continue;
+        }
+        let (domain, zones) =
+            zones_by_domain.pop_first().expect("checked length above");
+        let Some(domain) = domain else {
+            continue;
+        };
+        if zones.len() < 2 {
+            continue;
+        }
+        blippy.push_failure_domain_note(
+            domain.clone(),
+            Severity::Warning,
+            FailureDomainKind::AllReplicasInOneDomain {
+                zone_kind,
+                zones,
+                num_available_domains,
+            },
+        );
+    }
+}
+
+fn check_zone_placement_policy(blippy: &mut Blippy<'_>, input: &PlanningInput) {
+    let policy = input.zone_placement_policy();
+
+    for (&sled_id, sled_config) in &blippy.blueprint().sleds {
+        if sled_config.state != SledState::Active {
+            continue;
+        }
+
+        let zones = sled_config
+            .zones
+            .iter()
+            .filter(|z| {
+                z.disposition.is_in_service()
+                    && ZonePlacementPolicy::is_discretionary(z.zone_type.kind())
+            })
+            .collect::<Vec<_>>();
+
+        // The planner never places new discretionary zones on reserved
+        // sleds, but it doesn't move zones that were already there when the
+        // sled was reserved.
+        if policy.is_sled_reserved(sled_id) {
+            for zone in &zones {
+                blippy.push_sled_note(
+                    sled_id,
+                    Severity::Warning,
+                    SledKind::DiscretionaryZoneOnReservedSled {
+                        zone: (*zone).clone(),
+                    },
+                );
+            }
+        }
+
+        if let Some(max) = policy.max_discretionary_zones_per_sled {
+            if zones.len() > max as usize {
+                blippy.push_sled_note(
+                    sled_id,
+                    Severity::Warning,
+                    SledKind::TooManyDiscretionaryZones {
+                        num_zones: zones.len(),
+                        max,
+                    },
+                );
+            }
+        }
+    }
+}
+
+fn check_sleds_against_inventory(
+    blippy: &mut Blippy<'_>,
+    collection: &Collection,
+) {
+    for (&sled_id, sled_config) in &blippy.blueprint().sleds {
+        if sled_config.state != SledState::Active {
+            continue;
+        }
+
+        // Every active sled should be present in inventory. If it's not,
+        // there's nothing else to compare against.
+        let Some(sled_agent) = collection.sled_agents.get(&sled_id) else {
+            blippy.push_sled_note(
+                sled_id,
+                Severity::Warning,
+                SledKind::SledMissingFromInventory {
+                    collection_id: collection.id,
+                },
+            );
+            continue;
+        };
+
+        // Every in-service disk should be reported by the sled.
+        let inv_disks: BTreeSet<&DiskIdentity> =
+            sled_agent.disks.iter().map(|disk| &disk.identity).collect();
+        for disk in
+            sled_config.disks.iter().filter(|d| d.disposition.is_in_service())
+        {
+            if !inv_disks.contains(&disk.identity) {
+                blippy.push_sled_note(
+                    sled_id,
+                    Severity::Warning,
+                    SledKind::PhysicalDiskMissingFromInventory {
+                        disk_id: disk.id,
+                        identity: disk.identity.clone(),
+                        collection_id: collection.id,
+                    },
+                );
+            }
+        }
+
+        // Every zpool used by an in-service zone should be reported by the
+        // sled.
+        let inv_zpools: BTreeSet<ZpoolUuid> =
+            sled_agent.zpools.iter().map(|zpool| zpool.id).collect();
+        for zone in
+            sled_config.zones.iter().filter(|z| z.disposition.is_in_service())
+        {
+            let zpools = std::iter::once(&zone.filesystem_pool)
+                .chain(zone.zone_type.durable_zpool())
+                .collect::<BTreeSet<_>>();
+            for zpool in zpools {
+                if !inv_zpools.contains(&zpool.id()) {
+                    blippy.push_sled_note(
+                        sled_id,
+                        Severity::Warning,
+                        SledKind::ZoneOnZpoolMissingFromInventory {
+                            zone: zone.clone(),
+                            zpool: zpool.clone(),
+                            collection_id: collection.id,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+// We don't have a direct view of which artifacts are present in the repo
+// depot, so we consider an artifact to be known to the depot if it's part of
+// the current target release, or if inventory shows some zone already running
+// it (in which case it was necessarily fetched from the depot).
+fn check_zone_images_against_inventory(
+    blippy: &mut Blippy<'_>,
+    collection: &Collection,
+) {
+    let mut known_hashes: BTreeSet<ArtifactHash> = collection
+        .sled_agents
+        .values()
+        .flat_map(|sled_agent| sled_agent.omicron_zones.zones.iter())
+        .filter_map(|zone| match &zone.image_source {
+            OmicronZoneImageSource::Artifact { hash } => Some(*hash),
+            OmicronZoneImageSource::InstallDataset => None,
+        })
+        .collect();
+    if let Some(tuf_repo) =
+        blippy.planning_input().and_then(|input| input.tuf_repo())
+    {
+        known_hashes
+            .extend(tuf_repo.artifacts.iter().map(|artifact| artifact.hash));
+    }
+
+    for (sled_id, zone) in blippy
+        .blueprint()
+        .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+    {
+        match &zone.image_source {
+            BlueprintZoneImageSource::InstallDataset => {}
+            BlueprintZoneImageSource::Artifact { hash, .. } => {
+                if !known_hashes.contains(hash) {
+                    blippy.push_sled_note(
+                        sled_id,
+                        Severity::Warning,
+                        SledKind::ZoneImageNotInDepot {
+                            zone: zone.clone(),
+                            hash: *hash,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -548,9 +810,11 @@ mod tests {
     use crate::blippy::Note;
     use nexus_reconfigurator_planning::example::ExampleSystemBuilder;
     use nexus_reconfigurator_planning::example::example;
+    use nexus_types::deployment::BlueprintZoneImageVersion;
     use nexus_types::deployment::BlueprintZoneType;
     use nexus_types::deployment::blueprint_zone_type;
     use omicron_test_utils::dev::test_setup_log;
+    use omicron_uuid_kinds::OmicronZoneUuid;
     use std::mem;
 
     // The tests below all take the example blueprint, mutate in some invalid
@@ -560,10 +824,12 @@ mod tests {
     fn test_example_blueprint_is_blippy_clean() {
         static TEST_NAME: &str = "test_example_blueprint_is_blippy_clean";
         let logctx = test_setup_log(TEST_NAME);
-        let (_, _, blueprint) = example(&logctx.log, TEST_NAME);
+        let (collection, input, blueprint) = example(&logctx.log, TEST_NAME);
 
-        let report =
-            Blippy::new(&blueprint).into_report(BlippyReportSortKey::Kind);
+        let report = Blippy::new(&blueprint)
+            .with_planning_input(&input)
+            .with_collection(&collection)
+            .into_report(BlippyReportSortKey::Kind);
         if !report.notes().is_empty() {
             eprintln!("{}", report.display());
             panic!("example blueprint should have no blippy notes");
@@ -1569,4 +1835,287 @@ mod tests {
 
         logctx.cleanup_successful();
     }
+
+    #[test]
+    fn test_all_replicas_in_one_failure_domain() {
+        static TEST_NAME: &str = "test_all_replicas_in_one_failure_domain";
+        let logctx = test_setup_log(TEST_NAME);
+        let (example, blueprint) =
+            ExampleSystemBuilder::new(&logctx.log, TEST_NAME)
+                .nexus_count(2)
+                .build();
+
+        // Put both sleds running Nexus in one failure domain and the remaining
+        // sled in another.
+        let nexus_zones_by_sled: BTreeMap<SledUuid, OmicronZoneUuid> =
+            blueprint
+                .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+                .filter(|(_, zone)| zone.zone_type.is_nexus())
+                .map(|(sled_id, zone)| (sled_id, zone.id))
+                .collect();
+        assert_eq!(nexus_zones_by_sled.len(), 2);
+        let domain_a = FailureDomain::new("a");
+        let domain_b = FailureDomain::new("b");
+        let mut builder = example.input.into_builder();
+        for (sled_id, details) in builder.sleds_mut() {
+            details.failure_domain =
+                Some(if nexus_zones_by_sled.contains_key(sled_id) {
+                    domain_a.clone()
+                } else {
+                    domain_b.clone()
+                });
+        }
+        let input = builder.build();
+
+        // Without the planning input, blippy knows nothing about failure
+        // domains.
+        let report =
+            Blippy::new(&blueprint).into_report(BlippyReportSortKey::Kind);
+        assert!(report.notes().is_empty(), "{}", report.display());
+
+        // With it, we should get a warning about Nexus, but no other zone
+        // kinds (the example system places one internal DNS and one Crucible
+        // pantry zone on every sled).
+        let expected_notes = [Note {
+            severity: Severity::Warning,
+            kind: Kind::FailureDomain {
+                domain: domain_a,
+                kind: FailureDomainKind::AllReplicasInOneDomain {
+                    zone_kind: ZoneKind::Nexus,
+                    zones: nexus_zones_by_sled.into_values().collect(),
+                    num_available_domains: 2,
+                },
+            },
+        }];
+        let report = Blippy::new(&blueprint)
+            .with_planning_input(&input)
+            .into_report(BlippyReportSortKey::Kind);
+        eprintln!("{}", report.display());
+        assert_eq!(report.notes(), expected_notes);
+
+        logctx.cleanup_successful();
+    }
+
+    #[test]
+    fn test_zone_placement_policy_violations() {
+        static TEST_NAME: &str = "test_zone_placement_policy_violations";
+        let logctx = test_setup_log(TEST_NAME);
+        let (_, input, blueprint) = example(&logctx.log, TEST_NAME);
+
+        // The example system places no limits on zone placement.
+        let report = Blippy::new(&blueprint)
+            .with_planning_input(&input)
+            .into_report(BlippyReportSortKey::Kind);
+        assert!(report.notes().is_empty(), "{}", report.display());
+
+        // Reserve one sled for instances, and allow one fewer discretionary
+        // zone per sled than the example system already runs on each.
+        let mut sled_ids = blueprint.sleds.keys().copied();
+        let reserved_sled_id = sled_ids.next().expect("at least one sled");
+        let discretionary_zones = |sled_id| {
+            blueprint.sleds[&sled_id]
+                .zones
+                .iter()
+                .filter(|z| {
+                    z.disposition.is_in_service()
+                        && ZonePlacementPolicy::is_discretionary(
+                            z.zone_type.kind(),
+                        )
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        let num_zones = discretionary_zones(reserved_sled_id).len();
+        assert!(num_zones > 0, "example sled has discretionary zones");
+        let max = u32::try_from(num_zones - 1).unwrap();
+
+        let mut builder = input.into_builder();
+        builder.policy_mut().zone_placement_policy = ZonePlacementPolicy {
+            max_discretionary_zones_per_sled: Some(max),
+            reserved_sleds: BTreeSet::from([reserved_sled_id]),
+            ..ZonePlacementPolicy::new(2)
+        };
+        let input = builder.build();
+
+        let report = Blippy::new(&blueprint)
+            .with_planning_input(&input)
+            .into_report(BlippyReportSortKey::Kind);
+        eprintln!("{}", report.display());
+        for zone in discretionary_zones(reserved_sled_id) {
+            let note = Note {
+                severity: Severity::Warning,
+                kind: Kind::Sled {
+                    sled_id: reserved_sled_id,
+                    kind: SledKind::DiscretionaryZoneOnReservedSled { zone },
+                },
+            };
+            assert!(
+                report.notes().contains(&note),
+                "did not find expected note {note:?}"
+            );
+        }
+        for sled_id in blueprint.sleds.keys().copied() {
+            let num_zones = discretionary_zones(sled_id).len();
+            if num_zones <= max as usize {
+                continue;
+            }
+            let note = Note {
+                severity: Severity::Warning,
+                kind: Kind::Sled {
+                    sled_id,
+                    kind: SledKind::TooManyDiscretionaryZones {
+                        num_zones,
+                        max,
+                    },
+                },
+            };
+            assert!(
+                report.notes().contains(&note),
+                "did not find expected note {note:?}"
+            );
+        }
+
+        logctx.cleanup_successful();
+    }
+
+    #[test]
+    fn test_sleds_missing_from_inventory() {
+        static TEST_NAME: &str = "test_sleds_missing_from_inventory";
+        let logctx = test_setup_log(TEST_NAME);
+        let (mut collection, _, blueprint) = example(&logctx.log, TEST_NAME);
+
+        let mut sled_ids = blueprint.sleds.keys().copied();
+        let missing_sled_id = sled_ids.next().expect("at least one sled");
+        let modified_sled_id = sled_ids.next().expect("at least two sleds");
+        let mut expected_notes = Vec::new();
+
+        // Remove one sled from inventory entirely.
+        collection.sled_agents.remove(&missing_sled_id);
+        expected_notes.push(Note {
+            severity: Severity::Warning,
+            kind: Kind::Sled {
+                sled_id: missing_sled_id,
+                kind: SledKind::SledMissingFromInventory {
+                    collection_id: collection.id,
+                },
+            },
+        });
+
+        // Remove a disk and the zpool used by a Nexus zone from another sled.
+        let sled_config = &blueprint.sleds[&modified_sled_id];
+        let sled_agent = collection
+            .sled_agents
+            .get_mut(&modified_sled_id)
+            .expect("sled is present in inventory");
+        let disk = sled_config.disks.iter().next().expect("sled has a disk");
+        sled_agent.disks.retain(|d| d.identity != disk.identity);
+        expected_notes.push(Note {
+            severity: Severity::Warning,
+            kind: Kind::Sled {
+                sled_id: modified_sled_id,
+                kind: SledKind::PhysicalDiskMissingFromInventory {
+                    disk_id: disk.id,
+                    identity: disk.identity.clone(),
+                    collection_id: collection.id,
+                },
+            },
+        });
+        let nexus_zone = sled_config
+            .zones
+            .iter()
+            .find(|z| z.zone_type.is_nexus())
+            .expect("sled has a Nexus zone");
+        sled_agent
+            .zpools
+            .retain(|zpool| zpool.id != nexus_zone.filesystem_pool.id());
+        expected_notes.push(Note {
+            severity: Severity::Warning,
+            kind: Kind::Sled {
+                sled_id: modified_sled_id,
+                kind: SledKind::ZoneOnZpoolMissingFromInventory {
+                    zone: nexus_zone.clone(),
+                    zpool: nexus_zone.filesystem_pool.clone(),
+                    collection_id: collection.id,
+                },
+            },
+        });
+
+        // None of this is visible without the collection.
+        let report =
+            Blippy::new(&blueprint).into_report(BlippyReportSortKey::Kind);
+        assert!(report.notes().is_empty(), "{}", report.display());
+
+        let report = Blippy::new(&blueprint)
+            .with_collection(&collection)
+            .into_report(BlippyReportSortKey::Kind);
+        eprintln!("{}", report.display());
+        for note in expected_notes {
+            assert!(
+                report.notes().contains(&note),
+                "did not find expected note {note:?}"
+            );
+        }
+
+        logctx.cleanup_successful();
+    }
+
+    #[test]
+    fn test_zone_image_not_in_depot() {
+        static TEST_NAME: &str = "test_zone_image_not_in_depot";
+        let logctx = test_setup_log(TEST_NAME);
+        let (mut collection, input, mut blueprint) =
+            example(&logctx.log, TEST_NAME);
+
+        // Point a zone at an artifact that's neither in the (nonexistent)
+        // target release nor running anywhere.
+        let hash = ArtifactHash([1; 32]);
+        let (sled_id, zone) = {
+            let (&sled_id, sled_config) =
+                blueprint.sleds.iter_mut().next().expect("at least one sled");
+            let mut zone =
+                sled_config.zones.iter_mut().next().expect("sled has a zone");
+            zone.image_source = BlueprintZoneImageSource::Artifact {
+                version: BlueprintZoneImageVersion::Unknown,
+                hash,
+            };
+            (sled_id, zone.into_ref().clone())
+        };
+        let zone_id = zone.id;
+
+        let report = Blippy::new(&blueprint)
+            .with_planning_input(&input)
+            .with_collection(&collection)
+            .into_report(BlippyReportSortKey::Kind);
+        eprintln!("{}", report.display());
+        assert_eq!(
+            report.notes(),
+            [Note {
+                severity: Severity::Warning,
+                kind: Kind::Sled {
+                    sled_id,
+                    kind: SledKind::ZoneImageNotInDepot { zone, hash },
+                },
+            }]
+        );
+
+        // Once inventory reports the zone running that image, we know the
+        // depot has it.
+        let inv_zone = collection
+            .sled_agents
+            .get_mut(&sled_id)
+            .expect("sled is present in inventory")
+            .omicron_zones
+            .zones
+            .iter_mut()
+            .find(|z| z.id == zone_id)
+            .expect("zone is present in inventory");
+        inv_zone.image_source = OmicronZoneImageSource::Artifact { hash };
+        let report = Blippy::new(&blueprint)
+            .with_planning_input(&input)
+            .with_collection(&collection)
+            .into_report(BlippyReportSortKey::Kind);
+        assert!(report.notes().is_empty(), "{}", report.display());
+
+        logctx.cleanup_successful();
+    }
 }
@@ -10,12 +10,19 @@
 //! in-service zone"). It emits [`BlippyReport`]s in the form of a list of
 //! [`BlippyNote`]s, each of which has an associated severity and parent
 //! component (typically a sled).
+//!
+//! Blippy can additionally check the blueprint against the
+//! [`PlanningInput`](nexus_types::deployment::PlanningInput) (e.g., flagging
+//! redundant services whose replicas all share a failure domain) and against
+//! an inventory [`Collection`](nexus_types::inventory::Collection) (e.g.,
+//! flagging in-service zones on zpools that inventory no longer reports).
 
 mod blippy;
 mod checks;
 mod report;
 
 pub use blippy::Blippy;
+pub use blippy::FailureDomainKind as BlippyFailureDomainKind;
 pub use blippy::Kind as BlippyKind;
 pub use blippy::Note as BlippyNote;
 pub use blippy::Severity as BlippySeverity;
@@ -209,6 +209,7 @@ async fn test_blueprint_edit(cptestctx: &ControlPlaneTestContext) {
         .blueprint_target_set(&nexus_client::types::BlueprintTargetSet {
             target_id: new_blueprint.id,
             enabled: false,
+            actor: None,
         })
         .await
         .context("setting target blueprint")
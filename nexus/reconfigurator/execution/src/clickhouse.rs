@@ -80,6 +80,49 @@ pub(crate) async fn deploy_nodes(
     .await
 }
 
+/// Describe the requests [`deploy_nodes()`] would make, without making them
+pub(crate) fn plan_nodes(
+    blueprint: &Blueprint,
+    clickhouse_cluster_config: &ClickhouseClusterConfig,
+) -> anyhow::Result<Vec<String>> {
+    // See the comment in `deploy_nodes()` for why this uses
+    // `BlueprintZoneDisposition::any`.
+    let zones: Vec<_> = blueprint
+        .all_omicron_zones(BlueprintZoneDisposition::any)
+        .map(|(_, z)| z)
+        .collect();
+    let keeper_configs =
+        keeper_configs(zones.iter().copied(), clickhouse_cluster_config)?;
+    let keeper_hosts: Vec<_> = keeper_configs
+        .iter()
+        .map(|s| ClickhouseHost::Ipv6(s.settings.listen_addr))
+        .collect();
+    let server_configs = server_configs(
+        zones.iter().copied(),
+        clickhouse_cluster_config,
+        keeper_hosts,
+    )?;
+
+    let generation = clickhouse_cluster_config.generation;
+    let mut changes = Vec::new();
+    for config in &keeper_configs {
+        changes.push(format!(
+            "send config generation {generation} for clickhouse keeper {} \
+             to clickhouse-admin-keeper at [{}]:{CLICKHOUSE_ADMIN_PORT}",
+            config.settings.id, config.settings.listen_addr,
+        ));
+    }
+    for config in &server_configs {
+        changes.push(format!(
+            "send config generation {generation} for clickhouse server {} \
+             to clickhouse-admin-server at [{}]:{CLICKHOUSE_ADMIN_PORT} and \
+             initialize its database",
+            config.settings.id, config.settings.listen_addr,
+        ));
+    }
+    Ok(changes)
+}
+
 async fn deploy_nodes_impl<'a, I>(
     opctx: &OpContext,
     zones: I,
@@ -241,6 +284,22 @@ pub(crate) async fn deploy_single_node(
     .await
 }
 
+/// Describe the request [`deploy_single_node()`] would make, without making it
+pub(crate) fn plan_single_node(blueprint: &Blueprint) -> Vec<String> {
+    blueprint
+        .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+        .find(|(_, z)| z.zone_type.is_clickhouse())
+        .map(|(_, zone)| {
+            format!(
+                "initialize the single-node clickhouse database via \
+                 clickhouse-admin-single at [{}]:{CLICKHOUSE_ADMIN_PORT}",
+                zone.underlay_ip(),
+            )
+        })
+        .into_iter()
+        .collect()
+}
+
 async fn deploy_single_node_impl<'a, I>(
     opctx: &OpContext,
     mut zones: I,
@@ -5,6 +5,7 @@
 //! Ensures CockroachDB settings are set
 
 use anyhow::Context;
+use anyhow::bail;
 use nexus_db_queries::context::OpContext;
 use nexus_db_queries::db::DataStore;
 use nexus_types::deployment::Blueprint;
@@ -30,6 +31,38 @@ pub(crate) async fn ensure_settings(
     Ok(())
 }
 
+/// Describe the change [`ensure_settings()`] would make, without making it
+pub(crate) async fn plan_settings(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    blueprint: &Blueprint,
+) -> anyhow::Result<Vec<String>> {
+    let Some(value) =
+        blueprint.cockroachdb_setting_preserve_downgrade.to_optional_string()
+    else {
+        return Ok(Vec::new());
+    };
+    let settings = datastore
+        .cockroachdb_settings(opctx)
+        .await
+        .context("failed to read CockroachDB settings")?;
+    if settings.preserve_downgrade == value {
+        return Ok(Vec::new());
+    }
+    // Setting the value is conditional on the cluster's state fingerprint
+    // matching the one recorded in the blueprint.
+    if settings.state_fingerprint != blueprint.cockroachdb_fingerprint {
+        bail!(
+            "cannot set cluster.preserve_downgrade_option to {value:?}: the \
+             blueprint's CockroachDB state fingerprint is out of date"
+        );
+    }
+    Ok(vec![format!(
+        "set cluster.preserve_downgrade_option from {:?} to {value:?}",
+        settings.preserve_downgrade,
+    )])
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -5,6 +5,8 @@
 //! Propagates DNS changes in a given blueprint
 
 use crate::Sled;
+use internal_dns_types::config::DnsRecord;
+use internal_dns_types::config::Srv;
 use internal_dns_types::diff::DnsDiff;
 use nexus_db_model::DnsGroup;
 use nexus_db_queries::context::OpContext;
@@ -33,58 +35,14 @@ pub(crate) async fn deploy_dns(
     sleds_by_id: &BTreeMap<SledUuid, Sled>,
     overrides: &Overridables,
 ) -> Result<(), Error> {
-    // First, fetch the current DNS configs.
-    let internal_dns_config_current = datastore
-        .dns_config_read(opctx, DnsGroup::Internal)
-        .await
-        .internal_context("reading current DNS (internal)")?;
-    let external_dns_config_current = datastore
-        .dns_config_read(opctx, DnsGroup::External)
-        .await
-        .internal_context("reading current DNS (external)")?;
-
-    // We could check here that the DNS version we found isn't newer than when
-    // the blueprint was generated.  But we have to check later when we try to
-    // update the database anyway.  And we're not wasting much effort allowing
-    // this proceed for now.  This way, we have only one code path for this and
-    // we know it's being hit when we exercise this condition.
-
-    // Next, construct the DNS config represented by the blueprint.
-    let internal_dns_zone_blueprint =
-        blueprint_internal_dns_config(blueprint, sleds_by_id, overrides)
-            .map_err(|e| Error::InternalError {
-                internal_message: e.to_string(),
-            })?;
-    let silos = datastore
-        .silo_list_all_batched(opctx, Discoverability::All)
-        .await
-        .internal_context("listing Silos (for configuring external DNS)")?
-        .into_iter()
-        .map(|silo| silo.name().clone())
-        .collect::<Vec<_>>();
-
-    let nexus_external_dns_zone_names = datastore
-        .dns_zones_list_all(opctx, DnsGroup::External)
-        .await
-        .internal_context("listing DNS zones")?
-        .into_iter()
-        .map(|z| z.zone_name)
-        .collect::<Vec<_>>();
-    // Other parts of the system support multiple external DNS zone names.  We
-    // do not here.  If we decide to support this in the future, this mechanism
-    // will need to be updated.
-    bail_unless!(
-        nexus_external_dns_zone_names.len() == 1,
-        "expected exactly one external DNS zone"
-    );
-    // unwrap: we just checked the length.
-    let external_dns_zone_name =
-        nexus_external_dns_zone_names.into_iter().next().unwrap();
-    let external_dns_zone_blueprint = blueprint_external_dns_config(
+    let configs = BlueprintDnsConfigs::read(
+        opctx,
+        datastore,
         blueprint,
-        &silos,
-        external_dns_zone_name,
-    );
+        sleds_by_id,
+        overrides,
+    )
+    .await?;
 
     // Deploy the changes.
     deploy_dns_one(
@@ -92,8 +50,8 @@ pub(crate) async fn deploy_dns(
         datastore,
         creator.clone(),
         blueprint,
-        &internal_dns_config_current,
-        internal_dns_zone_blueprint,
+        &configs.internal_current,
+        configs.internal_blueprint,
         DnsGroup::Internal,
     )
     .await?;
@@ -102,14 +60,183 @@ pub(crate) async fn deploy_dns(
         datastore,
         creator,
         blueprint,
-        &external_dns_config_current,
-        external_dns_zone_blueprint,
+        &configs.external_current,
+        configs.external_blueprint,
         DnsGroup::External,
     )
     .await?;
     Ok(())
 }
 
+/// Describe the changes [`deploy_dns()`] would make, without making them
+pub(crate) async fn plan_dns(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    blueprint: &Blueprint,
+    sleds_by_id: &BTreeMap<SledUuid, Sled>,
+    overrides: &Overridables,
+) -> Result<Vec<String>, Error> {
+    let configs = BlueprintDnsConfigs::read(
+        opctx,
+        datastore,
+        blueprint,
+        sleds_by_id,
+        overrides,
+    )
+    .await?;
+
+    let mut changes = Vec::new();
+    plan_dns_one(
+        &mut changes,
+        &configs.internal_current,
+        &configs.internal_blueprint,
+        DnsGroup::Internal,
+    )?;
+    plan_dns_one(
+        &mut changes,
+        &configs.external_current,
+        &configs.external_blueprint,
+        DnsGroup::External,
+    )?;
+    Ok(changes)
+}
+
+fn plan_dns_one(
+    changes: &mut Vec<String>,
+    dns_config_current: &DnsConfigParams,
+    dns_zone_blueprint: &DnsConfigZone,
+    dns_group: DnsGroup,
+) -> Result<(), Error> {
+    let dns_zone_current = dns_config_current
+        .sole_zone()
+        .map_err(|e| Error::internal_error(&format!("{:#}", e)))?;
+    let diff = DnsDiff::new(&dns_zone_current, &dns_zone_blueprint)
+        .map_err(|e| Error::internal_error(&format!("{:#}", e)))?;
+    if diff.is_empty() {
+        return Ok(());
+    }
+
+    changes.push(format!(
+        "{dns_group} DNS zone {:?}: update from generation {} to {}",
+        dns_zone_blueprint.zone_name,
+        dns_config_current.generation,
+        dns_config_current.generation.next(),
+    ));
+    for (name, records) in diff.names_added() {
+        changes.push(format!(
+            "{dns_group} DNS: add name {name}: {}",
+            display_dns_records(records),
+        ));
+    }
+    for (name, records) in diff.names_removed() {
+        changes.push(format!(
+            "{dns_group} DNS: remove name {name}: {}",
+            display_dns_records(records),
+        ));
+    }
+    for (name, old_records, new_records) in diff.names_changed() {
+        changes.push(format!(
+            "{dns_group} DNS: change name {name}: {} -> {}",
+            display_dns_records(old_records),
+            display_dns_records(new_records),
+        ));
+    }
+    Ok(())
+}
+
+fn display_dns_records(records: &[DnsRecord]) -> String {
+    records
+        .iter()
+        .map(|r| match r {
+            DnsRecord::A(addr) => format!("A {addr}"),
+            DnsRecord::Aaaa(addr) => format!("AAAA {addr}"),
+            DnsRecord::Srv(Srv { port, target, .. }) => {
+                format!("SRV port {port} {target}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The current DNS configs alongside the DNS zones described by a blueprint
+struct BlueprintDnsConfigs {
+    internal_current: DnsConfigParams,
+    internal_blueprint: DnsConfigZone,
+    external_current: DnsConfigParams,
+    external_blueprint: DnsConfigZone,
+}
+
+impl BlueprintDnsConfigs {
+    async fn read(
+        opctx: &OpContext,
+        datastore: &DataStore,
+        blueprint: &Blueprint,
+        sleds_by_id: &BTreeMap<SledUuid, Sled>,
+        overrides: &Overridables,
+    ) -> Result<BlueprintDnsConfigs, Error> {
+        // First, fetch the current DNS configs.
+        let internal_dns_config_current = datastore
+            .dns_config_read(opctx, DnsGroup::Internal)
+            .await
+            .internal_context("reading current DNS (internal)")?;
+        let external_dns_config_current = datastore
+            .dns_config_read(opctx, DnsGroup::External)
+            .await
+            .internal_context("reading current DNS (external)")?;
+
+        // We could check here that the DNS version we found isn't newer than
+        // when the blueprint was generated.  But we have to check later when
+        // we try to update the database anyway.  And we're not wasting much
+        // effort allowing this proceed for now.  This way, we have only one
+        // code path for this and we know it's being hit when we exercise this
+        // condition.
+
+        // Next, construct the DNS config represented by the blueprint.
+        let internal_dns_zone_blueprint =
+            blueprint_internal_dns_config(blueprint, sleds_by_id, overrides)
+                .map_err(|e| Error::InternalError {
+                    internal_message: e.to_string(),
+                })?;
+        let silos = datastore
+            .silo_list_all_batched(opctx, Discoverability::All)
+            .await
+            .internal_context("listing Silos (for configuring external DNS)")?
+            .into_iter()
+            .map(|silo| silo.name().clone())
+            .collect::<Vec<_>>();
+
+        let nexus_external_dns_zone_names = datastore
+            .dns_zones_list_all(opctx, DnsGroup::External)
+            .await
+            .internal_context("listing DNS zones")?
+            .into_iter()
+            .map(|z| z.zone_name)
+            .collect::<Vec<_>>();
+        // Other parts of the system support multiple external DNS zone names.
+        // We do not here.  If we decide to support this in the future, this
+        // mechanism will need to be updated.
+        bail_unless!(
+            nexus_external_dns_zone_names.len() == 1,
+            "expected exactly one external DNS zone"
+        );
+        // unwrap: we just checked the length.
+        let external_dns_zone_name =
+            nexus_external_dns_zone_names.into_iter().next().unwrap();
+        let external_dns_zone_blueprint = blueprint_external_dns_config(
+            blueprint,
+            &silos,
+            external_dns_zone_name,
+        );
+
+        Ok(BlueprintDnsConfigs {
+            internal_current: internal_dns_config_current,
+            internal_blueprint: internal_dns_zone_blueprint,
+            external_current: external_dns_config_current,
+            external_blueprint: external_dns_zone_blueprint,
+        })
+    }
+}
+
 pub(crate) async fn deploy_dns_one(
     opctx: &OpContext,
     datastore: &DataStore,
@@ -343,6 +470,7 @@ mod test {
     use nexus_types::deployment::OximeterReadPolicy;
     use nexus_types::deployment::PendingMgsUpdates;
     use nexus_types::deployment::SledFilter;
+    use nexus_types::deployment::ZonePlacementPolicy;
     use nexus_types::deployment::blueprint_zone_type;
     use nexus_types::external_api::params;
     use nexus_types::external_api::shared;
@@ -704,6 +832,7 @@ mod test {
             time_created: now_db_precision(),
             creator: "test-suite".to_string(),
             comment: "test blueprint".to_string(),
+            planning_report: None,
         };
 
         // To make things slightly more interesting, let's add a zone that's
@@ -1424,6 +1553,9 @@ mod test {
                 target_crucible_pantry_zone_count: CRUCIBLE_PANTRY_REDUNDANCY,
                 clickhouse_policy: None,
                 oximeter_read_policy: OximeterReadPolicy::new(1),
+                tuf_repo: None,
+                zone_placement_policy: ZonePlacementPolicy::default(),
+                sled_failure_domains: BTreeMap::new(),
                 log,
             }
             .build()
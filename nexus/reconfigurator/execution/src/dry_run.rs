@@ -0,0 +1,710 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Computes what realizing a blueprint would change, without changing anything
+
+use crate::RealizeArgs;
+use crate::clickhouse;
+use crate::cockroachdb;
+use crate::dns;
+use crate::omicron_sled_config;
+use crate::register_sled_list_step;
+use anyhow::Context;
+use anyhow::anyhow;
+use nexus_db_lookup::LookupPath;
+use nexus_db_model::PhysicalDiskPolicy as DbPhysicalDiskPolicy;
+use nexus_db_model::PhysicalDiskState as DbPhysicalDiskState;
+use nexus_db_model::SledState as DbSledState;
+use nexus_db_queries::context::OpContext;
+use nexus_db_queries::db::DataStore;
+use nexus_types::deployment::Blueprint;
+use nexus_types::deployment::BlueprintDatasetDisposition;
+use nexus_types::deployment::BlueprintPhysicalDiskDisposition;
+use nexus_types::deployment::BlueprintZoneDisposition;
+use nexus_types::deployment::BlueprintZoneType;
+use nexus_types::deployment::PendingMgsUpdates;
+use nexus_types::deployment::execution::overridables;
+use nexus_types::deployment::execution::{
+    ExecutionComponent, ExecutionPlan, ExecutionPlanStep,
+    ExecutionPlanStepOutcome, ExecutionStepId, StepContext, StepHandle,
+    StepSkipped, UpdateEngine,
+};
+use nexus_types::external_api::views::SledState;
+use omicron_common::api::internal::shared::DatasetKind;
+use omicron_uuid_kinds::GenericUuid;
+use omicron_uuid_kinds::OmicronZoneUuid;
+use slog::info;
+use slog_error_chain::InlineErrorChain;
+use std::future::Future;
+use tokio::sync::watch;
+use update_engine::StepSuccess;
+use update_engine::StepWarning;
+use update_engine::merge_anyhow_list;
+
+/// Compute the changes [`crate::realize_blueprint`] would make to bring the
+/// system in line with the given blueprint, without making any of them
+///
+/// This accepts the same arguments as `realize_blueprint()` and runs one
+/// read-only step for each step of execution, in the same order, reporting
+/// progress to `sender` the same way. Each step compares the blueprint against
+/// the current state of the database, DNS, and the sled agents (which it may
+/// query) and records the requests and database changes that execution would
+/// make.
+pub async fn plan_blueprint_execution(
+    exec_ctx: RealizeArgs<'_>,
+) -> Result<ExecutionPlan, anyhow::Error> {
+    let RealizeArgs {
+        opctx,
+        datastore,
+        resolver: _,
+        blueprint,
+        nexus_id,
+        creator: _,
+        sender,
+        overrides,
+        mgs_updates,
+    } = exec_ctx;
+
+    info!(
+        opctx.log,
+        "planning execution of blueprint";
+        "blueprint_id" => %blueprint.id
+    );
+
+    let engine = UpdateEngine::new(&opctx.log, sender);
+    let mut steps = Vec::new();
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::ExternalNetworking,
+        ExecutionStepId::Ensure,
+        "Ensure external networking resources",
+        async move |_cx| {
+            plan_external_networking(opctx, datastore, blueprint).await.into()
+        },
+    ));
+
+    let sled_list = register_sled_list_step(
+        &engine.for_component(ExecutionComponent::SledList),
+        opctx,
+        datastore,
+    )
+    .into_shared();
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::SledAgent,
+        ExecutionStepId::Ensure,
+        "Deploy sled configs",
+        {
+            let sled_list = sled_list.clone();
+            async move |cx| {
+                let sleds_by_id = sled_list.into_value(cx.token()).await;
+                let (changes, errors) = omicron_sled_config::plan_sled_configs(
+                    opctx,
+                    &sleds_by_id,
+                    &blueprint.sleds,
+                )
+                .await;
+                if errors.is_empty() {
+                    PlannedChanges::new(changes)
+                } else {
+                    PlannedChanges::partial(changes, merge_anyhow_list(errors))
+                }
+            }
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::FirewallRules,
+        ExecutionStepId::Ensure,
+        "Plumb service firewall rules",
+        {
+            let sled_list = sled_list.clone();
+            async move |cx| {
+                // Plumbing firewall rules is idempotent and execution always
+                // does it, so the best we can do is say where they'd go.
+                let sleds_by_id = sled_list.into_value(cx.token()).await;
+                PlannedChanges::new(vec![format!(
+                    "send service firewall rules to the sled agents of {} \
+                     in-service sleds",
+                    sleds_by_id.len()
+                )])
+            }
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::Dns,
+        ExecutionStepId::Ensure,
+        "Deploy DNS records",
+        {
+            let sled_list = sled_list.clone();
+            let overrides = overrides.unwrap_or(&*overridables::DEFAULT);
+            async move |cx| {
+                let sleds_by_id = sled_list.into_value(cx.token()).await;
+                dns::plan_dns(
+                    opctx,
+                    datastore,
+                    blueprint,
+                    &sleds_by_id,
+                    overrides,
+                )
+                .await
+                .map_err(|e| anyhow!("{}", InlineErrorChain::new(&e)))
+                .into()
+            }
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::OmicronZones,
+        ExecutionStepId::Cleanup,
+        "Cleanup expunged zones",
+        async move |_cx| {
+            plan_clean_up_expunged_zones(opctx, datastore, blueprint)
+                .await
+                .into()
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::OmicronZones,
+        ExecutionStepId::Cleanup,
+        "Decommission sleds",
+        async move |_cx| {
+            plan_decommission_sleds(opctx, datastore, blueprint).await.into()
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::PhysicalDisks,
+        ExecutionStepId::Cleanup,
+        "Decommission expunged disks",
+        async move |_cx| {
+            plan_decommission_disks(opctx, datastore, blueprint).await.into()
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::Clickhouse,
+        ExecutionStepId::Ensure,
+        "Deploy clickhouse cluster nodes",
+        async move |_cx| match &blueprint.clickhouse_cluster_config {
+            Some(config) => clickhouse::plan_nodes(blueprint, config).into(),
+            None => PlannedChanges::new(Vec::new()),
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::Clickhouse,
+        ExecutionStepId::Ensure,
+        "Deploy single-node clickhouse cluster",
+        async move |_cx| {
+            PlannedChanges::new(clickhouse::plan_single_node(blueprint))
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::SupportBundles,
+        ExecutionStepId::Cleanup,
+        "Mark support bundles as failed if they rely on \
+         an expunged disk or sled",
+        async move |_cx| {
+            if nexus_id.is_none() {
+                return PlannedChanges::skipped("not running as Nexus");
+            }
+            PlannedChanges::new(plan_support_bundle_failure(blueprint))
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::OmicronZones,
+        ExecutionStepId::Cleanup,
+        "Reassign sagas",
+        async move |_cx| {
+            let Some(nexus_id) = nexus_id else {
+                return PlannedChanges::skipped("not running as Nexus");
+            };
+            plan_reassign_sagas(opctx, datastore, blueprint, nexus_id)
+                .await
+                .into()
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::Cockroach,
+        ExecutionStepId::Ensure,
+        "Ensure CockroachDB settings",
+        async move |_cx| {
+            cockroachdb::plan_settings(opctx, datastore, blueprint).await.into()
+        },
+    ));
+
+    steps.push(register_plan_step(
+        &engine,
+        ExecutionComponent::MgsUpdates,
+        ExecutionStepId::Ensure,
+        "Kick off MGS-managed updates",
+        move |_cx| async move {
+            PlannedChanges::new(plan_mgs_updates(blueprint, &mgs_updates))
+        },
+    ));
+
+    // All steps are registered, so execute the engine.
+    let result = engine.execute().await?;
+
+    let mut plan = ExecutionPlan {
+        blueprint_id: blueprint.id,
+        time_computed: chrono::Utc::now(),
+        steps: Vec::with_capacity(steps.len()),
+    };
+    for step in steps {
+        plan.steps.push(step.into_value(result.token()).await);
+    }
+    Ok(plan)
+}
+
+/// The changes one step would make, and whether they could all be determined
+struct PlannedChanges {
+    changes: Vec<String>,
+    outcome: ExecutionPlanStepOutcome,
+}
+
+impl PlannedChanges {
+    fn new(changes: Vec<String>) -> PlannedChanges {
+        PlannedChanges { changes, outcome: ExecutionPlanStepOutcome::Planned }
+    }
+
+    fn skipped(reason: &str) -> PlannedChanges {
+        PlannedChanges {
+            changes: Vec::new(),
+            outcome: ExecutionPlanStepOutcome::Skipped {
+                reason: reason.to_string(),
+            },
+        }
+    }
+
+    fn partial(changes: Vec<String>, error: anyhow::Error) -> PlannedChanges {
+        PlannedChanges {
+            changes,
+            outcome: ExecutionPlanStepOutcome::Warning {
+                message: format!("{error:#}"),
+            },
+        }
+    }
+}
+
+impl From<anyhow::Result<Vec<String>>> for PlannedChanges {
+    fn from(result: anyhow::Result<Vec<String>>) -> PlannedChanges {
+        match result {
+            Ok(changes) => PlannedChanges::new(changes),
+            Err(error) => PlannedChanges::partial(Vec::new(), error),
+        }
+    }
+}
+
+/// Register a step that computes the changes made by the execution step with
+/// the same component and description
+///
+/// Like most steps of execution itself, failing to determine the changes for
+/// one step is reported as a warning and doesn't stop later steps.
+fn register_plan_step<'a, F, Fut>(
+    engine: &UpdateEngine<'a>,
+    component: ExecutionComponent,
+    step_id: ExecutionStepId,
+    description: &'static str,
+    plan_fn: F,
+) -> StepHandle<ExecutionPlanStep>
+where
+    F: FnOnce(StepContext) -> Fut + Send + 'a,
+    Fut: Future<Output = PlannedChanges> + Send + 'a,
+{
+    engine
+        .for_component(component)
+        .new_step(step_id, description, move |cx| async move {
+            let PlannedChanges { changes, outcome } = plan_fn(cx).await;
+            let nchanges = changes.len();
+            let step = ExecutionPlanStep {
+                component,
+                description: description.to_string(),
+                outcome: outcome.clone(),
+                changes,
+            };
+            let result = match outcome {
+                ExecutionPlanStepOutcome::Planned => StepSuccess::new(step)
+                    .with_message(format!("{nchanges} change(s) planned"))
+                    .build(),
+                ExecutionPlanStepOutcome::Skipped { reason } => {
+                    StepSkipped::new(step, reason).build()
+                }
+                ExecutionPlanStepOutcome::Warning { message } => {
+                    StepWarning::new(step, message).build()
+                }
+            };
+            Ok(result)
+        })
+        .register()
+}
+
+async fn plan_external_networking(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    blueprint: &Blueprint,
+) -> anyhow::Result<Vec<String>> {
+    // Execution only changes external networking resources if the blueprint
+    // is the current target.
+    let target = datastore
+        .blueprint_target_get_current(opctx)
+        .await
+        .context("failed to read current target blueprint")?;
+    if target.target_id != blueprint.id {
+        return Err(anyhow!(
+            "blueprint is not the current target (current target is {}); \
+             execution would fail here until it is",
+            target.target_id
+        ));
+    }
+
+    let planned = datastore
+        .blueprint_external_networking_changes(opctx, blueprint)
+        .await
+        .context("failed to compare external networking resources")?;
+
+    let mut changes = Vec::new();
+    for (zone_id, kind, ip) in &planned.ips_to_deallocate {
+        changes.push(format!(
+            "deallocate external IP {} from {} zone {zone_id}",
+            ip.ip(),
+            kind.report_str(),
+        ));
+    }
+    for (zone_id, kind, nic) in &planned.nics_to_deallocate {
+        changes.push(format!(
+            "delete vNIC {} ({}) from {} zone {zone_id}",
+            nic.id,
+            nic.ip,
+            kind.report_str(),
+        ));
+    }
+    for (zone_id, kind, ip) in &planned.ips_to_allocate {
+        changes.push(format!(
+            "allocate external IP {} to {} zone {zone_id}",
+            ip.ip(),
+            kind.report_str(),
+        ));
+    }
+    for (zone_id, kind, nic) in &planned.nics_to_allocate {
+        changes.push(format!(
+            "create vNIC {} ({}, {}) for {} zone {zone_id}",
+            nic.id,
+            nic.ip,
+            nic.mac,
+            kind.report_str(),
+        ));
+    }
+    Ok(changes)
+}
+
+async fn plan_clean_up_expunged_zones(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    blueprint: &Blueprint,
+) -> anyhow::Result<Vec<String>> {
+    let mut changes = Vec::new();
+    for (sled_id, zone) in blueprint
+        .all_omicron_zones(BlueprintZoneDisposition::is_ready_for_cleanup)
+    {
+        match &zone.zone_type {
+            BlueprintZoneType::CockroachDb(_) => {
+                let node_id = datastore
+                    .cockroachdb_node_id(opctx, zone.id)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "failed to look up node ID of cockroach zone {}",
+                            zone.id
+                        )
+                    })?;
+                // Without a node ID, execution only logs a warning; see
+                // `decommission_cockroachdb_node()`.
+                if let Some(node_id) = node_id {
+                    changes.push(format!(
+                        "decommission CockroachDB node {node_id} \
+                         (zone {} on sled {sled_id}) via cockroach-admin",
+                        zone.id,
+                    ));
+                }
+            }
+            BlueprintZoneType::Oximeter(_) => {
+                changes.push(format!(
+                    "mark Oximeter collector {} (on sled {sled_id}) expunged \
+                     and reassign its metric producers",
+                    zone.id,
+                ));
+            }
+            // Execution does no cleanup for any other kind of zone.
+            BlueprintZoneType::Nexus(_)
+            | BlueprintZoneType::BoundaryNtp(_)
+            | BlueprintZoneType::Clickhouse(_)
+            | BlueprintZoneType::ClickhouseKeeper(_)
+            | BlueprintZoneType::ClickhouseServer(_)
+            | BlueprintZoneType::Crucible(_)
+            | BlueprintZoneType::CruciblePantry(_)
+            | BlueprintZoneType::ExternalDns(_)
+            | BlueprintZoneType::InternalDns(_)
+            | BlueprintZoneType::InternalNtp(_) => (),
+        }
+    }
+    Ok(changes)
+}
+
+async fn plan_decommission_sleds(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    blueprint: &Blueprint,
+) -> anyhow::Result<Vec<String>> {
+    let mut changes = Vec::new();
+    for (sled_id, _) in blueprint
+        .sleds
+        .iter()
+        .filter(|(_, sled)| sled.state == SledState::Decommissioned)
+    {
+        let (_, db_sled) = LookupPath::new(opctx, datastore)
+            .sled_id(sled_id.into_untyped_uuid())
+            .fetch()
+            .await
+            .with_context(|| format!("failed to look up sled {sled_id}"))?;
+        if db_sled.state() != DbSledState::Decommissioned {
+            changes.push(format!(
+                "set state of sled {sled_id} to decommissioned \
+                 (currently {})",
+                db_sled.state(),
+            ));
+        }
+    }
+    Ok(changes)
+}
+
+async fn plan_decommission_disks(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    blueprint: &Blueprint,
+) -> anyhow::Result<Vec<String>> {
+    let mut changes = Vec::new();
+    for (sled_id, disk) in blueprint.all_omicron_disks(
+        BlueprintPhysicalDiskDisposition::is_ready_for_cleanup,
+    ) {
+        let (_, db_disk) = LookupPath::new(opctx, datastore)
+            .physical_disk(disk.id)
+            .fetch()
+            .await
+            .with_context(|| {
+                format!("failed to look up physical disk {}", disk.id)
+            })?;
+        // This matches the conditions in `physical_disk_decommission()`.
+        if db_disk.disk_policy == DbPhysicalDiskPolicy::Expunged
+            && db_disk.disk_state != DbPhysicalDiskState::Decommissioned
+        {
+            changes.push(format!(
+                "set state of physical disk {} (on sled {sled_id}) to \
+                 decommissioned",
+                disk.id,
+            ));
+        }
+    }
+    Ok(changes)
+}
+
+fn plan_support_bundle_failure(blueprint: &Blueprint) -> Vec<String> {
+    // The bundles themselves are only examined inside the transaction that
+    // updates them, so describe what they'll be checked against.
+    let mut changes = Vec::new();
+    for (_, zone) in blueprint
+        .all_omicron_zones(BlueprintZoneDisposition::is_ready_for_cleanup)
+    {
+        if zone.zone_type.is_nexus() {
+            changes.push(format!(
+                "fail or reassign support bundles assigned to expunged \
+                 Nexus {}",
+                zone.id,
+            ));
+        }
+    }
+    for (_, dataset) in
+        blueprint.all_omicron_datasets(BlueprintDatasetDisposition::is_expunged)
+    {
+        if matches!(dataset.kind, DatasetKind::Debug) {
+            changes.push(format!(
+                "fail or delete support bundles stored on expunged debug \
+                 dataset {}",
+                dataset.id,
+            ));
+        }
+    }
+    changes
+}
+
+async fn plan_reassign_sagas(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    blueprint: &Blueprint,
+    nexus_id: OmicronZoneUuid,
+) -> anyhow::Result<Vec<String>> {
+    let mut changes = Vec::new();
+    for (_, zone) in blueprint
+        .all_omicron_zones(BlueprintZoneDisposition::is_ready_for_cleanup)
+    {
+        if !zone.zone_type.is_nexus() {
+            continue;
+        }
+        let sec_id = nexus_db_model::SecId(zone.id.into_untyped_uuid());
+        let sagas = datastore
+            .saga_list_recovery_candidates_batched(opctx, sec_id)
+            .await
+            .with_context(|| {
+                format!("failed to list sagas assigned to Nexus {}", zone.id)
+            })?;
+        if !sagas.is_empty() {
+            changes.push(format!(
+                "reassign {} unfinished sagas from expunged Nexus {} to \
+                 Nexus {nexus_id} (and activate saga recovery)",
+                sagas.len(),
+                zone.id,
+            ));
+        }
+    }
+    Ok(changes)
+}
+
+fn plan_mgs_updates(
+    blueprint: &Blueprint,
+    mgs_updates: &watch::Sender<PendingMgsUpdates>,
+) -> Vec<String> {
+    let current = mgs_updates.borrow();
+    let mut changes = Vec::new();
+    for update in current.iter() {
+        if blueprint.pending_mgs_updates.get(&update.baseboard_id)
+            != Some(update)
+        {
+            changes.push(format!(
+                "stop update of {:?} {} (serial {}, slot {}) to {}",
+                update.sp_type,
+                update.baseboard_id.part_number,
+                update.baseboard_id.serial_number,
+                update.slot_id,
+                update.artifact_version,
+            ));
+        }
+    }
+    for update in blueprint.pending_mgs_updates.iter() {
+        if current.get(&update.baseboard_id) != Some(update) {
+            changes.push(format!(
+                "start update of {:?} {} (serial {}, slot {}) to {}",
+                update.sp_type,
+                update.baseboard_id.part_number,
+                update.baseboard_id.serial_number,
+                update.slot_id,
+                update.artifact_version,
+            ));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::RequiredRealizeArgs;
+    use crate::test_utils::overridables_for_test;
+    use crate::test_utils::realize_blueprint_and_expect;
+    use nexus_db_queries::authn;
+    use nexus_db_queries::authz;
+    use nexus_test_utils::resource_helpers::DiskTest;
+    use nexus_test_utils_macros::nexus_test;
+    use nexus_types::deployment::execution::EventBuffer;
+    use std::sync::Arc;
+
+    type ControlPlaneTestContext =
+        nexus_test_utils::ControlPlaneTestContext<omicron_nexus::Server>;
+
+    #[nexus_test(extra_sled_agents = 1)]
+    async fn test_plan_realized_blueprint(cptestctx: &ControlPlaneTestContext) {
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let resolver = nexus.resolver();
+        let log = &cptestctx.logctx.log;
+        let opctx = OpContext::for_background(
+            log.clone(),
+            Arc::new(authz::Authz::new(log)),
+            authn::Context::internal_api(),
+            datastore.clone(),
+        );
+
+        // Fetch and execute the initial blueprint installed during rack
+        // initialization.
+        let (_blueprint_target, blueprint) = datastore
+            .blueprint_target_get_current_full(&opctx)
+            .await
+            .expect("failed to get blueprint from datastore");
+        let mut disk_test = DiskTest::new(&cptestctx).await;
+        disk_test.add_blueprint_disks(&blueprint).await;
+        let overrides = overridables_for_test(cptestctx);
+        _ = realize_blueprint_and_expect(
+            &opctx, datastore, resolver, &blueprint, &overrides,
+        )
+        .await;
+
+        // Now that it's been executed, planning its execution again should
+        // find nothing left to do.
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(128);
+        let receiver_task = tokio::spawn(async move {
+            let mut buffer = EventBuffer::default();
+            while let Some(msg) = receiver.recv().await {
+                buffer.add_event(msg);
+            }
+            buffer
+        });
+        let (mgs_updates, _rx) = watch::channel(PendingMgsUpdates::new());
+        let nexus_id = OmicronZoneUuid::new_v4();
+        let plan = plan_blueprint_execution(
+            RequiredRealizeArgs {
+                opctx: &opctx,
+                datastore,
+                resolver,
+                creator: nexus_id,
+                blueprint: &blueprint,
+                sender,
+                mgs_updates,
+            }
+            .with_overrides(&overrides)
+            .as_nexus(nexus_id),
+        )
+        .await
+        .expect("failed to plan blueprint execution");
+        receiver_task.await.expect("failed to receive events");
+        eprintln!("{plan}");
+
+        assert_eq!(plan.blueprint_id, blueprint.id);
+        for step in &plan.steps {
+            assert_eq!(
+                step.outcome,
+                ExecutionPlanStepOutcome::Planned,
+                "unexpected outcome for step {:?}",
+                step.description
+            );
+        }
+        assert!(plan.is_empty(), "expected no changes in plan:\n{plan}");
+    }
+}
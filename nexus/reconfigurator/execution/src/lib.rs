@@ -36,6 +36,7 @@ use update_engine::merge_anyhow_list;
 mod clickhouse;
 mod cockroachdb;
 mod dns;
+mod dry_run;
 mod omicron_physical_disks;
 mod omicron_sled_config;
 mod omicron_zones;
@@ -44,6 +45,8 @@ mod sled_state;
 #[cfg(test)]
 mod test_utils;
 
+pub use dry_run::plan_blueprint_execution;
+
 /// Encapsulates arguments used for [`realize_blueprint`]
 ///
 /// You probably want to start with `RequiredRealizeArgs` and use the
@@ -10,6 +10,8 @@ use anyhow::anyhow;
 use futures::StreamExt;
 use futures::stream;
 use nexus_db_queries::context::OpContext;
+use nexus_sled_agent_shared::inventory::Inventory;
+use nexus_sled_agent_shared::inventory::OmicronSledConfig;
 use nexus_sled_agent_shared::inventory::OmicronSledConfigResult;
 use nexus_types::deployment::BlueprintSledConfig;
 use omicron_uuid_kinds::GenericUuid;
@@ -18,6 +20,7 @@ use slog::Logger;
 use slog::info;
 use slog::warn;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use update_engine::merge_anyhow_list;
 
 /// Idempotently ensure that the specified Omicron sled configs are deployed to
@@ -78,6 +81,131 @@ pub(crate) async fn deploy_sled_configs(
     if errors.is_empty() { Ok(()) } else { Err(errors) }
 }
 
+/// Describe the requests [`deploy_sled_configs()`] would make, without making
+/// them
+///
+/// This compares each sled's blueprint config against what its sled agent
+/// reports in its inventory. Sleds that can't be reached are reported as
+/// errors alongside the changes that could be determined.
+pub(crate) async fn plan_sled_configs(
+    opctx: &OpContext,
+    sleds_by_id: &BTreeMap<SledUuid, Sled>,
+    sled_configs: &BTreeMap<SledUuid, BlueprintSledConfig>,
+) -> (Vec<String>, Vec<anyhow::Error>) {
+    let results: Vec<_> = stream::iter(sled_configs)
+        .then(async |(sled_id, config)| {
+            let Some(db_sled) = sleds_by_id.get(&sled_id) else {
+                if config.are_all_items_expunged() {
+                    return Ok(Vec::new());
+                }
+                return Err(anyhow!("sled not found in db list: {}", sled_id));
+            };
+
+            let log = opctx.log.new(slog::o!(
+                "sled_id" => sled_id.to_string(),
+                "generation" => i64::from(&config.sled_agent_generation),
+            ));
+            let client = nexus_networking::sled_client_from_address(
+                sled_id.into_untyped_uuid(),
+                db_sled.sled_agent_address(),
+                &log,
+            );
+            let inventory = client
+                .inventory()
+                .await
+                .with_context(|| {
+                    format!("Failed to fetch inventory from sled {sled_id}")
+                })?
+                .into_inner();
+
+            Ok(plan_sled_config(
+                *sled_id,
+                db_sled,
+                config.clone().into_in_service_sled_config(),
+                &inventory,
+            ))
+        })
+        .collect()
+        .await;
+
+    let mut changes = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(mut sled_changes) => changes.append(&mut sled_changes),
+            Err(error) => errors.push(error),
+        }
+    }
+    (changes, errors)
+}
+
+fn plan_sled_config(
+    sled_id: SledUuid,
+    db_sled: &Sled,
+    config: OmicronSledConfig,
+    inventory: &Inventory,
+) -> Vec<String> {
+    let current_zone_ids: BTreeSet<_> =
+        inventory.omicron_zones.zones.iter().map(|z| z.id).collect();
+    let current_dataset_ids: BTreeSet<_> =
+        inventory.datasets.iter().filter_map(|d| d.id).collect();
+
+    let mut changes = Vec::new();
+    for zone in &config.zones_config.zones {
+        if !current_zone_ids.contains(&zone.id) {
+            changes.push(format!(
+                "sled {sled_id}: start {} zone {}",
+                zone.zone_type.kind().report_str(),
+                zone.id,
+            ));
+        }
+    }
+    let new_zone_ids: BTreeSet<_> =
+        config.zones_config.zones.iter().map(|z| z.id).collect();
+    for zone in &inventory.omicron_zones.zones {
+        if !new_zone_ids.contains(&zone.id) {
+            changes.push(format!(
+                "sled {sled_id}: stop {} zone {}",
+                zone.zone_type.kind().report_str(),
+                zone.id,
+            ));
+        }
+    }
+    for (id, dataset) in &config.datasets_config.datasets {
+        if !current_dataset_ids.contains(id) {
+            changes.push(format!(
+                "sled {sled_id}: create {} dataset {id} on {}",
+                dataset.name.kind(),
+                dataset.name.pool(),
+            ));
+        }
+    }
+
+    let generation = config.zones_config.generation;
+    if !changes.is_empty()
+        || inventory.omicron_zones.generation != generation
+        || inventory.omicron_physical_disks_generation
+            != config.disks_config.generation
+    {
+        changes.insert(
+            0,
+            format!(
+                "sled {sled_id}: PUT omicron config to sled agent at {} \
+                 (zones generation {} -> {generation}, disks generation {} \
+                 -> {}, {} disks, {} datasets, {} zones)",
+                db_sled.sled_agent_address(),
+                inventory.omicron_zones.generation,
+                inventory.omicron_physical_disks_generation,
+                config.disks_config.generation,
+                config.disks_config.disks.len(),
+                config.datasets_config.datasets.len(),
+                config.zones_config.zones.len(),
+            ),
+        );
+    }
+    changes
+}
+
 fn parse_config_result(
     result: OmicronSledConfigResult,
     log: &Logger,
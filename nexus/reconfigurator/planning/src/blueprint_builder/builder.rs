@@ -43,6 +43,7 @@ use nexus_types::deployment::OmicronZoneExternalSnatIp;
 use nexus_types::deployment::OximeterReadMode;
 use nexus_types::deployment::PendingMgsUpdates;
 use nexus_types::deployment::PlanningInput;
+use nexus_types::deployment::PlanningReport;
 use nexus_types::deployment::SledFilter;
 use nexus_types::deployment::SledResources;
 use nexus_types::deployment::ZpoolFilter;
@@ -408,6 +409,7 @@ pub struct BlueprintBuilder<'a> {
     operations: Vec<Operation>,
     comments: Vec<String>,
     pending_mgs_updates: PendingMgsUpdates,
+    planning_report: Option<PlanningReport>,
 
     // Random number generator for new UUIDs
     rng: PlannerRng,
@@ -472,6 +474,7 @@ impl<'a> BlueprintBuilder<'a> {
             time_created: now_db_precision(),
             creator: creator.to_owned(),
             comment: format!("starting blueprint with {num_sleds} empty sleds"),
+            planning_report: None,
         }
     }
 
@@ -542,6 +545,7 @@ impl<'a> BlueprintBuilder<'a> {
             creator: creator.to_owned(),
             operations: Vec::new(),
             comments: Vec::new(),
+            planning_report: None,
             rng: PlannerRng::from_entropy(),
         })
     }
@@ -725,6 +729,7 @@ impl<'a> BlueprintBuilder<'a> {
                 .chain(self.operations.iter().map(|op| op.to_string()))
                 .collect::<Vec<String>>()
                 .join(", "),
+            planning_report: self.planning_report,
         }
     }
 
@@ -773,6 +778,11 @@ impl<'a> BlueprintBuilder<'a> {
         self.comments.push(String::from(comment));
     }
 
+    /// Attaches the planner's record of its decisions to the blueprint.
+    pub(crate) fn set_planning_report(&mut self, report: PlanningReport) {
+        self.planning_report = Some(report);
+    }
+
     /// Records an operation to the blueprint, identifying what changes have
     /// occurred.
     ///
@@ -4,6 +4,7 @@
 
 //! Example blueprints
 
+use std::collections::BTreeSet;
 use std::fmt;
 use std::hash::Hash;
 use std::net::IpAddr;
@@ -13,17 +14,30 @@ use crate::blueprint_builder::BlueprintBuilder;
 use crate::planner::rng::PlannerRng;
 use crate::system::SledBuilder;
 use crate::system::SystemDescription;
+use chrono::DateTime;
+use chrono::Utc;
 use nexus_inventory::CollectionBuilderRng;
+use nexus_sled_agent_shared::inventory::ZoneKind;
 use nexus_types::deployment::Blueprint;
 use nexus_types::deployment::OmicronZoneNic;
 use nexus_types::deployment::PlanningInput;
 use nexus_types::deployment::SledFilter;
 use nexus_types::inventory::Collection;
+use omicron_common::api::external::TufArtifactMeta;
+use omicron_common::api::external::TufRepoDescription;
+use omicron_common::api::external::TufRepoMeta;
 use omicron_common::policy::CRUCIBLE_PANTRY_REDUNDANCY;
 use omicron_common::policy::INTERNAL_DNS_REDUNDANCY;
+use omicron_common::update::ArtifactId;
 use omicron_uuid_kinds::GenericUuid;
 use omicron_uuid_kinds::SledKind;
 use omicron_uuid_kinds::VnicUuid;
+use sha2::Digest;
+use sha2::Sha256;
+use strum::IntoEnumIterator;
+use tufaceous_artifact::ArtifactHash;
+use tufaceous_artifact::ArtifactVersion;
+use tufaceous_artifact::KnownArtifactKind;
 use typed_rng::TypedUuidRng;
 
 /// Stateful PRNG for generating simulated systems.
@@ -522,6 +536,45 @@ impl ExampleSystemBuilder {
     }
 }
 
+/// Returns a TUF repo description for a fake release at `system_version`.
+///
+/// The repo contains one zone artifact per kind of Omicron zone, each at
+/// `system_version`. Artifact hashes are derived from the artifact's name and
+/// version, so the same version always produces the same repo, and different
+/// versions produce different hashes.
+pub fn example_tuf_repo(system_version: semver::Version) -> TufRepoDescription {
+    let version = ArtifactVersion::new(system_version.to_string())
+        .expect("semver versions are valid artifact versions");
+    let artifact_names: BTreeSet<&'static str> =
+        ZoneKind::iter().map(|kind| kind.artifact_name()).collect();
+    let artifacts = artifact_names
+        .into_iter()
+        .map(|name| TufArtifactMeta {
+            id: ArtifactId {
+                name: name.to_owned(),
+                version: version.clone(),
+                kind: KnownArtifactKind::Zone.into(),
+            },
+            hash: ArtifactHash(
+                Sha256::digest(format!("{name}-{system_version}")).into(),
+            ),
+            size: 0,
+        })
+        .collect();
+    TufRepoDescription {
+        repo: TufRepoMeta {
+            hash: ArtifactHash(
+                Sha256::digest(format!("repo-{system_version}")).into(),
+            ),
+            targets_role_version: 0,
+            valid_until: DateTime::<Utc>::MAX_UTC,
+            system_version: system_version.clone(),
+            file_name: format!("repo-{system_version}.zip"),
+        },
+        artifacts,
+    }
+}
+
 // A little wrapper to try and avoid having an `on` function which takes 3
 // usize parameters.
 #[derive(Clone, Copy, Debug)]
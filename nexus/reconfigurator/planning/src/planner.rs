@@ -14,16 +14,22 @@ use crate::blueprint_builder::Operation;
 use crate::blueprint_editor::DisksEditError;
 use crate::blueprint_editor::SledEditError;
 use crate::planner::omicron_zone_placement::PlacementError;
+use nexus_sled_agent_shared::inventory::OmicronZoneImageSource;
 use nexus_sled_agent_shared::inventory::OmicronZoneType;
 use nexus_sled_agent_shared::inventory::ZoneKind;
 use nexus_types::deployment::Blueprint;
 use nexus_types::deployment::BlueprintPhysicalDiskDisposition;
 use nexus_types::deployment::BlueprintZoneDisposition;
+use nexus_types::deployment::BlueprintZoneImageSource;
+use nexus_types::deployment::BlueprintZoneImageVersion;
 use nexus_types::deployment::CockroachDbClusterVersion;
 use nexus_types::deployment::CockroachDbPreserveDowngrade;
 use nexus_types::deployment::CockroachDbSettings;
 use nexus_types::deployment::DiskFilter;
 use nexus_types::deployment::PlanningInput;
+use nexus_types::deployment::PlanningOutcome;
+use nexus_types::deployment::PlanningReport;
+use nexus_types::deployment::PlanningStep;
 use nexus_types::deployment::SledDetails;
 use nexus_types::deployment::SledFilter;
 use nexus_types::deployment::ZpoolFilter;
@@ -36,8 +42,10 @@ use omicron_uuid_kinds::PhysicalDiskUuid;
 use omicron_uuid_kinds::SledUuid;
 use slog::error;
 use slog::{Logger, info, warn};
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::str::FromStr;
+use tufaceous_artifact::KnownArtifactKind;
 
 pub(crate) use self::omicron_zone_placement::DiscretionaryOmicronZone;
 use self::omicron_zone_placement::OmicronZonePlacement;
@@ -62,6 +70,9 @@ pub struct Planner<'a> {
     // information about all sleds that we expect), we should verify that up
     // front and update callers to ensure that it's true.
     inventory: &'a Collection,
+    // record of the decisions made so far, attached to the blueprint we
+    // produce
+    report: PlanningReport,
 }
 
 impl<'a> Planner<'a> {
@@ -81,7 +92,13 @@ impl<'a> Planner<'a> {
             inventory,
             creator,
         )?;
-        Ok(Planner { log, input, blueprint, inventory })
+        Ok(Planner {
+            log,
+            input,
+            blueprint,
+            inventory,
+            report: PlanningReport::new(),
+        })
     }
 
     /// Within tests, set a seeded RNG for deterministic results.
@@ -98,9 +115,23 @@ impl<'a> Planner<'a> {
     pub fn plan(mut self) -> Result<Blueprint, Error> {
         self.check_input_validity()?;
         self.do_plan()?;
+        let report = std::mem::take(&mut self.report);
+        self.blueprint.set_planning_report(report);
         Ok(self.blueprint.build())
     }
 
+    /// Record a decision in the planning report.
+    fn record(
+        &mut self,
+        step: PlanningStep,
+        sled_id: Option<SledUuid>,
+        zone_kind: Option<ZoneKind>,
+        outcome: PlanningOutcome,
+        reason: impl Into<String>,
+    ) {
+        self.report.record(step, sled_id, zone_kind, outcome, reason);
+    }
+
     fn check_input_validity(&self) -> Result<(), Error> {
         if self.input.target_internal_dns_zone_count() > INTERNAL_DNS_REDUNDANCY
         {
@@ -116,6 +147,7 @@ impl<'a> Planner<'a> {
         self.do_plan_expunge()?;
         self.do_plan_add()?;
         self.do_plan_decommission()?;
+        self.do_plan_zone_updates()?;
         self.do_plan_cockroachdb_settings();
 
         Ok(())
@@ -201,6 +233,23 @@ impl<'a> Planner<'a> {
 
             if all_zones_expunged && num_instances_assigned == 0 {
                 self.blueprint.set_sled_decommissioned(sled_id)?;
+                self.record(
+                    PlanningStep::Decommission,
+                    Some(sled_id),
+                    None,
+                    PlanningOutcome::Changed,
+                    "sled is expunged and all of its zones are expunged; \
+                     decommissioning it",
+                );
+            } else {
+                self.record(
+                    PlanningStep::Decommission,
+                    Some(sled_id),
+                    None,
+                    PlanningOutcome::Blocked,
+                    "sled is expunged, but not all of its zones are expunged \
+                     yet",
+                );
             }
         }
 
@@ -222,6 +271,29 @@ impl<'a> Planner<'a> {
         else {
             // There is no current inventory for the sled agent, so we cannot
             // decommission any disks.
+            let has_disks_to_decommission = self
+                .blueprint
+                .current_sled_disks(sled_id, |disposition| {
+                    matches!(
+                        disposition,
+                        BlueprintPhysicalDiskDisposition::Expunged {
+                            ready_for_cleanup: false,
+                            ..
+                        }
+                    )
+                })
+                .next()
+                .is_some();
+            if has_disks_to_decommission {
+                self.record(
+                    PlanningStep::Decommission,
+                    Some(sled_id),
+                    None,
+                    PlanningOutcome::Blocked,
+                    "sled has expunged disks, but is not present in the \
+                     latest inventory collection",
+                );
+            }
             return Ok(());
         };
 
@@ -249,6 +321,19 @@ impl<'a> Planner<'a> {
             })
             .collect();
 
+        if !disks_to_decommission.is_empty() {
+            self.record(
+                PlanningStep::Decommission,
+                Some(sled_id),
+                None,
+                PlanningOutcome::Changed,
+                format!(
+                    "decommissioning {} expunged disk(s) that the sled agent \
+                     has seen expunged",
+                    disks_to_decommission.len(),
+                ),
+            );
+        }
         self.blueprint.sled_decommission_disks(sled_id, disks_to_decommission)
     }
 
@@ -321,7 +406,17 @@ impl<'a> Planner<'a> {
                     })
                 {
                     match self.blueprint.expunge_disk(sled_id, disk.disk_id) {
-                        Ok(()) => (),
+                        Ok(()) => self.record(
+                            PlanningStep::Expunge,
+                            Some(sled_id),
+                            None,
+                            PlanningOutcome::Changed,
+                            format!(
+                                "disk {} has policy expunged; expunging it \
+                                 and anything using it",
+                                disk.disk_id,
+                            ),
+                        ),
                         Err(Error::SledEditError {
                             err:
                                 SledEditError::EditDisks(
@@ -344,6 +439,17 @@ impl<'a> Planner<'a> {
                                 "sled_id" => %sled_id,
                                 "disk" => ?disk,
                             );
+                            self.record(
+                                PlanningStep::Expunge,
+                                Some(sled_id),
+                                None,
+                                PlanningOutcome::Unchanged,
+                                format!(
+                                    "disk {} has policy expunged, but is not \
+                                     present in the parent blueprint",
+                                    disk.disk_id,
+                                ),
+                            );
                         }
                         Err(err) => return Err(err),
                     }
@@ -383,6 +489,14 @@ impl<'a> Planner<'a> {
                 match self.blueprint.current_sled_state(sled_id)? {
                     SledState::Active => {
                         self.blueprint.expunge_sled(sled_id)?;
+                        self.record(
+                            PlanningStep::Expunge,
+                            Some(sled_id),
+                            None,
+                            PlanningOutcome::Changed,
+                            "sled has policy expunged; expunging all of its \
+                             disks and zones",
+                        );
                     }
                     // If the sled is decommissioned, we've already expunged it
                     // in a prior planning run.
@@ -405,6 +519,30 @@ impl<'a> Planner<'a> {
                 (sled not present in latest inventory collection)";
                 "sled_id" => %sled_id,
             );
+            if self
+                .blueprint
+                .current_sled_zones(sled_id, |disposition| {
+                    matches!(
+                        disposition,
+                        BlueprintZoneDisposition::Expunged {
+                            ready_for_cleanup: false,
+                            ..
+                        }
+                    )
+                })
+                .next()
+                .is_some()
+            {
+                self.record(
+                    PlanningStep::Expunge,
+                    Some(sled_id),
+                    None,
+                    PlanningOutcome::Blocked,
+                    "sled has expunged zones, but is not present in the \
+                     latest inventory collection; can't tell whether they're \
+                     ready for cleanup",
+                );
+            }
             return Ok(());
         };
 
@@ -438,6 +576,17 @@ impl<'a> Planner<'a> {
                 sled_id,
                 &zones_ready_for_cleanup,
             )?;
+            self.record(
+                PlanningStep::Expunge,
+                Some(sled_id),
+                None,
+                PlanningOutcome::Changed,
+                format!(
+                    "marking {} expunged zone(s) ready for cleanup (inventory \
+                     shows they're no longer running)",
+                    zones_ready_for_cleanup.len(),
+                ),
+            );
         }
 
         Ok(())
@@ -486,6 +635,16 @@ impl<'a> Planner<'a> {
                     updated,
                     removed,
                 });
+                self.record(
+                    PlanningStep::Add,
+                    Some(sled_id),
+                    None,
+                    PlanningOutcome::Changed,
+                    format!(
+                        "bringing physical disks in line with planning input \
+                         ({added} added, {updated} updated, {removed} removed)"
+                    ),
+                );
 
                 // Note that this doesn't actually need to short-circuit the
                 // rest of the blueprint planning, as long as during execution
@@ -535,7 +694,24 @@ impl<'a> Planner<'a> {
                          considering it eligible for discretionary zones";
                         "sled_id" => %sled_id,
                     );
+                    self.record(
+                        PlanningStep::Add,
+                        Some(sled_id),
+                        Some(ZoneKind::InternalNtp),
+                        PlanningOutcome::Changed,
+                        "sled is missing an NTP zone; adding one (sled \
+                         already runs other services, so it remains eligible \
+                         for discretionary zones)",
+                    );
                 } else {
+                    self.record(
+                        PlanningStep::Add,
+                        Some(sled_id),
+                        Some(ZoneKind::InternalNtp),
+                        PlanningOutcome::Changed,
+                        "sled is missing an NTP zone; adding one (no other \
+                         zones will be added until it's running)",
+                    );
                     sleds_waiting_for_ntp_zone.insert(sled_id);
                     continue;
                 }
@@ -577,6 +753,14 @@ impl<'a> Planner<'a> {
                     inventory yet";
                     "sled_id" => %sled_id,
                 );
+                self.record(
+                    PlanningStep::Add,
+                    Some(sled_id),
+                    None,
+                    PlanningOutcome::Blocked,
+                    "waiting for the sled's NTP zone to appear in inventory \
+                     before adding other zones",
+                );
                 continue;
             }
 
@@ -610,6 +794,16 @@ impl<'a> Planner<'a> {
                     sled_id,
                     kind: ZoneKind::Crucible,
                 });
+                self.record(
+                    PlanningStep::Add,
+                    Some(sled_id),
+                    Some(ZoneKind::Crucible),
+                    PlanningOutcome::Changed,
+                    format!(
+                        "adding {ncrucibles_added} Crucible zone(s) for \
+                         in-service zpools that lack one"
+                    ),
+                );
                 continue;
             }
         }
@@ -648,6 +842,17 @@ impl<'a> Planner<'a> {
                     expunged,
                     removed,
                 });
+                self.record(
+                    PlanningStep::Add,
+                    Some(sled_id),
+                    None,
+                    PlanningOutcome::Changed,
+                    format!(
+                        "bringing datasets in line with zones ({added} added, \
+                         {updated} updated, {expunged} expunged, {removed} \
+                         removed)"
+                    ),
+                );
             }
         }
         Ok(())
@@ -661,18 +866,7 @@ impl<'a> Planner<'a> {
         // discretionary zones, so defer its creation until it's needed.
         let mut zone_placement = None;
 
-        for zone_kind in [
-            DiscretionaryOmicronZone::BoundaryNtp,
-            DiscretionaryOmicronZone::Clickhouse,
-            DiscretionaryOmicronZone::ClickhouseKeeper,
-            DiscretionaryOmicronZone::ClickhouseServer,
-            DiscretionaryOmicronZone::CockroachDb,
-            DiscretionaryOmicronZone::CruciblePantry,
-            DiscretionaryOmicronZone::InternalDns,
-            DiscretionaryOmicronZone::ExternalDns,
-            DiscretionaryOmicronZone::Nexus,
-            DiscretionaryOmicronZone::Oximeter,
-        ] {
+        for zone_kind in DiscretionaryOmicronZone::ALL {
             let num_zones_to_add = self.num_additional_zones_needed(zone_kind);
             if num_zones_to_add == 0 {
                 continue;
@@ -681,38 +875,7 @@ impl<'a> Planner<'a> {
             // (or reuse the existing one if a previous loop iteration already
             // created it).
             let zone_placement = zone_placement.get_or_insert_with(|| {
-                // This constructs a picture of the sleds as we currently
-                // understand them, as far as which sleds have discretionary
-                // zones. This will remain valid as we loop through the
-                // `zone_kind`s in this function, as any zone additions will
-                // update the `zone_placement` heap in-place.
-                let current_discretionary_zones = self
-                    .input
-                    .all_sled_resources(SledFilter::Discretionary)
-                    .filter(|(sled_id, _)| {
-                        !sleds_waiting_for_ntp_zone.contains(&sled_id)
-                    })
-                    .map(|(sled_id, sled_resources)| {
-                        OmicronZonePlacementSledState {
-                            sled_id,
-                            num_zpools: sled_resources
-                                .all_zpools(ZpoolFilter::InService)
-                                .count(),
-                            discretionary_zones: self
-                                .blueprint
-                                .current_sled_zones(
-                                    sled_id,
-                                    BlueprintZoneDisposition::is_in_service,
-                                )
-                                .filter_map(|zone| {
-                                    DiscretionaryOmicronZone::from_zone_type(
-                                        &zone.zone_type,
-                                    )
-                                })
-                                .collect(),
-                        }
-                    });
-                OmicronZonePlacement::new(current_discretionary_zones)
+                self.discretionary_zone_placement(sleds_waiting_for_ntp_zone)
             });
             self.add_discretionary_zones(
                 zone_placement,
@@ -724,6 +887,73 @@ impl<'a> Planner<'a> {
         Ok(())
     }
 
+    // Constructs a picture of the sleds as we currently understand them, as far
+    // as which sleds have discretionary zones and which are eligible for more.
+    // This remains valid across all the zone kinds we place in one planning
+    // pass, as any zone additions update the returned heap in-place.
+    fn discretionary_zone_placement(
+        &mut self,
+        sleds_waiting_for_ntp_zone: &BTreeSet<SledUuid>,
+    ) -> OmicronZonePlacement {
+        let policy = self.input.zone_placement_policy();
+        let max_discretionary_zones = policy
+            .max_discretionary_zones_per_sled
+            .map(|max| usize::try_from(max).unwrap_or(usize::MAX));
+        let mut reserved_sleds = Vec::new();
+        let mut sleds = Vec::new();
+        for (sled_id, sled_details) in
+            self.input.all_sleds(SledFilter::Discretionary)
+        {
+            if sleds_waiting_for_ntp_zone.contains(&sled_id) {
+                continue;
+            }
+            if policy.is_sled_reserved(sled_id) {
+                reserved_sleds.push(sled_id);
+                continue;
+            }
+            sleds.push(OmicronZonePlacementSledState {
+                sled_id,
+                num_zpools: sled_details
+                    .resources
+                    .all_zpools(ZpoolFilter::InService)
+                    .count(),
+                failure_domain: sled_details.failure_domain.clone(),
+                discretionary_zones: self
+                    .blueprint
+                    .current_sled_zones(
+                        sled_id,
+                        BlueprintZoneDisposition::is_in_service,
+                    )
+                    .filter_map(|zone| {
+                        DiscretionaryOmicronZone::from_zone_type(
+                            &zone.zone_type,
+                        )
+                    })
+                    .collect(),
+                max_discretionary_zones,
+            });
+        }
+
+        for sled_id in reserved_sleds {
+            info!(
+                self.log,
+                "not considering sled reserved for instances for \
+                 discretionary zones";
+                "sled_id" => %sled_id,
+            );
+            self.record(
+                PlanningStep::Add,
+                Some(sled_id),
+                None,
+                PlanningOutcome::Unchanged,
+                "sled is reserved for instances; not eligible for \
+                 discretionary zones",
+            );
+        }
+
+        OmicronZonePlacement::new(sleds.into_iter())
+    }
+
     // Given the current blueprint state and policy, returns the number of
     // additional zones needed of the given `zone_kind` to satisfy the policy.
     fn num_additional_zones_needed(
@@ -801,6 +1031,16 @@ impl<'a> Planner<'a> {
                 "desired_count" => target_count,
                 "current_count" => num_existing_kind_zones,
             );
+            self.record(
+                PlanningStep::Add,
+                None,
+                Some(zone_kind.into()),
+                PlanningOutcome::Unchanged,
+                format!(
+                    "sufficient zones exist (desired {target_count}, current \
+                     {num_existing_kind_zones})"
+                ),
+            );
         }
         num_zones_to_add
     }
@@ -830,6 +1070,16 @@ impl<'a> Planner<'a> {
                         "placed" => i,
                         "wanted_to_place" => num_zones_to_add,
                     );
+                    self.record(
+                        PlanningStep::Add,
+                        None,
+                        Some(kind.into()),
+                        PlanningOutcome::Blocked,
+                        format!(
+                            "no sleds eligible for another zone of this kind \
+                             (placed {i} of {num_zones_to_add} wanted)"
+                        ),
+                    );
 
                     break;
                 }
@@ -872,6 +1122,192 @@ impl<'a> Planner<'a> {
                 "sled_id" => %sled_id,
                 "kind" => ?kind,
             );
+            self.record(
+                PlanningStep::Add,
+                Some(sled_id),
+                Some(kind.into()),
+                PlanningOutcome::Changed,
+                format!(
+                    "placed new zone to satisfy policy ({} of {} wanted)",
+                    i + 1,
+                    num_zones_to_add,
+                ),
+            );
+        }
+
+        Ok(())
+    }
+
+    fn do_plan_zone_updates(&mut self) -> Result<(), Error> {
+        // If no target release has been set, there's nothing to update
+        // towards; leave zone images alone.
+        let Some(tuf_repo) = self.input.tuf_repo() else {
+            self.record(
+                PlanningStep::ZoneUpdates,
+                None,
+                None,
+                PlanningOutcome::Unchanged,
+                "no target release is set",
+            );
+            return Ok(());
+        };
+
+        // Map each zone artifact in the target release to the image source
+        // that zones of the corresponding kind should use.
+        let target_sources: BTreeMap<&str, BlueprintZoneImageSource> = tuf_repo
+            .artifacts
+            .iter()
+            .filter(|artifact| {
+                artifact.id.kind == KnownArtifactKind::Zone.into()
+            })
+            .map(|artifact| {
+                (
+                    artifact.id.name.as_str(),
+                    BlueprintZoneImageSource::Artifact {
+                        version: BlueprintZoneImageVersion::Available {
+                            version: artifact.id.version.clone(),
+                        },
+                        hash: artifact.hash,
+                    },
+                )
+            })
+            .collect();
+
+        // What image is each zone actually running, according to inventory?
+        let inventory_sources: BTreeMap<_, _> = self
+            .inventory
+            .all_omicron_zones()
+            .map(|zone| (zone.id, &zone.image_source))
+            .collect();
+
+        // Walk all in-service zones, sorting them into zones that are still
+        // in flight (the blueprint asks for something that inventory has not
+        // yet confirmed) and zones that are settled but out of date with
+        // respect to the target release.
+        let mut in_flight: BTreeMap<ZoneKind, usize> = BTreeMap::new();
+        let mut out_of_date = Vec::new();
+        for sled_id in self.input.all_sled_ids(SledFilter::InService) {
+            for zone in self.blueprint.current_sled_zones(
+                sled_id,
+                BlueprintZoneDisposition::is_in_service,
+            ) {
+                let kind = zone.zone_type.kind();
+                let expected =
+                    OmicronZoneImageSource::from(zone.image_source.clone());
+                if inventory_sources.get(&zone.id) != Some(&&expected) {
+                    // Either the zone is still being updated, or it hasn't
+                    // been deployed at all yet.  Either way, count it against
+                    // our budget.
+                    *in_flight.entry(kind).or_default() += 1;
+                    continue;
+                }
+
+                let Some(target) = target_sources.get(kind.artifact_name())
+                else {
+                    // The target release doesn't contain an image for this
+                    // kind of zone; leave it alone.
+                    continue;
+                };
+                if !zone_image_matches(&zone.image_source, target) {
+                    out_of_date.push((sled_id, zone.id, kind, target.clone()));
+                }
+            }
+        }
+
+        let mut num_in_flight: usize = in_flight.values().sum();
+        if out_of_date.is_empty() {
+            if num_in_flight == 0 {
+                info!(self.log, "all zones are up-to-date");
+                self.record(
+                    PlanningStep::ZoneUpdates,
+                    None,
+                    None,
+                    PlanningOutcome::Unchanged,
+                    "all zones are up-to-date",
+                );
+            } else {
+                info!(
+                    self.log,
+                    "waiting for zone updates to be confirmed by inventory";
+                    "num_in_flight" => num_in_flight,
+                );
+                self.record(
+                    PlanningStep::ZoneUpdates,
+                    None,
+                    None,
+                    PlanningOutcome::Blocked,
+                    format!(
+                        "waiting for inventory to confirm {num_in_flight} \
+                         zone update(s)"
+                    ),
+                );
+            }
+            return Ok(());
+        }
+
+        for (sled_id, zone_id, kind, target) in out_of_date {
+            if num_in_flight >= MAX_ZONE_UPDATES_IN_FLIGHT {
+                info!(
+                    self.log,
+                    "deferring remaining zone updates \
+                     (too many updates in flight)";
+                    "num_in_flight" => num_in_flight,
+                    "max_in_flight" => MAX_ZONE_UPDATES_IN_FLIGHT,
+                );
+                self.record(
+                    PlanningStep::ZoneUpdates,
+                    None,
+                    None,
+                    PlanningOutcome::Blocked,
+                    format!(
+                        "deferring remaining zone updates ({num_in_flight} \
+                         in flight, limit {MAX_ZONE_UPDATES_IN_FLIGHT})"
+                    ),
+                );
+                break;
+            }
+
+            let kind_in_flight = *in_flight.entry(kind).or_default();
+            if kind_in_flight >= max_zone_updates_in_flight_for_kind(kind) {
+                info!(
+                    self.log,
+                    "deferring zone update \
+                     (another zone of this kind is being updated)";
+                    "sled_id" => %sled_id,
+                    "zone_id" => %zone_id,
+                    "kind" => kind.report_str(),
+                );
+                self.record(
+                    PlanningStep::ZoneUpdates,
+                    Some(sled_id),
+                    Some(kind),
+                    PlanningOutcome::Blocked,
+                    format!(
+                        "deferring update of zone {zone_id} (another zone \
+                         of this kind is being updated)"
+                    ),
+                );
+                continue;
+            }
+
+            info!(
+                self.log,
+                "updating zone image source";
+                "sled_id" => %sled_id,
+                "zone_id" => %zone_id,
+                "kind" => kind.report_str(),
+                "image_source" => %target,
+            );
+            self.record(
+                PlanningStep::ZoneUpdates,
+                Some(sled_id),
+                Some(kind),
+                PlanningOutcome::Changed,
+                format!("updating zone {zone_id} to {target}"),
+            );
+            self.blueprint.sled_set_zone_source(sled_id, zone_id, target)?;
+            *in_flight.entry(kind).or_default() += 1;
+            num_in_flight += 1;
         }
 
         Ok(())
@@ -961,6 +1397,25 @@ impl<'a> Planner<'a> {
             "setting" => "cluster.preserve_downgrade_option",
             "value" => ?value,
         );
+        let parent_value = self
+            .blueprint
+            .parent_blueprint()
+            .cockroachdb_setting_preserve_downgrade;
+        let outcome = if value == parent_value {
+            PlanningOutcome::Unchanged
+        } else {
+            PlanningOutcome::Changed
+        };
+        self.record(
+            PlanningStep::CockroachdbSettings,
+            None,
+            None,
+            outcome,
+            format!(
+                "cluster.preserve_downgrade_option: {value:?} (target \
+                 cluster version {policy}, current version {version:?})"
+            ),
+        );
 
         // Hey! Listen!
         //
@@ -972,6 +1427,55 @@ impl<'a> Planner<'a> {
     }
 }
 
+/// The maximum number of zones whose image updates may be in flight at once
+/// (i.e., zones for which the blueprint specifies an image that inventory has
+/// not yet reported them running).
+const MAX_ZONE_UPDATES_IN_FLIGHT: usize = 2;
+
+/// The maximum number of zones of a given kind whose image updates may be in
+/// flight at once.
+///
+/// Zones whose kind relies on a quorum or on replicated data are updated one
+/// at a time so that we never take down more than one member of the group.
+fn max_zone_updates_in_flight_for_kind(kind: ZoneKind) -> usize {
+    match kind {
+        ZoneKind::BoundaryNtp
+        | ZoneKind::ClickhouseKeeper
+        | ZoneKind::ClickhouseServer
+        | ZoneKind::CockroachDb
+        | ZoneKind::Crucible
+        | ZoneKind::InternalDns => 1,
+        ZoneKind::Clickhouse
+        | ZoneKind::CruciblePantry
+        | ZoneKind::ExternalDns
+        | ZoneKind::InternalNtp
+        | ZoneKind::Nexus
+        | ZoneKind::Oximeter => MAX_ZONE_UPDATES_IN_FLIGHT,
+    }
+}
+
+/// Returns true if a zone using image source `current` is already running the
+/// image described by `target`.
+///
+/// Only the hash matters here: a zone deployed with a matching hash but an
+/// unknown version is already up-to-date.
+fn zone_image_matches(
+    current: &BlueprintZoneImageSource,
+    target: &BlueprintZoneImageSource,
+) -> bool {
+    match (current, target) {
+        (
+            BlueprintZoneImageSource::Artifact { hash: current, .. },
+            BlueprintZoneImageSource::Artifact { hash: target, .. },
+        ) => current == target,
+        (
+            BlueprintZoneImageSource::InstallDataset,
+            BlueprintZoneImageSource::InstallDataset,
+        ) => true,
+        _ => false,
+    }
+}
+
 /// The reason a sled's zones need to be expunged.
 ///
 /// This is used only for introspection and logging -- it's not part of the
@@ -989,6 +1493,7 @@ pub(crate) mod test {
     use crate::example::ExampleSystemBuilder;
     use crate::example::SimRngState;
     use crate::example::example;
+    use crate::example::example_tuf_repo;
     use crate::system::SledBuilder;
     use chrono::NaiveDateTime;
     use chrono::TimeZone;
@@ -999,11 +1504,14 @@ pub(crate) mod test {
     use nexus_types::deployment::BlueprintDatasetDisposition;
     use nexus_types::deployment::BlueprintDiffSummary;
     use nexus_types::deployment::BlueprintPhysicalDiskDisposition;
+    use nexus_types::deployment::BlueprintZoneConfig;
     use nexus_types::deployment::BlueprintZoneDisposition;
     use nexus_types::deployment::BlueprintZoneType;
     use nexus_types::deployment::ClickhouseMode;
     use nexus_types::deployment::ClickhousePolicy;
+    use nexus_types::deployment::FailureDomain;
     use nexus_types::deployment::SledDisk;
+    use nexus_types::deployment::ZonePlacementPolicy;
     use nexus_types::deployment::blueprint_zone_type;
     use nexus_types::deployment::blueprint_zone_type::InternalDns;
     use nexus_types::external_api::views::PhysicalDiskState;
@@ -1249,6 +1757,86 @@ pub(crate) mod test {
         logctx.cleanup_successful();
     }
 
+    /// Check that the planner explains what it did (and didn't do) in the
+    /// report it attaches to the blueprint
+    #[test]
+    fn test_planning_report_explains_decisions() {
+        static TEST_NAME: &str = "planner_planning_report_explains_decisions";
+        let logctx = test_setup_log(TEST_NAME);
+
+        let mut rng = SimRngState::from_seed(TEST_NAME);
+        let (mut example, blueprint1) = ExampleSystemBuilder::new_with_rng(
+            &logctx.log,
+            rng.next_system_rng(),
+        )
+        .build();
+
+        // Add a new sled; the planner should explain that it's adding an NTP
+        // zone there.
+        let new_sled_id = rng.next_sled_id_rng().next();
+        let _ =
+            example.system.sled(SledBuilder::new().id(new_sled_id)).unwrap();
+        let input = example.system.to_planning_input_builder().unwrap().build();
+
+        let blueprint2 = Planner::new_based_on(
+            logctx.log.clone(),
+            &blueprint1,
+            &input,
+            "test: add NTP",
+            &example.collection,
+        )
+        .expect("failed to create planner")
+        .with_rng(PlannerRng::from_seed((TEST_NAME, "bp2")))
+        .plan()
+        .expect("failed to plan");
+
+        let report = blueprint2
+            .planning_report
+            .as_ref()
+            .expect("planner attached a report");
+        println!("{report}");
+        assert!(report.entries_for_sled(new_sled_id).any(|entry| {
+            entry.step == PlanningStep::Add
+                && entry.outcome == PlanningOutcome::Changed
+                && entry.zone_kind == Some(ZoneKind::InternalNtp)
+        }));
+        assert_eq!(report.blocked().count(), 0);
+
+        // Fleet-wide decisions about discretionary zones are recorded even
+        // when nothing needs to change.
+        assert!(report.entries.iter().any(|entry| {
+            entry.sled_id.is_none()
+                && entry.outcome == PlanningOutcome::Unchanged
+                && entry.zone_kind == Some(ZoneKind::Nexus)
+        }));
+
+        // Inventory hasn't caught up with the new NTP zone, so the planner
+        // should explain that it's blocked on the new sled.
+        let blueprint3 = Planner::new_based_on(
+            logctx.log.clone(),
+            &blueprint2,
+            &input,
+            "test: wait for NTP",
+            &example.collection,
+        )
+        .expect("failed to create planner")
+        .with_rng(PlannerRng::from_seed((TEST_NAME, "bp3")))
+        .plan()
+        .expect("failed to plan");
+
+        let report = blueprint3
+            .planning_report
+            .as_ref()
+            .expect("planner attached a report");
+        println!("{report}");
+        let blocked: Vec<_> = report.blocked().collect();
+        assert_eq!(blocked.len(), 1, "unexpected blocked entries: {blocked:?}");
+        assert_eq!(blocked[0].sled_id, Some(new_sled_id));
+        assert_eq!(blocked[0].step, PlanningStep::Add);
+
+        logctx.cleanup_successful();
+    }
+
     /// Check that the planner will add more Nexus zones to a single sled, if
     /// needed
     #[test]
@@ -1431,6 +2019,107 @@ pub(crate) mod test {
         logctx.cleanup_successful();
     }
 
+    /// Check that the planner honors the zone placement policy: no new
+    /// discretionary zones on reserved sleds, and no sled beyond the maximum
+    #[test]
+    fn test_zone_placement_policy() {
+        static TEST_NAME: &str = "planner_zone_placement_policy";
+        let logctx = test_setup_log(TEST_NAME);
+
+        // Use our example system as a starting point.
+        let (collection, input, blueprint1) = example(&logctx.log, TEST_NAME);
+        assert_eq!(blueprint1.sleds.len(), 3);
+        let num_discretionary_zones = |blueprint: &Blueprint| {
+            blueprint
+                .sleds
+                .iter()
+                .map(|(&sled_id, sled_config)| {
+                    let count = sled_config
+                        .zones
+                        .iter()
+                        .filter(|z| z.disposition.is_in_service())
+                        .filter(|z| {
+                            DiscretionaryOmicronZone::from_zone_type(
+                                &z.zone_type,
+                            )
+                            .is_some()
+                        })
+                        .count();
+                    (sled_id, count)
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+        let before = num_discretionary_zones(&blueprint1);
+
+        // Reserve one sled for instances, allow two more discretionary zones
+        // than the busiest sled already has, and ask for far more Nexus zones
+        // than that leaves room for.
+        let reserved_sled_id = *before.keys().next().unwrap();
+        let max = before.values().copied().max().unwrap() + 2;
+        let mut builder = input.into_builder();
+        builder.policy_mut().target_nexus_zone_count = 14;
+        builder.policy_mut().zone_placement_policy = ZonePlacementPolicy {
+            version: 2,
+            max_discretionary_zones_per_sled: Some(max as u32),
+            reserved_sleds: BTreeSet::from([reserved_sled_id]),
+            cubbies_per_failure_domain: None,
+            time_created: Utc::now(),
+        };
+        let input = builder.build();
+        let blueprint2 = Planner::new_based_on(
+            logctx.log.clone(),
+            &blueprint1,
+            &input,
+            "test_blueprint2",
+            &collection,
+        )
+        .expect("failed to create planner")
+        .with_rng(PlannerRng::from_seed((TEST_NAME, "bp2")))
+        .plan()
+        .expect("failed to plan");
+
+        let summary = blueprint2.diff_since_blueprint(&blueprint1);
+        println!("1 -> 2 (limited zone placement):\n{}", summary.display());
+
+        // The reserved sled is untouched, and every other sled was filled up
+        // to (but not beyond) the maximum.
+        let after = num_discretionary_zones(&blueprint2);
+        assert_eq!(after[&reserved_sled_id], before[&reserved_sled_id]);
+        for (sled_id, &count) in &after {
+            if *sled_id != reserved_sled_id {
+                assert_eq!(count, max, "unexpected zone count on {sled_id}");
+            }
+        }
+
+        // The planner should explain both why it skipped the reserved sled and
+        // why it couldn't place every Nexus zone it wanted.
+        let report = blueprint2
+            .planning_report
+            .as_ref()
+            .expect("planner attached a report");
+        println!("{report}");
+        assert!(report.entries_for_sled(reserved_sled_id).any(|entry| {
+            entry.step == PlanningStep::Add
+                && entry.outcome == PlanningOutcome::Unchanged
+        }));
+        assert!(
+            report
+                .blocked()
+                .any(|entry| { entry.zone_kind == Some(ZoneKind::Nexus) })
+        );
+
+        // Test a no-op planning iteration.
+        assert_planning_makes_no_changes(
+            &logctx.log,
+            &blueprint2,
+            &input,
+            &collection,
+            TEST_NAME,
+        );
+
+        logctx.cleanup_successful();
+    }
+
     /// Check that the planner will spread additional internal DNS zones out across
     /// sleds as it adds them
     #[test]
@@ -1560,6 +2249,91 @@ pub(crate) mod test {
         logctx.cleanup_successful();
     }
 
+    /// Check that the planner prefers to spread Nexus zones across failure
+    /// domains, even when that means picking a sled with more discretionary
+    /// zones
+    #[test]
+    fn test_spread_nexus_zones_across_failure_domains() {
+        static TEST_NAME: &str =
+            "planner_spread_nexus_zones_across_failure_domains";
+        let logctx = test_setup_log(TEST_NAME);
+
+        // Start with an example system with no Nexus zones. Internal DNS and
+        // Crucible pantry zones are only placed on three of its five sleds.
+        let (example, blueprint1) =
+            ExampleSystemBuilder::new(&logctx.log, TEST_NAME)
+                .nsleds(5)
+                .nexus_count(0)
+                .build();
+        let collection = example.collection;
+
+        // Put the two sleds without internal DNS in failure domain "a" and the
+        // rest in "b". Absent failure domains, both new Nexus zones would go
+        // to the "a" sleds, since they have the fewest discretionary zones.
+        let sleds_with_internal_dns: BTreeSet<SledUuid> = blueprint1
+            .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+            .filter(|(_, zone)| zone.zone_type.is_internal_dns())
+            .map(|(sled_id, _)| sled_id)
+            .collect();
+        assert_eq!(sleds_with_internal_dns.len(), 3);
+        let domain_a = FailureDomain::new("a");
+        let domain_b = FailureDomain::new("b");
+        let mut builder = example.input.into_builder();
+        builder.policy_mut().target_nexus_zone_count = 2;
+        for (sled_id, details) in builder.sleds_mut() {
+            details.failure_domain =
+                Some(if sleds_with_internal_dns.contains(sled_id) {
+                    domain_b.clone()
+                } else {
+                    domain_a.clone()
+                });
+        }
+        let input = builder.build();
+
+        let blueprint2 = Planner::new_based_on(
+            logctx.log.clone(),
+            &blueprint1,
+            &input,
+            "test_blueprint2",
+            &collection,
+        )
+        .expect("failed to create planner")
+        .with_rng(PlannerRng::from_seed((TEST_NAME, "bp2")))
+        .plan()
+        .expect("failed to plan");
+
+        let summary = blueprint2.diff_since_blueprint(&blueprint1);
+        println!("1 -> 2 (added Nexus zones):\n{}", summary.display());
+
+        // We should have added one Nexus zone in each failure domain.
+        let nexus_domains: Vec<_> = blueprint2
+            .all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+            .filter(|(_, zone)| zone.zone_type.is_nexus())
+            .map(|(sled_id, _)| {
+                input
+                    .sled_lookup(SledFilter::All, sled_id)
+                    .expect("found sled")
+                    .failure_domain
+                    .clone()
+                    .expect("sled has failure domain")
+            })
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        assert_eq!(nexus_domains, [domain_a, domain_b]);
+
+        // Test a no-op planning iteration.
+        assert_planning_makes_no_changes(
+            &logctx.log,
+            &blueprint2,
+            &input,
+            &collection,
+            TEST_NAME,
+        );
+
+        logctx.cleanup_successful();
+    }
+
     /// Check that the planner will reuse external IPs that were previously
     /// assigned to expunged zones
     #[test]
@@ -4337,4 +5111,155 @@ pub(crate) mod test {
 
         logctx.cleanup_successful();
     }
+
+    /// Update the image sources reported by `collection` to match the zones in
+    /// `blueprint`, as if the blueprint had been executed and a new inventory
+    /// collected.
+    fn update_collection_from_blueprint(
+        collection: &mut Collection,
+        blueprint: &Blueprint,
+    ) {
+        for (sled_id, sled_agent) in &mut collection.sled_agents {
+            let Some(sled_config) = blueprint.sleds.get(sled_id) else {
+                continue;
+            };
+            for zone in &mut sled_agent.omicron_zones.zones {
+                if let Some(bp_zone) = sled_config.zones.get(&zone.id) {
+                    zone.image_source = bp_zone.image_source.clone().into();
+                }
+            }
+        }
+    }
+
+    /// Counts the in-service zones in `blueprint` whose image source differs
+    /// from the one reported by `collection`, grouped by kind.
+    fn zone_updates_in_flight(
+        blueprint: &Blueprint,
+        collection: &Collection,
+    ) -> BTreeMap<ZoneKind, usize> {
+        let inventory_sources: BTreeMap<_, _> = collection
+            .all_omicron_zones()
+            .map(|zone| (zone.id, zone.image_source.clone()))
+            .collect();
+        let mut in_flight = BTreeMap::new();
+        for (_, zone) in
+            blueprint.all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+        {
+            let expected =
+                OmicronZoneImageSource::from(zone.image_source.clone());
+            if inventory_sources.get(&zone.id) != Some(&expected) {
+                *in_flight.entry(zone.zone_type.kind()).or_default() += 1;
+            }
+        }
+        in_flight
+    }
+
+    #[test]
+    fn test_zone_updates_toward_target_release() {
+        static TEST_NAME: &str = "planner_zone_updates_toward_target_release";
+        let logctx = test_setup_log(TEST_NAME);
+        let log = logctx.log.clone();
+
+        // Use our example system.
+        let (mut collection, input, blueprint1) = example(&log, TEST_NAME);
+
+        // With no target release, the planner leaves zone images alone.
+        assert_eq!(input.tuf_repo(), None);
+        assert_planning_makes_no_changes(
+            &logctx.log,
+            &blueprint1,
+            &input,
+            &collection,
+            TEST_NAME,
+        );
+
+        // Set a target release.
+        let tuf_repo = example_tuf_repo("2.0.0".parse().unwrap());
+        let input = {
+            let mut builder = input.into_builder();
+            builder.policy_mut().tuf_repo = Some(tuf_repo.clone());
+            builder.build()
+        };
+        let target_hash = |kind: ZoneKind| {
+            tuf_repo
+                .artifacts
+                .iter()
+                .find(|artifact| artifact.id.name == kind.artifact_name())
+                .expect("repo contains an artifact for every zone kind")
+                .hash
+        };
+        let is_up_to_date = |zone: &BlueprintZoneConfig| {
+            matches!(
+                zone.image_source,
+                BlueprintZoneImageSource::Artifact { hash, .. }
+                    if hash == target_hash(zone.zone_type.kind())
+            )
+        };
+
+        // Plan repeatedly, executing each blueprint (from inventory's point of
+        // view) in between. At every step, the number of zones being updated
+        // must stay within the planner's limits.
+        let mut parent = blueprint1;
+        let mut nrounds = 0;
+        loop {
+            let blueprint = Planner::new_based_on(
+                logctx.log.clone(),
+                &parent,
+                &input,
+                "update zones",
+                &collection,
+            )
+            .expect("created planner")
+            .with_rng(PlannerRng::from_seed((TEST_NAME, nrounds)))
+            .plan()
+            .expect("planned");
+            verify_blueprint(&blueprint);
+
+            let summary = blueprint.diff_since_blueprint(&parent);
+            if summary.diff.sleds.modified().count() == 0 {
+                break;
+            }
+            nrounds += 1;
+            assert!(nrounds < 100, "zone updates did not converge");
+
+            // Zones should only ever be modified in place, never added or
+            // removed.
+            assert_eq!(summary.total_zones_added(), 0);
+            assert_eq!(summary.total_zones_removed(), 0);
+
+            let in_flight = zone_updates_in_flight(&blueprint, &collection);
+            eprintln!("round {nrounds}: in flight: {in_flight:?}");
+            assert!(
+                in_flight.values().sum::<usize>() <= MAX_ZONE_UPDATES_IN_FLIGHT
+            );
+            for (kind, count) in &in_flight {
+                assert!(*count <= max_zone_updates_in_flight_for_kind(*kind));
+            }
+
+            // Until inventory catches up, the planner must wait.
+            assert_planning_makes_no_changes(
+                &logctx.log,
+                &blueprint,
+                &input,
+                &collection,
+                TEST_NAME,
+            );
+
+            update_collection_from_blueprint(&mut collection, &blueprint);
+            parent = blueprint;
+        }
+
+        // Every zone should now be running the target release.
+        for (_, zone) in
+            parent.all_omicron_zones(BlueprintZoneDisposition::is_in_service)
+        {
+            assert!(is_up_to_date(zone), "zone not updated: {zone:?}");
+        }
+        assert!(
+            zone_updates_in_flight(&parent, &collection).is_empty(),
+            "inventory should reflect all updates"
+        );
+
+        logctx.cleanup_successful();
+    }
 }
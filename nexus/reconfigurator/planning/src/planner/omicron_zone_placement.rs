@@ -6,8 +6,11 @@
 
 use nexus_sled_agent_shared::inventory::ZoneKind;
 use nexus_types::deployment::BlueprintZoneType;
+use nexus_types::deployment::FailureDomain;
+use nexus_types::deployment::ZonePlacementPolicy;
 use omicron_uuid_kinds::SledUuid;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::collections::BinaryHeap;
 use std::mem;
 
@@ -27,30 +30,46 @@ pub(crate) enum DiscretionaryOmicronZone {
 }
 
 impl DiscretionaryOmicronZone {
+    /// Every kind of discretionary zone, in the order the planner adds them
+    pub(crate) const ALL: [Self; 10] = [
+        Self::BoundaryNtp,
+        Self::Clickhouse,
+        Self::ClickhouseKeeper,
+        Self::ClickhouseServer,
+        Self::CockroachDb,
+        Self::CruciblePantry,
+        Self::InternalDns,
+        Self::ExternalDns,
+        Self::Nexus,
+        Self::Oximeter,
+    ];
+
     pub(super) fn from_zone_type(
         zone_type: &BlueprintZoneType,
     ) -> Option<Self> {
-        match zone_type {
-            BlueprintZoneType::BoundaryNtp(_) => Some(Self::BoundaryNtp),
-            BlueprintZoneType::Clickhouse(_) => Some(Self::Clickhouse),
-            BlueprintZoneType::ClickhouseKeeper(_) => {
-                Some(Self::ClickhouseKeeper)
-            }
-            BlueprintZoneType::ClickhouseServer(_) => {
-                Some(Self::ClickhouseServer)
-            }
-            BlueprintZoneType::CockroachDb(_) => Some(Self::CockroachDb),
-            BlueprintZoneType::CruciblePantry(_) => Some(Self::CruciblePantry),
-            BlueprintZoneType::InternalDns(_) => Some(Self::InternalDns),
-            BlueprintZoneType::ExternalDns(_) => Some(Self::ExternalDns),
-            BlueprintZoneType::Nexus(_) => Some(Self::Nexus),
-            BlueprintZoneType::Oximeter(_) => Some(Self::Oximeter),
-            // Zones that get special handling for placement (all sleds get
-            // them, although internal NTP has some interactions with boundary
-            // NTP that are handled separately).
-            BlueprintZoneType::Crucible(_)
-            | BlueprintZoneType::InternalNtp(_) => None,
+        Self::from_zone_kind(zone_type.kind())
+    }
+
+    /// Which zone kinds are discretionary is decided by
+    /// [`ZonePlacementPolicy::is_discretionary`]; this only maps them onto
+    /// our variants.
+    fn from_zone_kind(kind: ZoneKind) -> Option<Self> {
+        if !ZonePlacementPolicy::is_discretionary(kind) {
+            return None;
         }
+        let zone =
+            Self::ALL.into_iter().find(|zone| ZoneKind::from(*zone) == kind);
+        assert!(
+            zone.is_some(),
+            "discretionary zone kind {kind:?} has no DiscretionaryOmicronZone"
+        );
+        zone
+    }
+
+    /// Returns true if replicas of this zone kind should be spread across as
+    /// many failure domains as possible.
+    pub(super) fn spread_across_failure_domains(self) -> bool {
+        FailureDomain::should_spread(self.into())
     }
 }
 
@@ -88,7 +107,11 @@ pub(super) enum PlacementError {
 pub(super) struct OmicronZonePlacementSledState {
     pub sled_id: SledUuid,
     pub num_zpools: usize,
+    pub failure_domain: Option<FailureDomain>,
     pub discretionary_zones: Vec<DiscretionaryOmicronZone>,
+    /// maximum number of discretionary zones (of all kinds) this sled may
+    /// hold, if limited by policy
+    pub max_discretionary_zones: Option<usize>,
 }
 
 impl OmicronZonePlacementSledState {
@@ -140,17 +163,26 @@ impl OmicronZonePlacement {
     /// returned sled, the `OmicronZonePlacement` instance should be discarded
     /// and a new one should be created for future placement decisions.
     ///
-    /// Placement is currently minimal. The only hard requirement we enforce is
+    /// Placement is currently minimal. The hard requirements we enforce are
     /// that a sled may only one run one instance of any given zone kind per
     /// zpool it has (e.g., a sled with 5 zpools could run 5 Nexus instances and
     /// 5 CockroachDb instances concurrently, but could not run 6 Nexus
-    /// instances). If there is at least one sled that satisfies this
-    /// requirement, this method will return `Ok(_)`. If there are multiple
-    /// sleds that satisfy this requirement, this method will return a sled
-    /// which has the fewest instances of `zone_kind`; if multiple sleds are
-    /// tied, it will pick the one with the fewest total discretionary zones; if
-    /// multiple sleds are still tied, it will pick deterministically (e.g.,
-    /// choosing the lowest or highest sled ID).
+    /// instances), and that a sled may not hold more discretionary zones in
+    /// total than its `max_discretionary_zones`, if it has one. If there is at
+    /// least one sled that satisfies these requirements, this method will
+    /// return `Ok(_)`. If there are multiple sleds that satisfy these
+    /// requirements, this method will return a sled which has the fewest
+    /// instances of `zone_kind`; if multiple sleds are tied and `zone_kind`
+    /// should be spread across failure domains, it will pick one whose failure
+    /// domain has the fewest instances of `zone_kind`; if multiple sleds are
+    /// still tied, it will pick the one with the fewest total discretionary
+    /// zones; if multiple sleds are still tied, it will pick deterministically
+    /// (e.g., choosing the lowest or highest sled ID).
+    ///
+    /// Sleds with no known failure domain are treated as though each is in a
+    /// failure domain of its own. Only the sleds provided when this
+    /// `OmicronZonePlacement` was created are considered when counting the
+    /// zones in a failure domain.
     ///
     /// `OmicronZonePlacement` currently does not track _which_ zpools are
     /// assigned to services. This could lead to it being overly conservative if
@@ -185,8 +217,9 @@ impl OmicronZonePlacement {
 
         let mut sleds_skipped = Vec::new();
         let mut chosen_sled = None;
-        while let Some(sled) = self.sleds.pop() {
-            let num_existing = sled.num_discretionary_zones_of_kind(zone_kind);
+        while let Some(ordered) = self.sleds.pop() {
+            let num_existing =
+                ordered.sled.num_discretionary_zones_of_kind(zone_kind);
 
             // For boundary NTP, a sled is only eligible if it does not already
             // hold a boundary NTP zone.
@@ -197,19 +230,27 @@ impl OmicronZonePlacement {
             // For all zone kinds, a sled is only eligible if it has at
             // least one zpool more than the number of `zone_kind` zones
             // already placed on this sled.
-            let should_skip = should_skip || num_existing >= sled.num_zpools;
+            let should_skip =
+                should_skip || num_existing >= ordered.sled.num_zpools;
+
+            // A sled is never eligible if it's already at its policy limit for
+            // discretionary zones of all kinds.
+            let should_skip = should_skip
+                || ordered.sled.max_discretionary_zones.is_some_and(|max| {
+                    ordered.sled.discretionary_zones.len() >= max
+                });
 
             if should_skip {
-                sleds_skipped.push(sled);
+                sleds_skipped.push(ordered);
             } else {
-                chosen_sled = Some(sled);
+                chosen_sled = Some(ordered.sled);
                 break;
             }
         }
 
         // Push any skipped sleds back onto our heap.
-        for sled in sleds_skipped {
-            self.sleds.push(sled);
+        for ordered in sleds_skipped {
+            self.sleds.push(ordered);
         }
 
         let mut sled =
@@ -219,7 +260,7 @@ impl OmicronZonePlacement {
         // Update our internal state so future `place_zone` calls take the new
         // zone we just placed into account.
         sled.discretionary_zones.push(zone_kind);
-        self.sleds.push(sled);
+        self.sleds.push_placed(sled);
 
         Ok(sled_id)
     }
@@ -231,7 +272,8 @@ impl OmicronZonePlacement {
 struct OrderedSleds {
     // The current zone type we're sorted to place. We maintain the invariant
     // that every element of `heap` has the same `ordered_by` value as this
-    // field's current value.
+    // field's current value, and that every element's
+    // `failure_domain_zones_of_interest` is up to date for that kind.
     ordered_by: DiscretionaryOmicronZone,
     heap: BinaryHeap<OrderedSledState>,
 }
@@ -243,8 +285,7 @@ impl OrderedSleds {
     ) -> Self {
         Self {
             ordered_by,
-            heap: sleds
-                .map(|sled| OrderedSledState { ordered_by, sled })
+            heap: OrderedSledState::order_by(ordered_by, sleds.collect())
                 .collect(),
         }
     }
@@ -253,33 +294,89 @@ impl OrderedSleds {
         if self.ordered_by == ordered_by {
             return;
         }
+        self.rebuild(ordered_by);
+    }
 
-        // Rebuild our heap, sorting by a new zone kind, and maintaining the
-        // invariant that all our heap members have the same `ordered_by` value
-        // as we do.
-        let mut sleds = mem::take(&mut self.heap).into_vec();
-        for s in &mut sleds {
-            s.ordered_by = ordered_by;
-        }
-        self.heap = BinaryHeap::from(sleds);
+    // Rebuild our heap, sorting by `ordered_by`, and maintaining the
+    // invariant that all our heap members have the same `ordered_by` value
+    // as we do.
+    fn rebuild(&mut self, ordered_by: DiscretionaryOmicronZone) {
+        let sleds = mem::take(&mut self.heap)
+            .into_vec()
+            .into_iter()
+            .map(|ordered| ordered.sled)
+            .collect();
+        self.heap = OrderedSledState::order_by(ordered_by, sleds).collect();
         self.ordered_by = ordered_by;
     }
 
-    fn pop(&mut self) -> Option<OmicronZonePlacementSledState> {
-        self.heap.pop().map(|ordered| ordered.sled)
+    fn pop(&mut self) -> Option<OrderedSledState> {
+        self.heap.pop()
     }
 
-    fn push(&mut self, sled: OmicronZonePlacementSledState) {
-        self.heap.push(OrderedSledState { ordered_by: self.ordered_by, sled })
+    // Push a sled that was previously popped and has not been modified.
+    fn push(&mut self, ordered: OrderedSledState) {
+        assert_eq!(ordered.ordered_by, self.ordered_by);
+        self.heap.push(ordered)
+    }
+
+    // Push a sled that was previously popped and has had a zone of our current
+    // ordering kind placed on it.
+    fn push_placed(&mut self, sled: OmicronZonePlacementSledState) {
+        self.heap.push(OrderedSledState {
+            ordered_by: self.ordered_by,
+            failure_domain_zones_of_interest: 0,
+            sled,
+        });
+
+        // Placing a zone we spread across failure domains changes the failure
+        // domain counts of every sled sharing a domain with `sled` (and of
+        // `sled` itself), so recompute them all.
+        if self.ordered_by.spread_across_failure_domains() {
+            self.rebuild(self.ordered_by);
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct OrderedSledState {
     ordered_by: DiscretionaryOmicronZone,
+    // Number of zones of kind `ordered_by` in `sled`'s failure domain (or on
+    // `sled` itself, if it has no known failure domain). Always 0 if
+    // `ordered_by` does not need to be spread across failure domains.
+    failure_domain_zones_of_interest: usize,
     sled: OmicronZonePlacementSledState,
 }
 
+impl OrderedSledState {
+    fn order_by(
+        ordered_by: DiscretionaryOmicronZone,
+        sleds: Vec<OmicronZonePlacementSledState>,
+    ) -> impl Iterator<Item = Self> {
+        let spread = ordered_by.spread_across_failure_domains();
+        let mut zones_by_domain: BTreeMap<FailureDomain, usize> =
+            BTreeMap::new();
+        if spread {
+            for sled in &sleds {
+                if let Some(domain) = &sled.failure_domain {
+                    *zones_by_domain.entry(domain.clone()).or_default() +=
+                        sled.num_discretionary_zones_of_kind(ordered_by);
+                }
+            }
+        }
+        sleds.into_iter().map(move |sled| {
+            let failure_domain_zones_of_interest = if !spread {
+                0
+            } else if let Some(domain) = &sled.failure_domain {
+                zones_by_domain.get(domain).copied().unwrap_or(0)
+            } else {
+                sled.num_discretionary_zones_of_kind(ordered_by)
+            };
+            Self { ordered_by, failure_domain_zones_of_interest, sled }
+        })
+    }
+}
+
 impl Ord for OrderedSledState {
     fn cmp(&self, other: &Self) -> Ordering {
         // Invariant: We should never compare other entries with a different
@@ -287,27 +384,27 @@ impl Ord for OrderedSledState {
         assert_eq!(self.ordered_by, other.ordered_by);
 
         // Count how many zones of our ordering type are in each side.
-        let our_zones_of_interest = self
-            .sled
-            .discretionary_zones
-            .iter()
-            .filter(|&&z| z == self.ordered_by)
-            .count();
-        let other_zones_of_interest = other
-            .sled
-            .discretionary_zones
-            .iter()
-            .filter(|&&z| z == self.ordered_by)
-            .count();
+        let our_zones_of_interest =
+            self.sled.num_discretionary_zones_of_kind(self.ordered_by);
+        let other_zones_of_interest =
+            other.sled.num_discretionary_zones_of_kind(self.ordered_by);
 
         // BinaryHeap is a max heap, and we want to be on the top of the heap if
         // we have fewer zones of interest, so reverse the comparisons below.
         our_zones_of_interest
             .cmp(&other_zones_of_interest)
             .reverse()
-            // If the zones of interest count is equal, we tiebreak by total
-            // discretionary zones, again reversing the order for our max heap
-            // to prioritize sleds with fewer total discretionary zones.
+            // If the zones of interest count is equal, we tiebreak by the
+            // number of zones of interest in each sled's failure domain (which
+            // is always 0 for zone kinds we don't spread across domains).
+            .then_with(|| {
+                self.failure_domain_zones_of_interest
+                    .cmp(&other.failure_domain_zones_of_interest)
+                    .reverse()
+            })
+            // If we're still tied, we tiebreak by total discretionary zones,
+            // again reversing the order for our max heap to prioritize sleds
+            // with fewer total discretionary zones.
             .then_with(|| {
                 self.sled
                     .discretionary_zones
@@ -335,10 +432,25 @@ pub mod test {
     use proptest::collection::btree_map;
     use proptest::sample::size_range;
     use std::collections::BTreeMap;
+    use strum::IntoEnumIterator;
     use test_strategy::Arbitrary;
     use test_strategy::proptest;
     use uuid::Uuid;
 
+    #[test]
+    fn test_discretionary_zone_kinds() {
+        for kind in ZoneKind::iter() {
+            let zone = DiscretionaryOmicronZone::from_zone_kind(kind);
+            assert_eq!(zone.map(ZoneKind::from), zone.map(|_| kind));
+        }
+        for zone in DiscretionaryOmicronZone::ALL {
+            assert_eq!(
+                DiscretionaryOmicronZone::from_zone_kind(zone.into()),
+                Some(zone)
+            );
+        }
+    }
+
     #[derive(Debug, Clone, Arbitrary)]
     struct ZonesToPlace {
         #[any(size_range(0..8).lift())]
@@ -350,6 +462,10 @@ pub mod test {
         zones: ZonesToPlace,
         #[strategy(0_usize..8)]
         num_zpools: usize,
+        #[strategy(proptest::option::of(0_u8..3))]
+        failure_domain: Option<u8>,
+        #[strategy(proptest::option::of(0_usize..10))]
+        max_discretionary_zones: Option<usize>,
     }
 
     #[derive(Debug, Arbitrary)]
@@ -376,6 +492,10 @@ pub mod test {
                     TestSledState {
                         zones: existing_sled.zones.zones.clone(),
                         num_zpools: existing_sled.num_zpools,
+                        failure_domain: existing_sled
+                            .failure_domain
+                            .map(|d| FailureDomain::new(format!("domain-{d}"))),
+                        max_zones: existing_sled.max_discretionary_zones,
                     },
                 );
             }
@@ -388,12 +508,18 @@ pub mod test {
     struct TestSledState {
         zones: Vec<DiscretionaryOmicronZone>,
         num_zpools: usize,
+        failure_domain: Option<FailureDomain>,
+        max_zones: Option<usize>,
     }
 
     impl TestSledState {
         fn count_zones_of_kind(&self, kind: DiscretionaryOmicronZone) -> usize {
             self.zones.iter().filter(|&&k| k == kind).count()
         }
+
+        fn is_at_max_zones(&self) -> bool {
+            self.max_zones.is_some_and(|max| self.zones.len() >= max)
+        }
     }
 
     #[derive(Debug)]
@@ -402,6 +528,28 @@ pub mod test {
     }
 
     impl TestState {
+        // Count the zones of `kind` in the failure domain of `sled_id`, or 0
+        // if `kind` is not spread across failure domains.
+        fn count_failure_domain_zones_of_kind(
+            &self,
+            sled_id: SledUuid,
+            kind: DiscretionaryOmicronZone,
+        ) -> usize {
+            if !kind.spread_across_failure_domains() {
+                return 0;
+            }
+            let sled_state = self.sleds.get(&sled_id).expect("valid sled_id");
+            match &sled_state.failure_domain {
+                Some(domain) => self
+                    .sleds
+                    .values()
+                    .filter(|s| s.failure_domain.as_ref() == Some(domain))
+                    .map(|s| s.count_zones_of_kind(kind))
+                    .sum(),
+                None => sled_state.count_zones_of_kind(kind),
+            }
+        }
+
         fn validate_sled_can_support_another_zone_of_kind(
             &self,
             sled_id: SledUuid,
@@ -423,6 +571,13 @@ pub mod test {
                      {kind:?} instances but only {} zpools",
                     sled_state.num_zpools
                 ))
+            } else if sled_state.is_at_max_zones() {
+                Err(format!(
+                    "sled {sled_id} already has {} discretionary zones \
+                     (policy maximum {:?})",
+                    sled_state.zones.len(),
+                    sled_state.max_zones,
+                ))
             } else {
                 Ok(())
             }
@@ -441,11 +596,15 @@ pub mod test {
 
             let sled_state = self.sleds.get(&sled_id).expect("valid sled_id");
             let existing_zones = sled_state.count_zones_of_kind(kind);
+            let existing_domain_zones =
+                self.count_failure_domain_zones_of_kind(sled_id, kind);
 
             // Ensure this sled is (at least tied for) the best choice for this
             // kind: it should have the minimum number of existing zones of this
-            // kind, and of all sleds tied for the minimum, it should have the
-            // fewest total discretionary services.
+            // kind; of all sleds tied for the minimum, its failure domain
+            // should have the fewest zones of this kind; and of all sleds
+            // still tied, it should have the fewest total discretionary
+            // services.
             for (&other_sled_id, other_sled_state) in &self.sleds {
                 // Ignore other sleds that can't run another zone of `kind`.
                 if self
@@ -467,7 +626,20 @@ pub mod test {
                          {other_zone_count} < {existing_zones})"
                     ));
                 }
-                if other_zone_count == existing_zones
+                if other_zone_count != existing_zones {
+                    continue;
+                }
+                let other_domain_zone_count = self
+                    .count_failure_domain_zones_of_kind(other_sled_id, kind);
+                if other_domain_zone_count < existing_domain_zones {
+                    return Err(format!(
+                        "sled {other_sled_id} would be a better choice \
+                         (same number of existing {kind:?} instances, but \
+                          fewer in its failure domain: \
+                          {other_domain_zone_count} < {existing_domain_zones})"
+                    ));
+                }
+                if other_domain_zone_count == existing_domain_zones
                     && other_sled_state.zones.len() < sled_state.zones.len()
                 {
                     return Err(format!(
@@ -503,6 +675,7 @@ pub mod test {
             for (sled_id, sled_state) in self.sleds.iter() {
                 if sled_state.count_zones_of_kind(kind)
                     < max_this_kind_for_sled(sled_state)
+                    && !sled_state.is_at_max_zones()
                 {
                     return Err(format!(
                         "sled {sled_id} is eligible for {kind:?} placement"
@@ -522,7 +695,9 @@ pub mod test {
                 |(&sled_id, sled_state)| OmicronZonePlacementSledState {
                     sled_id,
                     num_zpools: sled_state.num_zpools,
+                    failure_domain: sled_state.failure_domain.clone(),
                     discretionary_zones: sled_state.zones.clone(),
+                    max_discretionary_zones: sled_state.max_zones,
                 },
             ));
 
@@ -22,12 +22,14 @@ use nexus_sled_agent_shared::inventory::SledRole;
 use nexus_types::deployment::ClickhousePolicy;
 use nexus_types::deployment::CockroachDbClusterVersion;
 use nexus_types::deployment::CockroachDbSettings;
+use nexus_types::deployment::FailureDomain;
 use nexus_types::deployment::OximeterReadPolicy;
 use nexus_types::deployment::PlanningInputBuilder;
 use nexus_types::deployment::Policy;
 use nexus_types::deployment::SledDetails;
 use nexus_types::deployment::SledDisk;
 use nexus_types::deployment::SledResources;
+use nexus_types::deployment::ZonePlacementPolicy;
 use nexus_types::external_api::views::PhysicalDiskPolicy;
 use nexus_types::external_api::views::PhysicalDiskState;
 use nexus_types::external_api::views::SledPolicy;
@@ -44,10 +46,12 @@ use omicron_common::address::SLED_PREFIX;
 use omicron_common::address::get_sled_address;
 use omicron_common::api::external::ByteCount;
 use omicron_common::api::external::Generation;
+use omicron_common::api::external::TufRepoDescription;
 use omicron_common::disk::DiskIdentity;
 use omicron_common::disk::DiskVariant;
 use omicron_common::policy::INTERNAL_DNS_REDUNDANCY;
 use omicron_common::policy::NEXUS_REDUNDANCY;
+use omicron_uuid_kinds::PhysicalDiskUuid;
 use omicron_uuid_kinds::SledUuid;
 use omicron_uuid_kinds::ZpoolUuid;
 use std::collections::BTreeMap;
@@ -96,6 +100,8 @@ pub struct SystemDescription {
     external_dns_version: Generation,
     clickhouse_policy: Option<ClickhousePolicy>,
     oximeter_read_policy: OximeterReadPolicy,
+    tuf_repo: Option<TufRepoDescription>,
+    zone_placement_policy: ZonePlacementPolicy,
 }
 
 impl SystemDescription {
@@ -175,6 +181,8 @@ impl SystemDescription {
             external_dns_version: Generation::new(),
             clickhouse_policy: None,
             oximeter_read_policy: OximeterReadPolicy::new(1),
+            tuf_repo: None,
+            zone_placement_policy: ZonePlacementPolicy::default(),
         }
     }
 
@@ -254,6 +262,28 @@ impl SystemDescription {
         self
     }
 
+    /// Set the TUF repo for the current target release, or clear it
+    pub fn tuf_repo(
+        &mut self,
+        tuf_repo: Option<TufRepoDescription>,
+    ) -> &mut Self {
+        self.tuf_repo = tuf_repo;
+        self
+    }
+
+    pub fn get_tuf_repo(&self) -> Option<&TufRepoDescription> {
+        self.tuf_repo.as_ref()
+    }
+
+    /// Set the limits on where discretionary zones may be placed
+    pub fn zone_placement_policy(
+        &mut self,
+        policy: ZonePlacementPolicy,
+    ) -> &mut Self {
+        self.zone_placement_policy = policy;
+        self
+    }
+
     pub fn get_sled_mut(
         &mut self,
         sled_id: SledUuid,
@@ -300,7 +330,8 @@ impl SystemDescription {
                 .ok_or_else(|| anyhow!("ran out of slots for non-Scrimlets"))?
         };
 
-        let sled = Sled::new_simulated(
+        let failure_domain = sled.failure_domain;
+        let mut sled = Sled::new_simulated(
             sled_id,
             sled_subnet,
             sled.sled_role,
@@ -310,6 +341,7 @@ impl SystemDescription {
             sled.omicron_zones,
             sled.npools,
         );
+        sled.failure_domain = failure_domain;
         self.sleds.insert(sled_id, Arc::new(sled));
         Ok(self)
     }
@@ -376,6 +408,61 @@ impl SystemDescription {
         Ok(self)
     }
 
+    /// Set the failure domain reported for a sled in the planning input
+    ///
+    /// Returns an error if the sled is not found.
+    pub fn sled_set_failure_domain(
+        &mut self,
+        sled_id: SledUuid,
+        failure_domain: Option<FailureDomain>,
+    ) -> anyhow::Result<&mut Self> {
+        let sled = self.sleds.get_mut(&sled_id).with_context(|| {
+            format!("attempted to access sled {} not found in system", sled_id)
+        })?;
+        Arc::make_mut(sled).failure_domain = failure_domain;
+        Ok(self)
+    }
+
+    /// Mark a sled as expunged in the planning input
+    ///
+    /// The sled remains in the system (and in inventory); only its policy
+    /// changes, as it would when an operator expunges a sled.
+    ///
+    /// Returns an error if the sled is not found.
+    pub fn sled_expunge(
+        &mut self,
+        sled_id: SledUuid,
+    ) -> anyhow::Result<&mut Self> {
+        let sled = self.sleds.get_mut(&sled_id).with_context(|| {
+            format!("attempted to access sled {} not found in system", sled_id)
+        })?;
+        Arc::make_mut(sled).policy = SledPolicy::Expunged;
+        Ok(self)
+    }
+
+    /// Mark one of a sled's physical disks as expunged in the planning input
+    ///
+    /// Returns an error if the sled or disk is not found.
+    pub fn sled_expunge_disk(
+        &mut self,
+        sled_id: SledUuid,
+        disk_id: PhysicalDiskUuid,
+    ) -> anyhow::Result<&mut Self> {
+        let sled = self.sleds.get_mut(&sled_id).with_context(|| {
+            format!("attempted to access sled {} not found in system", sled_id)
+        })?;
+        let disk = Arc::make_mut(sled)
+            .resources
+            .zpools
+            .values_mut()
+            .find(|disk| disk.disk_id == disk_id)
+            .with_context(|| {
+                format!("disk {disk_id} not found on sled {sled_id}")
+            })?;
+        disk.policy = PhysicalDiskPolicy::Expunged;
+        Ok(self)
+    }
+
     pub fn to_collection_builder(&self) -> anyhow::Result<CollectionBuilder> {
         let collector_label = self
             .collector
@@ -427,6 +514,8 @@ impl SystemDescription {
                 .target_crucible_pantry_zone_count,
             clickhouse_policy: self.clickhouse_policy.clone(),
             oximeter_read_policy: self.oximeter_read_policy.clone(),
+            tuf_repo: self.tuf_repo.clone(),
+            zone_placement_policy: self.zone_placement_policy.clone(),
         };
         let mut builder = PlanningInputBuilder::new(
             policy,
@@ -440,6 +529,7 @@ impl SystemDescription {
                 policy: sled.policy,
                 state: sled.state,
                 resources: sled.resources.clone(),
+                failure_domain: sled.failure_domain.clone(),
             };
             builder.add_sled(sled.sled_id, sled_details)?;
         }
@@ -465,6 +555,7 @@ pub struct SledBuilder {
     sled_role: SledRole,
     omicron_zones: OmicronZonesConfig,
     npools: u8,
+    failure_domain: Option<FailureDomain>,
 }
 
 impl SledBuilder {
@@ -487,6 +578,7 @@ impl SledBuilder {
                 zones: Vec::new(),
             },
             npools: Self::DEFAULT_NPOOLS,
+            failure_domain: None,
         }
     }
 
@@ -540,6 +632,14 @@ impl SledBuilder {
         self.sled_role = sled_role;
         self
     }
+
+    /// Sets the failure domain reported for this sled in the planning input
+    ///
+    /// Default: none
+    pub fn failure_domain(mut self, failure_domain: FailureDomain) -> Self {
+        self.failure_domain = Some(failure_domain);
+        self
+    }
 }
 
 /// Convenience structure summarizing `Sled` inputs that come from inventory
@@ -562,6 +662,7 @@ pub struct Sled {
     policy: SledPolicy,
     state: SledState,
     resources: SledResources,
+    failure_domain: Option<FailureDomain>,
 }
 
 impl Sled {
@@ -700,6 +801,7 @@ impl Sled {
             },
             state: SledState::Active,
             resources: SledResources { subnet: sled_subnet, zpools },
+            failure_domain: None,
         }
     }
 
@@ -836,6 +938,7 @@ impl Sled {
             policy: sled_policy,
             state: sled_state,
             resources: sled_resources,
+            failure_domain: None,
         }
     }
 
@@ -19,6 +19,7 @@ use nexus_types::deployment::BlueprintMetadata;
 use nexus_types::deployment::ClickhousePolicy;
 use nexus_types::deployment::CockroachDbClusterVersion;
 use nexus_types::deployment::CockroachDbSettings;
+use nexus_types::deployment::FailureDomain;
 use nexus_types::deployment::OmicronZoneExternalIp;
 use nexus_types::deployment::OmicronZoneNic;
 use nexus_types::deployment::OximeterReadPolicy;
@@ -30,6 +31,7 @@ use nexus_types::deployment::SledDisk;
 use nexus_types::deployment::SledFilter;
 use nexus_types::deployment::SledResources;
 use nexus_types::deployment::UnstableReconfiguratorState;
+use nexus_types::deployment::ZonePlacementPolicy;
 use nexus_types::identity::Asset;
 use nexus_types::identity::Resource;
 use nexus_types::inventory::Collection;
@@ -39,6 +41,7 @@ use omicron_common::address::SLED_PREFIX;
 use omicron_common::api::external::Error;
 use omicron_common::api::external::InternalContext;
 use omicron_common::api::external::LookupType;
+use omicron_common::api::external::TufRepoDescription;
 use omicron_common::disk::DiskIdentity;
 use omicron_common::policy::BOUNDARY_NTP_REDUNDANCY;
 use omicron_common::policy::COCKROACHDB_REDUNDANCY;
@@ -55,6 +58,7 @@ use slog::error;
 use slog_error_chain::InlineErrorChain;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::num::NonZeroU32;
 
 /// Given various pieces of database state that go into the blueprint planning
 /// process, produce a `PlanningInput` object encapsulating what the planner
@@ -78,13 +82,24 @@ pub struct PlanningInputFromDb<'a> {
     pub cockroachdb_settings: &'a CockroachDbSettings,
     pub clickhouse_policy: Option<ClickhousePolicy>,
     pub oximeter_read_policy: OximeterReadPolicy,
+    pub tuf_repo: Option<TufRepoDescription>,
+    pub zone_placement_policy: ZonePlacementPolicy,
+    /// failure domains of sleds, as derived by [`sled_failure_domains()`]
+    pub sled_failure_domains: BTreeMap<SledUuid, FailureDomain>,
     pub log: &'a Logger,
 }
 
 impl PlanningInputFromDb<'_> {
+    /// Assembles a `PlanningInput` from the current database state
+    ///
+    /// `latest_collection` is used only to determine the failure domain of
+    /// each sled. Callers generally need the latest inventory collection for
+    /// planning anyway, so it's passed in rather than loaded here. If it's
+    /// `None`, the planner will not know about any failure domains.
     pub async fn assemble(
         opctx: &OpContext,
         datastore: &DataStore,
+        latest_collection: Option<&Collection>,
     ) -> Result<PlanningInput, Error> {
         opctx.check_complex_operations_allowed()?;
         // Note we list *all* rows here including the ones for decommissioned
@@ -151,6 +166,39 @@ impl PlanningInputFromDb<'_> {
             .await
             .internal_context("fetching oximeter read policy")?;
 
+        let zone_placement_policy = datastore
+            .zone_placement_policy_get_latest(opctx)
+            .await
+            .internal_context("fetching zone placement policy")?;
+
+        let target_release = datastore
+            .target_release_get_current(opctx)
+            .await
+            .internal_context("fetching current target release")?;
+        let tuf_repo = match target_release.tuf_repo_id {
+            None => None,
+            Some(tuf_repo_id) => Some(
+                datastore
+                    .update_tuf_repo_get_by_id(opctx, tuf_repo_id.into())
+                    .await
+                    .internal_context("fetching target release repo")?
+                    .into_external(),
+            ),
+        };
+
+        // If the placement policy enables cubby-range spreading, failure
+        // domains are derived from where each sled sits in the rack, which we
+        // only learn from inventory.
+        let sled_failure_domains = match (
+            latest_collection,
+            zone_placement_policy.cubbies_per_failure_domain,
+        ) {
+            (Some(collection), Some(cubbies_per_domain)) => {
+                sled_failure_domains(&sled_rows, collection, cubbies_per_domain)
+            }
+            _ => BTreeMap::new(),
+        };
+
         let planning_input = PlanningInputFromDb {
             sled_rows: &sled_rows,
             zpool_rows: &zpool_rows,
@@ -171,6 +219,9 @@ impl PlanningInputFromDb<'_> {
             cockroachdb_settings: &cockroachdb_settings,
             clickhouse_policy,
             oximeter_read_policy,
+            tuf_repo,
+            zone_placement_policy,
+            sled_failure_domains,
         }
         .build()
         .internal_context("assembling planning_input")?;
@@ -194,6 +245,8 @@ impl PlanningInputFromDb<'_> {
                 .target_crucible_pantry_zone_count,
             clickhouse_policy: self.clickhouse_policy.clone(),
             oximeter_read_policy: self.oximeter_read_policy.clone(),
+            tuf_repo: self.tuf_repo.clone(),
+            zone_placement_policy: self.zone_placement_policy.clone(),
         };
         let mut builder = PlanningInputBuilder::new(
             policy,
@@ -230,13 +283,17 @@ impl PlanningInputFromDb<'_> {
             let zpools = zpools_by_sled_id
                 .remove(&sled_id)
                 .unwrap_or_else(BTreeMap::new);
+            // TODO-cleanup use `TypedUuid` everywhere
+            let sled_id = SledUuid::from_untyped_uuid(sled_id);
             let sled_details = SledDetails {
                 policy: sled_row.policy(),
                 state: sled_row.state().into(),
                 resources: SledResources { subnet, zpools },
+                failure_domain: self
+                    .sled_failure_domains
+                    .get(&sled_id)
+                    .cloned(),
             };
-            // TODO-cleanup use `TypedUuid` everywhere
-            let sled_id = SledUuid::from_untyped_uuid(sled_id);
             builder.add_sled(sled_id, sled_details).map_err(|e| {
                 Error::internal_error(&format!(
                     "unexpectedly failed to add sled to planning input: {e}"
@@ -298,6 +355,32 @@ impl PlanningInputFromDb<'_> {
     }
 }
 
+/// Determine the failure domain of each sled in `sled_rows` based on the
+/// cubby in which inventory `collection` found it, grouping
+/// `cubbies_per_domain` consecutive cubbies into each domain
+///
+/// Sleds are matched to service processors by baseboard. Sleds that don't
+/// appear in the collection are omitted from the returned map.
+pub fn sled_failure_domains(
+    sled_rows: &[nexus_db_model::Sled],
+    collection: &Collection,
+    cubbies_per_domain: NonZeroU32,
+) -> BTreeMap<SledUuid, FailureDomain> {
+    sled_rows
+        .iter()
+        .filter_map(|sled_row| {
+            let (_, sp) = collection.sps.iter().find(|(baseboard_id, _)| {
+                baseboard_id.serial_number == sled_row.serial_number()
+                    && baseboard_id.part_number == sled_row.part_number()
+            })?;
+            Some((
+                SledUuid::from_untyped_uuid(sled_row.id()),
+                FailureDomain::for_cubby_range(sp.sp_slot, cubbies_per_domain),
+            ))
+        })
+        .collect()
+}
+
 /// Loads state for import into `reconfigurator-cli`
 ///
 /// This is only to be used in omdb or tests.
@@ -306,8 +389,6 @@ pub async fn reconfigurator_state_load(
     datastore: &DataStore,
 ) -> Result<UnstableReconfiguratorState, anyhow::Error> {
     opctx.check_complex_operations_allowed()?;
-    let planning_input =
-        PlanningInputFromDb::assemble(opctx, datastore).await?;
     let collection_ids = datastore
         .inventory_collections()
         .await
@@ -327,6 +408,11 @@ pub async fn reconfigurator_state_load(
         .collect::<Vec<Collection>>()
         .await;
 
+    let latest_collection = collections.iter().max_by_key(|c| c.time_started);
+    let planning_input =
+        PlanningInputFromDb::assemble(opctx, datastore, latest_collection)
+            .await?;
+
     let target_blueprint = datastore
         .blueprint_target_get_current(opctx)
         .await
@@ -595,14 +595,23 @@ impl SimSystemBuilderInner {
                     }
                 });
 
-            let result = self.system.description.sled_full(
-                sled_id,
-                sled_details.policy,
-                sled_details.state,
-                sled_details.resources.clone(),
-                inventory_sp,
-                inventory_sled_agent,
-            );
+            let result = self
+                .system
+                .description
+                .sled_full(
+                    sled_id,
+                    sled_details.policy,
+                    sled_details.state,
+                    sled_details.resources.clone(),
+                    inventory_sp,
+                    inventory_sled_agent,
+                )
+                .and_then(|description| {
+                    description.sled_set_failure_domain(
+                        sled_id,
+                        sled_details.failure_domain.clone(),
+                    )
+                });
 
             match result {
                 Ok(_) => {
@@ -660,6 +669,9 @@ impl SimSystemBuilderInner {
         );
         system_res.service_ip_pool_ranges =
             state.planning_input.service_ip_pool_ranges().to_vec();
+        self.system
+            .description
+            .tuf_repo(state.planning_input.tuf_repo().cloned());
 
         self.set_internal_dns(state.internal_dns);
         self.set_external_dns(state.external_dns);
@@ -118,10 +118,12 @@ use super::tasks::region_snapshot_replacement_start::*;
 use super::tasks::region_snapshot_replacement_step::*;
 use super::tasks::saga_recovery;
 use super::tasks::service_firewall_rules;
+use super::tasks::snapshot_policy;
 use super::tasks::support_bundle_collector;
 use super::tasks::sync_service_zone_nat::ServiceZoneNatTracker;
 use super::tasks::sync_switch_configuration::SwitchPortSettingsManager;
 use super::tasks::tuf_artifact_replication;
+use super::tasks::tuf_repo_upload_pruner;
 use super::tasks::v2p_mappings::V2PManager;
 use super::tasks::vpc_routes;
 use crate::Nexus;
@@ -222,6 +224,8 @@ impl BackgroundTasksInitializer {
             task_region_snapshot_replacement_finish: Activator::new(),
             task_tuf_artifact_replication: Activator::new(),
             task_read_only_region_replacement_start: Activator::new(),
+            task_snapshot_policy: Activator::new(),
+            task_tuf_repo_upload_pruner: Activator::new(),
 
             task_internal_dns_propagation: Activator::new(),
             task_external_dns_propagation: Activator::new(),
@@ -294,6 +298,8 @@ impl BackgroundTasksInitializer {
             task_region_snapshot_replacement_finish,
             task_tuf_artifact_replication,
             task_read_only_region_replacement_start,
+            task_snapshot_policy,
+            task_tuf_repo_upload_pruner,
             // Add new background tasks here.  Be sure to use this binding in a
             // call to `Driver::register()` below.  That's what actually wires
             // up the Activator to the corresponding background task.
@@ -828,7 +834,7 @@ impl BackgroundTasksInitializer {
             period: config.region_snapshot_replacement_finish.period_secs,
             task_impl: Box::new(RegionSnapshotReplacementFinishDetector::new(
                 datastore.clone(),
-                sagas,
+                sagas.clone(),
             )),
             opctx: opctx.child(BTreeMap::new()),
             watchers: vec![],
@@ -858,13 +864,41 @@ impl BackgroundTasksInitializer {
                 process",
             period: config.read_only_region_replacement_start.period_secs,
             task_impl: Box::new(ReadOnlyRegionReplacementDetector::new(
-                datastore,
+                datastore.clone(),
             )),
             opctx: opctx.child(BTreeMap::new()),
             watchers: vec![],
             activator: task_read_only_region_replacement_start,
         });
 
+        driver.register(TaskDefinition {
+            name: "tuf_repo_upload_pruner",
+            description: "discards resumable TUF repository uploads that have \
+                been left idle",
+            period: config.tuf_repo_upload_pruner.period_secs,
+            task_impl: Box::new(
+                tuf_repo_upload_pruner::TufRepoUploadPruner::new(
+                    datastore.clone(),
+                ),
+            ),
+            opctx: opctx.child(BTreeMap::new()),
+            watchers: vec![],
+            activator: task_tuf_repo_upload_pruner,
+        });
+
+        driver.register(TaskDefinition {
+            name: "snapshot_policy",
+            description: "takes and prunes scheduled disk snapshots according \
+                to snapshot policies",
+            period: config.snapshot_policy.period_secs,
+            task_impl: Box::new(snapshot_policy::SnapshotPolicyExecutor::new(
+                datastore, sagas,
+            )),
+            opctx: opctx.child(BTreeMap::new()),
+            watchers: vec![],
+            activator: task_snapshot_policy,
+        });
+
         driver
     }
 }
@@ -1132,6 +1166,7 @@ pub mod test {
             store,
             &dns_server::dns_server::Config {
                 bind_address: "[::1]:0".parse().unwrap(),
+                query_log: Default::default(),
             },
             &dropshot::ConfigDropshot {
                 bind_address: "[::1]:0".parse().unwrap(),
@@ -18,6 +18,7 @@ use nexus_types::deployment::{
 };
 use omicron_uuid_kinds::OmicronZoneUuid;
 use serde_json::json;
+use slog_error_chain::InlineErrorChain;
 use std::sync::Arc;
 use tokio::sync::watch;
 use update_engine::NestedError;
@@ -92,6 +93,34 @@ impl BlueprintExecutor {
             });
         }
 
+        // A blueprint that was proposed for review is held until it has been
+        // approved.
+        match self.datastore.blueprint_approval_get(opctx, blueprint.id).await {
+            Ok(Some(approval)) if approval.is_held() => {
+                warn!(&opctx.log,
+                      "Blueprint execution: skipped";
+                      "reason" => "blueprint awaiting approval",
+                      "target_id" => %blueprint.id,
+                      "required_approver" => &approval.required_approver);
+                return json!({
+                    "target_id": blueprint.id.to_string(),
+                    "enabled": true,
+                    "awaiting_approval_from": approval.required_approver,
+                });
+            }
+            Ok(_) => (),
+            Err(error) => {
+                // If we can't tell whether the blueprint is held, we must not
+                // execute it.
+                return json!({
+                    "error": format!(
+                        "failed to check blueprint approval status: {}",
+                        InlineErrorChain::new(&error)
+                    ),
+                });
+            }
+        }
+
         let (sender, mut receiver) = update_engine::channel();
 
         let receiver_task = tokio::spawn(async move {
@@ -192,8 +221,9 @@ mod test {
         StepOutcome, StepStatus,
     };
     use nexus_types::deployment::{
-        Blueprint, BlueprintSledConfig, BlueprintTarget, BlueprintZoneConfig,
-        BlueprintZoneDisposition, BlueprintZoneImageSource, BlueprintZoneType,
+        Blueprint, BlueprintApproval, BlueprintSledConfig, BlueprintTarget,
+        BlueprintZoneConfig, BlueprintZoneDisposition,
+        BlueprintZoneImageSource, BlueprintZoneType,
         CockroachDbPreserveDowngrade, OximeterReadMode, PendingMgsUpdates,
         blueprint_zone_type,
     };
@@ -223,6 +253,25 @@ mod test {
         opctx: &OpContext,
         blueprint_zones: BTreeMap<SledUuid, IdMap<BlueprintZoneConfig>>,
         dns_version: Generation,
+    ) -> (BlueprintTarget, Blueprint) {
+        let (target, blueprint) =
+            insert_blueprint(datastore, opctx, blueprint_zones, dns_version)
+                .await;
+        datastore
+            .blueprint_target_set_current(opctx, target)
+            .await
+            .expect("set new blueprint as current target");
+
+        (target, blueprint)
+    }
+
+    /// Like `create_blueprint()`, but doesn't make the new blueprint the
+    /// current target
+    async fn insert_blueprint(
+        datastore: &DataStore,
+        opctx: &OpContext,
+        blueprint_zones: BTreeMap<SledUuid, IdMap<BlueprintZoneConfig>>,
+        dns_version: Generation,
     ) -> (BlueprintTarget, Blueprint) {
         let id = BlueprintUuid::new_v4();
         // Assume all sleds are active with no disks or datasets.
@@ -242,9 +291,9 @@ mod test {
             })
             .collect();
 
-        // Ensure the blueprint we're creating is the current target (required
-        // for successful blueprint realization). This requires its parent to be
-        // the existing target, so fetch that first.
+        // The blueprint we're creating must be able to become the current
+        // target (required for successful blueprint realization). This
+        // requires its parent to be the existing target, so fetch that first.
         let current_target = datastore
             .blueprint_target_get_current(opctx)
             .await
@@ -271,16 +320,13 @@ mod test {
             time_created: chrono::Utc::now(),
             creator: "test".to_string(),
             comment: "test blueprint".to_string(),
+            planning_report: None,
         };
 
         datastore
             .blueprint_insert(opctx, &blueprint)
             .await
             .expect("inserted new blueprint");
-        datastore
-            .blueprint_target_set_current(opctx, target)
-            .await
-            .expect("set new blueprint as current target");
 
         (target, blueprint)
     }
@@ -603,6 +649,98 @@ mod test {
         s2.verify_and_clear();
     }
 
+    #[nexus_test(server = crate::Server)]
+    async fn test_held_until_approved(cptestctx: &ControlPlaneTestContext) {
+        // Set up the test.
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let resolver = nexus.resolver();
+        let opctx = OpContext::for_background(
+            cptestctx.logctx.log.clone(),
+            nexus.authz.clone(),
+            authn::Context::internal_api(),
+            datastore.clone(),
+        );
+
+        let (blueprint_tx, blueprint_rx) = watch::channel(None);
+        let (dummy_tx, _dummy_rx) = watch::channel(PendingMgsUpdates::new());
+        let mut task = BlueprintExecutor::new(
+            datastore.clone(),
+            resolver.clone(),
+            blueprint_rx,
+            OmicronZoneUuid::new_v4(),
+            Activator::new(),
+            dummy_tx,
+        );
+
+        // Propose a blueprint for review, then make it the (enabled) target
+        // before it has been approved.
+        let (target, blueprint) = insert_blueprint(
+            &datastore,
+            &opctx,
+            BTreeMap::new(),
+            Generation::new(),
+        )
+        .await;
+        let blueprint_id = blueprint.id;
+        datastore
+            .blueprint_propose(
+                &opctx,
+                BlueprintApproval {
+                    blueprint_id,
+                    proposed_by: "alice".to_string(),
+                    required_approver: "bob".to_string(),
+                    time_proposed: chrono::Utc::now(),
+                    approved_by: None,
+                    time_approved: None,
+                },
+            )
+            .await
+            .expect("proposed blueprint");
+        datastore
+            .blueprint_target_set_current(&opctx, target)
+            .await
+            .expect("set new blueprint as current target");
+        blueprint_tx.send(Some(Arc::new((target, blueprint)))).unwrap();
+
+        // The task must not execute the target while it's awaiting approval.
+        let value = task.activate(&opctx).await;
+        println!("activating while held: {:?}", value);
+        assert_eq!(
+            value,
+            json!({
+                "target_id": blueprint_id.to_string(),
+                "enabled": true,
+                "awaiting_approval_from": "bob",
+            })
+        );
+
+        // Once it's approved, the target is executed as usual.
+        datastore
+            .blueprint_approve(
+                &opctx,
+                blueprint_id,
+                "bob".to_string(),
+                chrono::Utc::now(),
+            )
+            .await
+            .expect("approved blueprint");
+        let mut value = task.activate(&opctx).await;
+        let event_buffer = extract_event_buffer(&mut value);
+
+        println!("activating once approved: {:?}", value);
+        assert_eq!(
+            value,
+            json!({
+                "target_id": blueprint_id,
+                "execution_error": null,
+                "enabled": true,
+                "needs_saga_recovery": false,
+            })
+        );
+        assert_event_buffer_completed(&event_buffer);
+    }
+
     fn extract_event_buffer(value: &mut serde_json::Value) -> EventBuffer {
         let event_report = value
             .as_object_mut()
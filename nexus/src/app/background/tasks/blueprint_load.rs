@@ -231,6 +231,7 @@ mod test {
                 time_created: now_db_precision(),
                 creator: "test".to_string(),
                 comment: "test blueprint".to_string(),
+                planning_report: None,
             },
         )
     }
@@ -34,9 +34,11 @@ pub mod region_snapshot_replacement_start;
 pub mod region_snapshot_replacement_step;
 pub mod saga_recovery;
 pub mod service_firewall_rules;
+pub mod snapshot_policy;
 pub mod support_bundle_collector;
 pub mod sync_service_zone_nat;
 pub mod sync_switch_configuration;
 pub mod tuf_artifact_replication;
+pub mod tuf_repo_upload_pruner;
 pub mod v2p_mappings;
 pub mod vpc_routes;
@@ -0,0 +1,472 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Background task for carrying out scheduled snapshot policies
+//!
+//! Each (policy, disk) pair is due for a snapshot once per policy interval.
+//! The time within the interval is derived from the policy and disk IDs, so
+//! that the snapshots of a fleet's disks are spread out rather than all taken
+//! at once. Whichever Nexus first claims a scheduled snapshot (by inserting a
+//! `snapshot_policy_snapshot` record for it) takes it, and then deletes the
+//! oldest of the policy's snapshots of that disk beyond its retention count.
+//!
+//! A scheduled snapshot that fails is not retried: the next one is taken at
+//! the next scheduled time, and the failure is recorded on the policy. Quota
+//! is enforced by the snapshot create saga like for any other snapshot.
+
+use crate::app::background::BackgroundTask;
+use crate::app::saga::StartSaga;
+use crate::app::sagas::NexusSaga;
+use crate::app::sagas::snapshot_create;
+use crate::app::sagas::snapshot_delete;
+use crate::app::snapshot::disk_snapshot_uses_pantry;
+use chrono::DateTime;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use nexus_db_lookup::LookupPath;
+use nexus_db_model::Disk;
+use nexus_db_model::SnapshotPolicy;
+use nexus_db_model::SnapshotPolicySnapshot;
+use nexus_db_model::SnapshotState;
+use nexus_db_queries::authn;
+use nexus_db_queries::authz;
+use nexus_db_queries::context::OpContext;
+use nexus_db_queries::db::DataStore;
+use nexus_db_queries::db::datastore::SQL_BATCH_SIZE;
+use nexus_db_queries::db::pagination::Paginator;
+use nexus_types::external_api::params;
+use nexus_types::identity::Resource;
+use nexus_types::internal_api::background::SnapshotPolicyStatus;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::Name;
+use omicron_common::api::external::NameOrId;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub struct SnapshotPolicyExecutor {
+    datastore: Arc<DataStore>,
+    sagas: Arc<dyn StartSaga>,
+    /// The most recent scheduled time this Nexus has seen claimed for each
+    /// (policy, disk) pair, so that the claim isn't attempted again on every
+    /// activation until the next scheduled time.
+    last_claimed: BTreeMap<(Uuid, Uuid), DateTime<Utc>>,
+}
+
+impl SnapshotPolicyExecutor {
+    pub fn new(datastore: Arc<DataStore>, sagas: Arc<dyn StartSaga>) -> Self {
+        SnapshotPolicyExecutor {
+            datastore,
+            sagas,
+            last_claimed: BTreeMap::new(),
+        }
+    }
+
+    async fn run_all_policies(
+        &mut self,
+        opctx: &OpContext,
+        status: &mut SnapshotPolicyStatus,
+    ) -> Result<(), Error> {
+        let mut last_claimed = BTreeMap::new();
+
+        let mut paginator = Paginator::new(SQL_BATCH_SIZE);
+        while let Some(p) = paginator.next() {
+            let batch = self
+                .datastore
+                .snapshot_policy_list_all(opctx, &p.current_pagparams())
+                .await?;
+            paginator = p.found_batch(&batch, &|policy| policy.id());
+            status.policies_found += batch.len();
+
+            for policy in batch {
+                self.run_policy(opctx, &policy, &mut last_claimed, status)
+                    .await;
+            }
+        }
+
+        // Forget about policies and disks that no longer exist.
+        self.last_claimed = last_claimed;
+        Ok(())
+    }
+
+    async fn run_policy(
+        &self,
+        opctx: &OpContext,
+        policy: &SnapshotPolicy,
+        last_claimed: &mut BTreeMap<(Uuid, Uuid), DateTime<Utc>>,
+        status: &mut SnapshotPolicyStatus,
+    ) {
+        let log = &opctx.log;
+        let now = Utc::now();
+        let mut errors = Vec::new();
+        let mut did_work = false;
+
+        let mut paginator = Paginator::new(SQL_BATCH_SIZE);
+        while let Some(p) = paginator.next() {
+            let batch = match self
+                .datastore
+                .snapshot_policy_disk_list(
+                    opctx,
+                    policy,
+                    &p.current_pagparams(),
+                )
+                .await
+            {
+                Ok(batch) => batch,
+                Err(e) => {
+                    errors.push(format!("listing disks: {e}"));
+                    break;
+                }
+            };
+            paginator = p.found_batch(&batch, &|disk| disk.id());
+
+            for disk in batch {
+                let key = (policy.id(), disk.id());
+                let scheduled = scheduled_time(
+                    policy.id(),
+                    disk.id(),
+                    *policy.interval_secs,
+                    now,
+                );
+                last_claimed.insert(key, scheduled);
+                if self.last_claimed.get(&key) == Some(&scheduled) {
+                    continue;
+                }
+
+                let record = SnapshotPolicySnapshot::new(
+                    policy.id(),
+                    disk.id(),
+                    scheduled,
+                );
+                match self
+                    .datastore
+                    .snapshot_policy_snapshot_claim(opctx, record.clone())
+                    .await
+                {
+                    Ok(true) => (),
+                    Ok(false) => continue,
+                    Err(e) => {
+                        // Try to claim it again on the next activation.
+                        last_claimed.remove(&key);
+                        errors.push(format!(
+                            "claiming snapshot of disk {}: {e}",
+                            disk.id()
+                        ));
+                        continue;
+                    }
+                }
+
+                did_work = true;
+                info!(
+                    log,
+                    "taking scheduled snapshot";
+                    "policy_id" => %policy.id(),
+                    "disk_id" => %disk.id(),
+                    "time_scheduled" => %scheduled,
+                );
+
+                match self.take_snapshot(opctx, policy, &disk, &record).await {
+                    Ok(snapshot_id) => {
+                        status.snapshots_created.push(snapshot_id);
+                    }
+                    Err(e) => {
+                        warn!(
+                            log,
+                            "failed to take scheduled snapshot";
+                            "policy_id" => %policy.id(),
+                            "disk_id" => %disk.id(),
+                            "error" => %e,
+                        );
+                        errors.push(format!(
+                            "snapshotting disk {}: {e}",
+                            disk.id()
+                        ));
+                        continue;
+                    }
+                }
+
+                self.prune_snapshots(opctx, policy, &disk, status, &mut errors)
+                    .await;
+            }
+        }
+
+        if !errors.is_empty() || did_work {
+            let last_error =
+                if errors.is_empty() { None } else { Some(errors.join("; ")) };
+            if let Err(e) = self
+                .datastore
+                .snapshot_policy_record_run(opctx, policy.id(), last_error)
+                .await
+            {
+                errors.push(format!("recording run: {e}"));
+            }
+        }
+
+        status.errors.extend(
+            errors
+                .into_iter()
+                .map(|e| format!("snapshot policy {}: {e}", policy.id())),
+        );
+    }
+
+    /// Takes the snapshot of `disk` claimed by `record`, returning its ID
+    async fn take_snapshot(
+        &self,
+        opctx: &OpContext,
+        policy: &SnapshotPolicy,
+        disk: &Disk,
+        record: &SnapshotPolicySnapshot,
+    ) -> Result<Uuid, Error> {
+        let (authz_silo, authz_project) =
+            LookupPath::new(opctx, &self.datastore)
+                .project_id(policy.project_id)
+                .lookup_for(authz::Action::Read)
+                .await?;
+
+        let name = snapshot_name(policy, disk, record.time_scheduled)?;
+        let use_the_pantry =
+            disk_snapshot_uses_pantry(opctx, &self.datastore, disk).await?;
+
+        let dag = snapshot_create::SagaSnapshotCreate::prepare(
+            &snapshot_create::Params {
+                serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+                silo_id: authz_silo.id(),
+                project_id: authz_project.id(),
+                disk_id: disk.id(),
+                attach_instance_id: disk.runtime_state.attach_instance_id,
+                use_the_pantry,
+                create_params: params::SnapshotCreate {
+                    identity: IdentityMetadataCreateParams {
+                        name: name.clone(),
+                        description: format!(
+                            "snapshot of disk {} taken by snapshot policy {}",
+                            disk.name(),
+                            policy.name(),
+                        ),
+                    },
+                    disk: NameOrId::Id(disk.id()),
+                },
+                group_snapshot_id: None,
+            },
+        )?;
+        let (_, completed) = self.sagas.saga_run(dag).await?;
+        completed.await?;
+
+        let (.., authz_snapshot) = LookupPath::new(opctx, &self.datastore)
+            .project_id(authz_project.id())
+            .snapshot_name_owned(name.into())
+            .lookup_for(authz::Action::Read)
+            .await?;
+        self.datastore
+            .snapshot_policy_snapshot_set_id(opctx, record, authz_snapshot.id())
+            .await?;
+
+        Ok(authz_snapshot.id())
+    }
+
+    /// Deletes the policy's snapshots of `disk` beyond its retention count
+    async fn prune_snapshots(
+        &self,
+        opctx: &OpContext,
+        policy: &SnapshotPolicy,
+        disk: &Disk,
+        status: &mut SnapshotPolicyStatus,
+        errors: &mut Vec<String>,
+    ) {
+        let expired = match self
+            .datastore
+            .snapshot_policy_expired_snapshots(opctx, policy, disk.id())
+            .await
+        {
+            Ok(expired) => expired,
+            Err(e) => {
+                errors.push(format!(
+                    "listing expired snapshots of disk {}: {e}",
+                    disk.id()
+                ));
+                return;
+            }
+        };
+
+        for snapshot in expired {
+            // Snapshots that are still being created or are already being
+            // deleted are left for a later run.
+            if !matches!(
+                snapshot.state,
+                SnapshotState::Ready | SnapshotState::Faulted
+            ) {
+                continue;
+            }
+
+            let snapshot_id = snapshot.id();
+            match self.delete_snapshot(opctx, snapshot).await {
+                Ok(()) => status.snapshots_deleted.push(snapshot_id),
+                Err(e) => errors.push(format!(
+                    "deleting expired snapshot {snapshot_id}: {e}"
+                )),
+            }
+        }
+    }
+
+    async fn delete_snapshot(
+        &self,
+        opctx: &OpContext,
+        snapshot: nexus_db_model::Snapshot,
+    ) -> Result<(), Error> {
+        let (.., authz_snapshot) = LookupPath::new(opctx, &self.datastore)
+            .snapshot_id(snapshot.id())
+            .lookup_for(authz::Action::Delete)
+            .await?;
+
+        // Like a user-requested delete, leave snapshots that are being
+        // exported alone.
+        let exports = self
+            .datastore
+            .snapshot_export_list_for_snapshot(opctx, &authz_snapshot)
+            .await?;
+        if !exports.is_empty() {
+            return Err(Error::invalid_request(
+                "snapshot has exports, which must be deleted first",
+            ));
+        }
+
+        let dag = snapshot_delete::SagaSnapshotDelete::prepare(
+            &snapshot_delete::Params {
+                serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+                authz_snapshot,
+                snapshot,
+            },
+        )?;
+        let (_, completed) = self.sagas.saga_run(dag).await?;
+        completed.await
+    }
+}
+
+impl BackgroundTask for SnapshotPolicyExecutor {
+    fn activate<'a>(
+        &'a mut self,
+        opctx: &'a OpContext,
+    ) -> BoxFuture<'a, serde_json::Value> {
+        Box::pin(async move {
+            let mut status = SnapshotPolicyStatus::default();
+
+            if let Err(e) = self.run_all_policies(opctx, &mut status).await {
+                let s = format!("listing snapshot policies: {e}");
+                error!(&opctx.log, "{s}");
+                status.errors.push(s);
+            }
+
+            if status.errors.is_empty() {
+                info!(
+                    &opctx.log,
+                    "snapshot policies ran successfully";
+                    "policies_found" => status.policies_found,
+                    "snapshots_created" => status.snapshots_created.len(),
+                    "snapshots_deleted" => status.snapshots_deleted.len(),
+                );
+            } else {
+                warn!(
+                    &opctx.log,
+                    "snapshot policies ran with errors";
+                    "policies_found" => status.policies_found,
+                    "snapshots_created" => status.snapshots_created.len(),
+                    "snapshots_deleted" => status.snapshots_deleted.len(),
+                    "errors" => status.errors.len(),
+                );
+            }
+
+            json!(status)
+        })
+    }
+}
+
+/// Returns the most recent time at or before `now` that a policy with the
+/// given interval is due to snapshot a disk
+///
+/// The scheduled times of each (policy, disk) pair are offset from the epoch
+/// by an amount derived from their IDs, which spreads the snapshots of many
+/// disks across the interval.
+fn scheduled_time(
+    policy_id: Uuid,
+    disk_id: Uuid,
+    interval_secs: u32,
+    now: DateTime<Utc>,
+) -> DateTime<Utc> {
+    let interval = i64::from(interval_secs.max(1));
+    let offset = ((policy_id.as_u128() ^ disk_id.as_u128())
+        % u128::from(interval.unsigned_abs())) as i64;
+    let now_secs = now.timestamp();
+    let scheduled = now_secs - (now_secs - offset).rem_euclid(interval);
+    DateTime::from_timestamp(scheduled, 0).unwrap_or(now)
+}
+
+/// Returns the name of the snapshot of `disk` scheduled for `time_scheduled`
+fn snapshot_name(
+    policy: &SnapshotPolicy,
+    disk: &Disk,
+    time_scheduled: DateTime<Utc>,
+) -> Result<Name, Error> {
+    // Names are at most 63 characters long: truncate the policy and disk
+    // names so that the 14 digit timestamp always fits.
+    let policy_name: String = policy.name().as_str().chars().take(20).collect();
+    let disk_name: String = disk.name().as_str().chars().take(26).collect();
+    format!(
+        "{policy_name}-{disk_name}-{}",
+        time_scheduled.format("%Y%m%d%H%M%S")
+    )
+    .parse()
+    .map_err(|e| Error::internal_error(&format!("invalid snapshot name: {e}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_scheduled_time() {
+        let policy_id = Uuid::new_v4();
+        let disk_id = Uuid::new_v4();
+        let interval_secs = 6 * 60 * 60;
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 34, 56).unwrap();
+
+        let scheduled = scheduled_time(policy_id, disk_id, interval_secs, now);
+        assert!(scheduled <= now);
+        assert!(
+            now - scheduled < chrono::Duration::seconds(interval_secs.into())
+        );
+
+        // Any time up to the next scheduled time maps to the same one.
+        let next = scheduled + chrono::Duration::seconds(interval_secs.into());
+        assert_eq!(
+            scheduled_time(
+                policy_id,
+                disk_id,
+                interval_secs,
+                next - chrono::Duration::seconds(1)
+            ),
+            scheduled,
+        );
+        assert_eq!(
+            scheduled_time(policy_id, disk_id, interval_secs, next),
+            next,
+        );
+    }
+
+    #[test]
+    fn test_scheduled_time_is_spread_out() {
+        let policy_id = Uuid::new_v4();
+        let interval_secs = 24 * 60 * 60;
+        let now = Utc::now();
+
+        let scheduled: std::collections::BTreeSet<_> = (0..16)
+            .map(|_| {
+                scheduled_time(policy_id, Uuid::new_v4(), interval_secs, now)
+            })
+            .collect();
+        assert!(scheduled.len() > 1);
+    }
+}
@@ -0,0 +1,74 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Background task for discarding idle resumable TUF repository uploads
+//!
+//! The data for a resumable upload is stored in the database until the upload
+//! is finalized or deleted. Clients that give up on an upload without deleting
+//! it would otherwise leave that data behind forever, so this task deletes
+//! uploads that have not received any data for
+//! [`TUF_REPO_UPLOAD_IDLE_TIMEOUT`]. It also finishes deleting the data of
+//! uploads whose deletion was interrupted.
+
+use crate::app::background::BackgroundTask;
+use chrono::Utc;
+use futures::future::BoxFuture;
+use nexus_db_queries::context::OpContext;
+use nexus_db_queries::db::DataStore;
+use nexus_types::internal_api::background::TufRepoUploadPrunerStatus;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a resumable TUF repository upload may go without receiving data
+/// before it is discarded.
+pub const TUF_REPO_UPLOAD_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+pub struct TufRepoUploadPruner {
+    datastore: Arc<DataStore>,
+}
+
+impl TufRepoUploadPruner {
+    pub fn new(datastore: Arc<DataStore>) -> Self {
+        TufRepoUploadPruner { datastore }
+    }
+}
+
+impl BackgroundTask for TufRepoUploadPruner {
+    fn activate<'a>(
+        &'a mut self,
+        opctx: &'a OpContext,
+    ) -> BoxFuture<'a, serde_json::Value> {
+        Box::pin(async move {
+            let mut status = TufRepoUploadPrunerStatus::default();
+
+            let cutoff = Utc::now()
+                - chrono::TimeDelta::from_std(TUF_REPO_UPLOAD_IDLE_TIMEOUT)
+                    .expect("idle timeout fits in a TimeDelta");
+            match self
+                .datastore
+                .tuf_repo_upload_delete_idle(opctx, cutoff)
+                .await
+            {
+                Ok(deleted) => {
+                    for id in &deleted {
+                        info!(
+                            &opctx.log,
+                            "discarded idle TUF repository upload";
+                            "upload_id" => %id,
+                        );
+                    }
+                    status.uploads_deleted = deleted;
+                }
+                Err(e) => {
+                    let s = format!("deleting idle uploads: {e}");
+                    error!(&opctx.log, "{s}");
+                    status.errors.push(s);
+                }
+            }
+
+            json!(status)
+        })
+    }
+}
@@ -6,13 +6,18 @@
 
 use nexus_db_queries::authz;
 use nexus_db_queries::context::OpContext;
+use nexus_reconfigurator_execution::RequiredRealizeArgs;
 use nexus_reconfigurator_planning::planner::Planner;
 use nexus_reconfigurator_preparation::PlanningInputFromDb;
 use nexus_types::deployment::Blueprint;
+use nexus_types::deployment::BlueprintApproval;
 use nexus_types::deployment::BlueprintMetadata;
 use nexus_types::deployment::BlueprintTarget;
+use nexus_types::deployment::BlueprintTargetAuditEntry;
 use nexus_types::deployment::BlueprintTargetSet;
+use nexus_types::deployment::ExecutionPlan;
 use nexus_types::deployment::PlanningInput;
+use nexus_types::external_api::params;
 use nexus_types::inventory::Collection;
 use omicron_common::api::external::CreateResult;
 use omicron_common::api::external::DataPageParams;
@@ -22,7 +27,10 @@ use omicron_common::api::external::InternalContext;
 use omicron_common::api::external::ListResultVec;
 use omicron_common::api::external::LookupResult;
 use omicron_common::api::external::LookupType;
+use omicron_uuid_kinds::BlueprintUuid;
+use omicron_uuid_kinds::GenericUuid;
 use slog_error_chain::InlineErrorChain;
+use tokio::sync::watch;
 use uuid::Uuid;
 
 /// Common structure for collecting information that the planner needs
@@ -86,7 +94,11 @@ impl super::Nexus {
         };
 
         self.db_datastore
-            .blueprint_target_set_current(opctx, new_target)
+            .blueprint_target_set_current_audited(
+                opctx,
+                new_target,
+                params.actor,
+            )
             .await?;
 
         // We have a new target: trigger the background task to load this
@@ -109,7 +121,11 @@ impl super::Nexus {
         };
 
         self.db_datastore
-            .blueprint_target_set_current_enabled(opctx, new_target)
+            .blueprint_target_set_current_enabled_audited(
+                opctx,
+                new_target,
+                params.actor,
+            )
             .await?;
 
         // We don't know whether this actually changed the enabled bit; activate
@@ -120,14 +136,133 @@ impl super::Nexus {
         Ok(new_target)
     }
 
+    pub async fn blueprint_propose(
+        &self,
+        opctx: &OpContext,
+        blueprint_id: BlueprintUuid,
+        params: params::BlueprintPropose,
+    ) -> Result<BlueprintApproval, Error> {
+        let &actor = opctx
+            .authn
+            .actor_required()
+            .internal_context("proposing blueprint")?;
+        let approval = BlueprintApproval {
+            blueprint_id,
+            proposed_by: actor.actor_id().to_string(),
+            required_approver: params.required_approver.to_string(),
+            time_proposed: chrono::Utc::now(),
+            approved_by: None,
+            time_approved: None,
+        };
+        self.db_datastore.blueprint_propose(opctx, approval.clone()).await?;
+        Ok(approval)
+    }
+
+    pub async fn blueprint_approve(
+        &self,
+        opctx: &OpContext,
+        blueprint_id: BlueprintUuid,
+    ) -> Result<BlueprintApproval, Error> {
+        let &actor = opctx
+            .authn
+            .actor_required()
+            .internal_context("approving blueprint")?;
+        let approval = self
+            .db_datastore
+            .blueprint_approve(
+                opctx,
+                blueprint_id,
+                actor.actor_id().to_string(),
+                chrono::Utc::now(),
+            )
+            .await?;
+
+        // If this blueprint is the current target, execution has been held
+        // waiting for this approval; kick the executor so it proceeds.
+        self.background_tasks
+            .activate(&self.background_tasks.task_blueprint_executor);
+
+        Ok(approval)
+    }
+
+    pub async fn blueprint_approval_view(
+        &self,
+        opctx: &OpContext,
+        blueprint_id: BlueprintUuid,
+    ) -> LookupResult<BlueprintApproval> {
+        self.db_datastore
+            .blueprint_approval_get(opctx, blueprint_id)
+            .await?
+            .ok_or_else(|| {
+                Error::non_resourcetype_not_found(format!(
+                    "blueprint {blueprint_id} has not been proposed for review"
+                ))
+            })
+    }
+
+    pub async fn blueprint_target_audit_list(
+        &self,
+        opctx: &OpContext,
+        blueprint_id: BlueprintUuid,
+    ) -> ListResultVec<BlueprintTargetAuditEntry> {
+        self.db_datastore.blueprint_target_audit_list(opctx, blueprint_id).await
+    }
+
+    /// Computes what executing the given blueprint would change right now,
+    /// without changing anything
+    pub async fn blueprint_plan_execution(
+        &self,
+        opctx: &OpContext,
+        blueprint_id: BlueprintUuid,
+    ) -> Result<ExecutionPlan, Error> {
+        let blueprint = self
+            .blueprint_view(opctx, blueprint_id.into_untyped_uuid())
+            .await?;
+
+        // Nobody is watching the progress of a dry run, but the engine still
+        // needs its events drained.
+        let (sender, mut receiver) = update_engine::channel();
+        let receiver_task =
+            tokio::spawn(
+                async move { while receiver.recv().await.is_some() {} },
+            );
+
+        // Planning MGS-managed updates only compares the blueprint against
+        // what execution last requested.  Give it a channel of its own so that
+        // nothing it does can reach the real update driver.
+        let (mgs_updates, _) = watch::channel(self.pending_mgs_updates());
+
+        let result = nexus_reconfigurator_execution::plan_blueprint_execution(
+            RequiredRealizeArgs {
+                opctx,
+                datastore: self.datastore(),
+                resolver: self.resolver(),
+                creator: self.id(),
+                blueprint: &blueprint,
+                sender,
+                mgs_updates,
+            }
+            .as_nexus(self.id()),
+        )
+        .await;
+
+        // The receiver task can't fail in a way we care about.
+        let _ = receiver_task.await;
+
+        result.map_err(|error| {
+            Error::internal_error(&format!(
+                "failed to plan execution of blueprint {blueprint_id}: \
+                 {error:#}"
+            ))
+        })
+    }
+
     async fn blueprint_planning_context(
         &self,
         opctx: &OpContext,
     ) -> Result<PlanningContext, Error> {
         let creator = self.id.to_string();
         let datastore = self.datastore();
-        let planning_input =
-            PlanningInputFromDb::assemble(opctx, datastore).await?;
 
         // The choice of which inventory collection to use here is not
         // necessarily trivial.  Inventory collections may be incomplete due to
@@ -146,6 +281,9 @@ impl super::Nexus {
             .internal_context(
                 "fetching latest inventory collection for blueprint planner",
             )?;
+        let planning_input =
+            PlanningInputFromDb::assemble(opctx, datastore, inventory.as_ref())
+                .await?;
 
         Ok(PlanningContext { planning_input, creator, inventory })
     }
@@ -130,11 +130,46 @@ impl super::Nexus {
 
                 db_image.block_size.to_bytes().into()
             }
+            params::DiskSource::Disk { disk_id } => {
+                let (.., db_disk) = LookupPath::new(opctx, &self.db_datastore)
+                    .disk_id(disk_id)
+                    .fetch()
+                    .await?;
+
+                // Return an error if the source disk does not belong to our
+                // project: the intermediate snapshot is taken there.
+                if db_disk.project_id != authz_project.id() {
+                    return Err(Error::invalid_request(
+                        "source disk does not belong to this project",
+                    ));
+                }
+
+                // If the size of the source disk is greater than the size of
+                // the new disk, return an error.
+                if db_disk.size.to_bytes() > params.size.to_bytes() {
+                    return Err(Error::invalid_request(&format!(
+                        "disk size {} must be greater than or equal to source disk size {}",
+                        params.size.to_bytes(),
+                        db_disk.size.to_bytes(),
+                    )));
+                }
+
+                db_disk.block_size.to_bytes().into()
+            }
         };
 
+        Self::validate_disk_size(params.size, block_size)
+    }
+
+    /// Checks that `size` is a valid size for a disk with the given
+    /// `block_size`.
+    pub(super) fn validate_disk_size(
+        size: ByteCount,
+        block_size: u64,
+    ) -> Result<(), Error> {
         // Reject disks where the block size doesn't evenly divide the
         // total size
-        if (params.size.to_bytes() % block_size) != 0 {
+        if (size.to_bytes() % block_size) != 0 {
             return Err(Error::invalid_value(
                 "size and block_size",
                 format!(
@@ -146,7 +181,7 @@ impl super::Nexus {
 
         // Reject disks where the size isn't at least
         // MIN_DISK_SIZE_BYTES
-        if params.size.to_bytes() < u64::from(MIN_DISK_SIZE_BYTES) {
+        if size.to_bytes() < u64::from(MIN_DISK_SIZE_BYTES) {
             return Err(Error::invalid_value(
                 "size",
                 format!(
@@ -158,7 +193,7 @@ impl super::Nexus {
 
         // Reject disks where the MIN_DISK_SIZE_BYTES doesn't evenly
         // divide the size
-        if (params.size.to_bytes() % u64::from(MIN_DISK_SIZE_BYTES)) != 0 {
+        if (size.to_bytes() % u64::from(MIN_DISK_SIZE_BYTES)) != 0 {
             return Err(Error::invalid_value(
                 "size",
                 format!(
@@ -169,7 +204,7 @@ impl super::Nexus {
         }
 
         // Reject disks where the size is greated than MAX_DISK_SIZE_BYTES
-        if params.size.to_bytes() > MAX_DISK_SIZE_BYTES {
+        if size.to_bytes() > MAX_DISK_SIZE_BYTES {
             return Err(Error::invalid_value(
                 "size",
                 format!(
@@ -192,20 +227,117 @@ impl super::Nexus {
             project_lookup.lookup_for(authz::Action::CreateChild).await?;
         self.validate_disk_create_params(opctx, &authz_project, params).await?;
 
-        let saga_params = sagas::disk_create::Params {
+        let saga_outputs = match params.disk_source {
+            params::DiskSource::Disk { disk_id } => {
+                // Cloning a disk snapshots the source disk first, so look it
+                // up the same way that creating a snapshot of it would.
+                let (authz_silo, .., authz_source_disk, db_source_disk) =
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .disk_id(disk_id)
+                        .fetch_for(authz::Action::Read)
+                        .await?;
+
+                let use_the_pantry = self
+                    .disk_snapshot_uses_pantry(opctx, &db_source_disk)
+                    .await?;
+
+                let saga_params = sagas::disk_clone::Params {
+                    serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+                    silo_id: authz_silo.id(),
+                    project_id: authz_project.id(),
+                    clone_id: Uuid::new_v4(),
+                    source_disk_id: authz_source_disk.id(),
+                    attach_instance_id: db_source_disk
+                        .runtime_state
+                        .attach_instance_id,
+                    use_the_pantry,
+                    create_params: params.clone(),
+                };
+                self.sagas
+                    .saga_execute::<sagas::disk_clone::SagaDiskClone>(
+                        saga_params,
+                    )
+                    .await?
+            }
+
+            _ => {
+                let saga_params = sagas::disk_create::Params {
+                    serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+                    project_id: authz_project.id(),
+                    create_params: params.clone(),
+                };
+                self.sagas
+                    .saga_execute::<sagas::disk_create::SagaDiskCreate>(
+                        saga_params,
+                    )
+                    .await?
+            }
+        };
+        let disk_created = saga_outputs
+            .lookup_node_output::<db::model::Disk>("created_disk")
+            .map_err(|e| Error::internal_error(&format!("{:#}", &e)))
+            .internal_context("looking up output from disk create saga")?;
+        Ok(disk_created)
+    }
+
+    /// Grows a disk to the size requested in `params`.
+    ///
+    /// The disk must be detached, or attached to an instance that is stopped.
+    /// Resizing a disk in use by a running instance fails with a conflict.
+    pub(crate) async fn disk_resize(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        disk_lookup: &lookup::Disk<'_>,
+        params: &params::DiskResize,
+    ) -> UpdateResult<db::model::Disk> {
+        let (.., authz_project, authz_disk, db_disk) =
+            disk_lookup.fetch_for(authz::Action::Modify).await?;
+
+        let disk_state: DiskState = db_disk.state().into();
+        match disk_state {
+            DiskState::Detached | DiskState::Attached(_) => {
+                // ok
+            }
+
+            _ => {
+                return Err(Error::invalid_request(&format!(
+                    "cannot resize disk in state {:?}",
+                    disk_state.label(),
+                )));
+            }
+        }
+
+        if params.size.to_bytes() <= db_disk.size.to_bytes() {
+            return Err(Error::invalid_value(
+                "size",
+                format!(
+                    "new size must be larger than the current size {}",
+                    db_disk.size.to_bytes(),
+                ),
+            ));
+        }
+        Self::validate_disk_size(
+            params.size,
+            u64::from(db_disk.block_size.to_bytes()),
+        )?;
+
+        let saga_params = sagas::disk_resize::Params {
             serialized_authn: authn::saga::Serialized::for_opctx(opctx),
             project_id: authz_project.id(),
-            create_params: params.clone(),
+            disk_id: authz_disk.id(),
+            volume_id: db_disk.volume_id(),
+            old_size: db_disk.size.into(),
+            new_size: params.size,
         };
         let saga_outputs = self
             .sagas
-            .saga_execute::<sagas::disk_create::SagaDiskCreate>(saga_params)
+            .saga_execute::<sagas::disk_resize::SagaDiskResize>(saga_params)
             .await?;
-        let disk_created = saga_outputs
-            .lookup_node_output::<db::model::Disk>("created_disk")
+        let disk_resized = saga_outputs
+            .lookup_node_output::<db::model::Disk>("resized_disk")
             .map_err(|e| Error::internal_error(&format!("{:#}", &e)))
-            .internal_context("looking up output from disk create saga")?;
-        Ok(disk_created)
+            .internal_context("looking up output from disk resize saga")?;
+        Ok(disk_resized)
     }
 
     pub(crate) async fn disk_list(
@@ -91,6 +91,17 @@ impl super::Nexus {
                 let (authz_silo, authz_project) =
                     project.lookup_for(authz::Action::CreateChild).await?;
 
+                if let params::ImageSource::Url { .. } = params.source {
+                    return self
+                        .image_import_from_url(
+                            opctx,
+                            authz_silo,
+                            authz_project,
+                            params,
+                        )
+                        .await;
+                }
+
                 sagas::image_create::ImageType::Project {
                     authz_silo,
                     authz_project,
@@ -101,6 +112,13 @@ impl super::Nexus {
                 let (.., authz_silo) =
                     silo.lookup_for(authz::Action::CreateChild).await?;
 
+                if let params::ImageSource::Url { .. } = params.source {
+                    return Err(Error::invalid_request(
+                        "images can only be imported into a project; import \
+                         the image into a project and then promote it",
+                    ));
+                }
+
                 sagas::image_create::ImageType::Silo { authz_silo }
             }
         };
@@ -109,6 +127,8 @@ impl super::Nexus {
             serialized_authn: authn::saga::Serialized::for_opctx(opctx),
             image_type,
             create_params: params.clone(),
+            url: None,
+            digest: None,
         };
 
         let saga_outputs = self
@@ -0,0 +1,816 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Creating images from raw or qcow2 image files
+//!
+//! Image files are either downloaded from a URL by Nexus or uploaded to it,
+//! and are streamed into an intermediate disk by the image import saga
+//! without being staged anywhere.
+
+use super::MIN_DISK_SIZE_BYTES;
+use super::sagas;
+use super::sagas::image_import::ImportSource;
+use bytes::Bytes;
+use dropshot::HttpError;
+use futures::Stream;
+use futures::StreamExt;
+use futures::stream::BoxStream;
+use nexus_db_queries::authn;
+use nexus_db_queries::authz;
+use nexus_db_queries::context::OpContext;
+use nexus_db_queries::db;
+use nexus_db_queries::db::identity::Resource;
+use nexus_types::external_api::params;
+use omicron_common::api::external::ByteCount;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::InternalContext;
+use omicron_common::api::external::Name;
+use sha2::Digest;
+use sha2::Sha256;
+use slog_error_chain::InlineErrorChain;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+mod qcow2;
+
+use qcow2::Qcow2Decoder;
+use qcow2::Qcow2Header;
+
+/// Largest write issued to the Pantry while importing an image
+const IMPORT_CHUNK_SIZE: usize = 512 * 1024;
+
+/// How long to wait to connect to the server an image is downloaded from
+const DOWNLOAD_CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long a download may go without receiving data before it's abandoned
+const DOWNLOAD_READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many redirects a download may follow
+const DOWNLOAD_MAX_REDIRECTS: usize = 10;
+
+/// Image files opened by requests to this Nexus, waiting to be read by their
+/// import sagas
+///
+/// Files are opened before the saga starts, to find out how big a disk they
+/// need. Uploaded files can't be opened a second time, so the saga picks them
+/// up from here.
+#[derive(Default)]
+pub(crate) struct ImageImportFiles {
+    files: std::sync::Mutex<BTreeMap<Uuid, ImageFile>>,
+}
+
+impl ImageImportFiles {
+    fn insert(&self, import_id: Uuid, file: ImageFile) {
+        self.files.lock().unwrap().insert(import_id, file);
+    }
+
+    fn take(&self, import_id: Uuid) -> Option<ImageFile> {
+        self.files.lock().unwrap().remove(&import_id)
+    }
+}
+
+/// Where the bytes of an image file come from
+enum Body {
+    Download(reqwest::Response),
+    Upload(BoxStream<'static, Result<Bytes, HttpError>>),
+}
+
+impl Body {
+    async fn next_chunk(&mut self) -> Result<Option<Bytes>, Error> {
+        match self {
+            Body::Download(response) => response.chunk().await.map_err(|e| {
+                Error::invalid_request(format!(
+                    "failed to download image: {}",
+                    InlineErrorChain::new(&e)
+                ))
+            }),
+            Body::Upload(stream) => {
+                stream.next().await.transpose().map_err(|e| {
+                    Error::invalid_request(format!(
+                        "failed to receive image: {}",
+                        e.external_message
+                    ))
+                })
+            }
+        }
+    }
+}
+
+/// Turns the bytes of an image file into the contents of the disk it holds
+enum Decoder {
+    Raw { size: u64, offset: u64 },
+    Qcow2(Qcow2Decoder),
+}
+
+impl Decoder {
+    fn virtual_size(&self) -> u64 {
+        match self {
+            Decoder::Raw { size, .. } => *size,
+            Decoder::Qcow2(decoder) => decoder.virtual_size(),
+        }
+    }
+
+    /// Returns the contents of the disk completed by the next bytes of the
+    /// file, as pairs of offset and data
+    fn feed(&mut self, data: Bytes) -> Result<Vec<(u64, Bytes)>, Error> {
+        match self {
+            Decoder::Raw { size, offset } => {
+                let start = *offset;
+                *offset += data.len() as u64;
+                if *offset > *size {
+                    return Err(Error::invalid_request(format!(
+                        "image file is larger than its declared size of \
+                         {size} bytes"
+                    )));
+                }
+                Ok(vec![(start, data)])
+            }
+            Decoder::Qcow2(decoder) => decoder.feed(&data),
+        }
+    }
+
+    fn finish(&mut self) -> Result<Vec<(u64, Bytes)>, Error> {
+        match self {
+            Decoder::Raw { size, offset } => {
+                if offset != size {
+                    return Err(Error::invalid_request(format!(
+                        "image file ended after {offset} bytes, but its size \
+                         is {size} bytes"
+                    )));
+                }
+                Ok(Vec::new())
+            }
+            Decoder::Qcow2(decoder) => decoder.finish(),
+        }
+    }
+}
+
+/// An image file being imported
+pub(crate) struct ImageFile {
+    body: Body,
+    /// Bytes read from `body` while opening the file, which haven't been
+    /// decoded yet
+    peeked: Option<Bytes>,
+    decoder: Decoder,
+}
+
+impl ImageFile {
+    /// Starts downloading an image file from `url`
+    ///
+    /// Unless `allow_internal` is set, the download is refused if the URL, or
+    /// any URL it redirects to, resolves to an address that isn't globally
+    /// routable: those belong to the control plane or the rack's networks,
+    /// which users must not be able to reach through Nexus.
+    async fn download(
+        url: &str,
+        format: params::ImageImportFormat,
+        block_size: u64,
+        allow_internal: bool,
+    ) -> Result<Self, Error> {
+        let mut url = reqwest::Url::parse(url).map_err(|e| {
+            Error::invalid_value("url", format!("invalid URL: {e}"))
+        })?;
+
+        // Redirects are followed here rather than by reqwest, so that each
+        // hop is checked before it's requested.
+        let mut redirects = 0;
+        let response = loop {
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(Error::invalid_value(
+                    "url",
+                    "images can only be imported from http or https URLs",
+                ));
+            }
+
+            // Downloads can take much longer than the requests made with
+            // Nexus's usual client, so this one has no overall timeout. It's
+            // pinned to the addresses checked here, so that the host can't
+            // resolve to something else by the time it's connected to.
+            let mut builder = reqwest::ClientBuilder::new()
+                .connect_timeout(DOWNLOAD_CONNECT_TIMEOUT)
+                .read_timeout(DOWNLOAD_READ_TIMEOUT)
+                .redirect(reqwest::redirect::Policy::none());
+            let addrs = resolve_download_host(&url).await?;
+            if let Some(addr) = addrs
+                .iter()
+                .find(|addr| !allow_internal && !is_global_addr(addr.ip()))
+            {
+                return Err(Error::invalid_value(
+                    "url",
+                    format!(
+                        "images can't be imported from {}, which resolves \
+                         to the non-global address {}",
+                        url.host_str().unwrap_or_default(),
+                        addr.ip(),
+                    ),
+                ));
+            }
+            if let Some(domain) = url.domain() {
+                builder = builder.resolve_to_addrs(domain, &addrs);
+            }
+            let client = builder.build().map_err(|e| {
+                Error::internal_error(&format!(
+                    "failed to build HTTP client: {}",
+                    InlineErrorChain::new(&e)
+                ))
+            })?;
+
+            let response = client
+                .get(url.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| {
+                    Error::invalid_request(format!(
+                        "failed to download image: {}",
+                        InlineErrorChain::new(&e)
+                    ))
+                })?;
+            if !response.status().is_redirection() {
+                break response;
+            }
+
+            redirects += 1;
+            if redirects > DOWNLOAD_MAX_REDIRECTS {
+                return Err(Error::invalid_request(format!(
+                    "failed to download image: more than \
+                     {DOWNLOAD_MAX_REDIRECTS} redirects"
+                )));
+            }
+            url = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok())
+                .ok_or_else(|| {
+                    Error::invalid_request(format!(
+                        "failed to download image: {} redirected without a \
+                         valid location",
+                        url.host_str().unwrap_or_default(),
+                    ))
+                })?;
+        };
+
+        let content_length = response.content_length();
+        Self::open(Body::Download(response), content_length, format, block_size)
+            .await
+    }
+
+    /// Starts reading an image file from the body of a request
+    async fn upload(
+        body: impl Stream<Item = Result<Bytes, HttpError>> + Send + 'static,
+        content_length: Option<u64>,
+        format: params::ImageImportFormat,
+        block_size: u64,
+    ) -> Result<Self, Error> {
+        Self::open(
+            Body::Upload(body.boxed()),
+            content_length,
+            format,
+            block_size,
+        )
+        .await
+    }
+
+    /// Reads enough of the file to find the size of the disk it holds
+    async fn open(
+        mut body: Body,
+        content_length: Option<u64>,
+        format: params::ImageImportFormat,
+        block_size: u64,
+    ) -> Result<Self, Error> {
+        let (decoder, peeked) = match format {
+            params::ImageImportFormat::Raw => {
+                let size = content_length.ok_or_else(|| {
+                    Error::invalid_request(
+                        "the size of a raw image file must be known before \
+                         it is imported",
+                    )
+                })?;
+                if size == 0 {
+                    return Err(Error::invalid_request("image file is empty"));
+                }
+                (Decoder::Raw { size, offset: 0 }, None)
+            }
+
+            params::ImageImportFormat::Qcow2 => {
+                let mut peeked = Vec::new();
+                while peeked.len() < qcow2::HEADER_PROBE_SIZE {
+                    match body.next_chunk().await? {
+                        Some(chunk) => peeked.extend_from_slice(&chunk),
+                        None => break,
+                    }
+                }
+
+                let header = Qcow2Header::parse(&peeked)?;
+                if header.cluster_size() < block_size {
+                    return Err(Error::invalid_request(format!(
+                        "qcow2 cluster size {} is smaller than the block size \
+                         {block_size}",
+                        header.cluster_size(),
+                    )));
+                }
+                (Decoder::Qcow2(Qcow2Decoder::new(header)), Some(peeked.into()))
+            }
+        };
+
+        Ok(Self { body, peeked, decoder })
+    }
+
+    /// Size of the disk held by the file, in bytes
+    fn virtual_size(&self) -> u64 {
+        self.decoder.virtual_size()
+    }
+
+    /// Reads the rest of the file, writing the disk it holds with `writer`,
+    /// and returns the SHA-256 digest of the file as a hex string
+    async fn copy_to(
+        mut self,
+        writer: &mut DiskWriter,
+    ) -> Result<String, Error> {
+        let mut hasher = Sha256::new();
+
+        let mut next = self.peeked.take();
+        loop {
+            let chunk = match next.take() {
+                Some(chunk) => chunk,
+                None => match self.body.next_chunk().await? {
+                    Some(chunk) => chunk,
+                    None => break,
+                },
+            };
+            hasher.update(&chunk);
+            for (offset, data) in self.decoder.feed(chunk)? {
+                writer.write(offset, &data).await?;
+            }
+        }
+
+        for (offset, data) in self.decoder.finish()? {
+            writer.write(offset, &data).await?;
+        }
+        writer.flush().await?;
+
+        Ok(hex::encode(hasher.finalize()))
+    }
+}
+
+/// Writes the contents of an image to a disk attached to a Pantry
+///
+/// Contiguous data is gathered into writes of up to [`IMPORT_CHUNK_SIZE`],
+/// padded out to whole blocks. The disk is new, so writes of nothing but
+/// zeroes are skipped.
+struct DiskWriter {
+    client: crucible_pantry_client::Client,
+    disk_id: Uuid,
+    block_size: u64,
+    disk_size: u64,
+    /// Offset on the disk of the first byte of `buf`
+    offset: u64,
+    buf: Vec<u8>,
+}
+
+impl DiskWriter {
+    async fn write(
+        &mut self,
+        offset: u64,
+        mut data: &[u8],
+    ) -> Result<(), Error> {
+        if offset != self.offset + self.buf.len() as u64 {
+            self.flush().await?;
+            self.offset = offset;
+        }
+
+        while !data.is_empty() {
+            let n = (IMPORT_CHUNK_SIZE - self.buf.len()).min(data.len());
+            self.buf.extend_from_slice(&data[..n]);
+            data = &data[n..];
+            if self.buf.len() == IMPORT_CHUNK_SIZE {
+                self.flush().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes out whatever has been gathered so far
+    async fn flush(&mut self) -> Result<(), Error> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+
+        let len = (self.buf.len() as u64).next_multiple_of(self.block_size);
+        if self.offset % self.block_size != 0 {
+            return Err(Error::internal_error(&format!(
+                "image import write at offset {} is not block aligned",
+                self.offset
+            )));
+        }
+        if self.offset + len > self.disk_size {
+            return Err(Error::invalid_request(
+                "image file holds more data than its declared size",
+            ));
+        }
+
+        if self.buf.iter().any(|b| *b != 0) {
+            self.buf.resize(len as usize, 0);
+            let request = crucible_pantry_client::types::BulkWriteRequest {
+                offset: self.offset,
+                base64_encoded_data: base64::Engine::encode(
+                    &base64::engine::general_purpose::STANDARD,
+                    &self.buf,
+                ),
+            };
+            let disk_id = self.disk_id.to_string();
+            if let Err(e) = self.client.bulk_write(&disk_id, &request).await {
+                return Err(Error::internal_error(&format!(
+                    "pantry bulk write failed: {}",
+                    InlineErrorChain::new(&e)
+                )));
+            }
+        }
+
+        self.offset += len;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+/// Returns the addresses the host of a download URL resolves to
+async fn resolve_download_host(
+    url: &reqwest::Url,
+) -> Result<Vec<SocketAddr>, Error> {
+    let port = url.port_or_known_default().unwrap_or(0);
+    let host = url
+        .host_str()
+        .ok_or_else(|| Error::invalid_value("url", "URL has no host"))?;
+    let addrs: Vec<SocketAddr> = match url.domain() {
+        Some(domain) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|e| {
+                Error::invalid_request(format!(
+                    "failed to resolve {domain}: {}",
+                    InlineErrorChain::new(&e)
+                ))
+            })?
+            .collect(),
+        // IPv6 literals are enclosed in brackets.
+        None => {
+            let ip: IpAddr = host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse()
+                .map_err(|_| {
+                    Error::invalid_value("url", format!("invalid host {host}"))
+                })?;
+            vec![SocketAddr::new(ip, port)]
+        }
+    };
+    if addrs.is_empty() {
+        return Err(Error::invalid_request(format!(
+            "failed to resolve {host}: no addresses"
+        )));
+    }
+    Ok(addrs)
+}
+
+/// Returns whether `ip` is a globally routable unicast address
+///
+/// This is a conservative stand-in for the unstable `IpAddr::is_global`.
+fn is_global_addr(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // 0.0.0.0/8, "this network"
+                || a == 0
+                // 100.64.0.0/10, shared address space
+                || (a == 100 && (b & 0xc0) == 64)
+                // 192.0.0.0/24, protocol assignments
+                || (a == 192 && b == 0 && c == 0)
+                // 198.18.0.0/15, benchmarking
+                || (a == 198 && (b & 0xfe) == 18)
+                // 240.0.0.0/4, reserved
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_global_addr(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // fc00::/7, unique local (including the underlay)
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10, link-local
+                || (first & 0xffc0) == 0xfe80
+                // 2001:db8::/32, documentation
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8)
+                // 64:ff9b:1::/48, local-use NAT64
+                || (first == 0x64
+                    && ip.segments()[1] == 0xff9b
+                    && ip.segments()[2] == 1))
+        }
+    }
+}
+
+/// What to import, and the image to create from it
+struct ImageImport {
+    source: ImportSource,
+    format: params::ImageImportFormat,
+    block_size: params::BlockSize,
+    expected_digest: Option<params::ExpectedDigest>,
+    identity: IdentityMetadataCreateParams,
+    os: String,
+    version: String,
+}
+
+impl super::Nexus {
+    /// Creates a project image from a file downloaded from a URL
+    pub(super) async fn image_import_from_url(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        authz_silo: authz::Silo,
+        authz_project: authz::Project,
+        params: &params::ImageCreate,
+    ) -> CreateResult<db::model::Image> {
+        let params::ImageSource::Url {
+            url,
+            format,
+            block_size,
+            expected_digest,
+        } = &params.source
+        else {
+            return Err(Error::internal_error("image source is not a URL"));
+        };
+
+        let file = ImageFile::download(
+            url,
+            *format,
+            u64::from(*block_size),
+            self.tunables.image_import_allow_internal_urls,
+        )
+        .await?;
+
+        let import = ImageImport {
+            source: ImportSource::Url { url: url.clone() },
+            format: *format,
+            block_size: *block_size,
+            expected_digest: expected_digest.clone(),
+            identity: params.identity.clone(),
+            os: params.os.clone(),
+            version: params.version.clone(),
+        };
+        self.image_import(opctx, authz_silo, authz_project, file, import).await
+    }
+
+    /// Creates a project image from a file uploaded in the body of a request
+    pub(crate) async fn image_upload(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        name: Name,
+        upload: params::ImageUpload,
+        content_length: Option<u64>,
+        body: impl Stream<Item = Result<Bytes, HttpError>> + Send + 'static,
+    ) -> CreateResult<db::model::Image> {
+        let project_lookup = self.project_lookup(
+            opctx,
+            params::ProjectSelector { project: upload.project },
+        )?;
+        let (authz_silo, authz_project) =
+            project_lookup.lookup_for(authz::Action::CreateChild).await?;
+
+        let file = ImageFile::upload(
+            body,
+            content_length,
+            upload.format,
+            u64::from(upload.block_size),
+        )
+        .await?;
+
+        let import = ImageImport {
+            source: ImportSource::Upload,
+            format: upload.format,
+            block_size: upload.block_size,
+            expected_digest: upload.sha256.map(params::ExpectedDigest::Sha256),
+            identity: IdentityMetadataCreateParams {
+                name,
+                description: upload.description,
+            },
+            os: upload.os,
+            version: upload.version,
+        };
+        self.image_import(opctx, authz_silo, authz_project, file, import).await
+    }
+
+    async fn image_import(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        authz_silo: authz::Silo,
+        authz_project: authz::Project,
+        file: ImageFile,
+        import: ImageImport,
+    ) -> CreateResult<db::model::Image> {
+        // The intermediate disk is rounded up to a size disks can have.
+        let disk_size = file
+            .virtual_size()
+            .next_multiple_of(u64::from(MIN_DISK_SIZE_BYTES));
+        let disk_size = ByteCount::try_from(disk_size).map_err(|e| {
+            Error::invalid_request(format!("image is too large: {e}"))
+        })?;
+        Self::validate_disk_size(disk_size, u64::from(import.block_size))?;
+
+        let import_id = Uuid::new_v4();
+        let saga_params = sagas::image_import::Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+            authz_silo,
+            authz_project,
+            import_id,
+            source: import.source,
+            format: import.format,
+            block_size: import.block_size,
+            disk_size,
+            expected_digest: import.expected_digest,
+            identity: import.identity,
+            os: import.os,
+            version: import.version,
+        };
+
+        self.image_import_files.insert(import_id, file);
+        let result = self
+            .sagas
+            .saga_execute::<sagas::image_import::SagaImageImport>(saga_params)
+            .await;
+
+        // The saga takes the file when it starts copying it, but if the saga
+        // failed before that, the file is still here.
+        self.image_import_files.take(import_id);
+
+        let created_image = result?
+            .lookup_node_output::<db::model::Image>("created_image")
+            .map_err(|e| Error::internal_error(&format!("{:#}", &e)))
+            .internal_context("looking up output from image import saga")?;
+
+        Ok(created_image)
+    }
+
+    /// Copies an image file to the intermediate disk of an import, and
+    /// returns the SHA-256 digest of the file as a hex string
+    ///
+    /// This is called by the image import saga.
+    pub(crate) async fn image_import_copy(
+        &self,
+        import_id: Uuid,
+        source: &ImportSource,
+        format: params::ImageImportFormat,
+        block_size: params::BlockSize,
+        disk: &db::model::Disk,
+    ) -> Result<String, Error> {
+        let block_size = u64::from(block_size);
+
+        let file = match self.image_import_files.take(import_id) {
+            Some(file) => file,
+            None => match source {
+                ImportSource::Url { url } => {
+                    ImageFile::download(
+                        url,
+                        format,
+                        block_size,
+                        self.tunables.image_import_allow_internal_urls,
+                    )
+                    .await?
+                }
+                ImportSource::Upload => {
+                    return Err(Error::unavail(
+                        "uploaded image file is no longer available",
+                    ));
+                }
+            },
+        };
+
+        let pantry_address = disk.pantry_address().ok_or_else(|| {
+            Error::internal_error(&format!(
+                "disk {} is not attached to a pantry",
+                disk.id()
+            ))
+        })?;
+
+        info!(
+            self.log,
+            "importing image to disk {} using pantry at {}",
+            disk.id(),
+            pantry_address;
+            "import_id" => %import_id,
+        );
+
+        let mut writer = DiskWriter {
+            client: crucible_pantry_client::Client::new_with_client(
+                &format!("http://{}", pantry_address),
+                self.reqwest_client.clone(),
+            ),
+            disk_id: disk.id(),
+            block_size,
+            disk_size: disk.size.to_bytes(),
+            offset: 0,
+            buf: Vec::new(),
+        };
+        file.copy_to(&mut writer).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use httptest::Expectation;
+    use httptest::matchers::request;
+    use httptest::responders::status_code;
+
+    #[test]
+    fn test_is_global_addr() {
+        for ip in ["1.1.1.1", "93.184.216.34", "2606:4700::1111"] {
+            assert!(is_global_addr(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fd00:1122:3344:101::1",
+            "fe80::1",
+            "ff02::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_global_addr(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_refuses_internal_urls() {
+        for url in [
+            "http://127.0.0.1:1/image.raw",
+            "http://[::1]:1/image.raw",
+            "http://localhost:1/image.raw",
+            "http://169.254.169.254/image.raw",
+        ] {
+            let Err(error) = ImageFile::download(
+                url,
+                params::ImageImportFormat::Raw,
+                512,
+                false,
+            )
+            .await
+            else {
+                panic!("download from {url} was allowed");
+            };
+            assert!(
+                error.to_string().contains("non-global address"),
+                "{url}: {error}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_follows_redirects() {
+        let server = httptest::Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/old.raw"))
+                .respond_with(
+                    status_code(302).append_header("Location", "/new.raw"),
+                ),
+        );
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/new.raw"))
+                .respond_with(status_code(200).body(vec![1; 4096])),
+        );
+
+        let file = ImageFile::download(
+            &server.url_str("/old.raw"),
+            params::ImageImportFormat::Raw,
+            512,
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(file.virtual_size(), 4096);
+    }
+}
@@ -0,0 +1,667 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Streaming decoder for imported qcow2 images
+//!
+//! Image files are read once, front to back, as they arrive, without being
+//! staged anywhere. That works for qcow2 as long as every table comes before
+//! the clusters it points to, which is how `qemu-img` lays out the images it
+//! writes: the L1 table follows the header, and each L2 table is allocated
+//! just before the first data cluster it maps.
+//!
+//! The decoder keeps track of the parts of the file it still needs (tables it
+//! has learned about and the data clusters they point to) and picks them out
+//! of the stream as they go by. Everything else, like the refcount tables, is
+//! skipped. If a table points back at a part of the file that has already
+//! gone by, the image can't be imported this way, and has to be rewritten
+//! with `qemu-img convert` first.
+
+use bytes::Bytes;
+use omicron_common::api::external::Error;
+use std::collections::BTreeMap;
+
+const QCOW_MAGIC: u32 = 0x5146_49fb;
+
+/// Size of a version 2 header
+const V2_HEADER_SIZE: usize = 72;
+
+/// Size of the fixed part of a version 3 header, which is enough to parse
+/// either version
+pub(super) const HEADER_PROBE_SIZE: usize = 104;
+
+/// Smallest and largest cluster sizes `qemu-img` supports, as log2
+const MIN_CLUSTER_BITS: u32 = 9;
+const MAX_CLUSTER_BITS: u32 = 21;
+
+/// Largest L1 table `qemu-img` supports
+const MAX_L1_SIZE_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Bits of an L1 or L2 table entry holding the offset of a cluster
+const OFFSET_MASK: u64 = 0x00ff_ffff_ffff_fe00;
+
+/// Set on L2 entries whose cluster is compressed
+const QCOW_OFLAG_COMPRESSED: u64 = 1 << 62;
+
+/// Set on (version 3) L2 entries whose cluster reads as zeroes
+const QCOW_OFLAG_ZERO: u64 = 1;
+
+/// Size of the sectors in which compressed cluster sizes are counted
+const COMPRESSED_SECTOR_SIZE: u64 = 512;
+
+/// Incompatible feature bit marking an image whose refcounts may be stale,
+/// which is the only incompatible feature that doesn't affect reading it
+const INCOMPAT_DIRTY: u64 = 1 << 0;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Qcow2Header {
+    /// Size of the virtual disk, in bytes
+    pub virtual_size: u64,
+    cluster_bits: u32,
+    l1_size: u64,
+    l1_table_offset: u64,
+}
+
+impl Qcow2Header {
+    /// Parses the header at the start of `buf`, rejecting images that use
+    /// features the decoder doesn't support
+    pub fn parse(buf: &[u8]) -> Result<Self, Error> {
+        if buf.len() < V2_HEADER_SIZE || read_u32(buf, 0) != QCOW_MAGIC {
+            return Err(invalid("image is not in qcow2 format"));
+        }
+
+        let version = read_u32(buf, 4);
+        match version {
+            2 => {}
+            3 => {
+                if buf.len() < HEADER_PROBE_SIZE {
+                    return Err(invalid("qcow2 header is truncated"));
+                }
+                let unsupported = read_u64(buf, 72) & !INCOMPAT_DIRTY;
+                if unsupported != 0 {
+                    return Err(invalid(format!(
+                        "qcow2 image uses unsupported incompatible features \
+                         ({unsupported:#x})"
+                    )));
+                }
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "qcow2 version {version} is not supported"
+                )));
+            }
+        }
+
+        if read_u64(buf, 8) != 0 {
+            return Err(invalid(
+                "qcow2 images with a backing file are not supported",
+            ));
+        }
+
+        let cluster_bits = read_u32(buf, 20);
+        if !(MIN_CLUSTER_BITS..=MAX_CLUSTER_BITS).contains(&cluster_bits) {
+            return Err(invalid(format!(
+                "qcow2 cluster size 2^{cluster_bits} is not supported"
+            )));
+        }
+
+        if read_u32(buf, 32) != 0 {
+            return Err(invalid("encrypted qcow2 images are not supported"));
+        }
+
+        let header = Self {
+            virtual_size: read_u64(buf, 24),
+            cluster_bits,
+            l1_size: u64::from(read_u32(buf, 36)),
+            l1_table_offset: read_u64(buf, 40),
+        };
+
+        if header.virtual_size == 0 {
+            return Err(invalid("qcow2 image has a virtual size of zero"));
+        }
+        if header.l1_size * 8 > MAX_L1_SIZE_BYTES
+            || header.l1_size * header.l2_coverage() < header.virtual_size
+        {
+            return Err(invalid("qcow2 L1 table size is invalid"));
+        }
+        if header.l1_table_offset % header.cluster_size() != 0 {
+            return Err(invalid("qcow2 L1 table is not cluster aligned"));
+        }
+
+        Ok(header)
+    }
+
+    pub fn cluster_size(&self) -> u64 {
+        1 << self.cluster_bits
+    }
+
+    /// Number of bytes of the virtual disk mapped by one L2 table
+    fn l2_coverage(&self) -> u64 {
+        self.cluster_size() * (self.cluster_size() / 8)
+    }
+}
+
+/// A part of the image file the decoder is waiting for
+#[derive(Debug)]
+enum Region {
+    L1Table,
+    /// An L2 table mapping the virtual disk starting at `guest_offset`
+    L2Table {
+        guest_offset: u64,
+    },
+    /// A data cluster holding the virtual disk at `guest_offset`
+    Data {
+        guest_offset: u64,
+    },
+    /// A deflate-compressed data cluster
+    Compressed {
+        guest_offset: u64,
+    },
+}
+
+pub(super) struct Qcow2Decoder {
+    header: Qcow2Header,
+    /// Regions still to come, keyed by their offset in the file (and, since
+    /// compressed clusters can share a sector, the order they were found in)
+    pending: BTreeMap<(u64, u64), (u64, Region)>,
+    next_seq: u64,
+    /// Offset in the file of the first byte of `buf`
+    buf_start: u64,
+    /// Bytes of the file that pending regions may still need
+    buf: Vec<u8>,
+}
+
+impl Qcow2Decoder {
+    pub fn new(header: Qcow2Header) -> Self {
+        let mut decoder = Self {
+            header,
+            pending: BTreeMap::new(),
+            next_seq: 0,
+            buf_start: 0,
+            buf: Vec::new(),
+        };
+        decoder.add_region(
+            decoder.header.l1_table_offset,
+            decoder.header.l1_size * 8,
+            Region::L1Table,
+        );
+        decoder
+    }
+
+    /// Size of the virtual disk, in bytes
+    pub fn virtual_size(&self) -> u64 {
+        self.header.virtual_size
+    }
+
+    /// Feeds the next bytes of the file to the decoder, returning the
+    /// contents of the virtual disk that they complete, as pairs of offset
+    /// and data
+    ///
+    /// Data is returned in the order it appears in the file, which isn't
+    /// necessarily the order of the virtual disk. Parts of the virtual disk
+    /// that are never returned read as zeroes.
+    pub fn feed(&mut self, data: &[u8]) -> Result<Vec<(u64, Bytes)>, Error> {
+        self.buf.extend_from_slice(data);
+        let mut out = Vec::new();
+        self.process(false, &mut out)?;
+        Ok(out)
+    }
+
+    /// Called at the end of the file, returning any remaining contents of the
+    /// virtual disk
+    pub fn finish(&mut self) -> Result<Vec<(u64, Bytes)>, Error> {
+        let mut out = Vec::new();
+        self.process(true, &mut out)?;
+        Ok(out)
+    }
+
+    fn buf_end(&self) -> u64 {
+        self.buf_start + self.buf.len() as u64
+    }
+
+    /// Drops buffered bytes before `offset`
+    fn discard_to(&mut self, offset: u64) {
+        let n = (offset - self.buf_start) as usize;
+        self.buf.drain(..n);
+        self.buf_start = offset;
+    }
+
+    fn add_region(&mut self, offset: u64, len: u64, region: Region) {
+        self.pending.insert((offset, self.next_seq), (len, region));
+        self.next_seq += 1;
+    }
+
+    fn process(
+        &mut self,
+        at_eof: bool,
+        out: &mut Vec<(u64, Bytes)>,
+    ) -> Result<(), Error> {
+        loop {
+            let Some((&key, (len, region))) = self.pending.first_key_value()
+            else {
+                self.discard_to(self.buf_end());
+                return Ok(());
+            };
+            let (start, _) = key;
+            let len = *len;
+            let compressed = matches!(region, Region::Compressed { .. });
+
+            if start < self.buf_start {
+                return Err(invalid(format!(
+                    "qcow2 image refers to offset {start} after that part of \
+                     the file has been read; rewrite the image with \
+                     `qemu-img convert` so that its tables come first"
+                )));
+            }
+
+            self.discard_to(start.min(self.buf_end()));
+            let available = self.buf_end().saturating_sub(start);
+            if available < len {
+                // The compressed size of the last cluster in a file may be
+                // rounded up past its end.
+                let partial_ok = at_eof && available > 0 && compressed;
+                if !partial_ok {
+                    if at_eof {
+                        return Err(invalid(format!(
+                            "qcow2 image is truncated: expected data at \
+                             offset {start}"
+                        )));
+                    }
+                    return Ok(());
+                }
+            }
+
+            let n = len.min(available) as usize;
+            let (_, region) = self.pending.remove(&key).unwrap();
+            let bytes = Bytes::copy_from_slice(&self.buf[..n]);
+            self.handle(region, bytes, out)?;
+        }
+    }
+
+    fn handle(
+        &mut self,
+        region: Region,
+        bytes: Bytes,
+        out: &mut Vec<(u64, Bytes)>,
+    ) -> Result<(), Error> {
+        let cluster_size = self.header.cluster_size();
+        let virtual_size = self.header.virtual_size;
+
+        match region {
+            Region::L1Table => {
+                let l2_coverage = self.header.l2_coverage();
+                for (i, entry) in table_entries(&bytes).enumerate() {
+                    let guest_offset = i as u64 * l2_coverage;
+                    let l2_offset = entry & OFFSET_MASK;
+                    if l2_offset == 0 || guest_offset >= virtual_size {
+                        continue;
+                    }
+                    if l2_offset % cluster_size != 0 {
+                        return Err(invalid(
+                            "qcow2 L2 table is not cluster aligned",
+                        ));
+                    }
+                    self.add_region(
+                        l2_offset,
+                        cluster_size,
+                        Region::L2Table { guest_offset },
+                    );
+                }
+            }
+
+            Region::L2Table { guest_offset: base } => {
+                for (i, entry) in table_entries(&bytes).enumerate() {
+                    let guest_offset = base + i as u64 * cluster_size;
+                    if guest_offset >= virtual_size {
+                        break;
+                    }
+
+                    if entry & QCOW_OFLAG_COMPRESSED != 0 {
+                        let (offset, len) = self.compressed_extent(entry);
+                        self.add_region(
+                            offset,
+                            len,
+                            Region::Compressed { guest_offset },
+                        );
+                        continue;
+                    }
+
+                    // Unallocated clusters read as zeroes too, since there's
+                    // no backing file.
+                    let offset = entry & OFFSET_MASK;
+                    if entry & QCOW_OFLAG_ZERO != 0 || offset == 0 {
+                        continue;
+                    }
+                    if offset % cluster_size != 0 {
+                        return Err(invalid(
+                            "qcow2 data cluster is not cluster aligned",
+                        ));
+                    }
+                    self.add_region(
+                        offset,
+                        cluster_size,
+                        Region::Data { guest_offset },
+                    );
+                }
+            }
+
+            Region::Data { guest_offset } => {
+                let n = cluster_size.min(virtual_size - guest_offset);
+                out.push((guest_offset, bytes.slice(..n as usize)));
+            }
+
+            Region::Compressed { guest_offset } => {
+                let cluster = self.inflate(&bytes)?;
+                let n = cluster_size.min(virtual_size - guest_offset);
+                out.push((guest_offset, cluster.slice(..n as usize)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the offset and length in the file of the compressed cluster
+    /// described by an L2 entry
+    fn compressed_extent(&self, entry: u64) -> (u64, u64) {
+        // The low `x` bits hold the offset, and the bits from there up to bit
+        // 61 the number of sectors the data extends past the first.
+        let x = 62 - (self.header.cluster_bits - 8);
+        let offset = entry & ((1 << x) - 1);
+        let extra_sectors = (entry & !(0b11 << 62)) >> x;
+        let len = (extra_sectors + 1) * COMPRESSED_SECTOR_SIZE
+            - offset % COMPRESSED_SECTOR_SIZE;
+        (offset, len)
+    }
+
+    fn inflate(&self, compressed: &[u8]) -> Result<Bytes, Error> {
+        let mut cluster = vec![0; self.header.cluster_size() as usize];
+        let mut decompress = flate2::Decompress::new(false);
+        decompress
+            .decompress(
+                compressed,
+                &mut cluster,
+                flate2::FlushDecompress::Finish,
+            )
+            .map_err(|e| {
+                invalid(format!("failed to decompress qcow2 cluster: {e}"))
+            })?;
+        if decompress.total_out() != cluster.len() as u64 {
+            return Err(invalid("qcow2 compressed cluster is truncated"));
+        }
+        Ok(Bytes::from(cluster))
+    }
+}
+
+fn table_entries(bytes: &[u8]) -> impl Iterator<Item = u64> + '_ {
+    bytes
+        .chunks_exact(8)
+        .map(|entry| u64::from_be_bytes(entry.try_into().unwrap()))
+}
+
+fn read_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(buf: &[u8], at: usize) -> u64 {
+    u64::from_be_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::invalid_request(message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Write;
+
+    /// Images built here use 512-byte clusters, so that one L2 table maps 32
+    /// KiB of the virtual disk.
+    const CLUSTER_BITS: u32 = 9;
+    const CLUSTER: usize = 1 << CLUSTER_BITS;
+
+    /// Builds a qcow2 image one cluster at a time
+    struct ImageBuilder {
+        virtual_size: u64,
+        file: Vec<u8>,
+    }
+
+    impl ImageBuilder {
+        /// Starts an image whose L1 table is the cluster after the header
+        fn new(version: u32, virtual_size: u64, l1_size: u32) -> Self {
+            let mut file = vec![0; 2 * CLUSTER];
+            let mut put = |at: usize, bytes: &[u8]| {
+                file[at..at + bytes.len()].copy_from_slice(bytes);
+            };
+            put(0, &QCOW_MAGIC.to_be_bytes());
+            put(4, &version.to_be_bytes());
+            put(20, &CLUSTER_BITS.to_be_bytes());
+            put(24, &virtual_size.to_be_bytes());
+            put(36, &l1_size.to_be_bytes());
+            put(40, &(CLUSTER as u64).to_be_bytes());
+            if version == 3 {
+                put(96, &4u32.to_be_bytes());
+                put(100, &(HEADER_PROBE_SIZE as u32).to_be_bytes());
+            }
+            Self { virtual_size, file }
+        }
+
+        fn put_u64(&mut self, at: usize, value: u64) {
+            self.file[at..at + 8].copy_from_slice(&value.to_be_bytes());
+        }
+
+        /// Appends a cluster, returning its offset
+        fn push_cluster(&mut self, contents: &[u8]) -> u64 {
+            let offset = self.file.len();
+            self.file.extend_from_slice(contents);
+            self.file.resize(offset + CLUSTER, 0);
+            offset as u64
+        }
+
+        /// Appends an empty L2 table, pointed to by L1 entry `index`
+        fn push_l2(&mut self, index: usize) -> u64 {
+            let offset = self.push_cluster(&[]);
+            self.put_u64(CLUSTER + index * 8, offset | (1 << 63));
+            offset
+        }
+
+        fn set_l2_entry(&mut self, l2: u64, index: usize, entry: u64) {
+            self.put_u64(l2 as usize + index * 8, entry);
+        }
+
+        fn decode(&self, chunk_size: usize) -> Result<Vec<u8>, Error> {
+            let header = Qcow2Header::parse(&self.file)?;
+            assert_eq!(header.virtual_size, self.virtual_size);
+            let mut decoder = Qcow2Decoder::new(header);
+            let mut disk = vec![0; self.virtual_size as usize];
+            let mut write = |writes: Vec<(u64, Bytes)>| {
+                for (offset, data) in writes {
+                    let offset = offset as usize;
+                    disk[offset..offset + data.len()].copy_from_slice(&data);
+                }
+            };
+            for chunk in self.file.chunks(chunk_size) {
+                write(decoder.feed(chunk)?);
+            }
+            write(decoder.finish()?);
+            Ok(disk)
+        }
+    }
+
+    fn pattern(seed: u8, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed))
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_in_any_chunk_size() {
+        for version in [2, 3] {
+            // 40 KiB of virtual disk needs two L2 tables.
+            let mut image = ImageBuilder::new(version, 40 * 1024, 2);
+            let mut expected = vec![0; 40 * 1024];
+
+            let l2 = image.push_l2(0);
+            for (index, seed) in [(0, 1), (5, 2), (63, 3)] {
+                let data = pattern(seed, CLUSTER);
+                let offset = image.push_cluster(&data);
+                image.set_l2_entry(l2, index, offset | (1 << 63));
+                expected[index * CLUSTER..][..CLUSTER].copy_from_slice(&data);
+            }
+
+            // Data clusters don't have to be in the order of the disk.
+            let l2 = image.push_l2(1);
+            for (index, seed) in [(15, 4), (2, 5)] {
+                let data = pattern(seed, CLUSTER);
+                let offset = image.push_cluster(&data);
+                image.set_l2_entry(l2, index, offset);
+                let at = 32 * 1024 + index * CLUSTER;
+                expected[at..][..CLUSTER].copy_from_slice(&data);
+            }
+
+            for chunk_size in [1, 7, 512, 1000, 4096, image.file.len()] {
+                let disk = image.decode(chunk_size).unwrap();
+                assert!(
+                    disk == expected,
+                    "version {version}, chunk size {chunk_size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_zero_and_unallocated_clusters() {
+        let mut image = ImageBuilder::new(3, 4096, 1);
+        let l2 = image.push_l2(0);
+        let offset = image.push_cluster(&pattern(1, CLUSTER));
+        // A zero cluster reads as zeroes even if it has an allocation.
+        image.set_l2_entry(l2, 0, offset | QCOW_OFLAG_ZERO);
+        image.set_l2_entry(l2, 1, QCOW_OFLAG_ZERO);
+
+        assert_eq!(image.decode(100).unwrap(), vec![0; 4096]);
+    }
+
+    #[test]
+    fn test_decode_compressed_clusters() {
+        let mut image = ImageBuilder::new(3, 4096, 1);
+        let l2 = image.push_l2(0);
+        let mut expected = vec![0; 4096];
+
+        // Compressed clusters are packed together, and needn't start on a
+        // sector boundary.
+        let x = 62 - (CLUSTER_BITS - 8);
+        let mut offset = image.file.len() as u64 + 100;
+        image.file.resize(offset as usize, 0);
+        for (index, seed) in [(1, 1), (6, 2)] {
+            let data = pattern(seed, CLUSTER);
+            let mut encoder = flate2::write::DeflateEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            );
+            encoder.write_all(&data).unwrap();
+            let compressed = encoder.finish().unwrap();
+            image.file.extend_from_slice(&compressed);
+
+            let end = offset + compressed.len() as u64;
+            let extra_sectors = (end - 1) / 512 - offset / 512;
+            image.set_l2_entry(
+                l2,
+                index,
+                QCOW_OFLAG_COMPRESSED | (extra_sectors << x) | offset,
+            );
+            expected[index * CLUSTER..][..CLUSTER].copy_from_slice(&data);
+            offset = end;
+        }
+
+        for chunk_size in [1, 512, image.file.len()] {
+            assert_eq!(image.decode(chunk_size).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_clips_to_virtual_size() {
+        let mut image = ImageBuilder::new(2, 1000, 1);
+        let l2 = image.push_l2(0);
+        let data = pattern(1, CLUSTER);
+        let offset = image.push_cluster(&data);
+        image.set_l2_entry(l2, 1, offset);
+        // This entry is past the end of the disk, and is ignored.
+        image.set_l2_entry(l2, 2, offset);
+
+        let disk = image.decode(512).unwrap();
+        assert_eq!(disk.len(), 1000);
+        assert_eq!(&disk[CLUSTER..], &data[..1000 - CLUSTER]);
+    }
+
+    #[test]
+    fn test_reject_tables_after_data() {
+        let mut image = ImageBuilder::new(2, 4096, 1);
+        let data_offset = image.push_cluster(&pattern(1, CLUSTER));
+        let l2 = image.push_l2(0);
+        image.set_l2_entry(l2, 0, data_offset);
+
+        let error = image.decode(4096).unwrap_err();
+        assert!(
+            error.to_string().contains("qemu-img convert"),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn test_reject_truncated_image() {
+        let mut image = ImageBuilder::new(2, 4096, 1);
+        let l2 = image.push_l2(0);
+        let offset = image.push_cluster(&pattern(1, CLUSTER));
+        image.set_l2_entry(l2, 0, offset);
+        image.file.truncate(image.file.len() - 1);
+
+        let error = image.decode(4096).unwrap_err();
+        assert!(
+            error.to_string().contains("truncated"),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn test_reject_unsupported_headers() {
+        let image = ImageBuilder::new(3, 4096, 1);
+
+        let mut file = image.file.clone();
+        file[0] = 0;
+        assert!(Qcow2Header::parse(&file).is_err());
+
+        // version 4
+        let mut file = image.file.clone();
+        file[7] = 4;
+        assert!(Qcow2Header::parse(&file).is_err());
+
+        // backing file
+        let mut file = image.file.clone();
+        file[15] = 0xff;
+        assert!(Qcow2Header::parse(&file).is_err());
+
+        // encryption
+        let mut file = image.file.clone();
+        file[35] = 1;
+        assert!(Qcow2Header::parse(&file).is_err());
+
+        // cluster size too small
+        let mut file = image.file.clone();
+        file[23] = 8;
+        assert!(Qcow2Header::parse(&file).is_err());
+
+        // L1 table too small for the disk
+        let mut file = image.file.clone();
+        file[25] = 1;
+        assert!(Qcow2Header::parse(&file).is_err());
+
+        // Only the dirty bit is allowed among the incompatible features.
+        let mut file = image.file.clone();
+        file[79] = 1;
+        assert!(Qcow2Header::parse(&file).is_ok());
+        file[79] = 1 << 3;
+        assert!(Qcow2Header::parse(&file).is_err());
+
+        assert!(Qcow2Header::parse(&image.file[..80]).is_err());
+    }
+}
@@ -610,16 +610,35 @@ impl super::Nexus {
         Ok(())
     }
 
+    /// Starts live-migrating a running instance to another sled.
+    ///
+    /// The destination sled is chosen by the caller, which requires
+    /// fleet-level privileges, or else by the sled allocator. Returns once the
+    /// migration has started; its progress is reported by
+    /// [`Self::instance_migration_list`].
     pub(crate) async fn instance_migrate(
         self: &Arc<Self>,
         opctx: &OpContext,
-        id: InstanceUuid,
-        params: nexus_types::internal_api::params::InstanceMigrateRequest,
+        instance_lookup: &lookup::Instance<'_>,
+        params: params::InstanceMigrate,
     ) -> UpdateResult<InstanceAndActiveVmm> {
-        let (.., authz_instance) = LookupPath::new(&opctx, &self.db_datastore)
-            .instance_id(id.into_untyped_uuid())
-            .lookup_for(authz::Action::Modify)
-            .await?;
+        let (.., authz_instance) =
+            instance_lookup.lookup_for(authz::Action::Modify).await?;
+
+        // Where an instance runs is otherwise hidden from its users, so only
+        // fleet operators can pick the destination.
+        let dst_sled_id = match params.dst_sled_id {
+            Some(sled_id) => {
+                opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+                let (.., authz_sled) =
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .sled_id(sled_id)
+                        .lookup_for(authz::Action::Read)
+                        .await?;
+                Some(SledUuid::from_untyped_uuid(authz_sled.id()))
+            }
+            None => None,
+        };
 
         let state = self
             .db_datastore
@@ -636,10 +655,12 @@ impl super::Nexus {
         }
 
         let vmm = vmm.as_ref().unwrap();
-        if vmm.sled_id == params.dst_sled_id {
-            return Err(Error::invalid_request(
-                "instance is already running on destination sled",
-            ));
+        if let Some(dst_sled_id) = dst_sled_id {
+            if vmm.sled_id == dst_sled_id.into_untyped_uuid() {
+                return Err(Error::invalid_request(
+                    "instance is already running on destination sled",
+                ));
+            }
         }
 
         if instance.runtime().migration_id.is_some() {
@@ -647,17 +668,40 @@ impl super::Nexus {
         }
 
         // Kick off the migration saga
+        let migration_id = Uuid::new_v4();
         let saga_params = sagas::instance_migrate::Params {
             serialized_authn: authn::saga::Serialized::for_opctx(opctx),
             instance: instance.clone(),
             src_vmm: vmm.clone(),
-            migrate_params: params,
+            migration_id,
+            dst_sled_id,
         };
-        self.sagas
+        if let Err(error) = self
+            .sagas
             .saga_execute::<sagas::instance_migrate::SagaInstanceMigrate>(
                 saga_params,
             )
-            .await?;
+            .await
+        {
+            // If the saga got far enough to create the migration record, it
+            // has marked it failed; say why, so that users listing the
+            // instance's migrations can see what happened.
+            let reason =
+                dropshot::HttpError::from(error.clone()).external_message;
+            if let Err(e) = self
+                .db_datastore
+                .migration_set_failure_reason(opctx, migration_id, &reason)
+                .await
+            {
+                warn!(
+                    opctx.log,
+                    "failed to record why migration failed";
+                    "migration_id" => %migration_id,
+                    "error" => ?e,
+                );
+            }
+            return Err(error);
+        }
 
         self.background_tasks.task_vpc_route_manager.activate();
 
@@ -667,6 +711,24 @@ impl super::Nexus {
         self.db_datastore.instance_fetch_with_vmm(opctx, &authz_instance).await
     }
 
+    /// Lists the migrations of an instance between sleds.
+    pub(crate) async fn instance_migration_list(
+        &self,
+        opctx: &OpContext,
+        instance_lookup: &lookup::Instance<'_>,
+        pagparams: &DataPageParams<'_, Uuid>,
+    ) -> ListResultVec<db::model::Migration> {
+        let (.., authz_instance) =
+            instance_lookup.lookup_for(authz::Action::Read).await?;
+        self.db_datastore
+            .instance_list_migrations(
+                opctx,
+                InstanceUuid::from_untyped_uuid(authz_instance.id()),
+                pagparams,
+            )
+            .await
+    }
+
     /// Reboot the specified instance.
     pub(crate) async fn instance_reboot(
         &self,
@@ -2220,7 +2282,7 @@ pub(crate) async fn process_vmm_update(
 
 /// Determines whether the supplied instance sizes (CPU count and memory size)
 /// are acceptable.
-fn check_instance_cpu_memory_sizes(
+pub(super) fn check_instance_cpu_memory_sizes(
     ncpus: InstanceCpuCount,
     memory: ByteCount,
 ) -> Result<(), Error> {
@@ -0,0 +1,457 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Instance templates, and creating instances from them in bulk
+
+use super::MAX_EPHEMERAL_IPS_PER_INSTANCE;
+use super::MAX_NICS_PER_INSTANCE;
+use super::instance::check_instance_cpu_memory_sizes;
+use crate::app::sagas::instance_start;
+use nexus_db_lookup::LookupPath;
+use nexus_db_lookup::lookup;
+use nexus_db_queries::authz;
+use nexus_db_queries::context::OpContext;
+use nexus_db_queries::db;
+use nexus_types::external_api::params;
+use nexus_types::external_api::views;
+use nexus_types::identity::Resource;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::Hostname;
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::Name;
+use omicron_common::api::external::NameOrId;
+use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::http_pagination::PaginatedBy;
+use omicron_uuid_kinds::GenericUuid;
+use omicron_uuid_kinds::InstanceUuid;
+use std::sync::Arc;
+
+/// The most instances that may be created from a template in one request
+pub const MAX_INSTANCE_TEMPLATE_INSTANTIATE_COUNT: u32 = 64;
+
+/// The placeholder in `name_pattern` that is replaced with each instance's
+/// index
+const NAME_PATTERN_INDEX: &str = "{n}";
+
+/// The names used for one instance created from a template
+struct InstantiatedNames {
+    instance: Name,
+    hostname: Hostname,
+    boot_disk: Name,
+}
+
+impl InstantiatedNames {
+    fn new(instance: String) -> Result<Self, Error> {
+        let invalid = |message: String| {
+            Error::invalid_value(
+                "name_pattern",
+                format!(
+                    "\"{instance}\" is not a valid instance name: {message}"
+                ),
+            )
+        };
+        Ok(Self {
+            instance: instance.parse().map_err(invalid)?,
+            hostname: instance
+                .parse()
+                .map_err(|e: anyhow::Error| invalid(e.to_string()))?,
+            boot_disk: format!("{instance}-boot").parse().map_err(invalid)?,
+        })
+    }
+}
+
+/// Returns the boot disk to create for an instance created from a template
+fn instance_template_boot_disk(
+    boot_disk: &params::InstanceTemplateBootDisk,
+    disk_name: Name,
+    instance_name: &Name,
+) -> params::DiskCreate {
+    params::DiskCreate {
+        identity: IdentityMetadataCreateParams {
+            name: disk_name,
+            description: format!("boot disk for instance {instance_name}"),
+        },
+        disk_source: boot_disk.disk_source.clone(),
+        size: boot_disk.size,
+    }
+}
+
+impl super::Nexus {
+    /// Fetches an instance template, along with the project it belongs to
+    pub(crate) async fn instance_template_fetch(
+        &self,
+        opctx: &OpContext,
+        selector: params::InstanceTemplateSelector,
+    ) -> LookupResult<(authz::Project, db::model::InstanceTemplate)> {
+        let (authz_project, template) = match selector {
+            params::InstanceTemplateSelector {
+                instance_template: NameOrId::Id(id),
+                project: None,
+            } => {
+                let project_id = self
+                    .db_datastore
+                    .instance_template_project_id(opctx, id)
+                    .await?;
+                // Don't reveal the project to callers that can't see it.
+                let (.., authz_project) =
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .project_id(project_id)
+                        .lookup_for(authz::Action::Read)
+                        .await
+                        .map_err(|error| match error {
+                            Error::ObjectNotFound { .. } => {
+                                Error::not_found_by_id(
+                                    ResourceType::InstanceTemplate,
+                                    &id,
+                                )
+                            }
+                            error => error,
+                        })?;
+                (authz_project, NameOrId::Id(id))
+            }
+            params::InstanceTemplateSelector {
+                instance_template: NameOrId::Name(name),
+                project: Some(project),
+            } => {
+                let (.., authz_project) = self
+                    .project_lookup(opctx, params::ProjectSelector { project })?
+                    .lookup_for(authz::Action::Read)
+                    .await?;
+                (authz_project, NameOrId::Name(name))
+            }
+            params::InstanceTemplateSelector {
+                instance_template: NameOrId::Id(_),
+                ..
+            } => {
+                return Err(Error::invalid_request(
+                    "when providing instance_template as an ID, project \
+                     should not be specified",
+                ));
+            }
+            _ => {
+                return Err(Error::invalid_request(
+                    "instance_template should either be an ID or project \
+                     should be specified",
+                ));
+            }
+        };
+
+        let template = self
+            .db_datastore
+            .instance_template_fetch(opctx, &authz_project, &template)
+            .await?;
+        Ok((authz_project, template))
+    }
+
+    pub(crate) async fn instance_template_create(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        project_lookup: &lookup::Project<'_>,
+        params: &params::InstanceTemplateCreate,
+    ) -> CreateResult<db::model::InstanceTemplate> {
+        let (.., authz_project) =
+            project_lookup.lookup_for(authz::Action::CreateChild).await?;
+
+        // Check what can be checked once, rather than having every instance
+        // created from the template fail for the same reason.
+        check_instance_cpu_memory_sizes(params.ncpus, params.memory)?;
+
+        if let params::InstanceNetworkInterfaceAttachment::Create(ref ifaces) =
+            params.network_interfaces
+        {
+            if ifaces.len() > MAX_NICS_PER_INSTANCE {
+                return Err(Error::invalid_request(&format!(
+                    "An instance may not have more than {} network interfaces",
+                    MAX_NICS_PER_INSTANCE,
+                )));
+            }
+            if ifaces.iter().any(|iface| iface.ip.is_some()) {
+                return Err(Error::invalid_value(
+                    "network_interfaces",
+                    "instance templates can't assign a specific IP address \
+                     to an interface",
+                ));
+            }
+        }
+
+        if params
+            .external_ips
+            .iter()
+            .any(|ip| matches!(ip, params::ExternalIpCreate::Floating { .. }))
+        {
+            return Err(Error::invalid_value(
+                "external_ips",
+                "instance templates can't attach floating IPs, since each can \
+                 only be attached to one instance",
+            ));
+        }
+        if params.external_ips.len() > MAX_EPHEMERAL_IPS_PER_INSTANCE {
+            return Err(Error::invalid_request(&format!(
+                "An instance may not have more than {} ephemeral IP address",
+                MAX_EPHEMERAL_IPS_PER_INSTANCE,
+            )));
+        }
+
+        if let Some(boot_disk) = &params.boot_disk {
+            let disk = instance_template_boot_disk(
+                boot_disk,
+                params.identity.name.clone(),
+                &params.identity.name,
+            );
+            self.validate_disk_create_params(opctx, &authz_project, &disk)
+                .await?;
+        }
+
+        let template = db::model::InstanceTemplate::new(
+            authz_project.id(),
+            params.clone(),
+        )?;
+        self.db_datastore
+            .instance_template_create(opctx, &authz_project, template)
+            .await
+    }
+
+    pub(crate) async fn instance_template_list(
+        &self,
+        opctx: &OpContext,
+        project_lookup: &lookup::Project<'_>,
+        pagparams: &PaginatedBy<'_>,
+    ) -> ListResultVec<db::model::InstanceTemplate> {
+        let (.., authz_project) =
+            project_lookup.lookup_for(authz::Action::ListChildren).await?;
+
+        self.db_datastore
+            .instance_template_list(opctx, &authz_project, pagparams)
+            .await
+    }
+
+    pub(crate) async fn instance_template_delete(
+        &self,
+        opctx: &OpContext,
+        selector: params::InstanceTemplateSelector,
+    ) -> DeleteResult {
+        let (authz_project, template) =
+            self.instance_template_fetch(opctx, selector).await?;
+
+        self.db_datastore
+            .instance_template_delete(opctx, &authz_project, template.id())
+            .await
+    }
+
+    /// Creates instances from a template
+    ///
+    /// Problems with the request as a whole, such as an invalid name pattern
+    /// or a missing affinity group, fail it before any instance is created.
+    /// After that, instances are created one at a time, and a failure to
+    /// create one doesn't stop the rest from being created: the outcome for
+    /// each is reported instead.
+    pub(crate) async fn instance_template_instantiate(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        selector: params::InstanceTemplateSelector,
+        params: &params::InstanceTemplateInstantiate,
+    ) -> Result<views::InstanceTemplateInstantiation, Error> {
+        let (authz_project, template) =
+            self.instance_template_fetch(opctx, selector).await?;
+
+        if params.count == 0
+            || params.count > MAX_INSTANCE_TEMPLATE_INSTANTIATE_COUNT
+        {
+            return Err(Error::invalid_value(
+                "count",
+                format!(
+                    "must be between 1 and \
+                     {MAX_INSTANCE_TEMPLATE_INSTANTIATE_COUNT}"
+                ),
+            ));
+        }
+        if params.name_pattern.matches(NAME_PATTERN_INDEX).count() != 1 {
+            return Err(Error::invalid_value(
+                "name_pattern",
+                format!("must contain \"{NAME_PATTERN_INDEX}\" exactly once"),
+            ));
+        }
+        let names = (0..params.count)
+            .map(|i| {
+                let index = u64::from(params.first_index) + u64::from(i);
+                InstantiatedNames::new(
+                    params
+                        .name_pattern
+                        .replace(NAME_PATTERN_INDEX, &index.to_string()),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let devices = template.devices()?;
+
+        // Resolve the groups once, so that every instance joins the same ones
+        // even if groups are renamed while the instances are being created.
+        let mut affinity_groups =
+            Vec::with_capacity(params.affinity_groups.len());
+        for group in &params.affinity_groups {
+            let (.., authz_group_project, authz_group) = match group {
+                NameOrId::Id(id) => LookupPath::new(opctx, &self.db_datastore)
+                    .affinity_group_id(*id),
+                NameOrId::Name(name) => {
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .project_id(authz_project.id())
+                        .affinity_group_name_owned(name.clone().into())
+                }
+            }
+            .lookup_for(authz::Action::Modify)
+            .await?;
+            if authz_group_project.id() != authz_project.id() {
+                return Err(Error::invalid_request(
+                    "affinity groups must be in the same project as the \
+                     instance template",
+                ));
+            }
+            affinity_groups.push(authz_group);
+        }
+        let anti_affinity_groups = self
+            .db_datastore
+            .anti_affinity_groups_batch_lookup(
+                opctx,
+                &authz_project,
+                &params.anti_affinity_groups,
+            )
+            .await?
+            .into_iter()
+            .map(|id| NameOrId::Id(id.into_untyped_uuid()))
+            .collect::<Vec<_>>();
+
+        let project_lookup = LookupPath::new(opctx, &self.db_datastore)
+            .project_id(authz_project.id());
+        let mut instances = Vec::with_capacity(names.len());
+        for names in names {
+            let create_params = params::InstanceCreate {
+                identity: IdentityMetadataCreateParams {
+                    name: names.instance.clone(),
+                    description: template.description().to_string(),
+                },
+                ncpus: template.ncpus.into(),
+                memory: template.memory.into(),
+                hostname: names.hostname,
+                user_data: template.user_data.clone(),
+                network_interfaces: devices.network_interfaces.clone(),
+                external_ips: devices.external_ips.clone(),
+                disks: Vec::new(),
+                boot_disk: devices.boot_disk.as_ref().map(|boot_disk| {
+                    params::InstanceDiskAttachment::Create(
+                        instance_template_boot_disk(
+                            boot_disk,
+                            names.boot_disk,
+                            &names.instance,
+                        ),
+                    )
+                }),
+                ssh_public_keys: devices.ssh_public_keys.clone(),
+                // Instances can only join affinity groups while they have no
+                // VMM, so they're started below once they've joined.
+                start: false,
+                auto_restart_policy: template
+                    .auto_restart_policy
+                    .map(Into::into),
+                anti_affinity_groups: anti_affinity_groups.clone(),
+            };
+
+            let result = self
+                .instance_template_create_one(
+                    opctx,
+                    &project_lookup,
+                    &create_params,
+                    &affinity_groups,
+                    params.start,
+                )
+                .await;
+            instances.push(views::InstanceTemplateInstanceResult {
+                name: names.instance,
+                instance: result.instance,
+                error: result.error.map(|error| {
+                    dropshot::HttpError::from(error).external_message
+                }),
+            });
+        }
+
+        Ok(views::InstanceTemplateInstantiation { instances })
+    }
+
+    /// Creates one instance from a template, adds it to its affinity groups
+    /// and, if asked to, starts it
+    async fn instance_template_create_one(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        project_lookup: &lookup::Project<'_>,
+        create_params: &params::InstanceCreate,
+        affinity_groups: &[authz::AffinityGroup],
+        start: bool,
+    ) -> InstantiateResult {
+        let instance = match self
+            .project_create_instance(opctx, project_lookup, create_params)
+            .await
+        {
+            Ok(instance) => instance,
+            Err(error) => {
+                return InstantiateResult {
+                    instance: None,
+                    error: Some(error),
+                };
+            }
+        };
+        let instance_id =
+            InstanceUuid::from_untyped_uuid(instance.instance().id());
+
+        for authz_group in affinity_groups {
+            if let Err(error) = self
+                .db_datastore
+                .affinity_group_member_instance_add(
+                    opctx,
+                    authz_group,
+                    instance_id,
+                )
+                .await
+            {
+                // Starting the instance now could place it somewhere the
+                // group doesn't allow, so leave it stopped.
+                return InstantiateResult {
+                    instance: Some(instance.into()),
+                    error: Some(error),
+                };
+            }
+        }
+
+        if !start {
+            return InstantiateResult {
+                instance: Some(instance.into()),
+                error: None,
+            };
+        }
+
+        let lookup = LookupPath::new(opctx, &self.db_datastore)
+            .instance_id(instance_id.into_untyped_uuid());
+        match self
+            .instance_start(opctx, &lookup, instance_start::Reason::AutoStart)
+            .await
+        {
+            Ok(started) => InstantiateResult {
+                instance: Some(started.into()),
+                error: None,
+            },
+            Err(error) => InstantiateResult {
+                instance: Some(instance.into()),
+                error: Some(error.into()),
+            },
+        }
+    }
+}
+
+/// The outcome of creating one instance from a template
+struct InstantiateResult {
+    instance: Option<omicron_common::api::external::Instance>,
+    error: Option<Error>,
+}
@@ -67,8 +67,10 @@ pub(crate) mod external_endpoints;
 mod external_ip;
 mod iam;
 mod image;
+mod image_import;
 mod instance;
 mod instance_network;
+mod instance_template;
 mod internet_gateway;
 mod ip_pool;
 mod lldp;
@@ -86,6 +88,9 @@ mod silo;
 mod sled;
 mod sled_instance;
 mod snapshot;
+mod snapshot_export;
+mod snapshot_group;
+mod snapshot_policy;
 mod ssh_key;
 pub(crate) mod support_bundles;
 mod switch;
@@ -242,8 +247,15 @@ pub struct Nexus {
     /// be replicated out to sleds in the background
     tuf_artifact_replication_tx: mpsc::Sender<ArtifactsWithPlan>,
 
+    /// Image files opened by requests to this Nexus, waiting for their import
+    /// sagas
+    image_import_files: image_import::ImageImportFiles,
+
     /// reports status of pending MGS-managed updates
     mgs_update_status_rx: watch::Receiver<MgsUpdateDriverStatus>,
+
+    /// MGS-managed updates most recently requested by blueprint execution
+    pending_mgs_updates_rx: watch::Receiver<PendingMgsUpdates>,
 }
 
 impl Nexus {
@@ -387,6 +399,11 @@ impl Nexus {
             ))
         };
 
+        let pantry_connection_pool =
+            make_pantry_connection_pool(&log, &qorb_resolver).map_err(|e| {
+                format!("failed to create pantry connection pool: {e}")
+            })?;
+
         let mut mgs_resolver =
             qorb_resolver.for_service(ServiceName::ManagementGatewayService);
         let mut repo_depot_resolver =
@@ -400,7 +417,7 @@ impl Nexus {
         let mgs_update_driver = MgsUpdateDriver::new(
             log.new(o!("component" => "MgsUpdateDriver")),
             artifact_cache,
-            mgs_updates_rx,
+            mgs_updates_rx.clone(),
             mgs_resolver.monitor(),
             DEFAULT_RETRY_TIMEOUT,
         );
@@ -438,7 +455,7 @@ impl Nexus {
                     as Arc<dyn nexus_auth::storage::Storage>,
             ),
             samael_max_issue_delay: std::sync::Mutex::new(None),
-            pantry_connection_pool: make_pantry_connection_pool(&qorb_resolver),
+            pantry_connection_pool,
             internal_resolver: resolver.clone(),
             external_resolver,
             external_dns_servers: config
@@ -456,7 +473,9 @@ impl Nexus {
                 CompletingDemoSagas::new(),
             )),
             tuf_artifact_replication_tx,
+            image_import_files: image_import::ImageImportFiles::default(),
             mgs_update_status_rx,
+            pending_mgs_updates_rx: mgs_updates_rx,
         };
 
         // TODO-cleanup all the extra Arcs here seems wrong
@@ -1020,6 +1039,12 @@ impl Nexus {
         // think about this internal detail.
         Ok(self.mgs_update_status_rx.borrow().clone())
     }
+
+    /// Returns the MGS-managed updates most recently requested by blueprint
+    /// execution
+    pub(crate) fn pending_mgs_updates(&self) -> PendingMgsUpdates {
+        self.pending_mgs_updates_rx.borrow().clone()
+    }
 }
 
 /// For unimplemented endpoints, indicates whether the resource identified
@@ -7,6 +7,8 @@
 use anyhow::Context;
 use anyhow::anyhow;
 use internal_dns_resolver::QorbResolver;
+use internal_dns_resolver::ResolveError;
+use internal_dns_resolver::WatchConfig;
 use internal_dns_types::names::ServiceName;
 use qorb::backend;
 use qorb::pool;
@@ -77,14 +79,25 @@ impl backend::Connector for PantryConnector {
 }
 
 pub(crate) fn make_pantry_connection_pool(
+    log: &slog::Logger,
     qorb_resolver: &QorbResolver,
-) -> pool::Pool<PooledPantryClient> {
-    match pool::Pool::new(
-        qorb_resolver.for_service(ServiceName::CruciblePantry),
-        Arc::new(PantryConnector),
-        qorb::policy::Policy::default(),
-    ) {
-        Ok(pool) => pool,
-        Err(e) => e.into_inner(),
-    }
+) -> Result<pool::Pool<PooledPantryClient>, ResolveError> {
+    // Pantries are added and expunged by the reconfigurator like any other
+    // zone, and sagas that pick one write its address down for later, so use
+    // a resolver that keeps following DNS rather than resolving only once.
+    let resolver = qorb_resolver.for_service_watched(
+        log,
+        ServiceName::CruciblePantry,
+        WatchConfig::default(),
+    )?;
+    Ok(
+        match pool::Pool::new(
+            resolver,
+            Arc::new(PantryConnector),
+            qorb::policy::Policy::default(),
+        ) {
+            Ok(pool) => pool,
+            Err(e) => e.into_inner(),
+        },
+    )
 }
@@ -0,0 +1,430 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Create a disk from a copy of another disk
+//!
+//! There's no way to copy a disk's blocks directly, so this saga takes an
+//! internal snapshot of the source disk with the `snapshot_create` saga,
+//! creates the new disk from that snapshot with the `disk_create` saga, and
+//! then deletes the snapshot with the `snapshot_delete` saga. Each of those
+//! runs as a subsaga, so if creating the new disk fails, unwinding the
+//! `snapshot_create` subsaga cleans up the snapshot too.
+//!
+//! Deleting the snapshot doesn't affect the new disk: its read-only parent
+//! holds references to the snapshot's region snapshots, which keeps them
+//! around for as long as the new disk needs them.
+
+use super::{ActionRegistry, NexusActionContext, NexusSaga, SagaInitError};
+use crate::app::sagas;
+use crate::app::sagas::declare_saga_actions;
+use crate::app::{authn, authz, db};
+use crate::external_api::params;
+use nexus_db_lookup::LookupPath;
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::Name;
+use omicron_common::api::external::NameOrId;
+use serde::Deserialize;
+use serde::Serialize;
+use steno::ActionError;
+use steno::Node;
+use uuid::Uuid;
+
+// disk clone saga: input parameters
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Params {
+    pub serialized_authn: authn::saga::Serialized,
+    pub silo_id: Uuid,
+    pub project_id: Uuid,
+    /// Identifies this clone, and names the intermediate snapshot
+    pub clone_id: Uuid,
+    pub source_disk_id: Uuid,
+    pub attach_instance_id: Option<Uuid>,
+    pub use_the_pantry: bool,
+    pub create_params: params::DiskCreate,
+}
+
+impl Params {
+    /// The name of the intermediate snapshot of the source disk
+    fn snapshot_name(&self) -> Name {
+        format!("disk-clone-{}", self.clone_id)
+            .parse()
+            .expect("disk clone snapshot name is valid")
+    }
+}
+
+// disk clone saga: actions
+
+declare_saga_actions! {
+    disk_clone;
+    DISK_CREATE_PARAMS -> "disk_create_params" {
+        + sdcl_disk_create_params
+    }
+    FETCH_CREATED_DISK -> "created_disk" {
+        + sdcl_fetch_created_disk
+    }
+    SNAPSHOT_DELETE_PARAMS -> "snapshot_delete_params" {
+        + sdcl_snapshot_delete_params
+    }
+}
+
+// disk clone saga: definition
+
+#[derive(Debug)]
+pub(crate) struct SagaDiskClone;
+impl NexusSaga for SagaDiskClone {
+    const NAME: &'static str = "disk-clone";
+    type Params = Params;
+
+    fn register_actions(registry: &mut ActionRegistry) {
+        disk_clone_register_actions(registry);
+    }
+
+    fn make_saga_dag(
+        params: &Self::Params,
+        mut builder: steno::DagBuilder,
+    ) -> Result<steno::Dag, SagaInitError> {
+        // Take the intermediate snapshot. Everything the snapshot create saga
+        // needs is known up front, so its parameters are a constant.
+        const SNAPSHOT_CREATE_PARAMS: &'static str = "snapshot_create_params";
+
+        let snapshot_create_params = sagas::snapshot_create::Params {
+            serialized_authn: params.serialized_authn.clone(),
+            silo_id: params.silo_id,
+            project_id: params.project_id,
+            disk_id: params.source_disk_id,
+            attach_instance_id: params.attach_instance_id,
+            use_the_pantry: params.use_the_pantry,
+            create_params: params::SnapshotCreate {
+                identity: IdentityMetadataCreateParams {
+                    name: params.snapshot_name(),
+                    description: format!(
+                        "internal snapshot for cloning disk {}",
+                        params.source_disk_id,
+                    ),
+                },
+                disk: NameOrId::Id(params.source_disk_id),
+            },
+            group_snapshot_id: None,
+        };
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::snapshot_create::SagaSnapshotCreate::NAME,
+        ));
+        let snapshot_create_dag =
+            sagas::snapshot_create::SagaSnapshotCreate::make_saga_dag(
+                &snapshot_create_params,
+                subsaga_builder,
+            )?;
+
+        builder.append(Node::constant(
+            SNAPSHOT_CREATE_PARAMS,
+            serde_json::to_value(&snapshot_create_params).map_err(|e| {
+                SagaInitError::SerializeError(
+                    String::from(SNAPSHOT_CREATE_PARAMS),
+                    e,
+                )
+            })?,
+        ));
+        builder.append(Node::subsaga(
+            "snapshot_create",
+            snapshot_create_dag,
+            SNAPSHOT_CREATE_PARAMS,
+        ));
+
+        // Create the new disk from the snapshot. The snapshot's ID isn't
+        // known until it has been created, so these parameters come from an
+        // action.
+        builder.append(disk_create_params_action());
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::disk_create::SagaDiskCreate::NAME,
+        ));
+        builder.append(Node::subsaga(
+            "disk_create",
+            sagas::disk_create::create_dag(subsaga_builder, false)?,
+            "disk_create_params",
+        ));
+
+        builder.append(fetch_created_disk_action());
+
+        // Garbage-collect the intermediate snapshot. This comes last so that
+        // as little as possible can fail (and unwind the snapshot create
+        // subsaga) once the snapshot has started being deleted.
+        builder.append(snapshot_delete_params_action());
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::snapshot_delete::SagaSnapshotDelete::NAME,
+        ));
+        builder.append(Node::subsaga(
+            "snapshot_delete",
+            sagas::snapshot_delete::create_dag(subsaga_builder)?,
+            "snapshot_delete_params",
+        ));
+
+        Ok(builder.build()?)
+    }
+}
+
+// disk clone saga: action implementations
+
+/// Looks up the intermediate snapshot created by the `snapshot_create`
+/// subsaga.
+async fn sdcl_lookup_snapshot(
+    sagactx: &NexusActionContext,
+    params: &Params,
+) -> Result<(authz::Snapshot, db::model::Snapshot), ActionError> {
+    let osagactx = sagactx.user_data();
+    let opctx = crate::context::op_context_for_saga_action(
+        sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., authz_snapshot, db_snapshot) =
+        LookupPath::new(&opctx, osagactx.datastore())
+            .project_id(params.project_id)
+            .snapshot_name_owned(params.snapshot_name().into())
+            .fetch()
+            .await
+            .map_err(ActionError::action_failed)?;
+
+    Ok((authz_snapshot, db_snapshot))
+}
+
+async fn sdcl_disk_create_params(
+    sagactx: NexusActionContext,
+) -> Result<sagas::disk_create::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    let (authz_snapshot, _) = sdcl_lookup_snapshot(&sagactx, &params).await?;
+
+    Ok(sagas::disk_create::Params {
+        serialized_authn: params.serialized_authn,
+        project_id: params.project_id,
+        create_params: params::DiskCreate {
+            disk_source: params::DiskSource::Snapshot {
+                snapshot_id: authz_snapshot.id(),
+            },
+            ..params.create_params
+        },
+    })
+}
+
+async fn sdcl_fetch_created_disk(
+    sagactx: NexusActionContext,
+) -> Result<db::model::Disk, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., db_disk) = LookupPath::new(&opctx, osagactx.datastore())
+        .project_id(params.project_id)
+        .disk_name_owned(params.create_params.identity.name.into())
+        .fetch()
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    Ok(db_disk)
+}
+
+async fn sdcl_snapshot_delete_params(
+    sagactx: NexusActionContext,
+) -> Result<sagas::snapshot_delete::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    let (authz_snapshot, db_snapshot) =
+        sdcl_lookup_snapshot(&sagactx, &params).await?;
+
+    Ok(sagas::snapshot_delete::Params {
+        serialized_authn: params.serialized_authn,
+        authz_snapshot,
+        snapshot: db_snapshot,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::app::saga::create_saga_dag;
+    use crate::app::sagas::disk_clone::Params;
+    use crate::app::sagas::disk_clone::SagaDiskClone;
+    use crate::app::sagas::test_helpers;
+    use crate::external_api::params;
+    use async_bb8_diesel::AsyncRunQueryDsl;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use nexus_db_lookup::LookupPath;
+    use nexus_db_queries::authn::saga::Serialized;
+    use nexus_db_queries::authz;
+    use nexus_db_queries::context::OpContext;
+    use nexus_db_queries::db::datastore::DataStore;
+    use nexus_db_queries::db::identity::Resource;
+    use nexus_test_utils::resource_helpers::create_disk;
+    use nexus_test_utils::resource_helpers::create_project;
+    use nexus_test_utils_macros::nexus_test;
+    use omicron_common::api::external::ByteCount;
+    use omicron_common::api::external::IdentityMetadataCreateParams;
+    use uuid::Uuid;
+
+    type ControlPlaneTestContext =
+        nexus_test_utils::ControlPlaneTestContext<crate::Server>;
+    type DiskTest<'a> =
+        nexus_test_utils::resource_helpers::DiskTest<'a, crate::Server>;
+
+    const PROJECT_NAME: &str = "springfield-squidport";
+    const SOURCE_DISK_NAME: &str = "disky-mcdiskface";
+    const CLONE_DISK_NAME: &str = "disky-mcclone";
+
+    struct SourceDisk {
+        silo_id: Uuid,
+        project_id: Uuid,
+        disk_id: Uuid,
+    }
+
+    async fn create_source_disk(
+        cptestctx: &ControlPlaneTestContext,
+        opctx: &OpContext,
+    ) -> SourceDisk {
+        let client = &cptestctx.external_client;
+        let nexus = &cptestctx.server.server_context().nexus;
+        create_project(client, PROJECT_NAME).await;
+        let disk = create_disk(client, PROJECT_NAME, SOURCE_DISK_NAME).await;
+
+        let (authz_silo, authz_project, authz_disk) =
+            LookupPath::new(opctx, nexus.datastore())
+                .disk_id(disk.identity.id)
+                .lookup_for(authz::Action::Read)
+                .await
+                .expect("Failed to look up created disk");
+
+        SourceDisk {
+            silo_id: authz_silo.id(),
+            project_id: authz_project.id(),
+            disk_id: authz_disk.id(),
+        }
+    }
+
+    fn new_test_params(opctx: &OpContext, source: &SourceDisk) -> Params {
+        Params {
+            serialized_authn: Serialized::for_opctx(opctx),
+            silo_id: source.silo_id,
+            project_id: source.project_id,
+            clone_id: Uuid::new_v4(),
+            source_disk_id: source.disk_id,
+            attach_instance_id: None,
+            use_the_pantry: true,
+            create_params: params::DiskCreate {
+                identity: IdentityMetadataCreateParams {
+                    name: CLONE_DISK_NAME.parse().unwrap(),
+                    description: String::from("a clone"),
+                },
+                disk_source: params::DiskSource::Disk {
+                    disk_id: source.disk_id,
+                },
+                size: ByteCount::from_gibibytes_u32(1),
+            },
+        }
+    }
+
+    async fn count_live_disks(datastore: &DataStore) -> i64 {
+        use nexus_db_schema::schema::disk::dsl;
+
+        dsl::disk
+            .filter(dsl::time_deleted.is_null())
+            .count()
+            .get_result_async(
+                &*datastore.pool_connection_for_tests().await.unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn count_live_snapshots(datastore: &DataStore) -> i64 {
+        use nexus_db_schema::schema::snapshot::dsl;
+
+        dsl::snapshot
+            .filter(dsl::time_deleted.is_null())
+            .count()
+            .get_result_async(
+                &*datastore.pool_connection_for_tests().await.unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_saga_basic_usage_succeeds(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let source = create_source_disk(cptestctx, &opctx).await;
+
+        let params = new_test_params(&opctx, &source);
+        let output =
+            nexus.sagas.saga_execute::<SagaDiskClone>(params).await.unwrap();
+        let disk = output
+            .lookup_node_output::<nexus_db_queries::db::model::Disk>(
+                "created_disk",
+            )
+            .unwrap();
+        assert_eq!(disk.project_id, source.project_id);
+        assert_eq!(disk.name().as_str(), CLONE_DISK_NAME);
+
+        // The source disk and its clone remain, but the intermediate
+        // snapshot has been cleaned up.
+        assert_eq!(count_live_disks(datastore).await, 2);
+        assert_eq!(count_live_snapshots(datastore).await, 0);
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_actions_succeed_idempotently(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let source = create_source_disk(cptestctx, &opctx).await;
+
+        let params = new_test_params(&opctx, &source);
+        let dag = create_saga_dag::<SagaDiskClone>(params).unwrap();
+        test_helpers::actions_succeed_idempotently(nexus, dag).await;
+
+        assert_eq!(count_live_disks(datastore).await, 2);
+        assert_eq!(count_live_snapshots(datastore).await, 0);
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_action_failure_can_unwind(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let source = create_source_disk(cptestctx, &opctx).await;
+
+        test_helpers::action_failure_can_unwind::<SagaDiskClone, _, _>(
+            nexus,
+            || Box::pin(async { new_test_params(&opctx, &source) }),
+            || {
+                Box::pin(async {
+                    test_helpers::assert_no_failed_undo_steps(
+                        &cptestctx.logctx.log,
+                        datastore,
+                    )
+                    .await;
+
+                    // Only the source disk is left behind.
+                    assert_eq!(count_live_disks(datastore).await, 1);
+                    assert_eq!(count_live_snapshots(datastore).await, 0);
+                })
+            },
+            &cptestctx.logctx.log,
+        )
+        .await;
+    }
+}
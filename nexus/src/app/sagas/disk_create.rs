@@ -90,39 +90,51 @@ impl NexusSaga for SagaDiskCreate {
 
     fn make_saga_dag(
         params: &Self::Params,
-        mut builder: steno::DagBuilder,
+        builder: steno::DagBuilder,
     ) -> Result<steno::Dag, SagaInitError> {
-        builder.append(Node::action(
-            "disk_id",
-            "GenerateDiskId",
-            ACTION_GENERATE_ID.as_ref(),
-        ));
-
-        builder.append(Node::action(
-            "volume_id",
-            "GenerateVolumeId",
-            ACTION_GENERATE_ID.as_ref(),
-        ));
-
-        builder.append(create_disk_record_action());
-        builder.append(regions_alloc_action());
-        builder.append(space_account_action());
-        builder.append(regions_ensure_undo_action());
-        builder.append(regions_ensure_action());
-        builder.append(create_volume_record_action());
-        builder.append(finalize_disk_record_action());
+        let importing_blocks = match &params.create_params.disk_source {
+            params::DiskSource::ImportingBlocks { .. } => true,
 
-        match &params.create_params.disk_source {
-            params::DiskSource::ImportingBlocks { .. } => {
-                builder.append(get_pantry_address_action());
-                builder.append(call_pantry_attach_for_disk_action());
-            }
+            _ => false,
+        };
 
-            _ => {}
-        }
+        create_dag(builder, importing_blocks)
+    }
+}
 
-        Ok(builder.build()?)
+/// Identical to [SagaDiskCreate::make_saga_dag], but using types to identify
+/// that parameters do not need to be supplied as input. Only whether or not
+/// the disk will be importing blocks affects the shape of the DAG.
+pub(crate) fn create_dag(
+    mut builder: steno::DagBuilder,
+    importing_blocks: bool,
+) -> Result<steno::Dag, SagaInitError> {
+    builder.append(Node::action(
+        "disk_id",
+        "GenerateDiskId",
+        ACTION_GENERATE_ID.as_ref(),
+    ));
+
+    builder.append(Node::action(
+        "volume_id",
+        "GenerateVolumeId",
+        ACTION_GENERATE_ID.as_ref(),
+    ));
+
+    builder.append(create_disk_record_action());
+    builder.append(regions_alloc_action());
+    builder.append(space_account_action());
+    builder.append(regions_ensure_undo_action());
+    builder.append(regions_ensure_action());
+    builder.append(create_volume_record_action());
+    builder.append(finalize_disk_record_action());
+
+    if importing_blocks {
+        builder.append(get_pantry_address_action());
+        builder.append(call_pantry_attach_for_disk_action());
     }
+
+    Ok(builder.build()?)
 }
 
 // disk create saga: action implementations
@@ -179,6 +191,13 @@ async fn sdc_create_disk_record(
 
                 image.block_size
             }
+            params::DiskSource::Disk { .. } => {
+                // The disk clone saga snapshots the source disk and hands
+                // this saga that snapshot as the disk source instead.
+                return Err(ActionError::action_failed(Error::internal_error(
+                    "disk source must be converted to a snapshot source",
+                )));
+            }
             params::DiskSource::ImportingBlocks { block_size } => {
                 db::model::BlockSize::try_from(*block_size).map_err(|e| {
                     ActionError::action_failed(Error::internal_error(
@@ -221,11 +240,19 @@ async fn sdc_create_disk_record_undo(
     let osagactx = sagactx.user_data();
 
     let disk_id = sagactx.lookup::<Uuid>("disk_id")?;
+
+    // Disks that are importing blocks are left in state ImportReady once
+    // their record is finalized.
     osagactx
         .datastore()
         .project_delete_disk_no_auth(
             &disk_id,
-            &[DiskState::Detached, DiskState::Faulted, DiskState::Creating],
+            &[
+                DiskState::Detached,
+                DiskState::Faulted,
+                DiskState::Creating,
+                DiskState::ImportReady,
+            ],
         )
         .await?;
     Ok(())
@@ -464,6 +491,11 @@ async fn sdc_regions_ensure(
                     },
                 )?))
             }
+            params::DiskSource::Disk { .. } => {
+                return Err(ActionError::action_failed(Error::internal_error(
+                    "disk source must be converted to a snapshot source",
+                )));
+            }
             params::DiskSource::ImportingBlocks { block_size: _ } => None,
         };
 
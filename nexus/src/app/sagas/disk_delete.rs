@@ -5,7 +5,6 @@
 use super::ActionRegistry;
 use super::NexusActionContext;
 use super::NexusSaga;
-use crate::app::sagas::SagaInitError;
 use crate::app::sagas::declare_saga_actions;
 use crate::app::sagas::volume_delete;
 use nexus_db_queries::authn;
@@ -40,6 +39,9 @@ declare_saga_actions! {
         + sdd_account_space
         - sdd_account_space_undo
     }
+    VOLUME_DELETE_PARAMS -> "params_for_volume_delete_subsaga" {
+        + sdd_volume_delete_params
+    }
 }
 
 // disk delete saga: definition
@@ -55,47 +57,34 @@ impl NexusSaga for SagaDiskDelete {
     }
 
     fn make_saga_dag(
-        params: &Self::Params,
-        mut builder: steno::DagBuilder,
+        _params: &Self::Params,
+        builder: steno::DagBuilder,
     ) -> Result<steno::Dag, super::SagaInitError> {
-        builder.append(delete_disk_record_action());
-        builder.append(space_account_action());
-
-        let subsaga_params = volume_delete::Params {
-            serialized_authn: params.serialized_authn.clone(),
-            volume_id: params.volume_id,
-        };
-
-        let subsaga_dag = {
-            let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
-                volume_delete::SagaVolumeDelete::NAME,
-            ));
-            volume_delete::SagaVolumeDelete::make_saga_dag(
-                &subsaga_params,
-                subsaga_builder,
-            )?
-        };
-
-        builder.append(Node::constant(
-            "params_for_volume_delete_subsaga",
-            serde_json::to_value(&subsaga_params).map_err(|e| {
-                SagaInitError::SerializeError(
-                    "params_for_volume_delete_subsaga".to_string(),
-                    e,
-                )
-            })?,
-        ));
-
-        builder.append(Node::subsaga(
-            "volume_delete_subsaga_no_result",
-            subsaga_dag,
-            "params_for_volume_delete_subsaga",
-        ));
-
-        Ok(builder.build()?)
+        create_dag(builder)
     }
 }
 
+/// Identical to [SagaDiskDelete::make_saga_dag], but using types to identify
+/// that parameters do not need to be supplied as input.
+pub(crate) fn create_dag(
+    mut builder: steno::DagBuilder,
+) -> Result<steno::Dag, super::SagaInitError> {
+    builder.append(delete_disk_record_action());
+    builder.append(space_account_action());
+    builder.append(volume_delete_params_action());
+
+    let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+        volume_delete::SagaVolumeDelete::NAME,
+    ));
+    builder.append(Node::subsaga(
+        "volume_delete_subsaga_no_result",
+        volume_delete::create_dag(subsaga_builder)?,
+        "params_for_volume_delete_subsaga",
+    ));
+
+    Ok(builder.build()?)
+}
+
 // disk delete saga: action implementations
 
 async fn sdd_delete_disk_record(
@@ -179,6 +168,17 @@ async fn sdd_account_space_undo(
     Ok(())
 }
 
+async fn sdd_volume_delete_params(
+    sagactx: NexusActionContext,
+) -> Result<volume_delete::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+
+    Ok(volume_delete::Params {
+        serialized_authn: params.serialized_authn,
+        volume_id: params.volume_id,
+    })
+}
+
 #[cfg(test)]
 pub(crate) mod test {
     use crate::{
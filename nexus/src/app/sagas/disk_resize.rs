@@ -0,0 +1,698 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Grow a Crucible-backed disk
+//!
+//! A disk's volume is a concatenation of its read-write sub-volumes. Growing a
+//! disk appends a new Region sub-volume, backed by its own set of regions,
+//! that covers the added space:
+//!
+//! 1. Check that the disk is not in use by a VMM. Propolis has no way to grow
+//!    a disk it's already using, so disks may only be resized while detached
+//!    or attached to an instance that isn't running.
+//! 2. Account for the new size against the project's storage (and the silo's
+//!    quota).
+//! 3. Allocate and ensure `REGION_REDUNDANCY_THRESHOLD` new regions sized for
+//!    the difference between the old and new sizes.
+//! 4. Append a Region sub-volume for the new regions to the disk's volume.
+//! 5. Record the disk's new size.
+//!
+//! A VMM started after step 4 receives the updated volume. One started while
+//! the saga runs, before step 4, keeps using the old volume (and sees the old
+//! size) until it's next started.
+
+use super::{
+    ACTION_GENERATE_ID, ActionRegistry, NexusActionContext, NexusSaga,
+    SagaInitError,
+};
+use crate::app::sagas::declare_saga_actions;
+use crate::app::{authn, authz, db};
+use nexus_db_lookup::LookupPath;
+use nexus_db_model::VmmState;
+use nexus_db_queries::db::datastore::REGION_REDUNDANCY_THRESHOLD;
+use omicron_common::api::external::ByteCount;
+use omicron_common::api::external::Error;
+use omicron_uuid_kinds::VolumeUuid;
+use rand::{RngCore, SeedableRng, rngs::StdRng};
+use serde::Deserialize;
+use serde::Serialize;
+use sled_agent_client::{CrucibleOpts, VolumeConstructionRequest};
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use steno::ActionError;
+use steno::Node;
+use uuid::Uuid;
+
+// disk resize saga: input parameters
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Params {
+    pub serialized_authn: authn::saga::Serialized,
+    pub project_id: Uuid,
+    pub disk_id: Uuid,
+    pub volume_id: VolumeUuid,
+    pub old_size: ByteCount,
+    pub new_size: ByteCount,
+}
+
+// disk resize saga: actions
+
+declare_saga_actions! {
+    disk_resize;
+    CHECK_NO_ACTIVE_VMM -> "no_active_vmm" {
+        + sdr_check_no_active_vmm
+    }
+    GET_EXISTING_REGIONS -> "existing_datasets_and_regions" {
+        + sdr_get_existing_regions
+    }
+    SPACE_ACCOUNT -> "no_result" {
+        + sdr_account_space
+        - sdr_account_space_undo
+    }
+    REGIONS_ALLOC -> "datasets_and_regions" {
+        + sdr_alloc_regions
+        - sdr_alloc_regions_undo
+    }
+    REGIONS_ENSURE_UNDO -> "regions_ensure_undo" {
+        + sdr_noop
+        - sdr_regions_ensure_undo
+    }
+    REGIONS_ENSURE -> "regions_ensure" {
+        + sdr_regions_ensure
+    }
+    APPEND_SUB_VOLUME -> "append_sub_volume" {
+        + sdr_append_sub_volume
+        - sdr_append_sub_volume_undo
+    }
+    UPDATE_DISK_SIZE -> "resized_disk" {
+        + sdr_update_disk_size
+        - sdr_update_disk_size_undo
+    }
+}
+
+// disk resize saga: definition
+
+#[derive(Debug)]
+pub(crate) struct SagaDiskResize;
+impl NexusSaga for SagaDiskResize {
+    const NAME: &'static str = "disk-resize";
+    type Params = Params;
+
+    fn register_actions(registry: &mut ActionRegistry) {
+        disk_resize_register_actions(registry);
+    }
+
+    fn make_saga_dag(
+        _params: &Self::Params,
+        mut builder: steno::DagBuilder,
+    ) -> Result<steno::Dag, SagaInitError> {
+        builder.append(Node::action(
+            "sub_volume_id",
+            "GenerateSubVolumeId",
+            ACTION_GENERATE_ID.as_ref(),
+        ));
+
+        builder.append(check_no_active_vmm_action());
+        builder.append(get_existing_regions_action());
+        builder.append(space_account_action());
+        builder.append(regions_alloc_action());
+        builder.append(regions_ensure_undo_action());
+        builder.append(regions_ensure_action());
+        builder.append(append_sub_volume_action());
+        builder.append(update_disk_size_action());
+
+        Ok(builder.build()?)
+    }
+}
+
+// disk resize saga: action implementations
+
+async fn sdr_check_no_active_vmm(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., db_disk) = LookupPath::new(&opctx, osagactx.datastore())
+        .disk_id(params.disk_id)
+        .fetch()
+        .await
+        .map_err(ActionError::action_failed)?;
+    let Some(instance_id) = db_disk.runtime().attach_instance_id else {
+        return Ok(());
+    };
+
+    let (.., authz_instance) = LookupPath::new(&opctx, osagactx.datastore())
+        .instance_id(instance_id)
+        .lookup_for(authz::Action::Read)
+        .await
+        .map_err(ActionError::action_failed)?;
+    let instance_and_vmm = osagactx
+        .datastore()
+        .instance_fetch_with_vmm(&opctx, &authz_instance)
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    match instance_and_vmm.vmm() {
+        None => Ok(()),
+        Some(vmm)
+            if VmmState::DESTROYABLE_STATES.contains(&vmm.runtime.state) =>
+        {
+            Ok(())
+        }
+        Some(vmm) => Err(ActionError::action_failed(Error::conflict(format!(
+            "cannot resize disk {} while instance {} has an active vmm \
+             (in state {})",
+            params.disk_id, instance_id, vmm.runtime.state,
+        )))),
+    }
+}
+
+async fn sdr_get_existing_regions(
+    sagactx: NexusActionContext,
+) -> Result<Vec<(db::model::CrucibleDataset, db::model::Region)>, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+
+    let datasets_and_regions = osagactx
+        .datastore()
+        .get_allocated_regions(params.volume_id)
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    if datasets_and_regions.is_empty() {
+        return Err(ActionError::action_failed(Error::internal_error(
+            &format!("volume {} has no regions", params.volume_id),
+        )));
+    }
+
+    Ok(datasets_and_regions)
+}
+
+async fn sdr_account_space(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+    osagactx
+        .datastore()
+        .virtual_provisioning_collection_resize_disk(
+            &opctx,
+            params.disk_id,
+            params.project_id,
+            params.old_size.into(),
+            params.new_size.into(),
+        )
+        .await
+        .map_err(ActionError::action_failed)?;
+    Ok(())
+}
+
+async fn sdr_account_space_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+    osagactx
+        .datastore()
+        .virtual_provisioning_collection_resize_disk(
+            &opctx,
+            params.disk_id,
+            params.project_id,
+            params.new_size.into(),
+            params.old_size.into(),
+        )
+        .await?;
+    Ok(())
+}
+
+async fn sdr_alloc_regions(
+    sagactx: NexusActionContext,
+) -> Result<Vec<(db::model::CrucibleDataset, db::model::Region)>, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let existing_datasets_and_regions =
+        sagactx
+            .lookup::<Vec<(db::model::CrucibleDataset, db::model::Region)>>(
+                "existing_datasets_and_regions",
+            )?;
+
+    // The new regions must use the same block and extent sizes as the
+    // existing ones, and hold exactly the difference between the two sizes.
+    let existing_region = &existing_datasets_and_regions[0].1;
+    let block_size = existing_region.block_size().to_bytes();
+    let blocks_per_extent = existing_region.blocks_per_extent();
+    let extent_bytes = block_size * blocks_per_extent;
+    let delta = params.new_size.to_bytes() - params.old_size.to_bytes();
+    if delta % extent_bytes != 0 {
+        return Err(ActionError::action_failed(Error::invalid_request(
+            &format!(
+                "disk size must grow by a multiple of {extent_bytes} bytes"
+            ),
+        )));
+    }
+
+    // Region allocation for a volume is idempotent: ask for the volume's
+    // existing regions plus a new set, and keep only the new ones.
+    let strategy = &osagactx.nexus().default_region_allocation_strategy;
+    let datasets_and_regions = osagactx
+        .datastore()
+        .arbitrary_region_allocate(
+            &opctx,
+            db::datastore::RegionAllocationFor::DiskVolume {
+                volume_id: params.volume_id,
+            },
+            db::datastore::RegionAllocationParameters::FromRaw {
+                block_size,
+                blocks_per_extent,
+                extent_count: delta / extent_bytes,
+            },
+            strategy,
+            existing_datasets_and_regions.len() + REGION_REDUNDANCY_THRESHOLD,
+        )
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    Ok(only_new_regions(&existing_datasets_and_regions, datasets_and_regions))
+}
+
+fn only_new_regions(
+    existing_datasets_and_regions: &[(
+        db::model::CrucibleDataset,
+        db::model::Region,
+    )],
+    datasets_and_regions: Vec<(db::model::CrucibleDataset, db::model::Region)>,
+) -> Vec<(db::model::CrucibleDataset, db::model::Region)> {
+    let existing_region_ids: BTreeSet<Uuid> = existing_datasets_and_regions
+        .iter()
+        .map(|(_, region)| region.id())
+        .collect();
+
+    datasets_and_regions
+        .into_iter()
+        .filter(|(_, region)| !existing_region_ids.contains(&region.id()))
+        .collect()
+}
+
+async fn sdr_alloc_regions_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+
+    let region_ids = sagactx
+        .lookup::<Vec<(db::model::CrucibleDataset, db::model::Region)>>(
+            "datasets_and_regions",
+        )?
+        .into_iter()
+        .map(|(_, region)| region.id())
+        .collect::<Vec<Uuid>>();
+
+    osagactx.datastore().regions_hard_delete(log, region_ids).await?;
+    Ok(())
+}
+
+async fn sdr_noop(_sagactx: NexusActionContext) -> Result<(), ActionError> {
+    Ok(())
+}
+
+/// Call out to Crucible agent and perform region creation, returning the
+/// sub-volume that will be appended to the disk's volume.
+async fn sdr_regions_ensure(
+    sagactx: NexusActionContext,
+) -> Result<VolumeConstructionRequest, ActionError> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let sub_volume_id = sagactx.lookup::<Uuid>("sub_volume_id")?;
+
+    let datasets_and_regions = osagactx
+        .nexus()
+        .ensure_all_datasets_and_regions(
+            &log,
+            sagactx
+                .lookup::<Vec<(db::model::CrucibleDataset, db::model::Region)>>(
+                    "datasets_and_regions",
+                )?,
+        )
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    let block_size = datasets_and_regions[0].1.block_size;
+    let blocks_per_extent = datasets_and_regions[0].1.extent_size;
+    let extent_count = datasets_and_regions[0].1.extent_count;
+
+    let mut rng = StdRng::from_entropy();
+    Ok(VolumeConstructionRequest::Region {
+        block_size,
+        blocks_per_extent,
+        extent_count,
+        gen: 1,
+        opts: CrucibleOpts {
+            id: sub_volume_id,
+            target: datasets_and_regions
+                .iter()
+                .map(|(dataset, region)| {
+                    SocketAddr::V6(
+                        dataset.address_with_port(region.port_number),
+                    )
+                })
+                .collect::<Vec<_>>(),
+
+            lossy: false,
+            flush_timeout: None,
+
+            // all downstairs will expect encrypted blocks
+            key: Some(base64::Engine::encode(
+                &base64::engine::general_purpose::STANDARD,
+                {
+                    let mut random_bytes: [u8; 32] = [0; 32];
+                    rng.fill_bytes(&mut random_bytes);
+                    random_bytes
+                },
+            )),
+
+            cert_pem: None,
+            key_pem: None,
+            root_cert_pem: None,
+
+            control: None,
+
+            read_only: false,
+        },
+    })
+}
+
+async fn sdr_regions_ensure_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let log = sagactx.user_data().log();
+    let osagactx = sagactx.user_data();
+
+    warn!(log, "sdr_regions_ensure_undo: Deleting crucible regions");
+
+    osagactx
+        .nexus()
+        .delete_crucible_regions(
+            log,
+            sagactx
+                .lookup::<Vec<(db::model::CrucibleDataset, db::model::Region)>>(
+                    "datasets_and_regions",
+                )?,
+        )
+        .await?;
+
+    info!(log, "sdr_regions_ensure_undo: Deleted crucible regions");
+    Ok(())
+}
+
+async fn sdr_append_sub_volume(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+
+    let sub_volume =
+        sagactx.lookup::<VolumeConstructionRequest>("regions_ensure")?;
+
+    osagactx
+        .datastore()
+        .volume_append_sub_volume(params.volume_id, sub_volume)
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    Ok(())
+}
+
+async fn sdr_append_sub_volume_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let sub_volume_id = sagactx.lookup::<Uuid>("sub_volume_id")?;
+
+    osagactx
+        .datastore()
+        .volume_remove_sub_volume(params.volume_id, sub_volume_id)
+        .await?;
+
+    Ok(())
+}
+
+async fn sdr_update_disk_size(
+    sagactx: NexusActionContext,
+) -> Result<db::model::Disk, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let datastore = osagactx.datastore();
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., authz_disk) = LookupPath::new(&opctx, datastore)
+        .disk_id(params.disk_id)
+        .lookup_for(authz::Action::Modify)
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    datastore
+        .disk_update_size(
+            &opctx,
+            &authz_disk,
+            params.old_size.into(),
+            params.new_size.into(),
+        )
+        .await
+        .map_err(ActionError::action_failed)
+}
+
+async fn sdr_update_disk_size_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let datastore = osagactx.datastore();
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., authz_disk) = LookupPath::new(&opctx, datastore)
+        .disk_id(params.disk_id)
+        .lookup_for(authz::Action::Modify)
+        .await?;
+
+    datastore
+        .disk_update_size(
+            &opctx,
+            &authz_disk,
+            params.new_size.into(),
+            params.old_size.into(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::app::sagas::disk_resize::Params;
+    use crate::app::sagas::disk_resize::SagaDiskResize;
+    use nexus_db_model::Disk;
+    use nexus_db_queries::authn::saga::Serialized;
+    use nexus_db_queries::context::OpContext;
+    use nexus_test_utils::resource_helpers::DiskTestBuilder;
+    use nexus_test_utils::resource_helpers::create_project;
+    use nexus_test_utils_macros::nexus_test;
+    use nexus_types::external_api::params;
+    use omicron_common::api::external::ByteCount;
+    use omicron_common::api::external::Name;
+    use sled_agent_client::VolumeConstructionRequest;
+    use uuid::Uuid;
+
+    type ControlPlaneTestContext =
+        nexus_test_utils::ControlPlaneTestContext<crate::Server>;
+
+    const PROJECT_NAME: &str = "springfield-squidport";
+
+    pub fn test_opctx(cptestctx: &ControlPlaneTestContext) -> OpContext {
+        OpContext::for_tests(
+            cptestctx.logctx.log.new(o!()),
+            cptestctx.server.server_context().nexus.datastore().clone(),
+        )
+    }
+
+    async fn create_disk(cptestctx: &ControlPlaneTestContext) -> Disk {
+        let nexus = &cptestctx.server.server_context().nexus;
+        let opctx = test_opctx(&cptestctx);
+
+        let project_selector = params::ProjectSelector {
+            project: Name::try_from(PROJECT_NAME.to_string()).unwrap().into(),
+        };
+        let project_lookup =
+            nexus.project_lookup(&opctx, project_selector).unwrap();
+
+        nexus
+            .project_create_disk(
+                &opctx,
+                &project_lookup,
+                &crate::app::sagas::disk_create::test::new_disk_create_params(),
+            )
+            .await
+            .expect("Failed to create disk")
+    }
+
+    fn new_test_params(
+        opctx: &OpContext,
+        project_id: Uuid,
+        disk: &Disk,
+    ) -> Params {
+        Params {
+            serialized_authn: Serialized::for_opctx(opctx),
+            project_id,
+            disk_id: disk.id(),
+            volume_id: disk.volume_id(),
+            old_size: disk.size.into(),
+            new_size: ByteCount::from_gibibytes_u32(2),
+        }
+    }
+
+    async fn sub_volume_count(
+        cptestctx: &ControlPlaneTestContext,
+        disk: &Disk,
+    ) -> usize {
+        let datastore = cptestctx.server.server_context().nexus.datastore();
+        let volume =
+            datastore.volume_get(disk.volume_id()).await.unwrap().unwrap();
+        match serde_json::from_str(volume.data()).unwrap() {
+            VolumeConstructionRequest::Volume { sub_volumes, .. } => {
+                sub_volumes.len()
+            }
+            vcr => panic!("unexpected volume {vcr:?}"),
+        }
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_saga_basic_usage_succeeds(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        // Growing a disk needs a second set of regions on distinct zpools.
+        let _test =
+            DiskTestBuilder::new(cptestctx).with_zpool_count(6).build().await;
+
+        let client = &cptestctx.external_client;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let project_id = create_project(client, PROJECT_NAME).await.identity.id;
+        let disk = create_disk(&cptestctx).await;
+
+        let opctx = test_opctx(&cptestctx);
+        let params = new_test_params(&opctx, project_id, &disk);
+        let output =
+            nexus.sagas.saga_execute::<SagaDiskResize>(params).await.unwrap();
+        let resized =
+            output.lookup_node_output::<Disk>("resized_disk").unwrap();
+
+        assert_eq!(resized.size.to_bytes(), 2 * 1024 * 1024 * 1024);
+        assert_eq!(sub_volume_count(cptestctx, &disk).await, 2);
+        assert_eq!(
+            datastore
+                .get_allocated_regions(disk.volume_id())
+                .await
+                .unwrap()
+                .len(),
+            6,
+        );
+        let provisioned = datastore
+            .virtual_provisioning_collection_get(&opctx, project_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            provisioned.virtual_disk_bytes_provisioned.to_bytes(),
+            2 * 1024 * 1024 * 1024,
+        );
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_action_failure_can_unwind(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        let _test =
+            DiskTestBuilder::new(cptestctx).with_zpool_count(6).build().await;
+        let log = &cptestctx.logctx.log;
+
+        let client = &cptestctx.external_client;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let project_id = create_project(client, PROJECT_NAME).await.identity.id;
+        let disk = create_disk(&cptestctx).await;
+        let opctx = test_opctx(cptestctx);
+
+        crate::app::sagas::test_helpers::action_failure_can_unwind::<
+            SagaDiskResize,
+            _,
+            _,
+        >(
+            nexus,
+            || Box::pin(async { new_test_params(&opctx, project_id, &disk) }),
+            || {
+                Box::pin(async {
+                    crate::app::sagas::test_helpers::assert_no_failed_undo_steps(
+                        &cptestctx.logctx.log,
+                        datastore,
+                    )
+                    .await;
+
+                    let (.., db_disk) = nexus_db_lookup::LookupPath::new(
+                        &opctx, datastore,
+                    )
+                    .disk_id(disk.id())
+                    .fetch()
+                    .await
+                    .unwrap();
+                    assert_eq!(db_disk.size, disk.size);
+                    assert_eq!(sub_volume_count(cptestctx, &disk).await, 1);
+                    assert_eq!(
+                        datastore
+                            .get_allocated_regions(disk.volume_id())
+                            .await
+                            .unwrap()
+                            .len(),
+                        3,
+                    );
+                    let provisioned = datastore
+                        .virtual_provisioning_collection_get(&opctx, project_id)
+                        .await
+                        .unwrap();
+                    assert_eq!(
+                        provisioned.virtual_disk_bytes_provisioned,
+                        disk.size,
+                    );
+                })
+            },
+            log,
+        )
+        .await;
+    }
+}
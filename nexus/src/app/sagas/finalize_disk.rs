@@ -45,6 +45,9 @@ declare_saga_actions! {
     GET_PANTRY_ADDRESS -> "pantry_address" {
         + sfd_get_pantry_address
     }
+    SNAPSHOT_PARAMS -> "params_for_snapshot_subsaga" {
+        + sfd_snapshot_params
+    }
     CALL_PANTRY_DETACH_FOR_DISK -> "call_pantry_detach_for_disk" {
         + sfd_call_pantry_detach_for_disk
     }
@@ -68,68 +71,43 @@ impl NexusSaga for SagaFinalizeDisk {
 
     fn make_saga_dag(
         params: &Self::Params,
-        mut builder: steno::DagBuilder,
+        builder: steno::DagBuilder,
     ) -> Result<steno::Dag, SagaInitError> {
-        builder.append(set_finalizing_state_action());
-
-        builder.append(get_pantry_address_action());
-
-        if let Some(snapshot_name) = &params.snapshot_name {
-            let subsaga_params = snapshot_create::Params {
-                serialized_authn: params.serialized_authn.clone(),
-                silo_id: params.silo_id,
-                project_id: params.project_id,
-                disk_id: params.disk_id,
-                attach_instance_id: None,
-                use_the_pantry: true,
-                create_params: params::SnapshotCreate {
-                    identity: external::IdentityMetadataCreateParams {
-                        name: snapshot_name.clone(),
-                        description: format!(
-                            "snapshot of finalized disk {}",
-                            params.disk_id
-                        ),
-                    },
-                    disk: params.disk_id.into(),
-                },
-            };
-
-            let subsaga_dag = {
-                let subsaga_builder =
-                    steno::DagBuilder::new(steno::SagaName::new(
-                        snapshot_create::SagaSnapshotCreate::NAME,
-                    ));
-                snapshot_create::SagaSnapshotCreate::make_saga_dag(
-                    &subsaga_params,
-                    subsaga_builder,
-                )?
-            };
-
-            builder.append(Node::constant(
-                "params_for_snapshot_subsaga",
-                serde_json::to_value(&subsaga_params).map_err(|e| {
-                    SagaInitError::SerializeError(
-                        "params_for_snapshot_subsaga".to_string(),
-                        e,
-                    )
-                })?,
-            ));
-
-            builder.append(Node::subsaga(
-                "snapshot_subsaga_no_result",
-                subsaga_dag,
-                "params_for_snapshot_subsaga",
-            ));
-        }
+        create_dag(builder, params.snapshot_name.is_some())
+    }
+}
 
-        builder.append(call_pantry_detach_for_disk_action());
+/// Identical to [SagaFinalizeDisk::make_saga_dag], but using types to identify
+/// that parameters do not need to be supplied as input. Only whether or not a
+/// snapshot is taken affects the shape of the DAG.
+pub(crate) fn create_dag(
+    mut builder: steno::DagBuilder,
+    take_snapshot: bool,
+) -> Result<steno::Dag, SagaInitError> {
+    builder.append(set_finalizing_state_action());
+
+    builder.append(get_pantry_address_action());
+
+    if take_snapshot {
+        builder.append(snapshot_params_action());
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            snapshot_create::SagaSnapshotCreate::NAME,
+        ));
+        builder.append(Node::subsaga(
+            "snapshot_subsaga_no_result",
+            snapshot_create::create_dag(subsaga_builder, true, None)?,
+            "params_for_snapshot_subsaga",
+        ));
+    }
 
-        builder.append(clear_pantry_address_action());
+    builder.append(call_pantry_detach_for_disk_action());
 
-        builder.append(set_detached_state_action());
+    builder.append(clear_pantry_address_action());
 
-        Ok(builder.build()?)
-    }
+    builder.append(set_detached_state_action());
+
+    Ok(builder.build()?)
 }
 
 async fn sfd_set_finalizing_state(
@@ -280,6 +258,37 @@ async fn sfd_get_pantry_address(
     Ok(pantry_address)
 }
 
+async fn sfd_snapshot_params(
+    sagactx: NexusActionContext,
+) -> Result<snapshot_create::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    let snapshot_name = params.snapshot_name.ok_or_else(|| {
+        ActionError::action_failed(Error::internal_error(
+            "no snapshot name to finalize with",
+        ))
+    })?;
+
+    Ok(snapshot_create::Params {
+        serialized_authn: params.serialized_authn,
+        silo_id: params.silo_id,
+        project_id: params.project_id,
+        disk_id: params.disk_id,
+        attach_instance_id: None,
+        use_the_pantry: true,
+        create_params: params::SnapshotCreate {
+            identity: external::IdentityMetadataCreateParams {
+                name: snapshot_name,
+                description: format!(
+                    "snapshot of finalized disk {}",
+                    params.disk_id
+                ),
+            },
+            disk: params.disk_id.into(),
+        },
+        group_snapshot_id: None,
+    })
+}
+
 async fn sfd_call_pantry_detach_for_disk(
     sagactx: NexusActionContext,
 ) -> Result<(), ActionError> {
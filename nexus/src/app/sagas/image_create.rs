@@ -53,6 +53,10 @@ pub(crate) struct Params {
     pub serialized_authn: authn::saga::Serialized,
     pub image_type: ImageType,
     pub create_params: params::ImageCreate,
+    /// For images imported from a file, the URL the file came from (if any)
+    pub url: Option<String>,
+    /// For images imported from a file, the file's digest
+    pub digest: Option<external::Digest>,
 }
 
 // image create saga: actions
@@ -83,37 +87,50 @@ impl NexusSaga for SagaImageCreate {
 
     fn make_saga_dag(
         params: &Self::Params,
-        mut builder: steno::DagBuilder,
+        builder: steno::DagBuilder,
     ) -> Result<steno::Dag, SagaInitError> {
-        builder.append(Node::action(
-            "image_id",
-            "GenerateImageId",
-            ACTION_GENERATE_ID.as_ref(),
-        ));
+        let boot_alpine = match &params.create_params.source {
+            params::ImageSource::YouCanBootAnythingAsLongAsItsAlpine => true,
+
+            params::ImageSource::Snapshot { .. }
+            | params::ImageSource::Url { .. } => false,
+        };
+
+        create_dag(builder, boot_alpine)
+    }
+}
 
+/// Identical to [SagaImageCreate::make_saga_dag], but using types to identify
+/// that parameters do not need to be supplied as input. Only whether or not
+/// the image is the Alpine ISO affects the shape of the DAG.
+pub(crate) fn create_dag(
+    mut builder: steno::DagBuilder,
+    boot_alpine: bool,
+) -> Result<steno::Dag, SagaInitError> {
+    builder.append(Node::action(
+        "image_id",
+        "GenerateImageId",
+        ACTION_GENERATE_ID.as_ref(),
+    ));
+
+    builder.append(Node::action(
+        "dest_volume_id",
+        "GenerateDestVolumeId",
+        ACTION_GENERATE_ID.as_ref(),
+    ));
+
+    if boot_alpine {
         builder.append(Node::action(
-            "dest_volume_id",
-            "GenerateDestVolumeId",
+            "alpine_volume_id",
+            "GenerateAlpineVolumeId",
             ACTION_GENERATE_ID.as_ref(),
         ));
+    }
 
-        match &params.create_params.source {
-            params::ImageSource::Snapshot { .. } => {}
-
-            params::ImageSource::YouCanBootAnythingAsLongAsItsAlpine => {
-                builder.append(Node::action(
-                    "alpine_volume_id",
-                    "GenerateAlpineVolumeId",
-                    ACTION_GENERATE_ID.as_ref(),
-                ));
-            }
-        }
-
-        builder.append(get_source_volume_action());
-        builder.append(create_image_record_action());
+    builder.append(get_source_volume_action());
+    builder.append(create_image_record_action());
 
-        Ok(builder.build()?)
-    }
+    Ok(builder.build()?)
 }
 
 // image create saga: action implementations
@@ -218,6 +235,12 @@ async fn simc_get_source_volume(
 
             Ok(SourceVolume { volume_id: alpine_volume_id, block_size, size })
         }
+
+        // Images are imported from files by the image import saga, which
+        // creates them from a snapshot of the imported blocks.
+        params::ImageSource::Url { .. } => Err(ActionError::action_failed(
+            Error::internal_error("image source must be imported first"),
+        )),
     }
 }
 
@@ -248,23 +271,21 @@ async fn simc_create_image_record(
     let source_volume = sagactx.lookup::<SourceVolume>("source_volume")?;
 
     let record = match &params.create_params.source {
-        params::ImageSource::Snapshot { .. } => {
-            db::model::Image {
-                identity: db::model::ImageIdentity::new(
-                    image_id,
-                    params.create_params.identity.clone(),
-                ),
-                silo_id: params.image_type.silo_id(),
-                project_id: params.image_type.project_id(),
-                volume_id: source_volume.volume_id.into(),
-                url: None,
-                os: params.create_params.os.clone(),
-                version: params.create_params.version.clone(),
-                digest: None, // TODO
-                block_size: source_volume.block_size,
-                size: source_volume.size.into(),
-            }
-        }
+        params::ImageSource::Snapshot { .. } => db::model::Image {
+            identity: db::model::ImageIdentity::new(
+                image_id,
+                params.create_params.identity.clone(),
+            ),
+            silo_id: params.image_type.silo_id(),
+            project_id: params.image_type.project_id(),
+            volume_id: source_volume.volume_id.into(),
+            url: params.url.clone(),
+            os: params.create_params.os.clone(),
+            version: params.create_params.version.clone(),
+            digest: params.digest.clone().map(Into::into),
+            block_size: source_volume.block_size,
+            size: source_volume.size.into(),
+        },
 
         params::ImageSource::YouCanBootAnythingAsLongAsItsAlpine => {
             db::model::Image {
@@ -283,6 +304,12 @@ async fn simc_create_image_record(
                 size: source_volume.size.into(),
             }
         }
+
+        params::ImageSource::Url { .. } => {
+            return Err(ActionError::action_failed(Error::internal_error(
+                "image source must be imported first",
+            )));
+        }
     };
 
     match &params.image_type {
@@ -405,6 +432,8 @@ pub(crate) mod test {
                 os: "debian".to_string(),
                 version: "12".to_string(),
             },
+            url: None,
+            digest: None,
         }
     }
 
@@ -457,6 +486,8 @@ pub(crate) mod test {
                 os: "debian".to_string(),
                 version: "12".to_string(),
             },
+            url: None,
+            digest: None,
         };
 
         let output =
@@ -0,0 +1,665 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Create a project image from a raw or qcow2 image file
+//!
+//! Images are backed by a read-only copy of a snapshot's volume, so this saga
+//! goes the long way around: it creates an intermediate disk with the
+//! `disk_create` saga, attached to a Pantry for importing blocks, and copies
+//! the contents of the image file to it. It then finalizes the disk with the
+//! `finalize_disk` saga, taking a snapshot as it does, creates the image from
+//! that snapshot with the `image_create` saga, and deletes the intermediate
+//! snapshot and disk. Each of those runs as a subsaga, so if anything fails
+//! (like the file not matching its expected digest), unwinding them cleans up
+//! after the import.
+//!
+//! Deleting the snapshot and disk doesn't affect the image, whose volume
+//! holds references to the snapshot's region snapshots.
+
+use super::{ActionRegistry, NexusActionContext, NexusSaga, SagaInitError};
+use crate::app::sagas;
+use crate::app::sagas::declare_saga_actions;
+use crate::app::{authn, authz, db};
+use crate::external_api::params;
+use nexus_db_lookup::LookupPath;
+use nexus_db_queries::db::identity::Resource;
+use omicron_common::api::external;
+use omicron_common::api::external::ByteCount;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::Name;
+use serde::Deserialize;
+use serde::Serialize;
+use steno::ActionError;
+use steno::Node;
+use uuid::Uuid;
+
+// image import saga: input parameters
+
+/// Where the image file is read from
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) enum ImportSource {
+    /// Downloaded from a URL, which is fetched again if the import has to be
+    /// restarted
+    Url { url: String },
+    /// Uploaded in the request that started the import, and only available
+    /// to the Nexus that received it
+    Upload,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Params {
+    pub serialized_authn: authn::saga::Serialized,
+    pub authz_silo: authz::Silo,
+    pub authz_project: authz::Project,
+    /// Identifies this import, and names the intermediate disk and snapshot
+    pub import_id: Uuid,
+    pub source: ImportSource,
+    pub format: params::ImageImportFormat,
+    pub block_size: params::BlockSize,
+    /// The size of the intermediate disk, which is at least the size of the
+    /// image
+    pub disk_size: ByteCount,
+    pub expected_digest: Option<params::ExpectedDigest>,
+    pub identity: IdentityMetadataCreateParams,
+    pub os: String,
+    pub version: String,
+}
+
+impl Params {
+    /// The name of the intermediate disk and snapshot
+    fn import_name(&self) -> Name {
+        format!("image-import-{}", self.import_id)
+            .parse()
+            .expect("image import name is valid")
+    }
+}
+
+// image import saga: actions
+
+declare_saga_actions! {
+    image_import;
+    IMPORT_BLOCKS -> "image_digest" {
+        + sii_import_blocks
+    }
+    FINALIZE_DISK_PARAMS -> "finalize_disk_params" {
+        + sii_finalize_disk_params
+    }
+    IMAGE_CREATE_PARAMS -> "image_create_params" {
+        + sii_image_create_params
+    }
+    FETCH_CREATED_IMAGE -> "created_image" {
+        + sii_fetch_created_image
+    }
+    SNAPSHOT_DELETE_PARAMS -> "snapshot_delete_params" {
+        + sii_snapshot_delete_params
+    }
+    DISK_DELETE_PARAMS -> "disk_delete_params" {
+        + sii_disk_delete_params
+    }
+}
+
+// image import saga: definition
+
+#[derive(Debug)]
+pub(crate) struct SagaImageImport;
+impl NexusSaga for SagaImageImport {
+    const NAME: &'static str = "image-import";
+    type Params = Params;
+
+    fn register_actions(registry: &mut ActionRegistry) {
+        image_import_register_actions(registry);
+    }
+
+    fn make_saga_dag(
+        params: &Self::Params,
+        mut builder: steno::DagBuilder,
+    ) -> Result<steno::Dag, SagaInitError> {
+        // Create the intermediate disk, attached to a Pantry. Everything the
+        // disk create saga needs is known up front, so its parameters are a
+        // constant.
+        const DISK_CREATE_PARAMS: &'static str = "disk_create_params";
+
+        let disk_create_params = sagas::disk_create::Params {
+            serialized_authn: params.serialized_authn.clone(),
+            project_id: params.authz_project.id(),
+            create_params: params::DiskCreate {
+                identity: IdentityMetadataCreateParams {
+                    name: params.import_name(),
+                    description: format!(
+                        "internal disk for importing image {}",
+                        params.identity.name,
+                    ),
+                },
+                disk_source: params::DiskSource::ImportingBlocks {
+                    block_size: params.block_size,
+                },
+                size: params.disk_size,
+            },
+        };
+
+        builder.append(Node::constant(
+            DISK_CREATE_PARAMS,
+            serde_json::to_value(&disk_create_params).map_err(|e| {
+                SagaInitError::SerializeError(
+                    String::from(DISK_CREATE_PARAMS),
+                    e,
+                )
+            })?,
+        ));
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::disk_create::SagaDiskCreate::NAME,
+        ));
+        builder.append(Node::subsaga(
+            "disk_create",
+            sagas::disk_create::create_dag(subsaga_builder, true)?,
+            DISK_CREATE_PARAMS,
+        ));
+
+        builder.append(import_blocks_action());
+
+        // Take the snapshot the image is created from. The disk's ID isn't
+        // known until it has been created, so these parameters (and those of
+        // the subsagas that follow) come from actions.
+        builder.append(finalize_disk_params_action());
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::finalize_disk::SagaFinalizeDisk::NAME,
+        ));
+        builder.append(Node::subsaga(
+            "finalize_disk",
+            sagas::finalize_disk::create_dag(subsaga_builder, true)?,
+            "finalize_disk_params",
+        ));
+
+        builder.append(image_create_params_action());
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::image_create::SagaImageCreate::NAME,
+        ));
+        builder.append(Node::subsaga(
+            "image_create",
+            sagas::image_create::create_dag(subsaga_builder, false)?,
+            "image_create_params",
+        ));
+
+        builder.append(fetch_created_image_action());
+
+        // Garbage-collect the intermediate snapshot and disk. This comes last
+        // so that as little as possible can fail once they have started being
+        // deleted.
+        builder.append(snapshot_delete_params_action());
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::snapshot_delete::SagaSnapshotDelete::NAME,
+        ));
+        builder.append(Node::subsaga(
+            "snapshot_delete",
+            sagas::snapshot_delete::create_dag(subsaga_builder)?,
+            "snapshot_delete_params",
+        ));
+
+        builder.append(disk_delete_params_action());
+
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::disk_delete::SagaDiskDelete::NAME,
+        ));
+        builder.append(Node::subsaga(
+            "disk_delete",
+            sagas::disk_delete::create_dag(subsaga_builder)?,
+            "disk_delete_params",
+        ));
+
+        Ok(builder.build()?)
+    }
+}
+
+// image import saga: action implementations
+
+/// Looks up the intermediate disk created by the `disk_create` subsaga.
+async fn sii_lookup_disk(
+    sagactx: &NexusActionContext,
+    params: &Params,
+) -> Result<db::model::Disk, ActionError> {
+    let osagactx = sagactx.user_data();
+    let opctx = crate::context::op_context_for_saga_action(
+        sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., db_disk) = LookupPath::new(&opctx, osagactx.datastore())
+        .project_id(params.authz_project.id())
+        .disk_name_owned(params.import_name().into())
+        .fetch()
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    Ok(db_disk)
+}
+
+/// Looks up the intermediate snapshot created by the `finalize_disk`
+/// subsaga.
+async fn sii_lookup_snapshot(
+    sagactx: &NexusActionContext,
+    params: &Params,
+) -> Result<(authz::Snapshot, db::model::Snapshot), ActionError> {
+    let osagactx = sagactx.user_data();
+    let opctx = crate::context::op_context_for_saga_action(
+        sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., authz_snapshot, db_snapshot) =
+        LookupPath::new(&opctx, osagactx.datastore())
+            .project_id(params.authz_project.id())
+            .snapshot_name_owned(params.import_name().into())
+            .fetch()
+            .await
+            .map_err(ActionError::action_failed)?;
+
+    Ok((authz_snapshot, db_snapshot))
+}
+
+async fn sii_import_blocks(
+    sagactx: NexusActionContext,
+) -> Result<external::Digest, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let db_disk = sii_lookup_disk(&sagactx, &params).await?;
+
+    // If this action is repeated, the image is copied over whatever a
+    // previous attempt wrote.
+    let digest = osagactx
+        .nexus()
+        .image_import_copy(
+            params.import_id,
+            &params.source,
+            params.format,
+            params.block_size,
+            &db_disk,
+        )
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    if let Some(params::ExpectedDigest::Sha256(expected)) =
+        &params.expected_digest
+    {
+        if !expected.eq_ignore_ascii_case(&digest) {
+            return Err(ActionError::action_failed(Error::invalid_request(
+                format!(
+                    "image file has SHA-256 digest {digest}, but {expected} \
+                     was expected"
+                ),
+            )));
+        }
+    }
+
+    Ok(external::Digest::Sha256(digest))
+}
+
+async fn sii_finalize_disk_params(
+    sagactx: NexusActionContext,
+) -> Result<sagas::finalize_disk::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    let db_disk = sii_lookup_disk(&sagactx, &params).await?;
+
+    Ok(sagas::finalize_disk::Params {
+        serialized_authn: params.serialized_authn.clone(),
+        silo_id: params.authz_silo.id(),
+        project_id: params.authz_project.id(),
+        disk_id: db_disk.id(),
+        snapshot_name: Some(params.import_name()),
+    })
+}
+
+async fn sii_image_create_params(
+    sagactx: NexusActionContext,
+) -> Result<sagas::image_create::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    let digest = sagactx.lookup::<external::Digest>("image_digest")?;
+    let (authz_snapshot, _) = sii_lookup_snapshot(&sagactx, &params).await?;
+
+    let url = match &params.source {
+        ImportSource::Url { url } => Some(url.clone()),
+        ImportSource::Upload => None,
+    };
+
+    Ok(sagas::image_create::Params {
+        serialized_authn: params.serialized_authn,
+        image_type: sagas::image_create::ImageType::Project {
+            authz_silo: params.authz_silo,
+            authz_project: params.authz_project,
+        },
+        create_params: params::ImageCreate {
+            identity: params.identity,
+            os: params.os,
+            version: params.version,
+            source: params::ImageSource::Snapshot { id: authz_snapshot.id() },
+        },
+        url,
+        digest: Some(digest),
+    })
+}
+
+async fn sii_fetch_created_image(
+    sagactx: NexusActionContext,
+) -> Result<db::model::Image, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., db_image) = LookupPath::new(&opctx, osagactx.datastore())
+        .project_id(params.authz_project.id())
+        .project_image_name_owned(params.identity.name.into())
+        .fetch()
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    Ok(db_image.into())
+}
+
+async fn sii_snapshot_delete_params(
+    sagactx: NexusActionContext,
+) -> Result<sagas::snapshot_delete::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    let (authz_snapshot, db_snapshot) =
+        sii_lookup_snapshot(&sagactx, &params).await?;
+
+    Ok(sagas::snapshot_delete::Params {
+        serialized_authn: params.serialized_authn,
+        authz_snapshot,
+        snapshot: db_snapshot,
+    })
+}
+
+async fn sii_disk_delete_params(
+    sagactx: NexusActionContext,
+) -> Result<sagas::disk_delete::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    let db_disk = sii_lookup_disk(&sagactx, &params).await?;
+
+    Ok(sagas::disk_delete::Params {
+        serialized_authn: params.serialized_authn,
+        project_id: params.authz_project.id(),
+        disk_id: db_disk.id(),
+        volume_id: db_disk.volume_id(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::app::saga::create_saga_dag;
+    use crate::app::sagas::image_import::ImportSource;
+    use crate::app::sagas::image_import::Params;
+    use crate::app::sagas::image_import::SagaImageImport;
+    use crate::app::sagas::test_helpers;
+    use crate::external_api::params;
+    use async_bb8_diesel::AsyncRunQueryDsl;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use httptest::Expectation;
+    use httptest::matchers::request;
+    use httptest::responders::status_code;
+    use nexus_db_lookup::LookupPath;
+    use nexus_db_queries::authn::saga::Serialized;
+    use nexus_db_queries::authz;
+    use nexus_db_queries::context::OpContext;
+    use nexus_db_queries::db::datastore::DataStore;
+    use nexus_db_queries::db::identity::Resource;
+    use nexus_test_utils::resource_helpers::create_project;
+    use nexus_test_utils_macros::nexus_test;
+    use omicron_common::api::external::ByteCount;
+    use omicron_common::api::external::IdentityMetadataCreateParams;
+    use sha2::Digest;
+    use uuid::Uuid;
+
+    type ControlPlaneTestContext =
+        nexus_test_utils::ControlPlaneTestContext<crate::Server>;
+    type DiskTest<'a> =
+        nexus_test_utils::resource_helpers::DiskTest<'a, crate::Server>;
+
+    const PROJECT_NAME: &str = "springfield-squidport";
+    const IMAGE_NAME: &str = "imported-image";
+    const IMAGE_PATH: &str = "/alpine.raw";
+
+    /// Serves a small raw image file, returning the server and the SHA-256
+    /// digest of the file
+    fn serve_image() -> (httptest::Server, String) {
+        let contents: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+        let digest = hex::encode(sha2::Sha256::digest(&contents));
+
+        let server = httptest::Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", IMAGE_PATH))
+                .times(..)
+                .respond_with(status_code(200).body(contents)),
+        );
+        (server, digest)
+    }
+
+    async fn new_test_params(
+        cptestctx: &ControlPlaneTestContext,
+        opctx: &OpContext,
+        url: String,
+        expected_digest: Option<String>,
+    ) -> Params {
+        let nexus = &cptestctx.server.server_context().nexus;
+        let project =
+            create_project(&cptestctx.external_client, PROJECT_NAME).await;
+        let (authz_silo, authz_project) =
+            LookupPath::new(opctx, nexus.datastore())
+                .project_id(project.identity.id)
+                .lookup_for(authz::Action::CreateChild)
+                .await
+                .expect("Failed to look up created project");
+
+        Params {
+            serialized_authn: Serialized::for_opctx(opctx),
+            authz_silo,
+            authz_project,
+            import_id: Uuid::new_v4(),
+            source: ImportSource::Url { url },
+            format: params::ImageImportFormat::Raw,
+            block_size: params::BlockSize::try_from(512).unwrap(),
+            disk_size: ByteCount::from_gibibytes_u32(1),
+            expected_digest: expected_digest
+                .map(params::ExpectedDigest::Sha256),
+            identity: IdentityMetadataCreateParams {
+                name: IMAGE_NAME.parse().unwrap(),
+                description: String::from("an imported image"),
+            },
+            os: String::from("alpine"),
+            version: String::from("edge"),
+        }
+    }
+
+    async fn count_live_disks(datastore: &DataStore) -> i64 {
+        use nexus_db_schema::schema::disk::dsl;
+
+        dsl::disk
+            .filter(dsl::time_deleted.is_null())
+            .count()
+            .get_result_async(
+                &*datastore.pool_connection_for_tests().await.unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn count_live_snapshots(datastore: &DataStore) -> i64 {
+        use nexus_db_schema::schema::snapshot::dsl;
+
+        dsl::snapshot
+            .filter(dsl::time_deleted.is_null())
+            .count()
+            .get_result_async(
+                &*datastore.pool_connection_for_tests().await.unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn count_live_images(datastore: &DataStore) -> i64 {
+        use nexus_db_schema::schema::image::dsl;
+
+        dsl::image
+            .filter(dsl::time_deleted.is_null())
+            .count()
+            .get_result_async(
+                &*datastore.pool_connection_for_tests().await.unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_saga_basic_usage_succeeds(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let (server, digest) = serve_image();
+
+        let params = new_test_params(
+            cptestctx,
+            &opctx,
+            server.url_str(IMAGE_PATH),
+            Some(digest.clone()),
+        )
+        .await;
+        let output =
+            nexus.sagas.saga_execute::<SagaImageImport>(params).await.unwrap();
+        let image = output
+            .lookup_node_output::<nexus_db_queries::db::model::Image>(
+                "created_image",
+            )
+            .unwrap();
+        assert_eq!(image.name().as_str(), IMAGE_NAME);
+        assert_eq!(image.url, Some(server.url_str(IMAGE_PATH)));
+        assert_eq!(
+            image.digest.map(|d| d.to_string()),
+            Some(format!("sha256:{digest}"))
+        );
+
+        // The intermediate disk and snapshot have been cleaned up.
+        assert_eq!(count_live_images(datastore).await, 1);
+        assert_eq!(count_live_disks(datastore).await, 0);
+        assert_eq!(count_live_snapshots(datastore).await, 0);
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_saga_digest_mismatch_fails(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let (server, _) = serve_image();
+
+        let params = new_test_params(
+            cptestctx,
+            &opctx,
+            server.url_str(IMAGE_PATH),
+            Some("0".repeat(64)),
+        )
+        .await;
+        nexus
+            .sagas
+            .saga_execute::<SagaImageImport>(params)
+            .await
+            .expect_err("import with the wrong digest should fail");
+
+        assert_eq!(count_live_images(datastore).await, 0);
+        assert_eq!(count_live_disks(datastore).await, 0);
+        assert_eq!(count_live_snapshots(datastore).await, 0);
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_actions_succeed_idempotently(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let (server, digest) = serve_image();
+
+        let params = new_test_params(
+            cptestctx,
+            &opctx,
+            server.url_str(IMAGE_PATH),
+            Some(digest),
+        )
+        .await;
+        let dag = create_saga_dag::<SagaImageImport>(params).unwrap();
+        test_helpers::actions_succeed_idempotently(nexus, dag).await;
+
+        assert_eq!(count_live_images(datastore).await, 1);
+        assert_eq!(count_live_disks(datastore).await, 0);
+        assert_eq!(count_live_snapshots(datastore).await, 0);
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_action_failure_can_unwind(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let (server, _) = serve_image();
+        let params = new_test_params(
+            cptestctx,
+            &opctx,
+            server.url_str(IMAGE_PATH),
+            None,
+        )
+        .await;
+        let (authz_silo, authz_project) =
+            (params.authz_silo.clone(), params.authz_project.clone());
+
+        test_helpers::action_failure_can_unwind::<SagaImageImport, _, _>(
+            nexus,
+            || {
+                Box::pin(async {
+                    Params {
+                        serialized_authn: Serialized::for_opctx(&opctx),
+                        authz_silo: authz_silo.clone(),
+                        authz_project: authz_project.clone(),
+                        import_id: Uuid::new_v4(),
+                        source: params.source.clone(),
+                        format: params.format,
+                        block_size: params.block_size,
+                        disk_size: params.disk_size,
+                        expected_digest: None,
+                        identity: params.identity.clone(),
+                        os: params.os.clone(),
+                        version: params.version.clone(),
+                    }
+                })
+            },
+            || {
+                Box::pin(async {
+                    test_helpers::assert_no_failed_undo_steps(
+                        &cptestctx.logctx.log,
+                        datastore,
+                    )
+                    .await;
+
+                    assert_eq!(count_live_images(datastore).await, 0);
+                    assert_eq!(count_live_disks(datastore).await, 0);
+                    assert_eq!(count_live_snapshots(datastore).await, 0);
+                })
+            },
+            &cptestctx.logctx.log,
+        )
+        .await;
+    }
+}
@@ -2,7 +2,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use super::{ACTION_GENERATE_ID, NexusActionContext, NexusSaga};
+use super::{NexusActionContext, NexusSaga};
 use crate::app::instance::{
     InstanceEnsureRegisteredApiResources, InstanceRegisterReason,
     InstanceStateChangeError, InstanceStateChangeRequest,
@@ -13,7 +13,6 @@ use crate::app::sagas::{
 use nexus_db_lookup::LookupPath;
 use nexus_db_queries::db::identity::Resource;
 use nexus_db_queries::{authn, authz, db};
-use nexus_types::internal_api::params::InstanceMigrateRequest;
 use omicron_common::api::external::Error;
 use omicron_uuid_kinds::{GenericUuid, InstanceUuid, PropolisUuid, SledUuid};
 use serde::Deserialize;
@@ -32,7 +31,11 @@ pub struct Params {
     pub serialized_authn: authn::saga::Serialized,
     pub instance: db::model::Instance,
     pub src_vmm: db::model::Vmm,
-    pub migrate_params: InstanceMigrateRequest,
+    /// The ID of the migration record the saga creates
+    pub migration_id: Uuid,
+    /// The sled to migrate the instance to, or `None` to have the saga choose
+    /// one
+    pub dst_sled_id: Option<SledUuid>,
 }
 
 // The migration saga is similar to the instance start saga: get a destination
@@ -48,8 +51,8 @@ declare_saga_actions! {
 
     // In order to set up migration, the saga needs to construct the following:
     //
-    // - A migration ID and destination Propolis ID (added to the DAG inline as
-    //   ACTION_GENERATE_ID actions)
+    // - A migration ID (chosen by the caller, so that it can find the
+    //   migration record if the saga fails) and a destination Propolis ID
     // - A sled ID
     // - An IP address for the destination Propolis server
     //
@@ -143,13 +146,17 @@ impl NexusSaga for SagaInstanceMigrate {
     }
 
     fn make_saga_dag(
-        _params: &Self::Params,
+        params: &Self::Params,
         mut builder: steno::DagBuilder,
     ) -> Result<steno::Dag, super::SagaInitError> {
-        builder.append(Node::action(
+        builder.append(Node::constant(
             "migrate_id",
-            "GenerateMigrateId",
-            ACTION_GENERATE_ID.as_ref(),
+            serde_json::to_value(params.migration_id).map_err(|e| {
+                super::SagaInitError::SerializeError(
+                    String::from("migration_id"),
+                    e,
+                )
+            })?,
         ));
 
         builder.append(generate_propolis_id_action());
@@ -171,7 +178,8 @@ async fn sim_generate_propolis_id(
     Ok(PropolisUuid::new_v4())
 }
 
-/// Reserves resources for the destination on the specified target sled.
+/// Reserves resources for the destination on the requested target sled, or on
+/// whichever sled the allocator chooses.
 async fn sim_reserve_sled_resources(
     sagactx: NexusActionContext,
 ) -> Result<SledUuid, ActionError> {
@@ -179,11 +187,17 @@ async fn sim_reserve_sled_resources(
     let params = sagactx.saga_params::<Params>()?;
     let propolis_id = sagactx.lookup::<PropolisUuid>("dst_propolis_id")?;
 
-    // Add a constraint that requires the allocator to reserve on the
-    // migration's destination sled instead of a random sled.
-    let constraints = db::model::SledReservationConstraintBuilder::new()
-        .must_select_from(&[params.migrate_params.dst_sled_id])
-        .build();
+    // If the caller asked for a destination sled, require the allocator to
+    // reserve resources there. Otherwise it may choose any sled but the one
+    // the instance is already running on, applying the instance's affinity
+    // and anti-affinity groups just as it does when the instance starts.
+    let constraints = match params.dst_sled_id {
+        Some(dst_sled_id) => db::model::SledReservationConstraintBuilder::new()
+            .must_select_from(&[dst_sled_id.into_untyped_uuid()]),
+        None => db::model::SledReservationConstraintBuilder::new()
+            .cannot_select_from(&[params.src_vmm.sled_id]),
+    }
+    .build();
 
     let resource = super::instance_common::reserve_vmm_resources(
         osagactx.nexus(),
@@ -217,12 +231,9 @@ async fn sim_allocate_propolis_ip(
         &sagactx,
         &params.serialized_authn,
     );
-    allocate_vmm_ipv6(
-        &opctx,
-        sagactx.user_data().datastore(),
-        SledUuid::from_untyped_uuid(params.migrate_params.dst_sled_id),
-    )
-    .await
+    let dst_sled_id = sagactx.lookup::<SledUuid>("dst_sled_id")?;
+    allocate_vmm_ipv6(&opctx, sagactx.user_data().datastore(), dst_sled_id)
+        .await
 }
 
 async fn sim_create_migration_record(
@@ -644,9 +655,8 @@ mod tests {
             serialized_authn: authn::saga::Serialized::for_opctx(&opctx),
             instance: state.instance().clone(),
             src_vmm: vmm.clone(),
-            migrate_params: InstanceMigrateRequest {
-                dst_sled_id: dst_sled_id.into_untyped_uuid(),
-            },
+            migration_id: Uuid::new_v4(),
+            dst_sled_id: Some(dst_sled_id),
         };
 
         nexus
@@ -667,6 +677,51 @@ mod tests {
         );
     }
 
+    #[nexus_test(server = crate::Server, extra_sled_agents = 1)]
+    async fn test_saga_chooses_destination_sled(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        let client = &cptestctx.external_client;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let _project_id = setup_test_project(&client).await;
+
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let instance = create_instance(client).await;
+        let instance_id = InstanceUuid::from_untyped_uuid(instance.identity.id);
+
+        // Poke the instance to get it into the Running state.
+        test_helpers::instance_simulate(cptestctx, &instance_id).await;
+
+        let state = test_helpers::instance_fetch(cptestctx, instance_id).await;
+        let vmm = state.vmm().as_ref().unwrap();
+        let migration_id = Uuid::new_v4();
+        let params = Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(&opctx),
+            instance: state.instance().clone(),
+            src_vmm: vmm.clone(),
+            migration_id,
+            dst_sled_id: None,
+        };
+
+        nexus
+            .sagas
+            .saga_execute::<SagaInstanceMigrate>(params)
+            .await
+            .expect("Migration saga should succeed");
+
+        // The saga picked the only other sled, and created the migration
+        // record it was asked to.
+        let new_state =
+            test_helpers::instance_fetch_all(cptestctx, instance_id).await;
+        let target_vmm =
+            new_state.target_vmm.expect("instance should have a target VMM");
+        assert_ne!(target_vmm.sled_id, vmm.sled_id);
+        assert_eq!(
+            new_state.migration.expect("instance should be migrating").id,
+            migration_id
+        );
+    }
+
     #[nexus_test(server = crate::Server, extra_sled_agents = 1)]
     async fn test_action_failure_can_unwind(
         cptestctx: &ControlPlaneTestContext,
@@ -711,9 +766,8 @@ mod tests {
                         ),
                         instance: old_instance.clone(),
                         src_vmm: old_vmm.clone(),
-                        migrate_params: InstanceMigrateRequest {
-                            dst_sled_id: dst_sled_id.into_untyped_uuid(),
-                        },
+                        migration_id: Uuid::new_v4(),
+                        dst_sled_id: Some(dst_sled_id),
                     }
                 }
             })
@@ -351,6 +351,7 @@ use crate::app::db::model::ByteCount;
 use crate::app::db::model::Generation;
 use crate::app::db::model::InstanceRuntimeState;
 use crate::app::db::model::InstanceState;
+use crate::app::db::model::Migration;
 use crate::app::db::model::MigrationState;
 use crate::app::db::model::Vmm;
 use crate::app::db::model::VmmState;
@@ -485,6 +486,10 @@ struct UpdatesRequired {
     /// instance has moved to a new sled, or deleting them if it is no longer
     /// incarnated.
     network_config: Option<NetworkConfigUpdate>,
+
+    /// If this is [`Some`], the instance's migration has failed, and why it
+    /// failed must be recorded in its migration record.
+    failed_migration: Option<FailedMigration>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -493,6 +498,13 @@ enum NetworkConfigUpdate {
     Update { active_propolis_id: PropolisUuid, new_sled_id: Uuid },
 }
 
+/// A failed migration, and the reason it failed.
+#[derive(Debug, Deserialize, Serialize)]
+struct FailedMigration {
+    migration_id: Uuid,
+    reason: String,
+}
+
 /// Virtual provisioning counters to release when an instance no longer has a
 /// VMM.
 #[derive(Debug, Deserialize, Serialize)]
@@ -515,6 +527,7 @@ impl UpdatesRequired {
         let mut update_required = false;
         let mut active_vmm_failed = false;
         let mut network_config = None;
+        let mut failed_migration = None;
 
         // Has the active VMM been destroyed?
         let destroy_active_vmm =
@@ -571,6 +584,10 @@ impl UpdatesRequired {
                 new_runtime.migration_id = None;
                 new_runtime.dst_propolis_id = None;
                 update_required = true;
+                failed_migration = Some(FailedMigration {
+                    migration_id: migration.id,
+                    reason: migration_failure_reason(snapshot, migration),
+                });
                 // If the active VMM was destroyed, the network config must be
                 // deleted (which was determined above). Otherwise, if the
                 // migration failed but the active VMM was still there, we must
@@ -715,10 +732,41 @@ impl UpdatesRequired {
             destroy_target_vmm,
             deprovision,
             network_config,
+            failed_migration,
         })
     }
 }
 
+/// Describes why `migration` failed, from which of its sides reported the
+/// failure and what has become of the VMM on that side.
+fn migration_failure_reason(
+    snapshot: &InstanceGestalt,
+    migration: &Migration,
+) -> String {
+    let describe = |side: &str, vmm_id: Uuid| {
+        let vmm = [&snapshot.active_vmm, &snapshot.target_vmm]
+            .into_iter()
+            .flatten()
+            .find(|vmm| vmm.id == vmm_id);
+        match vmm.map(|vmm| vmm.runtime.state) {
+            Some(VmmState::Failed) => format!("the {side} VMM failed"),
+            Some(VmmState::Destroyed | VmmState::SagaUnwound) => format!(
+                "the {side} VMM was destroyed before the migration finished"
+            ),
+            _ => format!("the migration failed on the {side} sled"),
+        }
+    };
+
+    let mut reasons = Vec::new();
+    if migration.source_state == MigrationState::FAILED {
+        reasons.push(describe("source", migration.source_propolis_id));
+    }
+    if migration.target_state == MigrationState::FAILED {
+        reasons.push(describe("destination", migration.target_propolis_id));
+    }
+    reasons.join("; ")
+}
+
 impl NetworkConfigUpdate {
     fn to_vmm(vmm: &Vmm) -> Self {
         Self::Update {
@@ -1154,6 +1202,20 @@ async fn siu_commit_instance_updates(
 
     let instance_id = authz_instance.id();
 
+    // Record why the migration failed before the instance is unlocked, so
+    // that the reason is in place by the time another migration can start.
+    if let Some(ref failed) = update.failed_migration {
+        osagactx
+            .datastore()
+            .migration_set_failure_reason(
+                &opctx,
+                failed.migration_id,
+                &failed.reason,
+            )
+            .await
+            .map_err(ActionError::action_failed)?;
+    }
+
     debug!(
         log,
         "instance update: committing new runtime state and unlocking...";
@@ -1484,7 +1546,7 @@ mod test {
         create_default_ip_pool, create_project, object_create,
     };
     use nexus_test_utils_macros::nexus_test;
-    use nexus_types::internal_api::params::InstanceMigrateRequest;
+    use omicron_common::api::external::DataPageParams;
     use omicron_common::api::internal::nexus::{
         MigrationRuntimeState, MigrationState, Migrations,
     };
@@ -2447,9 +2509,8 @@ mod test {
                 serialized_authn: authn::saga::Serialized::for_opctx(&opctx),
                 instance: state.instance().clone(),
                 src_vmm: vmm.clone(),
-                migrate_params: InstanceMigrateRequest {
-                    dst_sled_id: dst_sled_id.into_untyped_uuid(),
-                },
+                migration_id: Uuid::new_v4(),
+                dst_sled_id: Some(dst_sled_id),
             };
 
             nexus
@@ -2688,6 +2749,41 @@ mod test {
                 .map(|&(_, state)| state)
                 .unwrap_or(VmmState::Migrating);
 
+            // If either side of the migration failed, the update saga must
+            // have recorded why.
+            let migration_id = self
+                .initial_state
+                .instance
+                .runtime()
+                .migration_id
+                .expect("migrating instance must have a migration ID");
+            let migration = cptestctx
+                .server
+                .server_context()
+                .nexus
+                .datastore()
+                .instance_list_migrations(
+                    &self.opctx,
+                    self.instance_id,
+                    &DataPageParams::max_page(),
+                )
+                .await
+                .expect("failed to list migrations")
+                .into_iter()
+                .find(|migration| migration.id == migration_id)
+                .expect("migration record must exist");
+            let migration_failed = [&self.outcome.source, &self.outcome.target]
+                .into_iter()
+                .flatten()
+                .any(|(state, _)| *state == MigrationState::Failed);
+            assert_eq!(
+                migration.failure_reason.is_some(),
+                migration_failed,
+                "a failure reason must be recorded if and only if the \
+                 migration failed; got {:?}",
+                migration.failure_reason,
+            );
+
             if self.outcome.failed {
                 assert_eq!(
                     instance_runtime.migration_id, None,
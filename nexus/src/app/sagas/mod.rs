@@ -23,11 +23,14 @@ use thiserror::Error;
 use uuid::Uuid;
 
 pub mod demo;
+pub mod disk_clone;
 pub mod disk_create;
 pub mod disk_delete;
+pub mod disk_resize;
 pub mod finalize_disk;
 pub mod image_create;
 pub mod image_delete;
+pub mod image_import;
 pub(crate) mod instance_common;
 pub mod instance_create;
 pub mod instance_delete;
@@ -47,6 +50,10 @@ pub mod region_snapshot_replacement_step;
 pub mod region_snapshot_replacement_step_garbage_collect;
 pub mod snapshot_create;
 pub mod snapshot_delete;
+pub mod snapshot_export_create;
+pub mod snapshot_export_delete;
+pub mod snapshot_group_create;
+pub mod snapshot_group_restore;
 pub mod test_saga;
 pub mod volume_delete;
 pub mod volume_remove_rop;
@@ -156,8 +163,10 @@ fn make_action_registry() -> ActionRegistry {
     register_actions! [
         registry,
         demo::SagaDemo,
+        disk_clone::SagaDiskClone,
         disk_create::SagaDiskCreate,
         disk_delete::SagaDiskDelete,
+        disk_resize::SagaDiskResize,
         finalize_disk::SagaFinalizeDisk,
         instance_create::SagaInstanceCreate,
         instance_delete::SagaInstanceDelete,
@@ -169,6 +178,10 @@ fn make_action_registry() -> ActionRegistry {
         project_create::SagaProjectCreate,
         snapshot_create::SagaSnapshotCreate,
         snapshot_delete::SagaSnapshotDelete,
+        snapshot_export_create::SagaSnapshotExportCreate,
+        snapshot_export_delete::SagaSnapshotExportDelete,
+        snapshot_group_create::SagaSnapshotGroupCreate,
+        snapshot_group_restore::SagaSnapshotGroupRestore,
         volume_delete::SagaVolumeDelete,
         volume_remove_rop::SagaVolumeRemoveROP,
         vpc_create::SagaVpcCreate,
@@ -184,7 +197,8 @@ fn make_action_registry() -> ActionRegistry {
         region_snapshot_replacement_step::SagaRegionSnapshotReplacementStep,
         region_snapshot_replacement_step_garbage_collect::SagaRegionSnapshotReplacementStepGarbageCollect,
         region_snapshot_replacement_finish::SagaRegionSnapshotReplacementFinish,
-        image_create::SagaImageCreate
+        image_create::SagaImageCreate,
+        image_import::SagaImageImport
     ];
 
     #[cfg(test)]
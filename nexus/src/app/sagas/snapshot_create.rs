@@ -138,6 +138,10 @@ pub(crate) struct Params {
     pub attach_instance_id: Option<Uuid>,
     pub use_the_pantry: bool,
     pub create_params: params::SnapshotCreate,
+    /// Set if the snapshot is one of a snapshot group's, to the ID the group
+    /// chose for it
+    #[serde(default)]
+    pub group_snapshot_id: Option<Uuid>,
 }
 
 // snapshot create saga: actions
@@ -229,93 +233,115 @@ impl NexusSaga for SagaSnapshotCreate {
 
     fn make_saga_dag(
         params: &Self::Params,
-        mut builder: steno::DagBuilder,
+        builder: steno::DagBuilder,
     ) -> Result<steno::Dag, SagaInitError> {
-        // Generate IDs
-        builder.append(Node::action(
-            "snapshot_id",
-            "GenerateSnapshotId",
-            ACTION_GENERATE_ID.as_ref(),
-        ));
-
-        builder.append(Node::action(
-            "volume_id",
-            "GenerateVolumeId",
-            ACTION_GENERATE_ID.as_ref(),
-        ));
-
-        builder.append(Node::action(
-            "destination_volume_id",
-            "GenerateDestinationVolumeId",
-            ACTION_GENERATE_ID.as_ref(),
-        ));
-
-        builder.append(Node::action(
-            "lock_id",
-            "GenerateLockId",
-            ACTION_GENERATE_ID.as_ref(),
-        ));
-
-        builder.append(take_volume_lock_action());
-
-        // (DB) Allocate region space for snapshot to store blocks post-scrub
-        builder.append(regions_alloc_action());
-        // (Sleds) Reaches out to each dataset, and ensures the regions exist
-        // for the destination volume
-        builder.append(regions_ensure_undo_action());
-        builder.append(regions_ensure_action());
-        // (DB) Creates a record of the destination volume in the DB
-        builder.append(create_destination_volume_record_action());
-        // (DB) Creates a record of the snapshot, referencing both the
-        // original disk ID and the destination volume
-        builder.append(create_snapshot_record_action());
-        // (DB) Tracks virtual resource provisioning.
-        builder.append(space_account_action());
-
-        if !params.use_the_pantry {
-            // (Sleds) If the disk is attached to an instance, send a
-            // snapshot request to sled-agent to create a ZFS snapshot.
-            builder.append(send_snapshot_request_to_sled_agent_action());
-        } else {
-            // (Pantry) Record the address of a Pantry service
-            builder.append(get_pantry_address_action());
+        create_dag(builder, params.use_the_pantry, params.group_snapshot_id)
+    }
+}
 
-            // (Pantry) If the disk is _not_ attached to an instance:
-            // "attach" the disk to the pantry
-            builder.append(attach_disk_to_pantry_action());
+/// Identical to [SagaSnapshotCreate::make_saga_dag], but using types to
+/// identify that parameters do not need to be supplied as input. Only whether
+/// or not the Pantry is used, and the snapshot's ID if it's part of a group,
+/// affect the shape of the DAG.
+pub(crate) fn create_dag(
+    mut builder: steno::DagBuilder,
+    use_the_pantry: bool,
+    group_snapshot_id: Option<Uuid>,
+) -> Result<steno::Dag, SagaInitError> {
+    // Generate IDs
+    match group_snapshot_id {
+        Some(snapshot_id) => {
+            builder.append(Node::constant(
+                "snapshot_id",
+                serde_json::json!(snapshot_id),
+            ));
+        }
+        None => {
+            builder.append(Node::action(
+                "snapshot_id",
+                "GenerateSnapshotId",
+                ACTION_GENERATE_ID.as_ref(),
+            ));
+        }
+    }
 
-            // (Pantry) Call the Pantry's /attach
-            builder.append(call_pantry_attach_for_disk_action());
+    builder.append(Node::action(
+        "volume_id",
+        "GenerateVolumeId",
+        ACTION_GENERATE_ID.as_ref(),
+    ));
+
+    builder.append(Node::action(
+        "destination_volume_id",
+        "GenerateDestinationVolumeId",
+        ACTION_GENERATE_ID.as_ref(),
+    ));
+
+    builder.append(Node::action(
+        "lock_id",
+        "GenerateLockId",
+        ACTION_GENERATE_ID.as_ref(),
+    ));
+
+    builder.append(take_volume_lock_action());
+
+    // (DB) Allocate region space for snapshot to store blocks post-scrub
+    builder.append(regions_alloc_action());
+    // (Sleds) Reaches out to each dataset, and ensures the regions exist
+    // for the destination volume
+    builder.append(regions_ensure_undo_action());
+    builder.append(regions_ensure_action());
+    // (DB) Creates a record of the destination volume in the DB
+    builder.append(create_destination_volume_record_action());
+    // (DB) Creates a record of the snapshot, referencing both the
+    // original disk ID and the destination volume
+    builder.append(create_snapshot_record_action());
+    // (DB) Tracks virtual resource provisioning.
+    builder.append(space_account_action());
+
+    if !use_the_pantry {
+        // (Sleds) If the disk is attached to an instance, send a
+        // snapshot request to sled-agent to create a ZFS snapshot.
+        builder.append(send_snapshot_request_to_sled_agent_action());
+    } else {
+        // (Pantry) Record the address of a Pantry service
+        builder.append(get_pantry_address_action());
 
-            // (Pantry) Call the Pantry's /snapshot
-            builder.append(call_pantry_snapshot_for_disk_action());
+        // (Pantry) If the disk is _not_ attached to an instance:
+        // "attach" the disk to the pantry
+        builder.append(attach_disk_to_pantry_action());
 
-            // (Pantry) Call the Pantry's /detach
-            builder.append(call_pantry_detach_for_disk_action());
-        }
+        // (Pantry) Call the Pantry's /attach
+        builder.append(call_pantry_attach_for_disk_action());
 
-        // (Sleds + DB) Start snapshot downstairs, add an entry in the DB for
-        // the dataset's snapshot.
-        builder.append(start_running_snapshot_undo_action());
-        builder.append(start_running_snapshot_action());
-        // (DB) Copy and modify the disk volume construction request to point
-        // to the new running snapshot
-        builder.append(create_volume_record_action());
-        // (DB) Mark snapshot as "ready"
-        builder.append(finalize_snapshot_record_action());
-
-        if params.use_the_pantry {
-            // (Pantry) Set the state back to Detached
-            //
-            // This has to be the last saga node! Otherwise, concurrent
-            // operation on this disk is possible.
-            builder.append(detach_disk_from_pantry_action());
-        }
+        // (Pantry) Call the Pantry's /snapshot
+        builder.append(call_pantry_snapshot_for_disk_action());
 
-        builder.append(release_volume_lock_action());
+        // (Pantry) Call the Pantry's /detach
+        builder.append(call_pantry_detach_for_disk_action());
+    }
 
-        Ok(builder.build()?)
+    // (Sleds + DB) Start snapshot downstairs, add an entry in the DB for
+    // the dataset's snapshot.
+    builder.append(start_running_snapshot_undo_action());
+    builder.append(start_running_snapshot_action());
+    // (DB) Copy and modify the disk volume construction request to point
+    // to the new running snapshot
+    builder.append(create_volume_record_action());
+    // (DB) Mark snapshot as "ready"
+    builder.append(finalize_snapshot_record_action());
+
+    if use_the_pantry {
+        // (Pantry) Set the state back to Detached
+        //
+        // This has to be the last saga node! Otherwise, concurrent
+        // operation on this disk is possible.
+        builder.append(detach_disk_from_pantry_action());
     }
+
+    builder.append(release_volume_lock_action());
+
+    Ok(builder.build()?)
 }
 
 // snapshot create saga: action implementations
@@ -716,12 +742,22 @@ async fn ssc_create_snapshot_record_undo(
     let snapshot_id = sagactx.lookup::<Uuid>("snapshot_id")?;
     info!(log, "deleting snapshot {}", snapshot_id);
 
+    // When this saga runs as part of the disk clone saga, the snapshot may
+    // have already been deleted by that saga's `snapshot_delete` subsaga
+    // before the unwind started.
     let (.., authz_snapshot, db_snapshot) =
-        LookupPath::new(&opctx, osagactx.datastore())
+        match LookupPath::new(&opctx, osagactx.datastore())
             .snapshot_id(snapshot_id)
             .fetch_for(authz::Action::Delete)
             .await
-            .map_err(ActionError::action_failed)?;
+        {
+            Ok(result) => result,
+            Err(Error::ObjectNotFound { .. }) => {
+                info!(log, "snapshot {} already deleted", snapshot_id);
+                return Ok(());
+            }
+            Err(e) => return Err(ActionError::action_failed(e).into()),
+        };
 
     osagactx
         .datastore()
@@ -2027,6 +2063,7 @@ mod test {
                 },
                 disk,
             },
+            group_snapshot_id: None,
         }
     }
 
@@ -9,7 +9,6 @@ use nexus_db_queries::{authn, authz, db};
 use serde::Deserialize;
 use serde::Serialize;
 use steno::ActionError;
-use steno::Node;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) struct Params {
@@ -26,6 +25,12 @@ declare_saga_actions! {
     SPACE_ACCOUNT -> "no_result2" {
         + ssd_account_space
     }
+    DELETE_VOLUME_PARAMS -> "delete_volume_params" {
+        + ssd_delete_volume_params
+    }
+    DELETE_VOLUME_DESTINATION_PARAMS -> "delete_volume_destination_params" {
+        + ssd_delete_volume_destination_params
+    }
     NOOP -> "no_result3" {
         + ssd_noop
     }
@@ -42,68 +47,50 @@ impl NexusSaga for SagaSnapshotDelete {
     }
 
     fn make_saga_dag(
-        params: &Self::Params,
-        mut builder: steno::DagBuilder,
+        _params: &Self::Params,
+        builder: steno::DagBuilder,
     ) -> Result<steno::Dag, super::SagaInitError> {
-        builder.append(delete_snapshot_record_action());
-        builder.append(space_account_action());
-
-        const DELETE_VOLUME_PARAMS: &'static str = "delete_volume_params";
-        const DELETE_VOLUME_DESTINATION_PARAMS: &'static str =
-            "delete_volume_destination_params";
-
-        let volume_delete_params = sagas::volume_delete::Params {
-            serialized_authn: params.serialized_authn.clone(),
-            volume_id: params.snapshot.volume_id(),
-        };
-        builder.append(Node::constant(
-            DELETE_VOLUME_PARAMS,
-            serde_json::to_value(&volume_delete_params).map_err(|e| {
-                super::SagaInitError::SerializeError(
-                    String::from("volume_id"),
-                    e,
-                )
-            })?,
-        ));
+        create_dag(builder)
+    }
+}
 
-        let volume_delete_params = sagas::volume_delete::Params {
-            serialized_authn: params.serialized_authn.clone(),
-            volume_id: params.snapshot.destination_volume_id(),
-        };
-        builder.append(Node::constant(
-            DELETE_VOLUME_DESTINATION_PARAMS,
-            serde_json::to_value(&volume_delete_params).map_err(|e| {
-                super::SagaInitError::SerializeError(
-                    String::from("destination_volume_id"),
-                    e,
-                )
-            })?,
+/// Identical to [SagaSnapshotDelete::make_saga_dag], but using types
+/// to identify that parameters do not need to be supplied as input.
+pub(crate) fn create_dag(
+    mut builder: steno::DagBuilder,
+) -> Result<steno::Dag, super::SagaInitError> {
+    builder.append(delete_snapshot_record_action());
+    builder.append(space_account_action());
+
+    // The volume delete parameters are produced by actions (rather than
+    // constants) so that this saga can be used as a subsaga of a saga that
+    // does not yet know which snapshot it will delete when it is built.
+    builder.append(delete_volume_params_action());
+    builder.append(delete_volume_destination_params_action());
+
+    let make_volume_delete_dag = || {
+        let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+            sagas::volume_delete::SagaVolumeDelete::NAME,
         ));
-
-        let make_volume_delete_dag = || {
-            let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
-                sagas::volume_delete::SagaVolumeDelete::NAME,
-            ));
-            sagas::volume_delete::create_dag(subsaga_builder)
-        };
-
-        builder.append_parallel(vec![
-            steno::Node::subsaga(
-                "delete_volume",
-                make_volume_delete_dag()?,
-                DELETE_VOLUME_PARAMS,
-            ),
-            steno::Node::subsaga(
-                "delete_destination_volume",
-                make_volume_delete_dag()?,
-                DELETE_VOLUME_DESTINATION_PARAMS,
-            ),
-        ]);
-
-        builder.append(noop_action());
-
-        Ok(builder.build()?)
-    }
+        sagas::volume_delete::create_dag(subsaga_builder)
+    };
+
+    builder.append_parallel(vec![
+        steno::Node::subsaga(
+            "delete_volume",
+            make_volume_delete_dag()?,
+            "delete_volume_params",
+        ),
+        steno::Node::subsaga(
+            "delete_destination_volume",
+            make_volume_delete_dag()?,
+            "delete_volume_destination_params",
+        ),
+    ]);
+
+    builder.append(noop_action());
+
+    Ok(builder.build()?)
 }
 
 // snapshot delete saga: action implementations
@@ -157,6 +144,26 @@ async fn ssd_account_space(
     Ok(())
 }
 
+async fn ssd_delete_volume_params(
+    sagactx: NexusActionContext,
+) -> Result<sagas::volume_delete::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    Ok(sagas::volume_delete::Params {
+        serialized_authn: params.serialized_authn,
+        volume_id: params.snapshot.volume_id(),
+    })
+}
+
+async fn ssd_delete_volume_destination_params(
+    sagactx: NexusActionContext,
+) -> Result<sagas::volume_delete::Params, ActionError> {
+    let params = sagactx.saga_params::<Params>()?;
+    Ok(sagas::volume_delete::Params {
+        serialized_authn: params.serialized_authn,
+        volume_id: params.snapshot.destination_volume_id(),
+    })
+}
+
 // Sagas must end in one node, not parallel
 async fn ssd_noop(_sagactx: NexusActionContext) -> Result<(), ActionError> {
     Ok(())
@@ -0,0 +1,398 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Attach a read-only copy of a snapshot to a Pantry so it can be exported
+//!
+//! The export reads from its own copy of the snapshot's volume, checked out
+//! with randomized ids, so that the snapshot itself is never attached
+//! anywhere. The copy stays attached to the Pantry until the export is
+//! deleted by the `snapshot_export_delete` saga.
+
+use super::{
+    ACTION_GENERATE_ID, ActionRegistry, NexusActionContext, NexusSaga,
+    SagaInitError,
+    common_storage::{
+        call_pantry_attach_for_volume, call_pantry_detach, get_pantry_address,
+    },
+};
+use crate::app::sagas::declare_saga_actions;
+use crate::app::{authn, authz, db};
+use anyhow::anyhow;
+use crucible_pantry_client::types::VolumeConstructionRequest;
+use omicron_common::api::external;
+use omicron_common::api::external::Error;
+use omicron_common::progenitor_operation_retry::ProgenitorOperationRetryError;
+use omicron_uuid_kinds::VolumeUuid;
+use serde::Deserialize;
+use serde::Serialize;
+use slog_error_chain::InlineErrorChain;
+use std::net::SocketAddrV6;
+use steno::ActionError;
+use steno::Node;
+use uuid::Uuid;
+
+// snapshot export create saga: input parameters
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Params {
+    pub serialized_authn: authn::saga::Serialized,
+    pub authz_snapshot: authz::Snapshot,
+    pub snapshot: db::model::Snapshot,
+    pub format: db::model::SnapshotExportFormat,
+    /// Size of the exported image, which depends on the format
+    pub size: external::ByteCount,
+}
+
+// snapshot export create saga: actions
+
+declare_saga_actions! {
+    snapshot_export_create;
+    CHECKOUT_VOLUME -> "no_result1" {
+        + ssec_checkout_volume
+        - ssec_checkout_volume_undo
+    }
+    GET_PANTRY_ADDRESS -> "pantry_address" {
+        + ssec_get_pantry_address
+    }
+    ATTACH_TO_PANTRY -> "no_result2" {
+        + ssec_attach_to_pantry
+        - ssec_attach_to_pantry_undo
+    }
+    CREATE_EXPORT_RECORD -> "export" {
+        + ssec_create_export_record
+        - ssec_create_export_record_undo
+    }
+}
+
+// snapshot export create saga: definition
+
+#[derive(Debug)]
+pub(crate) struct SagaSnapshotExportCreate;
+impl NexusSaga for SagaSnapshotExportCreate {
+    const NAME: &'static str = "snapshot-export-create";
+    type Params = Params;
+
+    fn register_actions(registry: &mut ActionRegistry) {
+        snapshot_export_create_register_actions(registry);
+    }
+
+    fn make_saga_dag(
+        _params: &Self::Params,
+        mut builder: steno::DagBuilder,
+    ) -> Result<steno::Dag, SagaInitError> {
+        builder.append(Node::action(
+            "export_id",
+            "GenerateExportId",
+            ACTION_GENERATE_ID.as_ref(),
+        ));
+
+        builder.append(Node::action(
+            "volume_id",
+            "GenerateVolumeId",
+            ACTION_GENERATE_ID.as_ref(),
+        ));
+
+        builder.append(checkout_volume_action());
+        builder.append(get_pantry_address_action());
+        builder.append(attach_to_pantry_action());
+        builder.append(create_export_record_action());
+
+        Ok(builder.build()?)
+    }
+}
+
+// snapshot export create saga: action implementations
+
+async fn ssec_checkout_volume(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let volume_id = sagactx.lookup::<VolumeUuid>("volume_id")?;
+
+    // Copy the Volume data for this snapshot with randomized ids - this is
+    // safe because the snapshot is read-only, and it means the Pantry never
+    // activates the snapshot's own volume.
+    osagactx
+        .datastore()
+        .volume_checkout_randomize_ids(
+            db::datastore::SourceVolume(params.snapshot.volume_id()),
+            db::datastore::DestVolume(volume_id),
+            db::datastore::VolumeCheckoutReason::ReadOnlyCopy,
+        )
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    Ok(())
+}
+
+async fn ssec_checkout_volume_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let volume_id = sagactx.lookup::<VolumeUuid>("volume_id")?;
+
+    osagactx.datastore().soft_delete_volume(volume_id).await?;
+
+    Ok(())
+}
+
+async fn ssec_get_pantry_address(
+    sagactx: NexusActionContext,
+) -> Result<SocketAddrV6, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+
+    let pantry_address = get_pantry_address(osagactx.nexus()).await?;
+
+    info!(
+        osagactx.log(),
+        "using pantry at {} for export of snapshot {}",
+        pantry_address,
+        params.authz_snapshot.id(),
+    );
+
+    Ok(pantry_address)
+}
+
+async fn ssec_attach_to_pantry(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let export_id = sagactx.lookup::<Uuid>("export_id")?;
+    let volume_id = sagactx.lookup::<VolumeUuid>("volume_id")?;
+    let pantry_address = sagactx.lookup::<SocketAddrV6>("pantry_address")?;
+
+    let volume = osagactx
+        .datastore()
+        .volume_get(volume_id)
+        .await
+        .map_err(ActionError::action_failed)?
+        .ok_or_else(|| {
+            ActionError::action_failed(Error::internal_error(&format!(
+                "volume {volume_id} not found"
+            )))
+        })?;
+
+    let volume_construction_request: VolumeConstructionRequest =
+        serde_json::from_str(&volume.data()).map_err(|e| {
+            ActionError::action_failed(Error::internal_error(&format!(
+                "failed to deserialize volume {volume_id} data: {e}"
+            )))
+        })?;
+
+    // The volume is attached under the export's id, which is what the
+    // download path uses to read from it.
+    call_pantry_attach_for_volume(
+        &log,
+        osagactx.nexus(),
+        export_id,
+        volume_construction_request,
+        pantry_address,
+    )
+    .await
+}
+
+async fn ssec_attach_to_pantry_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let log = osagactx.log();
+    let export_id = sagactx.lookup::<Uuid>("export_id")?;
+    let pantry_address = sagactx.lookup::<SocketAddrV6>("pantry_address")?;
+
+    match call_pantry_detach(osagactx.nexus(), &log, export_id, pantry_address)
+        .await
+    {
+        // We can treat the pantry being permanently gone as success.
+        Ok(()) | Err(ProgenitorOperationRetryError::Gone) => Ok(()),
+        Err(err) => Err(anyhow!(
+            "failed to detach export {} from pantry at {}: {}",
+            export_id,
+            pantry_address,
+            InlineErrorChain::new(&err)
+        )),
+    }
+}
+
+async fn ssec_create_export_record(
+    sagactx: NexusActionContext,
+) -> Result<db::model::SnapshotExport, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let export_id = sagactx.lookup::<Uuid>("export_id")?;
+    let volume_id = sagactx.lookup::<VolumeUuid>("volume_id")?;
+    let pantry_address = sagactx.lookup::<SocketAddrV6>("pantry_address")?;
+
+    let export = db::model::SnapshotExport::new(
+        export_id,
+        params.authz_snapshot.id(),
+        volume_id,
+        pantry_address,
+        params.format,
+        params.size.into(),
+    );
+
+    osagactx
+        .datastore()
+        .snapshot_export_create(&opctx, &params.authz_snapshot, export)
+        .await
+        .map_err(ActionError::action_failed)
+}
+
+async fn ssec_create_export_record_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let export_id = sagactx.lookup::<Uuid>("export_id")?;
+
+    osagactx
+        .datastore()
+        .snapshot_export_delete(&opctx, &params.authz_snapshot, export_id)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::app::saga::create_saga_dag;
+    use crate::app::sagas::snapshot_export_create::Params;
+    use crate::app::sagas::snapshot_export_create::SagaSnapshotExportCreate;
+    use crate::app::sagas::test_helpers;
+    use nexus_db_lookup::LookupPath;
+    use nexus_db_queries::authn::saga::Serialized;
+    use nexus_db_queries::authz;
+    use nexus_db_queries::context::OpContext;
+    use nexus_db_queries::db;
+    use nexus_db_queries::db::model::SnapshotExportFormat;
+    use nexus_test_utils::resource_helpers::DiskTest;
+    use nexus_test_utils::resource_helpers::create_disk;
+    use nexus_test_utils::resource_helpers::create_project;
+    use nexus_test_utils::resource_helpers::create_snapshot;
+    use nexus_test_utils_macros::nexus_test;
+    use omicron_common::api::external;
+
+    type ControlPlaneTestContext =
+        nexus_test_utils::ControlPlaneTestContext<crate::Server>;
+
+    const PROJECT_NAME: &str = "springfield-squidport";
+    const DISK_NAME: &str = "disky-mcdiskface";
+    const SNAPSHOT_NAME: &str = "snappy";
+
+    async fn create_test_snapshot(
+        cptestctx: &ControlPlaneTestContext,
+        opctx: &OpContext,
+    ) -> (authz::Snapshot, db::model::Snapshot) {
+        let client = &cptestctx.external_client;
+        create_project(client, PROJECT_NAME).await;
+        create_disk(client, PROJECT_NAME, DISK_NAME).await;
+        let snapshot =
+            create_snapshot(client, PROJECT_NAME, DISK_NAME, SNAPSHOT_NAME)
+                .await;
+
+        let nexus = &cptestctx.server.server_context().nexus;
+        let (.., authz_snapshot, db_snapshot) =
+            LookupPath::new(opctx, nexus.datastore())
+                .snapshot_id(snapshot.identity.id)
+                .fetch()
+                .await
+                .unwrap();
+
+        (authz_snapshot, db_snapshot)
+    }
+
+    fn new_test_params(
+        opctx: &OpContext,
+        authz_snapshot: &authz::Snapshot,
+        db_snapshot: &db::model::Snapshot,
+    ) -> Params {
+        Params {
+            serialized_authn: Serialized::for_opctx(opctx),
+            authz_snapshot: authz_snapshot.clone(),
+            snapshot: db_snapshot.clone(),
+            format: SnapshotExportFormat::Raw,
+            size: external::ByteCount::from(db_snapshot.size),
+        }
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_actions_succeed_idempotently(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let (authz_snapshot, db_snapshot) =
+            create_test_snapshot(cptestctx, &opctx).await;
+
+        let params = new_test_params(&opctx, &authz_snapshot, &db_snapshot);
+        let dag = create_saga_dag::<SagaSnapshotExportCreate>(params).unwrap();
+        test_helpers::actions_succeed_idempotently(nexus, dag).await;
+
+        let exports = nexus
+            .datastore()
+            .snapshot_export_list_for_snapshot(&opctx, &authz_snapshot)
+            .await
+            .unwrap();
+        assert_eq!(exports.len(), 1);
+    }
+
+    #[nexus_test(server = crate::Server)]
+    async fn test_action_failure_can_unwind(
+        cptestctx: &ControlPlaneTestContext,
+    ) {
+        DiskTest::new(cptestctx).await;
+        let nexus = &cptestctx.server.server_context().nexus;
+        let datastore = nexus.datastore();
+        let opctx = test_helpers::test_opctx(cptestctx);
+        let (authz_snapshot, db_snapshot) =
+            create_test_snapshot(cptestctx, &opctx).await;
+
+        test_helpers::action_failure_can_unwind::<
+            SagaSnapshotExportCreate,
+            _,
+            _,
+        >(
+            nexus,
+            || {
+                Box::pin(async {
+                    new_test_params(&opctx, &authz_snapshot, &db_snapshot)
+                })
+            },
+            || {
+                Box::pin(async {
+                    test_helpers::assert_no_failed_undo_steps(
+                        &cptestctx.logctx.log,
+                        datastore,
+                    )
+                    .await;
+
+                    let exports = datastore
+                        .snapshot_export_list_for_snapshot(
+                            &opctx,
+                            &authz_snapshot,
+                        )
+                        .await
+                        .unwrap();
+                    assert!(exports.is_empty());
+                })
+            },
+            &cptestctx.logctx.log,
+        )
+        .await;
+    }
+}
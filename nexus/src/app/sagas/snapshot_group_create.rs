@@ -0,0 +1,294 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Snapshot every disk attached to a stopped instance together
+//!
+//! Propolis can't pause a running guest or hold its I/O while several disks
+//! are snapshotted, so snapshots of a running instance's disks would not be
+//! crash-consistent with each other. Snapshot groups are therefore only taken
+//! of instances without an active VMM: nothing is writing to the disks, and
+//! each one is snapshotted through a Pantry like any other detached disk.
+//!
+//! The rest of the work of creating each snapshot (snapshotting the disk's
+//! volume, recording region snapshots, and so on) is done by a
+//! `snapshot_create` subsaga per disk. The snapshot IDs are chosen up front so
+//! that the group's members can be recorded before the snapshots are taken.
+//! Before the group is marked ready, the saga checks that the instance wasn't
+//! started while the disks were being snapshotted; if it was, the saga unwinds
+//! and the snapshots are deleted.
+
+use super::{ActionRegistry, NexusActionContext, NexusSaga, SagaInitError};
+use crate::app::sagas;
+use crate::app::sagas::declare_saga_actions;
+use crate::app::{authn, authz, db};
+use crate::external_api::params;
+use nexus_db_lookup::LookupPath;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::Name;
+use omicron_common::api::external::NameOrId;
+use serde::Deserialize;
+use serde::Serialize;
+use steno::ActionError;
+use steno::Node;
+use uuid::Uuid;
+
+// snapshot group create saga: input parameters
+
+/// One of the disks a snapshot group is taken of
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct GroupDisk {
+    pub disk_id: Uuid,
+    pub disk_name: Name,
+    /// The ID the disk's snapshot will have
+    pub snapshot_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Params {
+    pub serialized_authn: authn::saga::Serialized,
+    pub silo_id: Uuid,
+    pub project_id: Uuid,
+    pub instance_id: Uuid,
+    pub group_id: Uuid,
+    /// The generation of the instance's runtime state when it was found to
+    /// have no active VMM
+    pub instance_generation: db::model::Generation,
+    pub create_params: params::SnapshotGroupCreate,
+    pub disks: Vec<GroupDisk>,
+}
+
+impl Params {
+    /// The name of the snapshot of one of the group's disks
+    fn snapshot_name(disk: &GroupDisk) -> Name {
+        format!("snapshot-group-{}", disk.snapshot_id)
+            .parse()
+            .expect("snapshot group member name is valid")
+    }
+}
+
+// snapshot group create saga: actions
+
+declare_saga_actions! {
+    snapshot_group_create;
+    CREATE_GROUP_RECORD -> "created_group" {
+        + ssgc_create_group_record
+        - ssgc_create_group_record_undo
+    }
+    CHECK_INSTANCE_STOPPED -> "instance_stopped" {
+        + ssgc_check_instance_stopped
+    }
+    FINALIZE_GROUP_RECORD -> "finalized_group" {
+        + ssgc_finalize_group_record
+    }
+}
+
+// snapshot group create saga: definition
+
+#[derive(Debug)]
+pub(crate) struct SagaSnapshotGroupCreate;
+impl NexusSaga for SagaSnapshotGroupCreate {
+    const NAME: &'static str = "snapshot-group-create";
+    type Params = Params;
+
+    fn register_actions(registry: &mut ActionRegistry) {
+        snapshot_group_create_register_actions(registry);
+    }
+
+    fn make_saga_dag(
+        params: &Self::Params,
+        mut builder: steno::DagBuilder,
+    ) -> Result<steno::Dag, SagaInitError> {
+        builder.append(create_group_record_action());
+
+        for (i, disk) in params.disks.iter().enumerate() {
+            let snapshot_create_params = sagas::snapshot_create::Params {
+                serialized_authn: params.serialized_authn.clone(),
+                silo_id: params.silo_id,
+                project_id: params.project_id,
+                disk_id: disk.disk_id,
+                attach_instance_id: Some(params.instance_id),
+                use_the_pantry: true,
+                create_params: params::SnapshotCreate {
+                    identity: IdentityMetadataCreateParams {
+                        name: Params::snapshot_name(disk),
+                        description: format!(
+                            "snapshot of disk {} in snapshot group {}",
+                            disk.disk_name, params.create_params.identity.name,
+                        ),
+                    },
+                    disk: NameOrId::Id(disk.disk_id),
+                },
+                group_snapshot_id: Some(disk.snapshot_id),
+            };
+
+            let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+                sagas::snapshot_create::SagaSnapshotCreate::NAME,
+            ));
+            let snapshot_create_dag =
+                sagas::snapshot_create::SagaSnapshotCreate::make_saga_dag(
+                    &snapshot_create_params,
+                    subsaga_builder,
+                )?;
+
+            let params_node_name = format!("snapshot_create_params{i}");
+            builder.append(Node::constant(
+                &params_node_name,
+                serde_json::to_value(&snapshot_create_params).map_err(|e| {
+                    SagaInitError::SerializeError(params_node_name.clone(), e)
+                })?,
+            ));
+            builder.append(Node::subsaga(
+                format!("snapshot_create{i}").as_str(),
+                snapshot_create_dag,
+                params_node_name,
+            ));
+        }
+
+        builder.append(check_instance_stopped_action());
+        builder.append(finalize_group_record_action());
+
+        Ok(builder.build()?)
+    }
+}
+
+// snapshot group create saga: action implementations
+
+async fn ssgc_lookup_project(
+    sagactx: &NexusActionContext,
+    params: &Params,
+) -> Result<authz::Project, ActionError> {
+    let osagactx = sagactx.user_data();
+    let opctx = crate::context::op_context_for_saga_action(
+        sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., authz_project) = LookupPath::new(&opctx, osagactx.datastore())
+        .project_id(params.project_id)
+        .lookup_for(authz::Action::CreateChild)
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    Ok(authz_project)
+}
+
+async fn ssgc_create_group_record(
+    sagactx: NexusActionContext,
+) -> Result<db::model::SnapshotGroup, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+    let authz_project = ssgc_lookup_project(&sagactx, &params).await?;
+
+    let group = db::model::SnapshotGroup::new(
+        params.group_id,
+        params.project_id,
+        params.instance_id,
+        params.create_params.clone(),
+    );
+    let members = params
+        .disks
+        .iter()
+        .map(|disk| db::model::SnapshotGroupMember {
+            group_id: params.group_id,
+            snapshot_id: disk.snapshot_id,
+            disk_id: disk.disk_id,
+            disk_name: disk.disk_name.clone().into(),
+        })
+        .collect();
+
+    osagactx
+        .datastore()
+        .snapshot_group_create(&opctx, &authz_project, group, members)
+        .await
+        .map_err(ActionError::action_failed)
+}
+
+async fn ssgc_create_group_record_undo(
+    sagactx: NexusActionContext,
+) -> Result<(), anyhow::Error> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+    let authz_project = ssgc_lookup_project(&sagactx, &params).await?;
+
+    osagactx
+        .datastore()
+        .snapshot_group_hard_delete(&opctx, &authz_project, params.group_id)
+        .await?;
+
+    Ok(())
+}
+
+async fn ssgc_check_instance_stopped(
+    sagactx: NexusActionContext,
+) -> Result<(), ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let (.., authz_instance) = LookupPath::new(&opctx, osagactx.datastore())
+        .instance_id(params.instance_id)
+        .lookup_for(authz::Action::Read)
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    let instance_and_vmm = osagactx
+        .datastore()
+        .instance_fetch_with_vmm(&opctx, &authz_instance)
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    // Starting the instance gives it a VMM and advances its runtime state's
+    // generation, even if it has since stopped again. Either way, the guest
+    // may have written to some disks after they were snapshotted.
+    if instance_and_vmm.vmm().is_some()
+        || instance_and_vmm.instance().runtime().r#gen
+            != params.instance_generation
+    {
+        return Err(ActionError::action_failed(Error::conflict(
+            "instance was started while its disks were being snapshotted",
+        )));
+    }
+
+    Ok(())
+}
+
+async fn ssgc_finalize_group_record(
+    sagactx: NexusActionContext,
+) -> Result<db::model::SnapshotGroup, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+    let authz_project = ssgc_lookup_project(&sagactx, &params).await?;
+
+    osagactx
+        .datastore()
+        .snapshot_group_set_ready(&opctx, &authz_project, params.group_id)
+        .await
+        .map_err(ActionError::action_failed)?;
+
+    osagactx
+        .datastore()
+        .snapshot_group_fetch(
+            &opctx,
+            &authz_project,
+            &NameOrId::Id(params.group_id),
+        )
+        .await
+        .map_err(ActionError::action_failed)
+}
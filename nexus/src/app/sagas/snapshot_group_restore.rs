@@ -0,0 +1,115 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Restore a snapshot group as a set of new disks
+//!
+//! Each disk is created from one of the group's snapshots by a `disk_create`
+//! subsaga. Running them all in one saga means that if any disk can't be
+//! created, the disks created before it are deleted again, rather than
+//! leaving the user with part of a set.
+
+use super::{ActionRegistry, NexusActionContext, NexusSaga, SagaInitError};
+use crate::app::sagas;
+use crate::app::sagas::declare_saga_actions;
+use crate::app::{authn, db};
+use crate::external_api::params;
+use nexus_db_lookup::LookupPath;
+use serde::Deserialize;
+use serde::Serialize;
+use steno::ActionError;
+use steno::Node;
+use uuid::Uuid;
+
+// snapshot group restore saga: input parameters
+
+#[derive(Debug, Deserialize, Serialize)]
+pub(crate) struct Params {
+    pub serialized_authn: authn::saga::Serialized,
+    pub project_id: Uuid,
+    pub group_id: Uuid,
+    /// The disks to create, one for each of the group's snapshots
+    pub disks: Vec<params::DiskCreate>,
+}
+
+// snapshot group restore saga: actions
+
+declare_saga_actions! {
+    snapshot_group_restore;
+    FETCH_CREATED_DISKS -> "created_disks" {
+        + ssgr_fetch_created_disks
+    }
+}
+
+// snapshot group restore saga: definition
+
+#[derive(Debug)]
+pub(crate) struct SagaSnapshotGroupRestore;
+impl NexusSaga for SagaSnapshotGroupRestore {
+    const NAME: &'static str = "snapshot-group-restore";
+    type Params = Params;
+
+    fn register_actions(registry: &mut ActionRegistry) {
+        snapshot_group_restore_register_actions(registry);
+    }
+
+    fn make_saga_dag(
+        params: &Self::Params,
+        mut builder: steno::DagBuilder,
+    ) -> Result<steno::Dag, SagaInitError> {
+        for (i, create_params) in params.disks.iter().enumerate() {
+            let disk_create_params = sagas::disk_create::Params {
+                serialized_authn: params.serialized_authn.clone(),
+                project_id: params.project_id,
+                create_params: create_params.clone(),
+            };
+
+            let params_node_name = format!("disk_create_params{i}");
+            builder.append(Node::constant(
+                &params_node_name,
+                serde_json::to_value(&disk_create_params).map_err(|e| {
+                    SagaInitError::SerializeError(params_node_name.clone(), e)
+                })?,
+            ));
+
+            let subsaga_builder = steno::DagBuilder::new(steno::SagaName::new(
+                sagas::disk_create::SagaDiskCreate::NAME,
+            ));
+            builder.append(Node::subsaga(
+                format!("disk_create{i}").as_str(),
+                sagas::disk_create::create_dag(subsaga_builder, false)?,
+                params_node_name,
+            ));
+        }
+
+        builder.append(fetch_created_disks_action());
+
+        Ok(builder.build()?)
+    }
+}
+
+// snapshot group restore saga: action implementations
+
+async fn ssgr_fetch_created_disks(
+    sagactx: NexusActionContext,
+) -> Result<Vec<db::model::Disk>, ActionError> {
+    let osagactx = sagactx.user_data();
+    let params = sagactx.saga_params::<Params>()?;
+    let opctx = crate::context::op_context_for_saga_action(
+        &sagactx,
+        &params.serialized_authn,
+    );
+
+    let mut disks = Vec::with_capacity(params.disks.len());
+    for create_params in params.disks {
+        let (.., db_disk) = LookupPath::new(&opctx, osagactx.datastore())
+            .project_id(params.project_id)
+            .disk_name_owned(create_params.identity.name.into())
+            .fetch()
+            .await
+            .map_err(ActionError::action_failed)?;
+        disks.push(db_disk);
+    }
+
+    Ok(disks)
+}
@@ -6,6 +6,7 @@ use nexus_db_queries::authn;
 use nexus_db_queries::authz;
 use nexus_db_queries::context::OpContext;
 use nexus_db_queries::db;
+use nexus_db_queries::db::DataStore;
 use nexus_types::external_api::params;
 use nexus_types::external_api::params::DiskSelector;
 use omicron_common::api::external::CreateResult;
@@ -90,29 +91,8 @@ impl super::Nexus {
             ));
         }
 
-        // If there isn't a running propolis, Nexus needs to use the Crucible
-        // Pantry to make this snapshot
-        let use_the_pantry = if let Some(attach_instance_id) =
-            &db_disk.runtime_state.attach_instance_id
-        {
-            let (.., authz_instance) =
-                LookupPath::new(opctx, &self.db_datastore)
-                    .instance_id(*attach_instance_id)
-                    .lookup_for(authz::Action::Read)
-                    .await?;
-
-            let instance_state = self
-                .datastore()
-                .instance_fetch_with_vmm(&opctx, &authz_instance)
-                .await?;
-
-            // If a Propolis _may_ exist, send the snapshot request there,
-            // otherwise use the pantry.
-            instance_state.vmm().is_none()
-        } else {
-            // This disk is not attached to an instance, use the pantry.
-            true
-        };
+        let use_the_pantry =
+            self.disk_snapshot_uses_pantry(opctx, &db_disk).await?;
 
         let saga_params = sagas::snapshot_create::Params {
             serialized_authn: authn::saga::Serialized::for_opctx(opctx),
@@ -122,6 +102,7 @@ impl super::Nexus {
             attach_instance_id: db_disk.runtime_state.attach_instance_id,
             use_the_pantry,
             create_params: params.clone(),
+            group_snapshot_id: None,
         };
 
         let saga_outputs = self
@@ -140,6 +121,16 @@ impl super::Nexus {
         Ok(snapshot_created)
     }
 
+    /// Returns whether a snapshot of `db_disk` must be taken through the
+    /// Crucible Pantry, rather than by the Propolis the disk is attached to.
+    pub(crate) async fn disk_snapshot_uses_pantry(
+        &self,
+        opctx: &OpContext,
+        db_disk: &db::model::Disk,
+    ) -> Result<bool, Error> {
+        disk_snapshot_uses_pantry(opctx, &self.db_datastore, db_disk).await
+    }
+
     pub(crate) async fn snapshot_list(
         &self,
         opctx: &OpContext,
@@ -160,6 +151,18 @@ impl super::Nexus {
         let (.., authz_snapshot, db_snapshot) =
             snapshot_lookup.fetch_for(authz::Action::Delete).await?;
 
+        // An export reads from a copy of the snapshot's volume through the
+        // Pantry, so make sure those are cleaned up first.
+        let exports = self
+            .db_datastore
+            .snapshot_export_list_for_snapshot(opctx, &authz_snapshot)
+            .await?;
+        if !exports.is_empty() {
+            return Err(Error::invalid_request(
+                "snapshot has exports, which must be deleted first",
+            ));
+        }
+
         let saga_params = sagas::snapshot_delete::Params {
             serialized_authn: authn::saga::Serialized::for_opctx(opctx),
             authz_snapshot,
@@ -175,3 +178,31 @@ impl super::Nexus {
         Ok(())
     }
 }
+
+/// Returns whether a snapshot of `db_disk` must be taken through the Crucible
+/// Pantry, rather than by the Propolis the disk is attached to.
+pub(crate) async fn disk_snapshot_uses_pantry(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    db_disk: &db::model::Disk,
+) -> Result<bool, Error> {
+    // If there isn't a running propolis, Nexus needs to use the Crucible
+    // Pantry to make this snapshot
+    if let Some(attach_instance_id) = &db_disk.runtime_state.attach_instance_id
+    {
+        let (.., authz_instance) = LookupPath::new(opctx, datastore)
+            .instance_id(*attach_instance_id)
+            .lookup_for(authz::Action::Read)
+            .await?;
+
+        let instance_state =
+            datastore.instance_fetch_with_vmm(opctx, &authz_instance).await?;
+
+        // If a Propolis _may_ exist, send the snapshot request there,
+        // otherwise use the pantry.
+        Ok(instance_state.vmm().is_none())
+    } else {
+        // This disk is not attached to an instance, use the pantry.
+        Ok(true)
+    }
+}
@@ -0,0 +1,484 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Exporting snapshots as downloadable disk images
+
+use super::sagas;
+use bytes::Bytes;
+use dropshot::Body;
+use http::Response;
+use nexus_db_lookup::lookup;
+use nexus_db_queries::authn;
+use nexus_db_queries::authz;
+use nexus_db_queries::context::OpContext;
+use nexus_db_queries::db;
+use nexus_db_queries::db::DataStore;
+use nexus_db_queries::db::model::SnapshotExportFormat;
+use nexus_db_queries::db::model::SnapshotState;
+use nexus_types::external_api::params;
+use omicron_common::api::external;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::LookupResult;
+use range_requests::ByteRanges;
+use range_requests::EntityTag;
+use range_requests::PotentialRange;
+use slog_error_chain::InlineErrorChain;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+mod qcow2;
+
+use qcow2::Qcow2Layout;
+
+/// Largest read issued to the Pantry while streaming an export
+const EXPORT_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// How many bytes are streamed between updates of an export's progress
+const EXPORT_PROGRESS_INTERVAL: u64 = 64 * EXPORT_CHUNK_SIZE;
+
+/// The shape of an exported image, and where each part of it comes from
+#[derive(Clone, Debug)]
+enum ExportImage {
+    /// The snapshot contents, byte for byte
+    Raw { size: u64 },
+    /// The snapshot contents following qcow2 metadata
+    Qcow2(Qcow2Layout),
+}
+
+impl ExportImage {
+    fn new(format: SnapshotExportFormat, snapshot_size: u64) -> Self {
+        match format {
+            SnapshotExportFormat::Raw => Self::Raw { size: snapshot_size },
+            SnapshotExportFormat::Qcow2 => {
+                Self::Qcow2(Qcow2Layout::new(snapshot_size))
+            }
+        }
+    }
+
+    fn file_size(&self) -> u64 {
+        match self {
+            Self::Raw { size } => *size,
+            Self::Qcow2(layout) => layout.file_size(),
+        }
+    }
+
+    /// Offset in the image file at which the snapshot contents begin
+    fn data_offset(&self) -> u64 {
+        match self {
+            Self::Raw { .. } => 0,
+            Self::Qcow2(layout) => layout.data_offset(),
+        }
+    }
+
+    fn read_metadata(&self, offset: u64, buf: &mut [u8]) {
+        match self {
+            Self::Raw { .. } => {
+                unreachable!("raw images have no metadata")
+            }
+            Self::Qcow2(layout) => layout.read_metadata(offset, buf),
+        }
+    }
+}
+
+/// Reads the contents of an export out of the Pantry it's attached to
+struct ExportReader {
+    client: crucible_pantry_client::Client,
+    export_id: Uuid,
+    image: ExportImage,
+    snapshot_size: u64,
+    block_size: u64,
+}
+
+impl ExportReader {
+    /// Returns `len` bytes of the image file starting at `offset`
+    async fn read(&self, offset: u64, len: u64) -> Result<Bytes, Error> {
+        let mut buf = vec![0; len as usize];
+        let data_offset = self.image.data_offset();
+
+        // Anything before the snapshot contents is generated metadata.
+        let metadata_len = data_offset.saturating_sub(offset).min(len);
+        if metadata_len > 0 {
+            self.image.read_metadata(offset, &mut buf[..metadata_len as usize]);
+        }
+
+        // The rest comes from the snapshot, whose contents start at
+        // `data_offset`. Anything past the end of the snapshot is padding out
+        // the last cluster, and stays zeroed.
+        let start = offset.max(data_offset) - data_offset;
+        let end =
+            (offset + len).saturating_sub(data_offset).min(self.snapshot_size);
+        if start < end {
+            let data = self.bulk_read(start, end - start).await?;
+            let at = metadata_len as usize;
+            buf[at..at + data.len()].copy_from_slice(&data);
+        }
+
+        Ok(Bytes::from(buf))
+    }
+
+    /// Reads `len` bytes of the snapshot starting at `offset`
+    ///
+    /// The Pantry only reads whole blocks, so this reads the blocks covering
+    /// the requested range and trims the result.
+    async fn bulk_read(&self, offset: u64, len: u64) -> Result<Vec<u8>, Error> {
+        let aligned_start = offset - offset % self.block_size;
+        let aligned_end = (offset + len).next_multiple_of(self.block_size);
+        let size: usize =
+            (aligned_end - aligned_start).try_into().map_err(|_| {
+                Error::internal_error("bulk read size out of range")
+            })?;
+
+        let response = self
+            .client
+            .bulk_read(
+                &self.export_id.to_string(),
+                &crucible_pantry_client::types::BulkReadRequest {
+                    offset: aligned_start,
+                    size: size.try_into().map_err(|_| {
+                        Error::internal_error("bulk read size out of range")
+                    })?,
+                },
+            )
+            .await
+            .map_err(|e| {
+                Error::internal_error(&format!(
+                    "pantry bulk read failed: {}",
+                    InlineErrorChain::new(&e)
+                ))
+            })?;
+
+        let data = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            &response.base64_encoded_data,
+        )
+        .map_err(|e| {
+            Error::internal_error(&format!(
+                "pantry returned invalid base64: {e}"
+            ))
+        })?;
+
+        let skip = (offset - aligned_start) as usize;
+        let data = data.get(skip..skip + len as usize).ok_or_else(|| {
+            Error::internal_error(&format!(
+                "pantry returned {} bytes, expected {size}",
+                data.len()
+            ))
+        })?;
+
+        Ok(data.to_vec())
+    }
+}
+
+impl super::Nexus {
+    pub(crate) async fn snapshot_export_create(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        snapshot_lookup: &lookup::Snapshot<'_>,
+        params: &params::SnapshotExportCreate,
+    ) -> CreateResult<db::model::SnapshotExport> {
+        let (.., authz_snapshot, db_snapshot) =
+            snapshot_lookup.fetch_for(authz::Action::Modify).await?;
+
+        if db_snapshot.state != SnapshotState::Ready {
+            return Err(Error::invalid_request(format!(
+                "snapshot must be ready to export, but is {:?}",
+                db_snapshot.state
+            )));
+        }
+
+        let format = SnapshotExportFormat::from(params.format);
+        let image = ExportImage::new(format, db_snapshot.size.to_bytes());
+        let size =
+            external::ByteCount::try_from(image.file_size()).map_err(|e| {
+                Error::invalid_request(format!(
+                    "snapshot is too large to export: {e}"
+                ))
+            })?;
+
+        let saga_params = sagas::snapshot_export_create::Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+            authz_snapshot,
+            snapshot: db_snapshot,
+            format,
+            size,
+        };
+
+        let saga_outputs = self
+            .sagas
+            .saga_execute::<sagas::snapshot_export_create::SagaSnapshotExportCreate>(
+                saga_params,
+            )
+            .await?;
+
+        let export = saga_outputs
+            .lookup_node_output::<db::model::SnapshotExport>("export")
+            .map_err(|e| Error::InternalError {
+                internal_message: e.to_string(),
+            })?;
+
+        Ok(export)
+    }
+
+    pub(crate) async fn snapshot_export_view(
+        &self,
+        opctx: &OpContext,
+        snapshot_lookup: &lookup::Snapshot<'_>,
+        export_id: Uuid,
+    ) -> LookupResult<db::model::SnapshotExport> {
+        let (.., authz_snapshot) =
+            snapshot_lookup.lookup_for(authz::Action::Read).await?;
+
+        self.db_datastore
+            .snapshot_export_fetch(opctx, &authz_snapshot, export_id)
+            .await
+    }
+
+    pub(crate) async fn snapshot_export_delete(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        snapshot_lookup: &lookup::Snapshot<'_>,
+        export_id: Uuid,
+    ) -> DeleteResult {
+        let (.., authz_snapshot) =
+            snapshot_lookup.lookup_for(authz::Action::Modify).await?;
+
+        let export = self
+            .db_datastore
+            .snapshot_export_fetch(opctx, &authz_snapshot, export_id)
+            .await?;
+
+        let saga_params = sagas::snapshot_export_delete::Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+            authz_snapshot,
+            export,
+        };
+
+        self.sagas
+            .saga_execute::<sagas::snapshot_export_delete::SagaSnapshotExportDelete>(
+                saga_params,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Streams the contents of an export
+    ///
+    /// The image is read from the Pantry as the response body is consumed,
+    /// and the export's progress is updated as it goes. If the client goes
+    /// away, reading stops.
+    pub(crate) async fn snapshot_export_download(
+        &self,
+        opctx: &OpContext,
+        snapshot_lookup: &lookup::Snapshot<'_>,
+        export_id: Uuid,
+        head: bool,
+        range: Option<PotentialRange>,
+    ) -> Result<Response<Body>, Error> {
+        let (.., authz_snapshot, db_snapshot) =
+            snapshot_lookup.fetch_for(authz::Action::Read).await?;
+
+        let export = self
+            .db_datastore
+            .snapshot_export_fetch(opctx, &authz_snapshot, export_id)
+            .await?;
+
+        let image =
+            ExportImage::new(export.format, db_snapshot.size.to_bytes());
+        let len = image.file_size();
+
+        // An export's contents never change, so its ID identifies them.
+        let etag = EntityTag::new(export_id.to_string())
+            .expect("UUIDs are valid entity tags");
+        let range = range.filter(|range| range.if_range_matches(&etag));
+
+        let ranges = match range.map(|range| range.parse(len)).transpose() {
+            Ok(ranges) => ranges,
+            Err(err_response) => return Ok(err_response),
+        };
+
+        let response = if head {
+            range_requests::make_head_response(
+                ranges,
+                len,
+                None::<http::HeaderValue>,
+            )
+        } else {
+            let reader = ExportReader {
+                client: crucible_pantry_client::Client::new(&format!(
+                    "http://{}",
+                    export.pantry_address()
+                )),
+                export_id,
+                image,
+                snapshot_size: db_snapshot.size.to_bytes(),
+                block_size: u64::from(db_snapshot.block_size.to_bytes()),
+            };
+
+            let stream = stream_export(
+                opctx.child(BTreeMap::new()),
+                self.datastore().clone(),
+                authz_snapshot,
+                reader,
+                ranges.clone(),
+                len,
+            );
+
+            range_requests::make_get_response(
+                ranges,
+                len,
+                None::<http::HeaderValue>,
+                stream,
+            )
+        };
+
+        let mut response =
+            response.map_err(|e| Error::internal_error(&e.to_string()))?;
+        response
+            .headers_mut()
+            .insert(http::header::ETAG, etag.to_header_value());
+        Ok(response)
+    }
+}
+
+/// Returns a stream of the export's contents within `ranges`, or all of it
+/// if there are none
+fn stream_export(
+    opctx: OpContext,
+    datastore: Arc<DataStore>,
+    authz_snapshot: authz::Snapshot,
+    reader: ExportReader,
+    ranges: Option<ByteRanges>,
+    len: u64,
+) -> impl futures::Stream<Item = Result<Bytes, std::io::Error>> + Send + Sync + 'static
+{
+    let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+
+    tokio::spawn(async move {
+        let spans: Vec<(u64, u64)> = match &ranges {
+            Some(ranges) => ranges
+                .iter()
+                .map(|range| (range.start(), range.end_inclusive() + 1))
+                .collect(),
+            None => vec![(0, len)],
+        };
+
+        let mut unrecorded = 0;
+        'spans: for (start, end) in spans {
+            let mut offset = start;
+            while offset < end {
+                let n = (end - offset).min(EXPORT_CHUNK_SIZE);
+                let chunk = reader.read(offset, n).await.map_err(|e| {
+                    std::io::Error::other(InlineErrorChain::new(&e).to_string())
+                });
+                let failed = chunk.is_err();
+
+                // The receiver goes away if the client disconnects.
+                if tx.send(chunk).await.is_err() || failed {
+                    break 'spans;
+                }
+
+                offset += n;
+                unrecorded += n;
+                if unrecorded >= EXPORT_PROGRESS_INTERVAL {
+                    record_progress(
+                        &opctx,
+                        &datastore,
+                        &authz_snapshot,
+                        reader.export_id,
+                        unrecorded,
+                    )
+                    .await;
+                    unrecorded = 0;
+                }
+            }
+        }
+
+        if unrecorded > 0 {
+            record_progress(
+                &opctx,
+                &datastore,
+                &authz_snapshot,
+                reader.export_id,
+                unrecorded,
+            )
+            .await;
+        }
+    });
+
+    futures::stream::poll_fn(move |cx| rx.poll_recv(cx))
+}
+
+async fn record_progress(
+    opctx: &OpContext,
+    datastore: &DataStore,
+    authz_snapshot: &authz::Snapshot,
+    export_id: Uuid,
+    bytes: u64,
+) {
+    // Progress is informational, so failing to record it shouldn't fail the
+    // download.
+    if let Err(e) = datastore
+        .snapshot_export_add_bytes_exported(
+            opctx,
+            authz_snapshot,
+            export_id,
+            bytes,
+        )
+        .await
+    {
+        warn!(
+            opctx.log,
+            "failed to record snapshot export progress";
+            "export_id" => %export_id,
+            InlineErrorChain::new(&e),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns a reader for a qcow2 export of a 1 GiB snapshot whose Pantry
+    /// can't be reached, so any read of the snapshot contents fails
+    fn qcow2_reader() -> ExportReader {
+        let snapshot_size = 1024 * 1024 * 1024;
+        ExportReader {
+            client: crucible_pantry_client::Client::new("http://[::1]:0"),
+            export_id: Uuid::new_v4(),
+            image: ExportImage::new(SnapshotExportFormat::Qcow2, snapshot_size),
+            snapshot_size,
+            block_size: 512,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_within_metadata() {
+        let reader = qcow2_reader();
+        let data_offset = reader.image.data_offset();
+        let mut whole = vec![0; data_offset as usize];
+        reader.image.read_metadata(0, &mut whole);
+
+        // Ranges that end at or before the snapshot contents are served
+        // entirely from the generated metadata, without asking the Pantry for
+        // anything.
+        for (offset, len) in
+            [(0, 512), (100, 1000), (data_offset - 512, 512), (0, data_offset)]
+        {
+            let bytes = reader.read(offset, len).await.unwrap();
+            assert_eq!(
+                &bytes[..],
+                &whole[offset as usize..(offset + len) as usize]
+            );
+        }
+
+        // A range that reaches into the snapshot contents does need the
+        // Pantry.
+        assert!(reader.read(data_offset - 512, 1024).await.is_err());
+    }
+}
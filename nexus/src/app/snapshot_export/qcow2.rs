@@ -0,0 +1,346 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Layout of the qcow2 images produced by snapshot exports
+//!
+//! Exports are streamed straight out of the Pantry without being staged
+//! anywhere, and clients may ask for any byte range of the image, so every
+//! byte of the file has to be computable from the snapshot's size alone. To
+//! make that possible the image is fully allocated and laid out in a fixed
+//! order:
+//!
+//! ```text
+//! | header | L1 table | refcount table | refcount blocks | L2 tables | data |
+//! ```
+//!
+//! Each region starts on a cluster boundary, and the data clusters hold the
+//! snapshot contents in order. Reading the image at some offset in the data
+//! region is therefore a read of the snapshot at that offset minus the start
+//! of the data region, and everything before it is metadata generated here.
+//!
+//! This uses version 2 of the format, with 64 KiB clusters and 16-bit
+//! refcounts, which every qcow2 consumer supports.
+
+/// log2 of the cluster size
+const CLUSTER_BITS: u32 = 16;
+const CLUSTER_SIZE: u64 = 1 << CLUSTER_BITS;
+
+/// Number of 8-byte entries in an L1 table, L2 table, or refcount table
+/// cluster
+const ENTRIES_PER_TABLE_CLUSTER: u64 = CLUSTER_SIZE / 8;
+
+/// Number of 16-bit entries in a refcount block
+const ENTRIES_PER_REFCOUNT_BLOCK: u64 = CLUSTER_SIZE / 2;
+
+const QCOW_MAGIC: u32 = 0x5146_49fb;
+const QCOW_VERSION: u32 = 2;
+
+/// Set on L1 and L2 entries whose cluster has a refcount of exactly one
+const QCOW_OFLAG_COPIED: u64 = 1 << 63;
+
+/// Size of a version 2 header
+const HEADER_SIZE: usize = 72;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Qcow2Layout {
+    /// Size of the virtual disk, in bytes
+    virtual_size: u64,
+    /// Number of L1 table entries, equal to the number of L2 tables
+    l1_entries: u64,
+    l1_clusters: u64,
+    refcount_table_clusters: u64,
+    refcount_blocks: u64,
+    data_clusters: u64,
+}
+
+impl Qcow2Layout {
+    pub fn new(virtual_size: u64) -> Self {
+        let data_clusters = virtual_size.div_ceil(CLUSTER_SIZE);
+        let l1_entries = data_clusters.div_ceil(ENTRIES_PER_TABLE_CLUSTER);
+        let l1_clusters = l1_entries.div_ceil(ENTRIES_PER_TABLE_CLUSTER).max(1);
+
+        // The refcount blocks have to cover every cluster in the image,
+        // including the refcount table and the refcount blocks themselves, so
+        // grow them until they're big enough to describe themselves.
+        let fixed_clusters = 1 + l1_clusters + l1_entries + data_clusters;
+        let mut refcount_table_clusters = 1;
+        let mut refcount_blocks = 1;
+        loop {
+            let total =
+                fixed_clusters + refcount_table_clusters + refcount_blocks;
+            let needed_blocks = total.div_ceil(ENTRIES_PER_REFCOUNT_BLOCK);
+            let needed_table_clusters =
+                needed_blocks.div_ceil(ENTRIES_PER_TABLE_CLUSTER);
+            if needed_blocks <= refcount_blocks
+                && needed_table_clusters <= refcount_table_clusters
+            {
+                break;
+            }
+            refcount_blocks = refcount_blocks.max(needed_blocks);
+            refcount_table_clusters =
+                refcount_table_clusters.max(needed_table_clusters);
+        }
+
+        Self {
+            virtual_size,
+            l1_entries,
+            l1_clusters,
+            refcount_table_clusters,
+            refcount_blocks,
+            data_clusters,
+        }
+    }
+
+    fn l1_start(&self) -> u64 {
+        1
+    }
+
+    fn refcount_table_start(&self) -> u64 {
+        self.l1_start() + self.l1_clusters
+    }
+
+    fn refcount_blocks_start(&self) -> u64 {
+        self.refcount_table_start() + self.refcount_table_clusters
+    }
+
+    fn l2_start(&self) -> u64 {
+        self.refcount_blocks_start() + self.refcount_blocks
+    }
+
+    fn data_start(&self) -> u64 {
+        self.l2_start() + self.l1_entries
+    }
+
+    fn total_clusters(&self) -> u64 {
+        self.data_start() + self.data_clusters
+    }
+
+    /// Size of the image file, in bytes
+    pub fn file_size(&self) -> u64 {
+        self.total_clusters() * CLUSTER_SIZE
+    }
+
+    /// Offset in the image file at which the snapshot contents begin
+    pub fn data_offset(&self) -> u64 {
+        self.data_start() * CLUSTER_SIZE
+    }
+
+    /// Fills `buf` with the metadata at `offset` in the image file
+    ///
+    /// The range must end at or before [`Self::data_offset`].
+    pub fn read_metadata(&self, offset: u64, buf: &mut [u8]) {
+        assert!(offset + buf.len() as u64 <= self.data_offset());
+
+        let mut done = 0;
+        while done < buf.len() {
+            let position = offset + done as u64;
+            let cluster_index = position / CLUSTER_SIZE;
+            let within = (position % CLUSTER_SIZE) as usize;
+            let n = (CLUSTER_SIZE as usize - within).min(buf.len() - done);
+
+            let cluster = self.metadata_cluster(cluster_index);
+            buf[done..done + n].copy_from_slice(&cluster[within..within + n]);
+            done += n;
+        }
+    }
+
+    fn metadata_cluster(&self, index: u64) -> Vec<u8> {
+        let mut cluster = vec![0; CLUSTER_SIZE as usize];
+
+        if index == 0 {
+            cluster[..HEADER_SIZE].copy_from_slice(&self.header());
+        } else if index < self.refcount_table_start() {
+            // Each L1 entry points at the L2 table with the same index.
+            let first = (index - self.l1_start()) * ENTRIES_PER_TABLE_CLUSTER;
+            self.fill_u64_entries(&mut cluster, first, self.l1_entries, |i| {
+                ((self.l2_start() + i) * CLUSTER_SIZE) | QCOW_OFLAG_COPIED
+            });
+        } else if index < self.refcount_blocks_start() {
+            let first = (index - self.refcount_table_start())
+                * ENTRIES_PER_TABLE_CLUSTER;
+            self.fill_u64_entries(
+                &mut cluster,
+                first,
+                self.refcount_blocks,
+                |i| (self.refcount_blocks_start() + i) * CLUSTER_SIZE,
+            );
+        } else if index < self.l2_start() {
+            // Every cluster in the image is used exactly once.
+            let first = (index - self.refcount_blocks_start())
+                * ENTRIES_PER_REFCOUNT_BLOCK;
+            let last =
+                (first + ENTRIES_PER_REFCOUNT_BLOCK).min(self.total_clusters());
+            for i in first..last {
+                let at = ((i - first) * 2) as usize;
+                cluster[at..at + 2].copy_from_slice(&1u16.to_be_bytes());
+            }
+        } else if index < self.data_start() {
+            let first = (index - self.l2_start()) * ENTRIES_PER_TABLE_CLUSTER;
+            self.fill_u64_entries(
+                &mut cluster,
+                first,
+                self.data_clusters,
+                |i| {
+                    ((self.data_start() + i) * CLUSTER_SIZE) | QCOW_OFLAG_COPIED
+                },
+            );
+        } else {
+            panic!("cluster {index} is not a metadata cluster");
+        }
+
+        cluster
+    }
+
+    /// Fills a table cluster whose first entry has index `first` with
+    /// `entry(i)` for each index below `count`, leaving the rest zeroed.
+    fn fill_u64_entries(
+        &self,
+        cluster: &mut [u8],
+        first: u64,
+        count: u64,
+        entry: impl Fn(u64) -> u64,
+    ) {
+        let last = (first + ENTRIES_PER_TABLE_CLUSTER).min(count);
+        for i in first..last {
+            let at = ((i - first) * 8) as usize;
+            cluster[at..at + 8].copy_from_slice(&entry(i).to_be_bytes());
+        }
+    }
+
+    fn header(&self) -> [u8; HEADER_SIZE] {
+        let mut header = [0; HEADER_SIZE];
+        let mut put = |at: usize, bytes: &[u8]| {
+            header[at..at + bytes.len()].copy_from_slice(bytes);
+        };
+
+        put(0, &QCOW_MAGIC.to_be_bytes());
+        put(4, &QCOW_VERSION.to_be_bytes());
+        // backing_file_offset (8) and backing_file_size (16) are zero.
+        put(20, &CLUSTER_BITS.to_be_bytes());
+        put(24, &self.virtual_size.to_be_bytes());
+        // crypt_method (32) is zero.
+        put(36, &(self.l1_entries as u32).to_be_bytes());
+        put(40, &(self.l1_start() * CLUSTER_SIZE).to_be_bytes());
+        put(48, &(self.refcount_table_start() * CLUSTER_SIZE).to_be_bytes());
+        put(56, &(self.refcount_table_clusters as u32).to_be_bytes());
+        // nb_snapshots (60) and snapshots_offset (64) are zero.
+
+        header
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn read_u16(buf: &[u8], at: usize) -> u16 {
+        u16::from_be_bytes(buf[at..at + 2].try_into().unwrap())
+    }
+
+    fn read_u32(buf: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes(buf[at..at + 4].try_into().unwrap())
+    }
+
+    fn read_u64(buf: &[u8], at: usize) -> u64 {
+        u64::from_be_bytes(buf[at..at + 8].try_into().unwrap())
+    }
+
+    fn metadata(layout: &Qcow2Layout) -> Vec<u8> {
+        let mut buf = vec![0; layout.data_offset() as usize];
+        layout.read_metadata(0, &mut buf);
+        buf
+    }
+
+    #[test]
+    fn test_header() {
+        let layout = Qcow2Layout::new(GIB);
+        let buf = metadata(&layout);
+
+        assert_eq!(&buf[0..4], b"QFI\xfb");
+        assert_eq!(read_u32(&buf, 4), 2);
+        assert_eq!(read_u32(&buf, 20), CLUSTER_BITS);
+        assert_eq!(read_u64(&buf, 24), GIB);
+
+        // 1 GiB of 64 KiB clusters is 16384 data clusters, which need two
+        // L2 tables.
+        assert_eq!(read_u32(&buf, 36), 2);
+        assert_eq!(read_u64(&buf, 40), CLUSTER_SIZE);
+        assert_eq!(read_u64(&buf, 48), 2 * CLUSTER_SIZE);
+        assert_eq!(read_u32(&buf, 56), 1);
+        assert_eq!(read_u32(&buf, 60), 0);
+    }
+
+    #[test]
+    fn test_tables_describe_layout() {
+        for size in [512, CLUSTER_SIZE, GIB, 3 * GIB + 512, 40 * GIB] {
+            let layout = Qcow2Layout::new(size);
+            let buf = metadata(&layout);
+            let total_clusters = layout.file_size() / CLUSTER_SIZE;
+
+            // Every data cluster is mapped, in order, after the metadata.
+            let l1_offset = read_u64(&buf, 40) as usize;
+            let l1_entries = read_u32(&buf, 36) as u64;
+            let mut data_cluster = 0;
+            for i in 0..l1_entries as usize {
+                let l2 = read_u64(&buf, l1_offset + i * 8);
+                assert_ne!(l2 & QCOW_OFLAG_COPIED, 0);
+                let l2_offset = (l2 & !QCOW_OFLAG_COPIED) as usize;
+                for j in 0..ENTRIES_PER_TABLE_CLUSTER as usize {
+                    let entry = read_u64(&buf, l2_offset + j * 8);
+                    if data_cluster < layout.data_clusters {
+                        assert_eq!(
+                            entry,
+                            (layout.data_offset()
+                                + data_cluster * CLUSTER_SIZE)
+                                | QCOW_OFLAG_COPIED
+                        );
+                        data_cluster += 1;
+                    } else {
+                        assert_eq!(entry, 0);
+                    }
+                }
+            }
+            assert_eq!(data_cluster, size.div_ceil(CLUSTER_SIZE));
+
+            // Every cluster in the file has a refcount of one, and no
+            // refcount is recorded past the end of the file.
+            let rt_offset = read_u64(&buf, 48) as usize;
+            let rt_clusters = read_u32(&buf, 56) as usize;
+            let mut cluster = 0;
+            for i in 0..rt_clusters * ENTRIES_PER_TABLE_CLUSTER as usize {
+                let block = read_u64(&buf, rt_offset + i * 8) as usize;
+                if block == 0 {
+                    continue;
+                }
+                for j in 0..ENTRIES_PER_REFCOUNT_BLOCK as usize {
+                    let refcount = read_u16(&buf, block + j * 2);
+                    if cluster < total_clusters {
+                        assert_eq!(refcount, 1);
+                    } else {
+                        assert_eq!(refcount, 0);
+                    }
+                    cluster += 1;
+                }
+            }
+            assert!(cluster >= total_clusters);
+        }
+    }
+
+    #[test]
+    fn test_read_metadata_at_offset() {
+        let layout = Qcow2Layout::new(GIB);
+        let whole = metadata(&layout);
+
+        // Reads that straddle cluster boundaries match the whole image.
+        let offset = CLUSTER_SIZE - 10;
+        let mut buf = vec![0; CLUSTER_SIZE as usize + 20];
+        layout.read_metadata(offset, &mut buf);
+        assert_eq!(
+            &buf[..],
+            &whole[offset as usize..offset as usize + buf.len()]
+        );
+    }
+}
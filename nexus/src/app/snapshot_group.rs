@@ -0,0 +1,346 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Snapshot groups: snapshots of all of an instance's disks, taken together
+
+use super::MAX_DISKS_PER_INSTANCE;
+use super::sagas;
+use nexus_db_lookup::LookupPath;
+use nexus_db_lookup::lookup;
+use nexus_db_queries::authn;
+use nexus_db_queries::authz;
+use nexus_db_queries::context::OpContext;
+use nexus_db_queries::db;
+use nexus_types::external_api::params;
+use nexus_types::identity::Resource;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DataPageParams;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::IdentityMetadataCreateParams;
+use omicron_common::api::external::InternalContext;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::Name;
+use omicron_common::api::external::NameOrId;
+use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::http_pagination::PaginatedBy;
+use std::sync::Arc;
+use uuid::Uuid;
+
+impl super::Nexus {
+    /// Fetches a snapshot group and its members, along with the project it
+    /// belongs to
+    pub(crate) async fn snapshot_group_fetch(
+        &self,
+        opctx: &OpContext,
+        selector: params::SnapshotGroupSelector,
+    ) -> LookupResult<(
+        authz::Project,
+        db::model::SnapshotGroup,
+        Vec<db::model::SnapshotGroupMember>,
+    )> {
+        let (authz_project, group) = match selector {
+            params::SnapshotGroupSelector {
+                snapshot_group: NameOrId::Id(id),
+                project: None,
+            } => {
+                let project_id = self
+                    .db_datastore
+                    .snapshot_group_project_id(opctx, id)
+                    .await?;
+                // Don't reveal the project to callers that can't see it.
+                let (.., authz_project) =
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .project_id(project_id)
+                        .lookup_for(authz::Action::Read)
+                        .await
+                        .map_err(|error| match error {
+                            Error::ObjectNotFound { .. } => {
+                                Error::not_found_by_id(
+                                    ResourceType::SnapshotGroup,
+                                    &id,
+                                )
+                            }
+                            error => error,
+                        })?;
+                (authz_project, NameOrId::Id(id))
+            }
+            params::SnapshotGroupSelector {
+                snapshot_group: NameOrId::Name(name),
+                project: Some(project),
+            } => {
+                let (.., authz_project) = self
+                    .project_lookup(opctx, params::ProjectSelector { project })?
+                    .lookup_for(authz::Action::Read)
+                    .await?;
+                (authz_project, NameOrId::Name(name))
+            }
+            params::SnapshotGroupSelector {
+                snapshot_group: NameOrId::Id(_),
+                ..
+            } => {
+                return Err(Error::invalid_request(
+                    "when providing snapshot_group as an ID, project should \
+                     not be specified",
+                ));
+            }
+            _ => {
+                return Err(Error::invalid_request(
+                    "snapshot_group should either be an ID or project should \
+                     be specified",
+                ));
+            }
+        };
+
+        let group = self
+            .db_datastore
+            .snapshot_group_fetch(opctx, &authz_project, &group)
+            .await?;
+        let members = self
+            .db_datastore
+            .snapshot_group_member_list(opctx, &authz_project, group.id())
+            .await?;
+        Ok((authz_project, group, members))
+    }
+
+    /// Snapshots every disk attached to a stopped instance at the same point
+    pub(crate) async fn snapshot_group_create(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        // Is passed by value due to `instance_name` taking ownership of `self`
+        // below
+        project_lookup: lookup::Project<'_>,
+        params: &params::SnapshotGroupCreate,
+    ) -> CreateResult<(
+        db::model::SnapshotGroup,
+        Vec<db::model::SnapshotGroupMember>,
+    )> {
+        let (authz_silo, authz_project) =
+            project_lookup.lookup_for(authz::Action::CreateChild).await?;
+
+        let (.., authz_instance_project, authz_instance) =
+            match params.instance.clone() {
+                NameOrId::Id(id) => self.instance_lookup(
+                    opctx,
+                    params::InstanceSelector {
+                        instance: NameOrId::Id(id),
+                        project: None,
+                    },
+                )?,
+                NameOrId::Name(name) => {
+                    project_lookup.instance_name_owned(name.into())
+                }
+            }
+            .lookup_for(authz::Action::Read)
+            .await?;
+
+        if authz_instance_project.id() != authz_project.id() {
+            return Err(Error::invalid_request(
+                "can't create a snapshot group of an instance in a different \
+                 project",
+            ));
+        }
+
+        let disks = self
+            .db_datastore
+            .instance_list_disks(
+                opctx,
+                &authz_instance,
+                &PaginatedBy::Name(DataPageParams {
+                    marker: None,
+                    direction: dropshot::PaginationOrder::Ascending,
+                    limit: std::num::NonZeroU32::new(MAX_DISKS_PER_INSTANCE)
+                        .unwrap(),
+                }),
+            )
+            .await?;
+        if disks.is_empty() {
+            return Err(Error::invalid_request(
+                "instance has no disks to snapshot",
+            ));
+        }
+
+        // Propolis can't hold a running guest's I/O while its disks are
+        // snapshotted, so only stopped instances' disks can be snapshotted
+        // together. They're all snapshotted through a Pantry.
+        let instance_and_vmm = self
+            .db_datastore
+            .instance_fetch_with_vmm(opctx, &authz_instance)
+            .await?;
+        if instance_and_vmm.vmm().is_some() {
+            return Err(Error::conflict(
+                "can't create a snapshot group of an instance with an active \
+                 VMM; stop the instance first",
+            ));
+        }
+
+        let saga_params = sagas::snapshot_group_create::Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+            silo_id: authz_silo.id(),
+            project_id: authz_project.id(),
+            instance_id: authz_instance.id(),
+            group_id: Uuid::new_v4(),
+            instance_generation: instance_and_vmm.instance().runtime().r#gen,
+            create_params: params.clone(),
+            disks: disks
+                .iter()
+                .map(|disk| sagas::snapshot_group_create::GroupDisk {
+                    disk_id: disk.id(),
+                    disk_name: disk.name().clone(),
+                    snapshot_id: Uuid::new_v4(),
+                })
+                .collect(),
+        };
+
+        let saga_outputs = self
+            .sagas
+            .saga_execute::<sagas::snapshot_group_create::SagaSnapshotGroupCreate>(
+                saga_params,
+            )
+            .await?;
+
+        let group = saga_outputs
+            .lookup_node_output::<db::model::SnapshotGroup>("finalized_group")
+            .map_err(|e| Error::internal_error(&format!("{:#}", &e)))
+            .internal_context(
+                "looking up output from snapshot group create saga",
+            )?;
+        let members = self
+            .db_datastore
+            .snapshot_group_member_list(opctx, &authz_project, group.id())
+            .await?;
+
+        Ok((group, members))
+    }
+
+    pub(crate) async fn snapshot_group_list(
+        &self,
+        opctx: &OpContext,
+        project_lookup: &lookup::Project<'_>,
+        pagparams: &PaginatedBy<'_>,
+    ) -> ListResultVec<(
+        db::model::SnapshotGroup,
+        Vec<db::model::SnapshotGroupMember>,
+    )> {
+        let (.., authz_project) =
+            project_lookup.lookup_for(authz::Action::ListChildren).await?;
+
+        self.db_datastore
+            .snapshot_group_list(opctx, &authz_project, pagparams)
+            .await
+    }
+
+    /// Deletes a snapshot group, along with its snapshots
+    pub(crate) async fn snapshot_group_delete(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        selector: params::SnapshotGroupSelector,
+    ) -> DeleteResult {
+        let (authz_project, group, members) =
+            self.snapshot_group_fetch(opctx, selector).await?;
+
+        if group.state != db::model::SnapshotGroupState::Ready {
+            return Err(Error::invalid_request(
+                "snapshot group is still being created",
+            ));
+        }
+
+        for member in members {
+            let snapshot_lookup = self.snapshot_lookup(
+                opctx,
+                params::SnapshotSelector {
+                    snapshot: NameOrId::Id(member.snapshot_id),
+                    project: None,
+                },
+            )?;
+            match self.snapshot_delete(opctx, &snapshot_lookup).await {
+                // The snapshot may have been deleted on its own, or by an
+                // earlier attempt to delete the group.
+                Ok(()) | Err(Error::ObjectNotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.db_datastore
+            .snapshot_group_delete(opctx, &authz_project, group.id())
+            .await
+    }
+
+    /// Creates a new disk from each of a snapshot group's snapshots
+    pub(crate) async fn snapshot_group_restore(
+        self: &Arc<Self>,
+        opctx: &OpContext,
+        selector: params::SnapshotGroupSelector,
+        params: &params::SnapshotGroupRestore,
+    ) -> CreateResult<Vec<db::model::Disk>> {
+        let (authz_project, group, members) =
+            self.snapshot_group_fetch(opctx, selector).await?;
+
+        if group.state != db::model::SnapshotGroupState::Ready {
+            return Err(Error::invalid_request(
+                "snapshot group is still being created",
+            ));
+        }
+
+        let mut disks = Vec::with_capacity(members.len());
+        for member in members {
+            let (.., db_snapshot) = LookupPath::new(opctx, &self.db_datastore)
+                .snapshot_id(member.snapshot_id)
+                .fetch()
+                .await?;
+
+            let name: Name = format!(
+                "{}-{}",
+                params.name_prefix, *member.disk_name
+            )
+            .parse()
+            .map_err(|e| {
+                Error::invalid_value(
+                    "name_prefix",
+                    format!(
+                        "can't name the disk restored from the snapshot \
+                             of disk {}: {e}",
+                        *member.disk_name,
+                    ),
+                )
+            })?;
+
+            disks.push(params::DiskCreate {
+                identity: IdentityMetadataCreateParams {
+                    name,
+                    description: format!(
+                        "restored from snapshot group {}",
+                        group.name(),
+                    ),
+                },
+                disk_source: params::DiskSource::Snapshot {
+                    snapshot_id: member.snapshot_id,
+                },
+                size: db_snapshot.size.into(),
+            });
+        }
+
+        let saga_params = sagas::snapshot_group_restore::Params {
+            serialized_authn: authn::saga::Serialized::for_opctx(opctx),
+            project_id: authz_project.id(),
+            group_id: group.id(),
+            disks,
+        };
+
+        let saga_outputs = self
+            .sagas
+            .saga_execute::<sagas::snapshot_group_restore::SagaSnapshotGroupRestore>(
+                saga_params,
+            )
+            .await?;
+
+        saga_outputs
+            .lookup_node_output::<Vec<db::model::Disk>>("created_disks")
+            .map_err(|e| Error::internal_error(&format!("{:#}", &e)))
+            .internal_context(
+                "looking up output from snapshot group restore saga",
+            )
+    }
+}
@@ -0,0 +1,191 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Scheduled snapshot policies
+//!
+//! The policies themselves are carried out by the `snapshot_policy`
+//! background task.
+
+use nexus_db_lookup::LookupPath;
+use nexus_db_lookup::lookup;
+use nexus_db_queries::authz;
+use nexus_db_queries::context::OpContext;
+use nexus_db_queries::db;
+use nexus_types::external_api::params;
+use nexus_types::external_api::params::DiskSelector;
+use nexus_types::identity::Resource;
+use omicron_common::api::external::CreateResult;
+use omicron_common::api::external::DeleteResult;
+use omicron_common::api::external::Error;
+use omicron_common::api::external::ListResultVec;
+use omicron_common::api::external::LookupResult;
+use omicron_common::api::external::NameOrId;
+use omicron_common::api::external::ResourceType;
+use omicron_common::api::external::http_pagination::PaginatedBy;
+
+/// The shortest interval at which a policy may snapshot a disk
+pub const MIN_SNAPSHOT_POLICY_INTERVAL_SECS: u32 = 15 * 60;
+
+/// The most snapshots of each disk that a policy may retain
+pub const MAX_SNAPSHOT_POLICY_RETENTION: u32 = 256;
+
+impl super::Nexus {
+    /// Fetches a snapshot policy, along with the project it belongs to
+    pub(crate) async fn snapshot_policy_fetch(
+        &self,
+        opctx: &OpContext,
+        selector: params::SnapshotPolicySelector,
+    ) -> LookupResult<(authz::Project, db::model::SnapshotPolicy)> {
+        let (authz_project, policy) = match selector {
+            params::SnapshotPolicySelector {
+                snapshot_policy: NameOrId::Id(id),
+                project: None,
+            } => {
+                let project_id = self
+                    .db_datastore
+                    .snapshot_policy_project_id(opctx, id)
+                    .await?;
+                // Don't reveal the project to callers that can't see it.
+                let (.., authz_project) =
+                    LookupPath::new(opctx, &self.db_datastore)
+                        .project_id(project_id)
+                        .lookup_for(authz::Action::Read)
+                        .await
+                        .map_err(|error| match error {
+                            Error::ObjectNotFound { .. } => {
+                                Error::not_found_by_id(
+                                    ResourceType::SnapshotPolicy,
+                                    &id,
+                                )
+                            }
+                            error => error,
+                        })?;
+                (authz_project, NameOrId::Id(id))
+            }
+            params::SnapshotPolicySelector {
+                snapshot_policy: NameOrId::Name(name),
+                project: Some(project),
+            } => {
+                let (.., authz_project) = self
+                    .project_lookup(opctx, params::ProjectSelector { project })?
+                    .lookup_for(authz::Action::Read)
+                    .await?;
+                (authz_project, NameOrId::Name(name))
+            }
+            params::SnapshotPolicySelector {
+                snapshot_policy: NameOrId::Id(_),
+                ..
+            } => {
+                return Err(Error::invalid_request(
+                    "when providing snapshot_policy as an ID, project should \
+                     not be specified",
+                ));
+            }
+            _ => {
+                return Err(Error::invalid_request(
+                    "snapshot_policy should either be an ID or project should \
+                     be specified",
+                ));
+            }
+        };
+
+        let policy = self
+            .db_datastore
+            .snapshot_policy_fetch(opctx, &authz_project, &policy)
+            .await?;
+        Ok((authz_project, policy))
+    }
+
+    pub(crate) async fn snapshot_policy_create(
+        &self,
+        opctx: &OpContext,
+        // Is passed by value due to `disk_name` taking ownership of `self` below
+        project_lookup: lookup::Project<'_>,
+        params: &params::SnapshotPolicyCreate,
+    ) -> CreateResult<db::model::SnapshotPolicy> {
+        let (.., authz_project) =
+            project_lookup.lookup_for(authz::Action::CreateChild).await?;
+
+        if params.interval_secs < MIN_SNAPSHOT_POLICY_INTERVAL_SECS {
+            return Err(Error::invalid_value(
+                "interval_secs",
+                format!(
+                    "snapshots may be taken at most every \
+                     {MIN_SNAPSHOT_POLICY_INTERVAL_SECS} seconds"
+                ),
+            ));
+        }
+        if params.retention_count == 0
+            || params.retention_count > MAX_SNAPSHOT_POLICY_RETENTION
+        {
+            return Err(Error::invalid_value(
+                "retention_count",
+                format!(
+                    "must be between 1 and {MAX_SNAPSHOT_POLICY_RETENTION}"
+                ),
+            ));
+        }
+
+        let disk_id = match params.disk.clone() {
+            None => None,
+            Some(disk) => {
+                let (.., authz_disk_project, authz_disk) = match disk {
+                    NameOrId::Id(id) => self.disk_lookup(
+                        opctx,
+                        DiskSelector { disk: NameOrId::Id(id), project: None },
+                    )?,
+                    NameOrId::Name(name) => {
+                        project_lookup.disk_name_owned(name.into())
+                    }
+                }
+                .lookup_for(authz::Action::Read)
+                .await?;
+
+                if authz_disk_project.id() != authz_project.id() {
+                    return Err(Error::invalid_request(
+                        "can't create a snapshot policy for a disk in a \
+                         different project",
+                    ));
+                }
+                Some(authz_disk.id())
+            }
+        };
+
+        let policy = db::model::SnapshotPolicy::new(
+            authz_project.id(),
+            disk_id,
+            params.clone(),
+        );
+        self.db_datastore
+            .snapshot_policy_create(opctx, &authz_project, policy)
+            .await
+    }
+
+    pub(crate) async fn snapshot_policy_list(
+        &self,
+        opctx: &OpContext,
+        project_lookup: &lookup::Project<'_>,
+        pagparams: &PaginatedBy<'_>,
+    ) -> ListResultVec<db::model::SnapshotPolicy> {
+        let (.., authz_project) =
+            project_lookup.lookup_for(authz::Action::ListChildren).await?;
+
+        self.db_datastore
+            .snapshot_policy_list(opctx, &authz_project, pagparams)
+            .await
+    }
+
+    pub(crate) async fn snapshot_policy_delete(
+        &self,
+        opctx: &OpContext,
+        selector: params::SnapshotPolicySelector,
+    ) -> DeleteResult {
+        let (authz_project, policy) =
+            self.snapshot_policy_fetch(opctx, selector).await?;
+
+        self.db_datastore
+            .snapshot_policy_delete(opctx, &authz_project, policy.id())
+            .await
+    }
+}
@@ -22,6 +22,7 @@ use omicron_uuid_kinds::DatasetUuid;
 use omicron_uuid_kinds::SupportBundleUuid;
 use omicron_uuid_kinds::ZpoolUuid;
 use range_requests::PotentialRange;
+use std::sync::Arc;
 use uuid::Uuid;
 
 /// Describes the type of access to the support bundle
@@ -71,7 +72,7 @@ impl super::Nexus {
         id: SupportBundleUuid,
         query: SupportBundleQueryType,
         head: bool,
-        _range: Option<PotentialRange>,
+        range: Option<PotentialRange>,
     ) -> Result<Response<Body>, Error> {
         // Lookup the bundle, confirm it's accessible
         let (.., bundle) = LookupPath::new(opctx, &self.db_datastore)
@@ -92,7 +93,32 @@ impl super::Nexus {
             .await?;
         let client = self.sled_client(&sled_id).await?;
 
-        // TODO: Use "range"?
+        // The sled agent knows how long the bundle (and each file within it)
+        // is, so let it interpret any range request.
+        let client = match range {
+            Some(range) => {
+                let headers = range.to_headers().map_err(|err| {
+                    Error::invalid_request(&format!("invalid range: {err}"))
+                })?;
+                let dur = std::time::Duration::from_secs(60);
+                let reqwest_client = reqwest::ClientBuilder::new()
+                    .connect_timeout(dur)
+                    .timeout(dur)
+                    .default_headers(headers)
+                    .build()
+                    .map_err(|err| {
+                        Error::internal_error(&format!(
+                            "building sled agent client: {err}"
+                        ))
+                    })?;
+                Arc::new(sled_agent_client::Client::new_with_client(
+                    client.baseurl(),
+                    reqwest_client,
+                    client.inner().clone(),
+                ))
+            }
+            None => client,
+        };
 
         let response = match (query, head) {
             (SupportBundleQueryType::Whole, true) => {
@@ -6,15 +6,32 @@
 
 use bytes::Bytes;
 use dropshot::HttpError;
-use futures::Stream;
+use futures::{Stream, TryStreamExt};
 use nexus_db_model::TufRepoDescription;
 use nexus_db_queries::authz;
 use nexus_db_queries::context::OpContext;
+use nexus_types::external_api::{params, views};
 use omicron_common::api::external::{
-    Error, TufRepoInsertResponse, TufRepoInsertStatus,
+    DataPageParams, Error, ResourceType, TufRepoInsertResponse,
+    TufRepoInsertStatus,
 };
 use semver::Version;
-use update_common::artifacts::{ArtifactsWithPlan, ControlPlaneZonesMode};
+use slog_error_chain::InlineErrorChain;
+use std::num::NonZeroU32;
+use update_common::artifacts::{
+    ArtifactsWithPlan, ControlPlaneZonesMode, RepositoryUpload,
+};
+use update_common::errors::RepositoryError;
+use uuid::Uuid;
+
+/// The largest piece of a resumable TUF repository upload stored in a single
+/// database row.
+const TUF_REPO_UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How many stored pieces of a resumable upload to read from the database at
+/// once when finalizing it.
+const TUF_REPO_UPLOAD_CHUNK_BATCH_SIZE: NonZeroU32 =
+    NonZeroU32::new(16).unwrap();
 
 impl super::Nexus {
     pub(crate) async fn updates_put_repository(
@@ -40,6 +57,16 @@ impl super::Nexus {
         .await
         .map_err(|error| error.to_http_error())?;
 
+        self.updates_insert_repository(opctx, artifacts_with_plan).await
+    }
+
+    /// Records a newly-extracted repository in the database and, if it was
+    /// not already present, hands its artifacts off for replication.
+    async fn updates_insert_repository(
+        &self,
+        opctx: &OpContext,
+        artifacts_with_plan: ArtifactsWithPlan,
+    ) -> Result<TufRepoInsertResponse, HttpError> {
         // Now store the artifacts in the database.
         let response = self
             .db_datastore
@@ -74,6 +101,247 @@ impl super::Nexus {
         Ok(response.into_external())
     }
 
+    pub(crate) async fn updates_repository_upload_create(
+        &self,
+        opctx: &OpContext,
+        params: params::TufRepoUploadCreate,
+    ) -> Result<views::TufRepoUpload, HttpError> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        let _updates_config =
+            self.updates_config.as_ref().ok_or_else(|| {
+                Error::internal_error("updates system not initialized")
+            })?;
+
+        if params.size == 0 {
+            return Err(Error::invalid_request(
+                "repository size must be greater than zero",
+            )
+            .into());
+        }
+
+        let upload = self
+            .db_datastore
+            .tuf_repo_upload_create(
+                opctx,
+                nexus_db_model::TufRepoUpload::new(
+                    params.file_name,
+                    params.size,
+                ),
+            )
+            .await?;
+        Ok(upload.into())
+    }
+
+    pub(crate) async fn updates_repository_upload_view(
+        &self,
+        opctx: &OpContext,
+        upload_id: Uuid,
+    ) -> Result<views::TufRepoUpload, HttpError> {
+        opctx.authorize(authz::Action::Read, &authz::FLEET).await?;
+
+        let upload =
+            self.db_datastore.tuf_repo_upload_get(opctx, upload_id).await?;
+        Ok(upload.into())
+    }
+
+    pub(crate) async fn updates_repository_upload_write(
+        &self,
+        opctx: &OpContext,
+        upload_id: Uuid,
+        offset: u64,
+        body: impl Stream<Item = Result<Bytes, HttpError>> + Send,
+    ) -> Result<views::TufRepoUpload, HttpError> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        // Check the offset and size up front, so that an obviously bad write
+        // is rejected before we read any of the body. The datastore checks
+        // these again as each piece is stored, which is what prevents
+        // concurrent writes from interleaving.
+        let mut upload =
+            self.db_datastore.tuf_repo_upload_get(opctx, upload_id).await?;
+        let bytes_received = upload.bytes_received();
+        if offset != bytes_received {
+            return Err(Error::conflict(format!(
+                "write offset {offset} does not match the number of bytes \
+                 received so far ({bytes_received})"
+            ))
+            .into());
+        }
+        let size = upload.size();
+
+        let mut body = std::pin::pin!(body);
+        let mut buf = Vec::with_capacity(TUF_REPO_UPLOAD_CHUNK_SIZE);
+        let result = loop {
+            let bytes = match body.try_next().await {
+                Ok(Some(bytes)) => bytes,
+                Ok(None) => break Ok(()),
+                // Keep whatever data arrived before the stream failed, so that
+                // the client can resume from there.
+                Err(error) => break Err(error),
+            };
+            let new_len =
+                upload.bytes_received() + buf.len() as u64 + bytes.len() as u64;
+            if new_len > size {
+                break Err(Error::invalid_request(format!(
+                    "write would extend the repository past its declared \
+                     size of {size} bytes"
+                ))
+                .into());
+            }
+
+            let mut bytes = &bytes[..];
+            while !bytes.is_empty() {
+                let n = bytes.len().min(TUF_REPO_UPLOAD_CHUNK_SIZE - buf.len());
+                buf.extend_from_slice(&bytes[..n]);
+                bytes = &bytes[n..];
+                if buf.len() == TUF_REPO_UPLOAD_CHUNK_SIZE {
+                    upload = self
+                        .db_datastore
+                        .tuf_repo_upload_append(
+                            opctx,
+                            upload_id,
+                            upload.bytes_received(),
+                            std::mem::take(&mut buf),
+                        )
+                        .await?;
+                }
+            }
+        };
+
+        // Store whatever is left over, including after a failed stream.
+        if !buf.is_empty() {
+            upload = self
+                .db_datastore
+                .tuf_repo_upload_append(
+                    opctx,
+                    upload_id,
+                    upload.bytes_received(),
+                    buf,
+                )
+                .await?;
+        }
+        result.map(|()| upload.into())
+    }
+
+    pub(crate) async fn updates_repository_upload_finalize(
+        &self,
+        opctx: &OpContext,
+        upload_id: Uuid,
+        params: params::TufRepoUploadFinalize,
+    ) -> Result<TufRepoInsertResponse, HttpError> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        let upload =
+            self.db_datastore.tuf_repo_upload_get(opctx, upload_id).await?;
+        if upload.bytes_received() != upload.size() {
+            return Err(Error::invalid_request(format!(
+                "upload is incomplete: received {} of {} bytes",
+                upload.bytes_received(),
+                upload.size(),
+            ))
+            .into());
+        }
+
+        // Copy the data out of the database into a temporary file, which is
+        // what the repository extraction code works with.
+        let mut repository = RepositoryUpload::new()
+            .await
+            .map_err(|error| error.to_http_error())?;
+        let mut marker = None;
+        loop {
+            let pagparams = DataPageParams {
+                marker: marker.as_ref(),
+                direction: dropshot::PaginationOrder::Ascending,
+                limit: TUF_REPO_UPLOAD_CHUNK_BATCH_SIZE,
+            };
+            let chunks = self
+                .db_datastore
+                .tuf_repo_upload_chunk_list(opctx, upload_id, &pagparams)
+                .await?;
+            let Some(last) = chunks.last() else {
+                break;
+            };
+            marker = Some(last.byte_offset);
+            for chunk in chunks {
+                if chunk.byte_offset as u64 != repository.bytes_received() {
+                    return Err(Error::internal_error(&format!(
+                        "TUF repository upload {upload_id} has a gap at byte \
+                         {}",
+                        repository.bytes_received(),
+                    ))
+                    .into());
+                }
+                repository
+                    .append(&chunk.data)
+                    .await
+                    .map_err(|error| error.to_http_error())?;
+            }
+        }
+        if repository.bytes_received() != upload.size() {
+            // Another request deleted the upload while we were reading it.
+            return Err(Error::not_found_by_id(
+                ResourceType::TufRepoUpload,
+                &upload_id,
+            )
+            .into());
+        }
+
+        let file_name = upload.file_name;
+        let artifacts_with_plan = repository
+            .finish(
+                Some(params.sha256),
+                Some(file_name),
+                ControlPlaneZonesMode::Split,
+                &self.log,
+            )
+            .await
+            .map_err(|error| {
+                if let RepositoryError::RepositoryHashMismatch { .. } = &error {
+                    warn!(
+                        opctx.log,
+                        "TUF repository upload does not match its checksum";
+                        "upload_id" => %upload_id,
+                        InlineErrorChain::new(&error),
+                    );
+                }
+                error.to_http_error()
+            })?;
+
+        let response =
+            self.updates_insert_repository(opctx, artifacts_with_plan).await?;
+
+        // Only discard the upload once the repository has been recorded, so
+        // that a failed finalization can be retried. If another request
+        // finalized or deleted the upload in the meantime, there's nothing
+        // left to do; and if we fail to delete it, the upload will eventually
+        // be discarded as idle.
+        match self.db_datastore.tuf_repo_upload_delete(opctx, upload_id).await {
+            Ok(()) | Err(Error::ObjectNotFound { .. }) => {}
+            Err(error) => {
+                warn!(
+                    opctx.log,
+                    "failed to delete finalized TUF repository upload";
+                    "upload_id" => %upload_id,
+                    InlineErrorChain::new(&error),
+                );
+            }
+        }
+
+        Ok(response)
+    }
+
+    pub(crate) async fn updates_repository_upload_delete(
+        &self,
+        opctx: &OpContext,
+        upload_id: Uuid,
+    ) -> Result<(), HttpError> {
+        opctx.authorize(authz::Action::Modify, &authz::FLEET).await?;
+
+        self.db_datastore.tuf_repo_upload_delete(opctx, upload_id).await?;
+        Ok(())
+    }
+
     pub(crate) async fn updates_get_repository(
         &self,
         opctx: &OpContext,
@@ -48,6 +48,7 @@ use nexus_db_queries::db::model::Name;
 use nexus_external_api::*;
 use nexus_types::{
     authn::cookies::Cookies,
+    deployment::BlueprintApproval,
     external_api::{
         params::SystemMetricsPathParam,
         shared::{BfdStatus, ProbeInfo},
@@ -99,6 +100,7 @@ use omicron_common::api::external::http_pagination::marker_for_name;
 use omicron_common::api::external::http_pagination::marker_for_name_or_id;
 use omicron_common::api::external::http_pagination::name_or_id_pagination;
 use omicron_common::bail_unless;
+use omicron_uuid_kinds::BlueprintUuid;
 use omicron_uuid_kinds::GenericUuid;
 use omicron_uuid_kinds::SupportBundleUuid;
 use propolis_client::support::WebSocketStream;
@@ -2028,6 +2030,37 @@ impl NexusExternalApi for NexusExternalApiImpl {
             .await
     }
 
+    async fn disk_resize(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::DiskPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+        resize_params: TypedBody<params::DiskResize>,
+    ) -> Result<HttpResponseOk<Disk>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let params = resize_params.into_inner();
+            let disk_selector = params::DiskSelector {
+                disk: path.disk,
+                project: query.project,
+            };
+            let disk_lookup = nexus.disk_lookup(&opctx, disk_selector)?;
+
+            let disk = nexus.disk_resize(&opctx, &disk_lookup, &params).await?;
+
+            Ok(HttpResponseOk(disk.into()))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
     // Instances
 
     async fn instance_list(
@@ -2220,6 +2253,78 @@ impl NexusExternalApi for NexusExternalApiImpl {
             .await
     }
 
+    async fn instance_migrate(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<params::OptionalProjectSelector>,
+        path_params: Path<params::InstancePath>,
+        migrate_params: TypedBody<params::InstanceMigrate>,
+    ) -> Result<HttpResponseAccepted<Instance>, HttpError> {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let path = path_params.into_inner();
+        let query = query_params.into_inner();
+        let migrate = migrate_params.into_inner();
+        let instance_selector = params::InstanceSelector {
+            project: query.project,
+            instance: path.instance,
+        };
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let instance_lookup =
+                nexus.instance_lookup(&opctx, instance_selector)?;
+            let instance = nexus
+                .instance_migrate(&opctx, &instance_lookup, migrate)
+                .await?;
+            Ok(HttpResponseAccepted(instance.into()))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn instance_migration_list(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<PaginatedById<params::OptionalProjectSelector>>,
+        path_params: Path<params::InstancePath>,
+    ) -> Result<HttpResponseOk<ResultsPage<views::InstanceMigration>>, HttpError>
+    {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let path = path_params.into_inner();
+        let query = query_params.into_inner();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let pag_params = data_page_params_for(&rqctx, &query)?;
+            let scan_params = ScanById::from_query(&query)?;
+            let instance_selector = params::InstanceSelector {
+                project: scan_params.selector.project.clone(),
+                instance: path.instance,
+            };
+            let instance_lookup =
+                nexus.instance_lookup(&opctx, instance_selector)?;
+            let migrations = nexus
+                .instance_migration_list(&opctx, &instance_lookup, &pag_params)
+                .await?
+                .into_iter()
+                .map(|m| m.into())
+                .collect();
+            Ok(HttpResponseOk(ScanById::results_page(
+                &query,
+                migrations,
+                &|_, migration: &views::InstanceMigration| migration.id,
+            )?))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
     async fn instance_start(
         rqctx: RequestContext<ApiContext>,
         query_params: Query<params::OptionalProjectSelector>,
@@ -2602,6 +2707,160 @@ impl NexusExternalApi for NexusExternalApiImpl {
             .await
     }
 
+    // Instance templates
+
+    async fn instance_template_list(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<PaginatedByNameOrId<params::ProjectSelector>>,
+    ) -> Result<HttpResponseOk<ResultsPage<views::InstanceTemplate>>, HttpError>
+    {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let query = query_params.into_inner();
+            let pag_params = data_page_params_for(&rqctx, &query)?;
+            let scan_params = ScanByNameOrId::from_query(&query)?;
+            let paginated_by = name_or_id_pagination(&pag_params, scan_params)?;
+            let project_lookup =
+                nexus.project_lookup(&opctx, scan_params.selector.clone())?;
+            let templates = nexus
+                .instance_template_list(&opctx, &project_lookup, &paginated_by)
+                .await?
+                .into_iter()
+                .map(|t| t.try_into())
+                .collect::<Result<Vec<_>, Error>>()?;
+            Ok(HttpResponseOk(ScanByNameOrId::results_page(
+                &query,
+                templates,
+                &marker_for_name_or_id,
+            )?))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn instance_template_create(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<params::ProjectSelector>,
+        new_template: TypedBody<params::InstanceTemplateCreate>,
+    ) -> Result<HttpResponseCreated<views::InstanceTemplate>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let query = query_params.into_inner();
+            let new_template_params = new_template.into_inner();
+            let project_lookup = nexus.project_lookup(&opctx, query)?;
+            let template = nexus
+                .instance_template_create(
+                    &opctx,
+                    &project_lookup,
+                    &new_template_params,
+                )
+                .await?;
+            Ok(HttpResponseCreated(template.try_into()?))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn instance_template_view(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::InstanceTemplatePath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseOk<views::InstanceTemplate>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let template_selector = params::InstanceTemplateSelector {
+                project: query.project,
+                instance_template: path.instance_template,
+            };
+            let (_, template) = nexus
+                .instance_template_fetch(&opctx, template_selector)
+                .await?;
+            Ok(HttpResponseOk(template.try_into()?))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn instance_template_delete(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::InstanceTemplatePath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseDeleted, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let template_selector = params::InstanceTemplateSelector {
+                project: query.project,
+                instance_template: path.instance_template,
+            };
+            nexus.instance_template_delete(&opctx, template_selector).await?;
+            Ok(HttpResponseDeleted())
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn instance_template_instantiate(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::InstanceTemplatePath>,
+        query_params: Query<params::OptionalProjectSelector>,
+        instantiate_params: TypedBody<params::InstanceTemplateInstantiate>,
+    ) -> Result<HttpResponseOk<views::InstanceTemplateInstantiation>, HttpError>
+    {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let template_selector = params::InstanceTemplateSelector {
+                project: query.project,
+                instance_template: path.instance_template,
+            };
+            let instantiation = nexus
+                .instance_template_instantiate(
+                    &opctx,
+                    template_selector,
+                    &instantiate_params.into_inner(),
+                )
+                .await?;
+            Ok(HttpResponseOk(instantiation))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
     // Affinity Groups
 
     async fn affinity_group_list(
@@ -4326,6 +4585,38 @@ impl NexusExternalApi for NexusExternalApiImpl {
             .await
     }
 
+    async fn image_upload(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::ImageUploadPath>,
+        query_params: Query<params::ImageUpload>,
+        body: StreamingBody,
+    ) -> Result<HttpResponseCreated<Image>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let name = path_params.into_inner().image;
+            let upload = query_params.into_inner();
+            let content_length = rqctx
+                .request
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok());
+            let body = body.into_stream();
+            let image = nexus
+                .image_upload(&opctx, name, upload, content_length, body)
+                .await?;
+            Ok(HttpResponseCreated(image.into()))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
     async fn image_view(
         rqctx: RequestContext<ApiContext>,
         path_params: Path<params::ImagePath>,
@@ -4851,19 +5142,468 @@ impl NexusExternalApi for NexusExternalApiImpl {
             .await
     }
 
-    // VPCs
-
-    async fn vpc_list(
+    async fn snapshot_export_create(
         rqctx: RequestContext<ApiContext>,
-        query_params: Query<PaginatedByNameOrId<params::ProjectSelector>>,
-    ) -> Result<HttpResponseOk<ResultsPage<Vpc>>, HttpError> {
+        path_params: Path<params::SnapshotPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+        export_params: TypedBody<params::SnapshotExportCreate>,
+    ) -> Result<HttpResponseCreated<views::SnapshotExport>, HttpError> {
         let apictx = rqctx.context();
         let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
             let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
             let query = query_params.into_inner();
-            let pag_params = data_page_params_for(&rqctx, &query)?;
-            let scan_params = ScanByNameOrId::from_query(&query)?;
-            let paginated_by = name_or_id_pagination(&pag_params, scan_params)?;
+            let export_params = export_params.into_inner();
+            let snapshot_selector = params::SnapshotSelector {
+                project: query.project,
+                snapshot: path.snapshot,
+            };
+            let snapshot_lookup =
+                nexus.snapshot_lookup(&opctx, snapshot_selector)?;
+            let export = nexus
+                .snapshot_export_create(
+                    &opctx,
+                    &snapshot_lookup,
+                    &export_params,
+                )
+                .await?;
+            Ok(HttpResponseCreated(export.into()))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_export_view(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotExportPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseOk<views::SnapshotExport>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let snapshot_selector = params::SnapshotSelector {
+                project: query.project,
+                snapshot: path.snapshot.snapshot,
+            };
+            let snapshot_lookup =
+                nexus.snapshot_lookup(&opctx, snapshot_selector)?;
+            let export = nexus
+                .snapshot_export_view(&opctx, &snapshot_lookup, path.export_id)
+                .await?;
+            Ok(HttpResponseOk(export.into()))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_export_delete(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotExportPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseDeleted, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let snapshot_selector = params::SnapshotSelector {
+                project: query.project,
+                snapshot: path.snapshot.snapshot,
+            };
+            let snapshot_lookup =
+                nexus.snapshot_lookup(&opctx, snapshot_selector)?;
+            nexus
+                .snapshot_export_delete(
+                    &opctx,
+                    &snapshot_lookup,
+                    path.export_id,
+                )
+                .await?;
+            Ok(HttpResponseDeleted())
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_export_download(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotExportPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<Response<Body>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let snapshot_selector = params::SnapshotSelector {
+                project: query.project,
+                snapshot: path.snapshot.snapshot,
+            };
+            let snapshot_lookup =
+                nexus.snapshot_lookup(&opctx, snapshot_selector)?;
+            let head = false;
+            let range = rqctx.range();
+
+            let body = nexus
+                .snapshot_export_download(
+                    &opctx,
+                    &snapshot_lookup,
+                    path.export_id,
+                    head,
+                    range,
+                )
+                .await?;
+            Ok(body)
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_export_head(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotExportPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<Response<Body>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let snapshot_selector = params::SnapshotSelector {
+                project: query.project,
+                snapshot: path.snapshot.snapshot,
+            };
+            let snapshot_lookup =
+                nexus.snapshot_lookup(&opctx, snapshot_selector)?;
+            let head = true;
+            let range = rqctx.range();
+
+            let body = nexus
+                .snapshot_export_download(
+                    &opctx,
+                    &snapshot_lookup,
+                    path.export_id,
+                    head,
+                    range,
+                )
+                .await?;
+            Ok(body)
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    // Snapshot policies
+
+    async fn snapshot_policy_list(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<PaginatedByNameOrId<params::ProjectSelector>>,
+    ) -> Result<HttpResponseOk<ResultsPage<views::SnapshotPolicy>>, HttpError>
+    {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let query = query_params.into_inner();
+            let pag_params = data_page_params_for(&rqctx, &query)?;
+            let scan_params = ScanByNameOrId::from_query(&query)?;
+            let paginated_by = name_or_id_pagination(&pag_params, scan_params)?;
+            let project_lookup =
+                nexus.project_lookup(&opctx, scan_params.selector.clone())?;
+            let policies = nexus
+                .snapshot_policy_list(&opctx, &project_lookup, &paginated_by)
+                .await?
+                .into_iter()
+                .map(|p| p.into())
+                .collect();
+            Ok(HttpResponseOk(ScanByNameOrId::results_page(
+                &query,
+                policies,
+                &marker_for_name_or_id,
+            )?))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_policy_create(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<params::ProjectSelector>,
+        new_policy: TypedBody<params::SnapshotPolicyCreate>,
+    ) -> Result<HttpResponseCreated<views::SnapshotPolicy>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let query = query_params.into_inner();
+            let new_policy_params = new_policy.into_inner();
+            let project_lookup = nexus.project_lookup(&opctx, query)?;
+            let policy = nexus
+                .snapshot_policy_create(
+                    &opctx,
+                    project_lookup,
+                    &new_policy_params,
+                )
+                .await?;
+            Ok(HttpResponseCreated(policy.into()))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_policy_view(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotPolicyPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseOk<views::SnapshotPolicy>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let policy_selector = params::SnapshotPolicySelector {
+                project: query.project,
+                snapshot_policy: path.snapshot_policy,
+            };
+            let (_, policy) =
+                nexus.snapshot_policy_fetch(&opctx, policy_selector).await?;
+            Ok(HttpResponseOk(policy.into()))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_policy_delete(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotPolicyPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseDeleted, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let policy_selector = params::SnapshotPolicySelector {
+                project: query.project,
+                snapshot_policy: path.snapshot_policy,
+            };
+            nexus.snapshot_policy_delete(&opctx, policy_selector).await?;
+            Ok(HttpResponseDeleted())
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    // Snapshot groups
+
+    async fn snapshot_group_list(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<PaginatedByNameOrId<params::ProjectSelector>>,
+    ) -> Result<HttpResponseOk<ResultsPage<views::SnapshotGroup>>, HttpError>
+    {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let query = query_params.into_inner();
+            let pag_params = data_page_params_for(&rqctx, &query)?;
+            let scan_params = ScanByNameOrId::from_query(&query)?;
+            let paginated_by = name_or_id_pagination(&pag_params, scan_params)?;
+            let project_lookup =
+                nexus.project_lookup(&opctx, scan_params.selector.clone())?;
+            let groups = nexus
+                .snapshot_group_list(&opctx, &project_lookup, &paginated_by)
+                .await?
+                .into_iter()
+                .map(|(group, members)| group.into_view(members))
+                .collect();
+            Ok(HttpResponseOk(ScanByNameOrId::results_page(
+                &query,
+                groups,
+                &marker_for_name_or_id,
+            )?))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_group_create(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<params::ProjectSelector>,
+        new_group: TypedBody<params::SnapshotGroupCreate>,
+    ) -> Result<HttpResponseCreated<views::SnapshotGroup>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let query = query_params.into_inner();
+            let new_group_params = new_group.into_inner();
+            let project_lookup = nexus.project_lookup(&opctx, query)?;
+            let (group, members) = nexus
+                .snapshot_group_create(
+                    &opctx,
+                    project_lookup,
+                    &new_group_params,
+                )
+                .await?;
+            Ok(HttpResponseCreated(group.into_view(members)))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_group_view(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotGroupPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseOk<views::SnapshotGroup>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let group_selector = params::SnapshotGroupSelector {
+                project: query.project,
+                snapshot_group: path.snapshot_group,
+            };
+            let (_, group, members) =
+                nexus.snapshot_group_fetch(&opctx, group_selector).await?;
+            Ok(HttpResponseOk(group.into_view(members)))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_group_delete(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotGroupPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+    ) -> Result<HttpResponseDeleted, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let group_selector = params::SnapshotGroupSelector {
+                project: query.project,
+                snapshot_group: path.snapshot_group,
+            };
+            nexus.snapshot_group_delete(&opctx, group_selector).await?;
+            Ok(HttpResponseDeleted())
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn snapshot_group_restore(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::SnapshotGroupPath>,
+        query_params: Query<params::OptionalProjectSelector>,
+        restore_params: TypedBody<params::SnapshotGroupRestore>,
+    ) -> Result<HttpResponseCreated<Vec<Disk>>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let nexus = &apictx.context.nexus;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let restore_params = restore_params.into_inner();
+            let group_selector = params::SnapshotGroupSelector {
+                project: query.project,
+                snapshot_group: path.snapshot_group,
+            };
+            let disks = nexus
+                .snapshot_group_restore(&opctx, group_selector, &restore_params)
+                .await?
+                .into_iter()
+                .map(|disk| disk.into())
+                .collect();
+            Ok(HttpResponseCreated(disks))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    // VPCs
+
+    async fn vpc_list(
+        rqctx: RequestContext<ApiContext>,
+        query_params: Query<PaginatedByNameOrId<params::ProjectSelector>>,
+    ) -> Result<HttpResponseOk<ResultsPage<Vpc>>, HttpError> {
+        let apictx = rqctx.context();
+        let handler = async {
+            let nexus = &apictx.context.nexus;
+            let query = query_params.into_inner();
+            let pag_params = data_page_params_for(&rqctx, &query)?;
+            let scan_params = ScanByNameOrId::from_query(&query)?;
+            let paginated_by = name_or_id_pagination(&pag_params, scan_params)?;
             let opctx =
                 crate::context::op_context_for_external_api(&rqctx).await?;
             let project_lookup =
@@ -6480,6 +7220,128 @@ impl NexusExternalApi for NexusExternalApiImpl {
             .await
     }
 
+    async fn system_update_repository_upload_create(
+        rqctx: RequestContext<ApiContext>,
+        params: TypedBody<params::TufRepoUploadCreate>,
+    ) -> Result<HttpResponseCreated<views::TufRepoUpload>, HttpError> {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let upload = nexus
+                .updates_repository_upload_create(&opctx, params.into_inner())
+                .await?;
+            Ok(HttpResponseCreated(upload))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn system_update_repository_upload_view(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::TufRepoUploadPath>,
+    ) -> Result<HttpResponseOk<views::TufRepoUpload>, HttpError> {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let path = path_params.into_inner();
+            let upload = nexus
+                .updates_repository_upload_view(&opctx, path.upload_id)
+                .await?;
+            Ok(HttpResponseOk(upload))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn system_update_repository_upload_write(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::TufRepoUploadPath>,
+        query_params: Query<params::TufRepoUploadWriteParams>,
+        body: StreamingBody,
+    ) -> Result<HttpResponseOk<views::TufRepoUpload>, HttpError> {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let path = path_params.into_inner();
+            let query = query_params.into_inner();
+            let upload = nexus
+                .updates_repository_upload_write(
+                    &opctx,
+                    path.upload_id,
+                    query.offset,
+                    body.into_stream(),
+                )
+                .await?;
+            Ok(HttpResponseOk(upload))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn system_update_repository_upload_finalize(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::TufRepoUploadPath>,
+        params: TypedBody<params::TufRepoUploadFinalize>,
+    ) -> Result<HttpResponseOk<TufRepoInsertResponse>, HttpError> {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let path = path_params.into_inner();
+            let response = nexus
+                .updates_repository_upload_finalize(
+                    &opctx,
+                    path.upload_id,
+                    params.into_inner(),
+                )
+                .await?;
+            Ok(HttpResponseOk(response))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn system_update_repository_upload_delete(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::TufRepoUploadPath>,
+    ) -> Result<HttpResponseDeleted, HttpError> {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let path = path_params.into_inner();
+            nexus
+                .updates_repository_upload_delete(&opctx, path.upload_id)
+                .await?;
+            Ok(HttpResponseDeleted())
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
     async fn system_update_get_repository(
         rqctx: RequestContext<ApiContext>,
         path_params: Path<params::UpdatesGetRepositoryParams>,
@@ -6601,6 +7463,54 @@ impl NexusExternalApi for NexusExternalApiImpl {
             .await
     }
 
+    async fn system_update_blueprint_propose(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::BlueprintPath>,
+        params: TypedBody<params::BlueprintPropose>,
+    ) -> Result<HttpResponseOk<BlueprintApproval>, HttpError> {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let path = path_params.into_inner();
+            let blueprint_id =
+                BlueprintUuid::from_untyped_uuid(path.blueprint_id);
+            let approval = nexus
+                .blueprint_propose(&opctx, blueprint_id, params.into_inner())
+                .await?;
+            Ok(HttpResponseOk(approval))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn system_update_blueprint_approve(
+        rqctx: RequestContext<ApiContext>,
+        path_params: Path<params::BlueprintPath>,
+    ) -> Result<HttpResponseOk<BlueprintApproval>, HttpError> {
+        let apictx = rqctx.context();
+        let nexus = &apictx.context.nexus;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_external_api(&rqctx).await?;
+            let path = path_params.into_inner();
+            let blueprint_id =
+                BlueprintUuid::from_untyped_uuid(path.blueprint_id);
+            let approval =
+                nexus.blueprint_approve(&opctx, blueprint_id).await?;
+            Ok(HttpResponseOk(approval))
+        };
+        apictx
+            .context
+            .external_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
     // Silo users
 
     async fn user_list(
@@ -19,11 +19,17 @@ use dropshot::ResultsPage;
 use dropshot::TypedBody;
 use nexus_internal_api::*;
 use nexus_types::deployment::Blueprint;
+use nexus_types::deployment::BlueprintApproval;
 use nexus_types::deployment::BlueprintMetadata;
 use nexus_types::deployment::BlueprintTarget;
+use nexus_types::deployment::BlueprintTargetAuditEntry;
 use nexus_types::deployment::BlueprintTargetSet;
 use nexus_types::deployment::ClickhousePolicy;
+use nexus_types::deployment::ExecutionPlan;
 use nexus_types::deployment::OximeterReadPolicy;
+use nexus_types::deployment::ZonePlacementPolicy;
+use nexus_types::external_api::params::InstanceMigrate;
+use nexus_types::external_api::params::InstanceSelector;
 use nexus_types::external_api::params::PhysicalDiskPath;
 use nexus_types::external_api::params::SledSelector;
 use nexus_types::external_api::params::UninitializedSledId;
@@ -41,6 +47,7 @@ use nexus_types::internal_api::views::MgsUpdateDriverStatus;
 use nexus_types::internal_api::views::Saga;
 use nexus_types::internal_api::views::to_list;
 use omicron_common::api::external::Instance;
+use omicron_common::api::external::NameOrId;
 use omicron_common::api::external::http_pagination::PaginatedById;
 use omicron_common::api::external::http_pagination::ScanById;
 use omicron_common::api::external::http_pagination::ScanParams;
@@ -54,8 +61,8 @@ use omicron_common::api::internal::nexus::RepairFinishInfo;
 use omicron_common::api::internal::nexus::RepairProgress;
 use omicron_common::api::internal::nexus::RepairStartInfo;
 use omicron_common::api::internal::nexus::SledVmmState;
+use omicron_uuid_kinds::BlueprintUuid;
 use omicron_uuid_kinds::GenericUuid;
-use omicron_uuid_kinds::InstanceUuid;
 use std::collections::BTreeMap;
 
 type NexusApiDescription = ApiDescription<ApiContext>;
@@ -209,11 +216,18 @@ impl NexusInternalApi for NexusInternalApiImpl {
         let handler = async {
             let opctx =
                 crate::context::op_context_for_internal_api(&rqctx).await;
+            let instance_lookup = nexus.instance_lookup(
+                &opctx,
+                InstanceSelector {
+                    project: None,
+                    instance: NameOrId::Id(path.instance_id),
+                },
+            )?;
             let instance = nexus
                 .instance_migrate(
                     &opctx,
-                    InstanceUuid::from_untyped_uuid(path.instance_id),
-                    migrate,
+                    &instance_lookup,
+                    InstanceMigrate { dst_sled_id: Some(migrate.dst_sled_id) },
                 )
                 .await?;
             Ok(HttpResponseOk(instance.into()))
@@ -811,6 +825,72 @@ impl NexusInternalApi for NexusInternalApiImpl {
             .await
     }
 
+    async fn blueprint_approval_view(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<nexus_types::external_api::params::BlueprintPath>,
+    ) -> Result<HttpResponseOk<BlueprintApproval>, HttpError> {
+        let apictx = &rqctx.context().context;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_internal_api(&rqctx).await;
+            let nexus = &apictx.nexus;
+            let path = path_params.into_inner();
+            let blueprint_id =
+                BlueprintUuid::from_untyped_uuid(path.blueprint_id);
+            let approval =
+                nexus.blueprint_approval_view(&opctx, blueprint_id).await?;
+            Ok(HttpResponseOk(approval))
+        };
+        apictx
+            .internal_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn blueprint_target_audit_list(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<nexus_types::external_api::params::BlueprintPath>,
+    ) -> Result<HttpResponseOk<Vec<BlueprintTargetAuditEntry>>, HttpError> {
+        let apictx = &rqctx.context().context;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_internal_api(&rqctx).await;
+            let nexus = &apictx.nexus;
+            let path = path_params.into_inner();
+            let blueprint_id =
+                BlueprintUuid::from_untyped_uuid(path.blueprint_id);
+            let entries =
+                nexus.blueprint_target_audit_list(&opctx, blueprint_id).await?;
+            Ok(HttpResponseOk(entries))
+        };
+        apictx
+            .internal_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn blueprint_execution_plan(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<nexus_types::external_api::params::BlueprintPath>,
+    ) -> Result<HttpResponseOk<ExecutionPlan>, HttpError> {
+        let apictx = &rqctx.context().context;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_internal_api(&rqctx).await;
+            let nexus = &apictx.nexus;
+            let path = path_params.into_inner();
+            let blueprint_id =
+                BlueprintUuid::from_untyped_uuid(path.blueprint_id);
+            let plan =
+                nexus.blueprint_plan_execution(&opctx, blueprint_id).await?;
+            Ok(HttpResponseOk(plan))
+        };
+        apictx
+            .internal_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
     async fn blueprint_regenerate(
         rqctx: RequestContext<Self::Context>,
     ) -> Result<HttpResponseOk<Blueprint>, HttpError> {
@@ -1034,4 +1114,48 @@ impl NexusInternalApi for NexusInternalApiImpl {
             .instrument_dropshot_handler(&rqctx, handler)
             .await
     }
+
+    async fn zone_placement_policy_get(
+        rqctx: RequestContext<Self::Context>,
+    ) -> Result<HttpResponseOk<ZonePlacementPolicy>, HttpError> {
+        let apictx = &rqctx.context().context;
+        let handler = async {
+            let nexus = &apictx.nexus;
+            let opctx =
+                crate::context::op_context_for_internal_api(&rqctx).await;
+            let policy = nexus
+                .datastore()
+                .zone_placement_policy_get_latest(&opctx)
+                .await?;
+            Ok(HttpResponseOk(policy))
+        };
+        apictx
+            .internal_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
+
+    async fn zone_placement_policy_set(
+        rqctx: RequestContext<Self::Context>,
+        policy: TypedBody<ZonePlacementPolicy>,
+    ) -> Result<HttpResponseUpdatedNoContent, HttpError> {
+        let apictx = &rqctx.context().context;
+        let nexus = &apictx.nexus;
+        let handler = async {
+            let opctx =
+                crate::context::op_context_for_internal_api(&rqctx).await;
+            nexus
+                .datastore()
+                .zone_placement_policy_insert_latest_version(
+                    &opctx,
+                    &policy.into_inner(),
+                )
+                .await?;
+            Ok(HttpResponseUpdatedNoContent())
+        };
+        apictx
+            .internal_latencies
+            .instrument_dropshot_handler(&rqctx, handler)
+            .await
+    }
 }
@@ -451,3 +451,26 @@ pub async fn run_tuf_artifact_replication_step(
     assert_eq!(status.last_run_counters.err(), 0);
     status
 }
+
+pub async fn run_snapshot_policy(
+    internal_client: &ClientTestContext,
+) -> SnapshotPolicyStatus {
+    let last_background_task =
+        activate_background_task(&internal_client, "snapshot_policy").await;
+
+    let LastResult::Completed(last_result_completed) =
+        last_background_task.last
+    else {
+        panic!(
+            "unexpected {:?} returned from snapshot_policy task",
+            last_background_task.last,
+        );
+    };
+
+    let status = serde_json::from_value::<SnapshotPolicyStatus>(
+        last_result_completed.details,
+    )
+    .unwrap();
+    assert!(status.errors.is_empty(), "{:?}", status.errors);
+    status
+}
@@ -252,6 +252,8 @@ impl<'a> RequestBuilder<'a> {
             http::header::CONTENT_LENGTH,
             http::header::CONTENT_TYPE,
             http::header::ACCEPT_RANGES,
+            http::header::CONTENT_RANGE,
+            http::header::ETAG,
         ]);
         self.expect_response_header(
             http::header::CONTENT_TYPE,
@@ -260,6 +262,21 @@ impl<'a> RequestBuilder<'a> {
         .expect_response_header(http::header::ACCEPT_RANGES, "bytes")
     }
 
+    /// Tells the request to expect a download of a disk image, or of a range
+    /// of one
+    pub fn expect_image_download(mut self) -> Self {
+        self.allowed_headers.as_mut().unwrap().extend([
+            http::header::ACCEPT_RANGES,
+            http::header::CONTENT_RANGE,
+            http::header::ETAG,
+        ]);
+        self.expect_response_header(
+            http::header::CONTENT_TYPE,
+            "application/octet-stream",
+        )
+        .expect_response_header(http::header::ACCEPT_RANGES, "bytes")
+    }
+
     /// Tells the request to initiate and expect a WebSocket upgrade handshake.
     /// This also sets the request method to GET.
     pub fn expect_websocket_handshake(mut self) -> Self {
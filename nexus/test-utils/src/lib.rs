@@ -958,6 +958,7 @@ impl<'a, N: NexusServer> ControlPlaneTestContextBuilder<'a, N> {
                 time_created: Utc::now(),
                 creator: "nexus-test-utils".to_string(),
                 comment: "initial test blueprint".to_string(),
+                planning_report: None,
             }
         };
 
@@ -1894,6 +1895,7 @@ pub async fn start_dns_server(
         store,
         &dns_server::dns_server::Config {
             bind_address: "[::1]:0".parse().unwrap(),
+            query_log: Default::default(),
         },
         &dropshot::ConfigDropshot {
             bind_address: "[::1]:0".parse().unwrap(),
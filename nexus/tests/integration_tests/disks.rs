@@ -31,10 +31,13 @@ use nexus_test_utils::resource_helpers::create_disk;
 use nexus_test_utils::resource_helpers::create_instance;
 use nexus_test_utils::resource_helpers::create_instance_with;
 use nexus_test_utils::resource_helpers::create_project;
+use nexus_test_utils::resource_helpers::object_create;
+use nexus_test_utils::resource_helpers::object_create_error;
 use nexus_test_utils::resource_helpers::objects_list_page_authz;
 use nexus_test_utils::wait_for_producer;
 use nexus_test_utils_macros::nexus_test;
 use nexus_types::external_api::params;
+use nexus_types::external_api::views;
 use nexus_types::identity::Asset;
 use nexus_types::silo::DEFAULT_SILO_ID;
 use omicron_common::api::external::Disk;
@@ -1069,6 +1072,183 @@ async fn test_disk_too_big(cptestctx: &ControlPlaneTestContext) {
     .unwrap();
 }
 
+// Tests growing a disk, and that shrinking one is rejected.
+#[nexus_test]
+async fn test_disk_resize(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.server_context().nexus;
+    let datastore = nexus.datastore();
+
+    // Growing the disk needs a second set of regions on distinct zpools.
+    let _test =
+        DiskTestBuilder::new(&cptestctx).with_zpool_count(6).build().await;
+    let project_id = create_project_and_pool(client).await;
+    let disk = create_disk(&client, PROJECT_NAME, DISK_NAME).await;
+    assert_eq!(disk.size, ByteCount::from_gibibytes_u32(1));
+
+    let resize_url =
+        format!("/v1/disks/{DISK_NAME}/resize?project={PROJECT_NAME}");
+    let resized: Disk = NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &resize_url)
+            .body(Some(&params::DiskResize {
+                size: ByteCount::from_gibibytes_u32(2),
+            }))
+            .expect_status(Some(StatusCode::OK)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert_eq!(resized.identity.id, disk.identity.id);
+    assert_eq!(resized.size, ByteCount::from_gibibytes_u32(2));
+    assert_eq!(resized.state, DiskState::Detached);
+
+    // The disk is now backed by two sets of regions, and its new size is
+    // accounted for.
+    let opctx =
+        OpContext::for_tests(cptestctx.logctx.log.new(o!()), datastore.clone());
+    let (.., db_disk) = LookupPath::new(&opctx, datastore)
+        .disk_id(disk.identity.id)
+        .fetch()
+        .await
+        .unwrap();
+    assert_eq!(
+        datastore
+            .get_allocated_regions(db_disk.volume_id())
+            .await
+            .unwrap()
+            .len(),
+        2 * REGION_REDUNDANCY_THRESHOLD,
+    );
+    let virtual_provisioning_collection = datastore
+        .virtual_provisioning_collection_get(&opctx, project_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        virtual_provisioning_collection.virtual_disk_bytes_provisioned.0,
+        ByteCount::from_gibibytes_u32(2),
+    );
+
+    // Disks can't be shrunk, or "resized" to their current size.
+    for size in [1, 2] {
+        let error = NexusRequest::new(
+            RequestBuilder::new(client, Method::POST, &resize_url)
+                .body(Some(&params::DiskResize {
+                    size: ByteCount::from_gibibytes_u32(size),
+                }))
+                .expect_status(Some(StatusCode::BAD_REQUEST)),
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .unwrap()
+        .parsed_body::<dropshot::HttpErrorResponseBody>()
+        .unwrap();
+        assert_eq!(
+            error.message,
+            format!(
+                "unsupported value for \"size\": new size must be larger \
+                than the current size {}",
+                ByteCount::from_gibibytes_u32(2).to_bytes(),
+            )
+        );
+    }
+
+    let disk: Disk = NexusRequest::object_get(client, &get_disk_url(DISK_NAME))
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .unwrap()
+        .parsed_body()
+        .unwrap();
+    assert_eq!(disk.size, ByteCount::from_gibibytes_u32(2));
+}
+
+#[nexus_test]
+async fn test_disk_resize_running_instance(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+    let nexus = &cptestctx.server.server_context().nexus;
+
+    let _test =
+        DiskTestBuilder::new(&cptestctx).with_zpool_count(6).build().await;
+    create_project_and_pool(client).await;
+    let disk = create_disk(&client, PROJECT_NAME, DISK_NAME).await;
+
+    // Attach the disk to a stopped instance, then start it.
+    let instance = create_instance(&client, PROJECT_NAME, INSTANCE_NAME).await;
+    let instance_id = InstanceUuid::from_untyped_uuid(instance.identity.id);
+    set_instance_state(&client, INSTANCE_NAME, "stop").await;
+    instance_simulate(nexus, &instance_id).await;
+    instance_wait_for_state(client, instance_id, InstanceState::Stopped).await;
+
+    let attached_disk = disk_post(
+        client,
+        &get_disk_attach_url(&instance.identity.id.into()),
+        disk.identity.name.clone(),
+    )
+    .await;
+    assert_eq!(attached_disk.state, DiskState::Attached(instance.identity.id));
+
+    set_instance_state(&client, INSTANCE_NAME, "start").await;
+    instance_simulate(nexus, &instance_id).await;
+    instance_wait_for_state(client, instance_id, InstanceState::Running).await;
+
+    // Propolis can't grow a disk that's in use, so resizing the disk of a
+    // running instance is refused.
+    let resize_url =
+        format!("/v1/disks/{DISK_NAME}/resize?project={PROJECT_NAME}");
+    let error = NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &resize_url)
+            .body(Some(&params::DiskResize {
+                size: ByteCount::from_gibibytes_u32(2),
+            }))
+            .expect_status(Some(StatusCode::CONFLICT)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body::<dropshot::HttpErrorResponseBody>()
+    .unwrap();
+    assert!(
+        error.message.starts_with(&format!(
+            "cannot resize disk {} while instance {} has an active vmm",
+            disk.identity.id, instance.identity.id,
+        )),
+        "unexpected error: {}",
+        error.message,
+    );
+
+    // The failed resize left the disk alone.
+    let disk = disk_get(&client, &get_disk_url(DISK_NAME)).await;
+    assert_eq!(disk.size, ByteCount::from_gibibytes_u32(1));
+
+    // Once the instance is stopped, the disk can be resized.
+    set_instance_state(&client, INSTANCE_NAME, "stop").await;
+    instance_simulate(nexus, &instance_id).await;
+    instance_wait_for_state(client, instance_id, InstanceState::Stopped).await;
+
+    let resized: Disk = NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &resize_url)
+            .body(Some(&params::DiskResize {
+                size: ByteCount::from_gibibytes_u32(2),
+            }))
+            .expect_status(Some(StatusCode::OK)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body()
+    .unwrap();
+    assert_eq!(resized.size, ByteCount::from_gibibytes_u32(2));
+    assert_eq!(resized.state, DiskState::Attached(instance.identity.id));
+}
+
 #[nexus_test]
 async fn test_disk_virtual_provisioning_collection(
     cptestctx: &ControlPlaneTestContext,
@@ -1980,6 +2160,72 @@ async fn test_disk_create_for_importing(cptestctx: &ControlPlaneTestContext) {
     disks_eq(&disks[0], &disk);
 }
 
+#[nexus_test]
+async fn test_disk_create_from_disk(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+    create_project_and_pool(client).await;
+    let disks_url = get_disks_url();
+
+    let source_disk = create_disk(&client, PROJECT_NAME, DISK_NAME).await;
+
+    // Clone the disk into a larger one.
+    let clone_name = "cloned-rainsticks";
+    let clone: Disk = object_create(
+        client,
+        &disks_url,
+        &params::DiskCreate {
+            identity: IdentityMetadataCreateParams {
+                name: clone_name.parse().unwrap(),
+                description: String::from("also sells rainsticks"),
+            },
+            disk_source: params::DiskSource::Disk {
+                disk_id: source_disk.identity.id,
+            },
+            size: ByteCount::from_gibibytes_u32(2),
+        },
+    )
+    .await;
+
+    assert_eq!(clone.identity.name.as_str(), clone_name);
+    assert_eq!(clone.state, DiskState::Detached);
+    assert_eq!(clone.size, ByteCount::from_gibibytes_u32(2));
+    assert_eq!(clone.block_size, source_disk.block_size);
+
+    // Both disks are there, but the snapshot taken to make the clone has
+    // been cleaned up.
+    let disks = disks_list(&client, &disks_url).await;
+    assert_eq!(disks.len(), 2);
+
+    let snapshots = objects_list_page_authz::<views::Snapshot>(
+        client,
+        &format!("/v1/snapshots?project={}", PROJECT_NAME),
+    )
+    .await
+    .items;
+    assert!(snapshots.is_empty());
+
+    // The source disk must be in the same project as the new disk.
+    create_project(client, PROJECT_NAME_2).await;
+    let error = object_create_error(
+        client,
+        &format!("/v1/disks?project={}", PROJECT_NAME_2),
+        &params::DiskCreate {
+            identity: IdentityMetadataCreateParams {
+                name: clone_name.parse().unwrap(),
+                description: String::from("also sells rainsticks"),
+            },
+            disk_source: params::DiskSource::Disk {
+                disk_id: source_disk.identity.id,
+            },
+            size: ByteCount::from_gibibytes_u32(1),
+        },
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+    assert_eq!(error.message, "source disk does not belong to this project");
+}
+
 #[nexus_test]
 async fn test_project_delete_disk_no_auth_idempotent(
     cptestctx: &ControlPlaneTestContext,
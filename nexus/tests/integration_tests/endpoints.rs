@@ -23,6 +23,7 @@ use nexus_types::external_api::shared;
 use nexus_types::external_api::shared::IpRange;
 use nexus_types::external_api::shared::Ipv4Range;
 use nexus_types::external_api::views::SledProvisionPolicy;
+use nexus_types::external_api::views::SnapshotExportFormat;
 use omicron_common::api::external::AddressLotKind;
 use omicron_common::api::external::AffinityPolicy;
 use omicron_common::api::external::AllowedSourceIps;
@@ -43,6 +44,7 @@ use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::str::FromStr;
 use std::sync::LazyLock;
+use tufaceous_artifact::ArtifactHash;
 
 type DiskTest<'a> =
     nexus_test_utils::resource_helpers::DiskTest<'a, omicron_nexus::Server>;
@@ -397,6 +399,15 @@ pub static DEMO_DISK_METRICS_URL: LazyLock<String> = LazyLock::new(|| {
         *DEMO_PROJECT_SELECTOR,
     )
 });
+pub static DEMO_DISK_RESIZE_URL: LazyLock<String> = LazyLock::new(|| {
+    format!("/v1/disks/{}/resize?{}", *DEMO_DISK_NAME, *DEMO_PROJECT_SELECTOR)
+});
+pub static DEMO_DISK_RESIZE: LazyLock<params::DiskResize> =
+    LazyLock::new(|| params::DiskResize {
+        size: ByteCount::from_gibibytes_u32(
+            2 * (DiskTest::DEFAULT_ZPOOL_SIZE_GIB / 5),
+        ),
+    });
 
 // Related to importing blocks from an external source
 pub static DEMO_IMPORT_DISK_NAME: LazyLock<Name> =
@@ -563,6 +574,19 @@ pub static DEMO_INSTANCE_REBOOT_URL: LazyLock<String> = LazyLock::new(|| {
         *DEMO_INSTANCE_NAME, *DEMO_PROJECT_SELECTOR
     )
 });
+pub static DEMO_INSTANCE_MIGRATE_URL: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "/v1/instances/{}/migrate?{}",
+        *DEMO_INSTANCE_NAME, *DEMO_PROJECT_SELECTOR
+    )
+});
+pub static DEMO_INSTANCE_MIGRATIONS_URL: LazyLock<String> =
+    LazyLock::new(|| {
+        format!(
+            "/v1/instances/{}/migrations?{}",
+            *DEMO_INSTANCE_NAME, *DEMO_PROJECT_SELECTOR
+        )
+    });
 pub static DEMO_INSTANCE_SERIAL_URL: LazyLock<String> = LazyLock::new(|| {
     format!(
         "/v1/instances/{}/serial-console?{}",
@@ -686,6 +710,53 @@ pub static DEMO_INSTANCE_UPDATE: LazyLock<params::InstanceUpdate> =
         memory: ByteCount::from_gibibytes_u32(16),
     });
 
+// Instance templates
+pub static DEMO_INSTANCE_TEMPLATE_NAME: LazyLock<Name> =
+    LazyLock::new(|| "demo-instance-template".parse().unwrap());
+pub static DEMO_INSTANCE_TEMPLATES_URL: LazyLock<String> =
+    LazyLock::new(|| {
+        format!("/v1/instance-templates?{}", *DEMO_PROJECT_SELECTOR)
+    });
+pub static DEMO_INSTANCE_TEMPLATE_URL: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "/v1/instance-templates/{}?{}",
+        *DEMO_INSTANCE_TEMPLATE_NAME, *DEMO_PROJECT_SELECTOR
+    )
+});
+pub static DEMO_INSTANCE_TEMPLATE_INSTANTIATE_URL: LazyLock<String> =
+    LazyLock::new(|| {
+        format!(
+            "/v1/instance-templates/{}/instantiate?{}",
+            *DEMO_INSTANCE_TEMPLATE_NAME, *DEMO_PROJECT_SELECTOR
+        )
+    });
+pub static DEMO_INSTANCE_TEMPLATE_CREATE: LazyLock<
+    params::InstanceTemplateCreate,
+> = LazyLock::new(|| params::InstanceTemplateCreate {
+    identity: IdentityMetadataCreateParams {
+        name: DEMO_INSTANCE_TEMPLATE_NAME.clone(),
+        description: String::from(""),
+    },
+    ncpus: InstanceCpuCount(1),
+    memory: ByteCount::from_gibibytes_u32(16),
+    user_data: vec![],
+    network_interfaces: params::InstanceNetworkInterfaceAttachment::Default,
+    external_ips: vec![],
+    boot_disk: None,
+    ssh_public_keys: Some(Vec::new()),
+    auto_restart_policy: Default::default(),
+});
+pub static DEMO_INSTANCE_TEMPLATE_INSTANTIATE: LazyLock<
+    params::InstanceTemplateInstantiate,
+> = LazyLock::new(|| params::InstanceTemplateInstantiate {
+    name_pattern: String::from("demo-templated-instance-{n}"),
+    count: 1,
+    first_index: 0,
+    start: false,
+    affinity_groups: Vec::new(),
+    anti_affinity_groups: Vec::new(),
+});
+
 // The instance needs a network interface, too.
 pub static DEMO_INSTANCE_NIC_NAME: LazyLock<Name> =
     LazyLock::new(|| nexus_defaults::DEFAULT_PRIMARY_NIC_NAME.parse().unwrap());
@@ -901,6 +972,15 @@ pub static DEMO_SILO_DEMOTE_IMAGE_URL: LazyLock<String> = LazyLock::new(|| {
     )
 });
 
+pub static DEMO_PROJECT_UPLOAD_IMAGE_URL: LazyLock<String> =
+    LazyLock::new(|| {
+        format!(
+            "/v1/images/demo-uploaded-image/upload?project={}&description=\
+             &os=fake-os&version=1.0&format=raw&block_size=512",
+            *DEMO_PROJECT_NAME
+        )
+    });
+
 pub static DEMO_IMAGE_CREATE: LazyLock<params::ImageCreate> =
     LazyLock::new(|| params::ImageCreate {
         identity: IdentityMetadataCreateParams {
@@ -999,6 +1079,70 @@ pub static DEMO_SNAPSHOT_CREATE: LazyLock<params::SnapshotCreate> =
         },
         disk: DEMO_DISK_NAME.clone().into(),
     });
+pub static DEMO_SNAPSHOT_EXPORTS_URL: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "/v1/snapshots/{}/exports?project={}",
+        *DEMO_SNAPSHOT_NAME, *DEMO_PROJECT_NAME
+    )
+});
+pub static DEMO_SNAPSHOT_EXPORT_CREATE: LazyLock<params::SnapshotExportCreate> =
+    LazyLock::new(|| params::SnapshotExportCreate {
+        format: SnapshotExportFormat::Raw,
+    });
+
+// Snapshot policies
+pub static DEMO_SNAPSHOT_POLICY_NAME: LazyLock<Name> =
+    LazyLock::new(|| "demo-snapshot-policy".parse().unwrap());
+pub static DEMO_SNAPSHOT_POLICIES_URL: LazyLock<String> = LazyLock::new(|| {
+    format!("/v1/snapshot-policies?{}", *DEMO_PROJECT_SELECTOR)
+});
+pub static DEMO_SNAPSHOT_POLICY_URL: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "/v1/snapshot-policies/{}?{}",
+        *DEMO_SNAPSHOT_POLICY_NAME, *DEMO_PROJECT_SELECTOR
+    )
+});
+pub static DEMO_SNAPSHOT_POLICY_CREATE: LazyLock<params::SnapshotPolicyCreate> =
+    LazyLock::new(|| params::SnapshotPolicyCreate {
+        identity: IdentityMetadataCreateParams {
+            name: DEMO_SNAPSHOT_POLICY_NAME.clone(),
+            description: String::from(""),
+        },
+        disk: Some(DEMO_DISK_NAME.clone().into()),
+        interval_secs: 6 * 60 * 60,
+        retention_count: 14,
+    });
+
+// Snapshot groups
+pub static DEMO_SNAPSHOT_GROUP_NAME: LazyLock<Name> =
+    LazyLock::new(|| "demo-snapshot-group".parse().unwrap());
+pub static DEMO_SNAPSHOT_GROUPS_URL: LazyLock<String> =
+    LazyLock::new(|| format!("/v1/snapshot-groups?{}", *DEMO_PROJECT_SELECTOR));
+pub static DEMO_SNAPSHOT_GROUP_URL: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "/v1/snapshot-groups/{}?{}",
+        *DEMO_SNAPSHOT_GROUP_NAME, *DEMO_PROJECT_SELECTOR
+    )
+});
+pub static DEMO_SNAPSHOT_GROUP_RESTORE_URL: LazyLock<String> =
+    LazyLock::new(|| {
+        format!(
+            "/v1/snapshot-groups/{}/restore?{}",
+            *DEMO_SNAPSHOT_GROUP_NAME, *DEMO_PROJECT_SELECTOR
+        )
+    });
+pub static DEMO_SNAPSHOT_GROUP_CREATE: LazyLock<params::SnapshotGroupCreate> =
+    LazyLock::new(|| params::SnapshotGroupCreate {
+        identity: IdentityMetadataCreateParams {
+            name: DEMO_SNAPSHOT_GROUP_NAME.clone(),
+            description: String::from(""),
+        },
+        instance: DEMO_INSTANCE_NAME.clone().into(),
+    });
+pub static DEMO_SNAPSHOT_GROUP_RESTORE: LazyLock<params::SnapshotGroupRestore> =
+    LazyLock::new(|| params::SnapshotGroupRestore {
+        name_prefix: "restored".parse().unwrap(),
+    });
 
 // SSH keys
 pub const DEMO_SSHKEYS_URL: &'static str = "/v1/me/ssh-keys";
@@ -1159,6 +1303,22 @@ pub static DEMO_TARGET_RELEASE: LazyLock<params::SetTargetReleaseParams> =
     LazyLock::new(|| params::SetTargetReleaseParams {
         system_version: Version::new(0, 0, 0),
     });
+pub static DEMO_TUF_REPO_UPLOAD_CREATE: LazyLock<params::TufRepoUploadCreate> =
+    LazyLock::new(|| params::TufRepoUploadCreate {
+        file_name: String::from("demo-repo.zip"),
+        size: 1024,
+    });
+pub static DEMO_TUF_REPO_UPLOAD_FINALIZE: LazyLock<
+    params::TufRepoUploadFinalize,
+> = LazyLock::new(|| params::TufRepoUploadFinalize {
+    sha256: ArtifactHash([0; 32]),
+});
+pub static DEMO_BLUEPRINT_PROPOSE: LazyLock<params::BlueprintPropose> =
+    LazyLock::new(|| params::BlueprintPropose {
+        required_approver: "4c3b2a1e-6d5f-4e8a-9b7c-0d1e2f3a4b5c"
+            .parse()
+            .unwrap(),
+    });
 
 /// Describes an API endpoint to be verified by the "unauthorized" test
 ///
@@ -1898,6 +2058,14 @@ pub static VERIFY_ENDPOINTS: LazyLock<Vec<VerifyEndpoint>> =
                 unprivileged_access: UnprivilegedAccess::None,
                 allowed_methods: vec![AllowedMethod::Get],
             },
+            VerifyEndpoint {
+                url: &DEMO_DISK_RESIZE_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_DISK_RESIZE).unwrap(),
+                )],
+            },
             VerifyEndpoint {
                 url: &DEMO_INSTANCE_DISKS_URL,
                 visibility: Visibility::Protected,
@@ -2107,6 +2275,14 @@ pub static VERIFY_ENDPOINTS: LazyLock<Vec<VerifyEndpoint>> =
                     serde_json::value::Value::Null,
                 )],
             },
+            VerifyEndpoint {
+                url: &DEMO_PROJECT_UPLOAD_IMAGE_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::value::Value::Null,
+                )],
+            },
             /* Snapshots */
             VerifyEndpoint {
                 url: &DEMO_PROJECT_URL_SNAPSHOTS,
@@ -2129,6 +2305,68 @@ pub static VERIFY_ENDPOINTS: LazyLock<Vec<VerifyEndpoint>> =
                     AllowedMethod::Delete,
                 ],
             },
+            VerifyEndpoint {
+                url: &DEMO_SNAPSHOT_EXPORTS_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_SNAPSHOT_EXPORT_CREATE)
+                        .unwrap(),
+                )],
+            },
+            /* Snapshot policies */
+            VerifyEndpoint {
+                url: &DEMO_SNAPSHOT_POLICIES_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![
+                    AllowedMethod::Get,
+                    AllowedMethod::Post(
+                        serde_json::to_value(&*DEMO_SNAPSHOT_POLICY_CREATE)
+                            .unwrap(),
+                    ),
+                ],
+            },
+            VerifyEndpoint {
+                url: &DEMO_SNAPSHOT_POLICY_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![
+                    AllowedMethod::Get,
+                    AllowedMethod::Delete,
+                ],
+            },
+            /* Snapshot groups */
+            VerifyEndpoint {
+                url: &DEMO_SNAPSHOT_GROUPS_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![
+                    AllowedMethod::Get,
+                    AllowedMethod::Post(
+                        serde_json::to_value(&*DEMO_SNAPSHOT_GROUP_CREATE)
+                            .unwrap(),
+                    ),
+                ],
+            },
+            VerifyEndpoint {
+                url: &DEMO_SNAPSHOT_GROUP_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![
+                    AllowedMethod::Get,
+                    AllowedMethod::Delete,
+                ],
+            },
+            VerifyEndpoint {
+                url: &DEMO_SNAPSHOT_GROUP_RESTORE_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_SNAPSHOT_GROUP_RESTORE)
+                        .unwrap(),
+                )],
+            },
             /* Instances */
             VerifyEndpoint {
                 url: &DEMO_PROJECT_URL_INSTANCES,
@@ -2177,6 +2415,21 @@ pub static VERIFY_ENDPOINTS: LazyLock<Vec<VerifyEndpoint>> =
                     serde_json::Value::Null,
                 )],
             },
+            VerifyEndpoint {
+                url: &DEMO_INSTANCE_MIGRATE_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::to_value(params::InstanceMigrate::default())
+                        .unwrap(),
+                )],
+            },
+            VerifyEndpoint {
+                url: &DEMO_INSTANCE_MIGRATIONS_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Get],
+            },
             VerifyEndpoint {
                 url: &DEMO_INSTANCE_SERIAL_URL,
                 visibility: Visibility::Protected,
@@ -2191,6 +2444,37 @@ pub static VERIFY_ENDPOINTS: LazyLock<Vec<VerifyEndpoint>> =
                 unprivileged_access: UnprivilegedAccess::None,
                 allowed_methods: vec![AllowedMethod::GetWebsocket],
             },
+            /* Instance templates */
+            VerifyEndpoint {
+                url: &DEMO_INSTANCE_TEMPLATES_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![
+                    AllowedMethod::Get,
+                    AllowedMethod::Post(
+                        serde_json::to_value(&*DEMO_INSTANCE_TEMPLATE_CREATE)
+                            .unwrap(),
+                    ),
+                ],
+            },
+            VerifyEndpoint {
+                url: &DEMO_INSTANCE_TEMPLATE_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![
+                    AllowedMethod::Get,
+                    AllowedMethod::Delete,
+                ],
+            },
+            VerifyEndpoint {
+                url: &DEMO_INSTANCE_TEMPLATE_INSTANTIATE_URL,
+                visibility: Visibility::Protected,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_INSTANCE_TEMPLATE_INSTANTIATE)
+                        .unwrap(),
+                )],
+            },
             /* Instance NICs */
             VerifyEndpoint {
                 url: &DEMO_INSTANCE_NICS_URL,
@@ -2385,6 +2669,65 @@ pub static VERIFY_ENDPOINTS: LazyLock<Vec<VerifyEndpoint>> =
                 // privileged users. That is captured by GetUnimplemented.
                 allowed_methods: vec![AllowedMethod::GetUnimplemented],
             },
+            VerifyEndpoint {
+                url: "/v1/system/update/repository-uploads",
+                visibility: Visibility::Public,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_TUF_REPO_UPLOAD_CREATE)
+                        .unwrap(),
+                )],
+            },
+            VerifyEndpoint {
+                url: "/v1/system/update/repository-uploads/\
+                      9b1c3a5e-8d2f-4c6b-a0e7-1f3d5b7c9e02",
+                visibility: Visibility::Public,
+                unprivileged_access: UnprivilegedAccess::None,
+                // Uploads only exist while they're in progress, so there's
+                // nothing to find here.
+                allowed_methods: vec![
+                    AllowedMethod::GetNonexistent,
+                    AllowedMethod::Delete,
+                ],
+            },
+            VerifyEndpoint {
+                url: "/v1/system/update/repository-uploads/\
+                      9b1c3a5e-8d2f-4c6b-a0e7-1f3d5b7c9e02/data?offset=0",
+                visibility: Visibility::Public,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Put(
+                    // In reality this is part of the contents of a zip file.
+                    serde_json::Value::Null,
+                )],
+            },
+            VerifyEndpoint {
+                url: "/v1/system/update/repository-uploads/\
+                      9b1c3a5e-8d2f-4c6b-a0e7-1f3d5b7c9e02/finalize",
+                visibility: Visibility::Public,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_TUF_REPO_UPLOAD_FINALIZE)
+                        .unwrap(),
+                )],
+            },
+            VerifyEndpoint {
+                url: "/v1/system/update/blueprints/\
+                      7e2d4f6a-1b3c-4d5e-8f9a-0b1c2d3e4f5a/propose",
+                visibility: Visibility::Public,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::to_value(&*DEMO_BLUEPRINT_PROPOSE).unwrap(),
+                )],
+            },
+            VerifyEndpoint {
+                url: "/v1/system/update/blueprints/\
+                      7e2d4f6a-1b3c-4d5e-8f9a-0b1c2d3e4f5a/approve",
+                visibility: Visibility::Public,
+                unprivileged_access: UnprivilegedAccess::None,
+                allowed_methods: vec![AllowedMethod::Post(
+                    serde_json::value::Value::Null,
+                )],
+            },
             VerifyEndpoint {
                 url: "/v1/system/update/target-release",
                 visibility: Visibility::Public,
@@ -5,8 +5,12 @@
 //! Tests images support in the API
 
 use dropshot::ResultsPage;
+use dropshot::test_util::ClientTestContext;
 use http::StatusCode;
 use http::method::Method;
+use httptest::Expectation;
+use httptest::matchers::request;
+use httptest::responders::status_code;
 use nexus_db_queries::db::fixed_data::silo::DEFAULT_SILO;
 use nexus_db_queries::db::fixed_data::silo_user::USER_TEST_UNPRIVILEGED;
 use nexus_test_utils::http_testing::AuthnMode;
@@ -21,8 +25,10 @@ use nexus_types::external_api::shared::SiloRole;
 use nexus_types::external_api::{params, views};
 use nexus_types::identity::Asset;
 use nexus_types::identity::Resource;
+use omicron_common::api::external::Digest;
 use omicron_common::api::external::Disk;
 use omicron_common::api::external::{ByteCount, IdentityMetadataCreateParams};
+use sha2::Digest as _;
 
 type ControlPlaneTestContext =
     nexus_test_utils::ControlPlaneTestContext<omicron_nexus::Server>;
@@ -574,3 +580,194 @@ async fn test_image_deletion_permissions(cptestctx: &ControlPlaneTestContext) {
     .await
     .expect("should be able to delete project image as unpriv user!");
 }
+
+/// Contents of the image files used by the import tests
+fn image_contents() -> Vec<u8> {
+    (0..8192u32).map(|i| b"alpine"[(i % 6) as usize]).collect()
+}
+
+/// Wraps `contents` in a qcow2 image file with 4 KiB clusters
+fn make_qcow2(contents: &[u8]) -> Vec<u8> {
+    const CLUSTER: usize = 4096;
+    let virtual_size = contents.len().next_multiple_of(CLUSTER);
+    assert!(virtual_size <= CLUSTER * CLUSTER / 8, "image is too large");
+
+    // The header is followed by the L1 table, a single L2 table, and the
+    // data clusters, in that order.
+    let l1_offset = CLUSTER;
+    let l2_offset = 2 * CLUSTER;
+    let data_offset = 3 * CLUSTER;
+    let mut file = vec![0; data_offset];
+    let mut put = |at: usize, bytes: &[u8]| {
+        file[at..at + bytes.len()].copy_from_slice(bytes);
+    };
+    put(0, b"QFI\xfb");
+    put(4, &2u32.to_be_bytes());
+    put(20, &12u32.to_be_bytes());
+    put(24, &(virtual_size as u64).to_be_bytes());
+    put(36, &1u32.to_be_bytes());
+    put(40, &(l1_offset as u64).to_be_bytes());
+    put(l1_offset, &(l2_offset as u64 | (1 << 63)).to_be_bytes());
+    for i in 0..virtual_size / CLUSTER {
+        let offset = (data_offset + i * CLUSTER) as u64;
+        put(l2_offset + i * 8, &(offset | (1 << 63)).to_be_bytes());
+    }
+
+    file.extend_from_slice(contents);
+    file.resize(data_offset + virtual_size, 0);
+    file
+}
+
+/// Checks that the intermediate disk and snapshot used to import an image
+/// have been cleaned up
+async fn assert_import_cleaned_up(client: &ClientTestContext) {
+    let disks = NexusRequest::object_get(
+        client,
+        &format!("/v1/disks?project={}", PROJECT_NAME),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute_and_parse_unwrap::<ResultsPage<Disk>>()
+    .await
+    .items;
+    assert!(disks.is_empty(), "import left disks behind: {disks:?}");
+
+    let snapshots = NexusRequest::object_get(
+        client,
+        &format!("/v1/snapshots?project={}", PROJECT_NAME),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute_and_parse_unwrap::<ResultsPage<views::Snapshot>>()
+    .await
+    .items;
+    assert!(snapshots.is_empty(), "import left snapshots behind");
+}
+
+#[nexus_test]
+async fn test_image_import_from_url(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+    create_project(client, PROJECT_NAME).await;
+
+    let contents = make_qcow2(&image_contents());
+    let digest = hex::encode(sha2::Sha256::digest(&contents));
+    let server = httptest::Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/alpine.qcow2"))
+            .respond_with(status_code(200).body(contents)),
+    );
+    let url = server.url_str("/alpine.qcow2");
+
+    let image = NexusRequest::objects_post(
+        client,
+        &get_project_images_url(PROJECT_NAME),
+        &get_image_create(params::ImageSource::Url {
+            url: url.clone(),
+            format: params::ImageImportFormat::Qcow2,
+            block_size: params::BlockSize::try_from(512).unwrap(),
+            expected_digest: Some(params::ExpectedDigest::Sha256(
+                digest.clone(),
+            )),
+        }),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute_and_parse_unwrap::<views::Image>()
+    .await;
+
+    assert_eq!(image.identity.name, "alpine-edge");
+    assert_eq!(image.url, Some(url));
+    assert_eq!(image.digest, Some(Digest::Sha256(digest)));
+    assert_eq!(image.block_size.to_bytes(), 512);
+    assert_eq!(image.size, ByteCount::from_gibibytes_u32(1));
+
+    assert_import_cleaned_up(client).await;
+}
+
+#[nexus_test]
+async fn test_image_import_from_url_into_silo_fails(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+
+    let error = NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, "/v1/images")
+            .body(Some(&get_image_create(params::ImageSource::Url {
+                url: String::from("http://[::1]:1/alpine.raw"),
+                format: params::ImageImportFormat::Raw,
+                block_size: params::BlockSize::try_from(512).unwrap(),
+                expected_digest: None,
+            })))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute_and_parse_unwrap::<dropshot::HttpErrorResponseBody>()
+    .await;
+    assert!(error.message.contains("into a project"), "{}", error.message);
+}
+
+#[nexus_test]
+async fn test_image_upload(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+    create_project(client, PROJECT_NAME).await;
+
+    let contents = String::from_utf8(image_contents()).unwrap();
+    let digest = hex::encode(sha2::Sha256::digest(&contents));
+    let upload_url = format!(
+        "/v1/images/alpine-edge/upload?project={}&description=uploaded\
+         &os=alpine&version=edge&format=raw&block_size=512&sha256={}",
+        PROJECT_NAME, digest,
+    );
+
+    let image = NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &upload_url)
+            .raw_body(Some(contents))
+            .expect_status(Some(StatusCode::CREATED)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute_and_parse_unwrap::<views::Image>()
+    .await;
+
+    assert_eq!(image.identity.name, "alpine-edge");
+    assert_eq!(image.url, None);
+    assert_eq!(image.digest, Some(Digest::Sha256(digest)));
+
+    assert_import_cleaned_up(client).await;
+}
+
+#[nexus_test]
+async fn test_image_upload_digest_mismatch(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+    create_project(client, PROJECT_NAME).await;
+
+    let contents = String::from_utf8(image_contents()).unwrap();
+    let upload_url = format!(
+        "/v1/images/alpine-edge/upload?project={}&description=uploaded\
+         &os=alpine&version=edge&format=raw&block_size=512&sha256={}",
+        PROJECT_NAME,
+        "0".repeat(64),
+    );
+
+    let error = NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &upload_url)
+            .raw_body(Some(contents))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute_and_parse_unwrap::<dropshot::HttpErrorResponseBody>()
+    .await;
+    assert!(error.message.contains("SHA-256 digest"), "{}", error.message);
+
+    // No image was created, and nothing was left behind.
+    let images =
+        NexusRequest::object_get(client, &get_project_images_url(PROJECT_NAME))
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute_and_parse_unwrap::<ResultsPage<views::Image>>()
+            .await
+            .items;
+    assert!(images.is_empty());
+    assert_import_cleaned_up(client).await;
+}
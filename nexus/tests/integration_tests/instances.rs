@@ -90,8 +90,8 @@ use dropshot::{HttpErrorResponseBody, ResultsPage};
 
 use nexus_test_utils::identity_eq;
 use nexus_test_utils::resource_helpers::{
-    create_instance, create_instance_with, create_instance_with_error,
-    create_project,
+    create_affinity_group, create_instance, create_instance_with,
+    create_instance_with_error, create_project,
 };
 use nexus_test_utils_macros::nexus_test;
 use nexus_types::external_api::shared::SiloRole;
@@ -898,6 +898,107 @@ async fn test_instance_migrate(cptestctx: &ControlPlaneTestContext) {
     assert_eq!(migration.source_state, MigrationState::Completed.into());
 }
 
+#[nexus_test(extra_sled_agents = 1)]
+async fn test_instance_migrate_external(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let apictx = &cptestctx.server.server_context();
+    let nexus = &apictx.nexus;
+    let instance_name = "kestrel-survey";
+
+    create_project_and_pool(&client).await;
+    let instance_url = get_instance_url(instance_name);
+
+    // Explicitly create an instance with no disks. Simulated sled agent assumes
+    // that disks are co-located with their instances.
+    let instance = nexus_test_utils::resource_helpers::create_instance_with(
+        client,
+        PROJECT_NAME,
+        instance_name,
+        &params::InstanceNetworkInterfaceAttachment::Default,
+        Vec::<params::InstanceDiskAttachment>::new(),
+        Vec::<params::ExternalIpCreate>::new(),
+        true,
+        Default::default(),
+    )
+    .await;
+    let instance_id = InstanceUuid::from_untyped_uuid(instance.identity.id);
+
+    instance_simulate(nexus, &instance_id).await;
+    let instance_next = instance_get(&client, &instance_url).await;
+    assert_eq!(instance_next.runtime.run_state, InstanceState::Running);
+
+    let original_sled = nexus
+        .active_instance_info(&instance_id, None)
+        .await
+        .unwrap()
+        .expect("running instance should have a sled")
+        .sled_id;
+
+    let migrations_url = format!(
+        "/v1/instances/{}/migrations?project={}",
+        instance_name, PROJECT_NAME
+    );
+    let migrations = objects_list_page_authz::<views::InstanceMigration>(
+        client,
+        &migrations_url,
+    )
+    .await
+    .items;
+    assert!(migrations.is_empty());
+
+    // Ask for a migration without naming a target. Nexus should pick a sled
+    // other than the one the instance is already running on.
+    let migrate_url = format!(
+        "/v1/instances/{}/migrate?project={}",
+        instance_name, PROJECT_NAME
+    );
+    let instance = NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &migrate_url)
+            .body(Some(&params::InstanceMigrate { dst_sled_id: None }))
+            .expect_status(Some(StatusCode::ACCEPTED)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body::<Instance>()
+    .unwrap();
+    assert_eq!(instance.identity.id, instance_id.into_untyped_uuid());
+
+    let info = nexus
+        .active_instance_info(&instance_id, None)
+        .await
+        .unwrap()
+        .expect("instance should be on a sled");
+    let dst_propolis_id =
+        info.dst_propolis_id.expect("instance should have a migration target");
+    let dst_sled_id = nexus
+        .datastore()
+        .vmm_fetch(
+            &OpContext::for_tests(
+                cptestctx.logctx.log.new(o!()),
+                nexus.datastore().clone(),
+            ),
+            &dst_propolis_id,
+        )
+        .await
+        .unwrap()
+        .sled_id;
+    assert_ne!(SledUuid::from_untyped_uuid(dst_sled_id), original_sled);
+
+    // The new migration should be visible to the user.
+    let migrations = objects_list_page_authz::<views::InstanceMigration>(
+        client,
+        &migrations_url,
+    )
+    .await
+    .items;
+    assert_eq!(migrations.len(), 1);
+    assert_eq!(migrations[0].instance_id, instance_id.into_untyped_uuid());
+    assert_eq!(migrations[0].state, views::InstanceMigrationState::Pending);
+    assert_eq!(migrations[0].failure_reason, None);
+}
+
 #[nexus_test(extra_sled_agents = 3)]
 async fn test_instance_migrate_v2p_and_routes(
     cptestctx: &ControlPlaneTestContext,
@@ -5613,6 +5714,210 @@ async fn test_instance_create_with_ssh_keys(
     assert_eq!(keys.len(), 0);
 }
 
+#[nexus_test]
+async fn test_instance_template_instantiate(
+    cptestctx: &ControlPlaneTestContext,
+) {
+    let client = &cptestctx.external_client;
+
+    cptestctx
+        .first_sled_agent()
+        .start_local_mock_propolis_server(&cptestctx.logctx.log)
+        .await
+        .unwrap();
+
+    // Test pre-reqs
+    DiskTest::new(&cptestctx).await;
+    create_project_and_pool(&client).await;
+    create_affinity_group(&client, PROJECT_NAME, "affinity1").await;
+    create_anti_affinity_groups(&client, &["anti-affinity1"]).await;
+
+    let templates_url =
+        format!("/v1/instance-templates?{}", get_project_selector());
+    let template_params = params::InstanceTemplateCreate {
+        identity: IdentityMetadataCreateParams {
+            name: "web".parse().unwrap(),
+            description: String::from("a web server"),
+        },
+        ncpus: InstanceCpuCount::try_from(2).unwrap(),
+        memory: ByteCount::from_gibibytes_u32(4),
+        user_data: b"#cloud-config".to_vec(),
+        network_interfaces: params::InstanceNetworkInterfaceAttachment::Default,
+        external_ips: vec![params::ExternalIpCreate::Ephemeral { pool: None }],
+        boot_disk: Some(params::InstanceTemplateBootDisk {
+            disk_source: params::DiskSource::Blank {
+                block_size: params::BlockSize::try_from(512).unwrap(),
+            },
+            size: ByteCount::from_gibibytes_u32(1),
+        }),
+        ssh_public_keys: Some(Vec::new()),
+        auto_restart_policy: None,
+    };
+
+    // A floating IP can only be attached to one instance, so a template can't
+    // hand the same one to each of its instances.
+    let error = object_create_error(
+        client,
+        &templates_url,
+        &params::InstanceTemplateCreate {
+            external_ips: vec![params::ExternalIpCreate::Floating {
+                floating_ip: NameOrId::Name("shared-ip".parse().unwrap()),
+            }],
+            ..template_params.clone()
+        },
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+    assert!(error.message.contains("floating IPs"), "{}", error.message);
+
+    let template: views::InstanceTemplate =
+        object_create(client, &templates_url, &template_params).await;
+    assert_eq!(template.identity.name.as_str(), "web");
+    assert_eq!(template.ncpus, template_params.ncpus);
+    assert_eq!(template.memory, template_params.memory);
+    assert_eq!(template.user_data, b"#cloud-config".to_vec());
+    assert!(template.boot_disk.is_some());
+
+    let instantiate_url = format!(
+        "/v1/instance-templates/web/instantiate?{}",
+        get_project_selector()
+    );
+    let instantiate = |name_pattern: &str, count: u32, first_index: u32| {
+        params::InstanceTemplateInstantiate {
+            name_pattern: name_pattern.to_string(),
+            count,
+            first_index,
+            start: true,
+            affinity_groups: vec![NameOrId::Name("affinity1".parse().unwrap())],
+            anti_affinity_groups: vec![NameOrId::Name(
+                "anti-affinity1".parse().unwrap(),
+            )],
+        }
+    };
+
+    // Without the index in the pattern, every instance would get the same
+    // name.
+    let error = object_create_error(
+        client,
+        &instantiate_url,
+        &instantiate("web", 3, 1),
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+    assert!(error.message.contains("{n}"), "{}", error.message);
+
+    let error = object_create_error(
+        client,
+        &instantiate_url,
+        &instantiate("web-{n}", 0, 1),
+        StatusCode::BAD_REQUEST,
+    )
+    .await;
+    assert!(error.message.contains("count"), "{}", error.message);
+
+    let instantiation: views::InstanceTemplateInstantiation =
+        NexusRequest::new(
+            RequestBuilder::new(client, http::Method::POST, &instantiate_url)
+                .body(Some(&instantiate("web-{n}", 3, 1)))
+                .expect_status(Some(StatusCode::OK)),
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute_and_parse_unwrap()
+        .await;
+    let names = instantiation
+        .instances
+        .iter()
+        .map(|result| result.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["web-1", "web-2", "web-3"]);
+
+    for result in &instantiation.instances {
+        assert_eq!(result.error, None);
+        let instance =
+            result.instance.as_ref().expect("instance should be created");
+        let name = result.name.as_str();
+        assert_eq!(instance.identity.name, result.name);
+        assert_eq!(instance.hostname, name);
+        assert_eq!(instance.ncpus, template_params.ncpus);
+        assert_eq!(instance.memory, template_params.memory);
+        assert_eq!(instance.runtime.run_state, InstanceState::Starting);
+
+        // Each instance gets its own boot disk, named after the instance.
+        let disk: Disk = NexusRequest::object_get(
+            client,
+            &format!("/v1/disks/{name}-boot?{}", get_project_selector()),
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute_and_parse_unwrap()
+        .await;
+        assert_eq!(instance.boot_disk_id, Some(disk.identity.id));
+
+        let affinity_groups = objects_list_page_authz::<views::AffinityGroup>(
+            client,
+            &format!(
+                "/v1/instances/{name}/affinity-groups?{}",
+                get_project_selector()
+            ),
+        )
+        .await
+        .items;
+        let affinity_group_names = affinity_groups
+            .iter()
+            .map(|g| g.identity.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(affinity_group_names, ["affinity1"]);
+        ensure_anti_affinity_groups_match(client, name, &["anti-affinity1"])
+            .await;
+    }
+
+    // A failure to create one instance is reported for that instance, and
+    // doesn't stop the others from being created.
+    let instantiation: views::InstanceTemplateInstantiation =
+        NexusRequest::new(
+            RequestBuilder::new(client, http::Method::POST, &instantiate_url)
+                .body(Some(&instantiate("web-{n}", 2, 3)))
+                .expect_status(Some(StatusCode::OK)),
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute_and_parse_unwrap()
+        .await;
+    let [existing, created] = instantiation.instances.as_slice() else {
+        panic!("expected two results, got {:?}", instantiation.instances);
+    };
+    assert_eq!(existing.name.as_str(), "web-3");
+    assert!(existing.instance.is_none());
+    let error = existing.error.as_ref().expect("web-3 already exists");
+    assert!(error.contains("already exists"), "{error}");
+    assert_eq!(created.name.as_str(), "web-4");
+    assert_eq!(created.error, None);
+    assert!(created.instance.is_some());
+
+    let templates = objects_list_page_authz::<views::InstanceTemplate>(
+        client,
+        &templates_url,
+    )
+    .await
+    .items;
+    assert_eq!(templates.len(), 1);
+    assert_eq!(templates[0].identity.id, template.identity.id);
+
+    // Deleting the template leaves the instances created from it alone.
+    let template_url =
+        format!("/v1/instance-templates/web?{}", get_project_selector());
+    object_delete(client, &template_url).await;
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        http::Method::GET,
+        &template_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    let _: Instance = object_get(client, &get_instance_url("web-1")).await;
+}
+
 async fn expect_instance_start_fail_507(
     client: &ClientTestContext,
     instance_name: &str,
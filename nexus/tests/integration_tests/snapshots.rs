@@ -5,6 +5,7 @@
 //! Tests basic snapshot support in the API
 
 use crate::integration_tests::instances::instance_simulate;
+use crate::integration_tests::instances::instance_wait_for_state;
 use chrono::Utc;
 use dropshot::test_util::ClientTestContext;
 use http::StatusCode;
@@ -20,13 +21,18 @@ use nexus_db_queries::db::datastore::RegionAllocationFor;
 use nexus_db_queries::db::datastore::RegionAllocationParameters;
 use nexus_db_queries::db::identity::Resource;
 use nexus_test_utils::SLED_AGENT_UUID;
+use nexus_test_utils::background::run_snapshot_policy;
 use nexus_test_utils::http_testing::AuthnMode;
 use nexus_test_utils::http_testing::NexusRequest;
 use nexus_test_utils::http_testing::RequestBuilder;
 use nexus_test_utils::resource_helpers::create_default_ip_pool;
 use nexus_test_utils::resource_helpers::create_disk;
 use nexus_test_utils::resource_helpers::create_project;
+use nexus_test_utils::resource_helpers::create_snapshot;
 use nexus_test_utils::resource_helpers::object_create;
+use nexus_test_utils::resource_helpers::object_create_error;
+use nexus_test_utils::resource_helpers::object_delete;
+use nexus_test_utils::resource_helpers::objects_list_page_authz;
 use nexus_test_utils_macros::nexus_test;
 use nexus_types::external_api::params;
 use nexus_types::external_api::views;
@@ -37,8 +43,11 @@ use omicron_common::api::external::DiskState;
 use omicron_common::api::external::IdentityMetadataCreateParams;
 use omicron_common::api::external::Instance;
 use omicron_common::api::external::InstanceCpuCount;
+use omicron_common::api::external::InstanceState;
 use omicron_common::api::external::Name;
 use omicron_nexus::app::MIN_DISK_SIZE_BYTES;
+use omicron_test_utils::dev::poll::CondCheckError;
+use omicron_test_utils::dev::poll::wait_for_condition;
 use omicron_uuid_kinds::DatasetUuid;
 use omicron_uuid_kinds::GenericUuid;
 use omicron_uuid_kinds::InstanceUuid;
@@ -1703,3 +1712,453 @@ async fn test_snapshot_expunge(cptestctx: &ControlPlaneTestContext) {
         assert_eq!(expunged_region_snapshot.snapshot_id, snapshot.identity.id);
     }
 }
+
+#[nexus_test]
+async fn test_snapshot_export(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+    create_project_and_pool(client).await;
+
+    let disk = create_disk(client, PROJECT_NAME, "exported-disk").await;
+    let snapshot =
+        create_snapshot(client, PROJECT_NAME, "exported-disk", "exported")
+            .await;
+    assert_eq!(snapshot.size, disk.size);
+
+    let exports_url =
+        format!("/v1/snapshots/exported/exports?project={}", PROJECT_NAME);
+
+    // Export the snapshot as a raw image, which is exactly as large as the
+    // snapshot.
+    let raw_export: views::SnapshotExport = object_create(
+        client,
+        &exports_url,
+        &params::SnapshotExportCreate {
+            format: views::SnapshotExportFormat::Raw,
+        },
+    )
+    .await;
+    assert_eq!(raw_export.snapshot_id, snapshot.identity.id);
+    assert_eq!(raw_export.size, snapshot.size);
+    assert_eq!(raw_export.bytes_exported.to_bytes(), 0);
+
+    let raw_url = format!(
+        "/v1/snapshots/exported/exports/{}?project={}",
+        raw_export.id, PROJECT_NAME
+    );
+    let raw_content_url = format!(
+        "/v1/snapshots/exported/exports/{}/content?project={}",
+        raw_export.id, PROJECT_NAME
+    );
+
+    // HEAD reports the size of the whole image.
+    let response = NexusRequest::new(
+        RequestBuilder::new(client, Method::HEAD, &raw_content_url)
+            .expect_status(Some(StatusCode::OK))
+            .expect_image_download()
+            .expect_response_header(
+                http::header::CONTENT_LENGTH,
+                snapshot.size.to_bytes().to_string(),
+            ),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    assert!(response.body.is_empty());
+
+    // Read an unaligned range out of the middle of the image. The simulated
+    // Pantry reads back zeroes.
+    let response = NexusRequest::new(
+        RequestBuilder::new(client, Method::GET, &raw_content_url)
+            .header(http::header::RANGE, "bytes=1000-5095")
+            .expect_status(Some(StatusCode::PARTIAL_CONTENT))
+            .expect_image_download()
+            .expect_response_header(
+                http::header::CONTENT_RANGE,
+                format!("bytes 1000-5095/{}", snapshot.size.to_bytes()),
+            ),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    assert_eq!(response.body.len(), 4096);
+    assert!(response.body.iter().all(|b| *b == 0));
+
+    // The export records what was downloaded, once the download finishes.
+    wait_for_condition(
+        || async {
+            let export: views::SnapshotExport =
+                NexusRequest::object_get(client, &raw_url)
+                    .authn_as(AuthnMode::PrivilegedUser)
+                    .execute_and_parse_unwrap()
+                    .await;
+            if export.bytes_exported.to_bytes() == 4096 {
+                Ok(())
+            } else {
+                Err(CondCheckError::<()>::NotYet)
+            }
+        },
+        &std::time::Duration::from_millis(50),
+        &std::time::Duration::from_secs(30),
+    )
+    .await
+    .expect("export progress should have been recorded");
+
+    // A qcow2 export is larger than the snapshot, because of its metadata,
+    // and starts with the qcow2 magic.
+    let qcow2_export: views::SnapshotExport = object_create(
+        client,
+        &exports_url,
+        &params::SnapshotExportCreate {
+            format: views::SnapshotExportFormat::Qcow2,
+        },
+    )
+    .await;
+    assert_eq!(qcow2_export.format, views::SnapshotExportFormat::Qcow2);
+    assert!(qcow2_export.size.to_bytes() > snapshot.size.to_bytes());
+
+    let qcow2_content_url = format!(
+        "/v1/snapshots/exported/exports/{}/content?project={}",
+        qcow2_export.id, PROJECT_NAME
+    );
+    let response = NexusRequest::new(
+        RequestBuilder::new(client, Method::GET, &qcow2_content_url)
+            .header(http::header::RANGE, "bytes=0-3")
+            .expect_status(Some(StatusCode::PARTIAL_CONTENT))
+            .expect_image_download(),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    assert_eq!(&response.body[..], b"QFI\xfb");
+
+    // The snapshot can't be deleted while it's being exported.
+    let snapshot_url =
+        format!("/v1/snapshots/exported?project={}", PROJECT_NAME);
+    let error = NexusRequest::expect_failure(
+        client,
+        StatusCode::BAD_REQUEST,
+        Method::DELETE,
+        &snapshot_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap()
+    .parsed_body::<dropshot::HttpErrorResponseBody>()
+    .unwrap();
+    assert_eq!(
+        error.message,
+        "snapshot has exports, which must be deleted first"
+    );
+
+    // Once the exports are deleted, they're gone, and so is the snapshot.
+    let qcow2_url = format!(
+        "/v1/snapshots/exported/exports/{}?project={}",
+        qcow2_export.id, PROJECT_NAME
+    );
+    for url in [&raw_url, &qcow2_url] {
+        NexusRequest::object_delete(client, url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .unwrap();
+
+        NexusRequest::expect_failure(
+            client,
+            StatusCode::NOT_FOUND,
+            Method::GET,
+            url,
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .unwrap();
+    }
+
+    NexusRequest::object_delete(client, &snapshot_url)
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .unwrap();
+}
+
+#[nexus_test]
+async fn test_snapshot_policy(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    let internal_client = &cptestctx.internal_client;
+    DiskTest::new(&cptestctx).await;
+    create_project_and_pool(client).await;
+
+    let nexus = &cptestctx.server.server_context().nexus;
+    let datastore = nexus.datastore();
+    let opctx =
+        OpContext::for_tests(cptestctx.logctx.log.new(o!()), datastore.clone());
+
+    let disk = create_disk(client, PROJECT_NAME, "scheduled-disk").await;
+    let older =
+        create_snapshot(client, PROJECT_NAME, "scheduled-disk", "older").await;
+
+    let policies_url =
+        format!("/v1/snapshot-policies?project={}", PROJECT_NAME);
+    let policy_params =
+        |interval_secs, retention_count| params::SnapshotPolicyCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "every-six-hours".parse().unwrap(),
+                description: String::from("keep the latest snapshot"),
+            },
+            disk: Some("scheduled-disk".parse::<Name>().unwrap().into()),
+            interval_secs,
+            retention_count,
+        };
+
+    // Policies can't snapshot disks too often, or keep no snapshots at all.
+    for (interval_secs, retention_count) in [(60, 1), (6 * 60 * 60, 0)] {
+        object_create_error(
+            client,
+            &policies_url,
+            &policy_params(interval_secs, retention_count),
+            StatusCode::BAD_REQUEST,
+        )
+        .await;
+    }
+
+    let policy: views::SnapshotPolicy =
+        object_create(client, &policies_url, &policy_params(6 * 60 * 60, 1))
+            .await;
+    assert_eq!(policy.disk_id, Some(disk.identity.id));
+    assert!(policy.time_last_run.is_none());
+
+    // Pretend that the policy took the existing snapshot a day ago.
+    let record = db::model::SnapshotPolicySnapshot::new(
+        policy.identity.id,
+        disk.identity.id,
+        Utc::now() - chrono::Duration::days(1),
+    );
+    assert!(
+        datastore
+            .snapshot_policy_snapshot_claim(&opctx, record.clone())
+            .await
+            .unwrap()
+    );
+    datastore
+        .snapshot_policy_snapshot_set_id(&opctx, &record, older.identity.id)
+        .await
+        .unwrap();
+
+    // A new policy is due to snapshot its disk straight away. The policy only
+    // keeps one snapshot, so the older one is deleted.
+    let status = run_snapshot_policy(internal_client).await;
+    assert_eq!(status.policies_found, 1);
+    assert_eq!(status.snapshots_created.len(), 1);
+    assert_eq!(status.snapshots_deleted, vec![older.identity.id]);
+
+    let snapshots_url = format!("/v1/snapshots?project={}", PROJECT_NAME);
+    let snapshots =
+        objects_list_page_authz::<views::Snapshot>(client, &snapshots_url)
+            .await
+            .items;
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].identity.id, status.snapshots_created[0]);
+    assert_eq!(snapshots[0].disk_id, disk.identity.id);
+
+    let policy_url = format!(
+        "/v1/snapshot-policies/every-six-hours?project={}",
+        PROJECT_NAME
+    );
+    let policy: views::SnapshotPolicy =
+        NexusRequest::object_get(client, &policy_url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute_and_parse_unwrap()
+            .await;
+    assert!(policy.time_last_run.is_some());
+    assert_eq!(policy.last_error, None);
+
+    // The disk isn't due for another snapshot for six hours.
+    let status = run_snapshot_policy(internal_client).await;
+    assert!(status.snapshots_created.is_empty());
+    assert!(status.snapshots_deleted.is_empty());
+
+    // Deleting the policy leaves its snapshots alone.
+    object_delete(client, &policy_url).await;
+    let status = run_snapshot_policy(internal_client).await;
+    assert_eq!(status.policies_found, 0);
+    let snapshots =
+        objects_list_page_authz::<views::Snapshot>(client, &snapshots_url)
+            .await
+            .items;
+    assert_eq!(snapshots.len(), 1);
+}
+
+#[nexus_test]
+async fn test_snapshot_group(cptestctx: &ControlPlaneTestContext) {
+    let client = &cptestctx.external_client;
+    DiskTest::new(&cptestctx).await;
+    create_project_and_pool(client).await;
+
+    let boot_disk = create_disk(client, PROJECT_NAME, "boot-disk").await;
+    let data_disk = create_disk(client, PROJECT_NAME, "data-disk").await;
+
+    // Boot an instance with both disks
+    let instances_url = format!("/v1/instances?project={}", PROJECT_NAME);
+    let instance: Instance = object_create(
+        client,
+        &instances_url,
+        &params::InstanceCreate {
+            identity: IdentityMetadataCreateParams {
+                name: "grouped-instance".parse().unwrap(),
+                description: String::from("has two disks"),
+            },
+            ncpus: InstanceCpuCount(2),
+            memory: ByteCount::from_gibibytes_u32(1),
+            hostname: "grouped-instance".parse().unwrap(),
+            user_data: vec![],
+            ssh_public_keys: Some(Vec::new()),
+            network_interfaces:
+                params::InstanceNetworkInterfaceAttachment::None,
+            boot_disk: Some(params::InstanceDiskAttachment::Attach(
+                params::InstanceDiskAttach {
+                    name: boot_disk.identity.name.clone(),
+                },
+            )),
+            disks: vec![params::InstanceDiskAttachment::Attach(
+                params::InstanceDiskAttach {
+                    name: data_disk.identity.name.clone(),
+                },
+            )],
+            external_ips: vec![],
+            start: true,
+            auto_restart_policy: Default::default(),
+            anti_affinity_groups: Vec::new(),
+        },
+    )
+    .await;
+    let instance_id = InstanceUuid::from_untyped_uuid(instance.identity.id);
+    let nexus = &cptestctx.server.server_context().nexus;
+    instance_simulate(nexus, &instance_id).await;
+
+    // Propolis can't hold a running guest's I/O, so the disks of a running
+    // instance can't be snapshotted together.
+    let groups_url = format!("/v1/snapshot-groups?project={}", PROJECT_NAME);
+    let group_create = params::SnapshotGroupCreate {
+        identity: IdentityMetadataCreateParams {
+            name: "both-disks".parse().unwrap(),
+            description: String::from("a pair of snapshots"),
+        },
+        instance: instance.identity.name.clone().into(),
+    };
+    let error = object_create_error(
+        client,
+        &groups_url,
+        &group_create,
+        StatusCode::CONFLICT,
+    )
+    .await;
+    assert!(error.message.contains("active VMM"), "{}", error.message);
+
+    // Stop the instance, then snapshot both disks at once
+    let stop_url = format!(
+        "/v1/instances/{}/stop?project={}",
+        instance.identity.name, PROJECT_NAME
+    );
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &stop_url)
+            .body(None as Option<&serde_json::Value>)
+            .expect_status(Some(StatusCode::ACCEPTED)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+    instance_simulate(nexus, &instance_id).await;
+    instance_wait_for_state(client, instance_id, InstanceState::Stopped).await;
+
+    let group: views::SnapshotGroup =
+        object_create(client, &groups_url, &group_create).await;
+    assert_eq!(group.instance_id, instance.identity.id);
+    assert_eq!(group.state, views::SnapshotGroupState::Ready);
+    assert_eq!(
+        group.members.iter().map(|m| m.disk_id).collect::<Vec<_>>(),
+        vec![boot_disk.identity.id, data_disk.identity.id],
+    );
+
+    let snapshots_url = format!("/v1/snapshots?project={}", PROJECT_NAME);
+    let snapshots =
+        objects_list_page_authz::<views::Snapshot>(client, &snapshots_url)
+            .await
+            .items;
+    assert_eq!(snapshots.len(), 2);
+    for member in &group.members {
+        let snapshot = snapshots
+            .iter()
+            .find(|s| s.identity.id == member.snapshot_id)
+            .expect("group member's snapshot exists");
+        assert_eq!(snapshot.disk_id, member.disk_id);
+    }
+
+    let groups =
+        objects_list_page_authz::<views::SnapshotGroup>(client, &groups_url)
+            .await
+            .items;
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].members, group.members);
+
+    // Restore the group as a new pair of disks
+    let group_url =
+        format!("/v1/snapshot-groups/both-disks?project={}", PROJECT_NAME);
+    let restore_url = format!(
+        "/v1/snapshot-groups/both-disks/restore?project={}",
+        PROJECT_NAME
+    );
+    let restored: Vec<Disk> = object_create(
+        client,
+        &restore_url,
+        &params::SnapshotGroupRestore {
+            name_prefix: "restored".parse().unwrap(),
+        },
+    )
+    .await;
+    assert_eq!(
+        restored
+            .iter()
+            .map(|d| d.identity.name.to_string())
+            .collect::<Vec<_>>(),
+        vec!["restored-boot-disk", "restored-data-disk"],
+    );
+    for (disk, member) in restored.iter().zip(&group.members) {
+        assert_eq!(disk.snapshot_id, Some(member.snapshot_id));
+        assert_eq!(disk.state, DiskState::Detached);
+    }
+
+    // Restoring again would reuse the same disk names.
+    NexusRequest::new(
+        RequestBuilder::new(client, Method::POST, &restore_url)
+            .body(Some(&params::SnapshotGroupRestore {
+                name_prefix: "restored".parse().unwrap(),
+            }))
+            .expect_status(Some(StatusCode::BAD_REQUEST)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .unwrap();
+
+    // Deleting the group deletes its snapshots, but not the restored disks.
+    object_delete(client, &group_url).await;
+    let snapshots =
+        objects_list_page_authz::<views::Snapshot>(client, &snapshots_url)
+            .await
+            .items;
+    assert!(snapshots.is_empty());
+    let groups =
+        objects_list_page_authz::<views::SnapshotGroup>(client, &groups_url)
+            .await
+            .items;
+    assert!(groups.is_empty());
+    let disks =
+        objects_list_page_authz::<Disk>(client, &get_disks_url()).await.items;
+    assert_eq!(disks.len(), 4);
+}
@@ -415,6 +415,25 @@ async fn test_support_bundle_lifecycle(cptestctx: &ControlPlaneTestContext) {
     // There's much more data in the bundle, but validating it isn't the point
     // of this test, which cares more about bundle lifecycle.
 
+    // Range requests are passed through to the sled agent holding the bundle.
+    let url = format!("{BUNDLES_URL}/{}/download", bundle.id);
+    let partial = NexusRequest::new(
+        RequestBuilder::new(client, Method::GET, &url)
+            .header(http::header::RANGE, "bytes=0-9")
+            .expect_status(Some(StatusCode::PARTIAL_CONTENT))
+            .expect_range_requestable()
+            .expect_response_header(
+                http::header::CONTENT_RANGE,
+                format!("bytes 0-9/{}", contents.len()),
+            ),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .expect("failed to request bundle range")
+    .body;
+    assert_eq!(partial, contents.slice(0..10));
+
     // We are also able to delete the bundle
     bundle_delete(&client, bundle.id).await.unwrap();
     let observed = bundle_get(&client, bundle.id).await.unwrap();
@@ -298,6 +298,13 @@ static SETUP_REQUESTS: LazyLock<Vec<SetupReq>> = LazyLock::new(|| {
             body: serde_json::to_value(&*DEMO_STOPPED_INSTANCE_CREATE).unwrap(),
             id_routes: vec!["/v1/instances/{id}"],
         },
+        // Create an Instance Template in the Project
+        SetupReq::Post {
+            url: &DEMO_INSTANCE_TEMPLATES_URL,
+            body: serde_json::to_value(&*DEMO_INSTANCE_TEMPLATE_CREATE)
+                .unwrap(),
+            id_routes: vec!["/v1/instance-templates/{id}"],
+        },
         // Create an affinity group in the Project
         SetupReq::Post {
             url: &DEMO_PROJECT_URL_AFFINITY_GROUPS,
@@ -334,6 +341,12 @@ static SETUP_REQUESTS: LazyLock<Vec<SetupReq>> = LazyLock::new(|| {
             body: serde_json::to_value(&*DEMO_SNAPSHOT_CREATE).unwrap(),
             id_routes: vec!["/by-id/snapshots/{id}"],
         },
+        // Create a Snapshot Policy in the Project
+        SetupReq::Post {
+            url: &DEMO_SNAPSHOT_POLICIES_URL,
+            body: serde_json::to_value(&*DEMO_SNAPSHOT_POLICY_CREATE).unwrap(),
+            id_routes: vec!["/v1/snapshot-policies/{id}"],
+        },
         // Create an Image in the Project
         SetupReq::Post {
             url: &DEMO_PROJECT_IMAGES_URL,
@@ -10,6 +10,7 @@
 use anyhow::{Context, Result, ensure};
 use camino::Utf8Path;
 use camino_tempfile::{Builder, Utf8TempPath};
+use chrono::Utc;
 use clap::Parser;
 use dropshot::test_util::LogContext;
 use http::{Method, StatusCode};
@@ -19,17 +20,20 @@ use nexus_test_utils::background::run_tuf_artifact_replication_step;
 use nexus_test_utils::background::wait_tuf_artifact_replication_step;
 use nexus_test_utils::http_testing::{AuthnMode, NexusRequest, RequestBuilder};
 use nexus_test_utils::{load_test_config, test_setup, test_setup_with_config};
+use nexus_types::external_api::{params, views};
 use omicron_common::api::external::{
-    TufRepoGetResponse, TufRepoInsertResponse, TufRepoInsertStatus,
+    DataPageParams, TufRepoGetResponse, TufRepoInsertResponse,
+    TufRepoInsertStatus,
 };
 use omicron_sled_agent::sim;
 use pretty_assertions::assert_eq;
 use semver::Version;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::io::Write;
-use tufaceous_artifact::KnownArtifactKind;
+use tufaceous_artifact::{ArtifactHash, KnownArtifactKind};
 use tufaceous_lib::assemble::{DeserializedManifest, ManifestTweak};
 
 #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
@@ -381,6 +385,286 @@ async fn test_repo_upload() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_repo_upload_resumable() -> Result<()> {
+    let mut config = load_test_config();
+    config.pkg.updates =
+        Some(UpdatesConfig { trusted_root: "does-not-exist.json".into() });
+    let logctx = LogContext::new("test_repo_upload_resumable", &config.pkg.log);
+    let cptestctx = test_setup_with_config::<omicron_nexus::Server>(
+        "test_repo_upload_resumable",
+        &mut config,
+        sim::SimMode::Explicit,
+        None,
+        0,
+    )
+    .await;
+    let client = &cptestctx.external_client;
+
+    // Build a fake TUF repo, and split it into two pieces.
+    let archive_path = make_archive(&logctx.log).await?;
+    let contents =
+        std::fs::read(&archive_path).context("error reading archive")?;
+    let sha256 = ArtifactHash(Sha256::digest(&contents).into());
+    let (first, second) = contents.split_at(contents.len() / 2);
+    let first_path = write_temp_file(first)?;
+    let second_path = write_temp_file(second)?;
+
+    let create = params::TufRepoUploadCreate {
+        file_name: String::from("archive.zip"),
+        size: contents.len() as u64,
+    };
+
+    // Start an upload and send the first half of the repository.
+    let upload: views::TufRepoUpload = NexusRequest::objects_post(
+        client,
+        "/v1/system/update/repository-uploads",
+        &create,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute_and_parse_unwrap()
+    .await;
+    assert_eq!(upload.size, contents.len() as u64);
+    assert_eq!(upload.bytes_received, 0);
+    let upload_url =
+        format!("/v1/system/update/repository-uploads/{}", upload.id);
+
+    let upload: views::TufRepoUpload = make_upload_write_request(
+        client,
+        &upload_url,
+        0,
+        &first_path,
+        StatusCode::OK,
+    )
+    .execute_and_parse_unwrap()
+    .await;
+    assert_eq!(upload.bytes_received, first.len() as u64);
+
+    // Sending the same piece again (as a client might after losing the
+    // response) fails without modifying the upload.
+    make_upload_write_request(
+        client,
+        &upload_url,
+        0,
+        &first_path,
+        StatusCode::CONFLICT,
+    )
+    .execute()
+    .await
+    .context("rewriting offset 0 should have failed")?;
+
+    // An incomplete upload can't be finalized.
+    let response = make_upload_finalize_request(
+        client,
+        &upload_url,
+        sha256,
+        StatusCode::BAD_REQUEST,
+    )
+    .execute()
+    .await
+    .context("finalizing incomplete upload should have failed")?;
+    assert_error_message_contains(&response.body, "upload is incomplete")?;
+
+    // Resume from the offset reported by Nexus and finish the upload.
+    let upload: views::TufRepoUpload =
+        NexusRequest::object_get(client, &upload_url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute_and_parse_unwrap()
+            .await;
+    let upload: views::TufRepoUpload = make_upload_write_request(
+        client,
+        &upload_url,
+        upload.bytes_received,
+        &second_path,
+        StatusCode::OK,
+    )
+    .execute_and_parse_unwrap()
+    .await;
+    assert_eq!(upload.bytes_received, contents.len() as u64);
+
+    let response: TufRepoInsertResponse = make_upload_finalize_request(
+        client,
+        &upload_url,
+        sha256,
+        StatusCode::OK,
+    )
+    .execute_and_parse_unwrap()
+    .await;
+    assert_eq!(response.status, TufRepoInsertStatus::Inserted);
+    assert_eq!(response.recorded.repo.hash, sha256);
+
+    // The upload is gone once it has been finalized.
+    NexusRequest::expect_failure(
+        client,
+        StatusCode::NOT_FOUND,
+        Method::GET,
+        &upload_url,
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+    .execute()
+    .await
+    .context("finalized upload should not be found")?;
+
+    // A complete upload whose checksum doesn't match is rejected, but kept so
+    // that finalization can be retried.
+    {
+        let upload: views::TufRepoUpload = NexusRequest::objects_post(
+            client,
+            "/v1/system/update/repository-uploads",
+            &create,
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute_and_parse_unwrap()
+        .await;
+        let upload_url =
+            format!("/v1/system/update/repository-uploads/{}", upload.id);
+        make_upload_write_request(
+            client,
+            &upload_url,
+            0,
+            &archive_path,
+            StatusCode::OK,
+        )
+        .execute()
+        .await
+        .context("error uploading repository")?;
+
+        let response = make_upload_finalize_request(
+            client,
+            &upload_url,
+            ArtifactHash([0; 32]),
+            StatusCode::BAD_REQUEST,
+        )
+        .execute()
+        .await
+        .context("finalizing with the wrong checksum should have failed")?;
+        assert_error_message_contains(
+            &response.body,
+            "repository hash mismatch",
+        )?;
+
+        let upload: views::TufRepoUpload =
+            NexusRequest::object_get(client, &upload_url)
+                .authn_as(AuthnMode::PrivilegedUser)
+                .execute_and_parse_unwrap()
+                .await;
+        assert_eq!(upload.bytes_received, contents.len() as u64);
+
+        // This repository was already recorded above.
+        let response: TufRepoInsertResponse = make_upload_finalize_request(
+            client,
+            &upload_url,
+            sha256,
+            StatusCode::OK,
+        )
+        .execute_and_parse_unwrap()
+        .await;
+        assert_eq!(response.status, TufRepoInsertStatus::AlreadyExists);
+
+        NexusRequest::expect_failure(
+            client,
+            StatusCode::NOT_FOUND,
+            Method::GET,
+            &upload_url,
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .context("finalized upload should not be found")?;
+    }
+
+    // Deleting an upload discards it.
+    {
+        let upload: views::TufRepoUpload = NexusRequest::objects_post(
+            client,
+            "/v1/system/update/repository-uploads",
+            &create,
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute_and_parse_unwrap()
+        .await;
+        let upload_url =
+            format!("/v1/system/update/repository-uploads/{}", upload.id);
+        NexusRequest::object_delete(client, &upload_url)
+            .authn_as(AuthnMode::PrivilegedUser)
+            .execute()
+            .await
+            .context("error deleting upload")?;
+        NexusRequest::expect_failure(
+            client,
+            StatusCode::NOT_FOUND,
+            Method::GET,
+            &upload_url,
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .context("deleted upload should not be found")?;
+    }
+
+    // Uploads that have gone idle are discarded, along with their data.
+    {
+        let upload: views::TufRepoUpload = NexusRequest::objects_post(
+            client,
+            "/v1/system/update/repository-uploads",
+            &create,
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute_and_parse_unwrap()
+        .await;
+        let upload_url =
+            format!("/v1/system/update/repository-uploads/{}", upload.id);
+        make_upload_write_request(
+            client,
+            &upload_url,
+            0,
+            &first_path,
+            StatusCode::OK,
+        )
+        .execute()
+        .await
+        .context("error uploading first half of repository")?;
+
+        let datastore = cptestctx.server.server_context().nexus.datastore();
+        let opctx = OpContext::for_tests(
+            cptestctx.logctx.log.new(o!()),
+            datastore.clone(),
+        );
+        let deleted = datastore
+            .tuf_repo_upload_delete_idle(
+                &opctx,
+                Utc::now() + chrono::TimeDelta::seconds(1),
+            )
+            .await
+            .context("error pruning idle uploads")?;
+        assert_eq!(deleted, vec![upload.id]);
+        let chunks = datastore
+            .tuf_repo_upload_chunk_list(
+                &opctx,
+                upload.id,
+                &DataPageParams::max_page(),
+            )
+            .await
+            .context("error listing upload data")?;
+        assert!(chunks.is_empty(), "pruned upload still has data");
+        NexusRequest::expect_failure(
+            client,
+            StatusCode::NOT_FOUND,
+            Method::GET,
+            &upload_url,
+        )
+        .authn_as(AuthnMode::PrivilegedUser)
+        .execute()
+        .await
+        .context("pruned upload should not be found")?;
+    }
+
+    cptestctx.teardown().await;
+    logctx.cleanup_successful();
+
+    Ok(())
+}
+
 async fn make_archive(log: &slog::Logger) -> anyhow::Result<Utf8TempPath> {
     make_tweaked_archive(log, &[]).await
 }
@@ -438,6 +722,52 @@ fn make_upload_request<'a>(
     request
 }
 
+fn make_upload_write_request<'a>(
+    client: &'a dropshot::test_util::ClientTestContext,
+    upload_url: &str,
+    offset: u64,
+    data_path: &'a Utf8Path,
+    expected_status: StatusCode,
+) -> NexusRequest<'a> {
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::PUT,
+            &format!("{upload_url}/data?offset={offset}"),
+        )
+        .body_file(Some(data_path))
+        .expect_status(Some(expected_status)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+}
+
+fn make_upload_finalize_request<'a>(
+    client: &'a dropshot::test_util::ClientTestContext,
+    upload_url: &str,
+    sha256: ArtifactHash,
+    expected_status: StatusCode,
+) -> NexusRequest<'a> {
+    NexusRequest::new(
+        RequestBuilder::new(
+            client,
+            Method::POST,
+            &format!("{upload_url}/finalize"),
+        )
+        .body(Some(&params::TufRepoUploadFinalize { sha256 }))
+        .expect_status(Some(expected_status)),
+    )
+    .authn_as(AuthnMode::PrivilegedUser)
+}
+
+fn write_temp_file(contents: &[u8]) -> anyhow::Result<Utf8TempPath> {
+    let mut file = Builder::new()
+        .prefix("archive-part")
+        .tempfile()
+        .context("error creating temp file for archive part")?;
+    file.write_all(contents)?;
+    Ok(file.into_temp_path())
+}
+
 fn make_get_request(
     client: &dropshot::test_util::ClientTestContext,
     system_version: Version,
@@ -63,6 +63,8 @@ mod clickhouse;
 pub mod execution;
 mod network_resources;
 mod planning_input;
+mod planning_report;
+mod topology;
 mod tri_map;
 mod zone_type;
 
@@ -87,6 +89,7 @@ pub use planning_input::CockroachDbClusterVersion;
 pub use planning_input::CockroachDbPreserveDowngrade;
 pub use planning_input::CockroachDbSettings;
 pub use planning_input::DiskFilter;
+pub use planning_input::FailureDomain;
 pub use planning_input::OximeterReadMode;
 pub use planning_input::OximeterReadPolicy;
 pub use planning_input::PlanningInput;
@@ -99,8 +102,22 @@ pub use planning_input::SledFilter;
 pub use planning_input::SledLookupError;
 pub use planning_input::SledLookupErrorKind;
 pub use planning_input::SledResources;
+pub use planning_input::ZonePlacementPolicy;
 pub use planning_input::ZpoolFilter;
+pub use planning_report::PlanningOutcome;
+pub use planning_report::PlanningReport;
+pub use planning_report::PlanningReportEntry;
+pub use planning_report::PlanningStep;
 use std::sync::Arc;
+pub use topology::BlueprintTopology;
+pub use topology::TopologyEdge;
+pub use topology::TopologyEdgeKind;
+pub use topology::TopologyGraph;
+pub use topology::TopologyGraphDot;
+pub use topology::TopologyInventoryStatus;
+pub use topology::TopologyNode;
+pub use topology::TopologyNodeId;
+pub use topology::TopologyNodeKind;
 pub use zone_type::BlueprintZoneType;
 pub use zone_type::DurableDataset;
 pub use zone_type::blueprint_zone_type;
@@ -207,6 +224,11 @@ pub struct Blueprint {
     /// human-readable string describing why this blueprint was created
     /// (for debugging)
     pub comment: String,
+    /// record of the decisions the planner made when generating this
+    /// blueprint (`None` for blueprints not produced by the planner)
+    #[daft(ignore)]
+    #[serde(default)]
+    pub planning_report: Option<PlanningReport>,
 }
 
 impl Blueprint {
@@ -540,6 +562,9 @@ impl fmt::Display for BlueprintDisplay<'_> {
             time_created: _,
             creator: _,
             comment: _,
+            // The planning report can be long; it's displayed on request via
+            // its own `Display` impl rather than as part of the blueprint.
+            planning_report: _,
         } = self.blueprint;
 
         writeln!(f, "blueprint  {}", id)?;
@@ -1623,6 +1648,75 @@ pub struct BlueprintTarget {
 pub struct BlueprintTargetSet {
     pub target_id: BlueprintUuid,
     pub enabled: bool,
+    /// who is making this change (recorded in the blueprint's audit trail)
+    #[serde(default)]
+    pub actor: Option<String>,
+}
+
+/// Review status of a proposed blueprint
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BlueprintApproval {
+    /// id of the proposed blueprint
+    pub blueprint_id: BlueprintUuid,
+    /// id of the user who proposed the blueprint
+    pub proposed_by: String,
+    /// id of the user who must approve the blueprint before it may be
+    /// executed
+    pub required_approver: String,
+    /// when the blueprint was proposed
+    pub time_proposed: chrono::DateTime<chrono::Utc>,
+    /// id of the user who approved the blueprint, if it has been approved
+    pub approved_by: Option<String>,
+    /// when the blueprint was approved, if it has been approved
+    pub time_approved: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl BlueprintApproval {
+    /// Returns true if execution of this blueprint is held pending approval.
+    pub fn is_held(&self) -> bool {
+        self.approved_by.is_none()
+    }
+}
+
+/// One entry in the audit trail of a blueprint's review and target history
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BlueprintTargetAuditEntry {
+    /// when the change was made
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// id of the blueprint the change concerns
+    pub blueprint_id: BlueprintUuid,
+    /// what changed
+    pub action: BlueprintTargetAuditAction,
+    /// who made the change, if known
+    pub actor: Option<String>,
+    /// for `made_target` and `set_enabled`, whether the blueprint was enabled
+    /// as a result
+    pub enabled: Option<bool>,
+}
+
+/// The kinds of change recorded in a blueprint's audit trail
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    strum::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum BlueprintTargetAuditAction {
+    /// the blueprint was proposed for review
+    Proposed,
+    /// the blueprint was approved for execution
+    Approved,
+    /// the blueprint was made the current target
+    MadeTarget,
+    /// the current target's `enabled` field was set
+    SetEnabled,
 }
 
 /// A unique identifier for a dataset within a collection.
@@ -0,0 +1,94 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use omicron_uuid_kinds::BlueprintUuid;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::ExecutionComponent;
+
+/// What executing a blueprint would change, computed without side effects
+///
+/// Steps appear in the order in which blueprint execution runs them.
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExecutionPlan {
+    /// id of the blueprint whose execution was planned
+    pub blueprint_id: BlueprintUuid,
+    /// when the plan was computed
+    ///
+    /// The plan reflects the state of the system at this time; executing the
+    /// blueprint later may do something different.
+    pub time_computed: DateTime<Utc>,
+    /// one entry per execution step
+    pub steps: Vec<ExecutionPlanStep>,
+}
+
+impl ExecutionPlan {
+    /// Returns true if no step of execution would change anything.
+    pub fn is_empty(&self) -> bool {
+        self.steps.iter().all(|step| step.changes.is_empty())
+    }
+}
+
+impl fmt::Display for ExecutionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "execution plan for blueprint {}", self.blueprint_id)?;
+        writeln!(f, "computed at: {}", self.time_computed)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f)?;
+            write!(
+                f,
+                "step {}: {} ({:?})",
+                i + 1,
+                step.description,
+                step.component
+            )?;
+            match &step.outcome {
+                ExecutionPlanStepOutcome::Planned => writeln!(f)?,
+                ExecutionPlanStepOutcome::Skipped { reason } => {
+                    writeln!(f, ": skipped: {reason}")?;
+                }
+                ExecutionPlanStepOutcome::Warning { message } => {
+                    writeln!(f, ": warning: {message}")?;
+                }
+            }
+            if step.changes.is_empty() {
+                writeln!(f, "    (no changes)")?;
+            }
+            for change in &step.changes {
+                writeln!(f, "    {change}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The changes one step of blueprint execution would make
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ExecutionPlanStep {
+    /// part of the system this step changes
+    pub component: ExecutionComponent,
+    /// description of the step (as reported during execution)
+    pub description: String,
+    /// whether the changes could be fully determined
+    pub outcome: ExecutionPlanStepOutcome,
+    /// human-readable descriptions of each change the step would make
+    pub changes: Vec<String>,
+}
+
+/// Whether the changes an execution step would make could be determined
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecutionPlanStepOutcome {
+    /// the changes listed are exactly those execution would make
+    Planned,
+    /// execution would skip this step
+    Skipped { reason: String },
+    /// some changes could not be determined (e.g., because a sled could not be
+    /// reached); execution would likely fail or warn in the same way
+    Warning { message: String },
+}
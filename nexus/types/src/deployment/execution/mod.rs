@@ -3,11 +3,13 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 mod dns;
+mod dry_run;
 pub mod overridables;
 mod spec;
 mod utils;
 
 pub use dns::*;
+pub use dry_run::*;
 pub use overridables::Overridables;
 pub use spec::*;
 pub use utils::*;
@@ -19,10 +19,12 @@ use chrono::Utc;
 use clap::ValueEnum;
 use daft::Diffable;
 use ipnetwork::IpNetwork;
+use nexus_sled_agent_shared::inventory::ZoneKind;
 use omicron_common::address::IpRange;
 use omicron_common::address::Ipv6Subnet;
 use omicron_common::address::SLED_PREFIX;
 use omicron_common::api::external::Generation;
+use omicron_common::api::external::TufRepoDescription;
 use omicron_common::api::internal::shared::SourceNatConfigError;
 use omicron_common::disk::DiskIdentity;
 use omicron_common::policy::SINGLE_NODE_CLICKHOUSE_REDUNDANCY;
@@ -34,9 +36,11 @@ use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
 use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::btree_map::Entry;
 use std::error;
 use std::fmt;
+use std::num::NonZeroU32;
 use strum::Display;
 use strum::IntoEnumIterator;
 
@@ -152,6 +156,39 @@ impl PlanningInput {
             .unwrap_or(0)
     }
 
+    /// desired total number of in-service zones of kind `zone_kind`, if the
+    /// policy specifies one
+    ///
+    /// This is `None` for Crucible and internal NTP zones, which are deployed
+    /// per disk and per sled, and for external DNS zones, whose count is
+    /// determined by the external DNS IPs the system was set up with.
+    pub fn target_zone_count(&self, zone_kind: ZoneKind) -> Option<usize> {
+        match zone_kind {
+            ZoneKind::BoundaryNtp => {
+                Some(self.target_boundary_ntp_zone_count())
+            }
+            ZoneKind::Clickhouse => Some(self.target_clickhouse_zone_count()),
+            ZoneKind::ClickhouseKeeper => {
+                Some(self.target_clickhouse_keeper_zone_count())
+            }
+            ZoneKind::ClickhouseServer => {
+                Some(self.target_clickhouse_server_zone_count())
+            }
+            ZoneKind::CockroachDb => Some(self.target_cockroachdb_zone_count()),
+            ZoneKind::CruciblePantry => {
+                Some(self.target_crucible_pantry_zone_count())
+            }
+            ZoneKind::InternalDns => {
+                Some(self.target_internal_dns_zone_count())
+            }
+            ZoneKind::Nexus => Some(self.target_nexus_zone_count()),
+            ZoneKind::Oximeter => Some(self.target_oximeter_zone_count()),
+            ZoneKind::Crucible
+            | ZoneKind::ExternalDns
+            | ZoneKind::InternalNtp => None,
+        }
+    }
+
     pub fn service_ip_pool_ranges(&self) -> &[IpRange] {
         &self.policy.service_ip_pool_ranges
     }
@@ -171,10 +208,19 @@ impl PlanningInput {
         clickhouse_policy.mode.single_node_enabled()
     }
 
+    /// contents of the TUF repo for the current target release, if any
+    pub fn tuf_repo(&self) -> Option<&TufRepoDescription> {
+        self.policy.tuf_repo.as_ref()
+    }
+
     pub fn oximeter_read_settings(&self) -> &OximeterReadPolicy {
         &self.policy.oximeter_read_policy
     }
 
+    pub fn zone_placement_policy(&self) -> &ZonePlacementPolicy {
+        &self.policy.zone_placement_policy
+    }
+
     pub fn oximeter_cluster_read_enabled(&self) -> bool {
         self.policy.oximeter_read_policy.mode.cluster_enabled()
     }
@@ -918,6 +964,17 @@ pub struct Policy {
     /// Eventually we will only allow reads from a cluster and this policy will
     /// no longer exist.
     pub oximeter_read_policy: OximeterReadPolicy,
+
+    /// contents of the TUF repo for the current target release
+    ///
+    /// If this is `None`, no target release has been set and the planner
+    /// leaves zone images alone.
+    #[serde(default)]
+    pub tuf_repo: Option<TufRepoDescription>,
+
+    /// limits on which sleds may run discretionary zones, and how many
+    #[serde(default)]
+    pub zone_placement_policy: ZonePlacementPolicy,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -1028,6 +1085,82 @@ impl ClickhouseMode {
     }
 }
 
+/// Limits on where the planner may place discretionary zones (Nexus,
+/// CockroachDB, DNS, etc.)
+///
+/// This does not affect zones that every sled runs (Crucible and NTP).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ZonePlacementPolicy {
+    pub version: u32,
+    /// maximum number of in-service discretionary zones, of all kinds
+    /// combined, the planner will place on any one sled
+    ///
+    /// If this is `None`, sleds are limited only by their number of zpools.
+    pub max_discretionary_zones_per_sled: Option<u32>,
+    /// sleds reserved for instances
+    ///
+    /// The planner never places new discretionary zones on these sleds. It
+    /// does not move discretionary zones already on them; blippy reports those
+    /// so an operator can decide what to do.
+    pub reserved_sleds: BTreeSet<SledUuid>,
+    /// number of consecutive cubbies to group into each failure domain
+    ///
+    /// Inventory doesn't describe which power shelf or switch a sled depends
+    /// on, so the planner only knows about failure domains if an operator
+    /// opts into cubby-range spreading by setting this: sleds in cubbies
+    /// `0..n`, `n..2n`, etc. are then treated as separate failure domains. If
+    /// this is `None`, sleds have no failure domain.
+    #[serde(default)]
+    pub cubbies_per_failure_domain: Option<NonZeroU32>,
+    pub time_created: DateTime<Utc>,
+}
+
+impl ZonePlacementPolicy {
+    /// Returns a policy at `version` that places no limits on zone placement
+    pub fn new(version: u32) -> Self {
+        ZonePlacementPolicy {
+            version,
+            max_discretionary_zones_per_sled: None,
+            reserved_sleds: BTreeSet::new(),
+            cubbies_per_failure_domain: None,
+            time_created: Utc::now(),
+        }
+    }
+
+    /// Returns true if `sled_id` is reserved for instances
+    pub fn is_sled_reserved(&self, sled_id: SledUuid) -> bool {
+        self.reserved_sleds.contains(&sled_id)
+    }
+
+    /// Returns true if zones of this kind are discretionary (i.e., subject to
+    /// this policy)
+    pub fn is_discretionary(zone_kind: ZoneKind) -> bool {
+        match zone_kind {
+            // Every sled runs these (although internal NTP has some
+            // interactions with boundary NTP that the planner handles
+            // separately).
+            ZoneKind::Crucible | ZoneKind::InternalNtp => false,
+            ZoneKind::BoundaryNtp
+            | ZoneKind::Clickhouse
+            | ZoneKind::ClickhouseKeeper
+            | ZoneKind::ClickhouseServer
+            | ZoneKind::CockroachDb
+            | ZoneKind::CruciblePantry
+            | ZoneKind::ExternalDns
+            | ZoneKind::InternalDns
+            | ZoneKind::Nexus
+            | ZoneKind::Oximeter => true,
+        }
+    }
+}
+
+impl Default for ZonePlacementPolicy {
+    fn default() -> Self {
+        // Version 1 is the unrestricted policy every system starts with.
+        Self::new(1)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SledDetails {
     /// current sled policy
@@ -1036,6 +1169,79 @@ pub struct SledDetails {
     pub state: SledState,
     /// current resources allocated to this sled
     pub resources: SledResources,
+    /// failure domain this sled belongs to, if known
+    #[serde(default)]
+    pub failure_domain: Option<FailureDomain>,
+}
+
+/// Label identifying a set of sleds that are expected to fail together
+///
+/// The planner tries to spread replicas of redundant services (e.g., Nexus or
+/// CockroachDB) across sleds with different failure domains. Sleds whose
+/// failure domain is unknown are treated as being in a domain of their own.
+///
+/// Outside of tests, the only source of these labels today is cubby-range
+/// spreading (see [`ZonePlacementPolicy::cubbies_per_failure_domain`]).
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[serde(transparent)]
+pub struct FailureDomain(String);
+
+impl FailureDomain {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self(label.into())
+    }
+
+    /// Returns the failure domain for the range of `cubbies_per_domain`
+    /// consecutive cubbies containing `cubby` (the SP slot a sled occupies)
+    ///
+    /// This is how failure domains are assigned when cubby-range spreading is
+    /// enabled by [`ZonePlacementPolicy::cubbies_per_failure_domain`].
+    pub fn for_cubby_range(cubby: u16, cubbies_per_domain: NonZeroU32) -> Self {
+        let cubby = u32::from(cubby);
+        let start = cubby - cubby % cubbies_per_domain.get();
+        let end = start + (cubbies_per_domain.get() - 1);
+        Self(format!("cubbies-{start}-{end}"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true if replicas of zones of kind `zone_kind` should be spread
+    /// across as many failure domains as possible.
+    pub fn should_spread(zone_kind: ZoneKind) -> bool {
+        match zone_kind {
+            ZoneKind::CockroachDb
+            | ZoneKind::CruciblePantry
+            | ZoneKind::InternalDns
+            | ZoneKind::Nexus => true,
+            ZoneKind::BoundaryNtp
+            | ZoneKind::Clickhouse
+            | ZoneKind::ClickhouseKeeper
+            | ZoneKind::ClickhouseServer
+            | ZoneKind::Crucible
+            | ZoneKind::ExternalDns
+            | ZoneKind::InternalNtp
+            | ZoneKind::Oximeter => false,
+        }
+    }
+}
+
+impl fmt::Display for FailureDomain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -1087,6 +1293,8 @@ impl PlanningInputBuilder {
                 target_crucible_pantry_zone_count: 0,
                 clickhouse_policy: None,
                 oximeter_read_policy: OximeterReadPolicy::new(1),
+                tuf_repo: None,
+                zone_placement_policy: ZonePlacementPolicy::default(),
             },
             internal_dns_version: Generation::new(),
             external_dns_version: Generation::new(),
@@ -1212,6 +1420,35 @@ impl PlanningInputBuilder {
 #[cfg(test)]
 mod tests {
     use super::CockroachDbClusterVersion;
+    use super::FailureDomain;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn failure_domain_for_cubby_range() {
+        let eight = NonZeroU32::new(8).unwrap();
+        assert_eq!(
+            FailureDomain::for_cubby_range(0, eight).as_str(),
+            "cubbies-0-7"
+        );
+        assert_eq!(
+            FailureDomain::for_cubby_range(7, eight).as_str(),
+            "cubbies-0-7"
+        );
+        assert_eq!(
+            FailureDomain::for_cubby_range(8, eight).as_str(),
+            "cubbies-8-15"
+        );
+        assert_eq!(
+            FailureDomain::for_cubby_range(31, eight).as_str(),
+            "cubbies-24-31"
+        );
+
+        let one = NonZeroU32::new(1).unwrap();
+        assert_eq!(
+            FailureDomain::for_cubby_range(5, one).as_str(),
+            "cubbies-5-5"
+        );
+    }
 
     #[test]
     fn cockroachdb_cluster_versions() {
@@ -0,0 +1,177 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Types describing the decisions the Reconfigurator planner made while
+//! producing a blueprint.
+
+use nexus_sled_agent_shared::inventory::ZoneKind;
+use omicron_uuid_kinds::SledUuid;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use strum::Display;
+
+/// A record of why the planner made (or didn't make) each change in a
+/// blueprint
+///
+/// The planner's log tells the same story, but logs are ephemeral and hard to
+/// correlate with a particular blueprint. The report is attached to the
+/// blueprint the planner produced so that operators can answer "why isn't
+/// this rack converging?" after the fact.
+#[derive(
+    Clone, Debug, Default, Eq, PartialEq, JsonSchema, Deserialize, Serialize,
+)]
+pub struct PlanningReport {
+    /// decisions, in the order the planner made them
+    pub entries: Vec<PlanningReportEntry>,
+}
+
+impl PlanningReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record a decision made by the planner.
+    pub fn record(
+        &mut self,
+        step: PlanningStep,
+        sled_id: Option<SledUuid>,
+        zone_kind: Option<ZoneKind>,
+        outcome: PlanningOutcome,
+        reason: impl Into<String>,
+    ) {
+        self.entries.push(PlanningReportEntry {
+            step,
+            sled_id,
+            zone_kind,
+            outcome,
+            reason: reason.into(),
+        });
+    }
+
+    /// Iterate over the decisions concerning a particular sled.
+    pub fn entries_for_sled(
+        &self,
+        sled_id: SledUuid,
+    ) -> impl Iterator<Item = &PlanningReportEntry> + '_ {
+        self.entries.iter().filter(move |e| e.sled_id == Some(sled_id))
+    }
+
+    /// Iterate over the decisions that were blocked.
+    pub fn blocked(&self) -> impl Iterator<Item = &PlanningReportEntry> + '_ {
+        self.entries.iter().filter(|e| e.outcome == PlanningOutcome::Blocked)
+    }
+}
+
+impl fmt::Display for PlanningReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.entries.is_empty() {
+            return writeln!(f, "planning report: no decisions recorded");
+        }
+
+        // Group entries by sled, preserving the order in which the planner
+        // made decisions within each group. Fleet-wide decisions (those not
+        // concerning a particular sled) sort first.
+        let mut by_sled: BTreeMap<Option<SledUuid>, Vec<&PlanningReportEntry>> =
+            BTreeMap::new();
+        for entry in &self.entries {
+            by_sled.entry(entry.sled_id).or_default().push(entry);
+        }
+
+        writeln!(f, "planning report:")?;
+        for (sled_id, entries) in by_sled {
+            match sled_id {
+                Some(sled_id) => writeln!(f, "  sled {sled_id}:")?,
+                None => writeln!(f, "  fleet-wide:")?,
+            }
+            for entry in entries {
+                writeln!(f, "    * {entry}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single decision recorded in a [`PlanningReport`]
+#[derive(Clone, Debug, Eq, PartialEq, JsonSchema, Deserialize, Serialize)]
+pub struct PlanningReportEntry {
+    /// the planning step during which the decision was made
+    pub step: PlanningStep,
+    /// the sled the decision concerns, if any
+    pub sled_id: Option<SledUuid>,
+    /// the kind of zone the decision concerns, if any
+    //
+    // This is serialized as `ZoneKind::report_str()`.
+    #[schemars(with = "Option<String>")]
+    pub zone_kind: Option<ZoneKind>,
+    /// what the planner decided
+    pub outcome: PlanningOutcome,
+    /// human-readable explanation of the decision
+    pub reason: String,
+}
+
+impl fmt::Display for PlanningReportEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] ", self.step)?;
+        if let Some(zone_kind) = self.zone_kind {
+            write!(f, "{}: ", zone_kind.report_str())?;
+        }
+        write!(f, "{}: {}", self.outcome, self.reason)
+    }
+}
+
+/// The planning steps, in the order the planner performs them
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Display,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    JsonSchema,
+    Deserialize,
+    Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PlanningStep {
+    Expunge,
+    Add,
+    Decommission,
+    ZoneUpdates,
+    CockroachdbSettings,
+}
+
+/// The result of a decision recorded in a [`PlanningReport`]
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Display,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    JsonSchema,
+    Deserialize,
+    Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PlanningOutcome {
+    /// the planner made a change
+    Changed,
+    /// the planner considered a change but none was needed
+    Unchanged,
+    /// the planner wanted to make a change but something prevented it
+    Blocked,
+}
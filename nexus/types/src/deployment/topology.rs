@@ -0,0 +1,860 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Export of a blueprint's topology as a graph
+//!
+//! The text tables produced by `Blueprint::display()` describe each sled's
+//! disks, datasets, and zones separately.  The graph built here connects them:
+//! which zpools belong to which sled, which datasets live on which zpools,
+//! which datasets each zone uses, and which underlay and external IPs each
+//! zone has.  The graph can be rendered as Graphviz DOT or serialized as JSON.
+
+use super::Blueprint;
+use super::BlueprintDatasetDisposition;
+use super::BlueprintPhysicalDiskDisposition;
+use super::BlueprintZoneDisposition;
+use super::OmicronZoneExternalIp;
+use crate::external_api::views::SledState;
+use crate::inventory::Collection;
+use omicron_common::disk::DatasetName;
+use omicron_uuid_kinds::BlueprintUuid;
+use omicron_uuid_kinds::CollectionUuid;
+use omicron_uuid_kinds::DatasetUuid;
+use omicron_uuid_kinds::OmicronZoneUuid;
+use omicron_uuid_kinds::SledUuid;
+use omicron_uuid_kinds::ZpoolUuid;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Builds a [`TopologyGraph`] from a blueprint and, optionally, an inventory
+/// collection
+///
+/// Only in-service parts of the blueprint are included: sleds that have not
+/// been decommissioned and their in-service disks, datasets, and zones.  When
+/// a collection is provided, each sled, zpool, dataset, and zone node records
+/// whether that collection found it on the sled.
+pub struct BlueprintTopology<'a> {
+    blueprint: &'a Blueprint,
+    collection: Option<&'a Collection>,
+}
+
+impl<'a> BlueprintTopology<'a> {
+    pub fn new(blueprint: &'a Blueprint) -> Self {
+        Self { blueprint, collection: None }
+    }
+
+    /// Annotate the graph with what `collection` found on each sled.
+    pub fn with_collection(mut self, collection: &'a Collection) -> Self {
+        self.collection = Some(collection);
+        self
+    }
+
+    pub fn build(&self) -> TopologyGraph {
+        let mut graph = TopologyGraph {
+            blueprint_id: self.blueprint.id,
+            collection_id: self.collection.map(|c| c.id),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        };
+        let mut ips = BTreeSet::new();
+
+        for (sled_id, sled_config) in &self.blueprint.sleds {
+            if sled_config.state != SledState::Active {
+                continue;
+            }
+
+            let sled_inventory =
+                self.collection.map(|c| c.sled_agents.get(sled_id));
+            let sled_node = TopologyNodeId::sled(*sled_id);
+            graph.nodes.push(TopologyNode {
+                id: sled_node.clone(),
+                kind: TopologyNodeKind::Sled,
+                label: format!("sled {sled_id}"),
+                attributes: BTreeMap::new(),
+                inventory: sled_inventory.map(|sa| sa.is_some().into()),
+            });
+
+            // Only in-service zpools have nodes, so keep track of which those
+            // are to avoid edges to zpools that aren't in the graph.
+            let mut zpools = BTreeSet::new();
+            for disk in &sled_config.disks {
+                if disk.disposition
+                    != BlueprintPhysicalDiskDisposition::InService
+                {
+                    continue;
+                }
+                zpools.insert(disk.pool_id);
+                let zpool_node = TopologyNodeId::zpool(disk.pool_id);
+                let inventory = sled_inventory.map(|sa| {
+                    sa.is_some_and(|sa| {
+                        sa.zpools.iter().any(|z| z.id == disk.pool_id)
+                    })
+                    .into()
+                });
+                graph.nodes.push(TopologyNode {
+                    id: zpool_node.clone(),
+                    kind: TopologyNodeKind::Zpool,
+                    label: format!("zpool {}", disk.pool_id),
+                    attributes: BTreeMap::from([
+                        ("disk_id".to_string(), disk.id.to_string()),
+                        (
+                            "disk_serial".to_string(),
+                            disk.identity.serial.clone(),
+                        ),
+                    ]),
+                    inventory,
+                });
+                graph.edges.push(TopologyEdge {
+                    from: sled_node.clone(),
+                    to: zpool_node,
+                    kind: TopologyEdgeKind::Contains,
+                });
+            }
+
+            // Zones refer to their datasets by name, not id, so keep track of
+            // the id of each dataset by name.
+            let mut dataset_ids: BTreeMap<DatasetName, DatasetUuid> =
+                BTreeMap::new();
+            for dataset in &sled_config.datasets {
+                if dataset.disposition != BlueprintDatasetDisposition::InService
+                {
+                    continue;
+                }
+                let name = DatasetName::new(
+                    dataset.pool.clone(),
+                    dataset.kind.clone(),
+                );
+                let dataset_node = TopologyNodeId::dataset(dataset.id);
+                let inventory = sled_inventory.map(|sa| {
+                    sa.is_some_and(|sa| {
+                        sa.datasets.iter().any(|d| d.id == Some(dataset.id))
+                    })
+                    .into()
+                });
+                let mut attributes = BTreeMap::from([(
+                    "kind".to_string(),
+                    dataset.kind.to_string(),
+                )]);
+                if let Some(quota) = dataset.quota {
+                    attributes.insert("quota".to_string(), quota.to_string());
+                }
+                if let Some(reservation) = dataset.reservation {
+                    attributes.insert(
+                        "reservation".to_string(),
+                        reservation.to_string(),
+                    );
+                }
+                graph.nodes.push(TopologyNode {
+                    id: dataset_node.clone(),
+                    kind: TopologyNodeKind::Dataset,
+                    label: name.full_name(),
+                    attributes,
+                    inventory,
+                });
+                if zpools.contains(&dataset.pool.id()) {
+                    graph.edges.push(TopologyEdge {
+                        from: TopologyNodeId::zpool(dataset.pool.id()),
+                        to: dataset_node,
+                        kind: TopologyEdgeKind::Contains,
+                    });
+                }
+                dataset_ids.insert(name, dataset.id);
+            }
+
+            // If the blueprint has no dataset for a zone's filesystem or
+            // durable data (which blippy would flag), point at the zpool
+            // instead so the graph still shows where the zone lives -- unless
+            // the zpool isn't in service either, in which case there's nothing
+            // to point at.
+            let dataset_or_zpool = |name: &DatasetName| {
+                dataset_ids
+                    .get(name)
+                    .map(|id| TopologyNodeId::dataset(*id))
+                    .or_else(|| {
+                        let zpool_id = name.pool().id();
+                        zpools
+                            .contains(&zpool_id)
+                            .then(|| TopologyNodeId::zpool(zpool_id))
+                    })
+            };
+
+            for zone in &sled_config.zones {
+                if zone.disposition != BlueprintZoneDisposition::InService {
+                    continue;
+                }
+                let zone_node = TopologyNodeId::zone(zone.id);
+                let inventory = sled_inventory.map(|sa| {
+                    sa.is_some_and(|sa| {
+                        sa.omicron_zones.zones.iter().any(|z| z.id == zone.id)
+                    })
+                    .into()
+                });
+                graph.nodes.push(TopologyNode {
+                    id: zone_node.clone(),
+                    kind: TopologyNodeKind::Zone,
+                    label: format!("{} {}", zone.kind().report_str(), zone.id),
+                    attributes: BTreeMap::from([
+                        (
+                            "kind".to_string(),
+                            zone.kind().report_str().to_string(),
+                        ),
+                        (
+                            "image_source".to_string(),
+                            zone.image_source.to_string(),
+                        ),
+                    ]),
+                    inventory,
+                });
+                graph.edges.push(TopologyEdge {
+                    from: sled_node.clone(),
+                    to: zone_node.clone(),
+                    kind: TopologyEdgeKind::Runs,
+                });
+
+                if let Some(to) = dataset_or_zpool(&zone.filesystem_dataset()) {
+                    graph.edges.push(TopologyEdge {
+                        from: zone_node.clone(),
+                        to,
+                        kind: TopologyEdgeKind::Filesystem,
+                    });
+                }
+                if let Some(durable) = zone.zone_type.durable_dataset() {
+                    let name = DatasetName::new(
+                        durable.dataset.pool_name.clone(),
+                        durable.kind,
+                    );
+                    if let Some(to) = dataset_or_zpool(&name) {
+                        graph.edges.push(TopologyEdge {
+                            from: zone_node.clone(),
+                            to,
+                            kind: TopologyEdgeKind::DurableDataset,
+                        });
+                    }
+                }
+
+                let underlay_ip = IpAddr::V6(zone.underlay_ip());
+                if ips.insert(underlay_ip) {
+                    graph.nodes.push(TopologyNode {
+                        id: TopologyNodeId::ip(underlay_ip),
+                        kind: TopologyNodeKind::UnderlayIp,
+                        label: underlay_ip.to_string(),
+                        attributes: BTreeMap::new(),
+                        inventory: None,
+                    });
+                }
+                graph.edges.push(TopologyEdge {
+                    from: zone_node.clone(),
+                    to: TopologyNodeId::ip(underlay_ip),
+                    kind: TopologyEdgeKind::UnderlayIp,
+                });
+
+                if let Some((external_ip, _nic)) =
+                    zone.zone_type.external_networking()
+                {
+                    let ip = external_ip.ip();
+                    if ips.insert(ip) {
+                        graph.nodes.push(TopologyNode {
+                            id: TopologyNodeId::ip(ip),
+                            kind: TopologyNodeKind::ExternalIp,
+                            label: ip.to_string(),
+                            attributes: BTreeMap::new(),
+                            inventory: None,
+                        });
+                    }
+                    let kind = match external_ip {
+                        OmicronZoneExternalIp::Floating(_) => {
+                            TopologyEdgeKind::ExternalIp { snat_ports: None }
+                        }
+                        OmicronZoneExternalIp::Snat(snat) => {
+                            TopologyEdgeKind::ExternalIp {
+                                snat_ports: Some(
+                                    snat.snat_cfg.port_range_raw(),
+                                ),
+                            }
+                        }
+                    };
+                    graph.edges.push(TopologyEdge {
+                        from: zone_node,
+                        to: TopologyNodeId::ip(ip),
+                        kind,
+                    });
+                }
+            }
+        }
+
+        graph
+    }
+}
+
+/// Graph of the sleds, zpools, datasets, zones, and IP addresses described by
+/// a blueprint
+///
+/// Serializes to JSON as a list of nodes and a list of edges referring to
+/// nodes by id.  Use [`TopologyGraph::dot()`] to render it with Graphviz.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopologyGraph {
+    /// blueprint this graph describes
+    pub blueprint_id: BlueprintUuid,
+    /// collection used to annotate nodes, if any
+    pub collection_id: Option<CollectionUuid>,
+    pub nodes: Vec<TopologyNode>,
+    pub edges: Vec<TopologyEdge>,
+}
+
+impl TopologyGraph {
+    /// Returns a type that renders this graph in Graphviz DOT format.
+    pub fn dot(&self) -> TopologyGraphDot<'_> {
+        TopologyGraphDot { graph: self }
+    }
+
+    pub fn node(&self, id: &TopologyNodeId) -> Option<&TopologyNode> {
+        self.nodes.iter().find(|node| node.id == *id)
+    }
+
+    /// Returns the edges leaving the node `id`.
+    pub fn edges_from<'a>(
+        &'a self,
+        id: &'a TopologyNodeId,
+    ) -> impl Iterator<Item = &'a TopologyEdge> + 'a {
+        self.edges.iter().filter(move |edge| edge.from == *id)
+    }
+}
+
+/// Unique identifier for a node in a [`TopologyGraph`]
+///
+/// These are of the form `<kind>:<id>` (e.g., `sled:<uuid>` or
+/// `ip:fd00:1122:3344:101::1`).
+#[derive(
+    Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct TopologyNodeId(String);
+
+impl TopologyNodeId {
+    pub fn sled(id: SledUuid) -> Self {
+        Self(format!("sled:{id}"))
+    }
+
+    pub fn zpool(id: ZpoolUuid) -> Self {
+        Self(format!("zpool:{id}"))
+    }
+
+    pub fn dataset(id: DatasetUuid) -> Self {
+        Self(format!("dataset:{id}"))
+    }
+
+    pub fn zone(id: OmicronZoneUuid) -> Self {
+        Self(format!("zone:{id}"))
+    }
+
+    pub fn ip(ip: IpAddr) -> Self {
+        Self(format!("ip:{ip}"))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for TopologyNodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopologyNode {
+    pub id: TopologyNodeId,
+    pub kind: TopologyNodeKind,
+    pub label: String,
+    /// additional details about the node (e.g., a zone's kind)
+    pub attributes: BTreeMap<String, String>,
+    /// whether the inventory collection found this node
+    ///
+    /// `None` if the graph was built without a collection or if inventory
+    /// does not report this kind of node.
+    pub inventory: Option<TopologyInventoryStatus>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopologyNodeKind {
+    Sled,
+    Zpool,
+    Dataset,
+    Zone,
+    UnderlayIp,
+    ExternalIp,
+}
+
+/// Whether an inventory collection found a node described by the blueprint
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopologyInventoryStatus {
+    Found,
+    Missing,
+}
+
+impl From<bool> for TopologyInventoryStatus {
+    fn from(found: bool) -> Self {
+        if found { Self::Found } else { Self::Missing }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TopologyEdge {
+    pub from: TopologyNodeId,
+    pub to: TopologyNodeId,
+    pub kind: TopologyEdgeKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TopologyEdgeKind {
+    /// a sled contains a zpool, or a zpool contains a dataset
+    Contains,
+    /// a sled runs a zone
+    Runs,
+    /// a zone's root filesystem is on this dataset (or zpool)
+    Filesystem,
+    /// a zone's durable data is on this dataset (or zpool)
+    DurableDataset,
+    /// a zone listens on this underlay IP
+    UnderlayIp,
+    /// a zone uses this external IP (with this SNAT port range, if any)
+    ExternalIp { snat_ports: Option<(u16, u16)> },
+}
+
+/// Renders a [`TopologyGraph`] in Graphviz DOT format
+///
+/// Nodes that the inventory collection did not find are drawn dashed and in
+/// red.
+pub struct TopologyGraphDot<'a> {
+    graph: &'a TopologyGraph,
+}
+
+impl fmt::Display for TopologyGraphDot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let graph = self.graph;
+        writeln!(f, "digraph \"blueprint {}\" {{", graph.blueprint_id)?;
+        writeln!(f, "    rankdir=\"LR\"")?;
+        for node in &graph.nodes {
+            let shape = match node.kind {
+                TopologyNodeKind::Sled => "box3d",
+                TopologyNodeKind::Zpool => "cylinder",
+                TopologyNodeKind::Dataset => "folder",
+                TopologyNodeKind::Zone => "box",
+                TopologyNodeKind::UnderlayIp | TopologyNodeKind::ExternalIp => {
+                    "ellipse"
+                }
+            };
+            let mut label = node.label.clone();
+            for (key, value) in &node.attributes {
+                label.push_str(&format!("\n{key}: {value}"));
+            }
+            write!(
+                f,
+                "    {} [shape=\"{shape}\", label={}",
+                DotString(node.id.as_str()),
+                DotString(&label),
+            )?;
+            match node.inventory {
+                None | Some(TopologyInventoryStatus::Found) => (),
+                Some(TopologyInventoryStatus::Missing) => {
+                    write!(f, ", style=\"dashed\", color=\"red\"")?;
+                }
+            }
+            if node.kind == TopologyNodeKind::ExternalIp {
+                write!(f, ", color=\"blue\"")?;
+            }
+            writeln!(f, "]")?;
+        }
+        for edge in &graph.edges {
+            let label = match &edge.kind {
+                TopologyEdgeKind::Contains | TopologyEdgeKind::Runs => None,
+                TopologyEdgeKind::Filesystem => Some("filesystem".to_string()),
+                TopologyEdgeKind::DurableDataset => Some("durable".to_string()),
+                TopologyEdgeKind::UnderlayIp => Some("underlay".to_string()),
+                TopologyEdgeKind::ExternalIp { snat_ports: None } => {
+                    Some("external".to_string())
+                }
+                TopologyEdgeKind::ExternalIp {
+                    snat_ports: Some((first, last)),
+                } => Some(format!("snat {first}-{last}")),
+            };
+            write!(
+                f,
+                "    {} -> {}",
+                DotString(edge.from.as_str()),
+                DotString(edge.to.as_str()),
+            )?;
+            if let Some(label) = label {
+                write!(f, " [label={}]", DotString(&label))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// Formats a string as a quoted DOT identifier
+struct DotString<'a>(&'a str);
+
+impl fmt::Display for DotString<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                c => write!(f, "{c}")?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::deployment::BlueprintDatasetConfig;
+    use crate::deployment::BlueprintPhysicalDiskConfig;
+    use crate::deployment::BlueprintSledConfig;
+    use crate::deployment::BlueprintZoneConfig;
+    use crate::deployment::BlueprintZoneImageSource;
+    use crate::deployment::BlueprintZoneType;
+    use crate::deployment::CockroachDbPreserveDowngrade;
+    use crate::deployment::OximeterReadMode;
+    use crate::deployment::PendingMgsUpdates;
+    use crate::deployment::ZpoolName;
+    use crate::deployment::blueprint_zone_type;
+    use crate::inventory::Dataset;
+    use crate::inventory::SledAgent;
+    use crate::inventory::Zpool;
+    use id_map::IdMap;
+    use nexus_sled_agent_shared::inventory::OmicronZoneDataset;
+    use nexus_sled_agent_shared::inventory::OmicronZonesConfig;
+    use nexus_sled_agent_shared::inventory::SledRole;
+    use omicron_common::api::external::ByteCount;
+    use omicron_common::api::external::Generation;
+    use omicron_common::api::internal::shared::DatasetKind;
+    use omicron_common::disk::CompressionAlgorithm;
+    use omicron_common::disk::DiskIdentity;
+    use omicron_uuid_kinds::PhysicalDiskUuid;
+    use std::net::Ipv6Addr;
+    use std::net::SocketAddrV6;
+
+    /// Builds a blueprint with one sled running one Crucible zone.
+    fn crucible_blueprint() -> (Blueprint, SledUuid, ZpoolUuid, OmicronZoneUuid)
+    {
+        let sled_id = SledUuid::new_v4();
+        let zpool_id = ZpoolUuid::new_v4();
+        let zone_id = OmicronZoneUuid::new_v4();
+        let pool = ZpoolName::new_external(zpool_id);
+        let address = SocketAddrV6::new(
+            Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1),
+            0,
+            0,
+            0,
+        );
+
+        let disks = [BlueprintPhysicalDiskConfig {
+            disposition: BlueprintPhysicalDiskDisposition::InService,
+            identity: DiskIdentity {
+                vendor: "vendor".to_string(),
+                model: "model".to_string(),
+                serial: "serial \"0\"".to_string(),
+            },
+            id: PhysicalDiskUuid::new_v4(),
+            pool_id: zpool_id,
+        }]
+        .into_iter()
+        .collect::<IdMap<_>>();
+        let zone = BlueprintZoneConfig {
+            disposition: BlueprintZoneDisposition::InService,
+            id: zone_id,
+            filesystem_pool: pool.clone(),
+            zone_type: BlueprintZoneType::Crucible(
+                blueprint_zone_type::Crucible {
+                    address,
+                    dataset: OmicronZoneDataset { pool_name: pool.clone() },
+                },
+            ),
+            image_source: BlueprintZoneImageSource::InstallDataset,
+        };
+        let datasets =
+            [DatasetKind::Crucible, zone.filesystem_dataset().kind().clone()]
+                .into_iter()
+                .map(|kind| BlueprintDatasetConfig {
+                    disposition: BlueprintDatasetDisposition::InService,
+                    id: DatasetUuid::new_v4(),
+                    pool: pool.clone(),
+                    kind,
+                    address: None,
+                    quota: None,
+                    reservation: None,
+                    compression: CompressionAlgorithm::Off,
+                })
+                .collect::<IdMap<_>>();
+
+        let blueprint = Blueprint {
+            id: BlueprintUuid::new_v4(),
+            sleds: BTreeMap::from([(
+                sled_id,
+                BlueprintSledConfig {
+                    state: SledState::Active,
+                    sled_agent_generation: Generation::new(),
+                    disks,
+                    datasets,
+                    zones: [zone].into_iter().collect(),
+                },
+            )]),
+            pending_mgs_updates: PendingMgsUpdates::new(),
+            parent_blueprint_id: None,
+            internal_dns_version: Generation::new(),
+            external_dns_version: Generation::new(),
+            cockroachdb_fingerprint: String::new(),
+            cockroachdb_setting_preserve_downgrade:
+                CockroachDbPreserveDowngrade::DoNotModify,
+            clickhouse_cluster_config: None,
+            oximeter_read_version: Generation::new(),
+            oximeter_read_mode: OximeterReadMode::SingleNode,
+            time_created: chrono::Utc::now(),
+            creator: "test".to_string(),
+            comment: "test".to_string(),
+            planning_report: None,
+        };
+        (blueprint, sled_id, zpool_id, zone_id)
+    }
+
+    #[test]
+    fn test_blueprint_topology() {
+        let (blueprint, sled_id, zpool_id, zone_id) = crucible_blueprint();
+        let graph = BlueprintTopology::new(&blueprint).build();
+
+        // One sled, one zpool, two datasets, one zone, one underlay IP.
+        assert_eq!(graph.nodes.len(), 6, "nodes: {:#?}", graph.nodes);
+        assert!(graph.nodes.iter().all(|node| node.inventory.is_none()));
+
+        let sled_node = TopologyNodeId::sled(sled_id);
+        let zpool_node = TopologyNodeId::zpool(zpool_id);
+        let zone_node = TopologyNodeId::zone(zone_id);
+        let sled_edges: BTreeSet<_> =
+            graph.edges_from(&sled_node).map(|e| e.to.clone()).collect();
+        assert_eq!(
+            sled_edges,
+            BTreeSet::from([zpool_node.clone(), zone_node.clone()])
+        );
+        assert_eq!(graph.edges_from(&zpool_node).count(), 2);
+
+        // The zone's filesystem and durable data should both point at
+        // datasets (not the zpool), and it should have an underlay IP.
+        let zone_edges: Vec<_> = graph.edges_from(&zone_node).collect();
+        assert_eq!(zone_edges.len(), 3, "edges: {zone_edges:#?}");
+        for edge in &zone_edges {
+            let target = graph.node(&edge.to).expect("edge target exists");
+            let expected = match edge.kind {
+                TopologyEdgeKind::Filesystem
+                | TopologyEdgeKind::DurableDataset => TopologyNodeKind::Dataset,
+                TopologyEdgeKind::UnderlayIp => TopologyNodeKind::UnderlayIp,
+                _ => panic!("unexpected zone edge: {edge:?}"),
+            };
+            assert_eq!(target.kind, expected);
+        }
+
+        // Every edge should refer to nodes in the graph.
+        for edge in &graph.edges {
+            assert!(graph.node(&edge.from).is_some(), "edge: {edge:?}");
+            assert!(graph.node(&edge.to).is_some(), "edge: {edge:?}");
+        }
+
+        // The JSON form should round-trip.
+        let json = serde_json::to_string(&graph).unwrap();
+        let parsed: TopologyGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, graph);
+
+        // The DOT form should contain every node and escape the disk serial.
+        let dot = graph.dot().to_string();
+        assert!(dot.starts_with("digraph "));
+        for node in &graph.nodes {
+            assert!(dot.contains(&format!("\"{}\" [", node.id)), "{dot}");
+        }
+        assert!(dot.contains("disk_serial: serial \\\"0\\\""), "{dot}");
+        assert!(
+            !dot.contains("color=\"red\""),
+            "no nodes should be missing: {dot}"
+        );
+
+        // Expunging the zone removes it (and its underlay IP) from the graph.
+        let mut blueprint = blueprint;
+        for mut zone in &mut blueprint.sleds.get_mut(&sled_id).unwrap().zones {
+            zone.disposition = BlueprintZoneDisposition::Expunged {
+                as_of_generation: Generation::new(),
+                ready_for_cleanup: false,
+            };
+        }
+        let graph = BlueprintTopology::new(&blueprint).build();
+        assert!(graph.node(&zone_node).is_none());
+        assert_eq!(graph.nodes.len(), 4, "nodes: {:#?}", graph.nodes);
+    }
+
+    #[test]
+    fn test_blueprint_topology_with_collection() {
+        let (blueprint, sled_id, zpool_id, zone_id) = crucible_blueprint();
+        let sled_config = &blueprint.sleds[&sled_id];
+
+        // Build a collection in which the sled reported its zpool and datasets
+        // but not its zone.
+        let now = chrono::Utc::now();
+        let sled_agent = SledAgent {
+            time_collected: now,
+            source: "test".to_string(),
+            sled_id,
+            baseboard_id: None,
+            sled_agent_address: "[::1]:0".parse().unwrap(),
+            sled_role: SledRole::Gimlet,
+            usable_hardware_threads: 1,
+            usable_physical_ram: ByteCount::from(0u32),
+            reservoir_size: ByteCount::from(0u32),
+            omicron_zones: OmicronZonesConfig {
+                generation: Generation::new(),
+                zones: Vec::new(),
+            },
+            disks: Vec::new(),
+            zpools: vec![Zpool {
+                time_collected: now,
+                id: zpool_id,
+                total_size: ByteCount::from(0u32),
+            }],
+            datasets: sled_config
+                .datasets
+                .iter()
+                .map(|dataset| Dataset {
+                    id: Some(dataset.id),
+                    name: DatasetName::new(
+                        dataset.pool.clone(),
+                        dataset.kind.clone(),
+                    )
+                    .full_name(),
+                    available: ByteCount::from(0u32),
+                    used: ByteCount::from(0u32),
+                    quota: None,
+                    reservation: None,
+                    compression: "off".to_string(),
+                })
+                .collect(),
+            omicron_physical_disks_generation: Generation::new(),
+        };
+        let collection = Collection {
+            id: CollectionUuid::new_v4(),
+            errors: Vec::new(),
+            time_started: now,
+            time_done: now,
+            collector: "test".to_string(),
+            baseboards: BTreeSet::new(),
+            cabooses: BTreeSet::new(),
+            rot_pages: BTreeSet::new(),
+            sps: BTreeMap::new(),
+            rots: BTreeMap::new(),
+            cabooses_found: BTreeMap::new(),
+            rot_pages_found: BTreeMap::new(),
+            sled_agents: BTreeMap::from([(sled_id, sled_agent)]),
+            clickhouse_keeper_cluster_membership: BTreeSet::new(),
+        };
+
+        let graph = BlueprintTopology::new(&blueprint)
+            .with_collection(&collection)
+            .build();
+        assert_eq!(graph.collection_id, Some(collection.id));
+        let zone_node = TopologyNodeId::zone(zone_id);
+        for node in &graph.nodes {
+            let expected = match node.kind {
+                TopologyNodeKind::Zone => {
+                    Some(TopologyInventoryStatus::Missing)
+                }
+                TopologyNodeKind::Sled
+                | TopologyNodeKind::Zpool
+                | TopologyNodeKind::Dataset => {
+                    Some(TopologyInventoryStatus::Found)
+                }
+                TopologyNodeKind::UnderlayIp | TopologyNodeKind::ExternalIp => {
+                    None
+                }
+            };
+            assert_eq!(node.inventory, expected, "node: {node:?}");
+        }
+
+        // Only the missing zone is drawn dashed and in red.
+        let dot = graph.dot().to_string();
+        let missing: Vec<_> = dot
+            .lines()
+            .filter(|line| line.contains("style=\"dashed\", color=\"red\""))
+            .collect();
+        assert_eq!(missing.len(), 1, "{dot}");
+        assert!(
+            missing[0].trim_start().starts_with(&format!("\"{zone_node}\" [")),
+            "{dot}"
+        );
+
+        // A sled the collection didn't find at all is missing, along with
+        // everything on it.
+        let collection =
+            Collection { sled_agents: BTreeMap::new(), ..collection };
+        let graph = BlueprintTopology::new(&blueprint)
+            .with_collection(&collection)
+            .build();
+        for node in &graph.nodes {
+            let expected = match node.kind {
+                TopologyNodeKind::UnderlayIp | TopologyNodeKind::ExternalIp => {
+                    None
+                }
+                _ => Some(TopologyInventoryStatus::Missing),
+            };
+            assert_eq!(node.inventory, expected, "node: {node:?}");
+        }
+    }
+
+    #[test]
+    fn test_blueprint_topology_expunged_disk() {
+        // If the zone's disk has been expunged but (inconsistently) its
+        // datasets and zone have not, the graph omits the zpool and every
+        // edge that would refer to it.
+        let (mut blueprint, sled_id, zpool_id, zone_id) = crucible_blueprint();
+        let sled_config = blueprint.sleds.get_mut(&sled_id).unwrap();
+        for mut disk in &mut sled_config.disks {
+            disk.disposition = BlueprintPhysicalDiskDisposition::Expunged {
+                as_of_generation: Generation::new(),
+                ready_for_cleanup: false,
+            };
+        }
+        let graph = BlueprintTopology::new(&blueprint).build();
+        assert!(graph.node(&TopologyNodeId::zpool(zpool_id)).is_none());
+        for edge in &graph.edges {
+            assert!(graph.node(&edge.from).is_some(), "edge: {edge:?}");
+            assert!(graph.node(&edge.to).is_some(), "edge: {edge:?}");
+        }
+
+        // The zone still points at its datasets.
+        let zone_node = TopologyNodeId::zone(zone_id);
+        assert_eq!(graph.edges_from(&zone_node).count(), 3);
+
+        // Without the datasets, the zone has nothing to point at but its
+        // underlay IP.
+        let sled_config = blueprint.sleds.get_mut(&sled_id).unwrap();
+        sled_config.datasets = IdMap::new();
+        let graph = BlueprintTopology::new(&blueprint).build();
+        for edge in &graph.edges {
+            assert!(graph.node(&edge.from).is_some(), "edge: {edge:?}");
+            assert!(graph.node(&edge.to).is_some(), "edge: {edge:?}");
+        }
+        let zone_edges: Vec<_> = graph.edges_from(&zone_node).collect();
+        assert_eq!(zone_edges.len(), 1, "edges: {zone_edges:#?}");
+        assert_eq!(zone_edges[0].kind, TopologyEdgeKind::UnderlayIp);
+    }
+}
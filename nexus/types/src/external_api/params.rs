@@ -29,6 +29,7 @@ use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::{net::IpAddr, str::FromStr};
+use tufaceous_artifact::ArtifactHash;
 use uuid::Uuid;
 
 macro_rules! path_param {
@@ -74,6 +75,7 @@ path_param!(AffinityGroupPath, affinity_group, "affinity group");
 path_param!(AntiAffinityGroupPath, anti_affinity_group, "anti affinity group");
 path_param!(ProjectPath, project, "project");
 path_param!(InstancePath, instance, "instance");
+path_param!(InstanceTemplatePath, instance_template, "instance template");
 path_param!(NetworkInterfacePath, interface, "network interface");
 path_param!(VpcPath, vpc, "VPC");
 path_param!(SubnetPath, subnet, "subnet");
@@ -83,6 +85,8 @@ path_param!(InternetGatewayPath, gateway, "gateway");
 path_param!(FloatingIpPath, floating_ip, "floating IP");
 path_param!(DiskPath, disk, "disk");
 path_param!(SnapshotPath, snapshot, "snapshot");
+path_param!(SnapshotPolicyPath, snapshot_policy, "snapshot policy");
+path_param!(SnapshotGroupPath, snapshot_group, "snapshot group");
 path_param!(ImagePath, image, "image");
 path_param!(SiloPath, silo, "silo");
 path_param!(ProviderPath, provider, "SAML identity provider");
@@ -155,6 +159,15 @@ pub struct SupportBundleFilePath {
     pub file: String,
 }
 
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SnapshotExportPath {
+    #[serde(flatten)]
+    pub snapshot: SnapshotPath,
+
+    /// ID of the export
+    pub export_id: Uuid,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct OptionalSiloSelector {
     /// Name or ID of the silo
@@ -223,6 +236,22 @@ pub struct SnapshotSelector {
     pub snapshot: NameOrId,
 }
 
+#[derive(Deserialize, JsonSchema)]
+pub struct SnapshotPolicySelector {
+    /// Name or ID of the project, only required if `snapshot_policy` is provided as a `Name`
+    pub project: Option<NameOrId>,
+    /// Name or ID of the snapshot policy
+    pub snapshot_policy: NameOrId,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct SnapshotGroupSelector {
+    /// Name or ID of the project, only required if `snapshot_group` is provided as a `Name`
+    pub project: Option<NameOrId>,
+    /// Name or ID of the snapshot group
+    pub snapshot_group: NameOrId,
+}
+
 #[derive(Deserialize, JsonSchema)]
 pub struct ImageSelector {
     /// Name or ID of the project, only required if `image` is provided as a `Name`
@@ -239,6 +268,14 @@ pub struct InstanceSelector {
     pub instance: NameOrId,
 }
 
+#[derive(Deserialize, JsonSchema)]
+pub struct InstanceTemplateSelector {
+    /// Name or ID of the project, only required if `instance_template` is provided as a `Name`
+    pub project: Option<NameOrId>,
+    /// Name or ID of the instance template
+    pub instance_template: NameOrId,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
 pub struct OptionalInstanceSelector {
     /// Name or ID of the project, only required if `instance` is provided as a `Name`
@@ -1266,6 +1303,112 @@ pub struct InstanceUpdate {
     pub auto_restart_policy: Option<InstanceAutoRestartPolicy>,
 }
 
+/// Parameters for live-migrating a running `Instance` to another sled
+#[derive(Clone, Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceMigrate {
+    /// The sled to migrate the instance to.
+    ///
+    /// Choosing a sled requires fleet-level privileges. If this is not
+    /// provided, the control plane chooses a sled other than the one the
+    /// instance is running on, honoring the instance's affinity and
+    /// anti-affinity groups.
+    #[serde(default)]
+    pub dst_sled_id: Option<Uuid>,
+}
+
+// INSTANCE TEMPLATES
+
+/// Create-time parameters for an `InstanceTemplate`
+///
+/// These are the parts of `InstanceCreate` that every instance created from
+/// the template shares. Each instance's name and hostname are chosen when the
+/// template is instantiated.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceTemplateCreate {
+    #[serde(flatten)]
+    pub identity: IdentityMetadataCreateParams,
+    /// The number of vCPUs to be allocated to each instance
+    pub ncpus: InstanceCpuCount,
+    /// The amount of RAM (in bytes) to be allocated to each instance
+    pub memory: ByteCount,
+
+    /// User data for instance initialization systems (such as cloud-init).
+    /// Must be a Base64-encoded string, as specified in RFC 4648 § 4 (+ and /
+    /// characters with padding). Maximum 32 KiB unencoded data.
+    #[serde(default, with = "UserData")]
+    pub user_data: Vec<u8>,
+
+    /// The network interfaces to be created for each instance.
+    ///
+    /// Interfaces may not request a specific IP address, since each instance
+    /// needs its own.
+    #[serde(default)]
+    pub network_interfaces: InstanceNetworkInterfaceAttachment,
+
+    /// The external IP addresses provided to each instance.
+    ///
+    /// Only ephemeral IPs are allowed, since a floating IP can only be
+    /// attached to one instance.
+    #[serde(default)]
+    pub external_ips: Vec<ExternalIpCreate>,
+
+    /// The boot disk to create for each instance.
+    #[serde(default)]
+    pub boot_disk: Option<InstanceTemplateBootDisk>,
+
+    /// An allowlist of SSH public keys to be transferred to each instance via
+    /// cloud-init.
+    ///
+    /// If not provided, all SSH public keys from the profile of the user
+    /// instantiating the template will be sent. If an empty list is provided,
+    /// no public keys will be transmitted to the instances.
+    pub ssh_public_keys: Option<Vec<NameOrId>>,
+
+    /// The auto-restart policy for each instance.
+    #[serde(default)]
+    pub auto_restart_policy: Option<InstanceAutoRestartPolicy>,
+}
+
+/// The boot disk created for each instance instantiated from a template
+///
+/// The disk is named after its instance, with a `-boot` suffix.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceTemplateBootDisk {
+    /// The initial source for the disk
+    pub disk_source: DiskSource,
+    /// The total size of the disk (in bytes)
+    pub size: ByteCount,
+}
+
+/// Parameters for creating instances from an `InstanceTemplate`
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceTemplateInstantiate {
+    /// The pattern used to name the instances. `{n}` is replaced with the
+    /// index of each instance, and must appear exactly once; the resulting
+    /// names are also used as the instances' hostnames.
+    pub name_pattern: String,
+
+    /// The number of instances to create
+    pub count: u32,
+
+    /// The index of the first instance; 0 by default.
+    #[serde(default)]
+    pub first_index: u32,
+
+    /// Whether the instances should be started once created; true by default.
+    #[serde(default = "bool_true")]
+    pub start: bool,
+
+    /// Affinity groups, in the template's project, to add each instance to.
+    #[serde(default)]
+    pub affinity_groups: Vec<NameOrId>,
+
+    /// Anti-affinity groups, in the template's project, to add each instance
+    /// to.
+    #[serde(default)]
+    pub anti_affinity_groups: Vec<NameOrId>,
+}
+
 #[inline]
 fn bool_true() -> bool {
     true
@@ -1275,7 +1418,7 @@ fn bool_true() -> bool {
 // `UserData::deserialize()` below.
 pub const MAX_USER_DATA_BYTES: usize = 32 * 1024; // 32 KiB
 
-struct UserData;
+pub(crate) struct UserData;
 impl UserData {
     pub fn serialize<S>(
         data: &Vec<u8>,
@@ -1609,6 +1752,8 @@ pub enum DiskSource {
     Snapshot { snapshot_id: Uuid },
     /// Create a disk from an image
     Image { image_id: Uuid },
+    /// Create a disk from a copy of another disk
+    Disk { disk_id: Uuid },
     /// Create a blank disk that will accept bulk writes or pull blocks from an
     /// external source.
     ImportingBlocks { block_size: BlockSize },
@@ -1626,6 +1771,14 @@ pub struct DiskCreate {
     pub size: ByteCount,
 }
 
+/// Parameters for resizing a `Disk`
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct DiskResize {
+    /// The new total size of the Disk (in bytes). Disks can only be grown, so
+    /// this must be larger than the current size.
+    pub size: ByteCount,
+}
+
 // equivalent to crucible_pantry_client::types::ExpectedDigest
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -2115,12 +2268,75 @@ pub enum ImageSource {
         id: Uuid,
     },
 
+    /// Import the image from a file served over HTTP or HTTPS
+    Url {
+        /// The URL of the image file
+        url: String,
+
+        /// The format of the image file
+        format: ImageImportFormat,
+
+        /// The block size of the disk the image is imported to
+        block_size: BlockSize,
+
+        /// If specified, the import fails unless the image file has this
+        /// digest
+        expected_digest: Option<ExpectedDigest>,
+    },
+
     /// Boot the Alpine ISO that ships with the Propolis zone. Intended for
     /// development purposes only.
     #[schemars(skip)] // keep it out of the OpenAPI schema
     YouCanBootAnythingAsLongAsItsAlpine,
 }
 
+/// The format of an imported image file
+#[derive(
+    Clone, Copy, Debug, Deserialize, Serialize, JsonSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageImportFormat {
+    /// A disk's blocks, byte for byte
+    Raw,
+    /// A QEMU copy-on-write (version 2 or 3) image
+    Qcow2,
+}
+
+/// Path parameters for creating an image from an uploaded file
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ImageUploadPath {
+    /// The name of the image to create
+    pub image: Name,
+}
+
+/// Parameters for creating an image from an uploaded file
+///
+/// The image file is sent as the request body.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct ImageUpload {
+    /// The project the image is created in
+    pub project: NameOrId,
+
+    /// A description of the image
+    pub description: String,
+
+    /// The family of the operating system (e.g. Debian, Ubuntu, etc.)
+    pub os: String,
+
+    /// The version of the operating system (e.g. 18.04, 20.04, etc.)
+    pub version: String,
+
+    /// The format of the image file
+    pub format: ImageImportFormat,
+
+    /// The block size of the disk the image is imported to
+    pub block_size: BlockSize,
+
+    /// If specified, the upload fails unless the SHA-256 digest of the image
+    /// file (as a hex string) matches
+    pub sha256: Option<String>,
+}
+
 /// OS image distribution
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct Distribution {
@@ -2149,6 +2365,13 @@ pub struct ImageCreate {
 
 // SNAPSHOTS
 
+/// Create-time parameters for an export of a `Snapshot`
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SnapshotExportCreate {
+    /// The format of the exported image
+    pub format: super::views::SnapshotExportFormat,
+}
+
 /// Create-time parameters for a `Snapshot`
 #[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
 pub struct SnapshotCreate {
@@ -2160,6 +2383,44 @@ pub struct SnapshotCreate {
     pub disk: NameOrId,
 }
 
+/// Create-time parameters for a `SnapshotPolicy`
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SnapshotPolicyCreate {
+    #[serde(flatten)]
+    pub identity: IdentityMetadataCreateParams,
+
+    /// The disk to snapshot. If not provided, every disk in the project is
+    /// snapshotted, including disks created after the policy.
+    pub disk: Option<NameOrId>,
+
+    /// How often each disk is snapshotted, in seconds
+    pub interval_secs: u32,
+
+    /// How many of the policy's snapshots of each disk to keep. Once a disk
+    /// has more, the oldest are deleted.
+    pub retention_count: u32,
+}
+
+/// Create-time parameters for a `SnapshotGroup`
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SnapshotGroupCreate {
+    #[serde(flatten)]
+    pub identity: IdentityMetadataCreateParams,
+
+    /// The instance whose disks are snapshotted. Every disk attached to the
+    /// instance is snapshotted.
+    pub instance: NameOrId,
+}
+
+/// Parameters for restoring a `SnapshotGroup` as a set of new disks
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SnapshotGroupRestore {
+    /// Prefix for the names of the new disks. Each disk is named by joining
+    /// the prefix and the name of the disk its snapshot was taken of with a
+    /// `-`.
+    pub name_prefix: Name,
+}
+
 // USERS AND GROUPS
 
 #[derive(Clone, Debug, Serialize, Deserialize, JsonSchema, PartialEq)]
@@ -2244,6 +2505,38 @@ pub struct UpdatesGetRepositoryParams {
     pub system_version: Version,
 }
 
+/// Create-time parameters for a resumable TUF repository upload.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TufRepoUploadCreate {
+    /// The name of the file being uploaded.
+    pub file_name: String,
+    /// The total size of the repository archive, in bytes.
+    pub size: u64,
+}
+
+/// Path parameters for TUF repository upload requests.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TufRepoUploadPath {
+    /// ID of the upload.
+    pub upload_id: Uuid,
+}
+
+/// Parameters for PUT requests to
+/// `/v1/system/update/repository-uploads/{upload_id}/data`.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TufRepoUploadWriteParams {
+    /// Offset within the repository archive at which the request body
+    /// begins. This must be equal to the number of bytes received so far.
+    pub offset: u64,
+}
+
+/// Parameters for completing a TUF repository upload.
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct TufRepoUploadFinalize {
+    /// The expected SHA-256 of the complete repository archive.
+    pub sha256: ArtifactHash,
+}
+
 /// Parameters for PUT requests to `/v1/system/update/target-release`.
 #[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
 pub struct SetTargetReleaseParams {
@@ -2251,6 +2544,14 @@ pub struct SetTargetReleaseParams {
     pub system_version: Version,
 }
 
+/// Parameters for proposing a blueprint for review before it's executed.
+#[derive(Clone, Debug, Deserialize, JsonSchema, Serialize)]
+pub struct BlueprintPropose {
+    /// ID of the user who must approve the blueprint before it may be
+    /// executed. This must be someone other than the proposer.
+    pub required_approver: Uuid,
+}
+
 // Probes
 
 /// Create time parameters for probes.
@@ -14,7 +14,8 @@ use chrono::Utc;
 use daft::Diffable;
 use omicron_common::api::external::{
     AffinityPolicy, AllowedSourceIps as ExternalAllowedSourceIps, ByteCount,
-    Digest, Error, FailureDomain, IdentityMetadata, InstanceState, Name,
+    Digest, Error, FailureDomain, IdentityMetadata, Instance,
+    InstanceAutoRestartPolicy, InstanceCpuCount, InstanceState, Name, NameOrId,
     ObjectIdentity, RoleName, SimpleIdentityOrName,
 };
 use oxnet::{Ipv4Net, Ipv6Net};
@@ -28,7 +29,10 @@ use std::net::IpAddr;
 use strum::{EnumIter, IntoEnumIterator};
 use uuid::Uuid;
 
-use super::params::PhysicalDiskKind;
+use super::params::{
+    ExternalIpCreate, InstanceNetworkInterfaceAttachment,
+    InstanceTemplateBootDisk, PhysicalDiskKind, UserData,
+};
 
 // SILOS
 
@@ -263,6 +267,89 @@ pub struct Snapshot {
     pub size: ByteCount,
 }
 
+/// The format of an exported snapshot image
+#[derive(
+    Clone, Copy, Debug, Deserialize, Serialize, JsonSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotExportFormat {
+    /// The snapshot's blocks, byte for byte
+    Raw,
+    /// A QEMU copy-on-write (version 2) image
+    Qcow2,
+}
+
+/// View of an export of a Snapshot
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SnapshotExport {
+    /// Unique, immutable, system-controlled identifier for the export
+    pub id: Uuid,
+    /// The snapshot being exported
+    pub snapshot_id: Uuid,
+    /// Timestamp when this export was created
+    pub time_created: DateTime<Utc>,
+    pub format: SnapshotExportFormat,
+    /// Size of the exported image
+    pub size: ByteCount,
+    /// Number of bytes of the image downloaded so far, counting any range that
+    /// was downloaded more than once each time
+    pub bytes_exported: ByteCount,
+}
+
+/// View of a SnapshotPolicy
+#[derive(ObjectIdentity, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SnapshotPolicy {
+    #[serde(flatten)]
+    pub identity: IdentityMetadata,
+
+    pub project_id: Uuid,
+    /// The disk the policy snapshots, or null if it snapshots every disk in
+    /// the project
+    pub disk_id: Option<Uuid>,
+
+    /// How often each disk is snapshotted, in seconds
+    pub interval_secs: u32,
+    /// How many of the policy's snapshots of each disk are kept
+    pub retention_count: u32,
+
+    /// Timestamp when the policy last took or deleted snapshots
+    pub time_last_run: Option<DateTime<Utc>>,
+    /// What went wrong the last time the policy took or deleted snapshots, if
+    /// anything
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotGroupState {
+    Creating,
+    Ready,
+}
+
+/// View of a SnapshotGroup
+#[derive(ObjectIdentity, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct SnapshotGroup {
+    #[serde(flatten)]
+    pub identity: IdentityMetadata,
+
+    pub project_id: Uuid,
+    /// The instance whose disks were snapshotted
+    pub instance_id: Uuid,
+    pub state: SnapshotGroupState,
+    /// The snapshots in the group, one for each of the instance's disks
+    pub members: Vec<SnapshotGroupMember>,
+}
+
+/// One of the snapshots in a snapshot group
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+pub struct SnapshotGroupMember {
+    pub snapshot_id: Uuid,
+    /// The disk the snapshot was taken of
+    pub disk_id: Uuid,
+    /// The name the disk had when the snapshot was taken
+    pub disk_name: Name,
+}
+
 // VPCs
 
 /// View of a VPC
@@ -548,6 +635,81 @@ impl TryFrom<ExternalIp> for FloatingIp {
     }
 }
 
+// INSTANCE MIGRATIONS
+
+/// The state of an instance migration
+#[derive(
+    Clone, Copy, Debug, Deserialize, Serialize, JsonSchema, PartialEq, Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum InstanceMigrationState {
+    /// The migration has not started yet.
+    Pending,
+    /// The instance is being migrated.
+    InProgress,
+    /// The instance is running on the sled it was migrated to.
+    Completed,
+    /// The migration failed, and the instance was not migrated.
+    Failed,
+}
+
+/// View of a live migration of an instance from one sled to another
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceMigration {
+    /// Unique, immutable, system-controlled identifier for the migration
+    pub id: Uuid,
+    /// The instance being migrated
+    pub instance_id: Uuid,
+    /// Timestamp when the migration was started
+    pub time_created: DateTime<Utc>,
+    /// Timestamp when the state of the migration last changed
+    pub time_updated: DateTime<Utc>,
+    pub state: InstanceMigrationState,
+    /// Why the migration failed, if it failed
+    pub failure_reason: Option<String>,
+}
+
+// INSTANCE TEMPLATES
+
+/// View of an InstanceTemplate
+#[derive(ObjectIdentity, Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceTemplate {
+    #[serde(flatten)]
+    pub identity: IdentityMetadata,
+
+    pub project_id: Uuid,
+
+    pub ncpus: InstanceCpuCount,
+    pub memory: ByteCount,
+    /// User data for instance initialization systems, Base64-encoded
+    #[serde(with = "UserData")]
+    pub user_data: Vec<u8>,
+    pub network_interfaces: InstanceNetworkInterfaceAttachment,
+    pub external_ips: Vec<ExternalIpCreate>,
+    pub boot_disk: Option<InstanceTemplateBootDisk>,
+    pub ssh_public_keys: Option<Vec<NameOrId>>,
+    pub auto_restart_policy: Option<InstanceAutoRestartPolicy>,
+}
+
+/// The outcome of creating one instance from an instance template
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceTemplateInstanceResult {
+    /// The name the instance was to be given
+    pub name: Name,
+    /// The instance, if it was created
+    pub instance: Option<Instance>,
+    /// What went wrong creating the instance, adding it to its affinity
+    /// groups, or starting it, if anything
+    pub error: Option<String>,
+}
+
+/// The outcome of instantiating an instance template
+#[derive(Clone, Debug, Deserialize, Serialize, JsonSchema)]
+pub struct InstanceTemplateInstantiation {
+    /// One entry per requested instance, in index order
+    pub instances: Vec<InstanceTemplateInstanceResult>,
+}
+
 // RACKS
 
 /// View of an Rack
@@ -1054,6 +1216,29 @@ pub struct OxqlQueryResult {
 
 // UPDATE
 
+/// View of an in-progress, resumable TUF repository upload.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, JsonSchema)]
+pub struct TufRepoUpload {
+    /// ID of the upload.
+    pub id: Uuid,
+
+    /// The name of the file being uploaded.
+    pub file_name: String,
+
+    /// The total size of the repository archive, in bytes.
+    pub size: u64,
+
+    /// The number of bytes received so far. The next write must begin at
+    /// this offset.
+    pub bytes_received: u64,
+
+    /// The time the upload was created.
+    pub time_created: DateTime<Utc>,
+
+    /// The time data was last written to the upload.
+    pub time_modified: DateTime<Utc>,
+}
+
 /// Source of a system software target release.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, JsonSchema, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
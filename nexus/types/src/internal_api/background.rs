@@ -458,3 +458,23 @@ pub struct ReadOnlyRegionReplacementStartStatus {
     pub requests_created_ok: Vec<String>,
     pub errors: Vec<String>,
 }
+
+/// The status of a `snapshot_policy` background task activation
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+pub struct SnapshotPolicyStatus {
+    /// number of snapshot policies found
+    pub policies_found: usize,
+    /// snapshots taken by policies
+    pub snapshots_created: Vec<Uuid>,
+    /// snapshots deleted because policies no longer retain them
+    pub snapshots_deleted: Vec<Uuid>,
+    pub errors: Vec<String>,
+}
+
+/// The status of a `tuf_repo_upload_pruner` background task activation
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq, Eq)]
+pub struct TufRepoUploadPrunerStatus {
+    /// resumable uploads discarded because they were left idle
+    pub uploads_deleted: Vec<Uuid>,
+    pub errors: Vec<String>,
+}
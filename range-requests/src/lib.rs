@@ -2,24 +2,47 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use bytes::Bytes;
 use dropshot::Body;
+use futures::Stream;
 use futures::TryStreamExt;
 use http::HeaderValue;
 use hyper::{
     Response, StatusCode,
     header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE},
 };
+use rand::Rng;
+use rand::distributions::Alphanumeric;
+use std::collections::VecDeque;
+use std::io::{Read, Seek, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
 
 const ACCEPT_RANGES_BYTES: http::HeaderValue =
     http::HeaderValue::from_static("bytes");
 const CONTENT_TYPE_OCTET_STREAM: http::HeaderValue =
     http::HeaderValue::from_static("application/octet-stream");
 
+/// The maximum number of ranges we'll accept in a single request
+///
+/// Each range becomes a separate part of a multipart response, so this bounds
+/// the amount of work a single request can ask of us.
+pub const MAX_RANGES: usize = 32;
+
+/// Length of the randomly-generated boundary between parts of a
+/// multipart/byteranges response
+const MULTIPART_BOUNDARY_LEN: usize = 32;
+
+/// Size of the chunks in which [stream_file_ranges] reads files
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
 /// Errors which may be returned when processing range requests
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
-    #[error("Using multiple ranges is not supported")]
-    MultipleRangesUnsupported,
+    #[error("Too many ranges requested (at most {MAX_RANGES} are supported)")]
+    TooManyRanges,
 
     #[error("Range would overflow (start + length is too large)")]
     RangeOverflow,
@@ -33,6 +56,9 @@ pub enum Error {
     #[error("Failed to parse range: {0:?}")]
     Parse(http_range::HttpRangeParseError),
 
+    #[error("Invalid entity tag: {0:?}")]
+    InvalidEntityTag(String),
+
     #[error(transparent)]
     Http(#[from] http::Error),
 }
@@ -72,10 +98,12 @@ fn not_satisfiable_response(file_size: u64) -> Response<Body> {
 /// for a range request is shorter.
 ///
 /// It is the responsibility of the caller to ensure that `rx` is a stream of
-/// data matching the requested range in the `range` argument, if it is
-/// supplied.
+/// data matching the requested ranges in the `range` argument, if it is
+/// supplied.  If there are multiple ranges, `rx` should produce the data for
+/// each of them in order, with nothing in between: this function takes care of
+/// encoding them as a `multipart/byteranges` response.
 pub fn make_get_response<E, S, D>(
-    range: Option<SingleRange>,
+    range: Option<ByteRanges>,
     file_length: u64,
     content_type: Option<impl Into<HeaderValue>>,
     rx: S,
@@ -85,90 +113,460 @@ where
     D: Into<bytes::Bytes>,
     S: Send + Sync + futures::stream::Stream<Item = Result<D, E>> + 'static,
 {
-    Ok(make_response_common(range, file_length, content_type).body(
-        Body::wrap(http_body_util::StreamBody::new(
+    let (res, multipart) =
+        make_response_common(range.as_ref(), file_length, content_type);
+    let body = match multipart {
+        Some(layout) => Body::wrap(http_body_util::StreamBody::new(
+            MultipartStream::new(layout, rx).map_ok(hyper::body::Frame::data),
+        )),
+        None => Body::wrap(http_body_util::StreamBody::new(
             rx.map_ok(|b| hyper::body::Frame::data(b.into())),
         )),
-    )?)
+    };
+    Ok(res.body(body)?)
 }
 
 /// Generate a HEAD response, optionally for a HTTP range request.  The total
 /// file length should be provided, whether or not the expected Content-Length
 /// for a range request is shorter.
 pub fn make_head_response(
-    range: Option<SingleRange>,
+    range: Option<ByteRanges>,
     file_length: u64,
     content_type: Option<impl Into<HeaderValue>>,
 ) -> Result<Response<Body>, Error> {
-    Ok(make_response_common(range, file_length, content_type)
-        .body(Body::empty())?)
+    let (res, _) =
+        make_response_common(range.as_ref(), file_length, content_type);
+    Ok(res.body(Body::empty())?)
 }
 
 fn make_response_common(
-    range: Option<SingleRange>,
+    range: Option<&ByteRanges>,
     file_length: u64,
     content_type: Option<impl Into<HeaderValue>>,
-) -> hyper::http::response::Builder {
+) -> (hyper::http::response::Builder, Option<MultipartLayout>) {
     let mut res = Response::builder();
     res = res.header(ACCEPT_RANGES, ACCEPT_RANGES_BYTES);
-    res = res.header(
-        CONTENT_TYPE,
-        content_type.map(|t| t.into()).unwrap_or(CONTENT_TYPE_OCTET_STREAM),
-    );
+    let content_type =
+        content_type.map(|t| t.into()).unwrap_or(CONTENT_TYPE_OCTET_STREAM);
 
-    if let Some(range) = range {
+    let Some(ranges) = range else {
+        res = res.header(CONTENT_TYPE, content_type);
+        res = res.header(CONTENT_LENGTH, file_length.to_string());
+        res = res.status(StatusCode::OK);
+        return (res, None);
+    };
+
+    res = res.status(StatusCode::PARTIAL_CONTENT);
+    if let Some(range) = ranges.single() {
+        res = res.header(CONTENT_TYPE, content_type);
         res = res.header(CONTENT_LENGTH, range.content_length().to_string());
         res = res.header(CONTENT_RANGE, range.to_content_range());
-        res = res.status(StatusCode::PARTIAL_CONTENT);
+        (res, None)
     } else {
-        res = res.header(CONTENT_LENGTH, file_length.to_string());
-        res = res.status(StatusCode::OK);
+        let layout = MultipartLayout::new(ranges, &content_type);
+        res = res.header(CONTENT_TYPE, layout.content_type());
+        res = res.header(CONTENT_LENGTH, layout.content_length().to_string());
+        (res, Some(layout))
+    }
+}
+
+/// Describes the framing of a `multipart/byteranges` response body
+///
+/// See RFC 9110, section 14.6.
+#[derive(Debug)]
+struct MultipartLayout {
+    boundary: String,
+    /// The delimiter and headers that precede each part, along with the
+    /// length of the data in that part
+    parts: Vec<(Bytes, u64)>,
+    /// The delimiter that ends the body
+    closing: Bytes,
+}
+
+impl MultipartLayout {
+    fn new(ranges: &ByteRanges, content_type: &HeaderValue) -> Self {
+        let boundary: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(MULTIPART_BOUNDARY_LEN)
+            .map(char::from)
+            .collect();
+        Self::with_boundary(ranges, content_type, boundary)
+    }
+
+    fn with_boundary(
+        ranges: &ByteRanges,
+        content_type: &HeaderValue,
+        boundary: String,
+    ) -> Self {
+        let parts = ranges
+            .iter()
+            .map(|range| {
+                let mut header = Vec::new();
+                header.extend_from_slice(b"--");
+                header.extend_from_slice(boundary.as_bytes());
+                header.extend_from_slice(b"\r\nContent-Type: ");
+                header.extend_from_slice(content_type.as_bytes());
+                header.extend_from_slice(b"\r\nContent-Range: ");
+                header.extend_from_slice(range.to_content_range().as_bytes());
+                header.extend_from_slice(b"\r\n\r\n");
+                (Bytes::from(header), range.content_length().get())
+            })
+            .collect();
+        let closing = Bytes::from(format!("--{boundary}--\r\n"));
+        Self { boundary, parts, closing }
+    }
+
+    fn content_type(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!(
+            "multipart/byteranges; boundary={}",
+            self.boundary
+        ))
+        .expect("multipart boundary should have been ASCII string")
+    }
+
+    /// Returns the total length of the response body, including framing
+    fn content_length(&self) -> u64 {
+        self.parts
+            .iter()
+            .map(|(header, length)| {
+                // Each part's data is followed by a CRLF.
+                header.len() as u64 + length + 2
+            })
+            .sum::<u64>()
+            + self.closing.len() as u64
+    }
+}
+
+/// Wraps a stream of the concatenated data for several ranges, interleaving
+/// the framing for a `multipart/byteranges` response
+struct MultipartStream<S> {
+    inner: Pin<Box<S>>,
+    parts: std::vec::IntoIter<(Bytes, u64)>,
+    closing: Option<Bytes>,
+    /// Number of bytes of data remaining in the current part
+    remaining: u64,
+    /// Output that's ready to be emitted
+    pending: VecDeque<Bytes>,
+    /// Set once we've queued the closing delimiter (or hit an error)
+    done: bool,
+}
+
+impl<S> MultipartStream<S> {
+    fn new(layout: MultipartLayout, inner: S) -> Self {
+        let mut stream = MultipartStream {
+            inner: Box::pin(inner),
+            parts: layout.parts.into_iter(),
+            closing: Some(layout.closing),
+            remaining: 0,
+            pending: VecDeque::new(),
+            done: false,
+        };
+        stream.start_next_part();
+        stream
+    }
+
+    // Queues the headers for the next part, or the closing delimiter if there
+    // are no parts left.
+    fn start_next_part(&mut self) {
+        match self.parts.next() {
+            Some((header, length)) => {
+                self.pending.push_back(header);
+                self.remaining = length;
+            }
+            None => {
+                self.pending.extend(self.closing.take());
+                self.done = true;
+            }
+        }
+    }
+
+    // Queues `data`, which may span the end of one or more parts.
+    fn push_data(&mut self, mut data: Bytes) -> Result<(), BoxError> {
+        while !data.is_empty() {
+            if self.done {
+                return Err(
+                    "data stream is longer than the requested ranges".into()
+                );
+            }
+            let n = usize::try_from(self.remaining)
+                .unwrap_or(usize::MAX)
+                .min(data.len());
+            self.pending.push_back(data.split_to(n));
+            self.remaining -= n as u64;
+            if self.remaining == 0 {
+                self.pending.push_back(Bytes::from_static(b"\r\n"));
+                self.start_next_part();
+            }
+        }
+        Ok(())
     }
+}
+
+impl<S, D, E> Stream for MultipartStream<S>
+where
+    S: Stream<Item = Result<D, E>>,
+    D: Into<Bytes>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    type Item = Result<Bytes, BoxError>;
 
-    res
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(bytes) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(bytes)));
+            }
+            if self.done {
+                return Poll::Ready(None);
+            }
+            match ready!(self.inner.as_mut().poll_next(cx)) {
+                Some(Ok(data)) => {
+                    if let Err(err) = self.push_data(data.into()) {
+                        self.done = true;
+                        self.pending.clear();
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                Some(Err(err)) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(Box::new(err))));
+                }
+                None => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(
+                        "data stream ended before the requested ranges".into(),
+                    )));
+                }
+            }
+        }
+    }
 }
 
-/// Represents the raw, unparsed values of "range" from a request header.
-pub struct PotentialRange(Vec<u8>);
+/// Returns a stream of the contents of `file` within each of `ranges`, in
+/// order, suitable for use with [make_get_response].
+///
+/// The file is read on a blocking task, starting from wherever each range
+/// begins.
+pub fn stream_file_ranges(
+    mut file: std::fs::File,
+    ranges: ByteRanges,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> + Send + Sync + 'static {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(16);
+    tokio::task::spawn_blocking(move || {
+        for range in ranges.iter() {
+            match send_file_range(&tx, &mut file, range) {
+                Ok(true) => (),
+                // The receiver has gone away, so there's no point in reading
+                // any more.
+                Ok(false) => return,
+                Err(err) => {
+                    let _ = tx.blocking_send(Err(err));
+                    return;
+                }
+            }
+        }
+    });
+    futures::stream::poll_fn(move |cx| rx.poll_recv(cx))
+}
+
+// Sends the contents of `file` within `range` over `tx`.
+//
+// Returns false if the receiving end has gone away.
+fn send_file_range(
+    tx: &tokio::sync::mpsc::Sender<Result<Bytes, std::io::Error>>,
+    file: &mut std::fs::File,
+    range: &SingleRange,
+) -> Result<bool, std::io::Error> {
+    file.seek(SeekFrom::Start(range.start()))?;
+    let mut reader = file.take(range.content_length().get());
+    loop {
+        let mut buf = vec![0; FILE_CHUNK_SIZE];
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        buf.truncate(n);
+        if tx.blocking_send(Ok(Bytes::from(buf))).is_err() {
+            return Ok(false);
+        }
+    }
+}
+
+/// A strong entity tag, identifying a specific version of a resource
+///
+/// This is sent to clients in the `ETag` header, and compared against the
+/// `If-Range` header of requests to decide whether a range request still
+/// applies to the current version of the resource.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntityTag(String);
+
+impl EntityTag {
+    /// Creates an entity tag from its opaque value (without quotes)
+    pub fn new(tag: impl Into<String>) -> Result<Self, Error> {
+        let tag = tag.into();
+        // See the definition of "etagc" in RFC 9110, section 8.8.3.
+        if tag.bytes().all(|b| b == 0x21 || (0x23..=0x7e).contains(&b)) {
+            Ok(Self(tag))
+        } else {
+            Err(Error::InvalidEntityTag(tag))
+        }
+    }
+
+    /// Generate the ETag header for inclusion in a HTTP response
+    pub fn to_header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!("\"{}\"", self.0))
+            .expect("entity tag should have been validated as ASCII string")
+    }
+
+    /// Returns true if `value` (e.g., the value of an `If-Range` header)
+    /// strongly matches this entity tag
+    fn matches(&self, value: &[u8]) -> bool {
+        // Weak entity tags ("W/\"...\"") never match, since If-Range requires
+        // a strong comparison.
+        value.strip_prefix(b"\"").and_then(|v| v.strip_suffix(b"\""))
+            == Some(self.0.as_bytes())
+    }
+}
+
+/// Represents the raw, unparsed values of "range" from a request header,
+/// along with the "if-range" header that may accompany it.
+pub struct PotentialRange {
+    range: Vec<u8>,
+    if_range: Option<Vec<u8>>,
+}
 
 impl PotentialRange {
     /// Creates a new [PotentialRange] from raw bytes.
     pub fn new(bytes: &[u8]) -> Self {
-        Self(Vec::from(bytes))
+        Self { range: Vec::from(bytes), if_range: None }
+    }
+
+    /// Attaches the raw value of an "if-range" header to this range.
+    pub fn with_if_range(mut self, bytes: &[u8]) -> Self {
+        self.if_range = Some(Vec::from(bytes));
+        self
+    }
+
+    /// Returns the "range" header (and "if-range" header, if any) from which
+    /// this range was constructed, for forwarding the request to another
+    /// server.
+    pub fn to_headers(&self) -> Result<http::HeaderMap, Error> {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            hyper::header::RANGE,
+            HeaderValue::from_bytes(&self.range).map_err(http::Error::from)?,
+        );
+        if let Some(if_range) = &self.if_range {
+            headers.insert(
+                hyper::header::IF_RANGE,
+                HeaderValue::from_bytes(if_range).map_err(http::Error::from)?,
+            );
+        }
+        Ok(headers)
+    }
+
+    /// Returns true if this range applies to the version of the document
+    /// identified by `etag`.
+    ///
+    /// This is always true unless the request included an "if-range" header
+    /// that does not match `etag`, in which case the range should be ignored
+    /// and the whole document returned instead.  We don't keep track of
+    /// modification times, so an "if-range" header containing a date never
+    /// matches.
+    pub fn if_range_matches(&self, etag: &EntityTag) -> bool {
+        self.if_range.as_ref().is_none_or(|value| etag.matches(value))
     }
 
-    /// Parses a single range request out of the range request.
+    /// Parses the ranges out of the range request.
     ///
     /// `len` is the total length of the document, for the range request being made.
     ///
+    /// The returned ranges are sorted, and ranges that overlap or are adjacent
+    /// to each other are coalesced.
+    ///
     /// On failure, returns a range response with the appropriate headers
     /// to inform the caller how to make a correct range request.
-    pub fn parse(&self, len: u64) -> Result<SingleRange, Response<Body>> {
-        self.single_range(len).map_err(|err| match err {
-            Error::MultipleRangesUnsupported | Error::Parse(_) => {
-                bad_request_response()
-            }
+    pub fn parse(&self, len: u64) -> Result<ByteRanges, Response<Body>> {
+        self.ranges(len).map_err(|err| match err {
+            Error::TooManyRanges | Error::Parse(_) => bad_request_response(),
             Error::RangeOverflow
             | Error::RangeUnderflow
             | Error::EmptyRange => not_satisfiable_response(len),
-            Error::Http(_err) => internal_error_response(),
+            Error::InvalidEntityTag(_) | Error::Http(_) => {
+                internal_error_response()
+            }
         })
     }
 
-    fn single_range(&self, len: u64) -> Result<SingleRange, Error> {
-        match http_range::HttpRange::parse_bytes(&self.0, len) {
-            Ok(ranges) => {
-                if ranges.len() != 1 || ranges[0].length < 1 {
-                    // Right now, we don't want to deal with encoding a
-                    // response that has multiple ranges.
-                    Err(Error::MultipleRangesUnsupported)
-                } else {
-                    Ok(SingleRange::new(ranges[0], len)?)
+    fn ranges(&self, len: u64) -> Result<ByteRanges, Error> {
+        let ranges = http_range::HttpRange::parse_bytes(&self.range, len)
+            .map_err(Error::Parse)?;
+        if ranges.len() > MAX_RANGES {
+            return Err(Error::TooManyRanges);
+        }
+        let ranges = ranges
+            .into_iter()
+            .map(|range| SingleRange::new(range, len))
+            .collect::<Result<Vec<_>, _>>()?;
+        ByteRanges::new(ranges)
+    }
+}
+
+/// A non-empty set of ranges within a document, sorted by their starting
+/// offset, none of which overlap or are adjacent to each other.
+#[derive(Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct ByteRanges {
+    ranges: Vec<SingleRange>,
+}
+
+impl ByteRanges {
+    fn new(mut ranges: Vec<SingleRange>) -> Result<Self, Error> {
+        ranges.sort_by_key(|range| range.start());
+        let mut coalesced: Vec<SingleRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match coalesced.last_mut() {
+                Some(last) if range.start() <= last.end_exclusive() => {
+                    let end = last.end_exclusive().max(range.end_exclusive());
+                    last.range.length = end - last.start();
                 }
+                _ => coalesced.push(range),
             }
-            Err(err) => Err(Error::Parse(err)),
+        }
+        if coalesced.is_empty() {
+            return Err(Error::EmptyRange);
+        }
+        Ok(Self { ranges: coalesced })
+    }
+
+    /// If there is exactly one range, returns it.
+    pub fn single(&self) -> Option<&SingleRange> {
+        match self.ranges.as_slice() {
+            [range] => Some(range),
+            _ => None,
         }
     }
+
+    /// Returns an iterator over the ranges, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &SingleRange> {
+        self.ranges.iter()
+    }
+
+    /// Returns the total number of bytes of the document covered by these
+    /// ranges.
+    ///
+    /// This does not include the framing of a multipart response.
+    pub fn data_length(&self) -> u64 {
+        self.ranges.iter().map(|range| range.content_length().get()).sum()
+    }
+}
+
+impl From<SingleRange> for ByteRanges {
+    fn from(range: SingleRange) -> Self {
+        Self { ranges: vec![range] }
+    }
 }
 
 /// A parsed range request, and associated "total document length".
@@ -220,6 +618,11 @@ impl SingleRange {
             .expect("start + length underflowed, but should have been checked in 'SingleRange::new'")
     }
 
+    // Return the offset just past the end of this range.
+    fn end_exclusive(&self) -> u64 {
+        self.range.start + self.range.length
+    }
+
     /// Generate the Content-Range header for inclusion in a HTTP 206 partial
     /// content response using this range.
     pub fn to_content_range(&self) -> HeaderValue {
@@ -261,13 +664,16 @@ impl<T> RequestContextEx for dropshot::RequestContext<T>
 where
     T: Send + Sync + 'static,
 {
-    /// If there is a Range header, return it for processing during response
-    /// generation.
+    /// If there is a Range header, return it (along with any If-Range header)
+    /// for processing during response generation.
     fn range(&self) -> Option<PotentialRange> {
-        self.request
-            .headers()
-            .get(hyper::header::RANGE)
-            .map(|hv| PotentialRange(hv.as_bytes().to_vec()))
+        let headers = self.request.headers();
+        let range =
+            PotentialRange::new(headers.get(hyper::header::RANGE)?.as_bytes());
+        Some(match headers.get(hyper::header::IF_RANGE) {
+            Some(if_range) => range.with_if_range(if_range.as_bytes()),
+            None => range,
+        })
     }
 }
 
@@ -288,12 +694,14 @@ mod test {
             bytes: Vec<u8>,
             len in 0_u64..=u64::MAX,
         ) {
-            let result = PotentialRange(bytes).parse(len);
-            let Ok(range) = result else { return Ok(()); };
-            let _ = range.start();
-            let _ = range.end_inclusive();
-            let _ = range.to_content_range();
-            let _ = range.to_range();
+            let result = PotentialRange::new(&bytes).parse(len);
+            let Ok(ranges) = result else { return Ok(()); };
+            for range in ranges.iter() {
+                let _ = range.start();
+                let _ = range.end_inclusive();
+                let _ = range.to_content_range();
+                let _ = range.to_range();
+            }
         }
 
         #[test]
@@ -342,51 +750,216 @@ mod test {
     #[test]
     fn parse_range_valid() {
         // Whole range
-        let pr = PotentialRange(b"bytes=0-100".to_vec());
+        let pr = PotentialRange::new(b"bytes=0-100");
         assert_eq!(
-            pr.single_range(100).unwrap(),
-            SingleRange {
+            pr.ranges(100).unwrap(),
+            ByteRanges::from(SingleRange {
                 range: http_range::HttpRange { start: 0, length: 100 },
                 total: 100
-            }
+            })
         );
 
         // Clipped
-        let pr = PotentialRange(b"bytes=0-100".to_vec());
+        let pr = PotentialRange::new(b"bytes=0-100");
         assert_eq!(
-            pr.single_range(50).unwrap(),
-            SingleRange {
+            pr.ranges(50).unwrap(),
+            ByteRanges::from(SingleRange {
                 range: http_range::HttpRange { start: 0, length: 50 },
                 total: 50
-            }
+            })
         );
 
         // Single byte
-        let pr = PotentialRange(b"bytes=49-49".to_vec());
+        let pr = PotentialRange::new(b"bytes=49-49");
         assert_eq!(
-            pr.single_range(50).unwrap(),
-            SingleRange {
+            pr.ranges(50).unwrap(),
+            ByteRanges::from(SingleRange {
                 range: http_range::HttpRange { start: 49, length: 1 },
                 total: 50
-            }
+            })
         );
     }
 
     #[test]
     fn parse_range_invalid() {
-        let pr = PotentialRange(b"bytes=50-50".to_vec());
+        let pr = PotentialRange::new(b"bytes=50-50");
         assert!(matches!(
-            pr.single_range(50).expect_err("Range should be invalid"),
+            pr.ranges(50).expect_err("Range should be invalid"),
             Error::Parse(http_range::HttpRangeParseError::NoOverlap),
         ));
 
-        let pr = PotentialRange(b"bytes=20-1".to_vec());
+        let pr = PotentialRange::new(b"bytes=20-1");
         assert!(matches!(
-            pr.single_range(50).expect_err("Range should be invalid"),
+            pr.ranges(50).expect_err("Range should be invalid"),
             Error::Parse(http_range::HttpRangeParseError::InvalidRange),
         ));
     }
 
+    #[test]
+    fn parse_multiple_ranges() {
+        let range = |start, length| SingleRange {
+            range: http_range::HttpRange { start, length },
+            total: 100,
+        };
+
+        // Disjoint ranges are sorted, but otherwise left alone.
+        let pr = PotentialRange::new(b"bytes=50-59,0-9");
+        let ranges = pr.ranges(100).unwrap();
+        assert_eq!(ranges.single(), None);
+        assert_eq!(
+            ranges.iter().cloned().collect::<Vec<_>>(),
+            [range(0, 10), range(50, 10)]
+        );
+        assert_eq!(ranges.data_length(), 20);
+
+        // Overlapping and adjacent ranges are coalesced.
+        let pr = PotentialRange::new(b"bytes=0-9,5-19,20-29,60-69,-5");
+        let ranges = pr.ranges(100).unwrap();
+        assert_eq!(
+            ranges.iter().cloned().collect::<Vec<_>>(),
+            [range(0, 30), range(60, 10), range(95, 5)]
+        );
+
+        // Ranges that coalesce into one are treated as a single range.
+        let pr = PotentialRange::new(b"bytes=10-19,0-14");
+        assert_eq!(pr.ranges(100).unwrap().single(), Some(&range(0, 20)));
+    }
+
+    #[test]
+    fn parse_too_many_ranges() {
+        let spec = (0..=MAX_RANGES)
+            .map(|i| format!("{}-{}", i * 2, i * 2))
+            .collect::<Vec<_>>()
+            .join(",");
+        let pr = PotentialRange::new(format!("bytes={spec}").as_bytes());
+        assert!(matches!(
+            pr.ranges(1000).expect_err("Too many ranges should be rejected"),
+            Error::TooManyRanges,
+        ));
+        assert_eq!(
+            pr.parse(1000).expect_err("Should fail").status(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn if_range() {
+        let etag = EntityTag::new("abc123").unwrap();
+        assert_eq!(etag.to_header_value(), "\"abc123\"");
+        assert!(EntityTag::new("has\"quote").is_err());
+        assert!(EntityTag::new("has space").is_err());
+
+        let pr = PotentialRange::new(b"bytes=0-1");
+        assert!(pr.if_range_matches(&etag));
+
+        let pr = PotentialRange::new(b"bytes=0-1").with_if_range(b"\"abc123\"");
+        assert!(pr.if_range_matches(&etag));
+
+        // Weak tags, other tags, and dates never match.
+        for if_range in [
+            b"W/\"abc123\"".as_slice(),
+            b"\"def456\"",
+            b"abc123",
+            b"Wed, 21 Oct 2015 07:28:00 GMT",
+        ] {
+            let pr = PotentialRange::new(b"bytes=0-1").with_if_range(if_range);
+            assert!(!pr.if_range_matches(&etag));
+        }
+    }
+
+    #[test]
+    fn to_headers() {
+        let headers =
+            PotentialRange::new(b"bytes=0-1,5-").to_headers().unwrap();
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[hyper::header::RANGE], "bytes=0-1,5-");
+
+        let headers = PotentialRange::new(b"bytes=0-1")
+            .with_if_range(b"\"abc123\"")
+            .to_headers()
+            .unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[hyper::header::RANGE], "bytes=0-1");
+        assert_eq!(headers[hyper::header::IF_RANGE], "\"abc123\"");
+    }
+
+    #[tokio::test]
+    async fn get_response_with_multiple_ranges() {
+        let data: Vec<u8> = (0..64).collect();
+        let ranges = PotentialRange::new(b"bytes=0-3,60-,10-13")
+            .parse(data.len() as u64)
+            .unwrap();
+
+        // Supply the data for the ranges in awkwardly-sized chunks to make
+        // sure we split them up correctly.
+        let mut selected = Vec::new();
+        for range in ranges.iter() {
+            selected.extend_from_slice(
+                &data[range.start() as usize..=range.end_inclusive() as usize],
+            );
+        }
+        let chunks: Vec<Result<Vec<u8>, Infallible>> =
+            selected.chunks(5).map(|c| Ok(c.to_vec())).collect();
+
+        let response = make_get_response(
+            Some(ranges),
+            data.len() as u64,
+            Some(HeaderValue::from_static("text/plain")),
+            futures::stream::iter(chunks),
+        )
+        .expect("Should have made response");
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+        let content_type =
+            response.headers()[CONTENT_TYPE].to_str().unwrap().to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .expect("Should be a multipart response");
+        let content_length: usize = response.headers()[CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(response.headers().get(CONTENT_RANGE), None);
+
+        let body =
+            response.into_body().collect().await.unwrap().to_bytes().to_vec();
+        assert_eq!(body.len(), content_length);
+
+        let mut expected = Vec::new();
+        for (first, last) in [(0, 3), (10, 13), (60, 63)] {
+            expected.extend_from_slice(
+                format!(
+                    "--{boundary}\r\n\
+                     Content-Type: text/plain\r\n\
+                     Content-Range: bytes {first}-{last}/64\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            expected.extend_from_slice(&data[first..=last]);
+            expected.extend_from_slice(b"\r\n");
+        }
+        expected.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn get_response_with_multiple_ranges_short_data() {
+        let ranges = PotentialRange::new(b"bytes=0-3,10-13").parse(64).unwrap();
+        let response = make_get_response(
+            Some(ranges),
+            64,
+            None::<HeaderValue>,
+            once(async move { Ok::<_, Infallible>(vec![0u8; 6]) }),
+        )
+        .expect("Should have made response");
+        response
+            .into_body()
+            .collect()
+            .await
+            .expect_err("Body should fail when the data stream is too short");
+    }
+
     #[test]
     fn get_response_no_range() {
         let bytes = b"Hello world";
@@ -434,7 +1007,7 @@ mod test {
             .collect();
 
         let response = make_get_response(
-            Some(range.clone()),
+            Some(range.clone().into()),
             total_length.into(),
             None::<HeaderValue>,
             once(async move { Ok::<_, Infallible>(b) }),
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS omicron.public.bp_target_approval (
+    blueprint_id UUID PRIMARY KEY,
+    proposed_by TEXT NOT NULL,
+    time_proposed TIMESTAMPTZ NOT NULL,
+    required_approver TEXT NOT NULL,
+    approved_by TEXT,
+    time_approved TIMESTAMPTZ,
+
+    CONSTRAINT approved_by_and_time_approved_consistent CHECK (
+        (approved_by IS NULL) = (time_approved IS NULL)
+    )
+);
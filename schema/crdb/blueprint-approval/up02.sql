@@ -0,0 +1,6 @@
+CREATE TYPE IF NOT EXISTS omicron.public.bp_target_audit_action AS ENUM (
+    'proposed',
+    'approved',
+    'made_target',
+    'set_enabled'
+);
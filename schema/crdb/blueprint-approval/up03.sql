@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS omicron.public.bp_target_audit (
+    id UUID PRIMARY KEY,
+    time_recorded TIMESTAMPTZ NOT NULL,
+    blueprint_id UUID NOT NULL,
+    action omicron.public.bp_target_audit_action NOT NULL,
+    actor TEXT,
+    enabled BOOL,
+
+    CONSTRAINT enabled_present_for_target_changes CHECK (
+        (action IN ('made_target', 'set_enabled')) = (enabled IS NOT NULL)
+    )
+);
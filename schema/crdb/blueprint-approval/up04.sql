@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS lookup_bp_target_audit_by_blueprint
+    ON omicron.public.bp_target_audit (blueprint_id, time_recorded);
@@ -119,6 +119,46 @@ INSERT INTO omicron.public.oximeter_read_policy (
     NOW()
 ) ON CONFLICT DO NOTHING;
 
+/*
+ * A planning policy limiting where discretionary control plane zones may be
+ * placed
+ */
+CREATE TABLE IF NOT EXISTS omicron.public.zone_placement_policy (
+    -- Monotonically increasing version for all policies
+    version INT8 PRIMARY KEY,
+
+    -- Maximum number of discretionary zones on any one sled, or NULL for no
+    -- limit beyond the sled's zpool count
+    max_discretionary_zones_per_sled INT8
+        CHECK (max_discretionary_zones_per_sled >= 0),
+
+    -- Sleds reserved for instances, on which no new discretionary zones are
+    -- placed
+    reserved_sled_ids UUID[] NOT NULL,
+
+    time_created TIMESTAMPTZ NOT NULL,
+
+    -- Number of consecutive cubbies grouped into each failure domain, or NULL
+    -- if sleds should not be grouped into failure domains
+    cubbies_per_failure_domain INT8
+        CHECK (cubbies_per_failure_domain > 0)
+);
+
+/*
+ * Zone placement policy defaults to placing no limits on zone placement.
+ */
+INSERT INTO omicron.public.zone_placement_policy (
+    version,
+    max_discretionary_zones_per_sled,
+    reserved_sled_ids,
+    time_created
+) VALUES (
+    1,
+    NULL,
+    ARRAY[]::UUID[],
+    NOW()
+) ON CONFLICT DO NOTHING;
+
 /*
  * Racks
  */
@@ -1256,6 +1296,43 @@ ON
 WHERE
     time_deleted IS NULL;
 
+/*
+ * A description of instances to create, from which many identical instances
+ * can be created at once. Each instance's name and hostname are chosen when
+ * the template is instantiated.
+ */
+CREATE TABLE IF NOT EXISTS omicron.public.instance_template (
+    /* Identity metadata (resource) */
+    id UUID PRIMARY KEY,
+    name STRING(63) NOT NULL,
+    description STRING(512) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    /* Indicates that the object has been deleted */
+    time_deleted TIMESTAMPTZ,
+
+    project_id UUID NOT NULL,
+
+    /* Configuration shared by every instance created from the template */
+    ncpus INT NOT NULL,
+    memory INT NOT NULL,
+    user_data BYTES NOT NULL,
+    auto_restart_policy omicron.public.instance_auto_restart,
+
+    /*
+     * The network interfaces, external IPs, boot disk and SSH keys of each
+     * instance, in the serialized form of their API parameters.
+     */
+    devices JSONB NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS lookup_instance_template_by_project
+    ON omicron.public.instance_template (
+        project_id,
+        name
+    ) WHERE
+        time_deleted IS NULL;
+
 /*
  * A special view of an instance provided to operators for insights into what's running
  * on a sled.
@@ -1504,6 +1581,137 @@ CREATE INDEX IF NOT EXISTS lookup_snapshot_by_destination_volume_id
 CREATE INDEX IF NOT EXISTS lookup_snapshot_by_volume_id
     ON omicron.public.snapshot ( volume_id );
 
+CREATE TYPE IF NOT EXISTS omicron.public.snapshot_export_format AS ENUM (
+  'raw',
+  'qcow2'
+);
+
+/*
+ * An export of a snapshot's contents as a downloadable image. The snapshot's
+ * data is read through a read-only copy of its volume, attached to a Crucible
+ * Pantry for as long as the export exists.
+ */
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_export (
+    id UUID PRIMARY KEY,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ,
+
+    snapshot_id UUID NOT NULL,
+    volume_id UUID NOT NULL,
+    pantry_address TEXT NOT NULL,
+
+    format omicron.public.snapshot_export_format NOT NULL,
+    /* Size of the exported image, which may differ from the snapshot's */
+    size_bytes INT8 NOT NULL,
+    /* Progress: how many bytes of the image have been downloaded */
+    bytes_exported INT8 NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS lookup_snapshot_export_by_snapshot
+    ON omicron.public.snapshot_export (
+        snapshot_id
+    ) WHERE
+        time_deleted IS NULL;
+
+/*
+ * A schedule for taking snapshots of a disk, or of every disk in a project,
+ * and for deleting the snapshots it took once there are more than it retains.
+ */
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_policy (
+    /* Identity metadata (resource) */
+    id UUID PRIMARY KEY,
+    name STRING(63) NOT NULL,
+    description STRING(512) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    /* Indicates that the object has been deleted */
+    time_deleted TIMESTAMPTZ,
+
+    project_id UUID NOT NULL,
+    /* The disk to snapshot, or NULL for every disk in the project */
+    disk_id UUID,
+
+    interval_secs INT8 NOT NULL CHECK (interval_secs > 0),
+    /* How many of the policy's snapshots of each disk are kept */
+    retention_count INT8 NOT NULL CHECK (retention_count > 0),
+
+    /* Status of the last time the policy took or deleted snapshots */
+    time_last_run TIMESTAMPTZ,
+    last_error TEXT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS lookup_snapshot_policy_by_project
+    ON omicron.public.snapshot_policy (
+        project_id,
+        name
+    ) WHERE
+        time_deleted IS NULL;
+
+/*
+ * The snapshots taken by a snapshot policy. A row is inserted when a Nexus
+ * claims a scheduled time for a disk, before the snapshot is taken, so only
+ * one Nexus takes each scheduled snapshot; `snapshot_id` is filled in once
+ * the snapshot exists, and stays NULL if it could not be taken.
+ */
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_policy_snapshot (
+    policy_id UUID NOT NULL,
+    disk_id UUID NOT NULL,
+    time_scheduled TIMESTAMPTZ NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+
+    snapshot_id UUID,
+
+    PRIMARY KEY (policy_id, disk_id, time_scheduled)
+);
+
+CREATE TYPE IF NOT EXISTS omicron.public.snapshot_group_state AS ENUM (
+  'creating',
+  'ready'
+);
+
+/*
+ * A set of snapshots of an instance's disks, taken at the same point so that
+ * data spread across the disks is captured consistently.
+ */
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_group (
+    /* Identity metadata (resource) */
+    id UUID PRIMARY KEY,
+    name STRING(63) NOT NULL,
+    description STRING(512) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    /* Indicates that the object has been deleted */
+    time_deleted TIMESTAMPTZ,
+
+    project_id UUID NOT NULL,
+    /* The instance whose disks were snapshotted */
+    instance_id UUID NOT NULL,
+
+    state omicron.public.snapshot_group_state NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS lookup_snapshot_group_by_project
+    ON omicron.public.snapshot_group (
+        project_id,
+        name
+    ) WHERE
+        time_deleted IS NULL;
+
+/*
+ * The snapshots in a snapshot group. The name of the snapshotted disk is
+ * recorded so that disks restored from the group can be named after it.
+ */
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_group_member (
+    group_id UUID NOT NULL,
+    snapshot_id UUID NOT NULL,
+
+    disk_id UUID NOT NULL,
+    disk_name STRING(63) NOT NULL,
+
+    PRIMARY KEY (group_id, snapshot_id)
+);
+
 /*
  * Oximeter collector servers.
  */
@@ -2505,6 +2713,48 @@ INSERT INTO omicron.public.tuf_generation (
     (TRUE, 1)
 ON CONFLICT DO NOTHING;
 
+-- Resumable TUF repository uploads that have not yet been finalized.
+--
+-- The data received so far is stored in `tuf_repo_upload_chunk`, so that an
+-- upload can be continued through any Nexus instance.
+CREATE TABLE IF NOT EXISTS omicron.public.tuf_repo_upload (
+    id UUID PRIMARY KEY,
+    file_name TEXT NOT NULL,
+    -- The declared size of the repository archive, in bytes.
+    size INT8 NOT NULL,
+    -- The total size of this upload's chunks, in bytes.
+    bytes_received INT8 NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    -- The time data was last written to the upload.
+    time_modified TIMESTAMPTZ NOT NULL,
+    -- Set when the upload is finalized or discarded. Its data is then deleted
+    -- in batches, after which the upload itself is deleted.
+    time_deleted TIMESTAMPTZ,
+
+    CONSTRAINT size_is_positive CHECK (size > 0),
+    CONSTRAINT bytes_received_within_size CHECK (
+        bytes_received >= 0 AND bytes_received <= size
+    )
+);
+
+-- Used to find uploads that have been left idle.
+CREATE INDEX IF NOT EXISTS lookup_tuf_repo_upload_by_time_modified
+    ON omicron.public.tuf_repo_upload (time_modified);
+
+-- Used to find discarded uploads whose data has not yet been deleted.
+CREATE INDEX IF NOT EXISTS lookup_deleted_tuf_repo_upload
+    ON omicron.public.tuf_repo_upload (id) WHERE time_deleted IS NOT NULL;
+
+-- Data received for a resumable TUF repository upload. An upload's chunks are
+-- contiguous: each begins where the previous one (by `byte_offset`) ends.
+CREATE TABLE IF NOT EXISTS omicron.public.tuf_repo_upload_chunk (
+    upload_id UUID NOT NULL,
+    byte_offset INT8 NOT NULL,
+    data BYTES NOT NULL,
+
+    PRIMARY KEY (upload_id, byte_offset)
+);
+
 /*******************************************************************/
 
 -- The source of the software release that should be deployed to the rack.
@@ -3876,7 +4126,12 @@ CREATE TABLE IF NOT EXISTS omicron.public.blueprint (
     -- represented by the presence of the default value in that field.
     --
     -- `cluster.preserve_downgrade_option`
-    cockroachdb_setting_preserve_downgrade TEXT
+    cockroachdb_setting_preserve_downgrade TEXT,
+
+    -- JSON-serialized record of the decisions the planner made when generating
+    -- this blueprint. This is for debugging only, and is NULL for blueprints
+    -- that were not produced by the planner.
+    planning_report JSONB
 );
 
 -- table describing both the current and historical target blueprints of the
@@ -3903,6 +4158,63 @@ CREATE TABLE IF NOT EXISTS omicron.public.bp_target (
     time_made_target TIMESTAMPTZ NOT NULL
 );
 
+-- blueprints that have been proposed for review before execution
+--
+-- A proposed blueprint that has not been approved is "held": it may be made
+-- the current target, but blueprint execution skips it until it is approved.
+CREATE TABLE IF NOT EXISTS omicron.public.bp_target_approval (
+    -- Effectively a foreign key into the `blueprint` table, but may reference a
+    -- blueprint that has been deleted.
+    blueprint_id UUID PRIMARY KEY,
+
+    -- who proposed the blueprint and when
+    proposed_by TEXT NOT NULL,
+    time_proposed TIMESTAMPTZ NOT NULL,
+
+    -- who must approve the blueprint before it may be executed
+    required_approver TEXT NOT NULL,
+
+    -- who approved the blueprint and when (both NULL until it's approved)
+    approved_by TEXT,
+    time_approved TIMESTAMPTZ,
+
+    CONSTRAINT approved_by_and_time_approved_consistent CHECK (
+        (approved_by IS NULL) = (time_approved IS NULL)
+    )
+);
+
+CREATE TYPE IF NOT EXISTS omicron.public.bp_target_audit_action AS ENUM (
+    'proposed',
+    'approved',
+    'made_target',
+    'set_enabled'
+);
+
+-- audit trail of who proposed, approved, and enabled each target blueprint
+CREATE TABLE IF NOT EXISTS omicron.public.bp_target_audit (
+    id UUID PRIMARY KEY,
+    time_recorded TIMESTAMPTZ NOT NULL,
+
+    -- Effectively a foreign key into the `blueprint` table, but may reference a
+    -- blueprint that has been deleted: the audit trail outlives blueprints.
+    blueprint_id UUID NOT NULL,
+
+    action omicron.public.bp_target_audit_action NOT NULL,
+
+    -- who made the change, if known
+    actor TEXT,
+
+    -- for `made_target` and `set_enabled`, the resulting `enabled` value
+    enabled BOOL,
+
+    CONSTRAINT enabled_present_for_target_changes CHECK (
+        (action IN ('made_target', 'set_enabled')) = (enabled IS NOT NULL)
+    )
+);
+
+CREATE INDEX IF NOT EXISTS lookup_bp_target_audit_by_blueprint
+    ON omicron.public.bp_target_audit (blueprint_id, time_recorded);
+
 -- metadata associated with a single sled in a blueprint
 CREATE TABLE IF NOT EXISTS omicron.public.bp_sled_metadata (
     -- foreign key into `blueprint` table
@@ -4987,7 +5299,10 @@ CREATE TABLE IF NOT EXISTS omicron.public.migration (
      * This is provided by the sled-agent when publishing a migration state
      * update.
      */
-    time_target_updated TIMESTAMPTZ
+    time_target_updated TIMESTAMPTZ,
+
+    /* Why the migration failed, if it failed and Nexus knows why. */
+    failure_reason TEXT
 );
 
 /* Lookup migrations by instance ID */
@@ -5098,7 +5413,7 @@ INSERT INTO omicron.public.db_metadata (
     version,
     target_version
 ) VALUES
-    (TRUE, NOW(), NOW(), '138.0.0', NULL)
+    (TRUE, NOW(), NOW(), '148.0.0', NULL)
 ON CONFLICT DO NOTHING;
 
 COMMIT;
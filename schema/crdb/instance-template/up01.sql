@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS omicron.public.instance_template (
+    id UUID PRIMARY KEY,
+    name STRING(63) NOT NULL,
+    description STRING(512) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ,
+
+    project_id UUID NOT NULL,
+
+    ncpus INT NOT NULL,
+    memory INT NOT NULL,
+    user_data BYTES NOT NULL,
+    auto_restart_policy omicron.public.instance_auto_restart,
+
+    devices JSONB NOT NULL
+);
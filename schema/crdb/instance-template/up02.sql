@@ -0,0 +1,6 @@
+CREATE UNIQUE INDEX IF NOT EXISTS lookup_instance_template_by_project
+    ON omicron.public.instance_template (
+        project_id,
+        name
+    ) WHERE
+        time_deleted IS NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE omicron.public.migration
+    ADD COLUMN IF NOT EXISTS failure_reason TEXT;
@@ -0,0 +1,4 @@
+CREATE TYPE IF NOT EXISTS omicron.public.snapshot_export_format AS ENUM (
+  'raw',
+  'qcow2'
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_export (
+    id UUID PRIMARY KEY,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ,
+
+    snapshot_id UUID NOT NULL,
+    volume_id UUID NOT NULL,
+    pantry_address TEXT NOT NULL,
+
+    format omicron.public.snapshot_export_format NOT NULL,
+    size_bytes INT8 NOT NULL,
+    bytes_exported INT8 NOT NULL
+);
@@ -0,0 +1,5 @@
+CREATE INDEX IF NOT EXISTS lookup_snapshot_export_by_snapshot
+    ON omicron.public.snapshot_export (
+        snapshot_id
+    ) WHERE
+        time_deleted IS NULL;
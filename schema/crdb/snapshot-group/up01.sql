@@ -0,0 +1,4 @@
+CREATE TYPE IF NOT EXISTS omicron.public.snapshot_group_state AS ENUM (
+  'creating',
+  'ready'
+);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_group (
+    id UUID PRIMARY KEY,
+    name STRING(63) NOT NULL,
+    description STRING(512) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ,
+
+    project_id UUID NOT NULL,
+    instance_id UUID NOT NULL,
+
+    state omicron.public.snapshot_group_state NOT NULL
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_group_member (
+    group_id UUID NOT NULL,
+    snapshot_id UUID NOT NULL,
+
+    disk_id UUID NOT NULL,
+    disk_name STRING(63) NOT NULL,
+
+    PRIMARY KEY (group_id, snapshot_id)
+);
@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_policy (
+    id UUID PRIMARY KEY,
+    name STRING(63) NOT NULL,
+    description STRING(512) NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ,
+
+    project_id UUID NOT NULL,
+    disk_id UUID,
+
+    interval_secs INT8 NOT NULL CHECK (interval_secs > 0),
+    retention_count INT8 NOT NULL CHECK (retention_count > 0),
+
+    time_last_run TIMESTAMPTZ,
+    last_error TEXT
+);
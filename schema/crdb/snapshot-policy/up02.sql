@@ -0,0 +1,6 @@
+CREATE UNIQUE INDEX IF NOT EXISTS lookup_snapshot_policy_by_project
+    ON omicron.public.snapshot_policy (
+        project_id,
+        name
+    ) WHERE
+        time_deleted IS NULL;
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS omicron.public.snapshot_policy_snapshot (
+    policy_id UUID NOT NULL,
+    disk_id UUID NOT NULL,
+    time_scheduled TIMESTAMPTZ NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+
+    snapshot_id UUID,
+
+    PRIMARY KEY (policy_id, disk_id, time_scheduled)
+);
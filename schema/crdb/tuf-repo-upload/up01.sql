@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS omicron.public.tuf_repo_upload (
+    id UUID PRIMARY KEY,
+    file_name TEXT NOT NULL,
+    size INT8 NOT NULL,
+    bytes_received INT8 NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL,
+    time_modified TIMESTAMPTZ NOT NULL,
+    time_deleted TIMESTAMPTZ,
+
+    CONSTRAINT size_is_positive CHECK (size > 0),
+    CONSTRAINT bytes_received_within_size CHECK (
+        bytes_received >= 0 AND bytes_received <= size
+    )
+);
@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS lookup_tuf_repo_upload_by_time_modified
+    ON omicron.public.tuf_repo_upload (time_modified);
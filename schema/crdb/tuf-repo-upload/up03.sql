@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS omicron.public.tuf_repo_upload_chunk (
+    upload_id UUID NOT NULL,
+    byte_offset INT8 NOT NULL,
+    data BYTES NOT NULL,
+
+    PRIMARY KEY (upload_id, byte_offset)
+);
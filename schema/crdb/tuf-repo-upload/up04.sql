@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS lookup_deleted_tuf_repo_upload
+    ON omicron.public.tuf_repo_upload (id) WHERE time_deleted IS NOT NULL;
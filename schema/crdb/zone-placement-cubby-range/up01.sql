@@ -0,0 +1,3 @@
+ALTER TABLE omicron.public.zone_placement_policy
+    ADD COLUMN IF NOT EXISTS cubbies_per_failure_domain INT8
+        CHECK (cubbies_per_failure_domain > 0);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS omicron.public.zone_placement_policy (
+    version INT8 PRIMARY KEY,
+    max_discretionary_zones_per_sled INT8
+        CHECK (max_discretionary_zones_per_sled >= 0),
+    reserved_sled_ids UUID[] NOT NULL,
+    time_created TIMESTAMPTZ NOT NULL
+);
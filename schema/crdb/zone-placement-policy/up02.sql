@@ -0,0 +1,12 @@
+-- Zone placement policy defaults to placing no limits on zone placement.
+INSERT INTO omicron.public.zone_placement_policy (
+    version,
+    max_discretionary_zones_per_sled,
+    reserved_sled_ids,
+    time_created
+) VALUES (
+    1,
+    NULL,
+    ARRAY[]::UUID[],
+    NOW()
+) ON CONFLICT DO NOTHING;
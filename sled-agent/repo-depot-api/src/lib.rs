@@ -2,7 +2,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use dropshot::{FreeformBody, HttpError, HttpResponseOk, Path, RequestContext};
+use dropshot::{
+    Body, FreeformBody, HttpError, HttpResponseOk, Path, RequestContext,
+};
 use schemars::JsonSchema;
 use serde::Deserialize;
 use tufaceous_artifact::ArtifactHash;
@@ -20,6 +22,19 @@ pub trait RepoDepotApi {
         rqctx: RequestContext<Self::Context>,
         path_params: Path<ArtifactPathParams>,
     ) -> Result<HttpResponseOk<FreeformBody>, HttpError>;
+
+    /// Fetch part of an artifact from the depot.
+    ///
+    /// Range requests (including requests for multiple ranges) are supported.
+    /// Requests without a `Range` header return the whole artifact.
+    #[endpoint {
+        method = GET,
+        path = "/artifact/sha256/{sha256}/range",
+    }]
+    async fn artifact_get_range_by_sha256(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<ArtifactPathParams>,
+    ) -> Result<http::Response<Body>, HttpError>;
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
@@ -35,6 +35,7 @@ use futures::{Stream, TryStreamExt};
 use omicron_common::address::REPO_DEPOT_PORT;
 use omicron_common::api::external::Generation;
 use omicron_common::ledger::Ledger;
+use range_requests::{EntityTag, PotentialRange, RequestContextEx};
 use repo_depot_api::*;
 use sha2::{Digest, Sha256};
 use sled_agent_api::{
@@ -47,6 +48,7 @@ use slog_error_chain::{InlineErrorChain, SlogInlineError};
 use tokio::fs::File;
 use tokio::sync::{mpsc, oneshot, watch};
 use tokio::task::JoinSet;
+use tokio_util::io::ReaderStream;
 use tufaceous_artifact::ArtifactHash;
 
 use crate::services::ServiceManager;
@@ -777,6 +779,65 @@ impl RepoDepotApi for RepoDepotImpl {
         let body = Body::wrap(hyper_staticfile::Body::Full(file_stream));
         Ok(HttpResponseOk(FreeformBody(body)))
     }
+
+    async fn artifact_get_range_by_sha256(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<ArtifactPathParams>,
+    ) -> Result<http::Response<Body>, HttpError> {
+        let sha256 = path_params.into_inner().sha256;
+        let file = rqctx.context().get(sha256).await?;
+        repo_depot_get_response(file, sha256, rqctx.range()).await
+    }
+}
+
+/// Builds the Repo Depot response for an artifact, honoring any range request.
+pub(crate) async fn repo_depot_get_response(
+    file: File,
+    sha256: ArtifactHash,
+    range: Option<PotentialRange>,
+) -> Result<http::Response<Body>, HttpError> {
+    let len = file
+        .metadata()
+        .await
+        .map_err(|err| {
+            HttpError::for_internal_error(format!(
+                "failed to stat artifact {sha256}: {}",
+                InlineErrorChain::new(&err)
+            ))
+        })?
+        .len();
+
+    // Artifacts are named by the hash of their contents, so the hash also
+    // identifies the version of the artifact being requested.
+    let etag = EntityTag::new(sha256.to_string())
+        .expect("hex strings are valid entity tags");
+    let range = range.filter(|range| range.if_range_matches(&etag));
+
+    let response = match range {
+        Some(range) => match range.parse(len) {
+            Ok(ranges) => {
+                let file = file.into_std().await;
+                range_requests::make_get_response(
+                    Some(ranges.clone()),
+                    len,
+                    None::<http::HeaderValue>,
+                    range_requests::stream_file_ranges(file, ranges),
+                )
+            }
+            Err(err_response) => Ok(err_response),
+        },
+        None => range_requests::make_get_response(
+            None,
+            len,
+            None::<http::HeaderValue>,
+            ReaderStream::new(file),
+        ),
+    };
+    let mut response = response.map_err(|err| {
+        HttpError::for_internal_error(InlineErrorChain::new(&err).to_string())
+    })?;
+    response.headers_mut().insert(http::header::ETAG, etag.to_header_value());
+    Ok(response)
 }
 
 #[derive(Debug, thiserror::Error, SlogInlineError)]
@@ -2311,6 +2311,7 @@ impl ServiceManager {
             ZoneArgs::Omicron(OmicronZoneConfigLocal {
                 zone:
                     OmicronZoneConfig {
+                        id,
                         zone_type:
                             OmicronZoneType::ExternalDns {
                                 http_address,
@@ -2345,6 +2346,7 @@ impl ServiceManager {
                     SocketAddr::new(nic.ip, dns_address.port()).to_string();
 
                 let external_dns_config = PropertyGroupBuilder::new("config")
+                    .add_property("id", "astring", id.to_string())
                     .add_property(
                         "http_address",
                         "astring",
@@ -2523,6 +2525,7 @@ impl ServiceManager {
             ZoneArgs::Omicron(OmicronZoneConfigLocal {
                 zone:
                     OmicronZoneConfig {
+                        id,
                         zone_type:
                             OmicronZoneType::InternalDns {
                                 http_address,
@@ -2585,6 +2588,7 @@ impl ServiceManager {
                     .add_internal_dns_subnet(Ipv6Subnet::new(*gz_address));
 
                 let internal_dns_config = PropertyGroupBuilder::new("config")
+                    .add_property("id", "astring", id.to_string())
                     .add_property(
                         "http_address",
                         "astring",
@@ -10,10 +10,13 @@ use dropshot::{
     Body, ConfigDropshot, FreeformBody, HttpError, HttpResponseOk, HttpServer,
     Path, RequestContext, ServerBuilder,
 };
+use range_requests::RequestContextEx;
 use repo_depot_api::*;
 use std::sync::Arc;
 
-use crate::artifact_store::{ArtifactStore, DatasetsManager};
+use crate::artifact_store::{
+    ArtifactStore, DatasetsManager, repo_depot_get_response,
+};
 
 #[derive(Clone)]
 pub(super) struct SimArtifactStorage {
@@ -77,4 +80,13 @@ impl RepoDepotApi for RepoDepotImpl {
         let body = Body::wrap(hyper_staticfile::Body::Full(file_stream));
         Ok(HttpResponseOk(FreeformBody(body)))
     }
+
+    async fn artifact_get_range_by_sha256(
+        rqctx: RequestContext<Self::Context>,
+        path_params: Path<ArtifactPathParams>,
+    ) -> Result<http::Response<Body>, HttpError> {
+        let sha256 = path_params.into_inner().sha256;
+        let file = rqctx.context().get(sha256).await?;
+        repo_depot_get_response(file, sha256, rqctx.range()).await
+    }
 }
@@ -2068,41 +2068,60 @@ impl Pantry {
             .map_err(|e| HttpError::for_internal_error(e.to_string()))
     }
 
-    pub fn bulk_write(
+    /// Returns the block size and total size of an attached volume
+    fn region_geometry(
         &self,
         volume_id: String,
-        offset: u64,
-        data: Vec<u8>,
-    ) -> Result<(), HttpError> {
+    ) -> Result<(u64, u64), HttpError> {
         let vcr = self.entry(volume_id)?;
 
-        // Currently, Nexus will only make volumes where the first subvolume is
-        // a Region. This will change in the future!
-        let (region_block_size, region_size) = match vcr {
+        // Currently, Nexus will only make volumes where the subvolumes are
+        // Regions. This will change in the future!
+        match vcr {
             VolumeConstructionRequest::Volume { sub_volumes, .. } => {
-                match sub_volumes[0] {
-                    VolumeConstructionRequest::Region {
-                        block_size,
-                        blocks_per_extent,
-                        extent_count,
-                        ..
-                    } => (
-                        block_size,
-                        block_size
-                            * blocks_per_extent
-                            * u64::from(extent_count),
-                    ),
-
-                    _ => {
-                        panic!("unexpected Volume layout");
+                let mut region_block_size = None;
+                let mut region_size = 0;
+
+                for sub_volume in &sub_volumes {
+                    match sub_volume {
+                        VolumeConstructionRequest::Region {
+                            block_size,
+                            blocks_per_extent,
+                            extent_count,
+                            ..
+                        } => {
+                            region_block_size.get_or_insert(*block_size);
+                            region_size += block_size
+                                * blocks_per_extent
+                                * u64::from(*extent_count);
+                        }
+
+                        _ => {
+                            panic!("unexpected Volume layout");
+                        }
                     }
                 }
+
+                match region_block_size {
+                    Some(block_size) => Ok((block_size, region_size)),
+                    None => panic!("unexpected Volume layout"),
+                }
             }
 
             _ => {
                 panic!("unexpected Volume layout");
             }
-        };
+        }
+    }
+
+    pub fn bulk_write(
+        &self,
+        volume_id: String,
+        offset: u64,
+        data: Vec<u8>,
+    ) -> Result<(), HttpError> {
+        let (region_block_size, region_size) =
+            self.region_geometry(volume_id)?;
 
         if (offset % region_block_size) != 0 {
             return Err(HttpError::for_bad_request(
@@ -2128,6 +2147,41 @@ impl Pantry {
         Ok(())
     }
 
+    pub fn bulk_read(
+        &self,
+        volume_id: String,
+        offset: u64,
+        size: usize,
+    ) -> Result<Vec<u8>, HttpError> {
+        let (region_block_size, region_size) =
+            self.region_geometry(volume_id)?;
+
+        if (offset % region_block_size) != 0 {
+            return Err(HttpError::for_bad_request(
+                None,
+                "offset not multiple of block size!".to_string(),
+            ));
+        }
+
+        if (size as u64 % region_block_size) != 0 {
+            return Err(HttpError::for_bad_request(
+                None,
+                "size not multiple of block size!".to_string(),
+            ));
+        }
+
+        if (offset + size as u64) > region_size {
+            return Err(HttpError::for_bad_request(
+                None,
+                "offset + size off end of region!".to_string(),
+            ));
+        }
+
+        // The simulated pantry doesn't store any data, so every volume reads
+        // back as zeroes.
+        Ok(vec![0; size])
+    }
+
     pub fn scrub(&self, volume_id: String) -> Result<String, HttpError> {
         self.entry(volume_id)?;
 
@@ -24,8 +24,9 @@ use omicron_uuid_kinds::SupportBundleUuid;
 use omicron_uuid_kinds::ZpoolUuid;
 use rand::distributions::Alphanumeric;
 use rand::{Rng, thread_rng};
+use range_requests::ByteRanges;
+use range_requests::EntityTag;
 use range_requests::PotentialRange;
-use range_requests::SingleRange;
 use sha2::{Digest, Sha256};
 use sled_agent_api::*;
 use sled_storage::manager::NestedDatasetConfig;
@@ -35,9 +36,9 @@ use sled_storage::manager::StorageHandle;
 use slog::Logger;
 use slog_error_chain::InlineErrorChain;
 use std::borrow::Cow;
+use std::io::Read;
 use std::io::Write;
 use tokio::io::AsyncReadExt;
-use tokio::io::AsyncSeekExt;
 use tokio::io::AsyncWriteExt;
 use tokio_util::io::ReaderStream;
 use tufaceous_artifact::ArtifactHash;
@@ -313,67 +314,88 @@ pub(crate) enum SupportBundleQueryType {
     Path { file_path: String },
 }
 
-// Implements "seeking" and "putting a capacity on a file" manually.
+// Skips over the next `skip` bytes of `reader`.
 //
 // TODO: When https://github.com/zip-rs/zip2/issues/231 is resolved,
 // this method should be replaced by calling "seek" directly,
 // via the "by_name_seek" method from the zip crate.
-fn skip_and_limit(
-    mut reader: impl std::io::Read,
-    skip: usize,
-    limit: usize,
-) -> std::io::Result<impl std::io::Read> {
-    const BUF_SIZE: usize = 4096;
-    let mut buf = vec![0; BUF_SIZE];
-    let mut skip_left = skip;
-
-    while skip_left > 0 {
-        let to_read = std::cmp::min(skip_left, BUF_SIZE);
-        reader.read_exact(&mut buf[0..to_read])?;
-        skip_left -= to_read;
+fn skip_forward(
+    reader: &mut impl std::io::Read,
+    skip: u64,
+) -> std::io::Result<()> {
+    let skipped =
+        std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())?;
+    if skipped < skip {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
     }
-
-    Ok(reader.take(limit as u64))
+    Ok(())
 }
 
-fn stream_zip_entry_helper(
+// Sends everything from `reader` over `tx`.
+//
+// Returns false if the receiving end has gone away.
+fn send_all(
     tx: &tokio::sync::mpsc::Sender<Result<Vec<u8>, HttpError>>,
-    mut archive: zip::ZipArchive<std::fs::File>,
-    entry_path: String,
-    range: Option<SingleRange>,
-) -> Result<(), Error> {
-    // TODO: When https://github.com/zip-rs/zip2/issues/231 is resolved,
-    // this should call "by_name_seek" instead.
-    let mut reader = archive.by_name(&entry_path)?;
-
-    let reader: &mut dyn std::io::Read = match range {
-        Some(range) => &mut skip_and_limit(
-            reader,
-            range.start() as usize,
-            range.content_length().get() as usize,
-        )?,
-        None => &mut reader,
-    };
-
+    reader: &mut impl std::io::Read,
+) -> Result<bool, Error> {
     loop {
         let mut buf = vec![0; 4096];
         let n = reader.read(&mut buf)?;
         if n == 0 {
-            return Ok(());
+            return Ok(true);
         }
         buf.truncate(n);
         if let Err(_) = tx.blocking_send(Ok(buf)) {
             // If we cannot send anything, just bail out - we also won't be able
             // to send an appropriate error in this case, since we'd also be
             // sending it on this borked channel
+            return Ok(false);
+        }
+    }
+}
+
+// Sends the contents of `reader` within `ranges` (or all of it, if there are
+// no ranges) over `tx`.
+//
+// Since the ranges are sorted and don't overlap, we can visit all of them in
+// a single pass over `reader`, even though it can't seek.
+fn send_ranges(
+    tx: &tokio::sync::mpsc::Sender<Result<Vec<u8>, HttpError>>,
+    reader: &mut impl std::io::Read,
+    ranges: Option<&ByteRanges>,
+) -> Result<(), Error> {
+    let Some(ranges) = ranges else {
+        send_all(tx, reader)?;
+        return Ok(());
+    };
+
+    let mut position = 0;
+    for range in ranges.iter() {
+        skip_forward(reader, range.start() - position)?;
+        let length = range.content_length().get();
+        if !send_all(tx, &mut reader.by_ref().take(length))? {
             return Ok(());
         }
+        position = range.start() + length;
     }
+    Ok(())
+}
+
+fn stream_zip_entry_helper(
+    tx: &tokio::sync::mpsc::Sender<Result<Vec<u8>, HttpError>>,
+    mut archive: zip::ZipArchive<std::fs::File>,
+    entry_path: String,
+    ranges: Option<ByteRanges>,
+) -> Result<(), Error> {
+    // TODO: When https://github.com/zip-rs/zip2/issues/231 is resolved,
+    // this should call "by_name_seek" instead.
+    let mut reader = archive.by_name(&entry_path)?;
+    send_ranges(tx, &mut reader, ranges.as_ref())
 }
 
 struct ZipEntryStream {
     stream: tokio_stream::wrappers::ReceiverStream<Result<Vec<u8>, HttpError>>,
-    range: Option<SingleRange>,
+    range: Option<ByteRanges>,
     size: u64,
 }
 
@@ -811,10 +833,41 @@ impl<'a> SupportBundleManager<'a> {
         range: Option<PotentialRange>,
         query: SupportBundleQueryType,
         head_only: bool,
+    ) -> Result<http::Response<Body>, Error> {
+        // Bundles are never modified once they've been written, so the ID of
+        // the bundle is enough to identify its contents.
+        let etag = EntityTag::new(support_bundle_id.to_string())
+            .expect("UUIDs are valid entity tags");
+        let range = range.filter(|range| range.if_range_matches(&etag));
+
+        let mut response = self
+            .get_response(
+                zpool_id,
+                dataset_id,
+                support_bundle_id,
+                range,
+                query,
+                head_only,
+            )
+            .await?;
+        response
+            .headers_mut()
+            .insert(http::header::ETAG, etag.to_header_value());
+        Ok(response)
+    }
+
+    async fn get_response(
+        &self,
+        zpool_id: ZpoolUuid,
+        dataset_id: DatasetUuid,
+        support_bundle_id: SupportBundleUuid,
+        range: Option<PotentialRange>,
+        query: SupportBundleQueryType,
+        head_only: bool,
     ) -> Result<http::Response<Body>, Error> {
         // Regardless of the type of query, we first need to access the entire
         // bundle as a file.
-        let mut file = self
+        let file = self
             .support_bundle_get_file(zpool_id, dataset_id, support_bundle_id)
             .await?;
 
@@ -834,10 +887,10 @@ impl<'a> SupportBundleManager<'a> {
                 }
 
                 if let Some(range) = range {
-                    // If this has a range request, we need to validate the range
-                    // and put bounds on the part of the file we're reading.
-                    let range = match range.parse(len) {
-                        Ok(range) => range,
+                    // If this has a range request, we need to validate the
+                    // ranges and read only those parts of the file.
+                    let ranges = match range.parse(len) {
+                        Ok(ranges) => ranges,
                         Err(err_response) => return Ok(err_response),
                     };
 
@@ -845,17 +898,16 @@ impl<'a> SupportBundleManager<'a> {
                         &self.log,
                         "SupportBundle GET whole file (ranged)";
                         "bundle_id" => %support_bundle_id,
-                        "start" => range.start(),
-                        "limit" => range.content_length().get(),
+                        "nranges" => ranges.iter().count(),
+                        "length" => ranges.data_length(),
                     );
 
-                    file.seek(std::io::SeekFrom::Start(range.start())).await?;
-                    let limit = range.content_length().get();
+                    let file = file.into_std().await;
                     return Ok(range_requests::make_get_response(
-                        Some(range),
+                        Some(ranges.clone()),
                         len,
                         content_type,
-                        ReaderStream::new(file.take(limit)),
+                        range_requests::stream_file_ranges(file, ranges),
                     )?);
                 } else {
                     return Ok(range_requests::make_get_response(
@@ -886,14 +938,20 @@ impl<'a> SupportBundleManager<'a> {
                 }
 
                 let (range, bytes) = if let Some(range) = range {
-                    let range = match range.parse(len) {
-                        Ok(range) => range,
+                    let ranges = match range.parse(len) {
+                        Ok(ranges) => ranges,
                         Err(err_response) => return Ok(err_response),
                     };
 
-                    let section = &all_names_bytes[range.start() as usize
-                        ..=range.end_inclusive() as usize];
-                    (Some(range), section.to_owned())
+                    let sections: Vec<u8> = ranges
+                        .iter()
+                        .flat_map(|range| {
+                            &all_names_bytes[range.start() as usize
+                                ..=range.end_inclusive() as usize]
+                        })
+                        .copied()
+                        .collect();
+                    (Some(ranges), sections)
                 } else {
                     (None, all_names_bytes.to_owned())
                 };
@@ -948,7 +1006,7 @@ mod tests {
     use futures::stream;
     use http::status::StatusCode;
     use hyper::header::{
-        ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE,
+        ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
     };
     use omicron_common::disk::DatasetConfig;
     use omicron_common::disk::DatasetKind;
@@ -1138,13 +1196,17 @@ mod tests {
             .await
             .expect("Should have been able to HEAD bundle");
         assert_eq!(read_body(&mut response).await, Vec::<u8>::new());
-        assert_eq!(response.headers().len(), 3);
+        assert_eq!(response.headers().len(), 4);
         assert_eq!(
             response.headers()[CONTENT_LENGTH],
             zipfile_data.len().to_string()
         );
         assert_eq!(response.headers()[CONTENT_TYPE], "application/zip");
         assert_eq!(response.headers()[ACCEPT_RANGES], "bytes");
+        assert_eq!(
+            response.headers()[ETAG],
+            format!("\"{support_bundle_id}\"")
+        );
 
         // GET the bundle we created, and observe the contents of the bundle
         let mut response = mgr
@@ -1158,7 +1220,7 @@ mod tests {
             .await
             .expect("Should have been able to GET bundle");
         assert_eq!(read_body(&mut response).await, zipfile_data);
-        assert_eq!(response.headers().len(), 3);
+        assert_eq!(response.headers().len(), 4);
         assert_eq!(
             response.headers()[CONTENT_LENGTH],
             zipfile_data.len().to_string()
@@ -1185,7 +1247,7 @@ mod tests {
             .collect::<Vec<&str>>()
             .join("\n");
         let expected_len = expected_index.len().to_string();
-        assert_eq!(response.headers().len(), 3);
+        assert_eq!(response.headers().len(), 4);
         assert_eq!(response.headers()[CONTENT_LENGTH], expected_len);
         assert_eq!(response.headers()[CONTENT_TYPE], "text/plain");
         assert_eq!(response.headers()[ACCEPT_RANGES], "bytes");
@@ -1202,7 +1264,7 @@ mod tests {
             .await
             .expect("Should have been able to GET bundle index");
         assert_eq!(read_body(&mut response).await, expected_index.as_bytes());
-        assert_eq!(response.headers().len(), 3);
+        assert_eq!(response.headers().len(), 4);
         assert_eq!(response.headers()[CONTENT_LENGTH], expected_len);
         assert_eq!(response.headers()[CONTENT_TYPE], "text/plain");
         assert_eq!(response.headers()[ACCEPT_RANGES], "bytes");
@@ -1221,7 +1283,7 @@ mod tests {
             .await
             .expect("Should have been able to HEAD single file");
         assert_eq!(read_body(&mut response).await, Vec::<u8>::new());
-        assert_eq!(response.headers().len(), 3);
+        assert_eq!(response.headers().len(), 4);
         assert_eq!(
             response.headers()[CONTENT_LENGTH],
             GREET_DATA.len().to_string()
@@ -1246,7 +1308,7 @@ mod tests {
             .await
             .expect("Should have been able to GET single file");
         assert_eq!(read_body(&mut response).await, GREET_DATA);
-        assert_eq!(response.headers().len(), 3);
+        assert_eq!(response.headers().len(), 4);
         assert_eq!(
             response.headers()[CONTENT_LENGTH],
             GREET_DATA.len().to_string()
@@ -1910,7 +1972,7 @@ mod tests {
                 .await
                 .expect("Should have been able to GET bundle");
             assert_eq!(read_body(&mut response).await, expected_data);
-            assert_eq!(response.headers().len(), 4);
+            assert_eq!(response.headers().len(), 5);
             assert_eq!(
                 response.headers()[CONTENT_RANGE],
                 format!("bytes {first}-{last}/{}", zipfile_data.len())
@@ -1923,6 +1985,78 @@ mod tests {
             assert_eq!(response.headers()[ACCEPT_RANGES], "bytes");
         }
 
+        // GET several ranges of the bundle at once.  The response is a
+        // multipart document with one part per range, after overlapping ranges
+        // have been coalesced.
+        let range = PotentialRange::new(b"bytes=1000-1001,0-9,5-19");
+        let mut response = mgr
+            .get(
+                harness.zpool_id,
+                dataset_id,
+                support_bundle_id,
+                Some(range),
+                SupportBundleQueryType::Whole,
+            )
+            .await
+            .expect("Should have been able to GET bundle");
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type =
+            response.headers()[CONTENT_TYPE].to_str().unwrap().to_string();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .expect("Should have been a multipart response");
+        let body = read_body(&mut response).await;
+        assert_eq!(response.headers()[CONTENT_LENGTH], body.len().to_string());
+        let mut expected = Vec::new();
+        for (first, last) in [(0, 19), (1000, 1001)] {
+            expected.extend_from_slice(
+                format!(
+                    "--{boundary}\r\n\
+                     Content-Type: application/zip\r\n\
+                     Content-Range: bytes {first}-{last}/{}\r\n\r\n",
+                    zipfile_data.len(),
+                )
+                .as_bytes(),
+            );
+            expected.extend_from_slice(&zipfile_data[first..=last]);
+            expected.extend_from_slice(b"\r\n");
+        }
+        expected.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+        assert_eq!(body, expected);
+
+        // A range request for some other version of the bundle gets the whole
+        // bundle back.
+        let range = PotentialRange::new(b"bytes=0-9")
+            .with_if_range(b"\"some-other-etag\"");
+        let mut response = mgr
+            .get(
+                harness.zpool_id,
+                dataset_id,
+                support_bundle_id,
+                Some(range),
+                SupportBundleQueryType::Whole,
+            )
+            .await
+            .expect("Should have been able to GET bundle");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read_body(&mut response).await, zipfile_data);
+
+        // ... but one that matches the current version gets just the range.
+        let range = PotentialRange::new(b"bytes=0-9")
+            .with_if_range(format!("\"{support_bundle_id}\"").as_bytes());
+        let mut response = mgr
+            .get(
+                harness.zpool_id,
+                dataset_id,
+                support_bundle_id,
+                Some(range),
+                SupportBundleQueryType::Whole,
+            )
+            .await
+            .expect("Should have been able to GET bundle");
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(read_body(&mut response).await, &zipfile_data[0..10]);
+
         // GET the index of the bundle.
         let expected_index_str = example_files()
             .into_iter()
@@ -1948,7 +2082,7 @@ mod tests {
                 .await
                 .expect("Should have been able to GET bundle index");
             assert_eq!(read_body(&mut response).await, expected_data);
-            assert_eq!(response.headers().len(), 4);
+            assert_eq!(response.headers().len(), 5);
             assert_eq!(
                 response.headers()[CONTENT_RANGE],
                 format!("bytes {first}-{last}/{}", expected_index.len())
@@ -1981,7 +2115,7 @@ mod tests {
                 .await
                 .expect("Should have been able to GET single file");
             assert_eq!(read_body(&mut response).await, expected_data);
-            assert_eq!(response.headers().len(), 4);
+            assert_eq!(response.headers().len(), 5);
             assert_eq!(
                 response.headers()[CONTENT_RANGE],
                 format!("bytes {first}-{last}/{}", GREET_DATA.len())
@@ -78,50 +78,20 @@ impl ArtifactsWithPlan {
         zone_mode: ControlPlaneZonesMode,
         log: &Logger,
     ) -> Result<Self, RepositoryError> {
-        // Create a temporary file to store the incoming archive.``
-        let tempfile = tokio::task::spawn_blocking(|| {
-            camino_tempfile::tempfile().map_err(RepositoryError::TempFileCreate)
-        })
-        .await
-        .unwrap()?;
-        let mut tempfile =
-            tokio::io::BufWriter::new(tokio::fs::File::from_std(tempfile));
+        let mut upload = RepositoryUpload::new().await?;
 
         let mut body = std::pin::pin!(body);
 
         // Stream the uploaded body into our tempfile.
-        let mut hasher = Sha256::new();
         while let Some(bytes) = body
             .try_next()
             .await
             .map_err(RepositoryError::ReadChunkFromStream)?
         {
-            hasher.update(&bytes);
-            tempfile
-                .write_all(&bytes)
-                .await
-                .map_err(RepositoryError::TempFileWrite)?;
+            upload.append(&bytes).await?;
         }
 
-        let repo_hash = ArtifactHash(hasher.finalize().into());
-
-        // Flush writes. We don't need to seek back to the beginning of the file
-        // because extracting the repository will do its own seeking as a part of
-        // unzipping this repo.
-        tempfile.flush().await.map_err(RepositoryError::TempFileFlush)?;
-
-        let tempfile = tempfile.into_inner().into_std().await;
-
-        let artifacts_with_plan = Self::from_zip(
-            io::BufReader::new(tempfile),
-            file_name,
-            repo_hash,
-            zone_mode,
-            log,
-        )
-        .await?;
-
-        Ok(artifacts_with_plan)
+        upload.finish(None, file_name, zone_mode, log).await
     }
 
     pub async fn from_zip<T>(
@@ -354,6 +324,100 @@ where
     Ok(dir)
 }
 
+/// A TUF repository archive that is received in pieces.
+///
+/// Data must be appended in order; the SHA-256 of the archive is computed as
+/// data arrives, so that once the last byte has been received the archive can
+/// be verified and extracted without reading it back an additional time.
+#[derive(Debug)]
+pub struct RepositoryUpload {
+    tempfile: DebugIgnore<tokio::io::BufWriter<tokio::fs::File>>,
+    hasher: DebugIgnore<Sha256>,
+    bytes_received: u64,
+}
+
+impl RepositoryUpload {
+    /// Creates a new, empty upload backed by a temporary file.
+    pub async fn new() -> Result<Self, RepositoryError> {
+        // Create a temporary file to store the incoming archive.
+        let tempfile = tokio::task::spawn_blocking(|| {
+            camino_tempfile::tempfile().map_err(RepositoryError::TempFileCreate)
+        })
+        .await
+        .unwrap()?;
+        let tempfile =
+            tokio::io::BufWriter::new(tokio::fs::File::from_std(tempfile));
+
+        Ok(Self {
+            tempfile: DebugIgnore(tempfile),
+            hasher: DebugIgnore(Sha256::new()),
+            bytes_received: 0,
+        })
+    }
+
+    /// Returns the number of bytes appended to this upload so far.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Appends `bytes` to the end of the archive.
+    ///
+    /// If this returns an error, the state of the underlying file is unknown
+    /// and the upload should be discarded.
+    pub async fn append(
+        &mut self,
+        bytes: &[u8],
+    ) -> Result<(), RepositoryError> {
+        self.hasher.update(bytes);
+        self.tempfile
+            .write_all(bytes)
+            .await
+            .map_err(RepositoryError::TempFileWrite)?;
+        self.bytes_received += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Completes the upload and extracts the repository it contains.
+    ///
+    /// If `expected_hash` is provided, the SHA-256 of the received archive
+    /// must match it; otherwise extraction is not attempted.
+    pub async fn finish(
+        self,
+        expected_hash: Option<ArtifactHash>,
+        file_name: Option<String>,
+        zone_mode: ControlPlaneZonesMode,
+        log: &Logger,
+    ) -> Result<ArtifactsWithPlan, RepositoryError> {
+        let Self { tempfile: DebugIgnore(mut tempfile), hasher, .. } = self;
+
+        let repo_hash = ArtifactHash(hasher.0.finalize().into());
+        if let Some(expected) = expected_hash {
+            if expected != repo_hash {
+                return Err(RepositoryError::RepositoryHashMismatch {
+                    expected,
+                    actual: repo_hash,
+                });
+            }
+        }
+
+        // Flush writes. We don't need to seek back to the beginning of the file
+        // because extracting the repository will do its own seeking as a part of
+        // unzipping this repo.
+        tempfile.flush().await.map_err(RepositoryError::TempFileFlush)?;
+
+        let tempfile = tempfile.into_inner().into_std().await;
+
+        ArtifactsWithPlan::from_zip(
+            io::BufReader::new(tempfile),
+            file_name,
+            repo_hash,
+            zone_mode,
+            log,
+        )
+        .await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -543,6 +607,67 @@ mod tests {
         Ok(())
     }
 
+    /// Test that a repository uploaded in pieces via `RepositoryUpload` is
+    /// verified against the expected hash before being extracted.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_repository_upload() -> Result<()> {
+        let logctx = test_setup_log("test_repository_upload");
+        let temp_dir = Utf8TempDir::new()?;
+        let archive_path = temp_dir.path().join("archive.zip");
+        create_fake_archive(&logctx.log, &archive_path).await?;
+
+        let contents = std::fs::read(&archive_path)
+            .context("error reading archive.zip")?;
+        let expected_hash = ArtifactHash(Sha256::digest(&contents).into());
+
+        // A mismatched hash is rejected.
+        let error = upload_in_pieces(&contents)
+            .await?
+            .finish(
+                Some(ArtifactHash([0u8; 32])),
+                None,
+                ControlPlaneZonesMode::Composite,
+                &logctx.log,
+            )
+            .await
+            .expect_err("mismatched hash should fail");
+        assert!(
+            matches!(
+                error,
+                RepositoryError::RepositoryHashMismatch { actual, .. }
+                    if actual == expected_hash
+            ),
+            "unexpected error: {error}"
+        );
+
+        // The correct hash is accepted, and recorded in the description.
+        let plan = upload_in_pieces(&contents)
+            .await?
+            .finish(
+                Some(expected_hash),
+                None,
+                ControlPlaneZonesMode::Composite,
+                &logctx.log,
+            )
+            .await?;
+        assert_eq!(plan.description().repo.hash, expected_hash);
+
+        logctx.cleanup_successful();
+
+        Ok(())
+    }
+
+    async fn upload_in_pieces(contents: &[u8]) -> Result<RepositoryUpload> {
+        let mut upload = RepositoryUpload::new().await?;
+        // Use an odd chunk size so chunk boundaries don't line up with
+        // anything in particular.
+        for chunk in contents.chunks(64 * 1024 + 7) {
+            upload.append(chunk).await?;
+        }
+        assert_eq!(upload.bytes_received(), contents.len() as u64);
+        Ok(upload)
+    }
+
     async fn create_fake_archive(
         log: &slog::Logger,
         archive_path: &Utf8Path,
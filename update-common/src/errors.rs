@@ -11,7 +11,8 @@ use omicron_common::update::ArtifactId;
 use slog::error;
 use thiserror::Error;
 use tufaceous_artifact::{
-    ArtifactHashId, ArtifactKind, ArtifactVersion, KnownArtifactKind,
+    ArtifactHash, ArtifactHashId, ArtifactKind, ArtifactVersion,
+    KnownArtifactKind,
 };
 
 #[derive(Debug, Error)]
@@ -28,6 +29,12 @@ pub enum RepositoryError {
     #[error("error reading chunk off of input stream")]
     ReadChunkFromStream(#[source] HttpError),
 
+    #[error(
+        "repository hash mismatch: expected {expected}, but received data \
+         has hash {actual}"
+    )]
+    RepositoryHashMismatch { expected: ArtifactHash, actual: ArtifactHash },
+
     #[error("error writing to temporary file")]
     TempFileWrite(#[source] std::io::Error),
 
@@ -195,7 +202,8 @@ impl RepositoryError {
             },
 
             // Errors that are definitely caused by bad repository contents.
-            RepositoryError::DuplicateArtifactKind(_)
+            RepositoryError::RepositoryHashMismatch { .. }
+            | RepositoryError::DuplicateArtifactKind(_)
             | RepositoryError::LocateTarget { .. }
             | RepositoryError::TargetHashLength(_)
             | RepositoryError::MissingArtifactKind(_)